@@ -35,8 +35,45 @@
 //! #[folder = "$MY_FRONTEND_DIST"]
 //! struct Assets;
 //! ```
+//!
+//! # Frontend configuration
+//!
+//! Values the frontend needs at runtime (a maps API key, a Sentry DSN, the
+//! build profile) can be picked from the build environment with a [`Config`]
+//! instead of `option_env!` calls scattered through the frontend crate:
+//!
+//! ```ignore
+//! axum_egui_build::Frontend::new("my-frontend")
+//!     .config(
+//!         axum_egui_build::Config::new()
+//!             .env("MAPS_API_KEY")
+//!             .optional_env("SENTRY_DSN")
+//!             .profile(),
+//!     )
+//!     .build();
+//! ```
+//!
+//! The values are written as a JSON object into `index.html` as the
+//! `config` slot, and exported to the server as `{CRATE_NAME}_CONFIG`.
+//! Deserialize both into one struct from the shared crate:
+//!
+//! ```ignore
+//! #[derive(Deserialize)]
+//! pub struct BuildConfig {
+//!     pub maps_api_key: String,
+//!     pub sentry_dsn: Option<String>,
+//!     pub profile: String,
+//! }
+//!
+//! // Client
+//! let config: BuildConfig = axum_egui::slots::read("config").unwrap();
+//!
+//! // Server
+//! let config: BuildConfig = serde_json::from_str(env!("MY_FRONTEND_CONFIG"))?;
+//! ```
 
-use std::path::Path;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{env, fs};
 
@@ -77,25 +114,614 @@ use std::{env, fs};
 /// }
 /// ```
 pub fn frontend(crate_name: &str) {
-    let crate_name_underscored = crate_name.replace('-', "_");
-    let crate_name_upper = crate_name_underscored.to_uppercase();
+    Frontend::new(crate_name).build();
+}
+
+/// Builder for processing a frontend with non-default options.
+///
+/// [`frontend`] is shorthand for `Frontend::new(crate_name).build()`.
+///
+/// # Example
+///
+/// ```ignore
+/// // build.rs
+/// fn main() {
+///     axum_egui_build::Frontend::new("my-frontend")
+///         .asset_base_url("https://cdn.example.com/my-frontend")
+///         .build();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Frontend {
+    crate_name: String,
+    asset_base_url: Option<String>,
+    config: Option<Config>,
+    features: Vec<String>,
+    toolchain: Option<String>,
+    locked: bool,
+    profiling: bool,
+    precompress: bool,
+    size_report: Option<usize>,
+    canvases: Vec<(String, String)>,
+}
+
+impl Frontend {
+    /// Start configuring the frontend crate `crate_name`.
+    pub fn new(crate_name: impl Into<String>) -> Self {
+        Self {
+            crate_name: crate_name.into(),
+            asset_base_url: None,
+            config: None,
+            features: Vec::new(),
+            toolchain: None,
+            locked: false,
+            profiling: false,
+            precompress: false,
+            size_report: None,
+            canvases: Vec::new(),
+        }
+    }
+
+    /// Build the frontend with these cargo features.
+    ///
+    /// An artifact dependency is always built with the features listed in
+    /// `Cargo.toml`, so with features set the frontend is instead built by
+    /// running `cargo build --target wasm32-unknown-unknown` on
+    /// `../{crate_name}` into a target directory under `OUT_DIR`. The
+    /// artifact dependency is then not needed.
+    ///
+    /// This lets one frontend crate ship in several flavors, e.g. chosen by
+    /// the server's own features:
+    ///
+    /// ```ignore
+    /// let mut frontend = axum_egui_build::Frontend::new("my-frontend");
+    /// if std::env::var_os("CARGO_FEATURE_DEMO").is_some() {
+    ///     frontend = frontend.features(["demo"]);
+    /// }
+    /// frontend.build();
+    /// ```
+    pub fn features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.features = features.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Build the frontend with this rustup toolchain, e.g.
+    /// `"nightly-2025-06-01"`, instead of the one building the server.
+    ///
+    /// Like [`features`](Self::features), this builds the frontend by
+    /// running cargo (through `rustup run`) rather than through the artifact
+    /// dependency.
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = Some(toolchain.into());
+        self
+    }
+
+    /// Pass `--locked` when running cargo for the frontend, so the build
+    /// fails instead of updating `Cargo.lock`.
+    ///
+    /// Only applies when the frontend is built by running cargo (with
+    /// [`features`](Self::features) or a [`toolchain`](Self::toolchain)); an
+    /// artifact dependency follows the flags of the server's own build, so
+    /// build the server with `--locked` as well.
+    pub fn locked(mut self) -> Self {
+        self.locked = true;
+        self
+    }
+
+    /// Load the JS/WASM from an external origin, such as a CDN.
+    ///
+    /// Relative `./` asset references in the generated (or copied)
+    /// `index.html` are rewritten to `base_url`, and module scripts get
+    /// `crossorigin="anonymous"`. Upload the dist directory to that origin.
+    ///
+    /// The `AXUM_EGUI_ASSET_BASE_URL` environment variable overrides this
+    /// value, so deploy pipelines can set it without editing `build.rs`.
+    pub fn asset_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.asset_base_url = Some(base_url.into());
+        self
+    }
+
+    /// Keep DWARF debug info and function names in the WASM, so browser
+    /// devtools and WASM profilers show real function names.
+    ///
+    /// `wasm-bindgen` gets `--keep-debug` and `wasm-opt` gets `-g`; a frontend
+    /// built by running cargo also gets `debug = true` and `strip = "none"`.
+    /// Debug info makes the WASM several times larger, so keep it out of
+    /// production builds. The `AXUM_EGUI_PROFILING` environment variable
+    /// enables this too (`cargo axum-egui build --profiling` sets it).
+    pub fn profiling(mut self) -> Self {
+        self.profiling = true;
+        self
+    }
+
+    /// Write brotli (`.br`) and gzip (`.gz`) compressed copies of the WASM
+    /// and JS next to them, at the highest levels, which
+    /// `axum_egui::static_handler` sends to clients accepting them. Servers
+    /// then skip compressing the largest assets on every request.
+    ///
+    /// Uses the `brotli` and `gzip` command line tools; without them a cargo
+    /// warning is printed instead. The `AXUM_EGUI_PRECOMPRESS` environment
+    /// variable enables this too (`cargo axum-egui build --release` sets it).
+    pub fn precompress(mut self) -> Self {
+        self.precompress = true;
+        self
+    }
+
+    /// Write the `top` largest items of the final WASM to
+    /// [`SIZE_REPORT_FILE`] in the dist directory, using
+    /// [twiggy](https://github.com/rustwasm/twiggy).
+    ///
+    /// `wasm-opt` strips function names, so combine this with
+    /// [`profiling`](Self::profiling) to see them in optimized builds. The
+    /// `AXUM_EGUI_SIZE_REPORT` environment variable (the number of items)
+    /// enables this too. Without `twiggy` installed, a cargo warning is
+    /// printed instead.
+    pub fn size_report(mut self, top: usize) -> Self {
+        self.size_report = Some(top);
+        self
+    }
+
+    /// Add a canvas with this id to the default `index.html`, styled with
+    /// `css`, for another app started from the same WASM module:
+    ///
+    /// ```ignore
+    /// axum_egui_build::Frontend::new("my-frontend")
+    ///     .canvas(
+    ///         "support_canvas",
+    ///         "position: fixed; right: 16px; bottom: 16px; width: 320px; height: 420px;",
+    ///     )
+    ///     .build();
+    /// ```
+    ///
+    /// The main canvas stays `the_canvas_id`, filling the page. A frontend
+    /// with its own `index.html` adds its canvases there.
+    pub fn canvas(mut self, id: impl Into<String>, css: impl Into<String>) -> Self {
+        self.canvases.push((id.into(), css.into()));
+        self
+    }
+
+    /// Inject `config` into the page and export it to the server. See
+    /// [Frontend configuration](crate#frontend-configuration).
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
 
-    // Set up rerun triggers
-    println!("cargo:rerun-if-changed=../{}/src/", crate_name);
-    println!("cargo:rerun-if-changed=../{}/Cargo.toml", crate_name);
+    /// Process the frontend artifact. See [`frontend`] for the steps taken.
+    ///
+    /// The output of `wasm-bindgen` and `wasm-opt` is cached in
+    /// `target/{profile}/axum-egui-cache` (or `AXUM_EGUI_CACHE_DIR`), keyed by
+    /// a hash of the WASM, the tool versions and the options, so rebuilding
+    /// the server without touching the frontend skips both tools.
+    ///
+    /// The tool versions and commands used are written to
+    /// `{crate_name}-build.txt` in `OUT_DIR`, whose path is exported as
+    /// `{CRATE_NAME}_BUILD_RECORD`, so the server can show it (e.g.
+    /// `include_str!(env!("MY_FRONTEND_BUILD_RECORD"))`) and two builds can be
+    /// compared.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as [`frontend`].
+    pub fn build(&self) {
+        let crate_name = self.crate_name.as_str();
+        let crate_name_underscored = crate_name.replace('-', "_");
+        let crate_name_upper = crate_name_underscored.to_uppercase();
 
-    // Create output directory
-    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
-    let dist_dir = Path::new(&out_dir).join(format!("{}-dist", crate_name));
-    fs::create_dir_all(&dist_dir).expect("Failed to create dist directory");
+        // Set up rerun triggers
+        println!("cargo:rerun-if-changed=../{}/src/", crate_name);
+        println!("cargo:rerun-if-changed=../{}/Cargo.toml", crate_name);
+
+        // Create output directory
+        let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set");
+        let dist_dir = Path::new(&out_dir).join(format!("{}-dist", crate_name));
+        fs::create_dir_all(&dist_dir).expect("Failed to create dist directory");
+        remove_precompressed(&dist_dir);
+
+        println!("cargo:rerun-if-env-changed=AXUM_EGUI_PROFILING");
+        let profiling = self.profiling || env::var_os("AXUM_EGUI_PROFILING").is_some();
+        println!("cargo:rerun-if-env-changed=AXUM_EGUI_PRECOMPRESS");
+        let precompress = self.precompress || env::var_os("AXUM_EGUI_PRECOMPRESS").is_some();
+
+        let mut record = BuildRecord::default();
+        record.version(self.cargo().arg("-V"));
+        record.version(self.rustc().arg("-V"));
+        let wasm_bindgen_version = record.version(Command::new("wasm-bindgen").arg("--version"));
+
+        // Find (or, with features or a toolchain, build) the WASM
+        let wasm_path = if self.features.is_empty() && self.toolchain.is_none() {
+            artifact_path(crate_name)
+        } else {
+            self.build_with_cargo(Path::new(&out_dir), profiling, &mut record)
+        };
+
+        // Reuse the output of wasm-bindgen and wasm-opt if neither the WASM
+        // nor the options changed
+        println!("cargo:rerun-if-env-changed=AXUM_EGUI_WASM_OPT");
+        let wasm_opt = env::var("AXUM_EGUI_WASM_OPT").ok();
+        let wasm_opt_version = wasm_opt
+            .as_ref()
+            .and_then(|_| record.version(Command::new("wasm-opt").arg("--version")));
+        let wasm = fs::read(&wasm_path).expect("Failed to read the WASM artifact");
+        let key = self.cache_key(
+            &wasm,
+            [
+                wasm_bindgen_version.as_deref(),
+                wasm_opt.as_deref(),
+                wasm_opt_version.as_deref(),
+            ],
+            profiling,
+            precompress,
+        );
+        let cache_dir = cache_dir(Path::new(&out_dir)).join(format!("{}-{:016x}", crate_name, key));
+        if cache_dir.join(CACHE_COMPLETE).exists() {
+            copy_dir(&cache_dir, &dist_dir);
+            record
+                .lines
+                .push(format!("# cached: {}", cache_dir.display()));
+        } else {
+            // Run wasm-bindgen
+            let mut command = Command::new("wasm-bindgen");
+            command.args([
+                &wasm_path,
+                "--out-dir",
+                dist_dir.to_str().unwrap(),
+                "--target",
+                "web",
+                "--no-typescript",
+            ]);
+            if profiling {
+                command.arg("--keep-debug");
+            }
+            let status = record.command(&mut command).status().expect(
+                "Failed to run wasm-bindgen. Is it installed?\n\
+                 Run: cargo install wasm-bindgen-cli --version 0.2.104",
+            );
+
+            if !status.success() {
+                panic!("wasm-bindgen failed for {}", crate_name);
+            }
+
+            // Optionally shrink the generated WASM with wasm-opt
+            // (set by `cargo axum-egui build --release`, or manually)
+            if let Some(level) = &wasm_opt {
+                let wasm_file = dist_dir.join(format!("{}_bg.wasm", crate_name_underscored));
+                run_wasm_opt(&wasm_file, level, profiling, &mut record);
+            }
+
+            if precompress {
+                precompress_assets(&dist_dir, &mut record);
+            }
+
+            store_in_cache(&dist_dir, &cache_dir, crate_name);
+        }
+
+        // Report what takes space in the WASM
+        println!("cargo:rerun-if-env-changed=AXUM_EGUI_SIZE_REPORT");
+        let size_report = env::var("AXUM_EGUI_SIZE_REPORT")
+            .ok()
+            .and_then(|top| top.parse().ok())
+            .or(self.size_report);
+        let report_file = dist_dir.join(SIZE_REPORT_FILE);
+        match size_report {
+            Some(top) => {
+                let wasm_file = dist_dir.join(format!("{}_bg.wasm", crate_name_underscored));
+                write_size_report(&wasm_file, top, &report_file, &mut record);
+            }
+            None => {
+                let _ = fs::remove_file(&report_file);
+            }
+        }
+
+        // Copy or create index.html
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let html_src = Path::new(&manifest_dir).join(format!("../{}/index.html", crate_name));
+        let html_dst = dist_dir.join("index.html");
+
+        if html_src.exists() {
+            fs::copy(&html_src, &html_dst).expect("Failed to copy index.html");
+        } else {
+            // Create default HTML
+            let js_name = format!("{}.js", crate_name_underscored);
+            let mut canvas_styles = String::new();
+            let mut canvas_elements = String::new();
+            for (id, css) in &self.canvases {
+                canvas_styles.push_str(&format!("\n            #{id} {{ {css} }}"));
+                canvas_elements.push_str(&format!("\n        <canvas id=\"{id}\"></canvas>"));
+            }
+            let default_html = format!(
+                r#"<!DOCTYPE html>
+    <html>
+    <head>
+        <meta charset="utf-8">
+        <meta name="viewport" content="width=device-width, initial-scale=1.0">
+        <title>axum-egui</title>
+        <style>
+            html, body {{ margin: 0; padding: 0; width: 100%; height: 100%; overflow: hidden; }}
+            #the_canvas_id {{ width: 100%; height: 100%; }}{canvas_styles}
+            #loading_text {{ position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%); font-family: sans-serif; font-size: 1.5em; color: #888; }}
+        </style>
+        <!--AXUM_EGUI_INITIAL_STATE-->
+        <!--AXUM_EGUI:head-->
+    </head>
+    <body>
+        <noscript>This app requires JavaScript and WebAssembly.</noscript>
+        <p id="loading_text">Loading...</p>
+        <canvas id="the_canvas_id"></canvas>{canvas_elements}
+        <script type="module">
+            import init from './{js_name}';
+            init();
+        </script>
+        <!--AXUM_EGUI:body-->
+    </body>
+    </html>"#
+            );
+            fs::write(&html_dst, default_html).expect("Failed to write index.html");
+        }
+
+        // Point asset references at an external origin (CDN mode)
+        println!("cargo:rerun-if-env-changed=AXUM_EGUI_ASSET_BASE_URL");
+        let asset_base_url = env::var("AXUM_EGUI_ASSET_BASE_URL")
+            .ok()
+            .or_else(|| self.asset_base_url.clone());
+        if let Some(base_url) = asset_base_url {
+            let html = fs::read_to_string(&html_dst).expect("Failed to read index.html");
+            fs::write(&html_dst, rewrite_asset_urls(&html, &base_url))
+                .expect("Failed to write index.html");
+        }
+
+        // Inject the frontend configuration as the `config` slot
+        if let Some(config) = &self.config {
+            let json = config.to_json();
+            let html = fs::read_to_string(&html_dst).expect("Failed to read index.html");
+            fs::write(&html_dst, inject_config(&html, &json)).expect("Failed to write index.html");
+            println!("cargo:rustc-env={}_CONFIG={}", crate_name_upper, json);
+        }
+
+        // Export the dist directory path for rust-embed
+        // Convention: {CRATE_NAME}_DIST
+        let env_var_out = format!("{}_DIST", crate_name_upper);
+        println!("cargo:rustc-env={}={}", env_var_out, dist_dir.display());
+
+        // Record how the frontend was built
+        let record_path = Path::new(&out_dir).join(format!("{}-build.txt", crate_name));
+        fs::write(&record_path, record.lines.join("\n") + "\n")
+            .expect("Failed to write build record");
+        println!(
+            "cargo:rustc-env={}_BUILD_RECORD={}",
+            crate_name_upper,
+            record_path.display()
+        );
+    }
+
+    /// A `cargo` command using the configured toolchain.
+    fn cargo(&self) -> Command {
+        match &self.toolchain {
+            Some(toolchain) => {
+                let mut command = Command::new("rustup");
+                command
+                    .args(["run", toolchain, "cargo"])
+                    // Tools of the toolchain building the server
+                    .env_remove("CARGO")
+                    .env_remove("RUSTC")
+                    .env_remove("RUSTDOC");
+                command
+            }
+            None => Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string())),
+        }
+    }
+
+    /// A `rustc` command using the configured toolchain.
+    fn rustc(&self) -> Command {
+        match &self.toolchain {
+            Some(toolchain) => {
+                let mut command = Command::new("rustup");
+                command.args(["run", toolchain, "rustc"]);
+                command
+            }
+            None => Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string())),
+        }
+    }
+
+    /// Hash of the inputs of wasm-bindgen and wasm-opt for `wasm`, given the
+    /// versions of wasm-bindgen, the wasm-opt level and the wasm-opt
+    /// version. Features and the toolchain are covered by `wasm` itself; the
+    /// options applied after the cache (asset URLs, canvas, config, size
+    /// report) are not inputs.
+    fn cache_key(
+        &self,
+        wasm: &[u8],
+        tools: [Option<&str>; 3],
+        profiling: bool,
+        precompress: bool,
+    ) -> u64 {
+        let [wasm_bindgen_version, wasm_opt, wasm_opt_version] = tools;
+        cache_key(&[
+            env!("CARGO_PKG_VERSION").as_bytes(),
+            self.crate_name.as_bytes(),
+            wasm,
+            wasm_bindgen_version.unwrap_or_default().as_bytes(),
+            wasm_opt.unwrap_or_default().as_bytes(),
+            wasm_opt_version.unwrap_or_default().as_bytes(),
+            &[profiling as u8, precompress as u8],
+        ])
+    }
+
+    /// Build the frontend by running cargo, returning the path of the WASM.
+    fn build_with_cargo(
+        &self,
+        out_dir: &Path,
+        profiling: bool,
+        record: &mut BuildRecord,
+    ) -> String {
+        let crate_name = self.crate_name.as_str();
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        let manifest = Path::new(&manifest_dir).join(format!("../{}/Cargo.toml", crate_name));
+        let target_dir = out_dir.join("frontend-target");
+        let release = env::var("PROFILE").is_ok_and(|profile| profile == "release");
+
+        let mut command = self.cargo_build(&manifest, &target_dir, release, profiling);
+        let status = record
+            .command(&mut command)
+            .status()
+            .unwrap_or_else(|e| panic!("Failed to run cargo for {}: {}", crate_name, e));
+        if !status.success() {
+            panic!("cargo build failed for {}", crate_name);
+        }
+
+        target_dir
+            .join("wasm32-unknown-unknown")
+            .join(if release { "release" } else { "debug" })
+            .join(format!("{}.wasm", crate_name.replace('-', "_")))
+            .display()
+            .to_string()
+    }
+
+    /// The `cargo build` of the frontend at `manifest`, with the configured
+    /// toolchain, features and `--locked`.
+    fn cargo_build(
+        &self,
+        manifest: &Path,
+        target_dir: &Path,
+        release: bool,
+        profiling: bool,
+    ) -> Command {
+        let mut command = self.cargo();
+        command
+            .args(["build", "--lib", "--target", "wasm32-unknown-unknown"])
+            .arg("--manifest-path")
+            .arg(manifest)
+            .arg("--target-dir")
+            .arg(target_dir)
+            // Flags meant for the server's own target
+            .env_remove("CARGO_ENCODED_RUSTFLAGS")
+            .env_remove("CARGO_TARGET_DIR");
+        if !self.features.is_empty() {
+            command.arg("--features").arg(self.features.join(","));
+        }
+        if release {
+            command.arg("--release");
+        }
+        if self.locked {
+            command.arg("--locked");
+        }
+        if profiling {
+            let profile = if release { "RELEASE" } else { "DEV" };
+            command
+                .env(format!("CARGO_PROFILE_{}_DEBUG", profile), "true")
+                .env(format!("CARGO_PROFILE_{}_STRIP", profile), "none");
+        }
+        command
+    }
+}
+
+/// Tool versions and commands used to build a frontend.
+#[derive(Debug, Default)]
+struct BuildRecord {
+    lines: Vec<String>,
+}
+
+impl BuildRecord {
+    /// Record the output of a `--version` command, if it runs.
+    fn version(&mut self, command: &mut Command) -> Option<String> {
+        let output = command
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        self.lines.push(version.clone());
+        Some(version)
+    }
+
+    /// Record `command` before it is run.
+    fn command<'a>(&mut self, command: &'a mut Command) -> &'a mut Command {
+        let mut line = format!("$ {}", command.get_program().to_string_lossy());
+        for arg in command.get_args() {
+            line.push(' ');
+            line.push_str(&arg.to_string_lossy());
+        }
+        self.lines.push(line);
+        command
+    }
+}
+
+/// Marker written into a cache entry once it is complete.
+const CACHE_COMPLETE: &str = ".axum-egui-complete";
+
+/// Directory caching processed frontends: `AXUM_EGUI_CACHE_DIR`, or
+/// `axum-egui-cache` in the profile directory of the target dir
+/// (`OUT_DIR` is `target/{profile}/build/{package}/out`).
+fn cache_dir(out_dir: &Path) -> PathBuf {
+    println!("cargo:rerun-if-env-changed=AXUM_EGUI_CACHE_DIR");
+    match env::var_os("AXUM_EGUI_CACHE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => out_dir
+            .ancestors()
+            .nth(3)
+            .unwrap_or(out_dir)
+            .join("axum-egui-cache"),
+    }
+}
+
+/// Hash of `inputs`, each hashed with its length so they can't run into one
+/// another. `DefaultHasher` is only stable within a Rust release, which is
+/// enough for a local cache.
+fn cache_key(inputs: &[&[u8]]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    inputs.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Save the processed `dist_dir` as `cache_dir`, dropping older entries of
+/// the same crate. Failures only cost a rebuild next time.
+fn store_in_cache(dist_dir: &Path, cache_dir: &Path, crate_name: &str) {
+    let prefix = format!("{}-", crate_name);
+    let entries = cache_dir
+        .parent()
+        .and_then(|parent| fs::read_dir(parent).ok());
+    for entry in entries.into_iter().flatten().flatten() {
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+    if fs::create_dir_all(cache_dir).is_ok() {
+        copy_dir(dist_dir, cache_dir);
+        let _ = fs::write(cache_dir.join(CACHE_COMPLETE), "");
+    }
+}
+
+/// Copy the files of `from` into `to`, recursively, except the cache marker.
+fn copy_dir(from: &Path, to: &Path) {
+    let entries = fs::read_dir(from).expect("Failed to read directory");
+    for entry in entries.flatten() {
+        let target = to.join(entry.file_name());
+        if entry.file_name() == CACHE_COMPLETE {
+            continue;
+        }
+        if entry.path().is_dir() {
+            fs::create_dir_all(&target).expect("Failed to create directory");
+            copy_dir(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), &target).expect("Failed to copy file");
+        }
+    }
+}
+
+/// Path of the WASM built through the artifact dependency on `crate_name`.
+fn artifact_path(crate_name: &str) -> String {
+    let crate_name_underscored = crate_name.replace('-', "_");
+    let crate_name_upper = crate_name_underscored.to_uppercase();
 
-    // Find the WASM artifact
     // Cargo sets: CARGO_CDYLIB_FILE_{CRATE_NAME}_{crate_name}
     let env_var_name = format!(
         "CARGO_CDYLIB_FILE_{}_{}",
         crate_name_upper, crate_name_underscored
     );
-    let wasm_path = env::var(&env_var_name)
+    env::var(&env_var_name)
         .or_else(|_| env::var(format!("CARGO_CDYLIB_FILE_{}", crate_name_upper)))
         .unwrap_or_else(|_| {
             panic!(
@@ -108,67 +734,406 @@ pub fn frontend(crate_name: &str) {
                  bindeps = true",
                 env_var_name, crate_name, crate_name
             )
+        })
+}
+
+/// Values picked from the build environment for the frontend.
+///
+/// Each value is a string (or `null` for unset optional variables) under a
+/// lowercase key. Every variable read is registered with
+/// `cargo:rerun-if-env-changed`, so changing it rebuilds the page.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    values: Vec<(String, Option<String>)>,
+}
+
+impl Config {
+    /// An empty configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the environment variable `var` under its lowercased name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `var` is not set.
+    pub fn env(self, var: &str) -> Self {
+        let value = read_env(var).unwrap_or_else(|| {
+            panic!(
+                "Environment variable {} is required by the frontend configuration",
+                var
+            )
         });
+        self.value(var.to_lowercase(), value)
+    }
 
-    // Run wasm-bindgen
-    let status = Command::new("wasm-bindgen")
-        .args([
-            &wasm_path,
-            "--out-dir",
-            dist_dir.to_str().unwrap(),
-            "--target",
-            "web",
-            "--no-typescript",
-        ])
-        .status()
-        .expect(
-            "Failed to run wasm-bindgen. Is it installed?\n\
-             Run: cargo install wasm-bindgen-cli --version 0.2.104",
+    /// Add the environment variable `var` under its lowercased name, or
+    /// `null` if it is not set.
+    pub fn optional_env(mut self, var: &str) -> Self {
+        self.values.push((var.to_lowercase(), read_env(var)));
+        self
+    }
+
+    /// Add a fixed value under `key`.
+    pub fn value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.push((key.into(), Some(value.into())));
+        self
+    }
+
+    /// Add the cargo profile (`debug` or `release`) under `profile`.
+    pub fn profile(self) -> Self {
+        let profile = env::var("PROFILE").unwrap_or_else(|_| "debug".to_string());
+        self.value("profile", profile)
+    }
+
+    /// The values as a JSON object.
+    fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .values
+            .iter()
+            .map(|(key, value)| {
+                let value = value.as_deref().map_or("null".to_string(), json_string);
+                format!("{}:{}", json_string(key), value)
+            })
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/// Read `var`, rebuilding when it changes.
+fn read_env(var: &str) -> Option<String> {
+    println!("cargo:rerun-if-env-changed={}", var);
+    env::var(var).ok()
+}
+
+/// `value` as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Insert `json` as the `config` slot before `</head>` (or at the start of
+/// the page if it has none). The element id matches
+/// `axum_egui::slots::element_id("config")`.
+fn inject_config(html: &str, json: &str) -> String {
+    let script = format!(
+        r#"<script id="axum-egui-slot-config" type="application/json">{}</script>"#,
+        json.replace("</", "<\\/")
+    );
+    match html.find("</head>") {
+        Some(at) => format!("{}{}\n{}", &html[..at], script, &html[at..]),
+        None => format!("{}\n{}", script, html),
+    }
+}
+
+/// File of the dist directory written by [`Frontend::size_report`].
+///
+/// It is served with the other assets; use the option in development or CI.
+pub const SIZE_REPORT_FILE: &str = "size-report.txt";
+
+/// Write the `top` largest items of `wasm_file` to `report_file` with
+/// `twiggy top`.
+///
+/// Like `wasm-opt`, a missing `twiggy` binary is only a cargo warning.
+fn write_size_report(wasm_file: &Path, top: usize, report_file: &Path, record: &mut BuildRecord) {
+    let mut command = Command::new("twiggy");
+    command
+        .arg("top")
+        .arg("-n")
+        .arg(top.to_string())
+        .arg(wasm_file);
+    match record.command(&mut command).output() {
+        Ok(output) if output.status.success() => {
+            fs::write(report_file, output.stdout).expect("Failed to write size report");
+        }
+        Ok(output) => panic!(
+            "twiggy failed for {}: {}",
+            wasm_file.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => println!(
+            "cargo:warning=a size report was requested but twiggy was not found; \
+             skipping it (install it with `cargo install twiggy`)"
+        ),
+    }
+}
+
+/// Run `wasm-opt -O{level}` on a WASM file in place, keeping debug info and
+/// names with `profiling`.
+///
+/// A missing `wasm-opt` binary is reported as a cargo warning rather than a
+/// hard error, so optimized builds degrade gracefully on machines without
+/// binaryen installed.
+fn run_wasm_opt(wasm_file: &Path, level: &str, profiling: bool, record: &mut BuildRecord) {
+    let level = level.trim_start_matches('-').trim_start_matches('O');
+    let level = if level.is_empty() { "z" } else { level };
+
+    let mut command = Command::new("wasm-opt");
+    command.arg(format!("-O{}", level));
+    if profiling {
+        command.arg("-g");
+    }
+    command.arg(wasm_file).arg("-o").arg(wasm_file);
+    let status = record.command(&mut command).status();
+
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(_) => panic!("wasm-opt failed for {}", wasm_file.display()),
+        Err(_) => println!(
+            "cargo:warning=AXUM_EGUI_WASM_OPT is set but wasm-opt was not found; \
+             skipping optimization (install binaryen to enable it)"
+        ),
+    }
+}
+
+/// Extensions of the assets [`Frontend::precompress`] compresses. The
+/// `index.html` is filled in per request, so it is left alone.
+const PRECOMPRESSED_EXTENSIONS: &[&str] = &["wasm", "js"];
+
+/// The extension of compressed copies, the tool writing them next to the
+/// input and its arguments.
+const COMPRESSORS: &[(&str, &str, &[&str])] = &[
+    ("br", "brotli", &["--best", "--keep", "--force"]),
+    ("gz", "gzip", &["--best", "--keep", "--force", "--no-name"]),
+];
+
+/// Write the compressed copies of the assets in `dist_dir`.
+///
+/// Like `wasm-opt`, a missing tool is only a cargo warning.
+fn precompress_assets(dist_dir: &Path, record: &mut BuildRecord) {
+    let mut assets: Vec<PathBuf> = fs::read_dir(dist_dir)
+        .expect("Failed to read dist directory")
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| PRECOMPRESSED_EXTENSIONS.contains(&extension))
+        })
+        .collect();
+    assets.sort();
+
+    for (extension, tool, args) in COMPRESSORS {
+        for asset in &assets {
+            let mut command = Command::new(tool);
+            command.args(*args).arg(asset);
+            match record.command(&mut command).status() {
+                Ok(status) if status.success() => {}
+                Ok(_) => panic!("{} failed for {}", tool, asset.display()),
+                Err(_) => {
+                    println!(
+                        "cargo:warning=precompression is enabled but {tool} was not found; \
+                         skipping the .{extension} files"
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Remove compressed copies left in `dist_dir` by an earlier build, which
+/// would otherwise be served for assets that changed since.
+fn remove_precompressed(dist_dir: &Path) {
+    for entry in fs::read_dir(dist_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let compressed = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                COMPRESSORS
+                    .iter()
+                    .any(|(compressed, _, _)| *compressed == extension)
+            });
+        if compressed {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Rewrite relative `./` asset references in `html` to load from `base_url`.
+fn rewrite_asset_urls(html: &str, base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let mut html = html.to_string();
+    for prefix in ["from '", "from \"", "src=\"", "href=\""] {
+        html = html.replace(&format!("{}./", prefix), &format!("{}{}/", prefix, base));
+    }
+    html.replace(
+        r#"<script type="module">"#,
+        r#"<script type="module" crossorigin="anonymous">"#,
+    )
+    .replace(
+        r#"<script type="module" src="#,
+        r#"<script type="module" crossorigin="anonymous" src="#,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn key(frontend: &Frontend, wasm: &[u8], tools: [Option<&str>; 3]) -> u64 {
+        frontend.cache_key(wasm, tools, false, false)
+    }
+
+    #[test]
+    fn cache_key_changes_with_the_inputs_of_the_tools() {
+        let frontend = Frontend::new("app");
+        let tools = [
+            Some("wasm-bindgen 0.2.100"),
+            Some("z"),
+            Some("wasm-opt 121"),
+        ];
+        let base = key(&frontend, b"wasm", tools);
+
+        assert_ne!(base, key(&Frontend::new("other"), b"wasm", tools));
+        assert_ne!(base, key(&frontend, b"wasm2", tools));
+        assert_ne!(
+            base,
+            key(
+                &frontend,
+                b"wasm",
+                [
+                    Some("wasm-bindgen 0.2.101"),
+                    Some("z"),
+                    Some("wasm-opt 121")
+                ]
+            )
+        );
+        assert_ne!(
+            base,
+            key(
+                &frontend,
+                b"wasm",
+                [
+                    Some("wasm-bindgen 0.2.100"),
+                    Some("3"),
+                    Some("wasm-opt 121")
+                ]
+            )
+        );
+        assert_ne!(
+            base,
+            key(
+                &frontend,
+                b"wasm",
+                [
+                    Some("wasm-bindgen 0.2.100"),
+                    Some("z"),
+                    Some("wasm-opt 122")
+                ]
+            )
+        );
+        assert_ne!(
+            base,
+            key(
+                &frontend,
+                b"wasm",
+                [Some("wasm-bindgen 0.2.100"), None, None]
+            )
+        );
+        assert_ne!(base, frontend.cache_key(b"wasm", tools, true, false));
+        assert_ne!(base, frontend.cache_key(b"wasm", tools, false, true));
+        // Inputs don't run into one another
+        assert_ne!(
+            cache_key(&[b"ab".as_slice(), b"c"]),
+            cache_key(&[b"a".as_slice(), b"bc"])
+        );
+    }
+
+    #[test]
+    fn cache_key_ignores_options_applied_after_the_tools() {
+        let tools = [Some("wasm-bindgen 0.2.100"), None, None];
+        let base = key(&Frontend::new("app"), b"wasm", tools);
+        let configured = Frontend::new("app")
+            .asset_base_url("https://cdn.example.com")
+            .canvas("support", "width: 320px;")
+            .config(Config::new().value("mode", "demo"))
+            .size_report(10)
+            // Covered by the WASM they build
+            .features(["demo"])
+            .toolchain("nightly")
+            .locked();
+        assert_eq!(base, key(&configured, b"wasm", tools));
+    }
+
+    fn args(command: &Command) -> Vec<&str> {
+        command
+            .get_args()
+            .map(|arg| arg.to_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn cargo_build_passes_features_and_locked() {
+        let manifest = Path::new("../app/Cargo.toml");
+        let target_dir = Path::new("out/frontend-target");
+        let plain = Frontend::new("app").cargo_build(manifest, target_dir, false, false);
+        assert_eq!(
+            args(&plain),
+            [
+                "build",
+                "--lib",
+                "--target",
+                "wasm32-unknown-unknown",
+                "--manifest-path",
+                "../app/Cargo.toml",
+                "--target-dir",
+                "out/frontend-target",
+            ]
         );
 
-    if !status.success() {
-        panic!("wasm-bindgen failed for {}", crate_name);
-    }
-
-    // Copy or create index.html
-    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let html_src = Path::new(&manifest_dir).join(format!("../{}/index.html", crate_name));
-    let html_dst = dist_dir.join("index.html");
-
-    if html_src.exists() {
-        fs::copy(&html_src, &html_dst).expect("Failed to copy index.html");
-    } else {
-        // Create default HTML
-        let js_name = format!("{}.js", crate_name_underscored);
-        let default_html = format!(
-            r#"<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="utf-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>axum-egui</title>
-    <style>
-        html, body {{ margin: 0; padding: 0; width: 100%; height: 100%; overflow: hidden; }}
-        canvas {{ width: 100%; height: 100%; }}
-        #loading_text {{ position: absolute; top: 50%; left: 50%; transform: translate(-50%, -50%); font-family: sans-serif; font-size: 1.5em; color: #888; }}
-    </style>
-    <!--AXUM_EGUI_INITIAL_STATE-->
-</head>
-<body>
-    <p id="loading_text">Loading...</p>
-    <canvas id="the_canvas_id"></canvas>
-    <script type="module">
-        import init from './{js_name}';
-        init();
-    </script>
-</body>
-</html>"#
+        let command = Frontend::new("app")
+            .features(["demo", "metrics"])
+            .locked()
+            .cargo_build(manifest, target_dir, true, false);
+        assert!(args(&command).ends_with(&["--features", "demo,metrics", "--release", "--locked"]));
+        assert!(
+            command
+                .get_envs()
+                .any(|(var, value)| var == "CARGO_ENCODED_RUSTFLAGS" && value.is_none())
         );
-        fs::write(&html_dst, default_html).expect("Failed to write index.html");
+
+        let profiling = Frontend::new("app").cargo_build(manifest, target_dir, true, true);
+        assert!(profiling.get_envs().any(|(var, value)| {
+            var == "CARGO_PROFILE_RELEASE_DEBUG" && value == Some(OsStr::new("true"))
+        }));
     }
 
-    // Export the dist directory path for rust-embed
-    // Convention: {CRATE_NAME}_DIST
-    let env_var_out = format!("{}_DIST", crate_name_upper);
-    println!("cargo:rustc-env={}={}", env_var_out, dist_dir.display());
+    #[test]
+    fn toolchain_runs_cargo_through_rustup() {
+        let command = Frontend::new("app")
+            .toolchain("nightly-2025-06-01")
+            .cargo_build(Path::new("Cargo.toml"), Path::new("target"), false, false);
+        assert_eq!(command.get_program(), "rustup");
+        assert_eq!(
+            args(&command)[..4],
+            ["run", "nightly-2025-06-01", "cargo", "build"]
+        );
+        // The server's own toolchain isn't passed on
+        assert!(
+            command
+                .get_envs()
+                .any(|(var, value)| var == "RUSTC" && value.is_none())
+        );
+
+        let rustc = Frontend::new("app").toolchain("nightly").rustc();
+        assert_eq!(rustc.get_program(), "rustup");
+        assert_eq!(args(&rustc), ["run", "nightly", "rustc"]);
+    }
 }
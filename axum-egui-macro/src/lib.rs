@@ -9,26 +9,756 @@ use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote};
 use syn::{
-    FnArg, GenericParam, Ident, ItemFn, LitStr, Pat, ReturnType, Type, TypePath,
+    Expr, FnArg, GenericParam, Ident, ItemFn, LitStr, Pat, ReturnType, Token, Type, TypePath,
     parse::Parse, parse::ParseStream, parse_macro_input,
 };
 
-/// Configuration parsed from `#[server]` or `#[server("/custom/path")]`
+/// How a server function is exposed over HTTP.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// JSON request/response over POST (the default).
+    Rpc,
+    /// A stream of items over Server-Sent Events.
+    Sse,
+    /// A bidirectional stream over a WebSocket.
+    Ws,
+}
+
+/// Configuration parsed from `#[server]`, `#[server("/custom/path")]`,
+/// `#[server(sse, conflate, delta)]`, `#[server(get, audit, redact(password))]`,
+/// `#[server(postcard)]`, `#[server(cbor)]` or
+/// `#[server(ws, post_params, auth, versioned, protocols("v1"), idle_timeout = "60s",
+/// max_message_size = "256KB", mock = mock_chat, "/custom/path")]`
 struct ServerFnArgs {
     path: Option<String>,
+    mode: Mode,
+    /// Serve an RPC function over GET, with its args in the query string.
+    get: bool,
+    /// Binary wire format of an RPC function (`postcard` or `cbor`), JSON if
+    /// `None`.
+    encoding: Option<Ident>,
+    /// Send streaming args in a POST body in exchange for a ticket.
+    post_params: bool,
+    /// Require a stream ticket identifying the user.
+    auth: bool,
+    /// WebSocket subprotocols accepted by a `ws` function, most preferred first.
+    protocols: Vec<LitStr>,
+    /// Arguments masked when calls are logged.
+    redact: Vec<Ident>,
+    /// Record calls in the audit trail.
+    audit: bool,
+    /// Share one run among identical concurrent calls.
+    coalesce: bool,
+    /// Per-user quota: name, calls allowed and period.
+    quota: Option<(String, u64, Ident)>,
+    /// Cache results for this many milliseconds.
+    cache_ms: Option<u64>,
+    /// Serve expired results for this many more milliseconds while
+    /// refreshing them.
+    stale_ms: Option<u64>,
+    /// Wrap stream messages in a schema-versioned envelope.
+    versioned: bool,
+    /// Keep only the newest pending stream item.
+    conflate: bool,
+    /// Send stream items as diffs against the previous one.
+    delta: bool,
+    /// Close `ws` connections idle for this many milliseconds.
+    idle_timeout_ms: Option<u64>,
+    /// Close `ws` connections sending messages larger than this many bytes.
+    max_message_size: Option<usize>,
+    /// Function the client calls instead of the server with a `mock` feature.
+    mock: Option<syn::Path>,
+    /// Static headers the client sends and the handler requires: name and
+    /// value expression.
+    headers: Vec<(LitStr, Expr)>,
+    /// Abort client calls after this many milliseconds.
+    timeout_ms: Option<u64>,
+    /// Give up on calls in the handler after this many milliseconds.
+    server_timeout_ms: Option<u64>,
 }
 
 impl Parse for ServerFnArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        if input.is_empty() {
-            return Ok(ServerFnArgs { path: None });
+        let mut args = ServerFnArgs {
+            path: None,
+            mode: Mode::Rpc,
+            get: false,
+            encoding: None,
+            post_params: false,
+            auth: false,
+            protocols: Vec::new(),
+            redact: Vec::new(),
+            audit: false,
+            coalesce: false,
+            quota: None,
+            cache_ms: None,
+            stale_ms: None,
+            versioned: false,
+            conflate: false,
+            delta: false,
+            idle_timeout_ms: None,
+            max_message_size: None,
+            mock: None,
+            headers: Vec::new(),
+            timeout_ms: None,
+            server_timeout_ms: None,
+        };
+        let mut mode_set = false;
+        let mut get: Option<Ident> = None;
+        let mut post_params: Option<Ident> = None;
+        let mut auth: Option<Ident> = None;
+        let mut protocols: Option<Ident> = None;
+        let mut redact: Option<Ident> = None;
+        let mut audit: Option<Ident> = None;
+        let mut coalesce: Option<Ident> = None;
+        let mut quota: Option<(Ident, (String, u64, Ident))> = None;
+        let mut versioned: Option<Ident> = None;
+        let mut conflate: Option<Ident> = None;
+        let mut delta: Option<Ident> = None;
+        let mut ws_limits: Vec<Ident> = Vec::new();
+        let mut cache: Option<Ident> = None;
+        let mut stale: Option<Ident> = None;
+        let mut headers: Option<Ident> = None;
+        let mut timeouts: Vec<Ident> = Vec::new();
+
+        while !input.is_empty() {
+            if input.peek(LitStr) {
+                let path: LitStr = input.parse()?;
+                if args.path.is_some() {
+                    return Err(syn::Error::new_spanned(path, "duplicate API path"));
+                }
+                args.path = Some(path.value());
+            } else {
+                let ident: Ident = input.parse()?;
+                let mode = match ident.to_string().as_str() {
+                    "sse" => Mode::Sse,
+                    "ws" => Mode::Ws,
+                    "get" => {
+                        if get.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `get`"));
+                        }
+                        get = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "postcard" | "cbor" => {
+                        if let Some(encoding) = &args.encoding {
+                            return Err(syn::Error::new_spanned(
+                                ident,
+                                format!("duplicate encoding, already `{encoding}`"),
+                            ));
+                        }
+                        args.encoding = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "post_params" => {
+                        if post_params.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `post_params`"));
+                        }
+                        post_params = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "auth" => {
+                        if auth.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `auth`"));
+                        }
+                        auth = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "audit" => {
+                        if audit.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `audit`"));
+                        }
+                        audit = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "coalesce" => {
+                        if coalesce.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `coalesce`"));
+                        }
+                        coalesce = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "versioned" => {
+                        if versioned.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `versioned`"));
+                        }
+                        versioned = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "conflate" => {
+                        if conflate.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `conflate`"));
+                        }
+                        conflate = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "delta" => {
+                        if delta.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `delta`"));
+                        }
+                        delta = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "protocols" => {
+                        if protocols.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `protocols`"));
+                        }
+                        let content;
+                        syn::parenthesized!(content in input);
+                        args.protocols = content
+                            .parse_terminated(<LitStr as Parse>::parse, Token![,])?
+                            .into_iter()
+                            .collect();
+                        if args.protocols.is_empty() {
+                            return Err(syn::Error::new_spanned(
+                                ident,
+                                "`protocols` needs at least one subprotocol name",
+                            ));
+                        }
+                        protocols = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "idle_timeout" | "max_message_size" => {
+                        if ws_limits.iter().any(|limit| limit == &ident) {
+                            return Err(syn::Error::new_spanned(
+                                &ident,
+                                format!("duplicate `{}`", ident),
+                            ));
+                        }
+                        input.parse::<Token![=]>()?;
+                        let value: LitStr = input.parse()?;
+                        if ident == "idle_timeout" {
+                            args.idle_timeout_ms = Some(parse_duration_ms(&value)?);
+                        } else {
+                            args.max_message_size = Some(parse_size(&value)?);
+                        }
+                        ws_limits.push(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "cache" | "stale" => {
+                        let slot = if ident == "cache" {
+                            &mut cache
+                        } else {
+                            &mut stale
+                        };
+                        if slot.is_some() {
+                            return Err(syn::Error::new_spanned(
+                                &ident,
+                                format!("duplicate `{}`", ident),
+                            ));
+                        }
+                        input.parse::<Token![=]>()?;
+                        let value: LitStr = input.parse()?;
+                        let ms = parse_duration_ms(&value)?;
+                        if ident == "cache" {
+                            args.cache_ms = Some(ms);
+                        } else {
+                            args.stale_ms = Some(ms);
+                        }
+                        *slot = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "timeout" | "server_timeout" => {
+                        if timeouts.iter().any(|timeout| timeout == &ident) {
+                            return Err(syn::Error::new_spanned(
+                                &ident,
+                                format!("duplicate `{}`", ident),
+                            ));
+                        }
+                        input.parse::<Token![=]>()?;
+                        let value: LitStr = input.parse()?;
+                        let ms = parse_duration_ms(&value)?;
+                        if ident == "timeout" {
+                            args.timeout_ms = Some(ms);
+                        } else {
+                            args.server_timeout_ms = Some(ms);
+                        }
+                        timeouts.push(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "quota" => {
+                        if quota.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `quota`"));
+                        }
+                        input.parse::<Token![=]>()?;
+                        let value: LitStr = input.parse()?;
+                        quota = Some((ident, parse_quota(&value)?));
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "mock" => {
+                        if args.mock.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `mock`"));
+                        }
+                        input.parse::<Token![=]>()?;
+                        args.mock = Some(input.parse()?);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "redact" => {
+                        if redact.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `redact`"));
+                        }
+                        let content;
+                        syn::parenthesized!(content in input);
+                        args.redact = content
+                            .parse_terminated(Ident::parse, Token![,])?
+                            .into_iter()
+                            .collect();
+                        if args.redact.is_empty() {
+                            return Err(syn::Error::new_spanned(
+                                ident,
+                                "`redact` needs at least one argument name",
+                            ));
+                        }
+                        redact = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    "headers" => {
+                        if headers.is_some() {
+                            return Err(syn::Error::new_spanned(ident, "duplicate `headers`"));
+                        }
+                        let content;
+                        syn::parenthesized!(content in input);
+                        for header in content.parse_terminated(syn::ExprTuple::parse, Token![,])? {
+                            args.headers.push(parse_header(&header)?);
+                        }
+                        if args.headers.is_empty() {
+                            return Err(syn::Error::new_spanned(
+                                ident,
+                                "`headers` needs at least one `(\"name\", value)` pair",
+                            ));
+                        }
+                        headers = Some(ident);
+                        if !input.is_empty() {
+                            input.parse::<Token![,]>()?;
+                        }
+                        continue;
+                    }
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            &ident,
+                            format!(
+                                "unknown server function option `{}`. \
+                                Expected an API path string, `get`, `postcard`, `cbor`, `sse`, `ws`, `post_params`, \
+                                `auth`, `audit`, `coalesce`, `versioned`, `conflate`, `delta`, \
+                                `protocols(...)`, `redact(...)`, `headers(...)`, `cache = \"...\"`, `stale = \"...\"`, `quota = \"...\"`, \
+                                `timeout = \"...\"`, `server_timeout = \"...\"`, `idle_timeout = \"...\"`, \
+                                `max_message_size = \"...\"` or `mock = path`.",
+                                other
+                            ),
+                        ));
+                    }
+                };
+                if mode_set {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        "only one of `sse` or `ws` may be specified",
+                    ));
+                }
+                args.mode = mode;
+                mode_set = true;
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
         }
 
-        let path: LitStr = input.parse()?;
-        Ok(ServerFnArgs {
-            path: Some(path.value()),
-        })
+        if let Some(ident) = get {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`get` only applies to regular server functions; streaming \
+                    functions are already served over GET",
+                ));
+            }
+            args.get = true;
+        }
+
+        if let Some(ident) = &args.encoding {
+            if args.mode != Mode::Rpc || args.get {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!(
+                        "`{ident}` only applies to regular server functions \
+                        served over POST, not to streams or `get` functions"
+                    ),
+                ));
+            }
+        }
+
+        if let Some(ident) = post_params {
+            if args.mode == Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`post_params` only applies to streaming server functions \
+                    (`sse` or `ws`); regular server functions already send \
+                    their arguments in the POST body",
+                ));
+            }
+            args.post_params = true;
+        }
+
+        if let Some(ident) = auth {
+            if args.mode == Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`auth` only applies to streaming server functions \
+                    (`sse` or `ws`); regular server functions receive the \
+                    request's headers and cookies",
+                ));
+            }
+            args.auth = true;
+        }
+
+        if let Some(ident) = versioned {
+            if args.mode == Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`versioned` only applies to streaming server functions \
+                    (`sse` or `ws`); regular server functions are called by \
+                    the frontend that was just loaded",
+                ));
+            }
+            args.versioned = true;
+        }
+
+        if let Some(ident) = conflate {
+            if args.mode == Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`conflate` only applies to streaming server functions \
+                    (`sse` or `ws`); regular server functions return one value",
+                ));
+            }
+            args.conflate = true;
+        }
+
+        if let Some(ident) = delta {
+            if args.mode == Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`delta` only applies to streaming server functions \
+                    (`sse` or `ws`); regular server functions return one value",
+                ));
+            }
+            if args.versioned {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`delta` can't be combined with `versioned`: diffs don't \
+                    carry the schema version of the items",
+                ));
+            }
+            args.delta = true;
+        }
+
+        if let Some(ident) = audit {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`audit` only applies to regular server functions; \
+                    streaming functions are not audited",
+                ));
+            }
+            args.audit = true;
+        }
+
+        if let Some(ident) = headers {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`headers` only applies to regular server functions; browsers \
+                    can't set headers on `EventSource` and WebSocket requests",
+                ));
+            }
+        }
+
+        if let Some(ident) = timeouts.first() {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!(
+                        "`{ident}` only applies to regular server functions; use \
+                        `idle_timeout` to close idle `ws` connections"
+                    ),
+                ));
+            }
+        }
+
+        if let Some(ident) = coalesce {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`coalesce` only applies to regular server functions; \
+                    each stream is its own connection",
+                ));
+            }
+            args.coalesce = true;
+        }
+
+        if let Some((ident, quota)) = quota {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`quota` only applies to regular server functions",
+                ));
+            }
+            args.quota = Some(quota);
+        }
+
+        if let Some(ident) = cache {
+            if args.mode != Mode::Rpc {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`cache` only applies to regular server functions; streams \
+                    are not cached",
+                ));
+            }
+        }
+
+        if let Some(ident) = stale {
+            if args.cache_ms.is_none() {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`stale` needs `cache = \"...\"`: it extends how long cached \
+                    results are served while they are refreshed",
+                ));
+            }
+        }
+
+        if let Some(ident) = protocols {
+            if args.mode != Mode::Ws {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    "`protocols` only applies to `ws` server functions",
+                ));
+            }
+        }
+
+        if let Some(ident) = ws_limits.first() {
+            if args.mode != Mode::Ws {
+                return Err(syn::Error::new_spanned(
+                    ident,
+                    format!("`{}` only applies to `ws` server functions", ident),
+                ));
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+/// Parse a quota like `"exports:10/day"` into its name, limit and
+/// `axum_egui::quota::QuotaPeriod` variant.
+fn parse_quota(lit: &LitStr) -> syn::Result<(String, u64, Ident)> {
+    let value = lit.value();
+    let invalid = || {
+        syn::Error::new_spanned(
+            lit,
+            "expected a quota like \"exports:10/day\": a name, the number of calls \
+            and a period of `minute`, `hour`, `day` or `week`",
+        )
+    };
+    let (name, allowance) = value.split_once(':').ok_or_else(invalid)?;
+    let (limit, period) = allowance.split_once('/').ok_or_else(invalid)?;
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let limit: u64 = limit.trim().parse().map_err(|_| invalid())?;
+    let period = match period.trim() {
+        "minute" => "Minute",
+        "hour" => "Hour",
+        "day" => "Day",
+        "week" => "Week",
+        _ => return Err(invalid()),
+    };
+    if !valid_name || limit == 0 {
+        return Err(invalid());
     }
+    Ok((name.to_string(), limit, Ident::new(period, lit.span())))
+}
+
+/// Parse a `("name", value)` pair of `headers(...)`. Names must be lowercase
+/// HTTP tokens and not one of the headers calls set themselves.
+fn parse_header(pair: &syn::ExprTuple) -> syn::Result<(LitStr, Expr)> {
+    let invalid = || {
+        syn::Error::new_spanned(
+            pair,
+            "expected a `(\"name\", value)` pair, e.g. \
+            `(\"x-app-version\", env!(\"CARGO_PKG_VERSION\"))`",
+        )
+    };
+    let mut elems = pair.elems.iter();
+    let (
+        Some(Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(name),
+            ..
+        })),
+        Some(value),
+        None,
+    ) = (elems.next(), elems.next(), elems.next())
+    else {
+        return Err(invalid());
+    };
+    let header = name.value();
+    let valid = !header.is_empty()
+        && header.bytes().all(|b| {
+            b.is_ascii_lowercase() || b.is_ascii_digit() || b"-_.!#$%&'*+^`|~".contains(&b)
+        });
+    if !valid {
+        return Err(syn::Error::new_spanned(
+            name,
+            "header names must be lowercase HTTP tokens, e.g. \"x-app-version\"",
+        ));
+    }
+    if ["accept", "content-type", "priority", "traceparent"].contains(&header.as_str()) {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!("`{header}` is set by every server function call"),
+        ));
+    }
+    Ok((name.clone(), value.clone()))
+}
+
+/// Parse a duration like `"250ms"`, `"60s"`, `"5m"` or `"1h"` into
+/// milliseconds.
+fn parse_duration_ms(lit: &LitStr) -> syn::Result<u64> {
+    let value = lit.value();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => 0,
+    };
+    match number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+    {
+        Some(ms) if ms > 0 => Ok(ms),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "expected a duration like \"500ms\", \"60s\", \"5m\" or \"1h\"",
+        )),
+    }
+}
+
+/// Parse a size like `"512B"`, `"256KB"` or `"4MB"` (powers of 1024) into
+/// bytes.
+fn parse_size(lit: &LitStr) -> syn::Result<usize> {
+    let value = lit.value();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "B" => 1,
+        "KB" | "KiB" => 1024,
+        "MB" | "MiB" => 1024 * 1024,
+        _ => 0,
+    };
+    match number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+    {
+        Some(bytes) if bytes > 0 => Ok(bytes),
+        _ => Err(syn::Error::new_spanned(
+            lit,
+            "expected a size like \"512B\", \"256KB\" or \"4MB\"",
+        )),
+    }
+}
+
+/// Whether `ty` is `impl Stream<...>`, the request body of an upload.
+fn is_body_stream(ty: &Type) -> bool {
+    let Type::ImplTrait(impl_trait) = ty else {
+        return false;
+    };
+    impl_trait.bounds.iter().any(|bound| {
+        matches!(bound, syn::TypeParamBound::Trait(bound)
+            if bound.path.segments.last().is_some_and(|segment| segment.ident == "Stream"))
+    })
+}
+
+/// Whether `ty` is (a path ending in) `FileUpload`, a file of a multipart
+/// upload.
+fn is_file_upload(ty: &Type) -> bool {
+    matches!(ty, Type::Path(TypePath { path, .. })
+        if path.segments.last().is_some_and(|seg| seg.ident == "FileUpload"))
+}
+
+/// Whether `ty` is (a path ending in) `ServerStream<..>`.
+fn is_server_stream(ty: &Type) -> bool {
+    matches!(ty, Type::Path(TypePath { path, .. })
+        if path.segments.last().is_some_and(|seg| seg.ident == "ServerStream"))
+}
+
+/// The first generic type argument of a path type, e.g. `T` in `Result<T, E>`.
+fn first_type_arg(ty: &Type) -> Option<&Type> {
+    let Type::Path(TypePath { path, .. }) = ty else {
+        return None;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &path.segments.last()?.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
 }
 
 /// Validate that an API path is safe and well-formed.
@@ -166,8 +896,157 @@ fn validate_generics(generics: &syn::Generics) -> syn::Result<()> {
 /// This generates:
 /// - A function that executes directly on the server (when `ssr` feature is enabled)
 /// - A function that makes an HTTP POST request (when `hydrate` feature is enabled)
+/// - The same request through `axum_egui::native` from native programs (when
+///   `native` feature is enabled, with `axum-egui/native-client`)
 /// - An axum handler function `{name}_handler` for server-side routing (ssr only)
-/// - An args struct `{Name}Args` for serialization
+/// - An args struct `{Name}Args` for serialization, with the endpoint's
+///   `PATH`, `METHOD` and a `url()` builder
+/// - An entry in `axum_egui::registry` (ssr only)
+/// - A hidden module `__server_fn_{name}` holding the generated items, which
+///   are re-exported next to the function, so two modules' server functions
+///   of the same name can't collide through glob imports
+/// - With axum-egui's `ts` feature, a `ts_rs::TS` derive on the args struct
+///   and an `axum_egui::ts` endpoint entry (ssr only)
+///
+/// The body runs with an `axum_egui::context::RequestContext` in scope, giving
+/// access to the request and to `on_disconnect` cleanup hooks.
+///
+/// RPC functions run in a `server_fn` tracing span joining the caller's W3C
+/// `traceparent` (see `axum_egui::trace`).
+///
+/// The handler answers requests that aren't `application/json` with `415
+/// Unsupported Media Type`, and bodies that don't decode as the arguments
+/// with `400 Bad Request`; both carry a JSON body naming the function and
+/// the expected content type (see `axum_egui::rpc::ArgsRejection`).
+///
+/// An `impl Stream<Item = Bytes>` argument makes the function an upload: it
+/// reads the request body as it arrives, and its other arguments travel in
+/// the query string. Browsers stream the body where they can (see
+/// `axum_egui::upload`). Uploads can't be `get`, `postcard`, `cbor`,
+/// `coalesce` or `cache`.
+///
+/// `FileUpload` arguments are sent as files of a `multipart/form-data` body,
+/// each in a part named after its argument, with the other arguments as
+/// JSON in an `args` part. The handler reads the whole body within the
+/// server's body limit. The same options don't apply, nor a streaming body.
+///
+/// `get` serves a read-only function with `GET` instead, its arguments
+/// URL-encoded in the query string (`/api/search?q=egui&page=2`), so
+/// responses can be cached by the browser or a proxy and the endpoint called
+/// with plain `curl`. Arguments must then be scalars: numbers, strings,
+/// booleans, options and unit enums. Route the handler with `get`.
+///
+/// `postcard` sends the arguments and result in the compact
+/// [postcard](https://docs.rs/postcard) binary format instead of JSON
+/// (needs `axum-egui/postcard`). The handler still accepts JSON bodies and
+/// answers in JSON unless the client lists `application/x-postcard` in
+/// `Accept`, so curl and non-Rust clients keep working. `cbor` does the same
+/// with [CBOR](https://cbor.io) (`application/cbor`, needs `axum-egui/cbor`),
+/// which non-Rust clients can decode too.
+///
+/// `coalesce` lets identical concurrent calls (same arguments) share one run
+/// of the function, so a burst of dashboards refreshing at once costs one
+/// query (see `axum_egui::coalesce`). The `Ok` type must be `Clone`, and the
+/// result must not depend on who is calling.
+///
+/// `cache = "30s"` answers calls with the same arguments from a server-side
+/// cache for that long; `stale = "5m"` then keeps serving the expired result
+/// for up to that much longer while one background call refreshes it. The
+/// `Ok` type must be `Clone`; see `axum_egui::cache`, whose `updates` stream
+/// refreshed results to open pages.
+///
+/// `quota = "exports:10/day"` counts calls per user against a named
+/// allowance (per `minute`, `hour`, `day` or `week`); calls beyond it fail
+/// with `429 Too Many Requests` and an `axum_egui::quota::QuotaExceeded`
+/// error saying when the quota resets. It counts requests, not executions,
+/// so calls answered by `cache` or `coalesce` count too. Needs
+/// `axum_egui::quota::Quotas` installed.
+///
+/// `headers(("x-app-version", env!("CARGO_PKG_VERSION")))` attaches static
+/// headers to every call, and the handler rejects calls without them, or
+/// with other values, with `400 Bad Request` (see
+/// `axum_egui::rpc::HeaderRejection`), e.g. clients of an older build or
+/// without a shared secret. Values are constants: string literals, `env!`
+/// or `const` items.
+///
+/// `timeout = "5s"` aborts calls still waiting for the response after that
+/// long; they return `ServerFnError::Timeout`. `server_timeout = "30s"`
+/// makes the handler give up too: the function is dropped at its next
+/// `.await` and the call fails with `504 Gateway Timeout`.
+///
+/// `mock = path::to::function` names an async function with the same
+/// signature that the client calls instead of the server when the crate's
+/// `mock` feature is enabled, to work on the frontend without a backend.
+///
+/// `redact(password, token)` masks those arguments when calls are logged
+/// with `axum_egui::logging::CallLog`. `audit` records every call (caller,
+/// function, argument hash, outcome) in the `axum_egui::audit` trail.
+///
+/// # Streaming
+///
+/// `#[server(sse)]` functions return `Result<ServerStream<T>, ServerFnError>`
+/// and are served over Server-Sent Events; route the handler with `get`.
+/// `#[server(ws)]` functions additionally take one `ServerStream<In>`
+/// argument carrying the client's messages, and are served over a WebSocket
+/// (also `get`). Other arguments are sent in the query string.
+///
+/// With `post_params`, streaming arguments are instead POSTed to the same
+/// path in exchange for a short-lived ticket, keeping large or sensitive
+/// inputs out of URLs. This also generates `{name}_params_handler`, which
+/// must be routed with `post` next to the stream handler.
+///
+/// `ws` functions can list the `Sec-WebSocket-Protocol`s they accept with
+/// `protocols("chat.v2", "chat.v1")`: the client offers them, the handler
+/// selects the first one it supports and rejects other connections with
+/// `400 Bad Request`.
+///
+/// `ws` functions can also set `idle_timeout = "60s"` (`ms`, `s`, `m` or
+/// `h`) and `max_message_size = "256KB"` (`B`, `KB` or `MB`, powers of 1024):
+/// connections without messages for that long are closed with
+/// `axum_egui::ws::CLOSE_GOING_AWAY`, and clients sending larger messages
+/// with `CLOSE_MESSAGE_TOO_BIG`.
+///
+/// With `auth`, the client first mints a short-lived ticket for the current
+/// user and adds it to the stream URL; the handler rejects requests without
+/// a valid one with `401 Unauthorized` and makes the user available through
+/// `axum_egui::auth::user()`.
+///
+/// With `conflate`, only the newest pending item is kept, on the server and
+/// on the client (see `ServerStream::latest_only`): a slow connection or a
+/// busy UI skips to the current value instead of working through a backlog.
+/// Suits prices, positions and other values that replace the previous ones.
+///
+/// With `delta`, the server sends each item as a JSON Patch against the one
+/// before it, and a full item every `axum_egui::delta::FULL_EVERY` items;
+/// the client reassembles them. For streams of items that change little
+/// between messages.
+///
+/// With `versioned`, the messages the server sends carry the schema version
+/// of their type, which must implement `axum_egui::schema::StreamSchema`
+/// (`#[derive(StreamSchema)]`). A client built against another version gets
+/// `ServerFnError::SchemaMismatch` instead of parse errors.
+///
+/// ```ignore
+/// #[server(sse)]
+/// pub async fn ticks(every_ms: u64) -> Result<ServerStream<u64>, ServerFnError> {
+///     Ok(ServerStream::new(tick_stream(every_ms)))
+/// }
+///
+/// #[server(ws, "/api/chat")]
+/// pub async fn chat(
+///     room: String,
+///     input: ServerStream<ChatMessage>,
+/// ) -> Result<ServerStream<ChatMessage>, ServerFnError> {
+///     Ok(relay(room, input))
+/// }
+///
+/// #[server(sse, post_params)]
+/// pub async fn search(filter: SearchFilter) -> Result<ServerStream<Hit>, ServerFnError> {
+///     Ok(run_search(filter))
+/// }
+///
+/// // Router::new().route("/api/search", get(search_handler).post(search_params_handler))
+/// ```
 #[proc_macro_attribute]
 pub fn server(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as ServerFnArgs);
@@ -196,13 +1075,22 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
     validate_return_type(&input_fn.sig.output)?;
 
     // Determine the API path and validate it
-    let api_path = args.path.unwrap_or_else(|| format!("/api/{}", fn_name_str));
+    let api_path = args
+        .path
+        .clone()
+        .unwrap_or_else(|| format!("/api/{}", fn_name_str));
     validate_api_path(&api_path, Span::call_site())?;
 
-    // Extract function arguments
+    // Extract function arguments. For `ws` functions, the `ServerStream`
+    // argument is the client's message stream rather than a serialized arg.
     let mut arg_names: Vec<Ident> = Vec::new();
     let mut arg_types: Vec<Type> = Vec::new();
     let mut fn_args: Vec<TokenStream2> = Vec::new();
+    let mut call_args: Vec<Ident> = Vec::new();
+    let mut input_stream: Option<Ident> = None;
+    let mut input_item: Option<Type> = None;
+    let mut upload_body: Option<Ident> = None;
+    let mut file_args: Vec<Ident> = Vec::new();
 
     for arg in &input_fn.sig.inputs {
         match arg {
@@ -210,9 +1098,43 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
                 if let Pat::Ident(pat_ident) = &*pat_type.pat {
                     let name = &pat_ident.ident;
                     let ty = &*pat_type.ty;
+                    fn_args.push(quote! { #name: #ty });
+                    call_args.push(name.clone());
+                    if args.mode == Mode::Ws && is_server_stream(ty) {
+                        if input_stream.is_some() {
+                            return Err(syn::Error::new_spanned(
+                                pat_type,
+                                "`ws` server functions take exactly one `ServerStream` argument \
+                                (the messages sent by the client)",
+                            ));
+                        }
+                        input_stream = Some(name.clone());
+                        input_item = first_type_arg(ty).cloned();
+                        continue;
+                    }
+                    if is_body_stream(ty) {
+                        if args.mode != Mode::Rpc || upload_body.is_some() {
+                            return Err(syn::Error::new_spanned(
+                                pat_type,
+                                "only regular server functions take a streaming body, \
+                                and only one `impl Stream<Item = Bytes>` argument",
+                            ));
+                        }
+                        upload_body = Some(name.clone());
+                        continue;
+                    }
+                    if is_file_upload(ty) {
+                        if args.mode != Mode::Rpc {
+                            return Err(syn::Error::new_spanned(
+                                pat_type,
+                                "only regular server functions take `FileUpload` arguments",
+                            ));
+                        }
+                        file_args.push(name.clone());
+                        continue;
+                    }
                     arg_names.push(name.clone());
                     arg_types.push(ty.clone());
-                    fn_args.push(quote! { #name: #ty });
                 }
             }
             FnArg::Receiver(_) => {
@@ -227,6 +1149,63 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
         }
     }
 
+    for name in &args.redact {
+        if !arg_names.contains(name) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!(
+                    "`redact` names `{}`, which is not an argument of this server function",
+                    name
+                ),
+            ));
+        }
+    }
+
+    // Uploads carry their arguments in the query string or a multipart body
+    // and can't be keyed by them, so options encoding the body or sharing
+    // results don't apply
+    if let (Some(body), Some(file)) = (&upload_body, file_args.first()) {
+        return Err(syn::Error::new_spanned(
+            file,
+            format!(
+                "functions taking a streaming body (`{body}`) cannot take `FileUpload` arguments"
+            ),
+        ));
+    }
+    let upload = match (&upload_body, file_args.first()) {
+        (Some(body), _) => Some((body, "a streaming body")),
+        (None, Some(file)) => Some((file, "files")),
+        (None, None) => None,
+    };
+    if let Some((arg, taking)) = upload {
+        let conflict = [
+            ("get", args.get),
+            (
+                "postcard",
+                args.encoding.as_ref().is_some_and(|e| e == "postcard"),
+            ),
+            ("cbor", args.encoding.as_ref().is_some_and(|e| e == "cbor")),
+            ("coalesce", args.coalesce),
+            ("cache", args.cache_ms.is_some()),
+        ]
+        .into_iter()
+        .find_map(|(option, set)| set.then_some(option));
+        if let Some(option) = conflict {
+            return Err(syn::Error::new_spanned(
+                arg,
+                format!("`{option}` does not apply to functions taking {taking}"),
+            ));
+        }
+    }
+
+    if args.mode == Mode::Ws && input_stream.is_none() {
+        return Err(syn::Error::new_spanned(
+            &input_fn.sig,
+            "`ws` server functions must take a `ServerStream<T>` argument \
+            carrying the messages sent by the client",
+        ));
+    }
+
     // Extract return type (already validated above)
     let return_type = match &input_fn.sig.output {
         ReturnType::Default => {
@@ -243,6 +1222,9 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
     // Generate the args struct name (CamelCase)
     let args_struct_name = format_ident!("{}Args", to_pascal_case(&fn_name_str));
     let handler_name = format_ident!("{}_handler", fn_name);
+    // Module holding the generated items, so they can't collide with
+    // another module's through glob imports
+    let mod_name = format_ident!("__server_fn_{}", fn_name);
 
     // Generate field definitions for the args struct
     let struct_fields: Vec<TokenStream2> = arg_names
@@ -251,16 +1233,794 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
         .map(|(name, ty)| quote! { pub #name: #ty })
         .collect();
 
+    // Idle and message size limits of `ws` handlers
+    let ws_limits = {
+        let idle_timeout = args.idle_timeout_ms.map(|ms| {
+            quote! { .idle_timeout(::std::time::Duration::from_millis(#ms)) }
+        });
+        let max_message_size = args
+            .max_message_size
+            .map(|bytes| quote! { .max_message_size(#bytes) });
+        quote! { ::axum_egui::ws::WsLimits::new() #idle_timeout #max_message_size }
+    };
+
+    // Subprotocol negotiation of `ws` handlers
+    let select_protocol = if args.protocols.is_empty() {
+        quote! {}
+    } else {
+        let protocols = &args.protocols;
+        quote! {
+            let __upgrade = match ::axum_egui::ws::select_protocol(__upgrade, &[#(#protocols),*]) {
+                Ok(upgrade) => upgrade,
+                Err(e) => {
+                    return ::axum_egui::error::error_response(
+                        ::axum::http::StatusCode::BAD_REQUEST,
+                        e,
+                        __request_id,
+                    );
+                }
+            };
+        }
+    };
+
+    // Stream URL carrying the arguments, for clients that extend it
+    let stream_url = if args.post_params {
+        quote! { ::axum_egui::tickets::ticket_url(#api_path, &__args).await? }
+    } else {
+        quote! { ::axum_egui::rpc::encode_query_args(#api_path, &__args)? }
+    };
+
+    // Ticket check of `auth` stream handlers, which adds the user to the request
+    let parts_mut = args.auth.then(|| quote! { mut });
+    let authorize = args.auth.then(|| {
+        quote! {
+            if let Err(e) = ::axum_egui::auth::authorize(&mut __parts) {
+                return e.into_response();
+            }
+        }
+    });
+
+    // `::axum_egui::rpc::Encoding` of functions with a binary wire format
+    let encoding = args.encoding.as_ref().map(|ident| {
+        let variant = format_ident!("{}", to_pascal_case(&ident.to_string()));
+        quote! { ::axum_egui::rpc::Encoding::#variant }
+    });
+
+    // Client path, per mode
+    let file_names: Vec<String> = file_args.iter().map(|name| name.to_string()).collect();
+    let client_call = match args.mode {
+        Mode::Rpc if upload_body.is_some() => quote! {
+            ::axum_egui::upload::call(#api_path, &__args, #upload_body).await
+        },
+        Mode::Rpc if !file_args.is_empty() => quote! {
+            ::axum_egui::upload::call_multipart(
+                #api_path,
+                &__args,
+                [#((#file_names, #file_args)),*],
+            ).await
+        },
+        Mode::Rpc if args.get => quote! {
+            ::axum_egui::rpc::call_get(#api_path, &__args).await
+        },
+        Mode::Rpc => match &encoding {
+            Some(encoding) => quote! {
+                ::axum_egui::rpc::call_encoded(#api_path, &__args, #encoding).await
+            },
+            None => quote! {
+                ::axum_egui::rpc::call(#api_path, &__args).await
+            },
+        },
+        Mode::Sse if args.auth => quote! {
+            {
+                let __url = #stream_url;
+                let __url = ::axum_egui::auth::authorize_url(&__url).await?;
+                ::axum_egui::sse::connect_at(&__url)
+            }
+        },
+        Mode::Sse if args.post_params => quote! {
+            ::axum_egui::sse::connect_with_ticket(#api_path, &__args).await
+        },
+        Mode::Sse => quote! {
+            ::axum_egui::sse::connect(#api_path, &__args).await
+        },
+        Mode::Ws if args.auth || !args.protocols.is_empty() => {
+            let protocols = (!args.protocols.is_empty()).then(|| {
+                let protocols = &args.protocols;
+                quote! { .protocols([#(#protocols),*]) }
+            });
+            let authorize = args.auth.then(|| {
+                quote! { let __url = ::axum_egui::auth::authorize_url(&__url).await?; }
+            });
+            quote! {
+                {
+                    let __url = #stream_url;
+                    #authorize
+                    let __options = ::axum_egui::ws::ConnectOptions::new() #protocols;
+                    ::axum_egui::ws::connect_stream_at(&__url, #input_stream, &__options).await
+                }
+            }
+        }
+        Mode::Ws if args.post_params => quote! {
+            ::axum_egui::ws::connect_stream_with_ticket(#api_path, &__args, #input_stream).await
+        },
+        Mode::Ws => quote! {
+            ::axum_egui::ws::connect_stream(#api_path, &__args, #input_stream).await
+        },
+    };
+
+    // Schema-versioned envelopes of `versioned` streams
+    let versioned_item = if args.versioned {
+        let item = first_type_arg(&return_type).and_then(first_type_arg);
+        Some(item.cloned().ok_or_else(|| {
+            syn::Error::new_spanned(
+                &return_type,
+                "`versioned` needs the return type spelled out as \
+                `Result<ServerStream<T>, ServerFnError>`",
+            )
+        })?)
+    } else {
+        None
+    };
+    // Stream adapters, in the order the server applies them: `conflate`
+    // keeps only the newest pending item, `delta` sends diffs, `versioned`
+    // wraps items in envelopes. The client undoes them in reverse.
+    let mut server_adapters = Vec::new();
+    let mut client_adapters = Vec::new();
+    let latest_only = quote! { ::axum_egui::ServerStream::latest_only };
+    if args.conflate {
+        server_adapters.push(latest_only.clone());
+    }
+    if args.delta {
+        server_adapters.push(quote! { ::axum_egui::delta::encode });
+        client_adapters.push(quote! { ::axum_egui::delta::decode });
+    }
+    if let Some(item) = &versioned_item {
+        server_adapters.push(quote! { ::axum_egui::schema::envelope });
+        client_adapters.push(quote! { ::axum_egui::schema::open::<#item> });
+    }
+    client_adapters.reverse();
+    if args.conflate {
+        client_adapters.push(latest_only);
+    }
+    let client_call = if client_adapters.is_empty() {
+        client_call
+    } else {
+        quote! {
+            {
+                let __stream = #client_call;
+                #(let __stream = ::std::result::Result::map(__stream, #client_adapters);)*
+                __stream
+            }
+        }
+    };
+    let (sse_stream, ws_output) = if server_adapters.is_empty() {
+        (quote! { stream }, quote! { #fn_name(#(#call_args),*) })
+    } else {
+        let sse_stream = quote! {
+            {
+                #(let stream = #server_adapters(stream);)*
+                stream
+            }
+        };
+        let ws_output = quote! {
+            async move {
+                let stream = #fn_name(#(#call_args),*).await;
+                #(let stream = ::std::result::Result::map(stream, #server_adapters);)*
+                stream
+            }
+        };
+        (sse_stream, ws_output)
+    };
+
+    // Typed route items, so other code never hardcodes the path
+    let (route_kind, route_method) = match args.mode {
+        Mode::Rpc if args.get => (quote! { Get }, "GET"),
+        Mode::Rpc => (quote! { Rpc }, "POST"),
+        Mode::Sse => (quote! { Sse }, "GET"),
+        Mode::Ws => (quote! { Ws }, "GET"),
+    };
+    let url_body = match args.mode {
+        Mode::Rpc if args.get => quote! { ::axum_egui::rpc::encode_get_args(Self::PATH, self) },
+        Mode::Rpc if upload_body.is_some() => {
+            quote! { ::axum_egui::rpc::encode_query_args(Self::PATH, self) }
+        }
+        Mode::Rpc => quote! { Ok(Self::PATH.to_string()) },
+        Mode::Sse | Mode::Ws => quote! { ::axum_egui::rpc::encode_query_args(Self::PATH, self) },
+    };
+    let path_doc = format!("API path of [`{}`].", fn_name_str);
+    let args_doc = format!("Arguments of [`{}`], as sent to the server.", fn_name_str);
+    let handler_doc = format!(
+        "axum handler serving [`{}`]; route it with `{}` at `{}`.",
+        fn_name_str,
+        route_method.to_lowercase(),
+        api_path
+    );
+    // Separates the user's docs from the generated section
+    let has_docs = attrs.iter().any(|attr| attr.path().is_ident("doc"));
+    let doc_separator = has_docs.then(|| quote! { #[doc = ""] });
+    let endpoint_doc = endpoint_doc(&args, &api_path, upload_body.is_some(), &file_names);
+    let post_params = args.post_params;
+    let auth = args.auth;
+    let header_names: Vec<&LitStr> = args.headers.iter().map(|(name, _)| name).collect();
+    let header_values: Vec<&Expr> = args.headers.iter().map(|(_, value)| value).collect();
+
+    // Request and response content types listed in the registry
+    let (accepts, produces): (Vec<&str>, Vec<&str>) = match args.mode {
+        Mode::Rpc if upload_body.is_some() => {
+            (vec!["application/octet-stream"], vec!["application/json"])
+        }
+        Mode::Rpc if !file_args.is_empty() => {
+            (vec!["multipart/form-data"], vec!["application/json"])
+        }
+        Mode::Rpc if args.get => (vec![], vec!["application/json"]),
+        Mode::Rpc => match &args.encoding {
+            Some(encoding) => {
+                let encoding = if encoding == "cbor" {
+                    "application/cbor"
+                } else {
+                    "application/x-postcard"
+                };
+                (
+                    vec![encoding, "application/json"],
+                    vec![encoding, "application/json"],
+                )
+            }
+            None => (vec!["application/json"], vec!["application/json"]),
+        },
+        Mode::Sse | Mode::Ws => {
+            let accepts = if args.post_params {
+                vec!["application/json"]
+            } else {
+                vec![]
+            };
+            let produces = if args.mode == Mode::Sse {
+                vec!["text/event-stream"]
+            } else {
+                vec![]
+            };
+            (accepts, produces)
+        }
+    };
+
+    // TypeScript endpoint entry (`axum-egui/ts`). The response is the `Ok` type,
+    // or the item type for streams; skipped if the return type is an alias.
+    let response_type = first_type_arg(&return_type).map(|ok| match args.mode {
+        Mode::Rpc => ok,
+        Mode::Sse | Mode::Ws => first_type_arg(ok).unwrap_or(ok),
+    });
+    let ts_input = match &input_item {
+        Some(item) => quote! { Some(::axum_egui::ts::TsType::of::<#item>()) },
+        None => quote! { None },
+    };
+    let ts_endpoint = response_type.map(|response| {
+        quote! {
+            #[cfg(feature = "ssr")]
+            ::axum_egui::__ts_endpoint! {
+                name: #fn_name_str,
+                path: #api_path,
+                kind: ::axum_egui::registry::RouteKind::#route_kind,
+                args: ::axum_egui::ts::TsType::of::<#mod_name::#args_struct_name>(),
+                response: ::axum_egui::ts::TsType::of::<#response>(),
+                input: #ts_input,
+            }
+        }
+    });
+
+    // JSON arguments from the request body, rejected with 415/400 and the
+    // function name on a wrong content type or body
+    let decode_args = |headers: TokenStream2| {
+        quote! {
+            let __args: #args_struct_name =
+                match ::axum_egui::rpc::server::decode_json_args(#fn_name_str, #headers, &__body) {
+                    Ok(args) => args,
+                    Err(rejection) => {
+                        return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+                    }
+                };
+        }
+    };
+    // `get` functions take their arguments from the query string instead,
+    // binary ones accept their encoding besides JSON
+    let (rpc_body, decode_rpc_args) = if upload_body.is_some() {
+        (
+            quote! { __body: ::axum::body::Body, },
+            quote! {
+                let __args: #args_struct_name =
+                    match ::axum_egui::upload::decode_args(#fn_name_str, __parts.uri.query()) {
+                        Ok(args) => args,
+                        Err(rejection) => {
+                            return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+                        }
+                    };
+            },
+        )
+    } else if !file_args.is_empty() {
+        (
+            quote! { __body: ::axum::body::Bytes, },
+            quote! {
+                let (__args, __files): (#args_struct_name, _) =
+                    match ::axum_egui::upload::decode_multipart(
+                        #fn_name_str,
+                        &__parts.headers,
+                        &__body,
+                        [#(#file_names),*],
+                    ) {
+                        Ok(decoded) => decoded,
+                        Err(rejection) => {
+                            return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+                        }
+                    };
+            },
+        )
+    } else if args.get {
+        (
+            quote! {},
+            quote! {
+                let __args: #args_struct_name =
+                    match ::axum_egui::rpc::server::decode_get_args(#fn_name_str, __parts.uri.query()) {
+                        Ok(args) => args,
+                        Err(rejection) => {
+                            return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+                        }
+                    };
+            },
+        )
+    } else if let Some(encoding) = &encoding {
+        (
+            quote! { __body: ::axum::body::Bytes, },
+            quote! {
+                let __args: #args_struct_name =
+                    match ::axum_egui::rpc::server::decode_args(#fn_name_str, #encoding, &__parts.headers, &__body) {
+                        Ok(args) => args,
+                        Err(rejection) => {
+                            return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+                        }
+                    };
+            },
+        )
+    } else {
+        (
+            quote! { __body: ::axum::body::Bytes, },
+            decode_args(quote! { &__parts.headers }),
+        )
+    };
+    // Results are JSON, or in the encoding of binary functions if the
+    // client accepts it
+    let (negotiate_encoding, ok_response) = match &encoding {
+        Some(encoding) => (
+            quote! {
+                let __encoding = ::axum_egui::rpc::server::response_encoding(&__parts.headers, #encoding);
+            },
+            quote! { ::axum_egui::rpc::server::encoded_response(__encoding, &result, __request_id) },
+        ),
+        None => (
+            quote! {},
+            quote! {
+                (
+                    ::axum::http::StatusCode::OK,
+                    ::axum::extract::Json(result),
+                ).into_response()
+            },
+        ),
+    };
+
+    // Server-only handler minting parameter tickets for `post_params`
+    let params_handler_name = args
+        .post_params
+        .then(|| format_ident!("{}_params_handler", fn_name));
+    let params_handler = params_handler_name.as_ref().map(|params_handler_name| {
+        let decode_args = decode_args(quote! { &__headers });
+        quote! {
+            #[cfg(feature = "ssr")]
+            pub async fn #params_handler_name(
+                __headers: ::axum::http::HeaderMap,
+                __body: ::axum::body::Bytes,
+            ) -> ::axum::response::Response {
+                let __request_id = ::axum_egui::error::request_id(&__headers);
+                #decode_args
+                ::axum_egui::tickets::issue_response(&__args)
+            }
+        }
+    });
+
+    // Arguments for `axum_egui::logging`, masked as listed in `redact(...)`
+    let redacted: Vec<String> = args.redact.iter().map(|name| name.to_string()).collect();
+    let capture_args = |extensions: TokenStream2| {
+        quote! {
+            let __logged_args =
+                ::axum_egui::logging::capture_args(#extensions, &__args, &[#(#redacted),*]);
+        }
+    };
+    let capture_rpc_args = capture_args(quote! { &__parts.extensions });
+    let capture_stream_args = capture_args(quote! { __ctx.extensions() });
+
+    // Audit trail of `audit` functions; calls fail if it is not installed
+    let (begin_audit, finish_audit) = if args.audit {
+        (
+            quote! {
+                let __audit = match ::axum_egui::audit::begin(&__parts, #fn_name_str, &__args) {
+                    Ok(audit) => audit,
+                    Err(e) => return e.into_response(),
+                };
+            },
+            quote! { __audit.finish(__result.is_ok()).await; },
+        )
+    } else {
+        (quote! {}, quote! {})
+    };
+
+    // `coalesce` functions share one run among identical calls in flight,
+    // `cache` ones answer from the cache first
+    let flight_key = (args.coalesce || args.cache_ms.is_some()).then(|| {
+        quote! { let __flight = ::axum_egui::coalesce::key(#fn_name_str, &__args); }
+    });
+    let mut rpc_call = quote! { ::axum_egui::context::scope(__ctx, #fn_name(#(#call_args),*)) };
+    if args.coalesce {
+        rpc_call = quote! { ::axum_egui::coalesce::run(__flight, move || #rpc_call) };
+    }
+    if let Some(ttl_ms) = args.cache_ms {
+        let stale_ms = args.stale_ms.unwrap_or(0);
+        // The inner `coalesce` takes the key, so keep a copy for the cache
+        let key = if args.coalesce {
+            quote! { __flight.clone() }
+        } else {
+            quote! { __flight }
+        };
+        rpc_call = quote! {
+            ::axum_egui::cache::run(
+                #key,
+                ::axum_egui::cache::CachePolicy::new(::std::time::Duration::from_millis(#ttl_ms))
+                    .stale(::std::time::Duration::from_millis(#stale_ms)),
+                move || #rpc_call,
+            )
+        };
+    }
+    if let Some(ms) = args.server_timeout_ms {
+        rpc_call = quote! {
+            ::axum_egui::rpc::server::deadline(::std::time::Duration::from_millis(#ms), #rpc_call)
+        };
+    }
+
+    // The body of uploads, streamed to the function; the call fails if it
+    // breaks off. Files of multipart calls bind to their arguments.
+    let (split_upload, check_upload) = match &upload_body {
+        Some(body) => (
+            quote! { let (#body, __upload) = ::axum_egui::upload::body(#fn_name_str, __body); },
+            quote! { let __result = __upload.finish(__result); },
+        ),
+        None if !file_args.is_empty() => (quote! { let [#(#file_args),*] = __files; }, quote! {}),
+        None => (quote! {}, quote! {}),
+    };
+
+    // `headers(...)` functions reject calls without their headers
+    let check_headers = (!args.headers.is_empty()).then(|| {
+        quote! {
+            if let Err(rejection) = ::axum_egui::rpc::server::check_headers(
+                #fn_name_str,
+                &__parts.headers,
+                &[#((#header_names, #header_values)),*],
+            ) {
+                return ::axum_egui::error::error_response(rejection.status(), rejection, __request_id);
+            }
+        }
+    });
+
+    // Calls of `quota` functions count against the caller's allowance
+    let charge_quota = args.quota.as_ref().map(|(name, limit, period)| {
+        quote! {
+            if let Err(e) = ::axum_egui::quota::charge(
+                &__parts,
+                #fn_name_str,
+                &::axum_egui::quota::Quota::new(#name, #limit, ::axum_egui::quota::QuotaPeriod::#period),
+            ).await {
+                return e.into_response();
+            }
+        }
+    });
+
+    // Server-only axum handler, per mode
+    let handler = match args.mode {
+        Mode::Rpc => quote! {
+            pub async fn #handler_name(
+                __parts: ::axum::http::request::Parts,
+                #rpc_body
+            ) -> impl ::axum::response::IntoResponse {
+                use ::axum::response::IntoResponse;
+
+                let __request_id = ::axum_egui::error::request_id(&__parts.headers);
+                #check_headers
+                #decode_rpc_args
+                #negotiate_encoding
+                #capture_rpc_args
+                #flight_key
+                #charge_quota
+                #begin_audit
+
+                // Request context for the body; hooks run when the handler ends
+                let __trace = ::axum_egui::trace::TraceContext::from_headers(&__parts.headers);
+                let __ctx = ::axum_egui::context::RequestContext::from_parts(__parts);
+                let __guard = __ctx.disconnect_guard();
+
+                // Destructure args
+                let #args_struct_name { #(#arg_names),* } = __args;
+                #split_upload
+
+                // Call the actual function and return JSON response
+                let __result = ::axum_egui::trace::instrument(
+                    #fn_name_str,
+                    __trace,
+                    #rpc_call,
+                ).await;
+                #check_upload
+                #finish_audit
+                let __response = match __result {
+                    Ok(result) => #ok_response,
+                    Err(e) => ::axum_egui::error::error_response(
+                        ::axum_egui::rpc::server::error_status(&e),
+                        e,
+                        __request_id,
+                    ),
+                };
+                drop(__guard);
+                ::axum_egui::logging::attach_args(__response, __logged_args)
+            }
+        },
+        Mode::Sse => quote! {
+            pub async fn #handler_name(
+                #parts_mut __parts: ::axum::http::request::Parts,
+            ) -> ::axum::response::Response {
+                use ::axum::response::IntoResponse;
+
+                let __request_id = ::axum_egui::error::request_id(&__parts.headers);
+                if let Err(e) = ::axum_egui::origin::check(&__parts) {
+                    return e.into_response();
+                }
+                #authorize
+
+                let __ctx = ::axum_egui::context::RequestContext::from_parts(__parts);
+                let __args: #args_struct_name =
+                    match ::axum_egui::tickets::decode_args(__ctx.uri().query()) {
+                        Ok(args) => args,
+                        Err(e) => {
+                            return ::axum_egui::error::error_response(
+                                ::axum::http::StatusCode::BAD_REQUEST,
+                                e,
+                                __request_id,
+                            );
+                        }
+                    };
+                #capture_stream_args
+                let #args_struct_name { #(#arg_names),* } = __args;
+
+                // Hooks run when the event stream is dropped (client disconnect)
+                let __guard = __ctx.disconnect_guard();
+                let __response = match ::axum_egui::context::scope(__ctx, #fn_name(#(#call_args),*)).await {
+                    Ok(stream) => ::axum_egui::sse::into_sse_response(__guard.bind(#sse_stream)),
+                    Err(e) => ::axum_egui::error::error_response(
+                        ::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        e,
+                        __request_id,
+                    ),
+                };
+                ::axum_egui::logging::attach_args(__response, __logged_args)
+            }
+        },
+        Mode::Ws => quote! {
+            pub async fn #handler_name(
+                #parts_mut __parts: ::axum::http::request::Parts,
+                __upgrade: ::axum::extract::ws::WebSocketUpgrade,
+            ) -> ::axum::response::Response {
+                use ::axum::response::IntoResponse;
+
+                let __request_id = ::axum_egui::error::request_id(&__parts.headers);
+                if let Err(e) = ::axum_egui::origin::check(&__parts) {
+                    return e.into_response();
+                }
+                #authorize
+
+                let __ctx = ::axum_egui::context::RequestContext::from_parts(__parts);
+                let __args: #args_struct_name =
+                    match ::axum_egui::tickets::decode_args(__ctx.uri().query()) {
+                        Ok(args) => args,
+                        Err(e) => {
+                            return ::axum_egui::error::error_response(
+                                ::axum::http::StatusCode::BAD_REQUEST,
+                                e,
+                                __request_id,
+                            );
+                        }
+                    };
+                #capture_stream_args
+                let #args_struct_name { #(#arg_names),* } = __args;
+                #select_protocol
+
+                let __response = ::axum_egui::ws::serve_stream_with(__upgrade, __ctx, #ws_limits, move |#input_stream| {
+                    #ws_output
+                });
+                ::axum_egui::logging::attach_args(__response, __logged_args)
+            }
+        },
+    };
+
+    let params_handler_export = params_handler_name.map(|params_handler_name| {
+        quote! {
+            #[cfg(feature = "ssr")]
+            #[allow(unused_imports)]
+            #vis use #mod_name::#params_handler_name;
+        }
+    });
+
+    // Client path: make the HTTP request, or call the mock with a `mock`
+    // feature
+    let client_request = quote! {
+        let __args = #mod_name::#args_struct_name { #(#arg_names: #arg_names.clone()),* };
+        #client_call
+    };
+    // Native path (`native` feature): the same call from a desktop app or
+    // CLI tool, through `axum_egui::native`
+    let native_request = match args.mode {
+        Mode::Rpc if upload_body.is_some() => quote! {
+            let __args = #mod_name::#args_struct_name { #(#arg_names: #arg_names.clone()),* };
+            ::axum_egui::native::call_upload(#api_path, &__args, #upload_body).await
+        },
+        Mode::Rpc if !file_args.is_empty() => quote! {
+            let __args = #mod_name::#args_struct_name { #(#arg_names: #arg_names.clone()),* };
+            ::axum_egui::native::call_multipart(
+                #api_path,
+                &__args,
+                [#((#file_names, #file_args)),*],
+            ).await
+        },
+        Mode::Rpc if args.get => quote! {
+            let __args = #mod_name::#args_struct_name { #(#arg_names: #arg_names.clone()),* };
+            ::axum_egui::native::call_get(#api_path, &__args).await
+        },
+        Mode::Rpc => {
+            let call = match &encoding {
+                Some(encoding) => {
+                    quote! { ::axum_egui::native::call_encoded(#api_path, &__args, #encoding) }
+                }
+                None => quote! { ::axum_egui::native::call(#api_path, &__args) },
+            };
+            quote! {
+                let __args = #mod_name::#args_struct_name { #(#arg_names: #arg_names.clone()),* };
+                #call.await
+            }
+        }
+        Mode::Sse | Mode::Ws => quote! {
+            let _ = (#(&#call_args),*);
+            ::std::result::Result::Err(::axum_egui::native::unsupported_stream(#api_path))
+        },
+    };
+    // Static headers of `headers(...)` functions, sent by both clients
+    let (client_request, native_request) = if args.headers.is_empty() {
+        (client_request, native_request)
+    } else {
+        let with_headers = |request: TokenStream2| {
+            quote! {
+                const __HEADERS: ::axum_egui::rpc::CallHeaders = &[#((#header_names, #header_values)),*];
+                ::axum_egui::rpc::with_headers(__HEADERS, async move { #request }).await
+            }
+        };
+        (with_headers(client_request), with_headers(native_request))
+    };
+    // Deadline of `timeout = "..."` functions, for both clients
+    let (client_request, native_request) = match args.timeout_ms {
+        Some(ms) => {
+            let with_timeout = |request: TokenStream2| {
+                quote! {
+                    ::axum_egui::rpc::with_timeout(
+                        ::std::time::Duration::from_millis(#ms),
+                        async move { #request },
+                    )
+                    .await
+                }
+            };
+            (with_timeout(client_request), with_timeout(native_request))
+        }
+        None => (client_request, native_request),
+    };
+    let client_path = match &args.mock {
+        Some(mock) => quote! {
+            #[cfg(all(any(feature = "hydrate", feature = "native"), feature = "mock"))]
+            {
+                #mock(#(#call_args),*).await
+            }
+
+            #[cfg(all(feature = "hydrate", not(feature = "mock")))]
+            {
+                #client_request
+            }
+
+            #[cfg(all(feature = "native", not(feature = "mock")))]
+            {
+                #native_request
+            }
+        },
+        None => quote! {
+            #[cfg(feature = "hydrate")]
+            {
+                #client_request
+            }
+
+            #[cfg(feature = "native")]
+            {
+                #native_request
+            }
+        },
+    };
+
     // Generate the output with BOTH code paths wrapped in #[cfg]
     let output = quote! {
-        // Args struct - always generated, used by both client and server
-        #[derive(::serde::Serialize, ::serde::Deserialize)]
-        #vis struct #args_struct_name {
-            #(#struct_fields),*
+        // Generated items, re-exported next to the function
+        #[doc(hidden)]
+        #vis mod #mod_name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            // Args struct - always generated, used by both client and server
+            ::axum_egui::__ts_derive! {
+                #[doc = #args_doc]
+                #[derive(::serde::Serialize, ::serde::Deserialize)]
+                pub struct #args_struct_name {
+                    #(#struct_fields),*
+                }
+            }
+
+            #[allow(dead_code)]
+            impl #args_struct_name {
+                #[doc = #path_doc]
+                pub const PATH: &'static str = #api_path;
+
+                /// HTTP method the handler is routed with.
+                pub const METHOD: &'static str = #route_method;
+
+                /// URL of the endpoint for these arguments. Streaming and `get`
+                /// endpoints carry them in the query string; other RPC endpoints
+                /// take them in the POST body, so this is just the path.
+                pub fn url(&self) -> ::std::result::Result<::std::string::String, ::axum_egui::rpc::ServerFnError> {
+                    #url_body
+                }
+            }
+
+            // Server-only: generate the axum handler
+            #[cfg(feature = "ssr")]
+            #[doc = #handler_doc]
+            #handler
+
+            // Server-only: ticket handler for `post_params`
+            #params_handler
         }
 
+        #[allow(unused_imports)]
+        #vis use #mod_name::#args_struct_name;
+        #[cfg(feature = "ssr")]
+        #[allow(unused_imports)]
+        #vis use #mod_name::#handler_name;
+        #params_handler_export
+
+        // Server-only: list the route in `axum_egui::registry`
+        #[cfg(feature = "ssr")]
+        ::axum_egui::registry::__submit! {
+            ::axum_egui::registry::ServerFnRoute {
+                name: #fn_name_str,
+                module: ::core::module_path!(),
+                path: #api_path,
+                kind: ::axum_egui::registry::RouteKind::#route_kind,
+                post_params: #post_params,
+                auth: #auth,
+                accepts: &[#(#accepts),*],
+                produces: &[#(#produces),*],
+                headers: &[#(#header_names),*],
+            }
+        }
+
+        #ts_endpoint
+
         // The main function - has feature-gated body
         #(#attrs)*
+        #doc_separator
+        #[doc = #endpoint_doc]
         #vis #asyncness fn #fn_name #generics (#(#fn_args),*) -> #return_type
         #where_clause
         {
@@ -270,47 +2030,460 @@ fn server_impl(args: ServerFnArgs, input_fn: ItemFn) -> syn::Result<TokenStream2
                 #block
             }
 
-            // Client path: make HTTP request
-            #[cfg(feature = "hydrate")]
-            {
-                let __args = #args_struct_name { #(#arg_names: #arg_names.clone()),* };
-                ::axum_egui::rpc::call(#api_path, &__args).await
-            }
+            #client_path
 
-            // Fallback for when neither feature is enabled
-            #[cfg(not(any(feature = "ssr", feature = "hydrate")))]
+            // Fallback for when no feature is enabled
+            #[cfg(not(any(feature = "ssr", feature = "hydrate", feature = "native")))]
             {
                 // Silence unused variable warnings
-                let _ = (#(&#arg_names),*);
-                unreachable!("Either 'ssr' or 'hydrate' feature must be enabled")
+                let _ = (#(&#call_args),*);
+                unreachable!("One of the 'ssr', 'hydrate' or 'native' features must be enabled")
+            }
+        }
+    };
+
+    Ok(output)
+}
+
+/// Derive `axum_egui::sync::SyncedState` for a struct.
+///
+/// The state is served at `/api/sync/{snake_case_name}` unless overridden
+/// with `#[synced(path = "/custom/path")]`.
+///
+/// ```ignore
+/// #[derive(Clone, Default, Serialize, Deserialize, SyncedState)]
+/// pub struct Board {
+///     pub cards: Vec<Card>,
+/// }
+///
+/// // Server: Router::new().merge(Board::router()); Board::modify(|b| ...);
+/// // Client: let board = Board::connect(repaint)?; board.mutate(|b| ...);
+/// ```
+#[proc_macro_derive(SyncedState, attributes(synced))]
+pub fn derive_synced_state(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    match synced_state_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn synced_state_impl(input: syn::DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`SyncedState` cannot be derived for generic types: each synced type \
+            has a single server-wide value and a fixed path",
+        ));
+    }
+
+    let mut path: Option<LitStr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("synced")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("path") {
+                path = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown `synced` option. Expected `path = \"...\"`"))
             }
+        })?;
+    }
+    let (api_path, span) = match &path {
+        Some(path) => (path.value(), path.span()),
+        None => (
+            format!("/api/sync/{}", to_snake_case(&ident.to_string())),
+            Span::call_site(),
+        ),
+    };
+    validate_api_path(&api_path, span)?;
+    let name = ident.to_string();
+
+    Ok(quote! {
+        impl ::axum_egui::sync::SyncedState for #ident {
+            const PATH: &'static str = #api_path;
         }
 
-        // Server-only: generate the axum handler
+        // Server-only: list the patch stream in `axum_egui::registry`
         #[cfg(feature = "ssr")]
-        #vis async fn #handler_name(
-            ::axum::extract::Json(__args): ::axum::extract::Json<#args_struct_name>,
-        ) -> impl ::axum::response::IntoResponse {
-            use ::axum::response::IntoResponse;
+        ::axum_egui::registry::__submit! {
+            ::axum_egui::registry::ServerFnRoute {
+                name: #name,
+                module: ::core::module_path!(),
+                path: #api_path,
+                kind: ::axum_egui::registry::RouteKind::Sse,
+                post_params: false,
+                auth: false,
+                accepts: &[],
+                produces: &["text/event-stream"],
+                headers: &[],
+            }
+        }
+    })
+}
 
-            // Destructure args
-            let #args_struct_name { #(#arg_names),* } = __args;
+/// Derive `axum_egui::schema::StreamSchema` for a message type.
+///
+/// The version is a fingerprint of the definition: its name, fields,
+/// variants, types and attributes, except doc comments. It changes whenever
+/// the definition does, so clients can detect a server sending another
+/// version (see `#[server(versioned)]`).
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize, StreamSchema)]
+/// pub enum Notice {
+///     ExportReady { url: String },
+/// }
+/// ```
+#[proc_macro_derive(StreamSchema)]
+pub fn derive_stream_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
 
-            // Call the actual function and return JSON response
-            match #fn_name(#(#arg_names),*).await {
-                Ok(result) => (
-                    ::axum::http::StatusCode::OK,
-                    ::axum::extract::Json(result),
-                ).into_response(),
-                Err(e) => (
-                    ::axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    ::axum::extract::Json(::serde_json::json!({ "error": e.to_string() })),
-                ).into_response(),
+    match stream_schema_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn stream_schema_impl(mut input: syn::DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "`StreamSchema` cannot be derived for generic types: the version \
+            would not account for the type parameters",
+        ));
+    }
+
+    // Doc comments don't change the wire format
+    let not_doc = |attr: &syn::Attribute| !attr.path().is_ident("doc");
+    input.attrs.retain(not_doc);
+    let fields: Vec<&mut syn::Field> = match &mut input.data {
+        syn::Data::Struct(data) => data.fields.iter_mut().collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter_mut()
+            .flat_map(|variant| {
+                variant.attrs.retain(not_doc);
+                variant.fields.iter_mut()
+            })
+            .collect(),
+        syn::Data::Union(data) => data.fields.named.iter_mut().collect(),
+    };
+    for field in fields {
+        field.attrs.retain(not_doc);
+    }
+
+    let ident = &input.ident;
+    let definition = quote! { #input }.to_string();
+    let version = fingerprint(&definition);
+    Ok(quote! {
+        impl ::axum_egui::schema::StreamSchema for #ident {
+            const SCHEMA_VERSION: u64 = #version;
+        }
+    })
+}
+
+/// Derive `axum_egui::error::ErrorCode` for an error type.
+///
+/// Each variant of an enum gets its name in snake_case as its code, a struct
+/// the name of the struct; `#[error_code = "..."]` on a variant (or the
+/// struct) sets another one. The type must implement `Display`.
+///
+/// ```ignore
+/// #[derive(Debug, thiserror::Error, ErrorCode)]
+/// pub enum InvoiceError {
+///     #[error("no invoice {0}")]
+///     NotFound(u64),   // "not_found"
+///     #[error("invoice is locked")]
+///     #[error_code = "locked"]
+///     AlreadyPaid,
+/// }
+/// ```
+#[proc_macro_derive(ErrorCode, attributes(error_code))]
+pub fn derive_error_code(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as syn::DeriveInput);
+
+    match error_code_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn error_code_impl(input: syn::DeriveInput) -> syn::Result<TokenStream2> {
+    // The `#[error_code = "..."]` of an item, or its name in snake_case
+    let code = |attrs: &[syn::Attribute], ident: &syn::Ident| -> syn::Result<LitStr> {
+        let Some(attr) = attrs.iter().find(|a| a.path().is_ident("error_code")) else {
+            return Ok(LitStr::new(
+                &to_snake_case(&ident.to_string()),
+                ident.span(),
+            ));
+        };
+        match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(code),
+                        ..
+                    }),
+                ..
+            }) => Ok(code.clone()),
+            _ => Err(syn::Error::new_spanned(
+                attr,
+                "expected `#[error_code = \"...\"]`",
+            )),
+        }
+    };
+
+    let ident = &input.ident;
+    let body = match &input.data {
+        syn::Data::Enum(data) => {
+            let arms = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = &variant.ident;
+                    let code = code(&variant.attrs, name)?;
+                    Ok(quote! { Self::#name { .. } => #code, })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+            quote! {
+                match self {
+                    #(#arms)*
+                }
             }
         }
+        syn::Data::Struct(_) => {
+            let code = code(&input.attrs, ident)?;
+            quote! { #code }
+        }
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                ident,
+                "`ErrorCode` can only be derived for enums and structs",
+            ));
+        }
     };
 
-    Ok(output)
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::axum_egui::error::ErrorCode for #ident #ty_generics #where_clause {
+            fn code(&self) -> &'static str {
+                #body
+            }
+        }
+    })
+}
+
+/// Make a function returning `axum_egui::bootstrap::Bootstrap` the entry
+/// point of a frontend.
+///
+/// The function is called when the WASM module loads, and the app it
+/// configures is started (or the apps, if it returns a tuple of them):
+///
+/// ```ignore
+/// #[axum_egui::main]
+/// fn main() -> Bootstrap<MyApp> {
+///     Bootstrap::new().canvas_id("app")
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(input as ItemFn);
+
+    match main_impl(args.into(), input_fn) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn main_impl(args: TokenStream2, input_fn: ItemFn) -> syn::Result<TokenStream2> {
+    if !args.is_empty() {
+        return Err(syn::Error::new_spanned(
+            args,
+            "`#[axum_egui::main]` takes no options: configure the app on the \
+            returned `Bootstrap`",
+        ));
+    }
+    let sig = &input_fn.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "`#[axum_egui::main]` functions cannot be async: the app starts \
+            asynchronously once they return",
+        ));
+    }
+    if !sig.inputs.is_empty() || !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            "`#[axum_egui::main]` functions take no parameters",
+        ));
+    }
+    if matches!(sig.output, ReturnType::Default) {
+        return Err(syn::Error::new_spanned(
+            sig,
+            "`#[axum_egui::main]` functions must return an \
+            `axum_egui::bootstrap::Bootstrap`, or a tuple of them",
+        ));
+    }
+
+    let fn_name = &sig.ident;
+    Ok(quote! {
+        #input_fn
+
+        #[doc(hidden)]
+        #[::axum_egui::bootstrap::wasm_bindgen::prelude::wasm_bindgen(
+            start,
+            wasm_bindgen = ::axum_egui::bootstrap::wasm_bindgen
+        )]
+        pub fn __axum_egui_start() {
+            ::axum_egui::bootstrap::Launch::launch(#fn_name());
+        }
+    })
+}
+
+/// 64-bit FNV-1a hash, as `axum_egui::schema::fingerprint`.
+fn fingerprint(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Convert PascalCase to snake_case
+fn to_snake_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 4);
+    for (i, c) in s.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// The "Endpoint" section added to the docs of a server function, describing
+/// its route and wire format for both the server and the client side.
+fn endpoint_doc(args: &ServerFnArgs, api_path: &str, upload: bool, files: &[String]) -> String {
+    let mut doc = String::from("# Endpoint\n\n");
+    match args.mode {
+        Mode::Rpc if !files.is_empty() => {
+            let files: Vec<String> = files.iter().map(|name| format!("`{name}`")).collect();
+            doc.push_str(&format!(
+                "`POST {api_path}`, encoding: `multipart/form-data`, with a part per \
+                file ({}) and the other arguments as JSON in the `args` part. The \
+                result is returned as JSON.",
+                files.join(", ")
+            ))
+        }
+        Mode::Rpc if upload => doc.push_str(&format!(
+            "`POST {api_path}`, encoding: streamed `application/octet-stream` body. \
+            The other arguments are sent as JSON in the query string, the result \
+            is returned as JSON."
+        )),
+        Mode::Rpc if args.get => doc.push_str(&format!(
+            "`GET {api_path}`, encoding: URL-encoded query string. The arguments \
+            are sent as query parameters, the result is returned as JSON."
+        )),
+        Mode::Rpc => match &args.encoding {
+            Some(encoding) => {
+                let encoding = if encoding == "cbor" {
+                    "CBOR"
+                } else {
+                    "postcard"
+                };
+                doc.push_str(&format!(
+                    "`POST {api_path}`, encoding: {encoding}. The arguments are sent in \
+                the body, the result is returned in the same encoding; JSON bodies \
+                are accepted too, and answered in JSON unless the client accepts \
+                {encoding}."
+                ))
+            }
+            None => doc.push_str(&format!(
+                "`POST {api_path}`, encoding: JSON. The arguments are sent as a JSON \
+                object in the body, the result is returned as JSON."
+            )),
+        },
+        Mode::Sse | Mode::Ws => {
+            let transport = if args.mode == Mode::Sse {
+                "Server-Sent Events, with each item as JSON `data`"
+            } else {
+                "WebSocket, with JSON messages in both directions"
+            };
+            let args_location = if args.post_params {
+                "POSTed to the same path as JSON in exchange for a ticket"
+            } else {
+                "sent in the query string"
+            };
+            doc.push_str(&format!(
+                "`GET {api_path}`, encoding: {transport}. The arguments are {args_location}."
+            ));
+        }
+    }
+    if args.auth {
+        doc.push_str(" Requires a stream ticket of the signed-in user.");
+    }
+    if !args.protocols.is_empty() {
+        let protocols: Vec<String> = args
+            .protocols
+            .iter()
+            .map(|protocol| format!("`{}`", protocol.value()))
+            .collect();
+        doc.push_str(&format!(" Subprotocols: {}.", protocols.join(", ")));
+    }
+    if args.versioned {
+        doc.push_str(" Messages carry the schema version of their type.");
+    }
+    if let Some(ms) = args.idle_timeout_ms {
+        doc.push_str(&format!(" Closed after {ms} ms without messages."));
+    }
+    if let Some(bytes) = args.max_message_size {
+        doc.push_str(&format!(" Client messages are limited to {bytes} bytes."));
+    }
+    if args.coalesce {
+        doc.push_str(" Identical concurrent calls share one run.");
+    }
+    if let Some(ms) = args.cache_ms {
+        doc.push_str(&format!(" Results are cached for {ms} ms"));
+        match args.stale_ms {
+            Some(stale) => doc.push_str(&format!(
+                ", then served stale for up to {stale} ms while refreshed."
+            )),
+            None => doc.push('.'),
+        }
+    }
+    if let Some((name, limit, period)) = &args.quota {
+        let period = period.to_string().to_lowercase();
+        doc.push_str(&format!(
+            " Limited to {limit} calls per {period} and user (quota `{name}`)."
+        ));
+    }
+    if args.audit {
+        doc.push_str(" Calls are recorded in the audit trail.");
+    }
+    if !args.headers.is_empty() {
+        let headers: Vec<String> = args
+            .headers
+            .iter()
+            .map(|(name, _)| format!("`{}`", name.value()))
+            .collect();
+        doc.push_str(&format!(
+            " Calls must carry the headers {}.",
+            headers.join(", ")
+        ));
+    }
+    if let Some(ms) = args.timeout_ms {
+        doc.push_str(&format!(" Calls are aborted after {ms} ms."));
+    }
+    if let Some(ms) = args.server_timeout_ms {
+        doc.push_str(&format!(
+            " The server gives up after {ms} ms with `504 Gateway Timeout`."
+        ));
+    }
+    doc
 }
 
 /// Convert snake_case to PascalCase
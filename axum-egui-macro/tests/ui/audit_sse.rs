@@ -0,0 +1,14 @@
+//! Test that `audit` is rejected on streaming server functions.
+
+use axum_egui_macro::server;
+
+#[server(sse, audit)]
+pub async fn ticks(every_ms: u64) -> Result<ServerStream<u64>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct ServerStream<T>(T);
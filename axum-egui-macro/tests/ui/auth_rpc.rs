@@ -0,0 +1,13 @@
+//! Test that `auth` is rejected on regular (RPC) server functions.
+
+use axum_egui_macro::server;
+
+#[server(auth)]
+pub async fn get_profile(id: u32) -> Result<String, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
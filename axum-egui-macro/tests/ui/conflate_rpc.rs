@@ -0,0 +1,13 @@
+//! Test that `conflate` is rejected on regular (RPC) server functions.
+
+use axum_egui_macro::server;
+
+#[server(conflate)]
+pub async fn price(symbol: String) -> Result<f64, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
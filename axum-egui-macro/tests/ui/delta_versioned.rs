@@ -0,0 +1,14 @@
+//! Test that `delta` is rejected together with `versioned`.
+
+use axum_egui_macro::server;
+
+#[server(sse, versioned, delta)]
+pub async fn ticks() -> Result<ServerStream<u64>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerStream<T>(T);
+pub struct ServerFnError;
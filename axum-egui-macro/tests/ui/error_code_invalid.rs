@@ -0,0 +1,12 @@
+//! Test that `#[error_code]` must be a string literal.
+
+use axum_egui_macro::ErrorCode;
+
+#[derive(ErrorCode)]
+pub enum InvoiceError {
+    NotFound,
+    #[error_code(locked)]
+    AlreadyPaid,
+}
+
+fn main() {}
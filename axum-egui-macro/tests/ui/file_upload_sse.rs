@@ -0,0 +1,15 @@
+//! Test that `FileUpload` arguments are rejected on stream functions.
+
+use axum_egui_macro::server;
+
+#[server(sse)]
+pub async fn progress(image: FileUpload) -> Result<ServerStream<u8>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct FileUpload;
+pub struct ServerStream<T>(T);
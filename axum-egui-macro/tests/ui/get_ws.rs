@@ -0,0 +1,14 @@
+//! Test that `get` is rejected on streaming server functions.
+
+use axum_egui_macro::server;
+
+#[server(ws, get)]
+pub async fn chat(input: ServerStream<String>) -> Result<ServerStream<String>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct ServerStream<T>(T);
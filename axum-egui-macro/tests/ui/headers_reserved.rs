@@ -0,0 +1,13 @@
+//! Test that `headers` rejects headers every call already sets.
+
+use axum_egui_macro::server;
+
+#[server(headers(("content-type", "text/plain")))]
+pub async fn save(value: u32) -> Result<u32, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
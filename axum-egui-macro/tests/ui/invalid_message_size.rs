@@ -0,0 +1,16 @@
+//! Test that malformed `max_message_size` values are rejected.
+
+use axum_egui_macro::server;
+
+#[server(ws, max_message_size = "256 kilobytes")]
+pub async fn chat(
+    input: ServerStream<String>,
+) -> Result<ServerStream<String>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct ServerStream<T>(T);
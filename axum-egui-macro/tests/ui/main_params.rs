@@ -0,0 +1,13 @@
+//! Test that `#[main]` functions cannot take parameters.
+
+use axum_egui_macro::main as frontend_main;
+
+#[frontend_main]
+fn start(canvas_id: &str) -> Bootstrap {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct Bootstrap;
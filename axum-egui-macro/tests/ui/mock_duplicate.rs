@@ -0,0 +1,13 @@
+//! Test that `mock` names one function.
+
+use axum_egui_macro::server;
+
+#[server(mock = mock_greet, mock = other_greet)]
+pub async fn greet(name: String) -> Result<String, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
@@ -0,0 +1,13 @@
+//! Test that `post_params` is rejected on regular (non-streaming) server functions.
+
+use axum_egui_macro::server;
+
+#[server(post_params)]
+pub async fn search(query: String) -> Result<Vec<String>, ServerFnError> {
+    Ok(vec![query])
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
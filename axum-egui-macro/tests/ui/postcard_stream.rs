@@ -0,0 +1,14 @@
+//! Test that `postcard` is rejected on streaming server functions.
+
+use axum_egui_macro::server;
+
+#[server(sse, postcard)]
+pub async fn ticks() -> Result<ServerStream<u64>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct ServerStream<T>(T);
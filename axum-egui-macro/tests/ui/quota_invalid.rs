@@ -0,0 +1,13 @@
+//! Test that a quota without a known period is rejected.
+
+use axum_egui_macro::server;
+
+#[server(quota = "exports:10/fortnight")]
+pub async fn export_report(id: u64) -> Result<Vec<u8>, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
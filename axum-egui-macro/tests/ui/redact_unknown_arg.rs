@@ -0,0 +1,13 @@
+//! Test that `redact` only accepts names of the function's arguments.
+
+use axum_egui_macro::server;
+
+#[server(redact(pasword))]
+pub async fn login(user: String, password: String) -> Result<String, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
@@ -0,0 +1,13 @@
+//! Test that `stale` is rejected without `cache`.
+
+use axum_egui_macro::server;
+
+#[server(stale = "5m")]
+pub async fn revenue(quarter: u32) -> Result<u64, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
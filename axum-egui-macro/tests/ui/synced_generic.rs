@@ -0,0 +1,10 @@
+//! Test that `SyncedState` cannot be derived for generic types.
+
+use axum_egui_macro::SyncedState;
+
+#[derive(SyncedState)]
+pub struct Board<T> {
+    cards: Vec<T>,
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+//! Test that unknown server function options are rejected.
+
+use axum_egui_macro::server;
+
+#[server(grpc)]
+pub async fn bad_option() -> Result<(), ServerFnError> {
+    Ok(())
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
@@ -0,0 +1,17 @@
+//! Test that a streaming body is rejected on `get` functions.
+
+use axum_egui_macro::server;
+
+#[server(get)]
+pub async fn upload(name: String, body: impl Stream<Item = Bytes>) -> Result<u64, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub types for the test
+pub struct ServerFnError;
+pub struct Bytes;
+pub trait Stream {
+    type Item;
+}
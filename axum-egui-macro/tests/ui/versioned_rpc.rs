@@ -0,0 +1,13 @@
+//! Test that `versioned` is rejected on regular (RPC) server functions.
+
+use axum_egui_macro::server;
+
+#[server(versioned)]
+pub async fn add(a: i32, b: i32) -> Result<i32, ServerFnError> {
+    todo!()
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
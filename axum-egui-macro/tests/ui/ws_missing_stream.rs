@@ -0,0 +1,13 @@
+//! Test that `ws` server functions require a client message stream.
+
+use axum_egui_macro::server;
+
+#[server(ws)]
+pub async fn no_input(room: String) -> Result<(), ServerFnError> {
+    Ok(())
+}
+
+fn main() {}
+
+// Stub type for the test
+pub struct ServerFnError;
@@ -0,0 +1,240 @@
+//! Sizes and speeds of the wire encodings and stream transports.
+//!
+//! ```sh
+//! cargo bench -p axum-egui --bench encodings --features snapshot,postcard,cbor
+//! ```
+//!
+//! Encodes a corpus of typical payloads (a small RPC answer, a table, a
+//! time series) as JSON (RPC, SSE and WebSocket messages), postcard and
+//! CBOR (`#[server(postcard)]`, `#[server(cbor)]`) and MessagePack
+//! (`snapshot`), then streams table rows over SSE and over a WebSocket,
+//! both in memory. Benchmarks are named `<payload>/<encode|decode>/<encoding>`
+//! and `stream/<transport>`; pass a part of the name to run matching ones
+//! only, e.g. `cargo bench ... -- table`. Encoded sizes are printed before
+//! each payload's benchmarks.
+
+use axum::body::Body;
+use axum_egui::rpc::ServerFnError;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use futures_util::{SinkExt, StreamExt};
+use http_body_util::BodyExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::Role;
+
+/// Items streamed per transport run.
+const STREAM_ITEMS: usize = 10_000;
+
+// ============================================================================
+// Corpus
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Session {
+    user_id: u64,
+    name: String,
+    roles: Vec<String>,
+    expires_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Row {
+    id: u64,
+    name: String,
+    price: f64,
+    quantity: u32,
+    tags: Vec<String>,
+    active: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Series {
+    name: String,
+    start_ms: u64,
+    step_ms: u32,
+    points: Vec<f32>,
+}
+
+fn session() -> Session {
+    Session {
+        user_id: 42,
+        name: "Ada Lovelace".into(),
+        roles: vec!["admin".into(), "billing".into()],
+        expires_at: 1_767_225_600,
+    }
+}
+
+fn row(i: u64) -> Row {
+    Row {
+        id: i,
+        name: format!("Item {i:05}"),
+        price: (i % 997) as f64 * 1.25,
+        quantity: (i % 50) as u32,
+        tags: ["new", "sale", "bulk"][..(i % 4) as usize]
+            .iter()
+            .map(|tag| tag.to_string())
+            .collect(),
+        active: i % 3 != 0,
+    }
+}
+
+fn table(rows: u64) -> Vec<Row> {
+    (0..rows).map(row).collect()
+}
+
+fn series(points: usize) -> Series {
+    Series {
+        name: "cpu".into(),
+        start_ms: 1_767_225_600_000,
+        step_ms: 1000,
+        points: (0..points)
+            .map(|i| (i as f32 * 0.1).sin() * 50.0 + 50.0)
+            .collect(),
+    }
+}
+
+// ============================================================================
+// Encodings
+// ============================================================================
+
+/// An encoding: its name, encoder and decoder.
+type Encoding<T> = (&'static str, fn(&T) -> Vec<u8>, fn(&[u8]) -> T);
+
+fn encodings<T: Serialize + DeserializeOwned>() -> [Encoding<T>; 4] {
+    [
+        (
+            "json",
+            |value| serde_json::to_vec(value).expect("corpus serializes"),
+            |bytes| serde_json::from_slice(bytes).expect("valid JSON"),
+        ),
+        (
+            "postcard",
+            |value| postcard::to_allocvec(value).expect("corpus serializes"),
+            |bytes| postcard::from_bytes(bytes).expect("valid postcard"),
+        ),
+        (
+            "cbor",
+            |value| {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes).expect("corpus serializes");
+                bytes
+            },
+            |bytes| ciborium::from_reader(bytes).expect("valid CBOR"),
+        ),
+        (
+            "msgpack",
+            |value| rmp_serde::to_vec_named(value).expect("corpus serializes"),
+            |bytes| rmp_serde::from_slice(bytes).expect("valid MessagePack"),
+        ),
+    ]
+}
+
+fn bench_payload<T>(c: &mut Criterion, payload: &str, value: &T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + std::fmt::Debug,
+{
+    let mut group = c.benchmark_group(payload);
+    let json_size = serde_json::to_vec(value).expect("corpus serializes").len();
+    for (name, encode, decode) in encodings::<T>() {
+        let bytes = encode(value);
+        assert_eq!(&decode(&bytes), value);
+        println!(
+            "{payload}/{name}: {} bytes, {:.0}% of JSON",
+            bytes.len(),
+            bytes.len() as f64 * 100.0 / json_size as f64,
+        );
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_with_input(BenchmarkId::new("encode", name), value, |b, value| {
+            b.iter(|| encode(black_box(value)))
+        });
+        group.bench_with_input(BenchmarkId::new("decode", name), &bytes, |b, bytes| {
+            b.iter(|| decode(black_box(bytes)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_encodings(c: &mut Criterion) {
+    bench_payload(c, "session", &session());
+    bench_payload(c, "table", &table(1_000));
+    bench_payload(c, "series", &series(10_000));
+}
+
+// ============================================================================
+// Transports
+// ============================================================================
+
+/// Stream `rows` as a `#[server(sse)]` handler does, read the events back
+/// from the body.
+async fn sse(rows: &[Row]) -> usize {
+    let items = futures_util::stream::iter(rows.to_vec()).map(Ok::<_, ServerFnError>);
+    let response = axum_egui::sse::into_sse_response(items);
+    let body: Body = response.into_body();
+    let body = body.collect().await.expect("in-memory body").to_bytes();
+    let text = std::str::from_utf8(&body).expect("SSE is text");
+    for event in text.split("\n\n") {
+        // The end event has a name and no JSON data
+        if let Some(data) = event.strip_prefix("data: ") {
+            black_box(serde_json::from_str::<Row>(data).expect("valid row"));
+        }
+    }
+    body.len()
+}
+
+/// Send `rows` as WebSocket text messages over an in-memory pipe, read them
+/// back on the other end.
+async fn ws(rows: &[Row]) -> usize {
+    let (server, client) = tokio::io::duplex(64 * 1024);
+    let mut server = WebSocketStream::from_raw_socket(server, Role::Server, None).await;
+    let mut client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+    // Owns the server end, so the pipe closes once everything is sent
+    let send = async move {
+        for row in rows {
+            let json = serde_json::to_string(row).expect("rows serialize");
+            server
+                .send(Message::text(json))
+                .await
+                .expect("in-memory pipe");
+        }
+        server.close(None).await.expect("in-memory pipe");
+    };
+    let receive = async {
+        let mut bytes = 0;
+        while let Some(Ok(message)) = client.next().await {
+            if let Message::Text(text) = message {
+                black_box(serde_json::from_str::<Row>(&text).expect("valid row"));
+                // Payload plus the frame header of a server message
+                bytes += text.len() + if text.len() < 126 { 2 } else { 4 };
+            }
+        }
+        bytes
+    };
+    tokio::join!(send, receive).1
+}
+
+fn bench_transports(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    let rows = table(STREAM_ITEMS as u64);
+    println!(
+        "stream/sse: {} bytes, stream/ws: {} bytes",
+        runtime.block_on(sse(&rows)),
+        runtime.block_on(ws(&rows)),
+    );
+
+    let mut group = c.benchmark_group("stream");
+    // Each run streams the whole table
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(STREAM_ITEMS as u64));
+    group.bench_function("sse", |b| b.iter(|| runtime.block_on(sse(&rows))));
+    group.bench_function("ws", |b| b.iter(|| runtime.block_on(ws(&rows))));
+    group.finish();
+}
+
+criterion_group!(benches, bench_encodings, bench_transports);
+criterion_main!(benches);
@@ -0,0 +1,321 @@
+//! First-party product analytics for egui apps.
+//!
+//! The client queues events with [`track`] and sends them in batches to
+//! [`ANALYTICS_PATH`] with `navigator.sendBeacon`, every few seconds and
+//! when the page is hidden, so events survive the tab being closed. The
+//! server hands every batch to an [`EventSink`]:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new().merge(analytics::router(analytics::TracingSink));
+//!
+//! // Client, once at startup (tracking is off until then)
+//! analytics::start(TrackerConfig::default());
+//! analytics::track("document_opened", &json!({ "pages": 3 }));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Path of the ingestion endpoint served by [`router`].
+pub const ANALYTICS_PATH: &str = "/api/analytics";
+
+/// Largest batch the server accepts.
+pub const MAX_BATCH_EVENTS: usize = 500;
+
+/// An event recorded by [`track`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedEvent {
+    /// The event name, e.g. `"document_opened"`.
+    pub name: String,
+    /// Event properties.
+    #[serde(default)]
+    pub props: Map<String, Value>,
+    /// When the event happened on the client, in milliseconds since the
+    /// Unix epoch.
+    pub timestamp_ms: u64,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{ANALYTICS_PATH, MAX_BATCH_EVENTS, TrackedEvent};
+    use axum::Router;
+    use axum::body::Bytes;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+    use axum::routing::post;
+    use std::sync::Arc;
+
+    /// Receives the event batches sent by clients.
+    ///
+    /// `record` runs on the request task; hand batches to a channel or spawn
+    /// a task for slow destinations. Implemented for closures.
+    pub trait EventSink: Send + Sync + 'static {
+        /// Store a batch of events sent with `request`.
+        fn record(&self, events: Vec<TrackedEvent>, request: &Parts);
+    }
+
+    impl<F> EventSink for F
+    where
+        F: Fn(Vec<TrackedEvent>, &Parts) + Send + Sync + 'static,
+    {
+        fn record(&self, events: Vec<TrackedEvent>, request: &Parts) {
+            self(events, request)
+        }
+    }
+
+    /// Logs every event with `tracing` at info level.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TracingSink;
+
+    impl EventSink for TracingSink {
+        fn record(&self, events: Vec<TrackedEvent>, _request: &Parts) {
+            for event in events {
+                tracing::info!(
+                    target: "axum_egui::analytics",
+                    event = %event.name,
+                    props = %serde_json::Value::Object(event.props),
+                    timestamp_ms = event.timestamp_ms,
+                );
+            }
+        }
+    }
+
+    /// The ingestion endpoint at [`ANALYTICS_PATH`], feeding `sink`.
+    ///
+    /// Beacons are sent as `text/plain`, so the body is parsed as JSON
+    /// whatever its content type. Invalid batches get `400 Bad Request`,
+    /// batches over [`MAX_BATCH_EVENTS`] `413 Payload Too Large`.
+    pub fn router<S>(sink: impl EventSink) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let sink: Arc<dyn EventSink> = Arc::new(sink);
+        Router::new().route(
+            ANALYTICS_PATH,
+            post(move |parts: Parts, body: Bytes| {
+                let sink = sink.clone();
+                async move {
+                    let Ok(events) = serde_json::from_slice::<Vec<TrackedEvent>>(&body) else {
+                        return StatusCode::BAD_REQUEST;
+                    };
+                    if events.len() > MAX_BATCH_EVENTS {
+                        return StatusCode::PAYLOAD_TOO_LARGE;
+                    }
+                    if !events.is_empty() {
+                        sink.record(events, &parts);
+                    }
+                    StatusCode::NO_CONTENT
+                }
+            }),
+        )
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{ANALYTICS_PATH, MAX_BATCH_EVENTS, TrackedEvent};
+    use serde::Serialize;
+    use std::cell::RefCell;
+    use std::time::Duration;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+
+    /// Batching settings for [`start`].
+    #[derive(Debug, Clone)]
+    pub struct TrackerConfig {
+        flush_interval: Duration,
+        max_batch: usize,
+    }
+
+    impl Default for TrackerConfig {
+        fn default() -> Self {
+            Self {
+                flush_interval: Duration::from_secs(10),
+                max_batch: 50,
+            }
+        }
+    }
+
+    impl TrackerConfig {
+        /// How often queued events are sent (default: 10 seconds).
+        pub fn flush_interval(mut self, interval: Duration) -> Self {
+            self.flush_interval = interval;
+            self
+        }
+
+        /// Send as soon as this many events are queued (default: 50, at
+        /// most [`MAX_BATCH_EVENTS`]).
+        pub fn max_batch(mut self, max_batch: usize) -> Self {
+            self.max_batch = max_batch.clamp(1, MAX_BATCH_EVENTS);
+            self
+        }
+    }
+
+    struct Tracker {
+        queue: Vec<TrackedEvent>,
+        max_batch: usize,
+    }
+
+    thread_local! {
+        static TRACKER: RefCell<Option<Tracker>> = const { RefCell::new(None) };
+    }
+
+    /// Start sending tracked events. Events tracked before this are dropped;
+    /// calling it again only changes the batch size.
+    pub fn start(config: TrackerConfig) {
+        let started = TRACKER.with_borrow_mut(|tracker| match tracker {
+            Some(tracker) => {
+                tracker.max_batch = config.max_batch;
+                true
+            }
+            None => {
+                *tracker = Some(Tracker {
+                    queue: Vec::new(),
+                    max_batch: config.max_batch,
+                });
+                false
+            }
+        });
+        if started {
+            return;
+        }
+
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let on_interval = Closure::<dyn FnMut()>::new(flush);
+        let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            on_interval.as_ref().unchecked_ref(),
+            config
+                .flush_interval
+                .as_millis()
+                .try_into()
+                .unwrap_or(i32::MAX),
+        );
+        on_interval.forget();
+
+        // Last chance before the tab is closed or put in the background
+        if let Some(document) = window.document() {
+            let on_hidden = Closure::<dyn FnMut()>::new(move || {
+                let hidden = web_sys::window()
+                    .and_then(|w| w.document())
+                    .is_none_or(|d| d.visibility_state() == web_sys::VisibilityState::Hidden);
+                if hidden {
+                    flush();
+                }
+            });
+            let callback = on_hidden.as_ref().unchecked_ref();
+            let _ = document.add_event_listener_with_callback("visibilitychange", callback);
+            let _ = window.add_event_listener_with_callback("pagehide", callback);
+            on_hidden.forget();
+        }
+    }
+
+    /// Queue an event. Does nothing until [`start`] has been called, or if
+    /// `props` does not serialize to a JSON object (or unit).
+    pub fn track(name: &str, props: &impl Serialize) {
+        let props = match serde_json::to_value(props) {
+            Ok(serde_json::Value::Object(props)) => props,
+            Ok(serde_json::Value::Null) => Default::default(),
+            _ => return,
+        };
+        let full = TRACKER.with_borrow_mut(|tracker| {
+            let Some(tracker) = tracker else {
+                return false;
+            };
+            tracker.queue.push(TrackedEvent {
+                name: name.to_string(),
+                props,
+                timestamp_ms: js_sys::Date::now() as u64,
+            });
+            tracker.queue.len() >= tracker.max_batch
+        });
+        if full {
+            flush();
+        }
+    }
+
+    /// Send all queued events now.
+    pub fn flush() {
+        let events = TRACKER.with_borrow_mut(|tracker| {
+            tracker
+                .as_mut()
+                .map(|t| std::mem::take(&mut t.queue))
+                .unwrap_or_default()
+        });
+        if events.is_empty() {
+            return;
+        }
+        let (Some(window), Ok(json)) = (web_sys::window(), serde_json::to_string(&events)) else {
+            return;
+        };
+        if !matches!(
+            window
+                .navigator()
+                .send_beacon_with_opt_str(ANALYTICS_PATH, Some(&json)),
+            Ok(true)
+        ) {
+            web_sys::console::warn_1(&format!("dropped {} analytics events", events.len()).into());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use std::sync::{Arc, Mutex};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn router_hands_batches_to_sink() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app: axum::Router = router({
+            let received = received.clone();
+            move |events: Vec<TrackedEvent>, _: &axum::http::request::Parts| {
+                received.lock().unwrap().extend(events)
+            }
+        });
+        let post = |body: String| {
+            Request::post(ANALYTICS_PATH)
+                .header("content-type", "text/plain;charset=UTF-8")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let batch = r#"[{"name":"opened","props":{"pages":3},"timestamp_ms":1},{"name":"closed","timestamp_ms":2}]"#;
+        let response = app.clone().oneshot(post(batch.into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 2);
+            assert_eq!(received[0].props["pages"], 3);
+            assert!(received[1].props.is_empty());
+        }
+
+        let response = app.clone().oneshot(post("nope".into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let event = r#"{"name":"x","timestamp_ms":0}"#;
+        let oversized = format!("[{}]", vec![event; MAX_BATCH_EVENTS + 1].join(","));
+        let response = app.oneshot(post(oversized)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,727 @@
+//! The `App<T>` response wrapper and embedded static asset serving.
+
+use crate::meta::PageMeta;
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{Html, IntoResponse, Response},
+    routing::{MethodRouter, get},
+};
+use futures_util::{StreamExt, stream};
+use rust_embed::RustEmbed;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+/// Environment variable that enables the live-reload script.
+///
+/// Set by `cargo axum-egui dev` when it (re)starts the server.
+pub const LIVE_RELOAD_ENV: &str = "AXUM_EGUI_LIVE_RELOAD";
+
+/// Polls the current page while the dev server restarts and reloads once
+/// it is reachable again.
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>(function(){var down=false;setInterval(function(){fetch(location.pathname,{method:"HEAD",cache:"no-store"}).then(function(){if(down){location.reload();}}).catch(function(){down=true;});},1000);})();</script>"#;
+
+fn live_reload_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| std::env::var_os(LIVE_RELOAD_ENV).is_some())
+}
+
+/// Axum response wrapper for serving egui apps with initial state.
+///
+/// This wrapper injects serialized state into the HTML template, allowing
+/// the frontend to hydrate with server-provided data.
+pub struct App<T, A: RustEmbed> {
+    state: T,
+    asset_base_url: Option<String>,
+    preload: bool,
+    state_at_end: bool,
+    loading: Option<String>,
+    noscript: Option<String>,
+    slots: Vec<(String, serde_json::Result<String>)>,
+    meta: PageMeta,
+    injections: Vec<(String, String)>,
+    transforms: Vec<Box<dyn FnOnce(String) -> String + Send>>,
+    _assets: PhantomData<A>,
+}
+
+impl<T, A: RustEmbed> App<T, A> {
+    /// Create a new App response with the given initial state.
+    pub fn new(state: T) -> Self {
+        Self {
+            state,
+            asset_base_url: None,
+            preload: true,
+            state_at_end: false,
+            loading: None,
+            noscript: None,
+            slots: Vec::new(),
+            meta: PageMeta::new(),
+            injections: Vec::new(),
+            transforms: Vec::new(),
+            _assets: PhantomData,
+        }
+    }
+
+    /// Whether to add `<link rel="preload">` hints for the WASM module and
+    /// `<link rel="modulepreload">` for its JS loader (on by default).
+    ///
+    /// The browser then starts downloading the WASM while it is still parsing
+    /// the page, instead of after the loader script has run.
+    pub fn preload_hints(mut self, enabled: bool) -> Self {
+        self.preload = enabled;
+        self
+    }
+
+    /// Inject the state right before `</body>` instead of at the
+    /// `<!--AXUM_EGUI_INITIAL_STATE-->` placeholder.
+    ///
+    /// With [`streaming`](App::streaming), this sends the whole document,
+    /// including the loading text, before the state. Module scripts run after
+    /// the document is parsed, so the frontend still finds the state.
+    pub fn state_at_end_of_body(mut self, enabled: bool) -> Self {
+        self.state_at_end = enabled;
+        self
+    }
+
+    /// Replace the contents of the `id="loading_text"` element with `html`,
+    /// e.g. a spinner with its own `<style>`.
+    ///
+    /// The element itself is kept, so the frontend can still remove it once
+    /// it has started. Templates without such an element get one at the start
+    /// of `<body>`.
+    pub fn loading_skeleton(mut self, html: impl Into<String>) -> Self {
+        self.loading = Some(html.into());
+        self
+    }
+
+    /// Show `html` to clients without JavaScript, such as crawlers, in a
+    /// `<noscript>` element. Replaces the template's own `<noscript>`, if any.
+    pub fn noscript(mut self, html: impl Into<String>) -> Self {
+        self.noscript = Some(html.into());
+        self
+    }
+
+    /// Inject `value` into the page as the [slot](crate::slots) `name`, next
+    /// to the state. The client reads it with `axum_egui::slots::read`.
+    pub fn slot(mut self, name: impl Into<String>, value: &impl Serialize) -> Self {
+        self.slots.push((name.into(), serde_json::to_string(value)));
+        self
+    }
+
+    /// Store `value` as a [snapshot](crate::snapshot) for the client to
+    /// fetch after starting, and inject only its id. For the bulk of a very
+    /// large state; `value` is encoded right away.
+    #[cfg(feature = "snapshot")]
+    pub fn snapshot(mut self, value: &impl Serialize) -> Self {
+        let snapshot = crate::snapshot::insert(value)
+            .map_err(<serde_json::Error as serde::ser::Error>::custom)
+            .and_then(|snapshot| serde_json::to_string(&snapshot));
+        self.slots
+            .push((crate::snapshot::SNAPSHOT_SLOT.to_string(), snapshot));
+        self
+    }
+
+    /// Inject the [feature flags](crate::flags) of the request.
+    pub fn feature_flags(self, flags: &crate::flags::FeatureFlags) -> Self {
+        self.slot(crate::flags::FLAGS_SLOT, flags)
+    }
+
+    /// Set the `<title>` and fill in description and OpenGraph tags for
+    /// link previews (see [`crate::meta`]).
+    pub fn meta(mut self, meta: &PageMeta) -> Self {
+        self.meta = meta.clone();
+        self
+    }
+
+    /// Insert `html` at the `<!--AXUM_EGUI:{name}-->` placeholder of the
+    /// template, e.g. an analytics snippet or a font stylesheet. Snippets for
+    /// the same placeholder keep their order.
+    ///
+    /// `head` and `body` default to the end of `<head>` and `<body>` in
+    /// templates without those placeholders. Other names are skipped (with a
+    /// warning) when the template lacks them. Snippets are inserted as
+    /// written, after [`asset_base_url`](Self::asset_base_url) rewriting.
+    pub fn inject(mut self, name: impl Into<String>, html: impl Into<String>) -> Self {
+        self.injections.push((name.into(), html.into()));
+        self
+    }
+
+    /// Post-process the page with `transform`, after every other change but
+    /// before the state and slots are added. Transforms run in the order
+    /// they were added.
+    pub fn transform_html(
+        mut self,
+        transform: impl FnOnce(String) -> String + Send + 'static,
+    ) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Tell the client which [`Route`](crate::route::Route) the page is
+    /// for, e.g. after resolving or redirecting the requested path.
+    /// `History::new` starts there.
+    pub fn route(self, route: &impl crate::route::Route) -> Self {
+        self.slot(crate::route::ROUTE_SLOT, &route.to_path())
+    }
+
+    /// Load the frontend's JS and WASM from another origin, such as a CDN.
+    ///
+    /// Relative `./` asset references in `index.html` are rewritten to point
+    /// at `base_url`, and module scripts get `crossorigin="anonymous"`. The
+    /// server then only needs to serve HTML and APIs; upload the contents of
+    /// the `{CRATE_NAME}_DIST` directory to the CDN, which must send
+    /// `Access-Control-Allow-Origin` for the page's origin.
+    ///
+    /// To bake the URL into the generated HTML at build time instead, use
+    /// `axum_egui_build::Frontend::asset_base_url`.
+    pub fn asset_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.asset_base_url = Some(base_url.into());
+        self
+    }
+}
+
+/// Preload hints for the frontend's WASM module and JS loader in `A`.
+///
+/// The hrefs must match the URLs the loader requests for the browser to
+/// reuse the preloaded responses, so they use the plain `./` asset names.
+fn preload_links<A: RustEmbed>() -> String {
+    let paths: Vec<_> = A::iter().collect();
+    let Some(wasm) = paths.iter().find(|path| path.ends_with(".wasm")) else {
+        return String::new();
+    };
+    // wasm-bindgen names the pair `{name}.js` and `{name}_bg.wasm`
+    let loader = format!(
+        "{}.js",
+        wasm.trim_end_matches(".wasm").trim_end_matches("_bg")
+    );
+
+    let mut links = format!(
+        r#"<link rel="preload" href="./{wasm}" as="fetch" type="application/wasm" crossorigin="anonymous">"#
+    );
+    if paths.iter().any(|path| *path == loader) {
+        links.push_str(&format!(r#"<link rel="modulepreload" href="./{loader}">"#));
+    }
+    links
+}
+
+/// Byte offset just past the opening `<body ...>` tag, or 0 if there is none.
+fn body_start(html: &str) -> usize {
+    html.find("<body")
+        .and_then(|at| html[at..].find('>').map(|end| at + end + 1))
+        .unwrap_or(0)
+}
+
+/// Replace the contents of the `id="loading_text"` element with `skeleton`.
+fn replace_loading(html: &str, skeleton: &str) -> String {
+    let element = html.find(r#"id="loading_text""#).and_then(|id| {
+        let open = html[..id].rfind('<')?;
+        let tag = html[open + 1..]
+            .split(|c: char| c.is_whitespace() || c == '>')
+            .next()?;
+        let inner = id + html[id..].find('>')? + 1;
+        // Skip nested elements of the same tag
+        let (opening, closing) = (format!("<{tag}"), format!("</{tag}>"));
+        let mut depth = 0;
+        let mut at = inner;
+        loop {
+            let next_close = at + html[at..].find(&closing)?;
+            match html[at..next_close].find(&opening) {
+                Some(offset) => {
+                    depth += 1;
+                    at += offset + opening.len();
+                }
+                None if depth > 0 => {
+                    depth -= 1;
+                    at = next_close + closing.len();
+                }
+                None => return Some((inner, next_close)),
+            }
+        }
+    });
+
+    match element {
+        Some((start, end)) => format!("{}{skeleton}{}", &html[..start], &html[end..]),
+        None => {
+            let at = body_start(html);
+            format!(
+                r#"{}<div id="loading_text">{skeleton}</div>{}"#,
+                &html[..at],
+                &html[at..]
+            )
+        }
+    }
+}
+
+/// Replace the first `<noscript>` element in `html`, or add one at the start
+/// of `<body>`.
+fn replace_noscript(html: &str, content: &str) -> String {
+    let noscript = format!("<noscript>{content}</noscript>");
+    let existing = html.find("<noscript").and_then(|start| {
+        let end = start + html[start..].find("</noscript>")? + "</noscript>".len();
+        Some((start, end))
+    });
+    let (start, end) = existing.unwrap_or_else(|| {
+        let at = body_start(html);
+        (at, at)
+    });
+    format!("{}{noscript}{}", &html[..start], &html[end..])
+}
+
+/// Rewrite relative `./` asset references in `html` to load from `base_url`.
+///
+/// Handles the references produced by the default template and wasm-bindgen's
+/// `--target web` loader: `import ... from './x.js'`, `src="./x"` and
+/// `href="./x"`. Module scripts get `crossorigin="anonymous"` so they are
+/// fetched in CORS mode without credentials.
+pub(crate) fn rewrite_asset_urls(html: &str, base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    let mut html = html.to_string();
+    for prefix in ["from '", "from \"", "src=\"", "href=\""] {
+        html = html.replace(&format!("{prefix}./"), &format!("{prefix}{base}/"));
+    }
+    html.replace(
+        r#"<script type="module">"#,
+        r#"<script type="module" crossorigin="anonymous">"#,
+    )
+    .replace(
+        r#"<script type="module" src="#,
+        r#"<script type="module" crossorigin="anonymous" src="#,
+    )
+}
+
+const STATE_PLACEHOLDER: &str = "<!--AXUM_EGUI_INITIAL_STATE-->";
+
+/// Insert `snippet` at the `name` placeholder of `html`, before the
+/// placeholder so later snippets go after it.
+pub(crate) fn inject(html: &str, name: &str, snippet: &str) -> String {
+    let placeholder = format!("<!--AXUM_EGUI:{name}-->");
+    let at = html.find(&placeholder).or_else(|| match name {
+        "head" => html.find("</head>"),
+        "body" => html.rfind("</body>"),
+        _ => None,
+    });
+    match at {
+        Some(at) => format!("{}{snippet}{}", &html[..at], &html[at..]),
+        None => {
+            tracing::warn!("index.html has no {placeholder} placeholder; skipping injection");
+            html.to_string()
+        }
+    }
+}
+
+/// The `<script>` carrying the state, followed by the already rendered
+/// `slots` and the live-reload script if enabled.
+fn state_script(state: &impl Serialize, slots: &str) -> serde_json::Result<String> {
+    let state_json = serde_json::to_string(state)?;
+    let mut script = format!(
+        r#"<script id="{}" type="application/json">{}</script>"#,
+        crate::slots::STATE_ID,
+        crate::slots::escape_json(&state_json)
+    );
+    script.push_str(slots);
+    if live_reload_enabled() {
+        script.push_str(LIVE_RELOAD_SCRIPT);
+    }
+    Ok(script)
+}
+
+impl<T, A: RustEmbed> App<T, A> {
+    /// Split `index.html` at the point where the state goes. Returns `None`
+    /// for the split point if the template has no placeholder.
+    fn template(&mut self) -> Option<(String, Option<String>)> {
+        let content = A::get("index.html")?;
+        let mut html = String::from_utf8_lossy(&content.data).into_owned();
+        if self.preload {
+            html = html.replacen("</head>", &format!("{}</head>", preload_links::<A>()), 1);
+        }
+        if let Some(skeleton) = &self.loading {
+            html = replace_loading(&html, skeleton);
+        }
+        if let Some(noscript) = &self.noscript {
+            html = replace_noscript(&html, noscript);
+        }
+        if let Some(base_url) = &self.asset_base_url {
+            html = rewrite_asset_urls(&html, base_url);
+        }
+        html = self.meta.apply(&html);
+        for (name, snippet) in &self.injections {
+            html = inject(&html, name, snippet);
+        }
+        for transform in std::mem::take(&mut self.transforms) {
+            html = transform(html);
+        }
+
+        if self.state_at_end {
+            html = html.replacen(STATE_PLACEHOLDER, "", 1);
+            let at = html.rfind("</body>").unwrap_or(html.len());
+            let rest = html.split_off(at);
+            return Some((html, Some(rest)));
+        }
+        match html.find(STATE_PLACEHOLDER) {
+            Some(at) => {
+                let rest = html.split_off(at)[STATE_PLACEHOLDER.len()..].to_string();
+                Some((html, Some(rest)))
+            }
+            None => Some((html, None)),
+        }
+    }
+
+    /// The page before and after the state, and the rendered slots; or the
+    /// response to send as it is, if the assets are missing, the template
+    /// has no place for the state or a slot didn't serialize.
+    fn page(&mut self) -> Result<(String, String, String), Box<Response>> {
+        let Some((head, rest)) = self.template() else {
+            return Err(Box::new(
+                Response::builder()
+                    .status(StatusCode::INTERNAL_SERVER_ERROR)
+                    .body(Body::from(
+                        "Frontend assets not found. Did you build the frontend?",
+                    ))
+                    .unwrap(),
+            ));
+        };
+        let Some(rest) = rest else {
+            return Err(Box::new(Html(head).into_response()));
+        };
+
+        let mut slots = String::new();
+        for (name, json) in &self.slots {
+            match json {
+                Ok(json) => slots.push_str(&crate::slots::script(name, json)),
+                Err(e) => {
+                    return Err(Box::new(
+                        Response::builder()
+                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .body(Body::from(format!(
+                                "Failed to serialize slot {name:?}: {e}"
+                            )))
+                            .unwrap(),
+                    ));
+                }
+            }
+        }
+        Ok((head, slots, rest))
+    }
+}
+
+impl<T, A> App<T, A>
+where
+    T: Serialize + Send + 'static,
+    A: RustEmbed,
+{
+    /// Stream the HTML instead of sending it in one piece. Call this last,
+    /// after the other options.
+    ///
+    /// Everything before the state is sent immediately, so the browser starts
+    /// fetching the JS and WASM while the state is serialized on a blocking
+    /// thread. Worth it for large initial states. The status is already sent
+    /// by then, so a serialization error aborts the response instead of
+    /// returning `500`.
+    pub fn streaming(self) -> StreamingApp<T, A> {
+        StreamingApp { app: self }
+    }
+}
+
+/// An [`App`] sending its HTML as a stream, from [`App::streaming`].
+pub struct StreamingApp<T, A: RustEmbed> {
+    app: App<T, A>,
+}
+
+impl<T, A> IntoResponse for StreamingApp<T, A>
+where
+    T: Serialize + Send + 'static,
+    A: RustEmbed,
+{
+    fn into_response(mut self) -> Response {
+        let (head, slots, rest) = match self.app.page() {
+            Ok(page) => page,
+            Err(response) => return *response,
+        };
+        let state = self.app.state;
+        let script = async move {
+            tokio::task::spawn_blocking(move || state_script(&state, &slots))
+                .await
+                .map_err(std::io::Error::other)?
+                .inspect_err(|e| tracing::error!("failed to serialize app state: {e}"))
+                .map_err(std::io::Error::other)
+        };
+        let body = stream::once(async { Ok(head) })
+            .chain(stream::once(script))
+            .chain(stream::once(async { Ok(rest) }));
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from_stream(body))
+            .unwrap()
+    }
+}
+
+impl<T, A> IntoResponse for App<T, A>
+where
+    T: Serialize,
+    A: RustEmbed,
+{
+    fn into_response(mut self) -> Response {
+        let (head, slots, rest) = match self.page() {
+            Ok(page) => page,
+            Err(response) => return *response,
+        };
+        match state_script(&self.state, &slots) {
+            Ok(script) => Html(head + &script + &rest).into_response(),
+            Err(e) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(format!("Failed to serialize app state: {e}")))
+                .unwrap(),
+        }
+    }
+}
+
+/// Handler for serving static assets from an embedded `RustEmbed` type.
+///
+/// Unknown paths fall back to `index.html` for client-side routing. Responses
+/// carry an `ETag` (the asset's SHA-256) and answer a matching
+/// `If-None-Match` with `304 Not Modified`; `HEAD` gets the same headers
+/// without a body, and other methods get `405 Method Not Allowed`.
+///
+/// Assets with precompressed `.br` or `.gz` siblings (written by
+/// `axum_egui_build::Frontend::precompress`) are answered with the sibling
+/// when the request's `Accept-Encoding` allows it.
+pub async fn static_handler<A: RustEmbed>(
+    method: Method,
+    headers: HeaderMap,
+    uri: Uri,
+) -> impl IntoResponse {
+    if method != Method::GET && method != Method::HEAD {
+        return Response::builder()
+            .status(StatusCode::METHOD_NOT_ALLOWED)
+            .header(header::ALLOW, "GET, HEAD")
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let path = uri.path().trim_start_matches('/');
+    let (asset, content, mime) = match A::get(path) {
+        Some(content) => (
+            path,
+            content,
+            mime_guess::from_path(path).first_or_octet_stream(),
+        ),
+        None => match A::get("index.html") {
+            Some(content) => ("index.html", content, mime_guess::mime::TEXT_HTML),
+            None => {
+                return Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::from("404 Not Found"))
+                    .unwrap();
+            }
+        },
+    };
+
+    // The first precompressed sibling the client accepts
+    let mut precompressed = false;
+    let mut encoded = None;
+    for (encoding, extension) in PRECOMPRESSED {
+        let Some(sibling) = A::get(&format!("{asset}.{extension}")) else {
+            continue;
+        };
+        precompressed = true;
+        if encoded.is_none() && accepts_encoding(&headers, encoding) {
+            encoded = Some((encoding, sibling));
+        }
+    }
+    let (content, encoding) = match encoded {
+        Some((encoding, sibling)) => (sibling, Some(encoding)),
+        None => (content, None),
+    };
+
+    let etag = format!("\"{}\"", hex(&content.metadata.sha256_hash()));
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| matches!(tag.trim(), "*") || tag.trim() == etag)
+        });
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::ETAG, &etag);
+    if precompressed {
+        builder = builder.header(header::VARY, "accept-encoding");
+    }
+    if let Some(encoding) = encoding {
+        builder = builder.header(header::CONTENT_ENCODING, *encoding);
+    }
+    if not_modified {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap();
+    }
+
+    let builder = builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_LENGTH, content.data.len());
+    if method == Method::HEAD {
+        builder.body(Body::empty()).unwrap()
+    } else {
+        builder.body(Body::from(content.data.into_owned())).unwrap()
+    }
+}
+
+/// Serve the embedded assets in `A` by path, and `index` for every other
+/// path, so deep links get an [`App`] with state built for the requested URL
+/// instead of a bare `index.html`:
+///
+/// ```ignore
+/// async fn index(uri: Uri) -> App<MyApp, Assets> {
+///     App::new(MyApp::for_path(uri.path()).await)
+/// }
+///
+/// let app = Router::new()
+///     .route("/api/items", post(items))
+///     .fallback_service(spa_router::<Assets, _>(get(index)));
+/// ```
+///
+/// Paths of assets answer other methods with `405 Method Not Allowed`.
+pub fn spa_router<A, S>(index: MethodRouter<S>) -> Router<S>
+where
+    A: RustEmbed + Send + Sync + 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    A::iter()
+        .fold(Router::new(), |router, path| {
+            router.route(&format!("/{path}"), get(static_handler::<A>))
+        })
+        .fallback(index)
+}
+
+/// `index.html` with the [`PageMeta`] of the request filled in, for
+/// [`Serve::page_meta`](crate::serve::Serve::page_meta) without an index
+/// handler.
+pub(crate) async fn index_with_meta<A: RustEmbed>(meta: PageMeta) -> Response {
+    match A::get("index.html") {
+        Some(content) => Html(meta.apply(&String::from_utf8_lossy(&content.data))).into_response(),
+        None => (StatusCode::NOT_FOUND, "404 Not Found").into_response(),
+    }
+}
+
+/// `Content-Encoding` and file extension of precompressed assets, in order of
+/// preference.
+const PRECOMPRESSED: &[(&str, &str)] = &[("br", "br"), ("gzip", "gz")];
+
+/// Whether `Accept-Encoding` lists `encoding` without `q=0`.
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut params = item.split(';');
+            let name = params.next().unwrap_or_default().trim();
+            let refused = params.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.trim().parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            name.eq_ignore_ascii_case(encoding) && !refused
+        })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_skeleton_replaces_element_contents() {
+        let html = r#"<body><div class="centered" id="loading_text"><div><p>Loading...</p></div></div><canvas></canvas></body>"#;
+        assert_eq!(
+            replace_loading(html, "<span class=\"spinner\"></span>"),
+            r#"<body><div class="centered" id="loading_text"><span class="spinner"></span></div><canvas></canvas></body>"#
+        );
+
+        assert_eq!(
+            replace_loading("<body class=\"x\"><canvas></canvas></body>", "..."),
+            r#"<body class="x"><div id="loading_text">...</div><canvas></canvas></body>"#
+        );
+    }
+
+    #[test]
+    fn inject_uses_placeholders_or_falls_back() {
+        let html = "<head></head><body><!--AXUM_EGUI:banner--><canvas></canvas></body>";
+        let html = inject(html, "banner", "<b>1</b>");
+        let html = inject(&html, "banner", "<b>2</b>");
+        let html = inject(
+            &html,
+            "head",
+            "<link rel=\"stylesheet\" href=\"/fonts.css\">",
+        );
+        let html = inject(&html, "body", "<script src=\"/stats.js\"></script>");
+        assert_eq!(
+            inject(&html, "footer", "ignored"),
+            "<head><link rel=\"stylesheet\" href=\"/fonts.css\"></head><body><b>1</b><b>2</b><!--AXUM_EGUI:banner--><canvas></canvas><script src=\"/stats.js\"></script></body>"
+        );
+    }
+
+    /// The content of the first `<script>` of `html`, as the browser parses
+    /// it: up to the first `</script`.
+    fn script_text(html: &str) -> &str {
+        let start = html.find('>').unwrap() + 1;
+        let end = html[start..].to_ascii_lowercase().find("</script").unwrap();
+        &html[start..start + end]
+    }
+
+    #[test]
+    fn state_script_embeds_the_state_bootstrap_reads() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct AppState {
+            label: String,
+            notes: Vec<String>,
+        }
+        let state = AppState {
+            label: "</script><script>alert(1)</script>".into(),
+            notes: vec![
+                "<!--<script>".into(),
+                "</SCRIPT >".into(),
+                "\"quoted\" & 'single' \u{2028}".into(),
+            ],
+        };
+        let slots = crate::slots::script("build", r#"{"commit":"</script>"}"#);
+        let html = state_script(&state, &slots).unwrap();
+
+        assert!(html.starts_with(r#"<script id="axum-egui-state" type="application/json">"#));
+        let json = script_text(&html);
+        assert!(!json.contains("</") && !json.contains("<!--"), "{json}");
+        let read: AppState = crate::slots::from_json(crate::slots::STATE_ID, json).unwrap();
+        assert_eq!(read, state);
+
+        // Slots follow the state, as separate elements
+        let slot = &html[html.find(&slots).unwrap()..];
+        assert!(slot.starts_with(r#"<script id="axum-egui-slot-build" type="application/json">"#));
+        assert_eq!(script_text(slot), r#"{"commit":"<\/script>"}"#);
+        let build: serde_json::Value =
+            crate::slots::from_json("axum-egui-slot-build", script_text(slot)).unwrap();
+        assert_eq!(build["commit"], "</script>");
+    }
+
+    #[test]
+    fn noscript_replaces_existing_element() {
+        assert_eq!(
+            replace_noscript("<body><noscript>old</noscript></body>", "new"),
+            "<body><noscript>new</noscript></body>"
+        );
+        assert_eq!(
+            replace_noscript("<body><canvas></canvas></body>", "Enable JavaScript"),
+            "<body><noscript>Enable JavaScript</noscript><canvas></canvas></body>"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Embedded asset inventory and startup verification.
+//!
+//! A frontend that failed to build still produces an embeddable (but empty or
+//! partial) dist directory, which would otherwise be served as a broken page.
+//! Check the assets once at startup instead:
+//!
+//! ```ignore
+//! #[derive(RustEmbed)]
+//! #[folder = "$MY_FRONTEND_DIST"]
+//! struct Assets;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     // Logs every asset with its size and hash, panics if the build is broken
+//!     axum_egui::assets::ensure::<Assets>();
+//!     // ...
+//! }
+//! ```
+
+use rust_embed::RustEmbed;
+use std::fmt::Write;
+
+/// A single embedded asset.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetEntry {
+    /// Path relative to the dist directory (e.g. `my_frontend_bg.wasm`).
+    pub path: String,
+    /// Size in bytes.
+    pub size: usize,
+    /// Hex-encoded SHA-256 of the contents.
+    pub sha256: String,
+}
+
+/// All assets embedded in a `RustEmbed` type, sorted by path.
+#[derive(Debug, Clone, Default)]
+pub struct AssetInventory {
+    entries: Vec<AssetEntry>,
+}
+
+impl AssetInventory {
+    /// All entries, sorted by path.
+    pub fn entries(&self) -> &[AssetEntry] {
+        &self.entries
+    }
+
+    /// Look up an entry by path.
+    pub fn get(&self, path: &str) -> Option<&AssetEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// The first `.wasm` asset, which is the frontend module for dists
+    /// produced by `axum-egui-build`.
+    pub fn wasm_entry(&self) -> Option<&AssetEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.path.ends_with(".wasm"))
+    }
+
+    /// Total size of all assets in bytes.
+    pub fn total_size(&self) -> usize {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Log every entry at `info` level.
+    pub fn log(&self) {
+        tracing::info!(
+            assets = self.entries.len(),
+            total_bytes = self.total_size(),
+            "embedded frontend assets"
+        );
+        for entry in &self.entries {
+            tracing::info!(
+                path = %entry.path,
+                bytes = entry.size,
+                sha256 = %entry.sha256,
+                "asset"
+            );
+        }
+    }
+}
+
+/// Error returned when embedded assets are incomplete.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AssetError {
+    /// No `index.html` is embedded.
+    #[error(
+        "frontend assets are missing index.html; did the frontend build (and axum-egui-build) succeed?"
+    )]
+    MissingIndex,
+
+    /// No `.wasm` module is embedded.
+    #[error(
+        "frontend assets contain no .wasm module (found: {found:?}); did wasm-bindgen run for the frontend?"
+    )]
+    MissingWasm {
+        /// The paths that were embedded.
+        found: Vec<String>,
+    },
+}
+
+/// Enumerate all assets embedded in `A` with their sizes and hashes.
+pub fn inventory<A: RustEmbed>() -> AssetInventory {
+    let mut entries: Vec<AssetEntry> = A::iter()
+        .filter_map(|path| {
+            let file = A::get(&path)?;
+            Some(AssetEntry {
+                path: path.into_owned(),
+                size: file.data.len(),
+                sha256: hex(&file.metadata.sha256_hash()),
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    AssetInventory { entries }
+}
+
+/// Check that `A` contains `index.html` and a `.wasm` module.
+pub fn verify<A: RustEmbed>() -> Result<AssetInventory, AssetError> {
+    let inventory = inventory::<A>();
+
+    if inventory.get("index.html").is_none() {
+        return Err(AssetError::MissingIndex);
+    }
+    if inventory.wasm_entry().is_none() {
+        return Err(AssetError::MissingWasm {
+            found: inventory.entries.iter().map(|e| e.path.clone()).collect(),
+        });
+    }
+
+    Ok(inventory)
+}
+
+/// Verify and log the assets in `A`, panicking with a clear message if they
+/// are incomplete. Intended to be called once at server startup.
+pub fn ensure<A: RustEmbed>() -> AssetInventory {
+    match verify::<A>() {
+        Ok(inventory) => {
+            inventory.log();
+            inventory
+        }
+        Err(e) => panic!("{e}"),
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets/"]
+    struct TestAssets;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets_no_index/"]
+    struct TestAssetsNoIndex;
+
+    #[test]
+    fn inventory_lists_assets_sorted_with_hashes() {
+        let inventory = inventory::<TestAssets>();
+        let paths: Vec<_> = inventory
+            .entries()
+            .iter()
+            .map(|e| e.path.as_str())
+            .collect();
+        assert_eq!(paths, ["app.js", "app.wasm", "index.html"]);
+
+        let index = inventory.get("index.html").unwrap();
+        assert_eq!(index.sha256.len(), 64);
+        assert!(index.size > 0);
+        assert_eq!(inventory.wasm_entry().unwrap().path, "app.wasm");
+    }
+
+    #[test]
+    fn verify_accepts_complete_assets() {
+        assert!(verify::<TestAssets>().is_ok());
+    }
+
+    #[test]
+    fn verify_reports_missing_index() {
+        assert_eq!(
+            verify::<TestAssetsNoIndex>().unwrap_err(),
+            AssetError::MissingIndex
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "missing index.html")]
+    fn ensure_panics_on_broken_build() {
+        ensure::<TestAssetsNoIndex>();
+    }
+}
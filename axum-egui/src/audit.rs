@@ -0,0 +1,629 @@
+//! Audit trail of mutating server functions.
+//!
+//! Functions declared with `audit` record who called them, what they called
+//! (the function and a SHA-256 hash of its arguments), when, and whether the
+//! call succeeded, in an [`AuditSink`]:
+//!
+//! ```ignore
+//! #[server(audit)]
+//! pub async fn delete_account(id: u64) -> Result<(), ServerFnError> { ... }
+//!
+//! // Server
+//! let audit = Audit::new(JsonLinesSink::open("audit.jsonl")?)
+//!     .identify(|request: &Parts| session_user(&request.headers))
+//!     .query_access(|request: &Parts| is_admin(&request.headers));
+//! let app = app.merge(audit.router()).layer(Extension(audit));
+//!
+//! // Client: read the trail, or show it with `AuditView` (`egui` feature)
+//! let entries = audit::query(&AuditQuery::default().function("delete_account")).await?;
+//! ```
+//!
+//! Audited calls fail with `500 Internal Server Error` if no [`Audit`] is
+//! installed, rather than run unrecorded. The arguments themselves are not
+//! stored, only their hash, so the trail can prove what was done without
+//! holding the data.
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the query endpoint served by [`Audit::router`].
+pub const AUDIT_PATH: &str = "/api/audit";
+
+/// Number of entries a query returns when it sets no limit.
+pub const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// One audited call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// The server function name.
+    pub function: String,
+    /// The caller, as identified by [`Audit::identify`].
+    pub user: Option<String>,
+    /// Hex SHA-256 of the JSON-serialized arguments.
+    pub args_sha256: String,
+    /// When the call started, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Whether the function returned `Ok`.
+    pub succeeded: bool,
+}
+
+/// Filter for reading the trail. Matching entries are returned newest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditQuery {
+    /// Only calls to this function.
+    pub function: Option<String>,
+    /// Only calls by this user.
+    pub user: Option<String>,
+    /// Only calls at or after this time, in milliseconds since the Unix
+    /// epoch.
+    pub since_ms: Option<u64>,
+    /// At most this many entries (default: [`DEFAULT_QUERY_LIMIT`]).
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    /// Only calls to `function`.
+    pub fn function(mut self, function: impl Into<String>) -> Self {
+        self.function = Some(function.into());
+        self
+    }
+
+    /// Only calls by `user`.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Only calls at or after `since_ms`.
+    pub fn since_ms(mut self, since_ms: u64) -> Self {
+        self.since_ms = Some(since_ms);
+        self
+    }
+
+    /// At most `limit` entries.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether `entry` passes the filter (ignoring the limit).
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        self.function.as_ref().is_none_or(|f| *f == entry.function)
+            && self
+                .user
+                .as_ref()
+                .is_none_or(|u| entry.user.as_ref() == Some(u))
+            && self
+                .since_ms
+                .is_none_or(|since| entry.timestamp_ms >= since)
+    }
+
+    /// The effective limit.
+    pub fn effective_limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_QUERY_LIMIT)
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{AUDIT_PATH, AuditEntry, AuditQuery};
+    use crate::auth::AuthUser;
+    use crate::error::ErrorEnvelope;
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+    use axum::response::{IntoResponse, Json, Response};
+    use axum::routing::post;
+    use serde::Serialize;
+    use sha2::{Digest, Sha256};
+    use std::collections::VecDeque;
+    use std::io::{BufRead, Write};
+    use std::path::Path;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Future returned by [`AuditSink`] methods.
+    pub type AuditFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AuditError>> + Send + 'a>>;
+
+    /// Stores the audit trail, e.g. in a database table or a file.
+    pub trait AuditSink: Send + Sync + 'static {
+        /// Store `entry`. Audited calls wait for this before responding.
+        fn record(&self, entry: AuditEntry) -> AuditFuture<'_, ()>;
+
+        /// The entries matching `query`, newest first.
+        fn query(&self, query: AuditQuery) -> AuditFuture<'_, Vec<AuditEntry>>;
+    }
+
+    /// Why the trail could not be written or read.
+    #[derive(Debug, thiserror::Error)]
+    pub enum AuditError {
+        /// An audited function was called without an [`Audit`] installed.
+        #[error("audit trail is not configured")]
+        NotConfigured,
+        /// The sink failed.
+        #[error("audit sink error: {0}")]
+        Sink(String),
+    }
+
+    impl From<std::io::Error> for AuditError {
+        fn from(error: std::io::Error) -> Self {
+            Self::Sink(error.to_string())
+        }
+    }
+
+    impl IntoResponse for AuditError {
+        fn into_response(self) -> Response {
+            let error = ErrorEnvelope::new("audit_failed", self.to_string());
+            crate::error::error_response(StatusCode::INTERNAL_SERVER_ERROR, error, None)
+        }
+    }
+
+    /// Keeps the latest entries in memory. For tests and development; the
+    /// trail is lost on restart.
+    #[derive(Debug, Clone)]
+    pub struct MemorySink {
+        entries: Arc<Mutex<VecDeque<AuditEntry>>>,
+        capacity: usize,
+    }
+
+    impl MemorySink {
+        /// Keep at most `capacity` entries, dropping the oldest.
+        pub fn new(capacity: usize) -> Self {
+            Self {
+                entries: Arc::default(),
+                capacity: capacity.max(1),
+            }
+        }
+    }
+
+    impl AuditSink for MemorySink {
+        fn record(&self, entry: AuditEntry) -> AuditFuture<'_, ()> {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+            Box::pin(async { Ok(()) })
+        }
+
+        fn query(&self, query: AuditQuery) -> AuditFuture<'_, Vec<AuditEntry>> {
+            let entries = self.entries.lock().unwrap();
+            let matching = entries
+                .iter()
+                .rev()
+                .filter(|entry| query.matches(entry))
+                .take(query.effective_limit())
+                .cloned()
+                .collect();
+            Box::pin(async { Ok(matching) })
+        }
+    }
+
+    /// Appends entries to a file, one JSON object per line.
+    #[derive(Debug, Clone)]
+    pub struct JsonLinesSink {
+        file: Arc<Mutex<std::fs::File>>,
+        path: Arc<Path>,
+    }
+
+    impl JsonLinesSink {
+        /// Append to the file at `path`, creating it if needed.
+        pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+            let path = path.as_ref();
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            Ok(Self {
+                file: Arc::new(Mutex::new(file)),
+                path: path.into(),
+            })
+        }
+    }
+
+    impl AuditSink for JsonLinesSink {
+        fn record(&self, entry: AuditEntry) -> AuditFuture<'_, ()> {
+            let file = self.file.clone();
+            Box::pin(async move {
+                tokio::task::spawn_blocking(move || {
+                    let mut line = serde_json::to_vec(&entry).expect("audit entries serialize");
+                    line.push(b'\n');
+                    let mut file = file.lock().unwrap();
+                    file.write_all(&line)?;
+                    file.sync_data()
+                })
+                .await
+                .map_err(|e| AuditError::Sink(e.to_string()))??;
+                Ok(())
+            })
+        }
+
+        fn query(&self, query: AuditQuery) -> AuditFuture<'_, Vec<AuditEntry>> {
+            let path = self.path.clone();
+            Box::pin(async move {
+                let mut entries = tokio::task::spawn_blocking(move || {
+                    let file = std::io::BufReader::new(std::fs::File::open(&path)?);
+                    let mut entries = Vec::new();
+                    for line in file.lines() {
+                        // Skip a line torn by a crash mid-write
+                        if let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) {
+                            if query.matches(&entry) {
+                                entries.push(entry);
+                            }
+                        }
+                    }
+                    let keep = entries.len().saturating_sub(query.effective_limit());
+                    entries.drain(..keep);
+                    Ok::<_, std::io::Error>(entries)
+                })
+                .await
+                .map_err(|e| AuditError::Sink(e.to_string()))??;
+                entries.reverse();
+                Ok(entries)
+            })
+        }
+    }
+
+    type Identify = dyn Fn(&Parts) -> Option<String> + Send + Sync;
+    type QueryAccess = dyn Fn(&Parts) -> bool + Send + Sync;
+
+    /// The audit configuration: where entries go, who the caller is, and who
+    /// may read the trail. Install it as an [`Extension`](axum::Extension).
+    /// Cheap to clone.
+    #[derive(Clone)]
+    pub struct Audit {
+        sink: Arc<dyn AuditSink>,
+        identify: Arc<Identify>,
+        query_access: Arc<QueryAccess>,
+    }
+
+    impl std::fmt::Debug for Audit {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Audit").finish_non_exhaustive()
+        }
+    }
+
+    impl Audit {
+        /// Record audited calls in `sink`. Callers are identified by the
+        /// [`AuthUser`] of the request, if any, and nobody may query the
+        /// trail over HTTP.
+        pub fn new(sink: impl AuditSink) -> Self {
+            Self {
+                sink: Arc::new(sink),
+                identify: Arc::new(|request: &Parts| {
+                    request
+                        .extensions
+                        .get::<AuthUser>()
+                        .map(|user| user.id.clone())
+                }),
+                query_access: Arc::new(|_: &Parts| false),
+            }
+        }
+
+        /// Identify the caller of a request, e.g. from its session cookie.
+        pub fn identify(
+            mut self,
+            identify: impl Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+        ) -> Self {
+            self.identify = Arc::new(identify);
+            self
+        }
+
+        /// Decide which requests may read the trail from [`AUDIT_PATH`].
+        pub fn query_access(
+            mut self,
+            allow: impl Fn(&Parts) -> bool + Send + Sync + 'static,
+        ) -> Self {
+            self.query_access = Arc::new(allow);
+            self
+        }
+
+        /// The entries matching `query`, newest first.
+        pub async fn query(&self, query: AuditQuery) -> Result<Vec<AuditEntry>, AuditError> {
+            self.sink.query(query).await
+        }
+
+        /// The query endpoint at [`AUDIT_PATH`], taking an [`AuditQuery`].
+        /// Requests not allowed by [`Self::query_access`] get
+        /// `403 Forbidden`.
+        pub fn router<S>(&self) -> Router<S>
+        where
+            S: Clone + Send + Sync + 'static,
+        {
+            let audit = self.clone();
+            Router::new().route(
+                AUDIT_PATH,
+                post(move |parts: Parts, Json(query): Json<AuditQuery>| {
+                    let audit = audit.clone();
+                    async move {
+                        if !(audit.query_access)(&parts) {
+                            return StatusCode::FORBIDDEN.into_response();
+                        }
+                        match audit.query(query).await {
+                            Ok(entries) => Json(entries).into_response(),
+                            Err(e) => e.into_response(),
+                        }
+                    }
+                }),
+            )
+        }
+    }
+
+    /// An audited call in progress, from [`begin`].
+    #[doc(hidden)]
+    pub struct PendingAudit {
+        audit: Audit,
+        entry: AuditEntry,
+    }
+
+    /// Start auditing a call to `function` with `args`. Called by the
+    /// handlers generated for `audit` functions.
+    #[doc(hidden)]
+    pub fn begin(
+        request: &Parts,
+        function: &str,
+        args: &impl Serialize,
+    ) -> Result<PendingAudit, AuditError> {
+        let audit = request
+            .extensions
+            .get::<Audit>()
+            .ok_or(AuditError::NotConfigured)?
+            .clone();
+        let args = serde_json::to_vec(args).map_err(|e| AuditError::Sink(e.to_string()))?;
+        let entry = AuditEntry {
+            function: function.to_string(),
+            user: (audit.identify)(request),
+            args_sha256: crate::assets::hex(&Sha256::digest(&args)),
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            succeeded: false,
+        };
+        Ok(PendingAudit { audit, entry })
+    }
+
+    impl PendingAudit {
+        /// Record the call. A failing sink is logged; the call's own result
+        /// is returned either way, since it has already taken effect.
+        pub async fn finish(mut self, succeeded: bool) {
+            self.entry.succeeded = succeeded;
+            let function = self.entry.function.clone();
+            if let Err(e) = self.audit.sink.record(self.entry).await {
+                tracing::error!(function, error = %e, "failed to record audit entry");
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{AUDIT_PATH, AuditEntry, AuditQuery};
+    use crate::rpc::ServerFnError;
+
+    /// Read the trail from [`AUDIT_PATH`]. Fails unless the server allows
+    /// this client to.
+    pub async fn query(query: &AuditQuery) -> Result<Vec<AuditEntry>, ServerFnError> {
+        crate::rpc::call(AUDIT_PATH, query).await
+    }
+
+    /// An egui table of the latest audit entries, with filters.
+    #[cfg(feature = "egui")]
+    #[derive(Default)]
+    pub struct AuditView {
+        function: String,
+        user: String,
+        state: std::rc::Rc<std::cell::RefCell<ViewState>>,
+    }
+
+    #[cfg(feature = "egui")]
+    #[derive(Default)]
+    struct ViewState {
+        loaded: bool,
+        loading: bool,
+        result: Option<Result<Vec<AuditEntry>, ServerFnError>>,
+    }
+
+    #[cfg(feature = "egui")]
+    impl AuditView {
+        /// Show the view, loading the entries the first time.
+        pub fn ui(&mut self, ui: &mut egui::Ui) {
+            let mut refresh = !self.state.borrow().loaded;
+            ui.horizontal(|ui| {
+                ui.label("Function");
+                refresh |= ui.text_edit_singleline(&mut self.function).lost_focus();
+                ui.label("User");
+                refresh |= ui.text_edit_singleline(&mut self.user).lost_focus();
+                refresh |= ui.button("Refresh").clicked();
+                if self.state.borrow().loading {
+                    ui.spinner();
+                }
+            });
+            if refresh && !self.state.borrow().loading {
+                self.load(ui.ctx().clone());
+            }
+
+            let state = self.state.borrow();
+            match &state.result {
+                None => {}
+                Some(Err(e)) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                }
+                Some(Ok(entries)) => {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        egui::Grid::new("axum_egui_audit")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for heading in ["Time", "User", "Function", "Result", "Args"] {
+                                    ui.strong(heading);
+                                }
+                                ui.end_row();
+                                for entry in entries {
+                                    let time =
+                                        js_sys::Date::new(&(entry.timestamp_ms as f64).into());
+                                    ui.label(String::from(time.to_iso_string()));
+                                    ui.label(entry.user.as_deref().unwrap_or("-"));
+                                    ui.monospace(&entry.function);
+                                    ui.label(if entry.succeeded { "ok" } else { "error" });
+                                    ui.monospace(
+                                        &entry.args_sha256[..12.min(entry.args_sha256.len())],
+                                    )
+                                    .on_hover_text(&entry.args_sha256);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                }
+            }
+        }
+
+        fn load(&self, ctx: egui::Context) {
+            let non_empty = |s: &str| (!s.trim().is_empty()).then(|| s.trim().to_string());
+            let filter = AuditQuery {
+                function: non_empty(&self.function),
+                user: non_empty(&self.user),
+                ..AuditQuery::default()
+            };
+            let state = self.state.clone();
+            {
+                let mut state = state.borrow_mut();
+                state.loaded = true;
+                state.loading = true;
+            }
+            wasm_bindgen_futures::spawn_local(async move {
+                let result = query(&filter).await;
+                let mut state = state.borrow_mut();
+                state.loading = false;
+                state.result = Some(result);
+                ctx.request_repaint();
+            });
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::auth::AuthUser;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn request(audit: Option<&Audit>, user: Option<&str>) -> axum::http::request::Parts {
+        let mut parts = Request::post("/api/delete_account")
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0;
+        if let Some(audit) = audit {
+            parts.extensions.insert(audit.clone());
+        }
+        if let Some(user) = user {
+            parts.extensions.insert(AuthUser::new(user));
+        }
+        parts
+    }
+
+    #[tokio::test]
+    async fn records_who_what_and_outcome() {
+        let audit = Audit::new(MemorySink::new(10));
+        let args = serde_json::json!({ "id": 7 });
+        assert!(matches!(
+            begin(&request(None, None), "delete_account", &args),
+            Err(AuditError::NotConfigured)
+        ));
+
+        begin(&request(Some(&audit), Some("ada")), "delete_account", &args)
+            .unwrap()
+            .finish(true)
+            .await;
+        begin(&request(Some(&audit), None), "rename", &args)
+            .unwrap()
+            .finish(false)
+            .await;
+
+        let entries = audit.query(AuditQuery::default()).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].function, "rename");
+        assert!(!entries[0].succeeded);
+        assert_eq!(entries[1].user.as_deref(), Some("ada"));
+        assert_eq!(entries[1].args_sha256, entries[0].args_sha256);
+        assert_eq!(entries[1].args_sha256.len(), 64);
+
+        let by_ada = AuditQuery::default().user("ada");
+        assert_eq!(audit.query(by_ada).await.unwrap().len(), 1);
+        let limited = AuditQuery::default().limit(1);
+        assert_eq!(audit.query(limited).await.unwrap()[0].function, "rename");
+    }
+
+    #[tokio::test]
+    async fn json_lines_sink_appends_and_queries() {
+        let path =
+            std::env::temp_dir().join(format!("axum-egui-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = JsonLinesSink::open(&path).unwrap();
+        for (i, function) in ["a", "b", "a"].into_iter().enumerate() {
+            let entry = AuditEntry {
+                function: function.into(),
+                user: None,
+                args_sha256: String::new(),
+                timestamp_ms: i as u64,
+                succeeded: true,
+            };
+            sink.record(entry).await.unwrap();
+        }
+        let entries = sink
+            .query(AuditQuery::default().function("a"))
+            .await
+            .unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.timestamp_ms).collect::<Vec<_>>(),
+            [2, 0]
+        );
+        let latest = sink.query(AuditQuery::default().limit(1)).await.unwrap();
+        assert_eq!(latest[0].timestamp_ms, 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn query_endpoint_requires_access() {
+        let audit =
+            Audit::new(MemorySink::new(10)).query_access(|request: &axum::http::request::Parts| {
+                request.headers.contains_key("x-admin")
+            });
+        begin(&request(Some(&audit), None), "rename", &())
+            .unwrap()
+            .finish(true)
+            .await;
+        let app: axum::Router = audit.router();
+        let query = |admin: bool| {
+            let mut request = Request::post(AUDIT_PATH).header("content-type", "application/json");
+            if admin {
+                request = request.header("x-admin", "1");
+            }
+            request.body(Body::from("{}")).unwrap()
+        };
+
+        let response = app.clone().oneshot(query(false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = app.oneshot(query(true)).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let entries: Vec<AuditEntry> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(entries[0].function, "rename");
+    }
+}
@@ -0,0 +1,403 @@
+//! Authentication of SSE and WebSocket connections with short-lived tickets.
+//!
+//! `EventSource` and `WebSocket` cannot send an `Authorization` header. For
+//! streaming functions declared with `auth`, the client first calls the
+//! ticket endpoint ([`AUTH_TICKET_PATH`]), where the server authenticates
+//! the request the usual way (session cookie, bearer token...) and returns
+//! a signed ticket valid for a minute. The ticket travels in the stream URL
+//! ([`AUTH_QUERY_PARAM`]); the generated handler verifies it and makes the
+//! user available to the function body through [`user`]:
+//!
+//! ```ignore
+//! #[server(ws, auth)]
+//! pub async fn chat(input: ServerStream<Msg>) -> Result<ServerStream<Msg>, ServerFnError> {
+//!     let user = axum_egui::auth::user().expect("`auth` functions always have a user");
+//!     Ok(relay(user.id, input))
+//! }
+//!
+//! // Server
+//! let auth = StreamAuth::new(secret, |request: Parts| async move {
+//!     let session = sessions::from_cookies(&request.headers).await?;
+//!     Some(AuthUser::new(session.user_id).claim("role", session.role))
+//! });
+//! axum_egui::serve(app, addr).stream_auth(auth).await?;
+//! ```
+//!
+//! Tickets are stateless (HMAC-SHA256 signed), so every server sharing the
+//! secret accepts them. Build the [`StreamAuth`] with
+//! [`StreamAuth::with_secrets`] to derive the secret from the app's
+//! [`Secrets`](crate::secrets::Secrets) and follow its key rotation. They can be replayed until they expire; keep the
+//! time-to-live short. An `EventSource` reconnecting after its ticket expired
+//! is rejected, and the function should be called again.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Path of the endpoint minting tickets, served by [`StreamAuth::router`].
+pub const AUTH_TICKET_PATH: &str = "/api/auth/ticket";
+
+/// Query parameter carrying the ticket in stream URLs.
+pub const AUTH_QUERY_PARAM: &str = "auth";
+
+/// An authenticated user, as carried by a ticket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthUser {
+    /// The user id.
+    pub id: String,
+    /// Extra claims, e.g. roles.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub claims: Map<String, Value>,
+}
+
+impl AuthUser {
+    /// A user without claims.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            claims: Map::new(),
+        }
+    }
+
+    /// Add a claim. Values that fail to serialize are stored as `null`.
+    pub fn claim(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.claims
+            .insert(name.into(), serde_json::to_value(value).unwrap_or_default());
+        self
+    }
+}
+
+/// Response of the ticket endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthTicket {
+    /// The signed ticket.
+    pub ticket: String,
+    /// Seconds until the ticket expires.
+    pub expires_in: u64,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{AUTH_QUERY_PARAM, AUTH_TICKET_PATH, AuthTicket, AuthUser};
+    use crate::error::ErrorEnvelope;
+    use crate::secrets::{Keyring, Secrets, purpose};
+    use axum::Router;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+    use axum::response::{IntoResponse, Json, Response};
+    use axum::routing::post;
+    use serde::{Deserialize, Serialize};
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// Default ticket time-to-live.
+    pub const DEFAULT_TICKET_TTL: Duration = Duration::from_secs(60);
+
+    type Authenticate =
+        dyn Fn(Parts) -> Pin<Box<dyn Future<Output = Option<AuthUser>> + Send>> + Send + Sync;
+
+    /// Mints and verifies stream tickets. Cheap to clone.
+    #[derive(Clone)]
+    pub struct StreamAuth {
+        keys: Keyring,
+        ttl: Duration,
+        authenticate: Arc<Authenticate>,
+    }
+
+    impl std::fmt::Debug for StreamAuth {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("StreamAuth")
+                .field("ttl", &self.ttl)
+                .finish_non_exhaustive()
+        }
+    }
+
+    /// Why a stream request was not authenticated. Responds with
+    /// `401 Unauthorized`.
+    #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+    pub enum AuthError {
+        /// The endpoint requires a ticket and the request has none.
+        #[error("missing stream ticket")]
+        Missing,
+        /// The ticket is malformed or its signature does not match.
+        #[error("invalid stream ticket")]
+        Invalid,
+        /// The ticket has expired.
+        #[error("expired stream ticket")]
+        Expired,
+        /// No [`StreamAuth`] is installed to verify the ticket.
+        #[error("stream authentication is not configured")]
+        NotConfigured,
+    }
+
+    impl IntoResponse for AuthError {
+        fn into_response(self) -> Response {
+            let error = ErrorEnvelope::new("unauthorized", self.to_string());
+            crate::error::error_response(StatusCode::UNAUTHORIZED, error, None)
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Claims {
+        #[serde(flatten)]
+        user: AuthUser,
+        exp: u64,
+    }
+
+    impl StreamAuth {
+        /// Sign tickets with `secret` and authenticate ticket requests with
+        /// `authenticate`, which returns `None` for anonymous requests.
+        ///
+        /// Use at least 32 random bytes, shared by every server instance.
+        pub fn new<F, Fut>(secret: impl AsRef<[u8]>, authenticate: F) -> Self
+        where
+            F: Fn(Parts) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Option<AuthUser>> + Send + 'static,
+        {
+            let keys = Keyring::derive([secret.as_ref()], purpose::STREAM_AUTH);
+            Self::with_keys(keys, authenticate)
+        }
+
+        /// Like [`Self::new`], with the ticket key derived from `secrets`, so
+        /// tickets follow its key rotation.
+        pub fn with_secrets<F, Fut>(secrets: &Secrets, authenticate: F) -> Self
+        where
+            F: Fn(Parts) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Option<AuthUser>> + Send + 'static,
+        {
+            Self::with_keys(secrets.keyring(purpose::STREAM_AUTH), authenticate)
+        }
+
+        fn with_keys<F, Fut>(keys: Keyring, authenticate: F) -> Self
+        where
+            F: Fn(Parts) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = Option<AuthUser>> + Send + 'static,
+        {
+            Self {
+                keys,
+                ttl: DEFAULT_TICKET_TTL,
+                authenticate: Arc::new(move |parts| Box::pin(authenticate(parts))),
+            }
+        }
+
+        /// How long tickets stay valid (default: [`DEFAULT_TICKET_TTL`]).
+        pub fn ttl(mut self, ttl: Duration) -> Self {
+            self.ttl = ttl;
+            self
+        }
+
+        /// A ticket for `user`.
+        pub fn issue(&self, user: &AuthUser) -> AuthTicket {
+            let claims = Claims {
+                user: user.clone(),
+                exp: now() + self.ttl.as_secs(),
+            };
+            AuthTicket {
+                ticket: self
+                    .keys
+                    .sign_value(&claims)
+                    .expect("ticket claims serialize"),
+                expires_in: self.ttl.as_secs(),
+            }
+        }
+
+        /// The user of a valid, unexpired `ticket`.
+        pub fn verify(&self, ticket: &str) -> Result<AuthUser, AuthError> {
+            let claims: Claims = self.keys.verify_value(ticket).ok_or(AuthError::Invalid)?;
+            if claims.exp < now() {
+                return Err(AuthError::Expired);
+            }
+            Ok(claims.user)
+        }
+
+        /// The ticket endpoint at [`AUTH_TICKET_PATH`]: `401` for anonymous
+        /// requests, an [`AuthTicket`] otherwise.
+        pub fn router<S>(&self) -> Router<S>
+        where
+            S: Clone + Send + Sync + 'static,
+        {
+            let auth = self.clone();
+            Router::new().route(
+                AUTH_TICKET_PATH,
+                post(move |parts: Parts| {
+                    let auth = auth.clone();
+                    async move {
+                        match (auth.authenticate)(parts).await {
+                            Some(user) => Json(auth.issue(&user)).into_response(),
+                            None => StatusCode::UNAUTHORIZED.into_response(),
+                        }
+                    }
+                }),
+            )
+        }
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    /// Verify the ticket in the query string of a stream request against the
+    /// [`StreamAuth`] in its extensions, and add its [`AuthUser`] to the
+    /// extensions. Called by the handlers generated for `auth` functions.
+    pub fn authorize(parts: &mut Parts) -> Result<(), AuthError> {
+        let prefix = format!("{AUTH_QUERY_PARAM}=");
+        let ticket = parts
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(&prefix))
+            .ok_or(AuthError::Missing)?;
+        let auth = parts
+            .extensions
+            .get::<StreamAuth>()
+            .ok_or(AuthError::NotConfigured)?;
+        let user = auth.verify(ticket)?;
+        parts.extensions.insert(user);
+        Ok(())
+    }
+
+    /// The user of the current server function, if its request was
+    /// authenticated with a ticket.
+    pub fn user() -> Option<AuthUser> {
+        crate::context::current()?
+            .extensions()
+            .get::<AuthUser>()
+            .cloned()
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{AUTH_QUERY_PARAM, AUTH_TICKET_PATH, AuthTicket};
+    use crate::rpc::ServerFnError;
+
+    /// Mint a ticket and append it to the stream `url`. Generated for
+    /// streaming functions declared with `auth`.
+    pub async fn authorize_url(url: &str) -> Result<String, ServerFnError> {
+        let ticket: AuthTicket = crate::rpc::call(AUTH_TICKET_PATH, &()).await?;
+        let separator = if url.contains('?') { '&' } else { '?' };
+        Ok(format!(
+            "{url}{separator}{AUTH_QUERY_PARAM}={}",
+            crate::rpc::percent_encode(&ticket.ticket)
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::secrets::Secrets;
+    use axum::body::Body;
+    use axum::http::request::Parts;
+    use axum::http::{Request, StatusCode};
+    use axum::response::IntoResponse;
+    use base64::Engine as _;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn auth() -> StreamAuth {
+        StreamAuth::new(
+            b"0123456789abcdef0123456789abcdef",
+            |parts: Parts| async move {
+                let user = parts.headers.get("x-user")?.to_str().ok()?;
+                Some(AuthUser::new(user).claim("role", "admin"))
+            },
+        )
+    }
+
+    #[test]
+    fn tickets_round_trip_and_reject_tampering() {
+        let auth = auth();
+        let user = AuthUser::new("ada").claim("role", "admin");
+        let ticket = auth.issue(&user).ticket;
+        assert_eq!(auth.verify(&ticket).unwrap(), user);
+
+        let (payload, signature) = ticket.split_once('.').unwrap();
+        let forged = URL_SAFE_NO_PAD.encode(br#"{"id":"root","exp":99999999999}"#);
+        assert_eq!(
+            auth.verify(&format!("{forged}.{signature}")),
+            Err(AuthError::Invalid)
+        );
+        let other = StreamAuth::new(b"another secret", |_: Parts| async { None });
+        assert_eq!(other.verify(&ticket), Err(AuthError::Invalid));
+        assert_eq!(auth.verify(payload), Err(AuthError::Invalid));
+
+        // Tickets keyed from `Secrets` survive a key rotation
+        let old = Secrets::new([1; 32]).unwrap();
+        let rotated = Secrets::new([2; 32]).unwrap().previous([1; 32]).unwrap();
+        let ticket = StreamAuth::with_secrets(&old, |_: Parts| async { None })
+            .issue(&user)
+            .ticket;
+        let auth = StreamAuth::with_secrets(&rotated, |_: Parts| async { None });
+        assert_eq!(auth.verify(&ticket).unwrap(), user);
+
+        let expired = auth
+            .clone()
+            .ttl(std::time::Duration::ZERO)
+            .issue(&user)
+            .ticket;
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert_eq!(auth.verify(&expired), Err(AuthError::Expired));
+    }
+
+    #[tokio::test]
+    async fn ticket_endpoint_and_authorize() {
+        let auth = auth();
+        let app: axum::Router = auth.router();
+
+        let anonymous = Request::post(AUTH_TICKET_PATH).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(anonymous).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let request = Request::post(AUTH_TICKET_PATH)
+            .header("x-user", "ada")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let ticket: AuthTicket = serde_json::from_slice(&body).unwrap();
+
+        let stream_request = |query: &str| {
+            let mut parts = Request::get(format!("/api/chat?{query}"))
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0;
+            parts.extensions.insert(auth.clone());
+            parts
+        };
+        let mut parts = stream_request(&format!("args=%7B%7D&auth={}", ticket.ticket));
+        authorize(&mut parts).unwrap();
+        assert_eq!(parts.extensions.get::<AuthUser>().unwrap().id, "ada");
+
+        let mut parts = stream_request("args=%7B%7D");
+        assert_eq!(authorize(&mut parts), Err(AuthError::Missing));
+        assert!(parts.extensions.get::<AuthUser>().is_none());
+
+        let mut parts = stream_request("auth=bogus");
+        assert_eq!(authorize(&mut parts), Err(AuthError::Invalid));
+        assert_eq!(
+            AuthError::Invalid.into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
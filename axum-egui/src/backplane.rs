@@ -0,0 +1,314 @@
+//! Fan-out between server instances.
+//!
+//! [`LoggedTopic`](crate::topic::LoggedTopic)s and [`push`](crate::push)
+//! deliver messages to the streams connected to the instance they were
+//! published on. Behind a load balancer, clients are spread over several
+//! instances; a [`Backplane`] relays each message to the others, which
+//! deliver it to their own clients:
+//!
+//! ```ignore
+//! let backplane: Arc<dyn Backplane> = Arc::new(RedisBackplane::new(&redis_url)?);
+//! axum_egui::push::backplane(backplane.clone());
+//! let room = LoggedTopic::new(500).backplane(backplane, "room:lobby");
+//! ```
+//!
+//! Messages are relayed as JSON, tagged with the sender so it skips its own.
+//! Delivery is best effort: messages sent while an instance is disconnected
+//! from the backplane are not delivered to it, and each instance numbers the
+//! events of a topic itself, so `subscribe_since` cursors only make sense on
+//! the instance that issued them (see sticky sessions).
+//!
+//! [`MemoryBackplane`] relays within the process, e.g. for tests;
+//! `RedisBackplane` (`redis` feature) uses Redis pub/sub.
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use futures_util::{FutureExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Delay before subscribing again after the backplane connection was lost.
+pub const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Error of a [`Backplane`] operation.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum BackplaneError {
+    /// The backplane could not be reached.
+    #[error("backplane connection failed: {0}")]
+    Connection(String),
+}
+
+/// A pub/sub transport shared by all instances of the server.
+pub trait Backplane: Send + Sync + 'static {
+    /// Send `payload` to every subscriber of `channel`, on any instance,
+    /// including this one.
+    fn publish(
+        &self,
+        channel: &str,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'static, Result<(), BackplaneError>>;
+
+    /// The payloads published on `channel` from now on. The stream ends if
+    /// the connection is lost.
+    fn subscribe(
+        &self,
+        channel: &str,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Vec<u8>>, BackplaneError>>;
+}
+
+/// A message on the backplane, tagged with the [`Link`] that sent it.
+#[derive(Serialize, Deserialize)]
+struct Relayed<T> {
+    origin: u64,
+    data: T,
+}
+
+/// One side of a backplane channel: relays local messages to the other
+/// instances, in order, and hands theirs to a callback.
+pub(crate) struct Link {
+    origin: u64,
+    outgoing: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl std::fmt::Debug for Link {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Link")
+            .field("origin", &self.origin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Link {
+    /// Relay over `channel`, passing the messages of other instances to
+    /// `receive`. Spawns the relay tasks, so must be called in a runtime.
+    pub(crate) fn new<T>(
+        backplane: Arc<dyn Backplane>,
+        channel: impl Into<String>,
+        mut receive: impl FnMut(T) + Send + 'static,
+    ) -> Self
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let channel = channel.into();
+        let mut origin = [0u8; 8];
+        getrandom::fill(&mut origin).expect("system random number generator");
+        let origin = u64::from_le_bytes(origin);
+
+        let (outgoing, mut queue) = mpsc::unbounded_channel::<Vec<u8>>();
+        tokio::spawn({
+            let backplane = backplane.clone();
+            let channel = channel.clone();
+            async move {
+                while let Some(payload) = queue.recv().await {
+                    if let Err(e) = backplane.publish(&channel, payload).await {
+                        tracing::warn!(channel = %channel, "dropping backplane message: {e}");
+                    }
+                }
+            }
+        });
+        tokio::spawn(async move {
+            loop {
+                match backplane.subscribe(&channel).await {
+                    Ok(mut payloads) => {
+                        while let Some(payload) = payloads.next().await {
+                            match serde_json::from_slice::<Relayed<T>>(&payload) {
+                                Ok(relayed) if relayed.origin == origin => {}
+                                Ok(relayed) => receive(relayed.data),
+                                Err(e) => tracing::warn!(
+                                    channel = %channel,
+                                    "skipping undecodable backplane message: {e}"
+                                ),
+                            }
+                        }
+                        tracing::warn!(channel = %channel, "backplane subscription ended");
+                    }
+                    Err(e) => tracing::warn!(channel = %channel, "backplane subscribe failed: {e}"),
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+        Self { origin, outgoing }
+    }
+
+    /// Relay `data` to the other instances.
+    pub(crate) fn send<T: Serialize>(&self, data: &T) {
+        let relayed = Relayed {
+            origin: self.origin,
+            data,
+        };
+        match serde_json::to_vec(&relayed) {
+            Ok(payload) => {
+                let _ = self.outgoing.send(payload);
+            }
+            Err(e) => tracing::error!("failed to encode backplane message: {e}"),
+        }
+    }
+}
+
+/// A [`Backplane`] within the process. Cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryBackplane {
+    channels: Arc<Mutex<HashMap<String, broadcast::Sender<Vec<u8>>>>>,
+}
+
+impl MemoryBackplane {
+    /// An empty backplane.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sender(&self, channel: &str) -> broadcast::Sender<Vec<u8>> {
+        self.channels
+            .lock()
+            .unwrap()
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(1024).0)
+            .clone()
+    }
+}
+
+impl Backplane for MemoryBackplane {
+    fn publish(
+        &self,
+        channel: &str,
+        payload: Vec<u8>,
+    ) -> BoxFuture<'static, Result<(), BackplaneError>> {
+        let _ = self.sender(channel).send(payload);
+        std::future::ready(Ok(())).boxed()
+    }
+
+    fn subscribe(
+        &self,
+        channel: &str,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Vec<u8>>, BackplaneError>> {
+        let receiver = self.sender(channel).subscribe();
+        let payloads = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(payload) => return Some((payload, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        std::future::ready(Ok(payloads.boxed())).boxed()
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use redis_backplane::RedisBackplane;
+
+#[cfg(feature = "redis")]
+mod redis_backplane {
+    use super::{Backplane, BackplaneError};
+    use futures_util::future::BoxFuture;
+    use futures_util::stream::BoxStream;
+    use futures_util::{FutureExt, StreamExt};
+    use redis::AsyncCommands;
+    use redis::aio::MultiplexedConnection;
+    use std::sync::Arc;
+    use tokio::sync::OnceCell;
+
+    /// A [`Backplane`] over Redis pub/sub. Cheap to clone.
+    #[derive(Clone)]
+    pub struct RedisBackplane {
+        client: redis::Client,
+        connection: Arc<OnceCell<MultiplexedConnection>>,
+    }
+
+    impl std::fmt::Debug for RedisBackplane {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisBackplane")
+                .field("server", &self.client.get_connection_info().addr)
+                .finish_non_exhaustive()
+        }
+    }
+
+    fn connection_error(e: redis::RedisError) -> BackplaneError {
+        BackplaneError::Connection(e.to_string())
+    }
+
+    impl RedisBackplane {
+        /// A backplane on the Redis server at `url`, e.g.
+        /// `redis://127.0.0.1:6379`. Connects on first use.
+        pub fn new(url: &str) -> Result<Self, BackplaneError> {
+            Ok(Self {
+                client: redis::Client::open(url).map_err(connection_error)?,
+                connection: Arc::default(),
+            })
+        }
+    }
+
+    impl Backplane for RedisBackplane {
+        fn publish(
+            &self,
+            channel: &str,
+            payload: Vec<u8>,
+        ) -> BoxFuture<'static, Result<(), BackplaneError>> {
+            let this = self.clone();
+            let channel = channel.to_string();
+            async move {
+                let mut connection = this
+                    .connection
+                    .get_or_try_init(|| this.client.get_multiplexed_async_connection())
+                    .await
+                    .map_err(connection_error)?
+                    .clone();
+                connection
+                    .publish::<_, _, ()>(channel, payload)
+                    .await
+                    .map_err(connection_error)
+            }
+            .boxed()
+        }
+
+        fn subscribe(
+            &self,
+            channel: &str,
+        ) -> BoxFuture<'static, Result<BoxStream<'static, Vec<u8>>, BackplaneError>> {
+            let client = self.client.clone();
+            let channel = channel.to_string();
+            async move {
+                let mut pubsub = client.get_async_pubsub().await.map_err(connection_error)?;
+                pubsub.subscribe(channel).await.map_err(connection_error)?;
+                let payloads = pubsub
+                    .into_on_message()
+                    .map(|message| message.get_payload_bytes().to_vec());
+                Ok(payloads.boxed())
+            }
+            .boxed()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn links_relay_to_other_instances_only() {
+        let backplane: Arc<dyn Backplane> = Arc::new(MemoryBackplane::new());
+        let (a_tx, mut a_rx) = mpsc::unbounded_channel::<String>();
+        let (b_tx, mut b_rx) = mpsc::unbounded_channel::<String>();
+        let a = Link::new(backplane.clone(), "test", move |msg| {
+            let _ = a_tx.send(msg);
+        });
+        let b = Link::new(backplane, "test", move |msg| {
+            let _ = b_tx.send(msg);
+        });
+        // Let both subscribe
+        for _ in 0..4 {
+            tokio::task::yield_now().await;
+        }
+
+        a.send(&"from a");
+        b.send(&"from b");
+        assert_eq!(b_rx.recv().await.unwrap(), "from a");
+        assert_eq!(a_rx.recv().await.unwrap(), "from b");
+        assert!(a_rx.try_recv().is_err() && b_rx.try_recv().is_err());
+    }
+}
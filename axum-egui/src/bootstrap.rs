@@ -0,0 +1,384 @@
+//! The frontend entry point: read the initial state and start eframe.
+//!
+//! Every frontend starts the same way: read the state the server injected
+//! into the page, find the canvas, start eframe on it and swap the loading
+//! text for an error if that fails. [`bootstrap`] does all of it for an app
+//! that is its own state:
+//!
+//! ```ignore
+//! #[wasm_bindgen(start)]
+//! pub fn start() {
+//!     axum_egui::bootstrap::bootstrap::<MyApp>();
+//! }
+//! ```
+//!
+//! [`Bootstrap`] builds the app from a separate state with
+//! [`Bootstrap::with_app`], and takes custom [`eframe::WebOptions`], another
+//! canvas, a fixed scale, the preferred [`Renderer`] (with the `wgpu`
+//! feature) and a hook to adjust the state before the app starts. With
+//! `#[axum_egui::main]` a function returning it becomes the entry point,
+//! without a direct `wasm-bindgen` dependency:
+//!
+//! ```ignore
+//! #[axum_egui::main]
+//! fn main() -> Bootstrap<AppState> {
+//!     Bootstrap::with_app(|state, cc| MyApp::new(state, &cc.egui_ctx))
+//!         .canvas_id("app")
+//!         .web_options(eframe::WebOptions { dithering: false, ..Default::default() })
+//!         .pixels_per_point(2.0)
+//!         .renderer(Renderer::WebGl)
+//!         .map_state(|mut state| {
+//!             state.started_at = js_sys::Date::now();
+//!             state
+//!         })
+//! }
+//! ```
+//!
+//! Several apps can run on one page, e.g. the main app and a floating
+//! support widget, each in its own canvas and with its own state, read from
+//! a [slot](crate::slots) of the page. The entry point starts them all when
+//! it returns a tuple:
+//!
+//! ```ignore
+//! // Server
+//! App::new(state).slot("support", &SupportState::for_user(&user))
+//!
+//! // Client
+//! #[axum_egui::main]
+//! fn main() -> (Bootstrap<AppState>, Bootstrap<SupportWidget>) {
+//!     (
+//!         Bootstrap::with_app(|state, _cc| MyApp::new(state)),
+//!         Bootstrap::new().canvas_id("support_canvas").state_slot("support"),
+//!     )
+//! }
+//! ```
+//!
+//! Each app has its own egui context, so the streams and server function
+//! calls it makes repaint only itself. The default `index.html` of
+//! `axum-egui-build` gets more canvases from `Frontend::canvas`.
+//!
+//! A page without a state element starts the app from the default state; a
+//! state that doesn't deserialize is logged to the console, then does the
+//! same. Panics are [reported](crate::crash) to the server, under the
+//! app's [`version`](Bootstrap::version), and replace the app with a crash
+//! screen; [`crash_screen`](Bootstrap::crash_screen) turns this off.
+
+use crate::slots::{self, ReadStateError};
+use serde::de::DeserializeOwned;
+use wasm_bindgen::JsCast;
+
+#[doc(hidden)]
+pub use wasm_bindgen;
+
+/// The id of the canvas eframe runs in, as in the `index.html` template.
+pub const CANVAS_ID: &str = "the_canvas_id";
+
+/// The id of the element shown until the app has started.
+pub const LOADING_ID: &str = "loading_text";
+
+/// What the loading element shows when the app fails to start.
+const CRASH_HTML: &str = "<p>The app has crashed. See the developer console for details.</p>";
+
+type CreateApp<S> = Box<dyn FnOnce(S, &eframe::CreationContext<'_>) -> Box<dyn eframe::App>>;
+
+type MapState<S> = Box<dyn FnOnce(S) -> S>;
+
+/// The graphics API an app on eframe's wgpu renderer uses.
+#[cfg(feature = "wgpu")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Renderer {
+    /// WebGPU where the browser supports it, WebGL elsewhere.
+    #[default]
+    WebGpu,
+    /// WebGL only, e.g. to avoid WebGPU driver bugs or for consistent
+    /// rendering across browsers.
+    WebGl,
+}
+
+#[cfg(feature = "wgpu")]
+impl Renderer {
+    fn backends(self) -> eframe::wgpu::Backends {
+        match self {
+            Renderer::WebGpu => eframe::wgpu::Backends::BROWSER_WEBGPU | eframe::wgpu::Backends::GL,
+            Renderer::WebGl => eframe::wgpu::Backends::GL,
+        }
+    }
+}
+
+/// How to start an app from the initial state `S`: the app built from it,
+/// its canvas, [`eframe::WebOptions`] and a hook on the state.
+pub struct Bootstrap<S> {
+    create_app: CreateApp<S>,
+    canvas_id: String,
+    web_options: eframe::WebOptions,
+    pixels_per_point: Option<f32>,
+    #[cfg(feature = "wgpu")]
+    renderer: Option<Renderer>,
+    map_state: Option<MapState<S>>,
+    state_slot: Option<String>,
+    version: String,
+    crash_screen: bool,
+    report_capabilities: bool,
+    restore_queries: bool,
+}
+
+impl<S> Default for Bootstrap<S>
+where
+    S: eframe::App + DeserializeOwned + Default + 'static,
+{
+    fn default() -> Self {
+        Self::with_app(|state, _cc| state)
+    }
+}
+
+impl<S> Bootstrap<S>
+where
+    S: eframe::App + DeserializeOwned + Default + 'static,
+{
+    /// Start the state as the app, in [`CANVAS_ID`] with the default
+    /// [`eframe::WebOptions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Bootstrap<S>
+where
+    S: DeserializeOwned + Default + 'static,
+{
+    /// Start the app `create_app` builds from the state, in [`CANVAS_ID`]
+    /// with the default [`eframe::WebOptions`].
+    pub fn with_app<A>(
+        create_app: impl FnOnce(S, &eframe::CreationContext<'_>) -> A + 'static,
+    ) -> Self
+    where
+        A: eframe::App + 'static,
+    {
+        Self {
+            create_app: Box::new(
+                move |state: S, cc: &eframe::CreationContext<'_>| -> Box<dyn eframe::App> {
+                    Box::new(create_app(state, cc))
+                },
+            ),
+            canvas_id: CANVAS_ID.to_string(),
+            web_options: eframe::WebOptions::default(),
+            pixels_per_point: None,
+            #[cfg(feature = "wgpu")]
+            renderer: None,
+            map_state: None,
+            state_slot: None,
+            version: String::new(),
+            crash_screen: true,
+            report_capabilities: false,
+            restore_queries: false,
+        }
+    }
+
+    /// Run in the canvas with this id instead of [`CANVAS_ID`]. Only the
+    /// app in [`CANVAS_ID`] removes the loading element [`LOADING_ID`].
+    pub fn canvas_id(mut self, id: impl Into<String>) -> Self {
+        self.canvas_id = id.into();
+        self
+    }
+
+    /// Start eframe with these options. [`pixels_per_point`](Self::pixels_per_point)
+    /// and [`renderer`](Self::renderer) apply on top of them.
+    pub fn web_options(mut self, options: eframe::WebOptions) -> Self {
+        self.web_options = options;
+        self
+    }
+
+    /// Start with this many physical pixels per point instead of the
+    /// display's device pixel ratio. Users can still zoom from there.
+    pub fn pixels_per_point(mut self, pixels_per_point: f32) -> Self {
+        self.pixels_per_point = Some(pixels_per_point);
+        self
+    }
+
+    /// Render with `renderer`. Without it, the backends of the
+    /// [`web_options`](Self::web_options) are used, by default WebGPU with a
+    /// WebGL fallback.
+    #[cfg(feature = "wgpu")]
+    pub fn renderer(mut self, renderer: Renderer) -> Self {
+        self.renderer = Some(renderer);
+        self
+    }
+
+    /// Read the state from the [slot](crate::slots) `name` instead of the
+    /// main state of the page, for a second app on it.
+    pub fn state_slot(mut self, name: impl Into<String>) -> Self {
+        self.state_slot = Some(name.into());
+        self
+    }
+
+    /// Adjust the initial state before the app starts, e.g. to fill in
+    /// fields the server doesn't send. Replaces an earlier hook.
+    pub fn map_state(mut self, f: impl FnOnce(S) -> S + 'static) -> Self {
+        self.map_state = Some(Box::new(f));
+        self
+    }
+
+    /// The app's version in crash reports, e.g. `env!("CARGO_PKG_VERSION")`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Whether to [install](crate::crash::install) the crash reporter and
+    /// screen (default: yes). Turn it off to set a panic hook of your own.
+    pub fn crash_screen(mut self, enabled: bool) -> Self {
+        self.crash_screen = enabled;
+        self
+    }
+
+    /// [Report](crate::capabilities::report) the device's graphics and
+    /// memory capabilities to the server once the app has started, so the
+    /// next page load can be lighter on a low-end device.
+    pub fn report_capabilities(mut self) -> Self {
+        self.report_capabilities = true;
+        self
+    }
+
+    /// Read the [persisted query results](crate::query#persistence) of
+    /// earlier sessions before the app starts, so its first frame can show
+    /// them.
+    pub fn restore_queries(mut self) -> Self {
+        self.restore_queries = true;
+        self
+    }
+
+    /// Start the app once the current task yields.
+    pub fn start(mut self) {
+        if self.crash_screen {
+            crate::crash::install(std::mem::take(&mut self.version));
+        }
+        wasm_bindgen_futures::spawn_local(self.run());
+    }
+
+    async fn run(mut self) {
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .expect("no document");
+
+        if self.restore_queries {
+            if let Err(e) = crate::query::load_persisted().await {
+                web_sys::console::warn_1(&format!("query cache not restored: {e}").into());
+            }
+        }
+
+        let state = match &self.state_slot {
+            Some(name) => slots::read_state::<S>(name),
+            None => slots::read_initial_state::<S>(),
+        };
+        let state = match state {
+            Ok(state) => state,
+            Err(ReadStateError::Missing { .. }) => S::default(),
+            Err(e) => {
+                web_sys::console::error_1(&e.to_string().into());
+                S::default()
+            }
+        };
+        let state = match self.map_state {
+            Some(map_state) => map_state(state),
+            None => state,
+        };
+
+        let canvas = document
+            .get_element_by_id(&self.canvas_id)
+            .unwrap_or_else(|| panic!("the page has no #{} element", self.canvas_id))
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap_or_else(|_| panic!("#{} is not a canvas", self.canvas_id));
+
+        #[cfg(feature = "wgpu")]
+        if let (Some(renderer), eframe::egui_wgpu::WgpuSetup::CreateNew(setup)) =
+            (self.renderer, &mut self.web_options.wgpu_options.wgpu_setup)
+        {
+            setup.instance_descriptor.backends = renderer.backends();
+        }
+
+        let create_app = self.create_app;
+        let pixels_per_point = self.pixels_per_point;
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                self.web_options,
+                Box::new(move |cc| {
+                    if let Some(pixels_per_point) = pixels_per_point {
+                        // Before the first frame egui doesn't know the
+                        // display's own scale yet
+                        let native = web_sys::window().map_or(1.0, |w| w.device_pixel_ratio());
+                        cc.egui_ctx
+                            .set_zoom_factor(pixels_per_point / native as f32);
+                    }
+                    Ok(create_app(state, cc))
+                }),
+            )
+            .await;
+
+        if result.is_ok() && self.report_capabilities {
+            wasm_bindgen_futures::spawn_local(async {
+                if let Err(e) = crate::capabilities::report().await {
+                    web_sys::console::warn_1(&format!("capabilities not reported: {e}").into());
+                }
+            });
+        }
+
+        let loading = (self.canvas_id == CANVAS_ID)
+            .then(|| document.get_element_by_id(LOADING_ID))
+            .flatten();
+        match result {
+            Ok(()) => {
+                if let Some(loading) = loading {
+                    loading.remove();
+                }
+            }
+            Err(e) => {
+                if let Some(loading) = loading {
+                    loading.set_inner_html(CRASH_HTML);
+                }
+                panic!("failed to start eframe: {e:?}");
+            }
+        }
+    }
+}
+
+/// What an `#[axum_egui::main]` function returns: a [`Bootstrap`], or a
+/// tuple of up to four for several apps on the page.
+pub trait Launch {
+    /// Start the apps.
+    fn launch(self);
+}
+
+impl<S> Launch for Bootstrap<S>
+where
+    S: DeserializeOwned + Default + 'static,
+{
+    fn launch(self) {
+        self.start();
+    }
+}
+
+macro_rules! impl_launch_for_tuples {
+    ($(($($app:ident),+)),+) => {
+        $(
+            impl<$($app: Launch),+> Launch for ($($app,)+) {
+                #[allow(non_snake_case)]
+                fn launch(self) {
+                    let ($($app,)+) = self;
+                    $($app.launch();)+
+                }
+            }
+        )+
+    };
+}
+
+impl_launch_for_tuples!((A, B), (A, B, C), (A, B, C, D));
+
+/// Start `A` from the page's initial state with the defaults of
+/// [`Bootstrap`].
+pub fn bootstrap<A>()
+where
+    A: eframe::App + DeserializeOwned + Default + 'static,
+{
+    Bootstrap::<A>::new().start();
+}
@@ -0,0 +1,284 @@
+//! Server-side result cache for `#[server(cache = "...")]` functions.
+//!
+//! A cached function answers calls with the same arguments from memory for
+//! its time to live. With a `stale` window on top, it keeps answering from
+//! memory for that long after the value expired (stale-while-revalidate):
+//! the caller gets the old value right away, and the function runs once in
+//! the background to replace it.
+//!
+//! ```ignore
+//! // Fresh for 30 seconds, then served stale for up to 5 minutes while
+//! // a background call refreshes it
+//! #[server(get, cache = "30s", stale = "5m")]
+//! pub async fn revenue(quarter: u32) -> Result<Revenue, ServerFnError> {
+//!     db::revenue(quarter).await
+//! }
+//! ```
+//!
+//! Dashboards can follow the refreshed values instead of polling: [`updates`]
+//! streams every value a call stores for the same arguments, so open pages
+//! converge as soon as any of them triggered a refresh:
+//!
+//! ```ignore
+//! #[server(sse)]
+//! pub async fn revenue_updates(quarter: u32) -> Result<ServerStream<Revenue>, ServerFnError> {
+//!     Ok(axum_egui::cache::updates("revenue", &RevenueArgs { quarter }))
+//! }
+//! ```
+//!
+//! Calls are keyed like [`coalesce`](crate::coalesce): the function name and
+//! its JSON-encoded arguments. Errors are not cached. As with `coalesce`,
+//! the result must not depend on who is calling: one user's cached value is
+//! served to everyone. The cache is in memory, per server instance; expired
+//! entries are dropped as new ones are stored.
+//!
+//! Answers from the cache are counted in the `axum_egui_cache_hits_total`
+//! metric.
+
+use crate::coalesce::FlightKey;
+use crate::rpc::ServerFnError;
+use crate::stream::ServerStream;
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+pub(crate) const CACHE_HITS: &str = "axum_egui_cache_hits_total";
+
+/// Refreshed values buffered for an [`updates`] stream that is not reading
+/// them.
+pub const UPDATES_BUFFER: usize = 16;
+
+/// How long a function's results are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachePolicy {
+    ttl: Duration,
+    stale: Duration,
+}
+
+impl CachePolicy {
+    /// Answer from the cache for `ttl` after a value was stored.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            stale: Duration::ZERO,
+        }
+    }
+
+    /// Keep answering with the expired value for `stale` after `ttl` ran
+    /// out, refreshing it in the background.
+    pub fn stale(mut self, stale: Duration) -> Self {
+        self.stale = stale;
+        self
+    }
+}
+
+struct Entry {
+    /// The cached `T`.
+    value: Box<dyn Any + Send + Sync>,
+    stored_at: Instant,
+    /// How long the entry is kept at all: `ttl + stale`.
+    keep: Duration,
+    refreshing: bool,
+}
+
+#[derive(Default)]
+struct Cache {
+    entries: HashMap<FlightKey, Entry>,
+    /// `broadcast::Sender<T>`s of the [`updates`] streams.
+    updates: HashMap<FlightKey, Box<dyn Any + Send + Sync>>,
+}
+
+fn cache() -> std::sync::MutexGuard<'static, Cache> {
+    static CACHE: OnceLock<Mutex<Cache>> = OnceLock::new();
+    CACHE
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Answer from the cache if `key` has a value under `policy`, else run
+/// `call` and store its result. A stale value is returned as is, with
+/// `call` spawned to refresh it unless a refresh is already running.
+///
+/// Used by the handlers `#[server(cache = "...")]` generates.
+pub async fn run<T, F, Fut>(
+    key: Option<FlightKey>,
+    policy: CachePolicy,
+    call: F,
+) -> Result<T, ServerFnError>
+where
+    T: Clone + Send + Sync + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ServerFnError>> + Send + 'static,
+{
+    let Some(key) = key else {
+        return call().await;
+    };
+    let cached = {
+        let mut cache = cache();
+        cache.entries.get_mut(&key).and_then(|entry| {
+            let value = entry.value.downcast_ref::<T>()?.clone();
+            let age = entry.stored_at.elapsed();
+            if age < policy.ttl {
+                Some((value, false))
+            } else if age < policy.ttl + policy.stale {
+                let refresh = !entry.refreshing;
+                entry.refreshing = true;
+                Some((value, refresh))
+            } else {
+                None
+            }
+        })
+    };
+
+    match cached {
+        Some((value, refresh)) => {
+            crate::metrics::counter(CACHE_HITS).inc();
+            if refresh {
+                let refresh = call();
+                tokio::spawn(async move {
+                    match refresh.await {
+                        Ok(value) => store(key, policy, value),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "failed to refresh a cached result");
+                            if let Some(entry) = cache().entries.get_mut(&key) {
+                                entry.refreshing = false;
+                            }
+                        }
+                    }
+                });
+            }
+            Ok(value)
+        }
+        None => {
+            let value = call().await?;
+            store(key, policy, value.clone());
+            Ok(value)
+        }
+    }
+}
+
+/// Store `value`, send it to the [`updates`] streams of `key`, and drop
+/// entries that have expired for good.
+fn store<T: Clone + Send + Sync + 'static>(key: FlightKey, policy: CachePolicy, value: T) {
+    let mut cache = cache();
+    let now = Instant::now();
+    cache
+        .entries
+        .retain(|_, entry| now.duration_since(entry.stored_at) < entry.keep);
+    let sent = cache
+        .updates
+        .get(&key)
+        .and_then(|sender| sender.downcast_ref::<broadcast::Sender<T>>())
+        .map(|sender| sender.send(value.clone()).is_ok());
+    if sent == Some(false) {
+        // Every stream of this key was dropped
+        cache.updates.remove(&key);
+    }
+    cache.entries.insert(
+        key,
+        Entry {
+            value: Box::new(value),
+            stored_at: now,
+            keep: policy.ttl + policy.stale,
+            refreshing: false,
+        },
+    );
+}
+
+/// The values stored from now on for calls of `function` with `args`,
+/// whether by a call missing the cache or by a background refresh.
+///
+/// A stream falling more than [`UPDATES_BUFFER`] values behind yields
+/// [`ServerFnError::Lagged`] and carries on with the newest ones.
+pub fn updates<T>(function: &'static str, args: &impl Serialize) -> ServerStream<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let Some(key) = crate::coalesce::key(function, args) else {
+        return ServerStream::empty();
+    };
+    let mut cache = cache();
+    let existing = cache
+        .updates
+        .get(&key)
+        .and_then(|sender| sender.downcast_ref::<broadcast::Sender<T>>())
+        .map(broadcast::Sender::subscribe);
+    let receiver = existing.unwrap_or_else(|| {
+        let (sender, receiver) = broadcast::channel(UPDATES_BUFFER);
+        cache.updates.insert(key, Box::new(sender));
+        receiver
+    });
+    ServerStream::from_broadcast(receiver)
+}
+
+/// Drop the cached result of `function` for `args`, so the next call runs
+/// it, e.g. after a write the result depends on.
+pub fn invalidate(function: &'static str, args: &impl Serialize) {
+    if let Some(key) = crate::coalesce::key(function, args) {
+        cache().entries.remove(&key);
+    }
+}
+
+/// Drop every cached result.
+pub fn clear() {
+    cache().entries.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A cached call of `function` returning how often it ran.
+    fn counted(
+        function: &'static str,
+        policy: CachePolicy,
+        runs: &Arc<AtomicU32>,
+    ) -> impl Future<Output = Result<u32, ServerFnError>> {
+        let runs = runs.clone();
+        run(
+            crate::coalesce::key(function, &()),
+            policy,
+            move || async move { Ok(runs.fetch_add(1, Ordering::SeqCst) + 1) },
+        )
+    }
+
+    #[tokio::test]
+    async fn fresh_results_are_served_from_the_cache() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let policy = CachePolicy::new(Duration::from_millis(50));
+        assert_eq!(counted("cache_fresh", policy, &runs).await.unwrap(), 1);
+        assert_eq!(counted("cache_fresh", policy, &runs).await.unwrap(), 1);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert_eq!(counted("cache_fresh", policy, &runs).await.unwrap(), 2);
+
+        invalidate("cache_fresh", &());
+        assert_eq!(counted("cache_fresh", policy, &runs).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn stale_results_are_served_while_refreshing() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let policy = CachePolicy::new(Duration::from_millis(20)).stale(Duration::from_secs(60));
+        let mut updates = updates::<u32>("cache_swr", &());
+
+        assert_eq!(counted("cache_swr", policy, &runs).await.unwrap(), 1);
+        assert_eq!(updates.next().await.unwrap().unwrap(), 1);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        // Stale: the old value now, the refreshed one to subscribers
+        assert_eq!(counted("cache_swr", policy, &runs).await.unwrap(), 1);
+        assert_eq!(counted("cache_swr", policy, &runs).await.unwrap(), 1);
+        assert_eq!(updates.next().await.unwrap().unwrap(), 2);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(counted("cache_swr", policy, &runs).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,620 @@
+//! Gradual rollouts of rewritten server functions.
+//!
+//! A [`Canary`] serves one endpoint with two implementations: the stable
+//! function and its rewrite, each a `#[server]` function with the same
+//! arguments and result. A share of the calls goes to the rewrite, raised as
+//! it proves itself, while clients keep calling the stable path:
+//!
+//! ```ignore
+//! #[server]
+//! pub async fn search(q: String) -> Result<Vec<Hit>, ServerFnError> { ... }
+//!
+//! #[server]
+//! pub async fn search_v2(q: String) -> Result<Vec<Hit>, ServerFnError> { ... }
+//!
+//! let canary = Canary::new("my_api::search", "my_api::search_v2")
+//!     .percent(5)
+//!     .sticky(|request: &Parts| session_id(&request.headers));
+//! let app = Router::new().route(SearchArgs::PATH, canary.route(search_handler, search_v2_handler));
+//!
+//! // Later, e.g. from an admin server function
+//! axum_egui::canary::find("my_api::search").unwrap().set_percent(50);
+//! ```
+//!
+//! Both functions are named by module path and name, and looked up in the
+//! [`registry`](crate::registry), which gives the split its path and method.
+//! They must be served the same way: same kind, `auth`, `post_params`,
+//! required headers and content types, so the canary's share of the calls
+//! doesn't skip a check of the stable function. Calls are assigned, in
+//! order:
+//!
+//! 1. by the [`VARIANT_HEADER`] of the request, `stable` or `canary`, if
+//!    [`Canary::allow_override`] is set, so testers and smoke tests can pick
+//!    one;
+//! 2. by the [`Canary::select`] rule, e.g. beta users always get the
+//!    rewrite;
+//! 3. by the percentage: evenly spread across calls, or by a hash of the
+//!    [`Canary::sticky`] key so a user sees the same variant on every call.
+//!
+//! Responses carry the [`VARIANT_HEADER`] of the variant that served them.
+//! Calls, server errors and latency are counted per variant, read with
+//! [`Canary::stats`] or in [`render_prometheus`](crate::metrics::render_prometheus):
+//!
+//! | Name | Type | Meaning |
+//! |------|------|---------|
+//! | `axum_egui_canary_calls_total` | counter | Calls served, by `endpoint` and `variant` |
+//! | `axum_egui_canary_errors_total` | counter | Calls answered with a 5xx status |
+//! | `axum_egui_canary_duration_seconds_total` | counter | Time spent answering them |
+
+use crate::registry::{RouteKind, ServerFnRoute};
+use axum::extract::{Request, State};
+use axum::handler::Handler;
+use axum::http::HeaderValue;
+use axum::http::request::Parts;
+use axum::response::Response;
+use axum::routing::MethodRouter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+/// Header picking the variant of a call (`stable` or `canary`) if the
+/// [`Canary`] allows it, and naming the one that served it in responses.
+pub const VARIANT_HEADER: &str = "x-server-fn-variant";
+
+/// One of the two implementations of a [`Canary`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// The implementation in service.
+    Stable,
+    /// The rewrite being rolled out.
+    Canary,
+}
+
+impl Variant {
+    /// The [`VARIANT_HEADER`] value and metric label of the variant.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Variant::Stable => "stable",
+            Variant::Canary => "canary",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Variant::Stable => 0,
+            Variant::Canary => 1,
+        }
+    }
+
+    /// Parse a [`VARIANT_HEADER`] value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "stable" => Some(Variant::Stable),
+            "canary" => Some(Variant::Canary),
+            _ => None,
+        }
+    }
+}
+
+/// Calls served by one variant of a [`Canary`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VariantStats {
+    /// The server function implementing the variant.
+    pub function: String,
+    /// Calls served since startup.
+    pub calls: u64,
+    /// Calls answered with a 5xx status.
+    pub errors: u64,
+    /// Mean time to answer a call, in milliseconds; 0 without calls.
+    pub mean_latency_ms: f64,
+}
+
+/// Calls served by both variants of a [`Canary`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CanaryStats {
+    /// The API path of the endpoint.
+    pub path: String,
+    /// Percentage of calls going to the canary.
+    pub percent: u8,
+    /// The stable implementation.
+    pub stable: VariantStats,
+    /// The canary.
+    pub canary: VariantStats,
+}
+
+type Select = Arc<dyn Fn(&Parts) -> Option<Variant> + Send + Sync>;
+type Sticky = Arc<dyn Fn(&Parts) -> Option<String> + Send + Sync>;
+
+#[derive(Debug, Default)]
+struct Counts {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    micros: AtomicU64,
+}
+
+impl Counts {
+    fn record(&self, response: &Response, started: Instant) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if response.status().is_server_error() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let micros = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+        self.micros.fetch_add(micros, Ordering::Relaxed);
+    }
+
+    fn stats(&self, function: &str) -> VariantStats {
+        let calls = self.calls.load(Ordering::Relaxed);
+        let micros = self.micros.load(Ordering::Relaxed);
+        VariantStats {
+            function: function.to_string(),
+            calls,
+            errors: self.errors.load(Ordering::Relaxed),
+            mean_latency_ms: if calls == 0 {
+                0.0
+            } else {
+                micros as f64 / calls as f64 / 1000.0
+            },
+        }
+    }
+}
+
+struct Inner {
+    stable: &'static str,
+    canary: &'static str,
+    route: &'static ServerFnRoute,
+    percent: AtomicU8,
+    allow_override: bool,
+    select: Option<Select>,
+    sticky: Option<Sticky>,
+    /// Calls assigned by percentage, spreading them evenly.
+    spread: AtomicU64,
+    counts: [Counts; 2],
+}
+
+/// A split of the calls to a server function between it and a rewrite.
+///
+/// Cheap to clone; clones share the percentage and counts.
+#[derive(Clone)]
+pub struct Canary {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for Canary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Canary")
+            .field("stable", &self.inner.stable)
+            .field("canary", &self.inner.canary)
+            .field("percent", &self.current_percent())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Canary {
+    /// A split of the calls to the server function `stable` with the
+    /// function `canary`, both named by module path and name (e.g.
+    /// `my_api::search`), sending none to it until
+    /// [`percent`](Self::percent) is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either function isn't in the [`registry`](crate::registry)
+    /// or they are served differently (e.g. one with `get`, or only one with
+    /// `auth`).
+    pub fn new(stable: &'static str, canary: &'static str) -> Self {
+        let find = |name: &str| {
+            crate::registry::find_qualified(name).unwrap_or_else(|| {
+                panic!("no server function `{name}` is registered (name it as `module::function`)")
+            })
+        };
+        let (route, canary_route) = (find(stable), find(canary));
+        let differences: Vec<&str> = [
+            ("kind", route.kind != canary_route.kind),
+            ("auth", route.auth != canary_route.auth),
+            ("post_params", route.post_params != canary_route.post_params),
+            ("required headers", route.headers != canary_route.headers),
+            (
+                "accepted content types",
+                route.accepts != canary_route.accepts,
+            ),
+            (
+                "response content types",
+                route.produces != canary_route.produces,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(what, differs)| differs.then_some(what))
+        .collect();
+        assert!(
+            differences.is_empty(),
+            "`{stable}` and its canary `{canary}` must be served the same way, \
+             but their {} differ",
+            differences.join(", ")
+        );
+        Self {
+            inner: Arc::new(Inner {
+                stable,
+                canary,
+                route,
+                percent: AtomicU8::new(0),
+                allow_override: false,
+                select: None,
+                sticky: None,
+                spread: AtomicU64::new(0),
+                counts: Default::default(),
+            }),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut Inner {
+        Arc::get_mut(&mut self.inner).expect("configure a canary before cloning it")
+    }
+
+    /// Send `percent` of the calls (0 to 100) to the canary.
+    pub fn percent(mut self, percent: u8) -> Self {
+        self.inner_mut().percent = AtomicU8::new(percent.min(100));
+        self
+    }
+
+    /// Let calls pick their variant with the [`VARIANT_HEADER`], e.g. for
+    /// smoke tests of the canary before any traffic goes to it. Off by
+    /// default, as any client could then reach the rewrite; a
+    /// [`select`](Self::select) rule checking who is calling is safer for
+    /// production.
+    pub fn allow_override(mut self, allow: bool) -> Self {
+        self.inner_mut().allow_override = allow;
+        self
+    }
+
+    /// Pick the variant of some calls by a rule, e.g. the canary for beta
+    /// users; calls it returns `None` for are split by percentage.
+    pub fn select<F>(mut self, select: F) -> Self
+    where
+        F: Fn(&Parts) -> Option<Variant> + Send + Sync + 'static,
+    {
+        self.inner_mut().select = Some(Arc::new(select));
+        self
+    }
+
+    /// Split calls by a hash of `key` (a user or session id), so each key
+    /// always gets the same variant at a given percentage, and raising it
+    /// only moves keys to the canary.
+    pub fn sticky<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+    {
+        self.inner_mut().sticky = Some(Arc::new(key));
+        self
+    }
+
+    /// Change the percentage of calls going to the canary, while serving.
+    pub fn set_percent(&self, percent: u8) {
+        self.inner
+            .percent
+            .store(percent.min(100), Ordering::Relaxed);
+    }
+
+    /// The percentage of calls going to the canary.
+    pub fn current_percent(&self) -> u8 {
+        self.inner.percent.load(Ordering::Relaxed)
+    }
+
+    /// The variant serving a call.
+    pub fn variant(&self, request: &Parts) -> Variant {
+        let inner = &self.inner;
+        let forced = request
+            .headers
+            .get(VARIANT_HEADER)
+            .filter(|_| inner.allow_override)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Variant::parse);
+        if let Some(variant) = forced.or_else(|| inner.select.as_ref().and_then(|f| f(request))) {
+            return variant;
+        }
+        let bucket = match inner.sticky.as_ref().and_then(|key| key(request)) {
+            // FNV-1a, so keys keep their bucket across Rust releases
+            Some(key) => crate::schema::fingerprint(&format!("{}\0{key}", inner.stable)) % 100,
+            // Stride through the buckets so any run of 100 calls splits
+            // exactly
+            None => inner.spread.fetch_add(1, Ordering::Relaxed) * 37 % 100,
+        };
+        if bucket < u64::from(self.current_percent()) {
+            Variant::Canary
+        } else {
+            Variant::Stable
+        }
+    }
+
+    /// Calls served by each variant so far.
+    pub fn stats(&self) -> CanaryStats {
+        let inner = &self.inner;
+        CanaryStats {
+            path: self.path().to_string(),
+            percent: self.current_percent(),
+            stable: inner.counts[0].stats(inner.stable),
+            canary: inner.counts[1].stats(inner.canary),
+        }
+    }
+
+    fn path(&self) -> &'static str {
+        self.inner.route.path
+    }
+
+    /// The route serving the endpoint with `stable` (the handler of the
+    /// stable function) and `canary` (the handler of its rewrite), with the
+    /// method of the stable function. Registers the split for [`find`].
+    pub fn route<H1, T1, H2, T2, S>(&self, stable: H1, canary: H2) -> MethodRouter<S>
+    where
+        H1: Handler<T1, S>,
+        H2: Handler<T2, S>,
+        T1: 'static,
+        T2: 'static,
+        S: Clone + Send + Sync + 'static,
+    {
+        splits()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(self.inner.stable, self.clone());
+        let split = self.clone();
+        let handler = move |State(state): State<S>, request: Request| async move {
+            let (parts, body) = request.into_parts();
+            let variant = split.variant(&parts);
+            let request = Request::from_parts(parts, body);
+            let started = Instant::now();
+            let mut response = match variant {
+                Variant::Stable => stable.call(request, state).await,
+                Variant::Canary => canary.call(request, state).await,
+            };
+            split.inner.counts[variant.index()].record(&response, started);
+            response
+                .headers_mut()
+                .insert(VARIANT_HEADER, HeaderValue::from_static(variant.as_str()));
+            response
+        };
+        match self.inner.route.kind {
+            RouteKind::Rpc => axum::routing::post(handler),
+            RouteKind::Get | RouteKind::Sse | RouteKind::Ws => axum::routing::get(handler),
+        }
+    }
+}
+
+/// Splits by the module path and name of their stable function.
+fn splits() -> &'static Mutex<BTreeMap<&'static str, Canary>> {
+    static SPLITS: OnceLock<Mutex<BTreeMap<&'static str, Canary>>> = OnceLock::new();
+    SPLITS.get_or_init(Default::default)
+}
+
+/// The routed split of the server function `stable` (e.g.
+/// `my_api::search`), to adjust it while serving.
+pub fn find(stable: &str) -> Option<Canary> {
+    splits()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(stable)
+        .cloned()
+}
+
+/// All routed splits, by the module path and name of their stable function.
+pub fn all() -> Vec<Canary> {
+    splits()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect()
+}
+
+/// Renders one per-variant metric from its counts.
+type MetricValue = fn(&Counts) -> String;
+
+/// Append the per-variant metrics of the routed splits to a Prometheus
+/// text exposition.
+pub(crate) fn render_prometheus(out: &mut String) {
+    let splits = all();
+    if splits.is_empty() {
+        return;
+    }
+    let metrics: [(&str, MetricValue); 3] = [
+        ("axum_egui_canary_calls_total", |counts| {
+            counts.calls.load(Ordering::Relaxed).to_string()
+        }),
+        ("axum_egui_canary_errors_total", |counts| {
+            counts.errors.load(Ordering::Relaxed).to_string()
+        }),
+        ("axum_egui_canary_duration_seconds_total", |counts| {
+            (counts.micros.load(Ordering::Relaxed) as f64 / 1e6).to_string()
+        }),
+    ];
+    for (name, value) in metrics {
+        let _ = writeln!(out, "# TYPE {name} counter");
+        for split in &splits {
+            let inner = &split.inner;
+            for (variant, function) in [
+                (Variant::Stable, inner.stable),
+                (Variant::Canary, inner.canary),
+            ] {
+                let _ = writeln!(
+                    out,
+                    "{name}{{endpoint=\"{}\",variant=\"{}\",function=\"{function}\"}} {}",
+                    split.path(),
+                    variant.as_str(),
+                    value(&inner.counts[variant.index()])
+                );
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::registry::ServerFnRoute;
+    use axum::Router;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    inventory::submit! {
+        ServerFnRoute {
+            name: "canary_test_search",
+            module: module_path!(),
+            path: "/api/canary_test_search",
+            kind: RouteKind::Rpc,
+            post_params: false,
+            auth: false,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    inventory::submit! {
+        ServerFnRoute {
+            name: "canary_test_search_v2",
+            module: module_path!(),
+            path: "/api/canary_test_search_v2",
+            kind: RouteKind::Rpc,
+            post_params: false,
+            auth: false,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    inventory::submit! {
+        ServerFnRoute {
+            name: "canary_test_search_authed",
+            module: module_path!(),
+            path: "/api/canary_test_search_authed",
+            kind: RouteKind::Rpc,
+            post_params: false,
+            auth: true,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    const STABLE: &str = concat!(module_path!(), "::canary_test_search");
+    const CANARY: &str = concat!(module_path!(), "::canary_test_search_v2");
+
+    fn request(headers: &[(&str, &str)]) -> Parts {
+        let mut request = Request::builder().uri("/api/canary_test_search");
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn splits_calls_by_header_rule_and_percentage() {
+        let canary = Canary::new(STABLE, CANARY).percent(20).select(|request| {
+            request
+                .headers
+                .contains_key("x-beta")
+                .then_some(Variant::Canary)
+        });
+        let to_canary = (0..100)
+            .filter(|_| canary.variant(&request(&[])) == Variant::Canary)
+            .count();
+        assert_eq!(to_canary, 20);
+
+        assert_eq!(
+            canary.variant(&request(&[("x-beta", "1")])),
+            Variant::Canary
+        );
+        // The header only picks the variant if the split allows it
+        let forced = request(&[("x-beta", "1"), (VARIANT_HEADER, "stable")]);
+        assert_eq!(canary.variant(&forced), Variant::Canary);
+        let canary = canary.allow_override(true);
+        assert_eq!(canary.variant(&forced), Variant::Stable);
+
+        // Sticky keys keep their variant, and only move to the canary
+        let sticky = Canary::new(STABLE, CANARY).percent(30).sticky(|request| {
+            let user = request.headers.get("x-user")?;
+            Some(user.to_str().ok()?.to_string())
+        });
+        let users: Vec<Parts> = (0..50)
+            .map(|user| request(&[("x-user", &user.to_string())]))
+            .collect();
+        let before: Vec<Variant> = users.iter().map(|user| sticky.variant(user)).collect();
+        assert_eq!(
+            before,
+            users
+                .iter()
+                .map(|user| sticky.variant(user))
+                .collect::<Vec<_>>()
+        );
+        sticky.set_percent(60);
+        for (user, variant) in users.iter().zip(before) {
+            if variant == Variant::Canary {
+                assert_eq!(sticky.variant(user), Variant::Canary);
+            }
+        }
+
+        // Buckets are pinned by FNV-1a, not by the std hasher of this build
+        assert_eq!(
+            crate::schema::fingerprint(&format!("{STABLE}\0alice")) % 100,
+            12
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must be served the same way, but their auth differ")]
+    fn rejects_a_canary_served_differently() {
+        Canary::new(
+            STABLE,
+            concat!(module_path!(), "::canary_test_search_authed"),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no server function `canary_test_search` is registered")]
+    fn requires_qualified_names() {
+        Canary::new("canary_test_search", "canary_test_search_v2");
+    }
+
+    #[tokio::test]
+    async fn routes_both_handlers_and_counts_calls_by_variant() {
+        // The first four calls fall in buckets 0, 37, 74 and 11
+        let canary = Canary::new(STABLE, CANARY).percent(20);
+        let app = Router::new().route(
+            "/api/canary_test_search",
+            canary.route(
+                || async { "stable" },
+                || async { (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "canary") },
+            ),
+        );
+        for _ in 0..4 {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::post("/api/canary_test_search")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let variant = response.headers()[VARIANT_HEADER]
+                .to_str()
+                .unwrap()
+                .to_string();
+            assert_eq!(response.status().is_server_error(), variant == "canary");
+        }
+
+        let stats = find(STABLE).unwrap().stats();
+        assert_eq!(stats.path, "/api/canary_test_search");
+        assert_eq!((stats.stable.calls, stats.canary.calls), (2, 2));
+        assert_eq!((stats.stable.errors, stats.canary.errors), (0, 2));
+        assert_eq!(stats.canary.function, CANARY);
+
+        let mut out = String::new();
+        render_prometheus(&mut out);
+        assert!(out.contains(
+            "axum_egui_canary_calls_total{endpoint=\"/api/canary_test_search\",variant=\"canary\",function=\"axum_egui::canary::tests::canary_test_search_v2\"} 2"
+        ));
+    }
+}
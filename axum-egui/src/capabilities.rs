@@ -0,0 +1,394 @@
+//! Graphics and memory capabilities of the client, as hints for the server.
+//!
+//! The page is rendered before any WASM runs, so the server can't know
+//! whether the device has WebGPU, only WebGL1, or half a gigabyte of
+//! memory. The client detects its [`Capabilities`] and reports them to
+//! [`CAPABILITIES_PATH`], and the server remembers them in the
+//! [`COOKIE_NAME`] cookie. From the next page load on, the handler serving
+//! the [`App`](crate::App) can pick a lighter initial state or asset set:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .merge(capabilities::router());
+//!
+//! async fn index(capabilities: Option<Capabilities>) -> Response {
+//!     match capabilities {
+//!         Some(c) if c.is_low_end() => App::<_, LiteAssets>::new(State::lite()).into_response(),
+//!         _ => App::<_, Assets>::new(State::full()).into_response(),
+//!     }
+//! }
+//!
+//! // Client, at startup (or `Bootstrap::report_capabilities`)
+//! spawn_local(async { let _ = capabilities::report().await; });
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the endpoint served by [`router`].
+pub const CAPABILITIES_PATH: &str = "/api/axum-egui/capabilities";
+
+/// The cookie remembering the reported capabilities.
+pub const COOKIE_NAME: &str = "axum-egui-capabilities";
+
+/// Devices with less memory than this, in gigabytes, are low-end.
+pub const LOW_MEMORY_GB: f32 = 2.0;
+
+/// The best graphics API the browser offers, from worst to best.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicsApi {
+    /// No WebGL at all: eframe can't run.
+    #[default]
+    None,
+    /// WebGL 1 only.
+    WebGl1,
+    /// WebGL 2.
+    WebGl2,
+    /// WebGPU, with an adapter.
+    WebGpu,
+}
+
+impl GraphicsApi {
+    fn as_str(self) -> &'static str {
+        match self {
+            GraphicsApi::None => "none",
+            GraphicsApi::WebGl1 => "webgl1",
+            GraphicsApi::WebGl2 => "webgl2",
+            GraphicsApi::WebGpu => "webgpu",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [
+            GraphicsApi::None,
+            GraphicsApi::WebGl1,
+            GraphicsApi::WebGl2,
+            GraphicsApi::WebGpu,
+        ]
+        .into_iter()
+        .find(|api| api.as_str() == s)
+    }
+}
+
+/// What the client can render with and how much memory it has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The best graphics API available.
+    pub graphics: GraphicsApi,
+    /// `navigator.deviceMemory`, in gigabytes, rounded down by the browser
+    /// (Chromium only).
+    pub device_memory_gb: Option<f32>,
+    /// `navigator.hardwareConcurrency`.
+    pub cpu_cores: Option<u32>,
+}
+
+impl Capabilities {
+    /// Whether the device only has WebGL1 (or less), or less than
+    /// [`LOW_MEMORY_GB`] of memory.
+    pub fn is_low_end(&self) -> bool {
+        self.graphics <= GraphicsApi::WebGl1
+            || self
+                .device_memory_gb
+                .is_some_and(|memory| memory < LOW_MEMORY_GB)
+    }
+
+    /// The value of the [`COOKIE_NAME`] cookie, e.g. `webgl2:4:8`, with `-`
+    /// for what the browser doesn't tell.
+    pub fn to_cookie_value(&self) -> String {
+        fn or_dash(value: Option<impl ToString>) -> String {
+            value.map_or_else(|| "-".to_string(), |value| value.to_string())
+        }
+        format!(
+            "{}:{}:{}",
+            self.graphics.as_str(),
+            or_dash(self.device_memory_gb),
+            or_dash(self.cpu_cores)
+        )
+    }
+
+    /// Parse a [`to_cookie_value`](Self::to_cookie_value) value.
+    pub fn from_cookie_value(value: &str) -> Option<Self> {
+        let mut parts = value.split(':');
+        let graphics = GraphicsApi::parse(parts.next()?)?;
+        let memory = parts.next()?;
+        let cores = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        let device_memory_gb = match memory {
+            "-" => None,
+            memory => Some(memory.parse().ok().filter(|m: &f32| m.is_finite())?),
+        };
+        let cpu_cores = match cores {
+            "-" => None,
+            cores => Some(cores.parse().ok()?),
+        };
+        Some(Self {
+            graphics,
+            device_memory_gb,
+            cpu_cores,
+        })
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{CAPABILITIES_PATH, COOKIE_NAME, Capabilities};
+    use axum::Json;
+    use axum::Router;
+    use axum::extract::OptionalFromRequestParts;
+    use axum::http::header::{COOKIE, SET_COOKIE};
+    use axum::http::request::Parts;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use std::convert::Infallible;
+
+    /// How long the cookie is kept: a year.
+    const COOKIE_MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+
+    impl Capabilities {
+        /// The capabilities in the request's [`COOKIE_NAME`] cookie.
+        pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+            headers
+                .get_all(COOKIE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|cookies| cookies.split(';'))
+                .filter_map(|cookie| cookie.trim().split_once('='))
+                .find(|(name, _)| *name == COOKIE_NAME)
+                .and_then(|(_, value)| Self::from_cookie_value(value))
+        }
+    }
+
+    /// `Option<Capabilities>` extracts the reported capabilities, `None`
+    /// until the client has reported them.
+    impl<S> OptionalFromRequestParts<S> for Capabilities
+    where
+        S: Send + Sync,
+    {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            _state: &S,
+        ) -> Result<Option<Self>, Self::Rejection> {
+            Ok(Self::from_headers(&parts.headers))
+        }
+    }
+
+    /// The endpoint at [`CAPABILITIES_PATH`], storing the reported
+    /// capabilities in the [`COOKIE_NAME`] cookie.
+    pub fn router<S>() -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new().route(
+            CAPABILITIES_PATH,
+            post(|Json(capabilities): Json<Capabilities>| async move {
+                let cookie = format!(
+                    "{COOKIE_NAME}={}; Path=/; Max-Age={COOKIE_MAX_AGE_SECS}; HttpOnly; SameSite=Lax",
+                    capabilities.to_cookie_value()
+                );
+                (StatusCode::NO_CONTENT, [(SET_COOKIE, cookie)])
+            }),
+        )
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{CAPABILITIES_PATH, Capabilities, GraphicsApi};
+    use crate::rpc::ServerFnError;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    /// Detect the capabilities of this browser and device.
+    pub async fn detect() -> Capabilities {
+        let Some(window) = web_sys::window() else {
+            return Capabilities::default();
+        };
+        let navigator = window.navigator();
+        let device_memory_gb = js_sys::Reflect::get(&navigator, &"deviceMemory".into())
+            .ok()
+            .and_then(|memory| memory.as_f64())
+            .map(|memory| memory as f32);
+        let cpu_cores = Some(navigator.hardware_concurrency() as u32).filter(|&cores| cores > 0);
+
+        let graphics = if has_webgpu_adapter(&navigator).await {
+            GraphicsApi::WebGpu
+        } else if has_context(&window, "webgl2") {
+            GraphicsApi::WebGl2
+        } else if has_context(&window, "webgl") {
+            GraphicsApi::WebGl1
+        } else {
+            GraphicsApi::None
+        };
+        Capabilities {
+            graphics,
+            device_memory_gb,
+            cpu_cores,
+        }
+    }
+
+    /// Whether `navigator.gpu.requestAdapter()` finds an adapter: browsers
+    /// may have `navigator.gpu` without a usable GPU.
+    async fn has_webgpu_adapter(navigator: &web_sys::Navigator) -> bool {
+        let Ok(gpu) = js_sys::Reflect::get(navigator, &"gpu".into()) else {
+            return false;
+        };
+        if gpu.is_undefined() || gpu.is_null() {
+            return false;
+        }
+        let Some(request_adapter) = js_sys::Reflect::get(&gpu, &"requestAdapter".into())
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok())
+        else {
+            return false;
+        };
+        let Some(promise) = request_adapter
+            .call0(&gpu)
+            .ok()
+            .and_then(|p| p.dyn_into::<js_sys::Promise>().ok())
+        else {
+            return false;
+        };
+        JsFuture::from(promise)
+            .await
+            .is_ok_and(|adapter| !adapter.is_null() && !adapter.is_undefined())
+    }
+
+    fn has_context(window: &web_sys::Window, kind: &str) -> bool {
+        window
+            .document()
+            .and_then(|document| document.create_element("canvas").ok())
+            .and_then(|canvas| canvas.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .and_then(|canvas| canvas.get_context(kind).ok().flatten())
+            .is_some()
+    }
+
+    /// [`detect`] the capabilities and report them to [`CAPABILITIES_PATH`],
+    /// for the next page load.
+    pub async fn report() -> Result<Capabilities, ServerFnError> {
+        let capabilities = detect().await;
+        let response = gloo_net::http::Request::post(CAPABILITIES_PATH)
+            .json(&capabilities)
+            .map_err(|e| ServerFnError::Serialization(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+        if !response.ok() {
+            return Err(ServerFnError::ServerError(format!(
+                "HTTP {} from {CAPABILITIES_PATH}",
+                response.status()
+            )));
+        }
+        Ok(capabilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_round_trip_through_the_cookie() {
+        let full = Capabilities {
+            graphics: GraphicsApi::WebGpu,
+            device_memory_gb: Some(8.0),
+            cpu_cores: Some(16),
+        };
+        assert_eq!(full.to_cookie_value(), "webgpu:8:16");
+        assert_eq!(Capabilities::from_cookie_value("webgpu:8:16"), Some(full));
+        assert!(!full.is_low_end());
+
+        let old = Capabilities {
+            graphics: GraphicsApi::WebGl1,
+            device_memory_gb: None,
+            cpu_cores: None,
+        };
+        assert_eq!(old.to_cookie_value(), "webgl1:-:-");
+        assert_eq!(Capabilities::from_cookie_value("webgl1:-:-"), Some(old));
+        assert!(old.is_low_end());
+
+        let small = Capabilities::from_cookie_value("webgl2:0.5:4").unwrap();
+        assert!(small.is_low_end());
+
+        for invalid in [
+            "",
+            "webgl3:-:-",
+            "webgl2:lots:4",
+            "webgl2:4",
+            "webgl2:4:8:x",
+        ] {
+            assert_eq!(Capabilities::from_cookie_value(invalid), None, "{invalid}");
+        }
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn reported_capabilities_come_back_in_the_cookie() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode, header};
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let app: axum::Router = router().route(
+            "/",
+            get(|capabilities: Option<Capabilities>| async move {
+                capabilities.map_or("unknown".to_string(), |c| c.to_cookie_value())
+            }),
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::post(CAPABILITIES_PATH)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"graphics":"webgl2","device_memory_gb":4.0,"cpu_cores":8}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let set_cookie = response.headers()[header::SET_COOKIE].to_str().unwrap();
+        assert!(set_cookie.starts_with("axum-egui-capabilities=webgl2:4:8;"));
+
+        let page = |cookie: Option<&str>| {
+            let mut request = Request::get("/");
+            if let Some(cookie) = cookie {
+                request = request.header(header::COOKIE, cookie);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        let body = |response: axum::response::Response| async {
+            axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap()
+        };
+        let cookie = set_cookie.split(';').next().unwrap();
+        let response = page(Some(&format!("theme=dark; {cookie}"))).await.unwrap();
+        assert_eq!(body(response).await, "webgl2:4:8");
+        let response = page(None).await.unwrap();
+        assert_eq!(body(response).await, "unknown");
+    }
+}
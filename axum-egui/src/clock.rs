@@ -0,0 +1,238 @@
+//! Clock synchronization between the client and the server.
+//!
+//! Stream events are timestamped by the server, but a chart placing them
+//! against the browser's clock is off by however much the user's clock is
+//! skewed. The client asks the server for the time at [`TIME_PATH`] a few
+//! times, NTP-style, and estimates the offset between the clocks from the
+//! round trip with the least delay; [`server_now`] is then the server's idea
+//! of now:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new().merge(axum_egui::clock::router());
+//!
+//! // Client, at startup (and every few minutes, clocks drift)
+//! spawn_local(async { let _ = clock::sync().await; });
+//!
+//! // In `update()`: the last minute of server-timestamped ticks
+//! let window = clock::server_now() - 60_000.0..=clock::server_now();
+//! ```
+//!
+//! Times are milliseconds since the Unix epoch, as `f64` like
+//! `js_sys::Date::now()`. Until the first sync, [`server_now`] is the local
+//! time.
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the endpoint served by [`router`].
+pub const TIME_PATH: &str = "/api/axum-egui/time";
+
+/// Round trips measured by [`sync`].
+pub const SAMPLES: usize = 5;
+
+/// The server's answer at [`TIME_PATH`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ServerTime {
+    /// The server's time when it answered.
+    pub now_ms: f64,
+}
+
+/// One round trip to the server, with the local time it left and came back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSample {
+    /// Local time the request was sent.
+    pub sent_ms: f64,
+    /// The server's time when it answered.
+    pub server_ms: f64,
+    /// Local time the answer arrived.
+    pub received_ms: f64,
+}
+
+impl ClockSample {
+    /// How far the server's clock is ahead of the local one, assuming the
+    /// request and the answer took equally long.
+    pub fn offset_ms(&self) -> f64 {
+        self.server_ms - (self.sent_ms + self.received_ms) / 2.0
+    }
+
+    /// The round trip time. The offset is off by at most half of it.
+    pub fn rtt_ms(&self) -> f64 {
+        self.received_ms - self.sent_ms
+    }
+}
+
+/// The estimated offset between the server's clock and the local one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockEstimate {
+    /// How far the server's clock is ahead of the local one.
+    pub offset_ms: f64,
+    /// Round trip time of the sample the offset comes from.
+    pub rtt_ms: f64,
+}
+
+impl ClockEstimate {
+    /// The estimate from the sample with the shortest round trip, which
+    /// leaves the least room for asymmetric delays. `None` without samples.
+    pub fn from_samples(samples: &[ClockSample]) -> Option<Self> {
+        samples
+            .iter()
+            .min_by(|a, b| a.rtt_ms().total_cmp(&b.rtt_ms()))
+            .map(|sample| Self {
+                offset_ms: sample.offset_ms(),
+                rtt_ms: sample.rtt_ms(),
+            })
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{ServerTime, TIME_PATH};
+    use axum::Json;
+    use axum::Router;
+    use axum::http::header::CACHE_CONTROL;
+    use axum::routing::get;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// The endpoint at [`TIME_PATH`].
+    pub fn router<S>() -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new().route(
+            TIME_PATH,
+            get(|| async {
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs_f64()
+                    * 1000.0;
+                ([(CACHE_CONTROL, "no-store")], Json(ServerTime { now_ms }))
+            }),
+        )
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{ClockEstimate, ClockSample, SAMPLES, ServerTime, TIME_PATH};
+    use crate::rpc::ServerFnError;
+    use std::cell::Cell;
+
+    thread_local! {
+        static ESTIMATE: Cell<Option<ClockEstimate>> = const { Cell::new(None) };
+    }
+
+    /// Measure [`SAMPLES`] round trips to [`TIME_PATH`] and keep the
+    /// resulting estimate for [`server_now`].
+    ///
+    /// The requests bypass the call queue of [`crate::concurrency`], whose
+    /// waiting would count as network delay.
+    pub async fn sync() -> Result<ClockEstimate, ServerFnError> {
+        let mut samples = Vec::with_capacity(SAMPLES);
+        for _ in 0..SAMPLES {
+            samples.push(sample().await?);
+        }
+        let estimate = ClockEstimate::from_samples(&samples).expect("samples were taken");
+        ESTIMATE.set(Some(estimate));
+        Ok(estimate)
+    }
+
+    async fn sample() -> Result<ClockSample, ServerFnError> {
+        let sent_ms = js_sys::Date::now();
+        let response = gloo_net::http::Request::get(TIME_PATH)
+            .send()
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+        let received_ms = js_sys::Date::now();
+        if !response.ok() {
+            return Err(ServerFnError::ServerError(format!(
+                "HTTP {} from {TIME_PATH}",
+                response.status()
+            )));
+        }
+        let time: ServerTime = response
+            .json()
+            .await
+            .map_err(|e| ServerFnError::Deserialization(e.to_string()))?;
+        Ok(ClockSample {
+            sent_ms,
+            server_ms: time.now_ms,
+            received_ms,
+        })
+    }
+
+    /// The estimate of the last [`sync`], if any.
+    pub fn estimate() -> Option<ClockEstimate> {
+        ESTIMATE.get()
+    }
+
+    /// The server's current time: the local time corrected by the last
+    /// [`sync`].
+    pub fn server_now() -> f64 {
+        js_sys::Date::now() + estimate().map_or(0.0, |estimate| estimate.offset_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_uses_the_fastest_round_trip() {
+        // The server is 1000ms ahead; the slow sample was delayed on the way back
+        let samples = [
+            ClockSample {
+                sent_ms: 0.0,
+                server_ms: 1010.0,
+                received_ms: 100.0,
+            },
+            ClockSample {
+                sent_ms: 200.0,
+                server_ms: 1210.0,
+                received_ms: 220.0,
+            },
+        ];
+        let estimate = ClockEstimate::from_samples(&samples).unwrap();
+        assert_eq!(estimate.offset_ms, 1000.0);
+        assert_eq!(estimate.rtt_ms, 20.0);
+        assert_eq!(samples[0].offset_ms(), 960.0);
+        assert_eq!(ClockEstimate::from_samples(&[]), None);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn router_answers_with_the_current_time() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let before = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as f64;
+        let response = router::<()>()
+            .oneshot(Request::get(TIME_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["cache-control"], "no-store");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let time: ServerTime = serde_json::from_slice(&body).unwrap();
+        assert!(time.now_ms >= before && time.now_ms < before + 60_000.0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Request coalescing for expensive read functions (`#[server(coalesce)]`).
+//!
+//! When fifty dashboards refresh at the same moment, they call the same
+//! function with the same arguments fifty times. With `coalesce`, the first
+//! call runs the function and the others arriving while it is in flight wait
+//! for its result instead of running it again, so the database sees one
+//! query:
+//!
+//! ```ignore
+//! #[server(coalesce)]
+//! pub async fn revenue(quarter: u32) -> Result<Revenue, ServerFnError> {
+//!     db::revenue(quarter).await
+//! }
+//! ```
+//!
+//! Calls are identical when they name the same function with the same
+//! arguments, compared in their JSON form. Nothing is cached: a call arriving
+//! after the result was returned runs the function again.
+//!
+//! The function body runs once, in the request context of the first caller,
+//! and its result is shared by cloning, so the `Ok` type must be `Clone`.
+//! Only coalesce functions whose result depends on their arguments alone:
+//! a function reading the signed-in user from the request would hand one
+//! user's data to everyone else calling at the same time.
+//!
+//! If every waiting caller disconnects, the computation is dropped. Shared
+//! calls are counted in the `axum_egui_coalesced_calls_total` metric.
+
+use crate::rpc::ServerFnError;
+use futures_util::FutureExt;
+use futures_util::future::{BoxFuture, Shared};
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+
+pub(crate) const COALESCED_CALLS: &str = "axum_egui_coalesced_calls_total";
+
+type Flight<T> = Shared<BoxFuture<'static, Result<T, ServerFnError>>>;
+
+/// In-flight calls by key, each a `Flight<T>` of its function's `T`.
+fn flights() -> &'static Mutex<HashMap<FlightKey, Box<dyn Any + Send + Sync>>> {
+    static FLIGHTS: OnceLock<Mutex<HashMap<FlightKey, Box<dyn Any + Send + Sync>>>> =
+        OnceLock::new();
+    FLIGHTS.get_or_init(Default::default)
+}
+
+/// Identifies a call: the function and its JSON-encoded arguments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlightKey {
+    function: &'static str,
+    args: String,
+}
+
+/// The key of a call to `function` with `args`, `None` if the arguments
+/// don't serialize (the call then runs on its own).
+///
+/// Used by the handlers `#[server(coalesce)]` generates.
+pub fn key(function: &'static str, args: &impl Serialize) -> Option<FlightKey> {
+    let args = serde_json::to_string(args).ok()?;
+    Some(FlightKey { function, args })
+}
+
+/// Run `call`, unless a call with the same `key` is in flight: then wait for
+/// that one and return a clone of its result.
+///
+/// Used by the handlers `#[server(coalesce)]` generates.
+pub async fn run<T, F, Fut>(key: Option<FlightKey>, call: F) -> Result<T, ServerFnError>
+where
+    T: Clone + Send + Sync + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ServerFnError>> + Send + 'static,
+{
+    let Some(key) = key else {
+        return call().await;
+    };
+    let flight = {
+        let mut flights = flights().lock().unwrap_or_else(|e| e.into_inner());
+        let joined = flights
+            .get(&key)
+            .and_then(|flight| flight.downcast_ref::<Flight<T>>())
+            .cloned();
+        match joined {
+            Some(flight) => {
+                crate::metrics::counter(COALESCED_CALLS).inc();
+                flight
+            }
+            None => {
+                let flight = call().boxed().shared();
+                flights.insert(key.clone(), Box::new(flight.clone()));
+                flight
+            }
+        }
+    };
+    let _landed = Landed {
+        key,
+        flight: flight.clone(),
+    };
+    flight.await
+}
+
+/// Removes a flight when the first of its callers gets the result (or
+/// gives up), so later calls run the function again.
+struct Landed<T: 'static> {
+    key: FlightKey,
+    flight: Flight<T>,
+}
+
+impl<T: 'static> Drop for Landed<T> {
+    fn drop(&mut self) {
+        let mut flights = flights().lock().unwrap_or_else(|e| e.into_inner());
+        let current = flights
+            .get(&self.key)
+            .and_then(|flight| flight.downcast_ref::<Flight<T>>())
+            .is_some_and(|flight| Shared::ptr_eq(flight, &self.flight));
+        if current {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn identical_concurrent_calls_share_one_run() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let call = |quarter: u32| {
+            let runs = runs.clone();
+            run(key("coalesce_test", &quarter), move || async move {
+                runs.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                Ok::<_, ServerFnError>(quarter * 10)
+            })
+        };
+
+        let results = futures_util::future::join_all((0..5).map(|_| call(1))).await;
+        assert!(results.into_iter().all(|result| result.unwrap() == 10));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        // Other arguments run separately, and nothing is cached
+        let (a, b) = tokio::join!(call(1), call(2));
+        assert_eq!((a.unwrap(), b.unwrap()), (10, 20));
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        let landed = key("coalesce_test", &1).unwrap();
+        assert!(!flights().lock().unwrap().contains_key(&landed));
+    }
+
+    #[tokio::test]
+    async fn errors_are_shared_too() {
+        let failing = || {
+            run(key("coalesce_error_test", &()), || async {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Err::<u32, _>(ServerFnError::ServerError("down".into()))
+            })
+        };
+        let (a, b) = tokio::join!(failing(), failing());
+        assert!(matches!(a, Err(ServerFnError::ServerError(message)) if message == "down"));
+        assert!(b.is_err());
+    }
+}
@@ -0,0 +1,351 @@
+//! Client-side limit on concurrent server function calls.
+//!
+//! An egui table that calls a server function for every visible row can
+//! start hundreds of fetches in one frame. Browsers allow only a handful of
+//! connections per host over HTTP/1.1, so the excess queues inside the
+//! browser, where it also starves SSE and WebSocket connections. Calls made
+//! with [`rpc::call`](crate::rpc::call) therefore wait for a permit first:
+//!
+//! ```ignore
+//! axum_egui::concurrency::set_limits(
+//!     RequestLimits::new()
+//!         .max_in_flight(8)
+//!         // Thumbnails are slow; don't let them hold every permit
+//!         .endpoint(ThumbnailArgs::PATH, 2),
+//! );
+//! ```
+//!
+//! Waiting calls are admitted in the order they were made, skipping calls
+//! whose endpoint is at its own limit. Streams are long-lived and do not
+//! count.
+//!
+//! # Priorities
+//!
+//! Calls are [`CallPriority::Interactive`] unless made inside
+//! [`with_priority`]. Waiting interactive calls are admitted before waiting
+//! background calls, and background calls leave
+//! [`RequestLimits::interactive_reserve`] permits free, so a click is never
+//! stuck behind a wall of prefetches:
+//!
+//! ```ignore
+//! for id in next_page {
+//!     spawn_local(with_priority(CallPriority::Background, async move {
+//!         cache.insert(id, load_row(id).await);
+//!     }));
+//! }
+//! ```
+
+use crate::rpc::CallPriority;
+use futures_channel::oneshot;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+
+/// Default [`RequestLimits::max_in_flight`]: the per-host connection limit of
+/// browsers over HTTP/1.1.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 6;
+
+/// How many calls may be in flight at once.
+#[derive(Debug, Clone)]
+pub struct RequestLimits {
+    max_in_flight: usize,
+    interactive_reserve: usize,
+    endpoints: HashMap<String, usize>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            interactive_reserve: 1,
+            endpoints: HashMap::new(),
+        }
+    }
+}
+
+impl RequestLimits {
+    /// The default limits: [`DEFAULT_MAX_IN_FLIGHT`] calls, no per-endpoint
+    /// limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// At most `max` calls in flight across all endpoints (at least 1).
+    pub fn max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = max.max(1);
+        self
+    }
+
+    /// Permits background calls leave free for interactive ones (default: 1).
+    /// Background calls can always use at least one permit.
+    pub fn interactive_reserve(mut self, reserve: usize) -> Self {
+        self.interactive_reserve = reserve;
+        self
+    }
+
+    /// At most `max` calls in flight to `path` (at least 1). Calls to `path`
+    /// still count towards [`Self::max_in_flight`].
+    pub fn endpoint(mut self, path: impl Into<String>, max: usize) -> Self {
+        self.endpoints.insert(path.into(), max.max(1));
+        self
+    }
+
+    fn endpoint_limit(&self, path: &str) -> usize {
+        self.endpoints.get(path).copied().unwrap_or(usize::MAX)
+    }
+
+    fn background_limit(&self) -> usize {
+        self.max_in_flight
+            .saturating_sub(self.interactive_reserve)
+            .max(1)
+    }
+}
+
+struct Waiter {
+    path: String,
+    priority: CallPriority,
+    admit: oneshot::Sender<Permit>,
+}
+
+#[derive(Default)]
+struct Limiter {
+    limits: RequestLimits,
+    in_flight: usize,
+    background_in_flight: usize,
+    per_endpoint: HashMap<String, usize>,
+    waiting: VecDeque<Waiter>,
+}
+
+impl Limiter {
+    fn try_take(&mut self, path: &str, priority: CallPriority) -> Option<Permit> {
+        let endpoint = self.per_endpoint.get(path).copied().unwrap_or(0);
+        let background = priority == CallPriority::Background;
+        if self.in_flight >= self.limits.max_in_flight
+            || endpoint >= self.limits.endpoint_limit(path)
+            || (background && self.background_in_flight >= self.limits.background_limit())
+        {
+            return None;
+        }
+        self.in_flight += 1;
+        if background {
+            self.background_in_flight += 1;
+        }
+        *self.per_endpoint.entry(path.to_string()).or_default() += 1;
+        Some(Permit {
+            path: path.to_string(),
+            priority,
+        })
+    }
+
+    fn release(&mut self, path: &str, priority: CallPriority) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        if priority == CallPriority::Background {
+            self.background_in_flight = self.background_in_flight.saturating_sub(1);
+        }
+        if let Some(count) = self.per_endpoint.get_mut(path) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_endpoint.remove(path);
+            }
+        }
+    }
+
+    /// Take permits for the waiters that fit, interactive calls first, then
+    /// oldest first.
+    fn admit(&mut self) -> Vec<(oneshot::Sender<Permit>, Permit)> {
+        self.waiting.retain(|waiter| !waiter.admit.is_canceled());
+        let mut admitted = Vec::new();
+        for priority in [CallPriority::Interactive, CallPriority::Background] {
+            let mut i = 0;
+            while i < self.waiting.len() && self.in_flight < self.limits.max_in_flight {
+                let waiter = &self.waiting[i];
+                if waiter.priority != priority {
+                    i += 1;
+                    continue;
+                }
+                match self.try_take(&waiter.path.clone(), priority) {
+                    Some(permit) => {
+                        let waiter = self.waiting.remove(i).expect("index in bounds");
+                        admitted.push((waiter.admit, permit));
+                    }
+                    None => i += 1,
+                }
+            }
+        }
+        admitted
+    }
+}
+
+thread_local! {
+    static LIMITER: RefCell<Limiter> = RefCell::default();
+    static PRIORITY: Cell<CallPriority> = const { Cell::new(CallPriority::Interactive) };
+}
+
+/// Hand permits to admitted waiters, outside the limiter borrow: a waiter
+/// that went away drops its permit, which releases it again.
+fn wake(admitted: Vec<(oneshot::Sender<Permit>, Permit)>) {
+    for (admit, permit) in admitted {
+        let _ = admit.send(permit);
+    }
+}
+
+/// Replace the limits. Calls already in flight keep their permits.
+pub fn set_limits(limits: RequestLimits) {
+    let admitted = LIMITER.with_borrow_mut(|limiter| {
+        limiter.limits = limits;
+        limiter.admit()
+    });
+    wake(admitted);
+}
+
+/// Number of calls currently in flight.
+pub fn in_flight() -> usize {
+    LIMITER.with_borrow(|limiter| limiter.in_flight)
+}
+
+/// Number of calls waiting for a permit.
+pub fn queued() -> usize {
+    LIMITER.with_borrow(|limiter| limiter.waiting.len())
+}
+
+/// Run `future` with calls made inside it at `priority`.
+pub async fn with_priority<F: Future>(priority: CallPriority, future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let outer = PRIORITY.replace(priority);
+        let poll = future.as_mut().poll(cx);
+        PRIORITY.set(outer);
+        poll
+    })
+    .await
+}
+
+/// The priority of calls made now: the innermost [`with_priority`], or
+/// [`CallPriority::Interactive`].
+pub fn current_priority() -> CallPriority {
+    PRIORITY.get()
+}
+
+/// Permission to send one call to `path`; released when dropped.
+#[must_use = "the permit is released when dropped"]
+#[derive(Debug)]
+pub struct Permit {
+    path: String,
+    priority: CallPriority,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let admitted = LIMITER.with_borrow_mut(|limiter| {
+            limiter.release(&self.path, self.priority);
+            limiter.admit()
+        });
+        wake(admitted);
+    }
+}
+
+/// Wait until a call to `path` at `priority` may be sent. Dropping the
+/// future gives up its place in the queue.
+pub async fn acquire(path: &str, priority: CallPriority) -> Permit {
+    let queued = LIMITER.with_borrow_mut(|limiter| {
+        // Queued calls are all blocked by a limit, so this one can only
+        // overtake calls it doesn't compete with
+        if let Some(permit) = limiter.try_take(path, priority) {
+            return Err(permit);
+        }
+        let (admit, admitted) = oneshot::channel();
+        limiter.waiting.push_back(Waiter {
+            path: path.to_string(),
+            priority,
+            admit,
+        });
+        Ok(admitted)
+    });
+    match queued {
+        Err(permit) => permit,
+        Ok(admitted) => admitted
+            .await
+            .expect("waiters are only removed once canceled or admitted"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    #[test]
+    fn calls_wait_for_global_and_endpoint_permits() {
+        set_limits(
+            RequestLimits::new()
+                .max_in_flight(3)
+                .endpoint("/api/row", 2),
+        );
+
+        let row1 = acquire("/api/row", CallPriority::Interactive)
+            .now_or_never()
+            .unwrap();
+        let row2 = acquire("/api/row", CallPriority::Interactive)
+            .now_or_never()
+            .unwrap();
+        // The endpoint is full; other endpoints are not blocked by its queue
+        let mut row3 = Box::pin(acquire("/api/row", CallPriority::Interactive));
+        assert!((&mut row3).now_or_never().is_none());
+        let save = acquire("/api/save", CallPriority::Interactive)
+            .now_or_never()
+            .unwrap();
+        assert_eq!((in_flight(), queued()), (3, 1));
+
+        // Freeing a global slot does not admit the row call
+        drop(save);
+        assert!((&mut row3).now_or_never().is_none());
+        assert_eq!((in_flight(), queued()), (2, 1));
+
+        drop(row1);
+        let row3 = row3.now_or_never().unwrap();
+        assert_eq!((in_flight(), queued()), (2, 0));
+
+        // Abandoned waiters give their permit back
+        let mut abandoned = Box::pin(acquire("/api/row", CallPriority::Interactive));
+        assert!((&mut abandoned).now_or_never().is_none());
+        assert_eq!(queued(), 1);
+        drop(abandoned);
+        drop(row2);
+        assert_eq!((in_flight(), queued()), (1, 0));
+        drop(row3);
+        assert_eq!(in_flight(), 0);
+    }
+
+    #[test]
+    fn background_calls_yield_to_interactive_calls() {
+        use CallPriority::{Background, Interactive};
+        set_limits(RequestLimits::new().max_in_flight(3));
+
+        // One permit stays free for interactive calls
+        let prefetches: Vec<_> = (0..2)
+            .map(|_| acquire("/api/row", Background).now_or_never().unwrap())
+            .collect();
+        let mut prefetch = Box::pin(acquire("/api/row", Background));
+        assert!((&mut prefetch).now_or_never().is_none());
+        let click = acquire("/api/open", Interactive).now_or_never().unwrap();
+
+        // A later interactive call is admitted before the waiting prefetch
+        let mut click2 = Box::pin(acquire("/api/open", Interactive));
+        assert!((&mut click2).now_or_never().is_none());
+        drop(click);
+        let click2 = click2.now_or_never().unwrap();
+        assert!((&mut prefetch).now_or_never().is_none());
+
+        drop(prefetches);
+        let prefetch = prefetch.now_or_never().unwrap();
+        assert_eq!((in_flight(), queued()), (2, 0));
+        drop((prefetch, click2));
+    }
+
+    #[test]
+    fn with_priority_scopes_the_current_priority() {
+        assert_eq!(current_priority(), CallPriority::Interactive);
+        let inner = with_priority(CallPriority::Background, async { current_priority() });
+        assert_eq!(inner.now_or_never(), Some(CallPriority::Background));
+        assert_eq!(current_priority(), CallPriority::Interactive);
+    }
+}
@@ -0,0 +1,354 @@
+//! Server configuration loaded from a TOML file and environment variables.
+//!
+//! Deployments usually need to change a handful of settings (listen
+//! address, base path, cache policy, CORS origins) without recompiling.
+//! [`AxumEguiConfig::load`] reads them from, in increasing priority:
+//!
+//! 1. built-in defaults
+//! 2. a TOML file: the path in `AXUM_EGUI_CONFIG`, or `axum-egui.toml` in the
+//!    working directory if it exists
+//! 3. `AXUM_EGUI_*` environment variables
+//!
+//! ```toml
+//! # axum-egui.toml
+//! listen_addr = "0.0.0.0:8080"
+//! base_path = "/dashboard"
+//! asset_cache_control = "public, max-age=3600"
+//! cors_origins = ["https://app.example.com"]
+//! max_stream_connections = 500
+//! max_stream_connections_per_ip = 8
+//!
+//! [reconnect]
+//! initial_delay_ms = 500
+//! max_delay_ms = 30000
+//! ```
+//!
+//! | Variable | Field |
+//! |----------|-------|
+//! | `AXUM_EGUI_LISTEN_ADDR` | `listen_addr` |
+//! | `AXUM_EGUI_BASE_PATH` | `base_path` |
+//! | `AXUM_EGUI_ASSET_CACHE_CONTROL` | `asset_cache_control` |
+//! | `AXUM_EGUI_RECONNECT_INITIAL_MS` | `reconnect.initial_delay_ms` |
+//! | `AXUM_EGUI_RECONNECT_MAX_MS` | `reconnect.max_delay_ms` |
+//! | `AXUM_EGUI_CORS_ORIGINS` | `cors_origins` (comma-separated) |
+//! | `AXUM_EGUI_MAX_STREAMS` | `max_stream_connections` |
+//! | `AXUM_EGUI_MAX_STREAMS_PER_IP` | `max_stream_connections_per_ip` |
+//!
+//! # Example
+//!
+//! ```ignore
+//! let config = axum_egui::config::AxumEguiConfig::load()?;
+//! axum_egui::serve_with_config(app, config)
+//!     .assets::<Assets>()
+//!     .await?;
+//! ```
+//!
+//! [`serve_with_config`] also adds the config as an
+//! `Extension<Arc<AxumEguiConfig>>`, so handlers can read it (for example to
+//! pass the reconnect policy to the frontend as initial state).
+
+use crate::limits::ConnectionLimits;
+use crate::serve::{Serve, serve};
+use axum::{Extension, Router};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable naming the TOML config file.
+pub const CONFIG_PATH_ENV: &str = "AXUM_EGUI_CONFIG";
+
+/// Config file read by [`AxumEguiConfig::load`] when [`CONFIG_PATH_ENV`] is unset.
+pub const DEFAULT_CONFIG_FILE: &str = "axum-egui.toml";
+
+/// Errors from loading an [`AxumEguiConfig`].
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    #[error("failed to read config file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The config file is not valid TOML for [`AxumEguiConfig`].
+    #[error("invalid config: {0}")]
+    Toml(#[from] toml::de::Error),
+    /// An environment variable override could not be parsed.
+    #[error("invalid value for {var}: {message}")]
+    InvalidEnv { var: &'static str, message: String },
+}
+
+/// Runtime settings for an axum-egui server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AxumEguiConfig {
+    /// Address to listen on (default: `127.0.0.1:3000`).
+    pub listen_addr: SocketAddr,
+    /// Path prefix to mount the app under, e.g. `/dashboard` (default: root).
+    pub base_path: String,
+    /// `Cache-Control` header for static assets (HTML is exempt).
+    pub asset_cache_control: Option<String>,
+    /// Reconnect backoff for SSE and WebSocket clients.
+    pub reconnect: ReconnectConfig,
+    /// Origins allowed to make cross-origin requests.
+    pub cors_origins: Vec<String>,
+    /// Cap on concurrent SSE/WebSocket connections (see [`crate::limits`]).
+    pub max_stream_connections: Option<usize>,
+    /// Cap on concurrent SSE/WebSocket connections per client IP.
+    pub max_stream_connections_per_ip: Option<usize>,
+}
+
+impl Default for AxumEguiConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: SocketAddr::from(([127, 0, 0, 1], 3000)),
+            base_path: String::new(),
+            asset_cache_control: None,
+            reconnect: ReconnectConfig::default(),
+            cors_origins: Vec::new(),
+            max_stream_connections: None,
+            max_stream_connections_per_ip: None,
+        }
+    }
+}
+
+/// Reconnect backoff settings, served to the frontend so clients can be
+/// tuned without a rebuild.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnect attempt, in milliseconds.
+    pub initial_delay_ms: u64,
+    /// Upper bound for the exponential backoff, in milliseconds.
+    pub max_delay_ms: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+impl AxumEguiConfig {
+    /// Load the config file (if any), then apply environment overrides.
+    ///
+    /// A missing `axum-egui.toml` is not an error, but a missing file named
+    /// by `AXUM_EGUI_CONFIG` is.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match std::env::var_os(CONFIG_PATH_ENV) {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(DEFAULT_CONFIG_FILE)?
+            }
+            None => Self::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    /// Parse a config from a TOML file, without environment overrides.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&contents)
+    }
+
+    /// Parse a config from a TOML string. Missing fields use their defaults.
+    pub fn from_toml_str(toml: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    /// Apply `AXUM_EGUI_*` environment variable overrides.
+    pub fn apply_env(&mut self) -> Result<(), ConfigError> {
+        self.apply_env_from(|var| std::env::var(var).ok())
+    }
+
+    /// Apply overrides using `lookup` in place of the process environment.
+    pub fn apply_env_from(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        if let Some(addr) = lookup("AXUM_EGUI_LISTEN_ADDR") {
+            self.listen_addr = parse_env("AXUM_EGUI_LISTEN_ADDR", &addr)?;
+        }
+        if let Some(path) = lookup("AXUM_EGUI_BASE_PATH") {
+            self.base_path = path;
+        }
+        if let Some(value) = lookup("AXUM_EGUI_ASSET_CACHE_CONTROL") {
+            self.asset_cache_control = Some(value).filter(|v| !v.is_empty());
+        }
+        if let Some(ms) = lookup("AXUM_EGUI_RECONNECT_INITIAL_MS") {
+            self.reconnect.initial_delay_ms = parse_env("AXUM_EGUI_RECONNECT_INITIAL_MS", &ms)?;
+        }
+        if let Some(ms) = lookup("AXUM_EGUI_RECONNECT_MAX_MS") {
+            self.reconnect.max_delay_ms = parse_env("AXUM_EGUI_RECONNECT_MAX_MS", &ms)?;
+        }
+        if let Some(origins) = lookup("AXUM_EGUI_CORS_ORIGINS") {
+            self.cors_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(max) = lookup("AXUM_EGUI_MAX_STREAMS") {
+            self.max_stream_connections = Some(parse_env("AXUM_EGUI_MAX_STREAMS", &max)?);
+        }
+        if let Some(max) = lookup("AXUM_EGUI_MAX_STREAMS_PER_IP") {
+            self.max_stream_connections_per_ip =
+                Some(parse_env("AXUM_EGUI_MAX_STREAMS_PER_IP", &max)?);
+        }
+        Ok(())
+    }
+}
+
+fn parse_env<T>(var: &'static str, value: &str) -> Result<T, ConfigError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e: T::Err| ConfigError::InvalidEnv {
+            var,
+            message: e.to_string(),
+        })
+}
+
+/// Like [`serve`], with the listen address, base path, CORS origins, asset
+/// cache policy and stream connection limits taken from `config`.
+///
+/// The config is also available to handlers as
+/// `Extension<Arc<AxumEguiConfig>>`.
+pub fn serve_with_config(router: Router, config: AxumEguiConfig) -> Serve {
+    let config = Arc::new(config);
+    let mut serve = serve(router.layer(Extension(config.clone())), config.listen_addr)
+        .base_path(config.base_path.as_str())
+        .cors_origins(&config.cors_origins);
+    if let Some(cache_control) = &config.asset_cache_control {
+        serve = serve.asset_cache_control(cache_control);
+    }
+    if config.max_stream_connections.is_some() || config.max_stream_connections_per_ip.is_some() {
+        let mut limits = ConnectionLimits::new();
+        if let Some(max) = config.max_stream_connections {
+            limits = limits.max_total(max);
+        }
+        if let Some(max) = config.max_stream_connections_per_ip {
+            limits = limits.max_per_ip(max);
+        }
+        serve = serve.connection_limits(limits);
+    }
+    serve
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::get;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |var| vars.get(var).cloned()
+    }
+
+    #[test]
+    fn partial_toml_uses_defaults() {
+        let config = AxumEguiConfig::from_toml_str(
+            r#"
+            base_path = "/dashboard"
+
+            [reconnect]
+            max_delay_ms = 5000
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.base_path, "/dashboard");
+        assert_eq!(config.reconnect.max_delay_ms, 5000);
+        assert_eq!(config.reconnect.initial_delay_ms, 1_000);
+        assert_eq!(config.listen_addr, AxumEguiConfig::default().listen_addr);
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let err = AxumEguiConfig::from_toml_str("listen_adr = \"0.0.0.0:80\"").unwrap_err();
+        assert!(matches!(err, ConfigError::Toml(_)));
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let mut config = AxumEguiConfig::from_toml_str("listen_addr = \"127.0.0.1:4000\"").unwrap();
+        config
+            .apply_env_from(env(&[
+                ("AXUM_EGUI_LISTEN_ADDR", "0.0.0.0:8080"),
+                ("AXUM_EGUI_RECONNECT_INITIAL_MS", "250"),
+                (
+                    "AXUM_EGUI_CORS_ORIGINS",
+                    "https://a.example.com, https://b.example.com,",
+                ),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.listen_addr, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.reconnect.initial_delay_ms, 250);
+        assert_eq!(
+            config.cors_origins,
+            ["https://a.example.com", "https://b.example.com"]
+        );
+    }
+
+    #[test]
+    fn invalid_env_names_the_variable() {
+        let err = AxumEguiConfig::default()
+            .apply_env_from(env(&[("AXUM_EGUI_RECONNECT_MAX_MS", "soon")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("AXUM_EGUI_RECONNECT_MAX_MS"));
+    }
+
+    #[tokio::test]
+    async fn serve_with_config_mounts_base_path_and_exposes_config() {
+        let config = AxumEguiConfig {
+            base_path: "/dashboard".into(),
+            ..Default::default()
+        };
+        let app = Router::new().route(
+            "/reconnect",
+            get(
+                |Extension(config): Extension<Arc<AxumEguiConfig>>| async move {
+                    config.reconnect.max_delay_ms.to_string()
+                },
+            ),
+        );
+        let router = serve_with_config(app, config).into_router();
+
+        let response = router
+            .oneshot(
+                Request::get("/dashboard/reconnect")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"30000");
+    }
+}
@@ -0,0 +1,408 @@
+//! Live stream connections, listed and closed by administrators.
+//!
+//! Every open `#[server(sse)]` and `#[server(ws)]` stream is tracked with its
+//! endpoint, user, uptime and message counts. [`ConnectionAdmin`] serves
+//! them to administrators, who can also close a connection, e.g. to kick a
+//! misbehaving client or one whose access was revoked:
+//!
+//! ```ignore
+//! // Server
+//! let admin = ConnectionAdmin::new()
+//!     .identify(|request: &Parts| session_user(&request.headers))
+//!     .access(|request: &Parts| is_admin(&request.headers));
+//! let app = app.merge(admin.router()).layer(Extension(admin));
+//!
+//! // Client
+//! let live = connections::list().await?;
+//! for connection in live.iter().filter(|c| c.user.as_deref() == Some("mallory")) {
+//!     connections::close(connection.id).await?;
+//! }
+//! ```
+//!
+//! Users are those of the [`AuthUser`](crate::auth::AuthUser) of `auth`
+//! streams unless [`ConnectionAdmin::identify`] says otherwise. A closed SSE
+//! stream ends like a finished one, so the browser doesn't reconnect; a
+//! WebSocket is closed with [`CLOSE_POLICY_VIOLATION`](crate::ws::CLOSE_POLICY_VIOLATION).
+//! Connections are tracked per server instance.
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the listing endpoint served by [`ConnectionAdmin::router`].
+pub const CONNECTIONS_PATH: &str = "/api/admin/connections";
+
+/// Path of the endpoint closing a connection, taking a [`CloseConnection`].
+pub const CLOSE_CONNECTION_PATH: &str = "/api/admin/connections/close";
+
+/// The transport of a stream connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamTransport {
+    /// Server-Sent Events (`#[server(sse)]`).
+    Sse,
+    /// A WebSocket (`#[server(ws)]`).
+    Ws,
+}
+
+/// An open stream connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    /// Identifies the connection on this instance, for [`CloseConnection`].
+    pub id: u64,
+    /// SSE or WebSocket.
+    pub transport: StreamTransport,
+    /// The path of the stream's endpoint.
+    pub endpoint: String,
+    /// The user, if identified.
+    pub user: Option<String>,
+    /// The client's address, when known.
+    pub remote_addr: Option<String>,
+    /// When the connection opened, in milliseconds since the Unix epoch.
+    pub opened_at_ms: u64,
+    /// How long the connection has been open, in milliseconds.
+    pub uptime_ms: u64,
+    /// Messages sent to the client.
+    pub messages_sent: u64,
+    /// Messages received from the client (WebSocket only).
+    pub messages_received: u64,
+}
+
+/// Request to close the connection with `id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloseConnection {
+    /// The [`ConnectionInfo::id`].
+    pub id: u64,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{
+        CLOSE_CONNECTION_PATH, CONNECTIONS_PATH, CloseConnection, ConnectionInfo, StreamTransport,
+    };
+    use crate::auth::AuthUser;
+    use crate::context::RequestContext;
+    use axum::Router;
+    use axum::http::request::Parts;
+    use axum::http::{StatusCode, header};
+    use axum::response::{IntoResponse, Json};
+    use axum::routing::{get, post};
+    use futures_util::task::AtomicWaker;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::task::Context;
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+    /// An open stream, as tracked while it lives.
+    pub(crate) struct Connection {
+        id: u64,
+        transport: StreamTransport,
+        endpoint: String,
+        user: Option<String>,
+        remote_addr: Option<String>,
+        opened_at_ms: u64,
+        opened: Instant,
+        sent: AtomicU64,
+        received: AtomicU64,
+        closing: AtomicBool,
+        waker: AtomicWaker,
+    }
+
+    impl Connection {
+        pub(crate) fn id(&self) -> u64 {
+            self.id
+        }
+
+        pub(crate) fn sent(&self) {
+            self.sent.fetch_add(1, Ordering::Relaxed);
+        }
+
+        pub(crate) fn received(&self) {
+            self.received.fetch_add(1, Ordering::Relaxed);
+        }
+
+        /// Whether an administrator closed the connection.
+        pub(crate) fn is_closing(&self) -> bool {
+            self.closing.load(Ordering::Acquire)
+        }
+
+        /// [`Self::is_closing`], waking the task polling with `cx` once it
+        /// becomes true.
+        pub(crate) fn poll_closing(&self, cx: &mut Context<'_>) -> bool {
+            self.waker.register(cx.waker());
+            self.is_closing()
+        }
+
+        fn close(&self) {
+            self.closing.store(true, Ordering::Release);
+            self.waker.wake();
+        }
+
+        fn info(&self) -> ConnectionInfo {
+            ConnectionInfo {
+                id: self.id,
+                transport: self.transport,
+                endpoint: self.endpoint.clone(),
+                user: self.user.clone(),
+                remote_addr: self.remote_addr.clone(),
+                opened_at_ms: self.opened_at_ms,
+                uptime_ms: self.opened.elapsed().as_millis() as u64,
+                messages_sent: self.sent.load(Ordering::Relaxed),
+                messages_received: self.received.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    fn live() -> std::sync::MutexGuard<'static, HashMap<u64, Arc<Connection>>> {
+        static LIVE: OnceLock<Mutex<HashMap<u64, Arc<Connection>>>> = OnceLock::new();
+        LIVE.get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Track the stream served for `ctx`. Called once per request by
+    /// [`RequestContext`], which unregisters it on disconnect.
+    pub(crate) fn register(ctx: &RequestContext) -> Arc<Connection> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let websocket = ctx
+            .headers()
+            .get(header::UPGRADE)
+            .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"));
+        let user = match ctx.extensions().get::<ConnectionAdmin>() {
+            Some(admin) => (admin.identify)(ctx.parts()),
+            None => default_user(ctx.parts()),
+        };
+        let connection = Arc::new(Connection {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            transport: if websocket {
+                StreamTransport::Ws
+            } else {
+                StreamTransport::Sse
+            },
+            endpoint: ctx.uri().path().to_string(),
+            user,
+            remote_addr: ctx.remote_addr().map(|addr| addr.to_string()),
+            opened_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            opened: Instant::now(),
+            sent: AtomicU64::new(0),
+            received: AtomicU64::new(0),
+            closing: AtomicBool::new(false),
+            waker: AtomicWaker::new(),
+        });
+        live().insert(connection.id, connection.clone());
+        connection
+    }
+
+    pub(crate) fn unregister(id: u64) {
+        live().remove(&id);
+    }
+
+    fn default_user(request: &Parts) -> Option<String> {
+        request
+            .extensions
+            .get::<AuthUser>()
+            .map(|user| user.id.clone())
+    }
+
+    type Identify = dyn Fn(&Parts) -> Option<String> + Send + Sync;
+    type Access = dyn Fn(&Parts) -> bool + Send + Sync;
+
+    /// Who the users of streams are, and who may list and close them.
+    /// Install it as an [`Extension`](axum::Extension) and merge its
+    /// [`router`](Self::router). Cheap to clone.
+    #[derive(Clone)]
+    pub struct ConnectionAdmin {
+        identify: Arc<Identify>,
+        access: Arc<Access>,
+    }
+
+    impl std::fmt::Debug for ConnectionAdmin {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("ConnectionAdmin").finish_non_exhaustive()
+        }
+    }
+
+    impl Default for ConnectionAdmin {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl ConnectionAdmin {
+        /// Users identified by the [`AuthUser`] of the request, if any, and
+        /// nobody allowed to use the endpoints.
+        pub fn new() -> Self {
+            Self {
+                identify: Arc::new(default_user),
+                access: Arc::new(|_: &Parts| false),
+            }
+        }
+
+        /// Identify the user of a stream request, e.g. from its session
+        /// cookie.
+        pub fn identify(
+            mut self,
+            identify: impl Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+        ) -> Self {
+            self.identify = Arc::new(identify);
+            self
+        }
+
+        /// Decide which requests may list and close connections.
+        pub fn access(mut self, allow: impl Fn(&Parts) -> bool + Send + Sync + 'static) -> Self {
+            self.access = Arc::new(allow);
+            self
+        }
+
+        /// The open connections, oldest first.
+        pub fn list(&self) -> Vec<ConnectionInfo> {
+            let mut connections: Vec<_> = live().values().map(|c| c.info()).collect();
+            connections.sort_by_key(|connection| connection.id);
+            connections
+        }
+
+        /// Close the connection with `id`. `false` if there is none.
+        pub fn close(&self, id: u64) -> bool {
+            let connection = live().get(&id).cloned();
+            match connection {
+                Some(connection) => {
+                    tracing::info!(
+                        id,
+                        endpoint = connection.endpoint,
+                        user = connection.user,
+                        "closing stream connection"
+                    );
+                    connection.close();
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// The listing endpoint at [`CONNECTIONS_PATH`] and the closing one
+        /// at [`CLOSE_CONNECTION_PATH`]. Requests not allowed by
+        /// [`Self::access`] get `403 Forbidden`.
+        pub fn router<S>(&self) -> Router<S>
+        where
+            S: Clone + Send + Sync + 'static,
+        {
+            let list = self.clone();
+            let close = self.clone();
+            Router::new()
+                .route(
+                    CONNECTIONS_PATH,
+                    get(move |parts: Parts| {
+                        let admin = list.clone();
+                        async move {
+                            if !(admin.access)(&parts) {
+                                return StatusCode::FORBIDDEN.into_response();
+                            }
+                            Json(admin.list()).into_response()
+                        }
+                    }),
+                )
+                .route(
+                    CLOSE_CONNECTION_PATH,
+                    post(move |parts: Parts, Json(request): Json<CloseConnection>| {
+                        let admin = close.clone();
+                        async move {
+                            if !(admin.access)(&parts) {
+                                return StatusCode::FORBIDDEN.into_response();
+                            }
+                            Json(admin.close(request.id)).into_response()
+                        }
+                    }),
+                )
+        }
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{CLOSE_CONNECTION_PATH, CONNECTIONS_PATH, CloseConnection, ConnectionInfo};
+    use crate::rpc::ServerFnError;
+
+    /// The open connections of the instance answering, from
+    /// [`CONNECTIONS_PATH`]. Fails unless the server allows this client to.
+    pub async fn list() -> Result<Vec<ConnectionInfo>, ServerFnError> {
+        crate::rpc::call_get(CONNECTIONS_PATH, &()).await
+    }
+
+    /// Close the connection with `id`. `false` if the instance answering
+    /// has none.
+    pub async fn close(id: u64) -> Result<bool, ServerFnError> {
+        crate::rpc::call(CLOSE_CONNECTION_PATH, &CloseConnection { id }).await
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::auth::AuthUser;
+    use crate::context::RequestContext;
+    use axum::http::Request;
+    use futures_util::StreamExt;
+
+    fn ctx(path: &str, user: Option<&str>) -> RequestContext {
+        let mut parts = Request::get(path).body(()).unwrap().into_parts().0;
+        if let Some(user) = user {
+            parts.extensions.insert(AuthUser::new(user));
+        }
+        RequestContext::from_parts(parts)
+    }
+
+    fn find(admin: &ConnectionAdmin, endpoint: &str) -> Option<ConnectionInfo> {
+        admin.list().into_iter().find(|c| c.endpoint == endpoint)
+    }
+
+    #[tokio::test]
+    async fn streams_are_listed_while_open() {
+        let admin = ConnectionAdmin::new();
+        let ctx = ctx("/api/connections_listed", Some("ada"));
+        let mut stream = ctx
+            .disconnect_guard()
+            .bind(futures_util::stream::iter([1, 2, 3]));
+        assert_eq!(stream.next().await, Some(1));
+        assert_eq!(stream.next().await, Some(2));
+
+        let info = find(&admin, "/api/connections_listed").unwrap();
+        assert_eq!(info.transport, StreamTransport::Sse);
+        assert_eq!(info.user.as_deref(), Some("ada"));
+        assert_eq!(info.messages_sent, 2);
+
+        drop(stream);
+        assert!(find(&admin, "/api/connections_listed").is_none());
+    }
+
+    #[tokio::test]
+    async fn closed_streams_end() {
+        let admin = ConnectionAdmin::new();
+        let ctx = ctx("/api/connections_closed", None);
+        let mut stream = ctx
+            .disconnect_guard()
+            .bind(futures_util::stream::pending::<u32>());
+        let next = tokio::spawn(async move { stream.next().await });
+        tokio::task::yield_now().await;
+
+        let id = find(&admin, "/api/connections_closed").unwrap().id;
+        assert!(admin.close(id));
+        assert_eq!(next.await.unwrap(), None);
+        assert!(!admin.close(id));
+    }
+}
@@ -0,0 +1,339 @@
+//! Per-request context for server function bodies.
+//!
+//! Handlers generated by `#[server]` run the function body with a
+//! [`RequestContext`] in scope, so the body can inspect the request (headers,
+//! URI, peer address, extensions) without extra parameters, and register
+//! cleanup to run when the client goes away:
+//!
+//! ```ignore
+//! use axum_egui::context;
+//!
+//! #[server(sse)]
+//! pub async fn watch_room(room: String) -> Result<ServerStream<Message>, ServerFnError> {
+//!     let user = current_user()?;
+//!     presence::join(&room, &user);
+//!     context::on_disconnect(move || presence::leave(&room, &user));
+//!     Ok(ServerStream::new(messages(&room)))
+//! }
+//! ```
+//!
+//! # When hooks run
+//!
+//! - `#[server]`: when the handler finishes, or is cancelled because the
+//!   client dropped the request.
+//! - `#[server(sse)]`: when the event stream is dropped, i.e. when the client
+//!   disconnects (detected within one keep-alive interval at the latest), or
+//!   when the function returns an error.
+//! - `#[server(ws)]`: when the socket closes or the returned stream ends.
+//!
+//! The returned stream itself is dropped *before* the hooks run, so anything
+//! it owns (DB cursors, subscriptions) is already released.
+//!
+//! Streams are polled with the context in scope too, so [`current`] and
+//! [`on_disconnect`] also work inside stream combinators.
+
+use crate::connections::Connection;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, Method, Uri};
+use futures_util::Stream;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use tokio::sync::watch;
+
+tokio::task_local! {
+    static CONTEXT: RequestContext;
+}
+
+type Hook = Box<dyn FnOnce() + Send>;
+
+/// The request a server function is handling. Cheap to clone.
+#[derive(Clone)]
+pub struct RequestContext {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    parts: Parts,
+    hooks: Mutex<Vec<Hook>>,
+    disconnected: watch::Sender<bool>,
+    /// The stream served for the request, once there is one.
+    connection: OnceLock<Arc<Connection>>,
+}
+
+impl std::fmt::Debug for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestContext")
+            .field("method", self.method())
+            .field("uri", self.uri())
+            .field("disconnected", &self.is_disconnected())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestContext {
+    /// Create a context for a request.
+    pub fn from_parts(parts: Parts) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                parts,
+                hooks: Mutex::new(Vec::new()),
+                disconnected: watch::Sender::new(false),
+                connection: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// The request method.
+    pub fn method(&self) -> &Method {
+        &self.inner.parts.method
+    }
+
+    /// The request URI.
+    pub fn uri(&self) -> &Uri {
+        &self.inner.parts.uri
+    }
+
+    /// The request headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.inner.parts.headers
+    }
+
+    /// Request extensions, including anything added by middleware.
+    pub fn extensions(&self) -> &axum::http::Extensions {
+        &self.inner.parts.extensions
+    }
+
+    /// The full request head.
+    pub fn parts(&self) -> &Parts {
+        &self.inner.parts
+    }
+
+    /// The peer address, when the server was started with connect info
+    /// (as [`serve()`](crate::serve()) does).
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.extensions()
+            .get::<axum::extract::ConnectInfo<SocketAddr>>()
+            .map(|info| info.0)
+    }
+
+    /// Run `hook` once the client has gone away (see the [module
+    /// docs](self) for exactly when). Runs immediately if that has already
+    /// happened.
+    pub fn on_disconnect(&self, hook: impl FnOnce() + Send + 'static) {
+        if self.is_disconnected() {
+            hook();
+            return;
+        }
+        self.inner.hooks.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// Whether the client has gone away.
+    pub fn is_disconnected(&self) -> bool {
+        *self.inner.disconnected.borrow()
+    }
+
+    /// Resolves once the client has gone away. Useful in `select!` loops of
+    /// background tasks spawned for the request.
+    pub async fn disconnected(&self) {
+        let mut rx = self.inner.disconnected.subscribe();
+        let _ = rx.wait_for(|disconnected| *disconnected).await;
+    }
+
+    /// A guard that marks the client as disconnected when dropped.
+    ///
+    /// Generated handlers hold one for the lifetime of the connection; only
+    /// custom handlers need this.
+    pub fn disconnect_guard(&self) -> DisconnectGuard {
+        DisconnectGuard { ctx: self.clone() }
+    }
+
+    /// The stream connection of the request, listed in
+    /// [`connections`](crate::connections) until the client disconnects.
+    pub(crate) fn connection(&self) -> &Arc<Connection> {
+        self.inner
+            .connection
+            .get_or_init(|| crate::connections::register(self))
+    }
+
+    fn fire_disconnect(&self) {
+        if let Some(connection) = self.inner.connection.get() {
+            crate::connections::unregister(connection.id());
+        }
+        self.inner.disconnected.send_replace(true);
+        let hooks = std::mem::take(&mut *self.inner.hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+    }
+}
+
+/// The context of the server function currently running, if any.
+pub fn current() -> Option<RequestContext> {
+    CONTEXT.try_with(Clone::clone).ok()
+}
+
+/// Register `hook` on the [`current`] context.
+///
+/// Outside of a server function (e.g. when calling one directly from a unit
+/// test) there is no connection to watch, and the hook is dropped without
+/// running.
+pub fn on_disconnect(hook: impl FnOnce() + Send + 'static) {
+    match current() {
+        Some(ctx) => ctx.on_disconnect(hook),
+        None => tracing::debug!("on_disconnect called outside a server function; ignoring"),
+    }
+}
+
+/// Run `future` with `ctx` as the [`current`] context.
+pub async fn scope<F: Future>(ctx: RequestContext, future: F) -> F::Output {
+    CONTEXT.scope(ctx, future).await
+}
+
+/// Marks a [`RequestContext`] as disconnected, running its hooks, when
+/// dropped.
+#[must_use = "the disconnect hooks run as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct DisconnectGuard {
+    ctx: RequestContext,
+}
+
+impl DisconnectGuard {
+    /// Hand the guard to `stream`: the stream is polled with the context in
+    /// scope, and dropping it drops `stream` and then runs the hooks.
+    ///
+    /// The stream is listed as a live connection in
+    /// [`connections`](crate::connections), and ends when an administrator
+    /// closes it.
+    pub fn bind<S: Stream>(self, stream: S) -> ContextStream<S> {
+        ContextStream {
+            stream: Some(Box::pin(stream)),
+            connection: self.ctx.connection().clone(),
+            guard: self,
+        }
+    }
+}
+
+impl Drop for DisconnectGuard {
+    fn drop(&mut self) {
+        self.ctx.fire_disconnect();
+    }
+}
+
+/// A stream bound to a request by [`DisconnectGuard::bind`].
+pub struct ContextStream<S> {
+    stream: Option<Pin<Box<S>>>,
+    connection: Arc<Connection>,
+    guard: DisconnectGuard,
+}
+
+impl<S: Stream> Stream for ContextStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.connection.poll_closing(cx) {
+            self.stream.take();
+        }
+        let ctx = self.guard.ctx.clone();
+        let Some(stream) = self.stream.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let poll = CONTEXT.sync_scope(ctx, || stream.as_mut().poll_next(cx));
+        if let Poll::Ready(Some(_)) = poll {
+            self.connection.sent();
+        }
+        poll
+    }
+}
+
+impl<S> Drop for ContextStream<S> {
+    fn drop(&mut self) {
+        // Release the user's stream before the guard runs the hooks
+        self.stream.take();
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use futures_util::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn ctx() -> RequestContext {
+        let (parts, ()) = Request::get("/api/feed?args=%7B%7D")
+            .header("x-test", "1")
+            .body(())
+            .unwrap()
+            .into_parts();
+        RequestContext::from_parts(parts)
+    }
+
+    #[tokio::test]
+    async fn exposes_request_inside_scope() {
+        let uri = scope(ctx(), async {
+            let ctx = current().unwrap();
+            assert_eq!(ctx.headers()["x-test"], "1");
+            ctx.uri().path().to_string()
+        })
+        .await;
+        assert_eq!(uri, "/api/feed");
+        assert!(current().is_none());
+    }
+
+    #[tokio::test]
+    async fn hooks_run_once_when_guard_drops() {
+        let ctx = ctx();
+        let runs = Arc::new(AtomicUsize::new(0));
+        let guard = ctx.disconnect_guard();
+        let counter = runs.clone();
+        scope(ctx.clone(), async move {
+            on_disconnect(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        })
+        .await;
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        drop(guard);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert!(ctx.is_disconnected());
+        ctx.disconnected().await;
+
+        // Late registrations run immediately
+        let counter = runs.clone();
+        ctx.on_disconnect(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn bound_stream_drops_stream_before_hooks() {
+        struct Owned(Arc<AtomicUsize>);
+        impl Drop for Owned {
+            fn drop(&mut self) {
+                self.0.store(1, Ordering::SeqCst);
+            }
+        }
+
+        let ctx = ctx();
+        let dropped = Arc::new(AtomicUsize::new(0));
+        let owned = Owned(dropped.clone());
+        let observed = dropped.clone();
+        ctx.on_disconnect(move || {
+            assert_eq!(observed.load(Ordering::SeqCst), 1, "stream still alive");
+        });
+
+        let mut stream = ctx
+            .disconnect_guard()
+            .bind(futures_util::stream::repeat(()).map(move |()| {
+                let _ = &owned;
+                current().is_some()
+            }));
+        assert_eq!(stream.next().await, Some(true));
+        drop(stream);
+        assert!(ctx.is_disconnected());
+    }
+}
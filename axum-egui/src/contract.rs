@@ -0,0 +1,333 @@
+//! Snapshot tests of the wire format of server functions.
+//!
+//! Browsers keep running cached WASM clients after a deploy, so a renamed
+//! field or a reshaped enum breaks users who haven't reloaded. [`Contracts`]
+//! serializes a sample request and response of every registered server
+//! function into one JSON file each, and fails when they no longer match
+//! the files committed next to the code:
+//!
+//! ```ignore
+//! #[test]
+//! fn wire_format_is_stable() {
+//!     Contracts::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/contracts"))
+//!         .module("shared::api")
+//!         .sample(AddArgs::PATH, &AddArgs { a: 1, b: 2 }, &3)
+//!         .sample(TicksArgs::PATH, &TicksArgs { every_ms: 500 }, &7u64)
+//!         .check();
+//! }
+//! ```
+//!
+//! For streaming functions the response is one stream item. Snapshots of
+//! new functions are written on the first run; changed or removed ones fail
+//! the test until it is run again with [`UPDATE_ENV`] set, after making sure
+//! old clients can cope. Registered functions without a sample fail too, so
+//! new endpoints can't slip through untested. The directory belongs to one
+//! check: snapshots in it without a function are reported as removed.
+
+use crate::registry::{RouteKind, ServerFnRoute};
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that, when set, makes [`Contracts::check`] accept
+/// the current wire format and rewrite the snapshots.
+pub const UPDATE_ENV: &str = "AXUM_EGUI_UPDATE_CONTRACTS";
+
+/// Sample calls of server functions, checked against snapshot files.
+#[derive(Debug, Clone)]
+pub struct Contracts {
+    dir: PathBuf,
+    module: Option<String>,
+    samples: BTreeMap<String, (Value, Value)>,
+    errors: Vec<Violation>,
+}
+
+/// A difference between the server functions and their snapshots.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// A registered server function has no sample.
+    MissingSample {
+        /// The function name.
+        name: &'static str,
+        /// Its API path.
+        path: &'static str,
+    },
+    /// A sample was given for a path no server function is registered at.
+    UnknownPath(String),
+    /// A sample no longer serializes like its snapshot.
+    Changed {
+        /// The snapshot file.
+        file: PathBuf,
+        /// The snapshot.
+        expected: Value,
+        /// The sample as serialized now.
+        found: Value,
+    },
+    /// A snapshot belongs to a server function that no longer exists.
+    Removed(PathBuf),
+    /// A sample doesn't serialize, or a snapshot can't be read or written.
+    Error(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::MissingSample { name, path } => {
+                write!(f, "no sample for `{name}` ({path})")
+            }
+            Violation::UnknownPath(path) => {
+                write!(
+                    f,
+                    "sample for {path}, where no server function is registered"
+                )
+            }
+            Violation::Changed {
+                file,
+                expected,
+                found,
+            } => {
+                let pretty = |value| serde_json::to_string_pretty(value).unwrap_or_default();
+                write!(
+                    f,
+                    "wire format changed ({})\n--- snapshot\n{}\n+++ now\n{}",
+                    file.display(),
+                    pretty(expected),
+                    pretty(found)
+                )
+            }
+            Violation::Removed(file) => write!(
+                f,
+                "{} belongs to a server function that no longer exists",
+                file.display()
+            ),
+            Violation::Error(error) => f.write_str(error),
+        }
+    }
+}
+
+impl Contracts {
+    /// Check against the snapshots in `dir`.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            module: None,
+            samples: BTreeMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Only cover the server functions of the module `prefix` and its
+    /// submodules (default: every registered one).
+    pub fn module(mut self, prefix: impl Into<String>) -> Self {
+        self.module = Some(prefix.into());
+        self
+    }
+
+    /// The call of the function at `path` (its args struct's `PATH`) with
+    /// `request`, answered with `response`.
+    pub fn sample(
+        mut self,
+        path: &str,
+        request: &impl Serialize,
+        response: &impl Serialize,
+    ) -> Self {
+        match (
+            serde_json::to_value(request),
+            serde_json::to_value(response),
+        ) {
+            (Ok(request), Ok(response)) => {
+                self.samples.insert(path.to_string(), (request, response));
+            }
+            (Err(e), _) | (_, Err(e)) => self
+                .errors
+                .push(Violation::Error(format!("sample for {path}: {e}"))),
+        }
+        self
+    }
+
+    /// Compare the samples with the snapshots, writing those that are
+    /// missing, or all of them if [`UPDATE_ENV`] is set.
+    pub fn verify(&self) -> Result<(), Vec<Violation>> {
+        let update = std::env::var_os(UPDATE_ENV).is_some();
+        let mut violations = self.errors.clone();
+        if let Err(e) = std::fs::create_dir_all(&self.dir) {
+            violations.push(Violation::Error(format!("{}: {e}", self.dir.display())));
+            return Err(violations);
+        }
+
+        let routes: Vec<_> = crate::registry::routes()
+            .into_iter()
+            .filter(|route| self.covers(route))
+            .collect();
+        let mut files = Vec::new();
+        for route in &routes {
+            let Some((request, response)) = self.samples.get(route.path) else {
+                violations.push(Violation::MissingSample {
+                    name: route.name,
+                    path: route.path,
+                });
+                continue;
+            };
+            let file = self.dir.join(file_name(route.path));
+            let found = json!({
+                "name": route.name,
+                "path": route.path,
+                "kind": kind(route.kind),
+                "request": request,
+                "response": response,
+            });
+            let expected = std::fs::read_to_string(&file)
+                .ok()
+                .and_then(|text| serde_json::from_str::<Value>(&text).ok());
+            match expected {
+                Some(expected) if expected == found => {}
+                Some(expected) if !update => violations.push(Violation::Changed {
+                    file: file.clone(),
+                    expected,
+                    found,
+                }),
+                _ => {
+                    let text = serde_json::to_string_pretty(&found).expect("JSON values serialize");
+                    if let Err(e) = std::fs::write(&file, text + "\n") {
+                        violations.push(Violation::Error(format!("{}: {e}", file.display())));
+                    }
+                }
+            }
+            files.push(file);
+        }
+
+        for path in self.samples.keys() {
+            if !routes.iter().any(|route| route.path == *path) {
+                violations.push(Violation::UnknownPath(path.clone()));
+            }
+        }
+
+        let snapshots = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext == "json"));
+        for file in snapshots {
+            if files.contains(&file) {
+                continue;
+            }
+            if !update {
+                violations.push(Violation::Removed(file));
+            } else if let Err(e) = std::fs::remove_file(&file) {
+                violations.push(Violation::Error(format!("{}: {e}", file.display())));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// [`verify`](Self::verify), panicking with every violation.
+    #[track_caller]
+    pub fn check(&self) {
+        if let Err(violations) = self.verify() {
+            let list: Vec<String> = violations.iter().map(ToString::to_string).collect();
+            panic!(
+                "server function contracts broken:\n\n{}\n\nIf old clients can cope, \
+                 rerun with {UPDATE_ENV}=1 to accept the changes.",
+                list.join("\n\n")
+            );
+        }
+    }
+
+    fn covers(&self, route: &ServerFnRoute) -> bool {
+        match &self.module {
+            Some(prefix) => {
+                route.module == prefix
+                    || route
+                        .module
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            }
+            None => true,
+        }
+    }
+}
+
+/// The snapshot file of the function at `path`: `/api/add` is
+/// `api.add.json`.
+fn file_name(path: &str) -> String {
+    format!("{}.json", path.trim_matches('/').replace('/', "."))
+}
+
+fn kind(kind: RouteKind) -> &'static str {
+    match kind {
+        RouteKind::Rpc => "rpc",
+        RouteKind::Get => "get",
+        RouteKind::Sse => "sse",
+        RouteKind::Ws => "ws",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    inventory::submit! {
+        ServerFnRoute {
+            name: "contract_test_add",
+            module: "axum_egui::contract::tests::api",
+            path: "/api/contract_test/add",
+            kind: RouteKind::Rpc,
+            post_params: false,
+            auth: false,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    #[derive(Serialize)]
+    struct AddArgs {
+        a: i32,
+        b: i32,
+    }
+
+    #[test]
+    fn snapshots_catch_wire_format_changes() {
+        let dir = std::env::temp_dir().join(format!("axum-egui-contract-{}", std::process::id()));
+        let contracts = || Contracts::new(&dir).module("axum_egui::contract::tests");
+
+        assert_eq!(
+            contracts().verify(),
+            Err(vec![Violation::MissingSample {
+                name: "contract_test_add",
+                path: "/api/contract_test/add",
+            }])
+        );
+
+        // First run writes the snapshot, later runs compare against it
+        let add = |args| contracts().sample("/api/contract_test/add", &args, &3);
+        add(AddArgs { a: 1, b: 2 }).check();
+        assert!(dir.join("api.contract_test.add.json").exists());
+        add(AddArgs { a: 1, b: 2 }).check();
+
+        let renamed = json!({ "left": 1, "b": 2 });
+        let violations = contracts()
+            .sample("/api/contract_test/add", &renamed, &3)
+            .sample("/api/contract_test/sub", &(), &0)
+            .verify()
+            .unwrap_err();
+        assert!(matches!(violations[0], Violation::Changed { .. }));
+        assert_eq!(
+            violations[1],
+            Violation::UnknownPath("/api/contract_test/sub".into())
+        );
+
+        std::fs::write(dir.join("api.contract_test.gone.json"), "{}").unwrap();
+        let violations = add(AddArgs { a: 1, b: 2 }).verify().unwrap_err();
+        assert!(matches!(violations[..], [Violation::Removed(_)]));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
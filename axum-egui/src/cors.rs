@@ -0,0 +1,124 @@
+//! CORS for serving the API to a frontend hosted on another origin.
+//!
+//! [`cors()`] returns a `tower-http` [`CorsLayer`] set up for what server
+//! functions send:
+//!
+//! - `GET` (SSE streams, queries, WebSocket handshakes) and `POST` (calls)
+//! - the `Content-Type` of any body format (JSON, MessagePack), `Accept`,
+//!   the call [`PRIORITY_HEADER`], SSE's `Last-Event-ID` and the headers of
+//!   `#[server(headers(...))]` functions in the [`registry`](crate::registry)
+//! - credentials, so session cookies reach `auth` functions
+//! - the [`INSTANCE_HEADER`] and `Retry-After` exposed to the client
+//! - preflights cached for [`PREFLIGHT_MAX_AGE`]
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/api/add", post(add_handler))
+//!     .layer(axum_egui::cors(["https://app.example.com"]));
+//! ```
+//!
+//! Browsers don't apply CORS to WebSockets; streaming functions check the
+//! `Origin` against the [`OriginPolicy`](crate::origin::OriginPolicy)
+//! instead. [`Serve::cors_origins`](crate::serve::Serve::cors_origins)
+//! installs both.
+//!
+//! [`PRIORITY_HEADER`]: crate::rpc::PRIORITY_HEADER
+//! [`INSTANCE_HEADER`]: crate::instance::INSTANCE_HEADER
+
+use crate::instance::INSTANCE_HEADER;
+use crate::rpc::PRIORITY_HEADER;
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// How long browsers may cache a preflight response.
+pub const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(3600);
+
+/// A CORS layer allowing server function calls and streams from
+/// `allowed_origins` (e.g. `https://app.example.com`). Invalid origins are
+/// ignored.
+pub fn cors<I, S>(allowed_origins: I) -> CorsLayer
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let origins: Vec<_> = allowed_origins
+        .into_iter()
+        .filter_map(|origin| HeaderValue::from_str(origin.as_ref()).ok())
+        .collect();
+    let function_headers = crate::registry::routes()
+        .into_iter()
+        .flat_map(|route| route.headers)
+        .filter_map(|name| HeaderName::from_bytes(name.as_bytes()).ok());
+    let mut headers = vec![
+        header::CONTENT_TYPE,
+        header::ACCEPT,
+        HeaderName::from_static(PRIORITY_HEADER),
+        HeaderName::from_static("last-event-id"),
+    ];
+    for name in function_headers {
+        if !headers.contains(&name) {
+            headers.push(name);
+        }
+    }
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers(headers)
+        .allow_credentials(true)
+        .expose_headers([
+            HeaderName::from_static(INSTANCE_HEADER),
+            header::RETRY_AFTER,
+        ])
+        .max_age(PREFLIGHT_MAX_AGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::routing::post;
+    use tower::ServiceExt;
+
+    async fn preflight(origin: &str) -> axum::http::HeaderMap {
+        let app = Router::new()
+            .route("/api/add", post(|| async { "3" }))
+            .layer(cors(["https://app.example.com"]));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/api/add")
+                    .header(header::ORIGIN, origin)
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .header(
+                        header::ACCESS_CONTROL_REQUEST_HEADERS,
+                        "content-type,priority",
+                    )
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn preflight_allows_server_fn_calls_with_credentials() {
+        let headers = preflight("https://app.example.com").await;
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        let allowed = headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(allowed.contains("content-type") && allowed.contains("priority"));
+
+        let headers = preflight("https://evil.example").await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+}
@@ -0,0 +1,310 @@
+//! Crash reports and the crash screen of the WASM client.
+//!
+//! When the app panics, eframe stops drawing and the user is left with a
+//! frozen canvas. [`install`] sets a panic hook that sends a
+//! [`CrashReport`] to [`CRASH_PATH`], where the server hands it to a
+//! [`CrashSink`], and covers the page with a crash screen: a short message,
+//! a reload button and the report's id, for the user to quote to support:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new().merge(crash::router(crash::TracingSink));
+//!
+//! // Client, once at startup (the bootstrap helper does it)
+//! crash::install(env!("CARGO_PKG_VERSION"));
+//! ```
+//!
+//! eframe's own hook, set when it starts, logs the panic to the console and
+//! then calls this one. Only the first panic is reported and shown.
+
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// Path of the endpoint served by [`router`].
+pub const CRASH_PATH: &str = "/api/axum-egui/crash";
+
+/// Element id of the crash screen.
+pub const SCREEN_ID: &str = "axum-egui-crash";
+
+/// What the client sends when it panics.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Random id shown on the crash screen.
+    pub id: String,
+    /// Version of the app, as given to [`install`].
+    pub version: String,
+    /// The panic message.
+    pub message: String,
+    /// `file:line:column` of the panic, if known.
+    pub location: Option<String>,
+    /// The page the app ran on.
+    pub url: String,
+    /// When the app panicked, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+}
+
+/// The message of a panic payload: the `&str` or `String` given to
+/// `panic!`, or a placeholder for other payloads.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{CRASH_PATH, CrashReport};
+    use axum::Router;
+    use axum::body::Bytes;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+    use axum::routing::post;
+    use std::sync::Arc;
+
+    /// Receives the reports sent by clients. Implemented for closures.
+    pub trait CrashSink: Send + Sync + 'static {
+        /// Store a report sent with `request`.
+        fn record(&self, report: CrashReport, request: &Parts);
+    }
+
+    impl<F> CrashSink for F
+    where
+        F: Fn(CrashReport, &Parts) + Send + Sync + 'static,
+    {
+        fn record(&self, report: CrashReport, request: &Parts) {
+            self(report, request)
+        }
+    }
+
+    /// Logs every report with `tracing` at error level.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TracingSink;
+
+    impl CrashSink for TracingSink {
+        fn record(&self, report: CrashReport, _request: &Parts) {
+            tracing::error!(
+                target: "axum_egui::crash",
+                id = %report.id,
+                version = %report.version,
+                location = report.location.as_deref().unwrap_or("unknown"),
+                url = %report.url,
+                "client panicked: {}",
+                report.message,
+            );
+        }
+    }
+
+    /// The endpoint at [`CRASH_PATH`], feeding `sink`.
+    ///
+    /// Reports are sent as beacons (`text/plain`), so the body is parsed as
+    /// JSON whatever its content type. Invalid reports get
+    /// `400 Bad Request`.
+    pub fn router<S>(sink: impl CrashSink) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let sink: Arc<dyn CrashSink> = Arc::new(sink);
+        Router::new().route(
+            CRASH_PATH,
+            post(move |parts: Parts, body: Bytes| {
+                let sink = sink.clone();
+                async move {
+                    let Ok(report) = serde_json::from_slice::<CrashReport>(&body) else {
+                        return StatusCode::BAD_REQUEST;
+                    };
+                    sink.record(report, &parts);
+                    StatusCode::NO_CONTENT
+                }
+            }),
+        )
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{CRASH_PATH, CrashReport, SCREEN_ID, panic_message};
+    use std::cell::Cell;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+
+    thread_local! {
+        static INSTALLED: Cell<bool> = const { Cell::new(false) };
+        static CRASHED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    const SCREEN_STYLE: &str = "position: fixed; inset: 0; z-index: 2147483647; \
+        display: flex; flex-direction: column; align-items: center; justify-content: center; \
+        gap: 12px; background: #1b1b1b; color: #e0e0e0; font-family: sans-serif;";
+
+    const ID_STYLE: &str = "font-family: monospace; color: #a0a0a0;";
+
+    /// Report the first panic to [`CRASH_PATH`] as app `version` and show
+    /// the crash screen, then call the previous panic hook. Call it before
+    /// starting eframe, whose hook logs the panic and then calls this one.
+    /// Later calls, e.g. for a second app on the page, do nothing.
+    pub fn install(version: impl Into<String>) {
+        if INSTALLED.replace(true) {
+            return;
+        }
+        let version = version.into();
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !CRASHED.replace(true) {
+                report(&version, info);
+            }
+            previous(info);
+        }));
+    }
+
+    fn report(version: &str, info: &std::panic::PanicHookInfo<'_>) {
+        let report = CrashReport {
+            id: new_id(),
+            version: version.to_string(),
+            message: panic_message(info.payload()),
+            location: info.location().map(ToString::to_string),
+            url: web_sys::window()
+                .and_then(|window| window.location().href().ok())
+                .unwrap_or_default(),
+            timestamp_ms: js_sys::Date::now() as u64,
+        };
+        send(&report);
+        show_screen(&report.id);
+    }
+
+    /// Whether the app has panicked since [`install`].
+    pub fn crashed() -> bool {
+        CRASHED.get()
+    }
+
+    fn new_id() -> String {
+        format!(
+            "{:08x}",
+            (js_sys::Math::random() * f64::from(u32::MAX)) as u32
+        )
+    }
+
+    fn send(report: &CrashReport) {
+        let (Some(window), Ok(json)) = (web_sys::window(), serde_json::to_string(report)) else {
+            return;
+        };
+        let _ = window
+            .navigator()
+            .send_beacon_with_opt_str(CRASH_PATH, Some(&json));
+    }
+
+    /// Cover the page with the crash screen, showing the report `id`.
+    pub fn show_screen(id: &str) {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        let Some(body) = document.body() else {
+            return;
+        };
+        // Built element by element: the page's CSP allows no inline scripts
+        let element = |tag: &str, text: &str| {
+            let element = document.create_element(tag).ok()?;
+            element.set_text_content(Some(text));
+            Some(element)
+        };
+        let (Some(screen), Some(message), Some(id), Some(reload)) = (
+            element("div", ""),
+            element("p", "Something went wrong and the app has stopped."),
+            element("p", &format!("Error id: {id}")),
+            element("button", "Reload"),
+        ) else {
+            return;
+        };
+        screen.set_id(SCREEN_ID);
+        let _ = screen.set_attribute("role", "alert");
+        let _ = screen.set_attribute("style", SCREEN_STYLE);
+        let _ = id.set_attribute("style", ID_STYLE);
+
+        let on_click = Closure::once_into_js(|| {
+            if let Some(window) = web_sys::window() {
+                let _ = window.location().reload();
+            }
+        });
+        let _ = reload.add_event_listener_with_callback("click", on_click.unchecked_ref());
+
+        for child in [&message, &id, &reload] {
+            let _ = screen.append_child(child);
+        }
+        let _ = body.append_child(&screen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_messages_are_read_from_the_payload() {
+        let payload = std::panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static message");
+        let row = 3;
+        let payload = std::panic::catch_unwind(|| panic!("no row {row}")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "no row 3");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(7)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "Box<dyn Any>");
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn router_hands_reports_to_sink() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use std::sync::{Arc, Mutex};
+        use tower::ServiceExt;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app: axum::Router = router({
+            let received = received.clone();
+            move |report: CrashReport, _: &axum::http::request::Parts| {
+                received.lock().unwrap().push(report)
+            }
+        });
+        let post = |body: String| {
+            Request::post(CRASH_PATH)
+                .header("content-type", "text/plain;charset=UTF-8")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let report = CrashReport {
+            id: "0badc0de".into(),
+            version: "1.2.0".into(),
+            message: "index out of bounds".into(),
+            location: Some("src/app.rs:42:13".into()),
+            url: "https://example.com/".into(),
+            timestamp_ms: 1,
+        };
+        let response = app
+            .clone()
+            .oneshot(post(serde_json::to_string(&report).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(*received.lock().unwrap(), [report]);
+
+        let response = app.oneshot(post("nope".into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,215 @@
+//! Delta encoding of stream items that change little from one to the next.
+//!
+//! A tick stream sending the same struct every 100ms with one field changed
+//! spends most of its bandwidth on repeats. Declare it `delta` and the
+//! server sends the first item in full, then only [JSON
+//! Patches](crate::patch::Patch) against the item before it, with a full
+//! item again every [`FULL_EVERY`] items. The client reassembles the items,
+//! so the stream is used exactly as without it:
+//!
+//! ```ignore
+//! #[server(sse, delta)]
+//! pub async fn board() -> Result<ServerStream<Board>, ServerFnError> {
+//!     Ok(ServerStream::new(board_ticks()))
+//! }
+//! ```
+//!
+//! Every connection starts with a full item, so reconnects need no special
+//! handling. Items the client can't reassemble yield a
+//! `ServerFnError::Deserialization` error, after which it waits for the next
+//! full item. Only the messages from the server are delta encoded.
+
+use crate::patch::Patch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Items sent between two full ones.
+pub const FULL_EVERY: u64 = 100;
+
+/// A message of a `delta` stream.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Delta {
+    /// The whole item.
+    Full(Value),
+    /// The changes since the previous item.
+    Diff(Patch),
+}
+
+/// Reassembles the items of a `delta` stream from its messages.
+#[derive(Debug, Clone, Default)]
+pub struct DeltaDecoder {
+    previous: Option<Value>,
+}
+
+impl DeltaDecoder {
+    /// A decoder waiting for a full item.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The item `delta` stands for. After an error the decoder skips
+    /// diffs until the next full item.
+    pub fn decode(&mut self, delta: Delta) -> Result<Value, DeltaError> {
+        let item = match delta {
+            Delta::Full(item) => item,
+            Delta::Diff(patch) => {
+                let mut item = self.previous.take().ok_or(DeltaError::NoBase)?;
+                json_patch::patch(&mut item, &patch)
+                    .map_err(|e| DeltaError::Patch(e.to_string()))?;
+                item
+            }
+        };
+        self.previous = Some(item.clone());
+        Ok(item)
+    }
+}
+
+/// A message of a `delta` stream could not be reassembled.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DeltaError {
+    /// A diff arrived without a full item to apply it to.
+    #[error("received a diff before a full item")]
+    NoBase,
+    /// A diff doesn't apply to the previous item.
+    #[error("diff does not apply: {0}")]
+    Patch(String),
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{Delta, FULL_EVERY};
+    use crate::rpc::ServerFnError;
+    use crate::stream::ServerStream;
+    use futures_util::StreamExt;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    /// Send the items of `stream` as [`Delta`]s, in full every
+    /// [`FULL_EVERY`] items. Used by `delta` handlers.
+    pub fn encode<T>(stream: ServerStream<T>) -> ServerStream<Delta>
+    where
+        T: Serialize + 'static,
+    {
+        encode_every(stream, FULL_EVERY)
+    }
+
+    /// [`encode`], sending every `full_every`th item in full.
+    pub fn encode_every<T>(stream: ServerStream<T>, full_every: u64) -> ServerStream<Delta>
+    where
+        T: Serialize + 'static,
+    {
+        let full_every = full_every.max(1);
+        let mut previous: Option<Value> = None;
+        let mut count = 0u64;
+        ServerStream::from_results(stream.map(move |item| {
+            let item = serde_json::to_value(item?)
+                .map_err(|e| ServerFnError::Serialization(e.to_string()))?;
+            let delta = match previous.as_ref() {
+                Some(previous) if count % full_every != 0 => {
+                    Delta::Diff(json_patch::diff(previous, &item))
+                }
+                _ => Delta::Full(item.clone()),
+            };
+            previous = Some(item);
+            count += 1;
+            Ok(delta)
+        }))
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{Delta, DeltaDecoder};
+    use crate::rpc::ServerFnError;
+    use crate::stream::ServerStream;
+    use futures_util::StreamExt;
+    use serde::de::DeserializeOwned;
+
+    /// Reassemble the items of a `delta` stream.
+    pub fn decode<T>(stream: ServerStream<Delta>) -> ServerStream<T>
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let mut decoder = DeltaDecoder::new();
+        ServerStream::from_results(stream.map(move |delta| {
+            let item = decoder
+                .decode(delta?)
+                .map_err(|e| ServerFnError::Deserialization(e.to_string()))?;
+            serde_json::from_value(item).map_err(|e| ServerFnError::Deserialization(e.to_string()))
+        }))
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::stream::ServerStream;
+    use futures_util::StreamExt;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn items_are_sent_as_diffs_between_full_ones() {
+        let ticks = (0..5).map(|i| json!({ "name": "cpu", "value": i, "unit": "%" }));
+        let deltas: Vec<Delta> =
+            encode_every(ServerStream::new(futures_util::stream::iter(ticks)), 3)
+                .map(Result::unwrap)
+                .collect()
+                .await;
+        assert!(matches!(
+            deltas[..],
+            [
+                Delta::Full(_),
+                Delta::Diff(_),
+                Delta::Diff(_),
+                Delta::Full(_),
+                Delta::Diff(_)
+            ]
+        ));
+        assert_eq!(
+            serde_json::to_value(&deltas[1]).unwrap(),
+            json!({ "diff": [{ "op": "replace", "path": "/value", "value": 1 }] })
+        );
+
+        let mut decoder = DeltaDecoder::new();
+        for (i, delta) in deltas.into_iter().enumerate() {
+            assert_eq!(decoder.decode(delta).unwrap()["value"], i);
+        }
+    }
+
+    #[test]
+    fn decoder_waits_for_a_full_item_after_an_error() {
+        let diff = |value| {
+            Delta::Diff(
+                serde_json::from_value(json!([{ "op": "replace", "path": "/v", "value": value }]))
+                    .unwrap(),
+            )
+        };
+        let mut decoder = DeltaDecoder::new();
+        assert_eq!(decoder.decode(diff(1)), Err(DeltaError::NoBase));
+        assert_eq!(
+            decoder.decode(Delta::Full(json!({ "v": 0 }))).unwrap(),
+            json!({ "v": 0 })
+        );
+        assert_eq!(decoder.decode(diff(1)).unwrap(), json!({ "v": 1 }));
+
+        let missing =
+            Delta::Diff(serde_json::from_value(json!([{ "op": "remove", "path": "/x" }])).unwrap());
+        assert!(matches!(decoder.decode(missing), Err(DeltaError::Patch(_))));
+        assert_eq!(decoder.decode(diff(2)), Err(DeltaError::NoBase));
+    }
+}
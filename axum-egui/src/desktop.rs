@@ -0,0 +1,204 @@
+//! Run the web app as a desktop tool.
+//!
+//! [`Serve::desktop`] serves the app on localhost and opens it in a window of
+//! its own, so the exact web build ships as a desktop tool:
+//!
+//! ```ignore
+//! axum_egui::serve(app, ([127, 0, 0, 1], 0))
+//!     .assets::<Assets>()
+//!     .desktop()
+//!     .window_size(1280, 800)
+//!     .await?;
+//! ```
+//!
+//! The window is a Chromium-family browser (Chrome, Chromium, Edge) in app
+//! mode (`--app=<url>`): no tabs or address bar, and a profile of its own in
+//! [`profile_dir`], so closing the window ends the process and the server
+//! shuts down with it. Without such a browser the app opens in the default
+//! browser and the server runs until Ctrl+C.
+//!
+//! Requires the `desktop` feature. For a native window without a browser,
+//! run the frontend crate itself with eframe's native backend.
+
+use crate::serve::{Serve, shutdown_signal};
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::{Child, Command, Stdio};
+
+/// Environment variable naming the browser binary to open the window with.
+pub const DESKTOP_BROWSER_ENV: &str = "AXUM_EGUI_DESKTOP_BROWSER";
+
+/// Browsers with an app mode, tried in order.
+#[cfg(target_os = "macos")]
+const APP_BROWSERS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+];
+#[cfg(target_os = "windows")]
+const APP_BROWSERS: &[&str] = &[
+    r"C:\Program Files (x86)\Microsoft\Edge\Application\msedge.exe",
+    r"C:\Program Files\Microsoft\Edge\Application\msedge.exe",
+    r"C:\Program Files\Google\Chrome\Application\chrome.exe",
+];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const APP_BROWSERS: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "microsoft-edge",
+];
+
+impl Serve {
+    /// Serve the app on localhost and open it in a desktop window instead
+    /// of waiting for browsers. Bind to `127.0.0.1` (port 0 picks a free
+    /// port). See the [module docs](crate::desktop).
+    pub fn desktop(self) -> Desktop {
+        Desktop {
+            serve: self,
+            browser: None,
+            window_size: None,
+        }
+    }
+}
+
+/// A [`Serve`] opened as a desktop window. `.await` it to run the app until
+/// the window is closed.
+pub struct Desktop {
+    serve: Serve,
+    browser: Option<PathBuf>,
+    window_size: Option<(u32, u32)>,
+}
+
+impl Desktop {
+    /// Open the window with this Chromium-family browser binary instead of
+    /// the first one found. [`DESKTOP_BROWSER_ENV`] overrides it.
+    pub fn browser(mut self, path: impl Into<PathBuf>) -> Self {
+        self.browser = Some(path.into());
+        self
+    }
+
+    /// Initial size of the window in pixels.
+    pub fn window_size(mut self, width: u32, height: u32) -> Self {
+        self.window_size = Some((width, height));
+        self
+    }
+
+    async fn run(self) -> io::Result<()> {
+        let (router, addr, shutdown) = self.serve.split();
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let url = format!("http://{}/", listener.local_addr()?);
+        tracing::info!("desktop app on {url}");
+
+        let browser = std::env::var_os(DESKTOP_BROWSER_ENV)
+            .map(PathBuf::from)
+            .or(self.browser);
+        let window = open_window(&url, browser.as_deref(), self.window_size);
+        let closed: Pin<Box<dyn Future<Output = ()> + Send>> = match window {
+            Some(mut child) => Box::pin(async move {
+                let _ = tokio::task::spawn_blocking(move || child.wait()).await;
+            }),
+            None => {
+                tracing::warn!(
+                    "no browser with an app mode found; opening {url} in the default browser"
+                );
+                open_default_browser(&url)?;
+                shutdown.unwrap_or_else(|| Box::pin(shutdown_signal()))
+            }
+        };
+
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(closed)
+        .await
+    }
+}
+
+impl IntoFuture for Desktop {
+    type Output = io::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.run())
+    }
+}
+
+/// The browser profile of desktop windows, kept across runs.
+pub fn profile_dir() -> PathBuf {
+    std::env::temp_dir().join("axum-egui-desktop")
+}
+
+/// Arguments opening `url` in an app window with its own profile.
+fn app_args(url: &str, profile_dir: &Path, window_size: Option<(u32, u32)>) -> Vec<String> {
+    let mut args = vec![
+        format!("--app={url}"),
+        format!("--user-data-dir={}", profile_dir.display()),
+        "--no-first-run".to_string(),
+        "--no-default-browser-check".to_string(),
+    ];
+    if let Some((width, height)) = window_size {
+        args.push(format!("--window-size={width},{height}"));
+    }
+    args
+}
+
+/// Start `browser` (or the first of [`APP_BROWSERS`] that starts) in app
+/// mode.
+fn open_window(
+    url: &str,
+    browser: Option<&Path>,
+    window_size: Option<(u32, u32)>,
+) -> Option<Child> {
+    let args = app_args(url, &profile_dir(), window_size);
+    let candidates = match browser {
+        Some(browser) => vec![browser],
+        None => APP_BROWSERS.iter().map(Path::new).collect(),
+    };
+    candidates.into_iter().find_map(|browser| {
+        Command::new(browser)
+            .args(&args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()
+    })
+}
+
+fn open_default_browser(url: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command.arg(url).spawn().map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn app_args_open_a_sized_window_with_its_own_profile() {
+        let args = app_args(
+            "http://127.0.0.1:4000/",
+            Path::new("/tmp/profile"),
+            Some((1280, 800)),
+        );
+        assert_eq!(args[0], "--app=http://127.0.0.1:4000/");
+        assert_eq!(args[1], "--user-data-dir=/tmp/profile");
+        assert_eq!(args.last().unwrap(), "--window-size=1280,800");
+
+        let args = app_args("http://127.0.0.1:4000/", Path::new("/tmp/profile"), None);
+        assert!(!args.iter().any(|arg| arg.starts_with("--window-size")));
+    }
+}
@@ -0,0 +1,134 @@
+//! Development helpers: local HTTPS with a self-signed certificate.
+//!
+//! Several browser APIs (clipboard, WebTransport, service workers on
+//! non-localhost hosts) require a secure context. [`serve_dev_tls`] serves a
+//! router on `https://localhost:3000` using a self-signed certificate that is
+//! generated once and reused across restarts, so the browser exception only
+//! has to be accepted once.
+//!
+//! Requires the `dev-tls` feature. Not intended for production.
+//!
+//! ```ignore
+//! #[tokio::main]
+//! async fn main() {
+//!     let app = Router::new().route("/", get(index));
+//!     axum_egui::dev::serve_dev_tls(app).await.unwrap();
+//! }
+//! ```
+//!
+//! The certificate lives in `$TMPDIR/axum-egui-dev-tls/` unless
+//! `AXUM_EGUI_DEV_CERT_DIR` points elsewhere. Placing your own `cert.pem` and
+//! `key.pem` there (e.g. from mkcert) uses them instead.
+
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Environment variable overriding the certificate directory.
+pub const DEV_CERT_DIR_ENV: &str = "AXUM_EGUI_DEV_CERT_DIR";
+
+/// Hostnames the generated certificate is valid for.
+const DEV_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// A PEM-encoded certificate and private key.
+#[derive(Debug, Clone)]
+pub struct DevCert {
+    /// The certificate chain in PEM format.
+    pub cert_pem: String,
+    /// The private key in PEM format.
+    pub key_pem: String,
+}
+
+/// Generate a fresh self-signed certificate for `localhost`.
+pub fn generate_dev_cert() -> io::Result<DevCert> {
+    let hosts: Vec<String> = DEV_HOSTS.iter().map(|h| h.to_string()).collect();
+    let certified = rcgen::generate_simple_self_signed(hosts).map_err(io::Error::other)?;
+    Ok(DevCert {
+        cert_pem: certified.cert.pem(),
+        key_pem: certified.key_pair.serialize_pem(),
+    })
+}
+
+/// The directory used to cache the development certificate.
+pub fn dev_cert_dir() -> PathBuf {
+    std::env::var_os(DEV_CERT_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| std::env::temp_dir().join("axum-egui-dev-tls"))
+}
+
+/// Load `cert.pem`/`key.pem` from `dir`, generating and saving them if absent.
+pub fn load_or_generate_dev_cert(dir: &Path) -> io::Result<DevCert> {
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+
+    if cert_path.exists() && key_path.exists() {
+        return Ok(DevCert {
+            cert_pem: std::fs::read_to_string(&cert_path)?,
+            key_pem: std::fs::read_to_string(&key_path)?,
+        });
+    }
+
+    let cert = generate_dev_cert()?;
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(&cert_path, &cert.cert_pem)?;
+    std::fs::write(&key_path, &cert.key_pem)?;
+    Ok(cert)
+}
+
+/// Serve `router` on `https://localhost:3000` with a development certificate.
+pub async fn serve_dev_tls(router: Router) -> io::Result<()> {
+    serve_dev_tls_on(router, SocketAddr::from(([127, 0, 0, 1], 3000))).await
+}
+
+/// Serve `router` over HTTPS on `addr` with a development certificate.
+pub async fn serve_dev_tls_on(router: Router, addr: SocketAddr) -> io::Result<()> {
+    // Several crypto providers may be compiled in; pin the one we enable
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let dir = dev_cert_dir();
+    let cert = load_or_generate_dev_cert(&dir)?;
+    let config =
+        RustlsConfig::from_pem(cert.cert_pem.into_bytes(), cert.key_pem.into_bytes()).await?;
+
+    tracing::info!("Server running on https://localhost:{}", addr.port());
+    tracing::info!(
+        "Using development certificate from {} (self-signed; accept it once in the browser)",
+        dir.display()
+    );
+
+    axum_server::bind_rustls(addr, config)
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_pem_encoded_cert() {
+        let cert = generate_dev_cert().unwrap();
+        assert!(cert.cert_pem.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(cert.key_pem.contains("PRIVATE KEY-----"));
+    }
+
+    #[test]
+    fn reuses_cached_cert() {
+        let dir = std::env::temp_dir().join(format!("axum-egui-dev-tls-{}", std::process::id()));
+        let first = load_or_generate_dev_cert(&dir).unwrap();
+        let second = load_or_generate_dev_cert(&dir).unwrap();
+        assert_eq!(first.cert_pem, second.cert_pem);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cert_is_accepted_by_rustls() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        let cert = generate_dev_cert().unwrap();
+        RustlsConfig::from_pem(cert.cert_pem.into_bytes(), cert.key_pem.into_bytes())
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+//! Simulated latency and failures, to exercise loading states and error UI.
+//!
+//! Against a local server every call returns instantly and never fails, so
+//! spinners, retries and error toasts go untested until production. The
+//! [`chaos`] layer delays every server function call and fails a share of
+//! them with [`CHAOS_CODE`]; on the client, [`set_chaos`] does the same
+//! before calls leave the browser, and can be switched at runtime:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new()
+//!     .route("/api/save", post(save_handler))
+//!     .layer(axum_egui::devtools::chaos(Duration::from_millis(800), 0.2));
+//!
+//! // Client, e.g. from a debug panel
+//! axum_egui::devtools::set_chaos(Some(Chaos::new(Duration::from_secs(2), 0.5)));
+//! ```
+//!
+//! Only `#[server]` functions are affected; assets and other routes pass
+//! through. Requires the `devtools` feature, which has no place in release
+//! builds.
+
+use std::time::Duration;
+
+/// The error code of injected failures.
+pub const CHAOS_CODE: &str = "chaos";
+
+/// How much to degrade server function calls.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chaos {
+    /// Delay added to every call.
+    pub latency: Duration,
+    /// Share of calls that fail, from 0.0 (none) to 1.0 (all).
+    pub error_rate: f64,
+}
+
+impl Chaos {
+    /// Delay calls by `latency` and fail `error_rate` of them.
+    pub fn new(latency: Duration, error_rate: f64) -> Self {
+        Self {
+            latency,
+            error_rate: error_rate.clamp(0.0, 1.0),
+        }
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{CHAOS_CODE, Chaos};
+    use crate::error::ErrorEnvelope;
+    use axum::Json;
+    use axum::body::Body;
+    use axum::http::{Request, Response, StatusCode};
+    use axum::response::IntoResponse;
+    use std::collections::HashSet;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tower::{Layer, Service};
+
+    /// A layer delaying server function calls by `latency` and failing
+    /// `error_rate` of them with `503 Service Unavailable`.
+    pub fn chaos(latency: Duration, error_rate: f64) -> ChaosLayer {
+        let chaos = Chaos::new(latency, error_rate);
+        tracing::warn!(
+            latency_ms = latency.as_millis() as u64,
+            error_rate = chaos.error_rate,
+            "chaos layer enabled: server function calls are delayed and fail on purpose"
+        );
+        ChaosLayer(chaos)
+    }
+
+    /// Layer returned by [`chaos`].
+    #[derive(Debug, Clone, Copy)]
+    pub struct ChaosLayer(Chaos);
+
+    impl<S> Layer<S> for ChaosLayer {
+        type Service = ChaosService<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            let paths = crate::registry::routes()
+                .into_iter()
+                .map(|route| route.path)
+                .collect();
+            ChaosService {
+                inner,
+                chaos: self.0,
+                paths: Arc::new(paths),
+            }
+        }
+    }
+
+    /// Service produced by [`ChaosLayer`].
+    #[derive(Debug, Clone)]
+    pub struct ChaosService<S> {
+        inner: S,
+        chaos: Chaos,
+        paths: Arc<HashSet<&'static str>>,
+    }
+
+    impl<S, ReqBody> Service<Request<ReqBody>> for ChaosService<S>
+    where
+        S: Service<Request<ReqBody>, Response = Response<Body>> + Clone + Send + 'static,
+        S::Future: Send + 'static,
+        ReqBody: Send + 'static,
+    {
+        type Response = Response<Body>;
+        type Error = S::Error;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx)
+        }
+
+        fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+            if !self.paths.contains(request.uri().path()) {
+                return Box::pin(self.inner.call(request));
+            }
+            // The clone that was ready serves this request
+            let clone = self.inner.clone();
+            let mut inner = std::mem::replace(&mut self.inner, clone);
+            let chaos = self.chaos;
+            Box::pin(async move {
+                tokio::time::sleep(chaos.latency).await;
+                if random_unit() < chaos.error_rate {
+                    let envelope = ErrorEnvelope::new(CHAOS_CODE, "injected failure");
+                    return Ok((StatusCode::SERVICE_UNAVAILABLE, Json(envelope)).into_response());
+                }
+                inner.call(request).await
+            })
+        }
+    }
+
+    /// A random number in `0.0..1.0`.
+    fn random_unit() -> f64 {
+        let mut bytes = [0; 8];
+        getrandom::fill(&mut bytes).expect("system random number generator");
+        (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{CHAOS_CODE, Chaos};
+    use crate::rpc::ServerFnError;
+    use std::cell::Cell;
+    use wasm_bindgen::JsValue;
+
+    thread_local! {
+        static CHAOS: Cell<Option<Chaos>> = const { Cell::new(None) };
+    }
+
+    /// Degrade the server function calls made from now on, or stop with
+    /// `None`.
+    pub fn set_chaos(chaos: Option<Chaos>) {
+        CHAOS.set(chaos);
+    }
+
+    /// The chaos in effect on the client, if any.
+    pub fn current_chaos() -> Option<Chaos> {
+        CHAOS.get()
+    }
+
+    /// Delay the call about to be made, and fail it at the error rate.
+    /// Called by [`call`](crate::rpc::call) before the request is sent.
+    pub(crate) async fn inject() -> Result<(), ServerFnError> {
+        let Some(chaos) = current_chaos() else {
+            return Ok(());
+        };
+        sleep(chaos.latency.as_millis().try_into().unwrap_or(i32::MAX)).await;
+        if js_sys::Math::random() < chaos.error_rate {
+            return Err(ServerFnError::Custom {
+                code: CHAOS_CODE.to_string(),
+                message: "injected failure".to_string(),
+                details: None,
+            });
+        }
+        Ok(())
+    }
+
+    async fn sleep(ms: i32) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let timeout = js_sys::Promise::new(&mut |resolve, _| {
+            let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms);
+        });
+        let _: Result<JsValue, JsValue> = wasm_bindgen_futures::JsFuture::from(timeout).await;
+    }
+
+    /// A checkbox and sliders switching [`set_chaos`], for a debug panel.
+    #[cfg(feature = "egui")]
+    pub fn chaos_controls(ui: &mut egui::Ui) {
+        let mut enabled = current_chaos().is_some();
+        let mut settings =
+            current_chaos().unwrap_or(Chaos::new(std::time::Duration::from_secs(1), 0.2));
+        let mut latency_ms = settings.latency.as_millis() as u64;
+        ui.checkbox(&mut enabled, "Simulate slow, failing calls");
+        ui.add_enabled_ui(enabled, |ui| {
+            ui.add(egui::Slider::new(&mut latency_ms, 0..=10_000).text("latency (ms)"));
+            ui.add(egui::Slider::new(&mut settings.error_rate, 0.0..=1.0).text("error rate"));
+        });
+        settings.latency = std::time::Duration::from_millis(latency_ms);
+        set_chaos(enabled.then_some(settings));
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::routing::{get, post};
+    use tower::ServiceExt;
+
+    inventory::submit! {
+        crate::registry::ServerFnRoute {
+            name: "devtools_test_save",
+            module: module_path!(),
+            path: "/api/devtools_test_save",
+            kind: crate::registry::RouteKind::Rpc,
+            post_params: false,
+            auth: false,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    fn app(error_rate: f64) -> Router {
+        Router::new()
+            .route("/api/devtools_test_save", post(|| async { "saved" }))
+            .route("/health", get(|| async { "ok" }))
+            .layer(chaos(Duration::from_millis(50), error_rate))
+    }
+
+    #[tokio::test]
+    async fn server_fn_calls_are_delayed_and_failed() {
+        let call = Request::post("/api/devtools_test_save")
+            .body(Body::empty())
+            .unwrap();
+        let start = std::time::Instant::now();
+        let response = app(0.0).oneshot(call).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        let call = Request::post("/api/devtools_test_save")
+            .body(Body::empty())
+            .unwrap();
+        let response = app(1.0).oneshot(call).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        // Other routes are left alone
+        let start = std::time::Instant::now();
+        let health = Request::get("/health").body(Body::empty()).unwrap();
+        let response = app(1.0).oneshot(health).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
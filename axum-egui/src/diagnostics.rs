@@ -0,0 +1,325 @@
+//! Client log level and diagnostics overlay, switched from the server.
+//!
+//! When a user reports a problem, asking them to open the browser console
+//! and reload a debug build rarely works. Instead, the client follows a
+//! control stream at [`DIAGNOSTICS_PATH`], and operators raise the log level
+//! or turn on the [telemetry overlay](crate::telemetry) of one user, or of
+//! one tab, while it runs:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new().merge(axum_egui::diagnostics::router());
+//!
+//! // An admin endpoint, a CLI command...
+//! diagnostics::set_for_user("ada", Diagnostics::new(LogLevel::Debug).overlay(true));
+//!
+//! // Client, at startup
+//! axum_egui::diagnostics::subscribe({
+//!     let ctx = cc.egui_ctx.clone();
+//!     move || ctx.request_repaint()
+//! });
+//! // Anywhere
+//! diagnostics::log(LogLevel::Debug, format_args!("loaded {} rows", rows.len()));
+//! // At the end of `update`
+//! diagnostics::overlay(ctx);
+//! ```
+//!
+//! Messages are delivered through the [push](crate::push) registry, on a
+//! channel of their own, so they also reach tabs connected to other
+//! instances sharing its backplane. The stream identifies its user with an
+//! [auth](crate::auth) ticket when the server has a `StreamAuth`; without
+//! one, tabs can only be reached by [`connection`], which they can show
+//! to the user to read out to support.
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the control stream served by [`router`].
+pub const DIAGNOSTICS_PATH: &str = "/api/axum-egui/diagnostics";
+
+/// How much the client logs to the browser console. Each level includes
+/// the ones before it.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    /// Nothing.
+    Off,
+    /// Errors only.
+    Error,
+    /// Errors and warnings, the default.
+    #[default]
+    Warn,
+    /// Also informational messages.
+    Info,
+    /// Also debugging messages.
+    Debug,
+    /// Everything.
+    Trace,
+}
+
+/// The diagnostics settings of a client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostics {
+    /// The most detailed level logged.
+    pub log_level: LogLevel,
+    /// Whether [`overlay`] shows the telemetry overlay.
+    pub overlay: bool,
+}
+
+impl Diagnostics {
+    /// Log at `log_level`, without the overlay.
+    pub fn new(log_level: LogLevel) -> Self {
+        Self {
+            log_level,
+            overlay: false,
+        }
+    }
+
+    /// Show the telemetry overlay or not.
+    pub fn overlay(mut self, overlay: bool) -> Self {
+        self.overlay = overlay;
+        self
+    }
+}
+
+/// A message of the control stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsUpdate {
+    /// The id operators address this tab by, see [`connection`].
+    pub connection: u64,
+    /// The settings to apply.
+    pub settings: Diagnostics,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{DIAGNOSTICS_PATH, Diagnostics, DiagnosticsUpdate};
+    use crate::push::{self, ConnectionId};
+    use crate::sse::{Event, KeepAlive, Sse};
+    use axum::Router;
+    use axum::http::request::Parts;
+    use axum::routing::get;
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::sync::{Mutex, OnceLock};
+
+    /// The push channel of the control streams.
+    const CHANNEL: &str = "axum-egui:diagnostics";
+
+    /// Settings of users, for the connections they open later.
+    fn users() -> &'static Mutex<HashMap<String, Diagnostics>> {
+        static USERS: OnceLock<Mutex<HashMap<String, Diagnostics>>> = OnceLock::new();
+        USERS.get_or_init(Default::default)
+    }
+
+    /// The control stream at [`DIAGNOSTICS_PATH`].
+    pub fn router<S>() -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new().route(
+            DIAGNOSTICS_PATH,
+            get(|parts: Parts| async move { sse(parts) }),
+        )
+    }
+
+    fn sse(
+        mut request: Parts,
+    ) -> Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
+        // Anonymous tabs are still reachable by connection
+        let user = crate::auth::authorize(&mut request)
+            .ok()
+            .and_then(|()| request.extensions.get::<crate::auth::AuthUser>())
+            .map(|user| user.id.clone());
+        let initial = user
+            .as_ref()
+            .and_then(|user| users().lock().unwrap().get(user).copied())
+            .unwrap_or_default();
+        let (id, updates) = push::subscribe_on::<Diagnostics>(CHANNEL, user);
+        let updates = updates.filter_map(|settings| async move { settings.ok() });
+        let stream = futures_util::stream::once(async move { initial })
+            .chain(updates)
+            .map(move |settings| {
+                let update = DiagnosticsUpdate {
+                    connection: id.0,
+                    settings,
+                };
+                let event = Event::new()
+                    .json_data(update)
+                    .expect("diagnostics serialize");
+                Ok(event.into())
+            });
+        Sse::new(stream).keep_alive(KeepAlive::default())
+    }
+
+    /// Apply `settings` to every open tab of `user_id`, and to the tabs
+    /// they open on this instance later. Returns how many tabs of this
+    /// instance were updated.
+    pub fn set_for_user(user_id: &str, settings: Diagnostics) -> usize {
+        users()
+            .lock()
+            .unwrap()
+            .insert(user_id.to_string(), settings);
+        push::push_to_user_on(CHANNEL, user_id, &settings)
+    }
+
+    /// Put every tab of `user_id` back to the default settings.
+    pub fn clear_for_user(user_id: &str) -> usize {
+        users().lock().unwrap().remove(user_id);
+        push::push_to_user_on(CHANNEL, user_id, &Diagnostics::default())
+    }
+
+    /// Apply `settings` to the tab `id` only, until it disconnects. Returns
+    /// whether it is connected to this instance.
+    pub fn set_for_connection(id: ConnectionId, settings: Diagnostics) -> bool {
+        push::push_to_connection(id, &settings)
+    }
+
+    /// The open control streams of `user_id` on this instance.
+    pub fn connections(user_id: &str) -> Vec<ConnectionId> {
+        push::connections_on(CHANNEL, user_id)
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{DIAGNOSTICS_PATH, Diagnostics, DiagnosticsUpdate, LogLevel};
+    use crate::sse::SseStream;
+    use futures_util::StreamExt;
+    use std::cell::Cell;
+    use std::fmt::Display;
+    use wasm_bindgen::JsValue;
+
+    thread_local! {
+        static SETTINGS: Cell<Diagnostics> = Cell::new(Diagnostics::default());
+        static CONNECTION: Cell<Option<u64>> = const { Cell::new(None) };
+    }
+
+    /// Follow the control stream at [`DIAGNOSTICS_PATH`], authenticated with
+    /// a ticket when the server issues them. `on_change` runs after every
+    /// update; pass `egui::Context::request_repaint` so the overlay shows.
+    pub fn subscribe(on_change: impl Fn() + 'static) {
+        wasm_bindgen_futures::spawn_local(async move {
+            let url = crate::auth::authorize_url(DIAGNOSTICS_PATH)
+                .await
+                .unwrap_or_else(|_| DIAGNOSTICS_PATH.to_string());
+            let mut stream = match SseStream::<DiagnosticsUpdate>::connect(&url) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log(LogLevel::Warn, format_args!("diagnostics unavailable: {e}"));
+                    return;
+                }
+            };
+            while let Some(update) = stream.next().await {
+                // Connection errors are retried by the EventSource itself
+                let Ok(update) = update else { continue };
+                CONNECTION.set(Some(update.connection));
+                SETTINGS.set(update.settings);
+                on_change();
+            }
+        });
+    }
+
+    /// The current settings: the defaults until [`subscribe`] receives
+    /// some.
+    pub fn settings() -> Diagnostics {
+        SETTINGS.get()
+    }
+
+    /// The id of this tab's control stream, once connected. Operators pass
+    /// it to `set_for_connection`.
+    pub fn connection() -> Option<u64> {
+        CONNECTION.get()
+    }
+
+    /// Whether messages at `level` are logged.
+    pub fn enabled(level: LogLevel) -> bool {
+        level != LogLevel::Off && level <= settings().log_level
+    }
+
+    /// Log `message` to the browser console, if `level` is [`enabled`].
+    pub fn log(level: LogLevel, message: impl Display) {
+        if !enabled(level) {
+            return;
+        }
+        let message = JsValue::from_str(&message.to_string());
+        match level {
+            LogLevel::Off => {}
+            LogLevel::Error => web_sys::console::error_1(&message),
+            LogLevel::Warn => web_sys::console::warn_1(&message),
+            LogLevel::Info => web_sys::console::info_1(&message),
+            LogLevel::Debug => web_sys::console::debug_1(&message),
+            LogLevel::Trace => web_sys::console::log_1(&message),
+        }
+    }
+
+    /// Show the [telemetry overlay](crate::telemetry::overlay) if the
+    /// settings ask for it. Call at the end of every frame.
+    #[cfg(feature = "egui")]
+    pub fn overlay(ctx: &egui::Context) {
+        if settings().overlay {
+            crate::telemetry::overlay(ctx);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn settings_reach_the_connected_tab() {
+        let response = router::<()>()
+            .oneshot(Request::get(DIAGNOSTICS_PATH).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let mut body = response.into_body();
+        let mut next_update = async || {
+            let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+            let frame = String::from_utf8(frame.to_vec()).unwrap();
+            let data = frame.trim().strip_prefix("data: ").unwrap();
+            serde_json::from_str::<DiagnosticsUpdate>(data).unwrap()
+        };
+
+        let first = next_update().await;
+        assert_eq!(first.settings, Diagnostics::default());
+
+        let debug = Diagnostics::new(LogLevel::Debug).overlay(true);
+        assert!(set_for_connection(
+            crate::push::ConnectionId(first.connection),
+            debug
+        ));
+        let update = next_update().await;
+        assert_eq!(update.connection, first.connection);
+        assert_eq!(update.settings, debug);
+    }
+
+    #[test]
+    fn log_levels_are_ordered() {
+        assert!(LogLevel::Error < LogLevel::Warn && LogLevel::Debug < LogLevel::Trace);
+        assert_eq!(
+            serde_json::to_string(&LogLevel::Debug).unwrap(),
+            "\"debug\""
+        );
+    }
+}
@@ -0,0 +1,419 @@
+//! One wire format for errors, with machine-readable codes.
+//!
+//! Every error a server function produces reaches the client as an
+//! [`ErrorEnvelope`]: the body of a failed call, the data of an SSE error
+//! event, and the `Err` of a WebSocket message all look alike:
+//!
+//! ```json
+//! {"code": "not_found", "message": "no invoice 42", "details": {"id": 42}, "request_id": "7f3a"}
+//! ```
+//!
+//! `details` and `request_id` are left out when empty. The request id is the
+//! [`REQUEST_ID_HEADER`] of the request, when a proxy or a layer set one.
+//!
+//! # Codes
+//!
+//! The variants of [`ServerFnError`] have fixed codes, which the client maps
+//! back to the same variant:
+//!
+//! | Variant           | Code              | Details             |
+//! |-------------------|-------------------|---------------------|
+//! | `Serialization`   | `serialization`   |                     |
+//! | `Deserialization` | `deserialization` |                     |
+//! | `Request`         | `request`         |                     |
+//! | `ServerError`     | `server_error`    |                     |
+//! | `SchemaMismatch`  | `schema_mismatch` | `expected`, `found` |
+//! | `Reconnect`       | `reconnect`       |                     |
+//! | `TooLarge`        | `too_large`       | `size`, `limit`     |
+//! | `Lagged`          | `lagged`          | `skipped`           |
+//! | `Timeout`         | `timeout`         | `timeout_ms`        |
+//!
+//! Rejections of the generated handlers use `unsupported_media_type` and
+//! `invalid_arguments` (see [`ArgsRejection`](crate::rpc::ArgsRejection)).
+//!
+//! Application errors bring their own codes: derive [`ErrorCode`] on an enum
+//! (each variant's code is its name in snake_case) and `?` turns it into a
+//! [`ServerFnError::Custom`] carrying the code:
+//!
+//! ```ignore
+//! #[derive(Debug, thiserror::Error, ErrorCode)]
+//! pub enum InvoiceError {
+//!     #[error("no invoice {0}")]
+//!     NotFound(u64),
+//!     #[error("invoice is locked")]
+//!     #[error_code = "locked"]
+//!     AlreadyPaid,
+//! }
+//!
+//! #[server]
+//! pub async fn pay(id: u64) -> Result<(), ServerFnError> {
+//!     let invoice = find(id).ok_or(InvoiceError::NotFound(id))?; // "not_found"
+//!     // ...
+//! }
+//! ```
+//!
+//! # User-facing messages
+//!
+//! [`ErrorMessages`] maps codes to the text the UI shows:
+//!
+//! ```ignore
+//! let messages = ErrorMessages::new()
+//!     .code("not_found", "This invoice no longer exists.")
+//!     .code("locked", "This invoice was already paid.");
+//! if let Err(e) = pay(id).await {
+//!     self.toast = Some(messages.message(&e));
+//! }
+//! ```
+
+use crate::rpc::ServerFnError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Header whose value becomes the [`ErrorEnvelope::request_id`] of errors
+/// answering the request.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The wire format of errors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    /// Stable, machine-readable error code, in snake_case.
+    pub code: String,
+    /// Human-readable description, for logs and developers.
+    pub message: String,
+    /// Structured data about the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+    /// The id of the request that failed, to find it in the server logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl ErrorEnvelope {
+    /// An error with `code` and `message`.
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            details: None,
+            request_id: None,
+        }
+    }
+
+    /// Attach structured data.
+    pub fn details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Attach the id of the failed request, if it has one.
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+}
+
+/// An error type with stable codes, usually derived with
+/// `#[derive(ErrorCode)]`. Such errors convert into
+/// [`ServerFnError::Custom`] with `?`.
+pub trait ErrorCode: std::fmt::Display {
+    /// The code of this error, in snake_case.
+    fn code(&self) -> &'static str;
+
+    /// Structured data sent along as [`ErrorEnvelope::details`].
+    fn details(&self) -> Option<serde_json::Value> {
+        None
+    }
+}
+
+impl<E: ErrorCode> From<E> for ServerFnError {
+    fn from(error: E) -> Self {
+        ServerFnError::Custom {
+            code: error.code().to_string(),
+            message: error.to_string(),
+            details: error.details(),
+        }
+    }
+}
+
+impl ServerFnError {
+    /// The [code](crate::error#codes) of this error on the wire.
+    pub fn code(&self) -> &str {
+        match self {
+            ServerFnError::Serialization(_) => "serialization",
+            ServerFnError::Deserialization(_) => "deserialization",
+            ServerFnError::Request(_) => "request",
+            ServerFnError::ServerError(_) => "server_error",
+            ServerFnError::SchemaMismatch { .. } => "schema_mismatch",
+            ServerFnError::Reconnect => "reconnect",
+            ServerFnError::TooLarge { .. } => "too_large",
+            ServerFnError::Lagged { .. } => "lagged",
+            ServerFnError::Timeout { .. } => "timeout",
+            ServerFnError::Custom { code, .. } => code,
+        }
+    }
+}
+
+impl From<ServerFnError> for ErrorEnvelope {
+    fn from(error: ServerFnError) -> Self {
+        let code = error.code().to_string();
+        match error {
+            ServerFnError::Serialization(message)
+            | ServerFnError::Deserialization(message)
+            | ServerFnError::Request(message)
+            | ServerFnError::ServerError(message) => ErrorEnvelope::new(code, message),
+            ServerFnError::SchemaMismatch { expected, found } => {
+                ErrorEnvelope::new(code, error.to_string())
+                    .details(serde_json::json!({ "expected": expected, "found": found }))
+            }
+            ServerFnError::TooLarge { size, limit } => ErrorEnvelope::new(code, error.to_string())
+                .details(serde_json::json!({ "size": size, "limit": limit })),
+            ServerFnError::Lagged { skipped } => ErrorEnvelope::new(code, error.to_string())
+                .details(serde_json::json!({ "skipped": skipped })),
+            ServerFnError::Timeout { timeout_ms } => ErrorEnvelope::new(code, error.to_string())
+                .details(serde_json::json!({ "timeout_ms": timeout_ms })),
+            ServerFnError::Reconnect => ErrorEnvelope::new(code, error.to_string()),
+            ServerFnError::Custom {
+                message, details, ..
+            } => ErrorEnvelope {
+                code,
+                message,
+                details,
+                request_id: None,
+            },
+        }
+    }
+}
+
+impl From<ErrorEnvelope> for ServerFnError {
+    fn from(envelope: ErrorEnvelope) -> Self {
+        let detail = |name: &str| {
+            envelope
+                .details
+                .as_ref()
+                .and_then(|details| details.get(name))
+                .and_then(serde_json::Value::as_u64)
+        };
+        match envelope.code.as_str() {
+            "serialization" => return ServerFnError::Serialization(envelope.message),
+            "deserialization" => return ServerFnError::Deserialization(envelope.message),
+            "request" => return ServerFnError::Request(envelope.message),
+            "server_error" => return ServerFnError::ServerError(envelope.message),
+            "reconnect" => return ServerFnError::Reconnect,
+            "schema_mismatch" => {
+                if let (Some(expected), Some(found)) = (detail("expected"), detail("found")) {
+                    return ServerFnError::SchemaMismatch { expected, found };
+                }
+            }
+            "too_large" => {
+                if let (Some(size), Some(limit)) = (detail("size"), detail("limit")) {
+                    return ServerFnError::TooLarge {
+                        size: size as usize,
+                        limit: limit as usize,
+                    };
+                }
+            }
+            "lagged" => {
+                if let Some(skipped) = detail("skipped") {
+                    return ServerFnError::Lagged { skipped };
+                }
+            }
+            "timeout" => {
+                if let Some(timeout_ms) = detail("timeout_ms") {
+                    return ServerFnError::Timeout { timeout_ms };
+                }
+            }
+            _ => {}
+        }
+        ServerFnError::Custom {
+            code: envelope.code,
+            message: envelope.message,
+            details: envelope.details,
+        }
+    }
+}
+
+/// User-facing messages for error codes.
+///
+/// [`ErrorMessages::new`] has messages for the codes of connection
+/// problems (`request`, `timeout`, `reconnect`, `schema_mismatch`,
+/// `too_large`);
+/// errors with other codes show their own message unless a
+/// [fallback](Self::fallback) is set.
+#[derive(Debug, Clone)]
+pub struct ErrorMessages {
+    messages: HashMap<String, String>,
+    fallback: Option<String>,
+}
+
+impl Default for ErrorMessages {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorMessages {
+    /// Messages for connection problems only.
+    pub fn new() -> Self {
+        let messages = [
+            (
+                "request",
+                "Can't reach the server. Check your connection and try again.",
+            ),
+            (
+                "timeout",
+                "The server is taking too long to respond. Try again later.",
+            ),
+            ("reconnect", "Reconnecting to the server…"),
+            (
+                "schema_mismatch",
+                "A new version of this app is available. Reload the page.",
+            ),
+            (
+                "too_large",
+                "The server sent more data than this page can show.",
+            ),
+        ];
+        Self {
+            messages: messages
+                .into_iter()
+                .map(|(code, message)| (code.to_string(), message.to_string()))
+                .collect(),
+            fallback: None,
+        }
+    }
+
+    /// Show `message` for errors with `code`.
+    pub fn code(mut self, code: impl Into<String>, message: impl Into<String>) -> Self {
+        self.messages.insert(code.into(), message.into());
+        self
+    }
+
+    /// Show `message` for codes without a message of their own, instead of
+    /// the error's message.
+    pub fn fallback(mut self, message: impl Into<String>) -> Self {
+        self.fallback = Some(message.into());
+        self
+    }
+
+    /// The message to show for `error`.
+    pub fn message(&self, error: &ServerFnError) -> String {
+        if let Some(message) = self.messages.get(error.code()) {
+            return message.clone();
+        }
+        match (&self.fallback, error) {
+            (Some(fallback), _) => fallback.clone(),
+            (
+                None,
+                ServerFnError::Custom { message, .. }
+                | ServerFnError::ServerError(message)
+                | ServerFnError::Serialization(message)
+                | ServerFnError::Deserialization(message)
+                | ServerFnError::Request(message),
+            ) => message.clone(),
+            (None, error) => error.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{ErrorEnvelope, REQUEST_ID_HEADER};
+    use axum::Json;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::{IntoResponse, Response};
+
+    /// The [`REQUEST_ID_HEADER`] of a request.
+    pub fn request_id(headers: &HeaderMap) -> Option<String> {
+        headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    }
+
+    /// `error` as a `status` response with an [`ErrorEnvelope`] body.
+    pub fn error_response(
+        status: StatusCode,
+        error: impl Into<ErrorEnvelope>,
+        request_id: Option<String>,
+    ) -> Response {
+        (status, Json(error.into().with_request_id(request_id))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    enum InvoiceError {
+        #[error("no invoice {0}")]
+        NotFound(u64),
+    }
+
+    impl ErrorCode for InvoiceError {
+        fn code(&self) -> &'static str {
+            "not_found"
+        }
+
+        fn details(&self) -> Option<serde_json::Value> {
+            let InvoiceError::NotFound(id) = self;
+            Some(serde_json::json!({ "id": id }))
+        }
+    }
+
+    #[test]
+    fn envelopes_round_trip_server_fn_errors() {
+        let errors = [
+            ServerFnError::Deserialization("bad".into()),
+            ServerFnError::ServerError("boom".into()),
+            ServerFnError::SchemaMismatch {
+                expected: 1,
+                found: 2,
+            },
+            ServerFnError::Reconnect,
+            ServerFnError::TooLarge { size: 10, limit: 5 },
+            ServerFnError::Lagged { skipped: 3 },
+            ServerFnError::Timeout { timeout_ms: 5000 },
+            InvoiceError::NotFound(42).into(),
+        ];
+        for error in errors {
+            let json = serde_json::to_string(&ErrorEnvelope::from(error.clone())).unwrap();
+            let envelope: ErrorEnvelope = serde_json::from_str(&json).unwrap();
+            assert_eq!(envelope.code, error.code());
+            assert_eq!(ServerFnError::from(envelope).to_string(), error.to_string());
+        }
+
+        let envelope = ErrorEnvelope::from(ServerFnError::from(InvoiceError::NotFound(42)));
+        assert_eq!(
+            serde_json::to_value(&envelope).unwrap(),
+            serde_json::json!({ "code": "not_found", "message": "no invoice 42", "details": { "id": 42 } })
+        );
+    }
+
+    #[test]
+    fn messages_map_codes() {
+        let messages = ErrorMessages::new().code("not_found", "Invoice is gone.");
+        assert_eq!(
+            messages.message(&InvoiceError::NotFound(1).into()),
+            "Invoice is gone."
+        );
+        assert_eq!(
+            messages.message(&ServerFnError::ServerError("db down".into())),
+            "db down"
+        );
+        assert!(
+            messages
+                .message(&ServerFnError::Request("offline".into()))
+                .starts_with("Can't reach the server")
+        );
+
+        let messages = messages.fallback("Something went wrong.");
+        assert_eq!(
+            messages.message(&ServerFnError::ServerError("db down".into())),
+            "Something went wrong."
+        );
+    }
+}
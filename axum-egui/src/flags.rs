@@ -0,0 +1,304 @@
+//! Feature flags evaluated by the server for each request.
+//!
+//! A [`FlagSource`] decides which flags are on for a request (by user,
+//! cookie, percentage rollout...). The page carries the flags of the request
+//! that loaded it in the [`FLAGS_SLOT`] slot, and [`Flags::router`] streams
+//! re-evaluated flags to connected clients whenever [`Flags::refresh`] is
+//! called, so UI features can be toggled without a redeploy:
+//!
+//! ```ignore
+//! // Server
+//! let flags = Flags::new(|request: &Parts| {
+//!     FeatureFlags::new().set("new_editor", is_beta_user(request))
+//! });
+//!
+//! async fn index(flags: FeatureFlags) -> App<MyApp, Assets> {
+//!     App::new(MyApp::default()).feature_flags(&flags)
+//! }
+//!
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .merge(flags.router())
+//!     .with_state(flags.clone()); // or any state with `FromRef` for `Flags`
+//!
+//! // Client
+//! axum_egui::flags::subscribe({
+//!     let ctx = cc.egui_ctx.clone();
+//!     move || ctx.request_repaint()
+//! })?;
+//! if axum_egui::flags::flags().is_enabled("new_editor") { /* ... */ }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Name of the [slot](crate::slots) carrying the flags of the page.
+pub const FLAGS_SLOT: &str = "flags";
+
+/// Path of the live flag stream served by [`Flags::router`].
+pub const FLAGS_PATH: &str = "/api/flags";
+
+/// The feature flags of one request. Flags that are not set are disabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureFlags(BTreeMap<String, bool>);
+
+impl FeatureFlags {
+    /// No flags enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn the flag `name` on or off.
+    pub fn set(mut self, name: impl Into<String>, enabled: bool) -> Self {
+        self.0.insert(name.into(), enabled);
+        self
+    }
+
+    /// Turn the flag `name` on.
+    pub fn enable(self, name: impl Into<String>) -> Self {
+        self.set(name, true)
+    }
+
+    /// Whether the flag `name` is on.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.0.get(name).copied().unwrap_or(false)
+    }
+
+    /// All flags that have been set, with their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.0
+            .iter()
+            .map(|(name, enabled)| (name.as_str(), *enabled))
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, bool)> for FeatureFlags {
+    fn from_iter<I: IntoIterator<Item = (K, bool)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{FLAGS_PATH, FeatureFlags};
+    use crate::sse::{Event, KeepAlive, Sse};
+    use axum::Router;
+    use axum::extract::{FromRef, FromRequestParts};
+    use axum::http::request::Parts;
+    use axum::routing::get;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+    use tokio::sync::watch;
+
+    /// Decides which flags are on for a request.
+    ///
+    /// Implemented for closures taking the request [`Parts`].
+    pub trait FlagSource: Send + Sync + 'static {
+        /// The flags of `request`.
+        fn evaluate(&self, request: &Parts) -> FeatureFlags;
+    }
+
+    impl<F> FlagSource for F
+    where
+        F: Fn(&Parts) -> FeatureFlags + Send + Sync + 'static,
+    {
+        fn evaluate(&self, request: &Parts) -> FeatureFlags {
+            self(request)
+        }
+    }
+
+    /// A shared [`FlagSource`] with change notifications. Cheap to clone.
+    #[derive(Clone)]
+    pub struct Flags {
+        source: Arc<dyn FlagSource>,
+        changed: Arc<watch::Sender<()>>,
+    }
+
+    impl std::fmt::Debug for Flags {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Flags").finish_non_exhaustive()
+        }
+    }
+
+    impl Flags {
+        /// Evaluate flags with `source`.
+        pub fn new(source: impl FlagSource) -> Self {
+            Self {
+                source: Arc::new(source),
+                changed: Arc::new(watch::Sender::new(())),
+            }
+        }
+
+        /// The flags of `request`.
+        pub fn evaluate(&self, request: &Parts) -> FeatureFlags {
+            self.source.evaluate(request)
+        }
+
+        /// Re-evaluate the flags of every connected client, e.g. after the
+        /// source's configuration changed. Clients whose flags differ get the
+        /// new ones.
+        pub fn refresh(&self) {
+            self.changed.send_replace(());
+        }
+
+        /// The live flag stream at [`FLAGS_PATH`], evaluated for the request
+        /// that opened it.
+        pub fn router<S>(&self) -> Router<S>
+        where
+            S: Clone + Send + Sync + 'static,
+        {
+            let flags = self.clone();
+            Router::new().route(
+                FLAGS_PATH,
+                get(move |parts: Parts| {
+                    let flags = flags.clone();
+                    async move { flags.sse(parts) }
+                }),
+            )
+        }
+
+        fn sse(
+            self,
+            request: Parts,
+        ) -> Sse<impl futures_util::Stream<Item = Result<axum::response::sse::Event, Infallible>>>
+        {
+            let mut changed = self.changed.subscribe();
+            changed.mark_changed();
+            let stream = futures_util::stream::unfold(
+                (self, request, changed, None),
+                |(flags, request, mut changed, last)| async move {
+                    loop {
+                        changed.changed().await.ok()?;
+                        let current = flags.evaluate(&request);
+                        if last.as_ref() == Some(&current) {
+                            continue;
+                        }
+                        let event = Event::new().json_data(&current).expect("flags serialize");
+                        return Some((Ok(event.into()), (flags, request, changed, Some(current))));
+                    }
+                },
+            );
+            Sse::new(stream).keep_alive(KeepAlive::default())
+        }
+    }
+
+    /// Extracts the flags of the request from a [`Flags`] in the router
+    /// state.
+    impl<S> FromRequestParts<S> for FeatureFlags
+    where
+        Flags: FromRef<S>,
+        S: Send + Sync,
+    {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+            Ok(Flags::from_ref(state).evaluate(parts))
+        }
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{FLAGS_PATH, FLAGS_SLOT, FeatureFlags};
+    use crate::sse::{SseError, SseStream};
+    use futures_util::StreamExt;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static FLAGS: RefCell<Option<FeatureFlags>> = const { RefCell::new(None) };
+    }
+
+    /// The current flags: those of the page until [`subscribe`] receives an
+    /// update. All flags are off if the page carries none.
+    pub fn flags() -> FeatureFlags {
+        FLAGS.with_borrow_mut(|flags| {
+            flags
+                .get_or_insert_with(|| crate::slots::read(FLAGS_SLOT).unwrap_or_default())
+                .clone()
+        })
+    }
+
+    /// Follow flag changes from [`FLAGS_PATH`]. `on_change` runs after every
+    /// update; pass `egui::Context::request_repaint` so the UI redraws.
+    pub fn subscribe(on_change: impl Fn() + 'static) -> Result<(), SseError> {
+        let mut stream = SseStream::<FeatureFlags>::connect(FLAGS_PATH)?;
+        wasm_bindgen_futures::spawn_local(async move {
+            while let Some(update) = stream.next().await {
+                // Connection errors are retried by the EventSource itself
+                let Ok(update) = update else { continue };
+                FLAGS.set(Some(update));
+                on_change();
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+    use axum::http::request::Parts;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tower::ServiceExt;
+
+    #[test]
+    fn unset_flags_are_disabled() {
+        let flags = FeatureFlags::new().enable("new_editor").set("beta", false);
+        assert!(flags.is_enabled("new_editor"));
+        assert!(!flags.is_enabled("beta"));
+        assert!(!flags.is_enabled("unknown"));
+        assert_eq!(
+            serde_json::to_string(&flags).unwrap(),
+            r#"{"beta":false,"new_editor":true}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_sends_flags_again_after_refresh() {
+        let rollout = Arc::new(AtomicBool::new(false));
+        let flags = Flags::new({
+            let rollout = rollout.clone();
+            move |request: &Parts| {
+                let beta = request.headers.contains_key("x-beta");
+                FeatureFlags::new().set("new_editor", beta && rollout.load(Ordering::SeqCst))
+            }
+        });
+        let app: axum::Router = flags.router();
+        let response = app
+            .oneshot(
+                Request::get(FLAGS_PATH)
+                    .header("x-beta", "1")
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let mut body = response.into_body();
+
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        assert!(String::from_utf8_lossy(&frame).contains(r#"{"new_editor":false}"#));
+
+        rollout.store(true, Ordering::SeqCst);
+        flags.refresh();
+        let frame = body.frame().await.unwrap().unwrap().into_data().unwrap();
+        assert!(String::from_utf8_lossy(&frame).contains(r#"{"new_editor":true}"#));
+    }
+}
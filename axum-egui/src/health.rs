@@ -0,0 +1,238 @@
+//! Liveness and readiness endpoints for load balancers and orchestrators.
+//!
+//! [`HEALTHZ_PATH`] answers `200` as long as the process serves requests.
+//! [`READYZ_PATH`] runs the readiness checks and answers `200` if they all
+//! pass, `503 Service Unavailable` otherwise, with the result of each check
+//! as JSON:
+//!
+//! ```ignore
+//! let health = Health::new()
+//!     .assets::<Assets>()
+//!     .check("database", move || {
+//!         let pool = pool.clone();
+//!         async move { pool.ping().await.map_err(|e| e.to_string()) }
+//!     });
+//! axum_egui::serve(app, addr).health(health).await?;
+//!
+//! // GET /readyz
+//! // {"ready":false,"checks":[{"name":"server_fns","ok":true},
+//! //   {"name":"assets","ok":true},{"name":"database","ok":false,"error":"connection refused"}]}
+//! ```
+//!
+//! Without custom checks, [`routes`] is enough. Checks run concurrently on
+//! every request; keep them cheap and bound their time yourself.
+
+use axum::Router;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Json};
+use axum::routing::get;
+use futures_util::future::{BoxFuture, join_all};
+use rust_embed::RustEmbed;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Path of the liveness endpoint.
+pub const HEALTHZ_PATH: &str = "/healthz";
+
+/// Path of the readiness endpoint.
+pub const READYZ_PATH: &str = "/readyz";
+
+type Check = dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync;
+
+/// The outcome of one readiness check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// The check name.
+    pub name: String,
+    /// Whether it passed.
+    pub ok: bool,
+    /// Why it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The body of a readiness response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Readiness {
+    /// Whether every check passed.
+    pub ready: bool,
+    /// Each check, in the order they were added.
+    pub checks: Vec<CheckResult>,
+}
+
+/// Readiness checks. Starts with a check that at least one server function
+/// is registered. Cheap to clone.
+#[derive(Clone)]
+pub struct Health {
+    checks: Vec<(String, Arc<Check>)>,
+}
+
+impl std::fmt::Debug for Health {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self.checks.iter().map(|(name, _)| name).collect();
+        f.debug_struct("Health").field("checks", &names).finish()
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self { checks: Vec::new() }.check("server_fns", || async {
+            if crate::registry::routes().is_empty() {
+                Err("no server functions are registered".to_string())
+            } else {
+                Ok(())
+            }
+        })
+    }
+}
+
+impl Health {
+    /// The default checks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the check `name`, which fails with a message, e.g. a database
+    /// ping.
+    pub fn check<F, Fut>(mut self, name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.checks
+            .push((name.into(), Arc::new(move || Box::pin(check()))));
+        self
+    }
+
+    /// Check that the frontend in `A` was embedded, i.e. that it has an
+    /// `index.html`.
+    pub fn assets<A: RustEmbed>(self) -> Self {
+        self.check("assets", || async {
+            match A::get("index.html") {
+                Some(_) => Ok(()),
+                None => Err("index.html is not embedded; was the frontend built?".to_string()),
+            }
+        })
+    }
+
+    /// Run every check. A [drained](crate::instance::drain) instance is
+    /// never ready.
+    pub async fn readiness(&self) -> Readiness {
+        let mut results = join_all(self.checks.iter().map(|(name, check)| async move {
+            let result = check().await;
+            CheckResult {
+                name: name.clone(),
+                ok: result.is_ok(),
+                error: result.err(),
+            }
+        }))
+        .await;
+        if crate::instance::is_draining() {
+            results.push(CheckResult {
+                name: "drain".to_string(),
+                ok: false,
+                error: Some("the instance is draining".to_string()),
+            });
+        }
+        Readiness {
+            ready: results.iter().all(|check| check.ok),
+            checks: results,
+        }
+    }
+
+    /// The [`HEALTHZ_PATH`] and [`READYZ_PATH`] endpoints.
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let health = self.clone();
+        Router::new()
+            .route(
+                HEALTHZ_PATH,
+                get(|| async { Json(serde_json::json!({ "status": "ok" })) }),
+            )
+            .route(
+                READYZ_PATH,
+                get(move || {
+                    let health = health.clone();
+                    async move {
+                        let readiness = health.readiness().await;
+                        let status = if readiness.ready {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        };
+                        (status, Json(readiness)).into_response()
+                    }
+                }),
+            )
+    }
+}
+
+/// The endpoints with the default checks.
+pub fn routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Health::new().router()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets/"]
+    struct TestAssets;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets_no_index/"]
+    struct NoAssets;
+
+    async fn get_json(router: &Router, path: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn readiness_reports_each_check() {
+        let router: Router = Health::new()
+            .assets::<TestAssets>()
+            .check("database", || async { Ok(()) })
+            .router();
+        let (status, body) = get_json(&router, HEALTHZ_PATH).await;
+        assert_eq!(
+            (status, body),
+            (StatusCode::OK, serde_json::json!({ "status": "ok" }))
+        );
+
+        // The crate's own tests register server functions
+        let (status, body) = get_json(&router, READYZ_PATH).await;
+        assert_eq!(status, StatusCode::OK);
+        let readiness: Readiness = serde_json::from_value(body).unwrap();
+        let names: Vec<_> = readiness.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["server_fns", "assets", "database"]);
+
+        let router: Router = Health::new()
+            .assets::<NoAssets>()
+            .check("database", || async {
+                Err("connection refused".to_string())
+            })
+            .router();
+        let (status, body) = get_json(&router, READYZ_PATH).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["checks"][1]["ok"], false);
+        assert_eq!(
+            body["checks"][2],
+            serde_json::json!({ "name": "database", "ok": false, "error": "connection refused" })
+        );
+    }
+}
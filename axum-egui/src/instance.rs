@@ -0,0 +1,265 @@
+//! Running several server instances behind a load balancer.
+//!
+//! Every stream handshake (SSE response, WebSocket upgrade) carries this
+//! instance's [`id`] in the [`INSTANCE_HEADER`], so it is visible which pod a
+//! client ended up on.
+//!
+//! Streams are long-lived, so a rolling deploy can't just wait for them to
+//! finish. [`drain`] asks the clients of this instance to reconnect, spread
+//! over a time window so the new instances aren't hit by all of them at
+//! once, and fails the readiness check of [`crate::health`] so the load
+//! balancer stops sending new ones. SSE streams end with a
+//! [`RECONNECT_EVENT`](crate::sse::RECONNECT_EVENT) and the browser
+//! reconnects on its own; WebSockets are closed with
+//! [`CLOSE_SERVICE_RESTART`](crate::ws::CLOSE_SERVICE_RESTART), which the client stream reports as
+//! `ServerFnError::Reconnect`. [`Serve::drain_on_shutdown`] drains before a
+//! graceful shutdown:
+//!
+//! ```ignore
+//! axum_egui::serve(app, addr)
+//!     .sticky_sessions(StickySessions::cookie("instance"))
+//!     .drain_on_shutdown(Duration::from_secs(30))
+//!     .await?;
+//! ```
+//!
+//! Load balancers that route on a cookie (nginx `sticky`, HAProxy, Traefik)
+//! can pin a client to one instance with [`StickySessions`], which sets the
+//! cookie to this instance's id, or a value derived from it, and clears it
+//! while draining so reconnecting clients are routed elsewhere.
+//!
+//! [`Serve::drain_on_shutdown`]: crate::serve::Serve::drain_on_shutdown
+
+use axum::http::header::{COOKIE, SET_COOKIE};
+use axum::http::{HeaderValue, Request, Response};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use tower::{Layer, Service};
+
+/// Response header carrying the [`id`] of the instance serving a stream.
+pub const INSTANCE_HEADER: &str = "x-instance-id";
+
+/// Environment variable setting the instance [`id`].
+pub const INSTANCE_ID_ENV: &str = "AXUM_EGUI_INSTANCE_ID";
+
+/// This instance's id: [`INSTANCE_ID_ENV`] if set, else `HOSTNAME` (the pod
+/// name on Kubernetes), else random.
+pub fn id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| {
+        std::env::var(INSTANCE_ID_ENV)
+            .or_else(|_| std::env::var("HOSTNAME"))
+            .ok()
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| format!("{:016x}", random()))
+    })
+}
+
+fn random() -> u64 {
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).expect("system random number generator");
+    u64::from_le_bytes(bytes)
+}
+
+/// When the drain started, and over how long.
+type Drain = Option<(Instant, Duration)>;
+
+fn drain_state() -> &'static watch::Sender<Drain> {
+    static DRAIN: OnceLock<watch::Sender<Drain>> = OnceLock::new();
+    DRAIN.get_or_init(|| watch::channel(None).0)
+}
+
+/// Ask every stream of this instance to reconnect, each at a random point
+/// within `over`. Streams opened later are closed within the same window.
+/// Calling it again has no effect.
+pub fn drain(over: Duration) {
+    drain_state().send_if_modified(|drain| {
+        if drain.is_some() {
+            return false;
+        }
+        tracing::info!(instance = id(), "draining streams over {over:?}");
+        *drain = Some((Instant::now(), over));
+        true
+    });
+}
+
+/// Whether [`drain`] was called.
+pub fn is_draining() -> bool {
+    drain_state().borrow().is_some()
+}
+
+/// Resolves when the stream calling it should be closed: at a random point
+/// of the drain window, never if the instance is not drained.
+pub(crate) async fn drained() {
+    let mut drain = drain_state().subscribe();
+    let Ok(Some((start, over))) = drain.wait_for(Option::is_some).await.map(|state| *state) else {
+        return std::future::pending().await;
+    };
+    let offset = Duration::from_millis(random() % (over.as_millis() as u64).max(1));
+    tokio::time::sleep_until(start + offset).await;
+}
+
+/// A random delay of up to a second, for clients to wait before
+/// reconnecting.
+pub(crate) fn reconnect_jitter() -> Duration {
+    Duration::from_millis(random() % 1000)
+}
+
+/// The [`INSTANCE_HEADER`] value.
+pub(crate) fn header_value() -> HeaderValue {
+    HeaderValue::from_str(id()).unwrap_or(HeaderValue::from_static("unknown"))
+}
+
+/// Sets a cookie naming this instance, for load balancers that route on it.
+///
+/// Install with [`Serve::sticky_sessions`](crate::serve::Serve::sticky_sessions),
+/// or as a layer.
+#[derive(Clone)]
+pub struct StickySessions {
+    cookie: String,
+    max_age: Option<Duration>,
+    value: Arc<dyn Fn(&str) -> String + Send + Sync>,
+}
+
+impl std::fmt::Debug for StickySessions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StickySessions")
+            .field("cookie", &self.cookie)
+            .field("max_age", &self.max_age)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StickySessions {
+    /// Set the cookie `name` to the instance [`id`]. It is a session cookie
+    /// unless [`Self::max_age`] is set.
+    pub fn cookie(name: impl Into<String>) -> Self {
+        Self {
+            cookie: name.into(),
+            max_age: None,
+            value: Arc::new(str::to_string),
+        }
+    }
+
+    /// Keep the cookie for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Derive the cookie value from the instance id, for load balancers
+    /// that expect their own format (e.g. a hash of the backend address).
+    pub fn value(mut self, value: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.value = Arc::new(value);
+        self
+    }
+
+    /// The value of the cookie on a request, if any.
+    fn current<'a>(&self, headers: &'a axum::http::HeaderMap) -> Option<&'a str> {
+        headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|cookies| cookies.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == self.cookie)
+            .map(|(_, value)| value)
+    }
+
+    /// The `Set-Cookie` header to send, if the request's cookie is not
+    /// already right.
+    fn set_cookie(&self, current: Option<&str>) -> Option<HeaderValue> {
+        let cookie = if is_draining() {
+            current?;
+            format!(
+                "{}=; Path=/; Max-Age=0; HttpOnly; SameSite=Lax",
+                self.cookie
+            )
+        } else {
+            let value = (self.value)(id());
+            if current == Some(value.as_str()) {
+                return None;
+            }
+            let mut cookie = format!("{}={value}; Path=/; HttpOnly; SameSite=Lax", self.cookie);
+            if let Some(max_age) = self.max_age {
+                cookie.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+            }
+            cookie
+        };
+        HeaderValue::from_str(&cookie).ok()
+    }
+}
+
+impl<S> Layer<S> for StickySessions {
+    type Service = StickySessionsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StickySessionsService {
+            inner,
+            sessions: self.clone(),
+        }
+    }
+}
+
+/// Service produced by [`StickySessions`].
+#[derive(Debug, Clone)]
+pub struct StickySessionsService<S> {
+    inner: S,
+    sessions: StickySessions,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for StickySessionsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let set_cookie = self
+            .sessions
+            .set_cookie(self.sessions.current(request.headers()));
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            if let Some(set_cookie) = set_cookie {
+                response.headers_mut().append(SET_COOKIE, set_cookie);
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+
+    #[test]
+    fn sticky_cookie_is_set_once() {
+        let sessions = StickySessions::cookie("instance").value(|id| format!("pod-{id}"));
+        let set = sessions.set_cookie(None).unwrap();
+        let set = set.to_str().unwrap();
+        assert!(set.starts_with(&format!("instance=pod-{}; Path=/", id())));
+
+        let mut headers = HeaderMap::new();
+        let cookie = format!("theme=dark; instance=pod-{}", id());
+        headers.insert(COOKIE, cookie.parse().unwrap());
+        assert_eq!(
+            sessions.current(&headers),
+            Some(format!("pod-{}", id()).as_str())
+        );
+        assert!(sessions.set_cookie(sessions.current(&headers)).is_none());
+        assert!(sessions.set_cookie(Some("pod-other")).is_some());
+    }
+}
@@ -0,0 +1,421 @@
+//! State fields resolved on demand.
+//!
+//! A [`Lazy<T>`] field lets one state type serve both light and heavy
+//! routes. Where the value is cheap, the server includes it; where it is
+//! not, [`Lazy::defer`] injects only a token and computes the value when the
+//! client first asks for it:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! pub struct State {
+//!     pub user: User,
+//!     pub history: Lazy<Vec<Order>>,
+//! }
+//!
+//! // Server: a heavy route
+//! App::new(State {
+//!     user,
+//!     history: Lazy::defer(move || async move { load_history(user_id).await }),
+//! })
+//!
+//! // Client, while drawing
+//! match state.history.get() {
+//!     Some(history) => show_history(ui, history),
+//!     None => { ui.spinner(); }
+//! }
+//! ```
+//!
+//! On the client, the first [`Lazy::get`] of a deferred value fetches it
+//! from [`LAZY_PATH`] in the background; later calls return it once it has
+//! arrived. Deferred values are kept on the server for [`LAZY_TTL`] after the
+//! page was rendered and computed at most once. [`serve()`](crate::serve())
+//! mounts the endpoint; use [`routes`] with a plain router.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Path of the endpoint resolving deferred values.
+pub const LAZY_PATH: &str = "/api/axum-egui/lazy";
+
+/// Arguments of the [`LAZY_PATH`] endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LazyArgs {
+    /// The token of the deferred value.
+    pub token: String,
+}
+
+/// How a [`Lazy`] is sent: the value, or the token to fetch it with.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Wire<T> {
+    Value(T),
+    Token(String),
+}
+
+/// Whether the value of a [`Lazy`] is being fetched.
+#[derive(Debug, Default)]
+enum Status {
+    #[default]
+    Idle,
+    #[cfg(feature = "client")]
+    Loading,
+    #[cfg(any(feature = "client", test))]
+    Failed(crate::rpc::ServerFnError),
+}
+
+/// A state field that is either included or fetched on first access.
+///
+/// Clones share the value, so a fetch started through one is seen by all.
+pub struct Lazy<T> {
+    token: Option<String>,
+    value: Arc<OnceLock<T>>,
+    status: Arc<Mutex<Status>>,
+}
+
+impl<T> Clone for Lazy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+            value: self.value.clone(),
+            status: self.status.clone(),
+        }
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for Lazy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.debug_tuple("Lazy").field(&"<deferred>").finish(),
+        }
+    }
+}
+
+impl<T> Lazy<T> {
+    /// A field with its value included.
+    pub fn new(value: T) -> Self {
+        Self {
+            token: None,
+            value: Arc::new(OnceLock::from(value)),
+            status: Arc::default(),
+        }
+    }
+
+    /// The value, if it is included or has been fetched, without fetching
+    /// it.
+    pub fn peek(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Whether the value is available.
+    pub fn is_ready(&self) -> bool {
+        self.value.get().is_some()
+    }
+}
+
+impl<T: Serialize> Serialize for Lazy<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.value.get(), &self.token) {
+            (Some(value), _) => Wire::Value(value).serialize(serializer),
+            (None, Some(token)) => Wire::<&T>::Token(token.clone()).serialize(serializer),
+            (None, None) => Err(serde::ser::Error::custom(
+                "Lazy has neither value nor token",
+            )),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Wire::deserialize(deserializer)? {
+            Wire::Value(value) => Self::new(value),
+            Wire::Token(token) => Self {
+                token: Some(token),
+                value: Arc::default(),
+                status: Arc::default(),
+            },
+        })
+    }
+}
+
+#[cfg(any(feature = "client", test))]
+impl<T: serde::de::DeserializeOwned> Lazy<T> {
+    /// Record the value fetched for this field.
+    fn resolved(&self, result: Result<serde_json::Value, crate::rpc::ServerFnError>) {
+        let result = result.and_then(|value| {
+            serde_json::from_value(value)
+                .map_err(|e| crate::rpc::ServerFnError::Deserialization(e.to_string()))
+        });
+        let mut status = self.status.lock().unwrap();
+        *status = match result {
+            Ok(value) => {
+                let _ = self.value.set(value);
+                Status::Idle
+            }
+            Err(e) => Status::Failed(e),
+        };
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{LAZY_PATH, Lazy, LazyArgs};
+    use crate::rpc::{ServerFnError, json_handler};
+    use axum::Router;
+    use axum::routing::post;
+    use futures_util::FutureExt;
+    use futures_util::future::BoxFuture;
+    use serde::Serialize;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+    use tokio::sync::OnceCell;
+
+    /// How long a deferred value can be fetched after the page was rendered.
+    pub const LAZY_TTL: Duration = Duration::from_secs(5 * 60);
+
+    /// Upper bound on stored deferred values; the closest to expiry are
+    /// evicted first.
+    const MAX_DEFERRED: usize = 10_000;
+
+    type Resolver =
+        Box<dyn FnOnce() -> BoxFuture<'static, Result<serde_json::Value, ServerFnError>> + Send>;
+
+    struct Deferred {
+        resolver: Mutex<Option<Resolver>>,
+        value: OnceCell<Result<serde_json::Value, ServerFnError>>,
+        expires: Instant,
+    }
+
+    fn store() -> &'static Mutex<HashMap<String, Arc<Deferred>>> {
+        static STORE: OnceLock<Mutex<HashMap<String, Arc<Deferred>>>> = OnceLock::new();
+        STORE.get_or_init(Default::default)
+    }
+
+    fn random_id() -> String {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).expect("failed to read OS randomness");
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    impl<T> Lazy<T> {
+        /// A field whose value is computed by `f` when the client first asks
+        /// for it. Only a token goes into the page.
+        pub fn defer<F, Fut>(f: F) -> Self
+        where
+            T: Serialize,
+            F: FnOnce() -> Fut + Send + 'static,
+            Fut: Future<Output = Result<T, ServerFnError>> + Send + 'static,
+        {
+            let resolver: Resolver = Box::new(move || {
+                async move {
+                    let value = f().await?;
+                    serde_json::to_value(value)
+                        .map_err(|e| ServerFnError::Serialization(e.to_string()))
+                }
+                .boxed()
+            });
+            let now = Instant::now();
+            let mut store = store().lock().unwrap();
+            store.retain(|_, deferred| deferred.expires > now);
+            if store.len() >= MAX_DEFERRED {
+                if let Some(oldest) = store
+                    .iter()
+                    .min_by_key(|(_, deferred)| deferred.expires)
+                    .map(|(token, _)| token.clone())
+                {
+                    store.remove(&oldest);
+                }
+            }
+
+            let token = random_id();
+            store.insert(
+                token.clone(),
+                Arc::new(Deferred {
+                    resolver: Mutex::new(Some(resolver)),
+                    value: OnceCell::new(),
+                    expires: now + LAZY_TTL,
+                }),
+            );
+            Self {
+                token: Some(token),
+                value: Arc::default(),
+                status: Arc::default(),
+            }
+        }
+    }
+
+    /// Compute (once) and return the deferred value of `token`.
+    pub async fn resolve(args: LazyArgs) -> Result<serde_json::Value, ServerFnError> {
+        let deferred = store()
+            .lock()
+            .unwrap()
+            .get(&args.token)
+            .filter(|deferred| deferred.expires > Instant::now())
+            .cloned()
+            .ok_or_else(|| ServerFnError::Request("lazy value expired; reload the page".into()))?;
+        deferred
+            .value
+            .get_or_init(|| async {
+                let resolver = deferred.resolver.lock().unwrap().take();
+                match resolver {
+                    Some(resolver) => resolver().await,
+                    None => Err(ServerFnError::ServerError(
+                        "lazy value was not computed".into(),
+                    )),
+                }
+            })
+            .await
+            .clone()
+    }
+
+    /// The [`LAZY_PATH`] endpoint.
+    pub fn routes<S>() -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new().route(LAZY_PATH, post(json_handler(resolve)))
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{LAZY_PATH, Lazy, LazyArgs, Status};
+    use serde::de::DeserializeOwned;
+
+    impl<T: DeserializeOwned + 'static> Lazy<T> {
+        /// The value, starting to fetch it if it is deferred and not being
+        /// fetched yet. Returns `None` until it has arrived.
+        pub fn get(&self) -> Option<&T> {
+            self.fetch(|| {});
+            self.value.get()
+        }
+
+        /// Like [`Lazy::get`], repainting `ctx` when the value arrives.
+        #[cfg(feature = "egui")]
+        pub fn get_with_repaint(&self, ctx: &egui::Context) -> Option<&T> {
+            let ctx = ctx.clone();
+            self.fetch(move || ctx.request_repaint());
+            self.value.get()
+        }
+
+        /// Why fetching the value failed, if it did. The next [`Lazy::get`]
+        /// tries again.
+        pub fn error(&self) -> Option<crate::rpc::ServerFnError> {
+            match &*self.status.lock().unwrap() {
+                Status::Failed(e) => Some(e.clone()),
+                _ => None,
+            }
+        }
+
+        /// Wait for the value, fetching it if needed.
+        pub async fn load(&self) -> Result<&T, crate::rpc::ServerFnError> {
+            if let Some(value) = self.value.get() {
+                return Ok(value);
+            }
+            self.resolved(self.call().await);
+            self.value.get().ok_or_else(|| {
+                self.error().unwrap_or_else(|| {
+                    crate::rpc::ServerFnError::Request("lazy value is not available".into())
+                })
+            })
+        }
+
+        async fn call(&self) -> Result<serde_json::Value, crate::rpc::ServerFnError> {
+            let token = self.token.clone().unwrap_or_default();
+            crate::rpc::call(LAZY_PATH, &LazyArgs { token }).await
+        }
+
+        fn fetch(&self, done: impl FnOnce() + 'static) {
+            if self.value.get().is_some() || self.token.is_none() {
+                return;
+            }
+            {
+                let mut status = self.status.lock().unwrap();
+                if matches!(*status, Status::Loading) {
+                    return;
+                }
+                *status = Status::Loading;
+            }
+            let this = self.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                this.resolved(this.call().await);
+                done();
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::ServerFnError;
+
+    #[test]
+    fn included_values_round_trip() {
+        let json = serde_json::to_string(&Lazy::new(vec![1, 2])).unwrap();
+        assert_eq!(json, r#"{"value":[1,2]}"#);
+        let lazy: Lazy<Vec<i32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(lazy.peek(), Some(&vec![1, 2]));
+    }
+
+    #[test]
+    fn failed_fetches_keep_the_error_until_resolved() {
+        let lazy: Lazy<Vec<i32>> = serde_json::from_str(r#"{"token":"t"}"#).unwrap();
+        lazy.resolved(Err(ServerFnError::Request("offline".into())));
+        match &*lazy.status.lock().unwrap() {
+            Status::Failed(e) => assert_eq!(e.to_string(), "Request error: offline"),
+            status => panic!("expected a failure, got {status:?}"),
+        }
+        assert_eq!(lazy.peek(), None);
+
+        lazy.resolved(Ok(serde_json::json!([3])));
+        assert!(matches!(*lazy.status.lock().unwrap(), Status::Idle));
+        assert_eq!(lazy.peek(), Some(&vec![3]));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn deferred_values_are_computed_once_on_request() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        let lazy = Lazy::defer(|| async {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok(vec!["order".to_string()])
+        });
+        let json = serde_json::to_value(&lazy).unwrap();
+        let token = json["token"].as_str().unwrap().to_string();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 0);
+
+        // The client's copy
+        let client: Lazy<Vec<String>> = serde_json::from_value(json).unwrap();
+        assert!(!client.is_ready());
+        for _ in 0..2 {
+            let value = resolve(LazyArgs {
+                token: token.clone(),
+            })
+            .await;
+            client.resolved(value);
+        }
+        assert_eq!(client.peek(), Some(&vec!["order".to_string()]));
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        let expired = resolve(LazyArgs {
+            token: "unknown".into(),
+        })
+        .await;
+        assert!(matches!(expired, Err(ServerFnError::Request(_))));
+    }
+}
@@ -7,9 +7,109 @@
 //!
 //! - `App<T>` response wrapper for serving egui apps with initial state
 //! - Static file serving utilities for embedded assets
+//! - Asset inventory and startup verification (`assets::ensure`)
+//! - Security headers layer (`security_headers()`)
+//! - CORS for a frontend hosted on another origin (`cors()`)
+//! - `robots.txt`, sitemap and `/.well-known/` files (`site`)
+//! - Per-page title and OpenGraph tags for link previews (`meta`)
+//! - Two-phase hydration of very large states from a MessagePack snapshot
+//!   (`snapshot` feature)
+//! - `Lazy<T>` state fields that the client fetches on first access
+//! - `/healthz` and `/readyz` endpoints with custom readiness checks (`health`)
+//! - One-call `serve()` with compression, tracing and graceful shutdown
+//! - Total and per-IP caps on concurrent streams (`limits`), with gauges in `metrics`
+//! - Live stream connection counts and message rates (`metrics::stream_stats`)
+//! - Same-origin checks on streaming endpoints, configurable with `origin`
+//! - One rotatable master key (`secrets`) for signed values, CSRF tokens and
+//!   private cookies
+//! - The same app as a desktop tool in a browser app window (`desktop`
+//!   feature)
+//! - Headless PNG screenshots of the UI as a loading placeholder or link
+//!   preview (`screenshot` feature)
+//! - Simulated latency and failures of server function calls, on the server
+//!   or toggled in the client (`devtools` feature)
+//! - Environment/TOML server configuration (`config` feature)
+//! - TypeScript definitions for server function endpoints (`ts` feature)
+//! - Postgres `LISTEN`/`NOTIFY` as a source of topic events (`postgres`
+//!   feature)
 //! - Server-Sent Events (SSE) for real-time server-to-client updates
+//! - Differential state updates (`patch`): JSON Patch diffs of a watched
+//!   value, applied to the client's copy with per-field change flags
+//! - `#[derive(SyncedState)]` for structs shared live by server and clients
+//! - Deep links and back/forward navigation for egui views (`route`)
+//! - A shared frontend entry point (`bootstrap`, `#[axum_egui::main]`) reading
+//!   the initial state and starting eframe in the page, with its canvas,
+//!   scale and WebGPU/WebGL preference (`wgpu` feature), and several apps on
+//!   one page
+//! - Named page `slots` next to the initial state, and per-request feature
+//!   `flags` with live updates
+//! - First-party product `analytics`: batched client events fed to a
+//!   pluggable server-side sink
+//! - Client `telemetry`: WASM memory and frame times, an overlay, and an
+//!   opt-in reporter to track heavy screens across releases
+//! - Client panics reported to the server (`crash`), with a crash screen
+//!   showing a reload button and the report's id
+//! - Client log level and telemetry overlay switched per user or tab from
+//!   the server (`diagnostics`)
+//! - Server time on the client, NTP-style, for charts of server-timestamped
+//!   events on skewed clocks (`clock`)
+//! - Client graphics and memory `capabilities` reported to the server, to
+//!   serve WebGL1-only or low-memory devices a lighter page
 //! - WebSockets for bidirectional real-time communication
-//! - Simple RPC helpers for client-server communication
+//! - Simple RPC helpers for client-server communication, over GET with
+//!   query-string arguments for cacheable reads (`#[server(get)]`) or in
+//!   the compact postcard or CBOR formats (`#[server(postcard)]`,
+//!   `#[server(cbor)]`, `postcard` and `cbor` features)
+//! - Identical concurrent calls of expensive reads served by one run of the
+//!   function (`#[server(coalesce)]`, `coalesce`)
+//! - A server-side result `cache` with stale-while-revalidate refreshes
+//!   streamed to open dashboards (`#[server(cache = "30s", stale = "5m")]`)
+//! - Static headers sent with a function's calls and required by its handler,
+//!   for API versioning or shared secrets (`#[server(headers(...))]`)
+//! - Call deadlines aborting the browser's `fetch` with a typed `Timeout`
+//!   error, and optionally on the server (`#[server(timeout = "5s")]`)
+//! - Canary rollouts splitting a function's calls with its rewrite by
+//!   percentage, rule or header, with per-variant metrics (`canary`)
+//! - Per-user call quotas on expensive functions with a pluggable store and
+//!   a typed `QuotaExceeded` error (`#[server(quota = "exports:10/day")]`)
+//! - Streaming uploads: functions taking `body: impl Stream<Item = Bytes>`
+//!   read the request body as it arrives, sent as a `ReadableStream`
+//! - File uploads: `FileUpload` arguments sent as multipart parts, from a
+//!   `web_sys::File` or a file dropped on the egui window
+//! - `#[server]` functions called from desktop apps and CLI tools over a
+//!   reqwest, or a pluggable HTTP client (`native` module, `native-client`
+//!   feature)
+//! - W3C `traceparent` propagation from client calls into server function
+//!   spans (`trace`)
+//! - One error envelope with stable codes across RPC, SSE and WebSocket
+//!   (`error`), `#[derive(ErrorCode)]` and user-facing messages per code
+//! - Client-side size limits on responses and stream messages (`payload`)
+//! - A client-side `query` cache with prefetching, optionally persisted to
+//!   IndexedDB across reloads
+//! - Streaming server functions (`#[server(sse)]`, `#[server(ws)]`) with a
+//!   per-request `context` and disconnect hooks
+//! - Server-initiated messages to one user's open tabs or one connection
+//!   (`push`)
+//! - Schema versions on stream messages (`versioned`, `schema`), so clients
+//!   notice a redeployed server and can reload
+//! - Streams of tokio `broadcast` and `watch` channels, reporting skipped
+//!   items of lagging receivers (`ServerStream::from_broadcast`)
+//! - Conflated streams keeping only the newest pending item, overall or per
+//!   key (`conflate`, `ServerStream::latest_only`)
+//! - Delta-encoded streams sending JSON Patches between periodic full items
+//!   (`delta`)
+//! - Broadcast topics with a bounded, optionally persistent history replayed
+//!   to new subscribers (`topic::LoggedTopic`)
+//! - Fan-out of topics and pushes across server instances over a `backplane`
+//!   (Redis pub/sub with the `redis` feature)
+//! - Instance ids on stream handshakes, sticky-session cookies and gradual
+//!   stream draining for rolling deploys (`instance`)
+//! - Live SSE and WebSocket `connections` with their user, uptime and
+//!   message counts, listed and closed by administrators
+//! - Typed endpoint paths and URL builders, and a `registry` of every server
+//!   function route, exportable as data for proxy and gateway configuration
+//! - Snapshot tests of every server function's wire format (`contract`), to
+//!   catch changes that break cached clients
 //!
 //! # Server Example
 //!
@@ -54,109 +154,263 @@
 
 pub mod rpc;
 
+pub mod error;
+
+pub use axum_egui_macro::ErrorCode;
+
+pub use error::ErrorCode;
+
 // Re-export the server macro
 pub use axum_egui_macro::server;
 
+#[cfg(any(feature = "server", feature = "client", feature = "native-client"))]
+pub mod stream;
+
+#[cfg(any(feature = "server", feature = "client", feature = "native-client"))]
+pub use stream::ServerStream;
+
+#[cfg(any(feature = "server", feature = "client", feature = "native-client"))]
+pub mod upload;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod tickets;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod schema;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod delta;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub use axum_egui_macro::StreamSchema;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod topic;
+
+#[cfg(all(feature = "snapshot", any(feature = "server", feature = "client")))]
+pub mod snapshot;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod lazy;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod patch;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod sync;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub use axum_egui_macro::SyncedState;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub use sync::SyncedState;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod slots;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod trace;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod flags;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod analytics;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod telemetry;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod diagnostics;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod crash;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod clock;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod capabilities;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod route;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod auth;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod audit;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod quota;
+
+#[cfg(any(feature = "server", feature = "client"))]
+pub mod connections;
+
+#[cfg(feature = "client")]
+pub mod concurrency;
+
+#[cfg(feature = "client")]
+pub mod payload;
+
+#[cfg(feature = "client")]
+pub mod query;
+
+#[cfg(all(feature = "eframe", target_arch = "wasm32"))]
+pub mod bootstrap;
+
+// Re-export the frontend entry point macro
+pub use axum_egui_macro::main;
+
+#[cfg(feature = "native-client")]
+pub mod native;
+
+#[cfg(feature = "server")]
+pub mod context;
+
+#[cfg(feature = "server")]
+pub mod registry;
+
+#[cfg(feature = "server")]
+pub mod contract;
+
+#[cfg(feature = "server")]
+pub mod coalesce;
+
+#[cfg(feature = "server")]
+pub mod cache;
+
+#[cfg(feature = "server")]
+pub mod canary;
+
 // ============================================================================
 // Server-only: App wrapper and static file serving
 // ============================================================================
 
 #[cfg(feature = "server")]
-mod app {
-    use axum::{
-        body::Body,
-        http::{StatusCode, Uri, header},
-        response::{Html, IntoResponse, Response},
+mod app;
+
+#[cfg(feature = "server")]
+pub use app::{App, LIVE_RELOAD_ENV, StreamingApp, spa_router, static_handler};
+
+#[cfg(feature = "server")]
+pub mod assets;
+
+#[cfg(feature = "server")]
+pub mod security;
+
+#[cfg(feature = "server")]
+pub use security::security_headers;
+
+#[cfg(feature = "server")]
+pub mod cors;
+
+#[cfg(feature = "server")]
+pub use cors::cors;
+
+#[cfg(feature = "server")]
+pub mod serve;
+
+#[cfg(feature = "server")]
+pub use serve::serve;
+
+#[cfg(feature = "server")]
+pub mod limits;
+
+#[cfg(feature = "server")]
+pub mod origin;
+
+#[cfg(feature = "server")]
+pub mod metrics;
+
+#[cfg(feature = "server")]
+pub mod logging;
+
+#[cfg(feature = "server")]
+pub mod secrets;
+
+#[cfg(feature = "server")]
+pub mod site;
+
+#[cfg(feature = "server")]
+pub mod meta;
+
+#[cfg(feature = "server")]
+pub mod health;
+
+#[cfg(feature = "server")]
+pub mod push;
+
+#[cfg(feature = "server")]
+pub mod backplane;
+
+#[cfg(feature = "server")]
+pub mod instance;
+
+#[cfg(feature = "dev-tls")]
+pub mod dev;
+
+#[cfg(feature = "desktop")]
+pub mod desktop;
+
+#[cfg(all(feature = "devtools", any(feature = "server", feature = "client")))]
+pub mod devtools;
+
+#[cfg(feature = "screenshot")]
+pub mod screenshot;
+
+#[cfg(feature = "config")]
+pub mod config;
+
+#[cfg(feature = "config")]
+pub use config::serve_with_config;
+
+#[cfg(feature = "ts")]
+pub mod ts;
+
+// Used by `#[server]` to derive `TS` for args structs and register
+// endpoints only when this crate's `ts` feature is enabled, so crates
+// defining server functions need no `ts` feature of their own
+#[cfg(feature = "ts")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ts_derive {
+    ($($item:tt)*) => {
+        #[derive($crate::ts::TS)]
+        #[ts(crate = "::axum_egui::ts::__ts_rs")]
+        $($item)*
     };
-    use rust_embed::RustEmbed;
-    use serde::Serialize;
-    use std::marker::PhantomData;
-
-    /// Axum response wrapper for serving egui apps with initial state.
-    ///
-    /// This wrapper injects serialized state into the HTML template, allowing
-    /// the frontend to hydrate with server-provided data.
-    pub struct App<T, A: RustEmbed> {
-        state: T,
-        _assets: PhantomData<A>,
-    }
+}
 
-    impl<T, A: RustEmbed> App<T, A> {
-        /// Create a new App response with the given initial state.
-        pub fn new(state: T) -> Self {
-            Self {
-                state,
-                _assets: PhantomData,
-            }
-        }
-    }
+#[cfg(not(feature = "ts"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ts_derive {
+    ($($item:tt)*) => {
+        $($item)*
+    };
+}
 
-    impl<T: Serialize, A: RustEmbed> IntoResponse for App<T, A> {
-        fn into_response(self) -> Response {
-            let state_json = match serde_json::to_string(&self.state) {
-                Ok(json) => json,
-                Err(e) => {
-                    return Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from(format!("Failed to serialize app state: {e}")))
-                        .unwrap();
-                }
-            };
-
-            let html = match A::get("index.html") {
-                Some(content) => {
-                    let html_str = String::from_utf8_lossy(&content.data);
-                    let state_script = format!(
-                        r#"<script id="axum-egui-state" type="application/json">{}</script>"#,
-                        state_json.replace("</", "<\\/")
-                    );
-                    html_str.replace("<!--AXUM_EGUI_INITIAL_STATE-->", &state_script)
-                }
-                None => {
-                    return Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
-                        .body(Body::from(
-                            "Frontend assets not found. Did you build the frontend?",
-                        ))
-                        .unwrap();
-                }
-            };
-
-            Html(html).into_response()
+#[cfg(feature = "ts")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ts_endpoint {
+    ($($endpoint:tt)*) => {
+        $crate::registry::__submit! {
+            $crate::ts::TsEndpoint { $($endpoint)* }
         }
-    }
+    };
+}
 
-    /// Handler for serving static assets from an embedded `RustEmbed` type.
-    pub async fn static_handler<A: RustEmbed>(uri: Uri) -> impl IntoResponse {
-        let path = uri.path().trim_start_matches('/');
-
-        match A::get(path) {
-            Some(content) => {
-                let mime = mime_guess::from_path(path).first_or_octet_stream();
-
-                Response::builder()
-                    .status(StatusCode::OK)
-                    .header(header::CONTENT_TYPE, mime.as_ref())
-                    .body(Body::from(content.data.to_vec()))
-                    .unwrap()
-            }
-            None => match A::get("index.html") {
-                Some(content) => Response::builder()
-                    .status(StatusCode::OK)
-                    .header(header::CONTENT_TYPE, "text/html")
-                    .body(Body::from(content.data.to_vec()))
-                    .unwrap(),
-                None => Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .body(Body::from("404 Not Found"))
-                    .unwrap(),
-            },
-        }
-    }
+#[cfg(not(feature = "ts"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ts_endpoint {
+    ($($endpoint:tt)*) => {};
 }
 
-#[cfg(feature = "server")]
-pub use app::{App, static_handler};
+#[cfg(feature = "postgres")]
+pub mod postgres;
 
 // ============================================================================
 // SSE (Server-Sent Events) support
@@ -177,11 +431,15 @@ pub use rpc::ServerFnError;
 
 /// Prelude module for convenient imports.
 pub mod prelude {
-    pub use crate::rpc::ServerFnError;
+    pub use crate::error::{ErrorCode, ErrorMessages};
+    pub use crate::rpc::{CallPriority, ServerFnError};
     pub use crate::server;
 
+    #[cfg(any(feature = "server", feature = "client"))]
+    pub use crate::{ServerStream, SyncedState};
+
     #[cfg(feature = "server")]
-    pub use crate::{App, static_handler};
+    pub use crate::{App, security_headers, serve, static_handler};
 
     #[cfg(feature = "server")]
     pub use crate::rpc::{ApiResponse, IntoApiResponse, json_handler};
@@ -192,17 +450,23 @@ pub mod prelude {
     #[cfg(feature = "server")]
     pub use crate::ws::{JsonWebSocket, Message, WebSocket, WebSocketUpgrade, WebSocketUpgradeExt};
 
+    #[cfg(feature = "client")]
+    pub use crate::concurrency::with_priority;
+
     #[cfg(feature = "client")]
     pub use crate::rpc::call;
 
     #[cfg(feature = "client")]
     pub use crate::ws::{WsClientReceiver, WsClientSender, WsError, WsStream};
+
+    #[cfg(feature = "egui")]
+    pub use crate::query::PrefetchExt;
 }
 
 #[cfg(all(test, feature = "server"))]
 mod tests {
     use super::*;
-    use axum::http::{StatusCode, Uri};
+    use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, Uri, header};
     use axum::response::IntoResponse;
     use http_body_util::BodyExt;
     use rust_embed::RustEmbed;
@@ -225,6 +489,12 @@ mod tests {
         String::from_utf8(bytes.to_vec()).unwrap()
     }
 
+    async fn get_static<A: RustEmbed>(uri: Uri) -> axum::response::Response {
+        static_handler::<A>(Method::GET, HeaderMap::new(), uri)
+            .await
+            .into_response()
+    }
+
     #[tokio::test]
     async fn app_injects_state_into_html() {
         let state = TestState {
@@ -263,10 +533,216 @@ mod tests {
         assert!(!body.contains(r#"</script><script>"#));
     }
 
+    #[tokio::test]
+    async fn app_rewrites_assets_to_base_url() {
+        let state = TestState {
+            counter: 1,
+            message: "./not-an-asset".into(),
+        };
+        let app: App<TestState, TestAssets> =
+            App::new(state).asset_base_url("https://cdn.example.com/app/");
+        let body = body_to_string(app.into_response()).await;
+
+        assert!(body.contains("import init from 'https://cdn.example.com/app/app.js';"));
+        assert!(body.contains(r#"<script type="module" crossorigin="anonymous">"#));
+        // State is injected after rewriting and must be left untouched
+        assert!(body.contains(r#""message":"./not-an-asset""#));
+    }
+
+    #[tokio::test]
+    async fn app_streams_same_html() {
+        let state = TestState {
+            counter: 7,
+            message: "streamed".into(),
+        };
+        let buffered: App<TestState, TestAssets> = App::new(state.clone());
+        let buffered = body_to_string(buffered.into_response()).await;
+
+        let streamed: App<TestState, TestAssets> = App::new(state);
+        let response = streamed.streaming().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(body_to_string(response).await, buffered);
+    }
+
+    #[tokio::test]
+    async fn app_renders_borrowed_and_local_state() {
+        // Only streaming moves the state to another thread
+        #[derive(Serialize)]
+        struct LocalState<'a> {
+            message: &'a str,
+            #[serde(skip)]
+            _local: std::marker::PhantomData<std::rc::Rc<()>>,
+        }
+        let message = String::from("local");
+        let app: App<LocalState<'_>, TestAssets> = App::new(LocalState {
+            message: &message,
+            _local: std::marker::PhantomData,
+        });
+        let body = body_to_string(app.into_response()).await;
+        assert!(body.contains(r#"{"message":"local"}"#));
+    }
+
+    #[tokio::test]
+    async fn app_injects_state_at_end_of_body() {
+        let state = TestState {
+            counter: 3,
+            message: "late".into(),
+        };
+        let app: App<TestState, TestAssets> = App::new(state).state_at_end_of_body(true);
+        let body = body_to_string(app.streaming().into_response()).await;
+
+        assert!(!body.contains("<!--AXUM_EGUI_INITIAL_STATE-->"));
+        let script = body.find(r#"<script id="axum-egui-state""#).unwrap();
+        assert!(body[script..].contains(r#""counter":3"#));
+        assert!(script > body.find("<script type=\"module\"").unwrap());
+        assert!(body[script..].contains("</body>"));
+    }
+
+    #[tokio::test]
+    async fn app_injects_loading_skeleton_and_noscript() {
+        let state = TestState {
+            counter: 1,
+            message: "test".into(),
+        };
+        let app: App<TestState, TestAssets> = App::new(state)
+            .loading_skeleton(r#"<div class="spinner"></div>"#)
+            .noscript("<h1>My App</h1>");
+        let body = body_to_string(app.into_response()).await;
+
+        assert!(body.contains(r#"<div id="loading_text"><div class="spinner"></div></div>"#));
+        assert!(body.contains("<body><noscript><h1>My App</h1></noscript>"));
+    }
+
+    #[tokio::test]
+    async fn app_injects_snippets_and_transforms_html() {
+        let state = TestState {
+            counter: 1,
+            message: "test".into(),
+        };
+        let app: App<TestState, TestAssets> = App::new(state)
+            .preload_hints(false)
+            .inject(
+                "head",
+                r#"<link rel="stylesheet" href="https://fonts.example.com/inter.css">"#,
+            )
+            .inject("body", "<script>track()</script>")
+            .transform_html(|html| html.replace("<title>Test</title>", "<title>Reports</title>"))
+            .transform_html(|html| {
+                assert!(!html.contains("axum-egui-state"));
+                html.replace("track()", "track('reports')")
+            });
+        let body = body_to_string(app.into_response()).await;
+
+        assert!(body.contains(
+            r#"<title>Reports</title><link rel="stylesheet" href="https://fonts.example.com/inter.css"></head>"#
+        ));
+        assert!(body.contains("<script>track('reports')</script></body>"));
+        assert!(body.contains(r#""counter":1"#));
+    }
+
+    #[tokio::test]
+    async fn app_injects_slots_next_to_state() {
+        let state = TestState {
+            counter: 1,
+            message: "test".into(),
+        };
+        let flags = flags::FeatureFlags::new().enable("new_editor");
+        let app: App<TestState, TestAssets> = App::new(state)
+            .feature_flags(&flags)
+            .slot("build", &"</script>");
+        let body = body_to_string(app.into_response()).await;
+
+        assert!(body.contains(
+            r#"<script id="axum-egui-slot-flags" type="application/json">{"new_editor":true}</script>"#
+        ));
+        assert!(body.contains(
+            r#"<script id="axum-egui-slot-build" type="application/json">"<\/script>"</script>"#
+        ));
+    }
+
+    #[cfg(feature = "snapshot")]
+    #[tokio::test]
+    async fn app_injects_snapshot_id() {
+        let state = TestState {
+            counter: 1,
+            message: "shell".into(),
+        };
+        let app: App<TestState, TestAssets> = App::new(state).snapshot(&vec![7u8; 4096]);
+        let body = body_to_string(app.into_response()).await;
+
+        let slot = r#"<script id="axum-egui-slot-snapshot" type="application/json">"#;
+        let start = body.find(slot).unwrap() + slot.len();
+        let json = &body[start..start + body[start..].find("</script>").unwrap()];
+        let snapshot: snapshot::SnapshotRef = serde_json::from_str(json).unwrap();
+        assert!(snapshot.size > 4096);
+        assert!(!body.contains(&"7,".repeat(16)));
+    }
+
+    #[tokio::test]
+    async fn app_preloads_wasm_and_loader() {
+        let state = TestState {
+            counter: 1,
+            message: "test".into(),
+        };
+        let app: App<TestState, TestAssets> = App::new(state.clone());
+        let body = body_to_string(app.into_response()).await;
+        assert!(body.contains(
+            r#"<link rel="preload" href="./app.wasm" as="fetch" type="application/wasm" crossorigin="anonymous">"#
+        ));
+        assert!(body.contains(r#"<link rel="modulepreload" href="./app.js"></head>"#));
+
+        let app: App<TestState, TestAssets> =
+            App::new(state.clone()).asset_base_url("https://cdn.example.com/app/");
+        let body = body_to_string(app.into_response()).await;
+        assert!(body.contains(r#"href="https://cdn.example.com/app/app.wasm""#));
+
+        let app: App<TestState, TestAssets> = App::new(state).preload_hints(false);
+        let body = body_to_string(app.into_response()).await;
+        assert!(!body.contains("preload"));
+    }
+
+    #[tokio::test]
+    async fn static_handler_answers_head_and_conditional_requests() {
+        let uri: Uri = "/app.wasm".parse().unwrap();
+        let response = static_handler::<TestAssets>(Method::HEAD, HeaderMap::new(), uri.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let length = TestAssets::get("app.wasm").unwrap().data.len().to_string();
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], length.as_str());
+        let etag = response.headers()[header::ETAG].clone();
+        assert!(body_to_string(response).await.is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag);
+        let response = static_handler::<TestAssets>(Method::GET, headers, uri.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(body_to_string(response).await.is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        let response = static_handler::<TestAssets>(Method::GET, headers, uri.clone())
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = static_handler::<TestAssets>(Method::POST, HeaderMap::new(), uri)
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[header::ALLOW], "GET, HEAD");
+    }
+
     #[tokio::test]
     async fn static_handler_serves_js_with_correct_mime() {
         let uri: Uri = "/app.js".parse().unwrap();
-        let response = static_handler::<TestAssets>(uri).await.into_response();
+        let response = get_static::<TestAssets>(uri).await;
 
         assert_eq!(response.status(), StatusCode::OK);
         assert_eq!(
@@ -278,7 +754,7 @@ mod tests {
     #[tokio::test]
     async fn static_handler_serves_wasm_with_correct_mime() {
         let uri: Uri = "/app.wasm".parse().unwrap();
-        let response = static_handler::<TestAssets>(uri).await.into_response();
+        let response = get_static::<TestAssets>(uri).await;
 
         assert_eq!(response.status(), StatusCode::OK);
         assert_eq!(
@@ -291,7 +767,7 @@ mod tests {
     async fn static_handler_falls_back_to_index_html() {
         // Unknown path should return index.html for SPA routing
         let uri: Uri = "/some/unknown/path".parse().unwrap();
-        let response = static_handler::<TestAssets>(uri).await.into_response();
+        let response = get_static::<TestAssets>(uri).await;
 
         assert_eq!(response.status(), StatusCode::OK);
         assert_eq!(response.headers().get("content-type").unwrap(), "text/html");
@@ -300,6 +776,40 @@ mod tests {
         assert!(body.contains("<!--AXUM_EGUI_INITIAL_STATE-->"));
     }
 
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets_precompressed/"]
+    struct PrecompressedAssets;
+
+    #[tokio::test]
+    async fn static_handler_serves_precompressed_siblings() {
+        let get = |accept_encoding: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::ACCEPT_ENCODING,
+                HeaderValue::from_static(accept_encoding),
+            );
+            static_handler::<PrecompressedAssets>(Method::GET, headers, "/app.js".parse().unwrap())
+        };
+
+        let response = get("br, gzip;q=0.8").await.into_response();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/javascript");
+        let gzipped = PrecompressedAssets::get("app.js.gz").unwrap().data;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, gzipped.as_ref());
+
+        for accept_encoding in ["br", "gzip;q=0", "identity"] {
+            let response = get(accept_encoding).await.into_response();
+            assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+            assert_eq!(response.headers()[header::VARY], "accept-encoding");
+            assert_eq!(
+                body_to_string(response).await,
+                include_str!("test_assets_precompressed/app.js")
+            );
+        }
+    }
+
     // Test assets without index.html
     #[derive(RustEmbed)]
     #[folder = "src/test_assets_no_index/"]
@@ -308,9 +818,7 @@ mod tests {
     #[tokio::test]
     async fn static_handler_returns_404_when_no_index() {
         let uri: Uri = "/unknown".parse().unwrap();
-        let response = static_handler::<TestAssetsNoIndex>(uri)
-            .await
-            .into_response();
+        let response = get_static::<TestAssetsNoIndex>(uri).await;
 
         assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
@@ -0,0 +1,342 @@
+//! Caps on concurrent SSE and WebSocket connections.
+//!
+//! Every open dashboard tab holds a stream. On a small server a few dozen
+//! forgotten tabs (or one reconnect loop) can exhaust file descriptors and
+//! memory. [`ConnectionLimits`] caps the total number of open streams and the
+//! number per client IP; handlers take a [`ConnectionPermit`] and hold it for
+//! as long as the stream is open.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use axum_egui::limits::{ConnectionLimits, ConnectionPermit, LimitExceeded};
+//!
+//! async fn events(permit: ConnectionPermit) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+//!     // The permit is released when the client disconnects.
+//!     Sse::new(permit.hold(updates()))
+//! }
+//!
+//! async fn socket(
+//!     ws: WebSocketUpgrade,
+//!     permit: Result<ConnectionPermit, LimitExceeded>,
+//! ) -> Response {
+//!     match permit {
+//!         Ok(permit) => ws.on_upgrade(move |socket| async move {
+//!             let _permit = permit;
+//!             handle(socket).await
+//!         }),
+//!         // Browsers hide the HTTP status of a failed handshake, so tell the
+//!         // client why with a close frame instead.
+//!         Err(exceeded) => exceeded.reject_websocket(ws),
+//!     }
+//! }
+//!
+//! axum_egui::serve(app, addr)
+//!     .connection_limits(ConnectionLimits::new().max_total(500).max_per_ip(8))
+//!     .await?;
+//! ```
+//!
+//! Without a `ConnectionLimits` installed, [`ConnectionPermit`] extraction
+//! always succeeds. The per-IP cap needs the peer address, which [`serve()`]
+//! provides; behind a reverse proxy every client shares the proxy's IP, so
+//! only set `max_total` there.
+//!
+//! Open and rejected connections are reported through [`crate::metrics`].
+//!
+//! [`serve()`]: crate::serve()
+
+use crate::metrics::{self, Counter, Gauge};
+use axum::extract::ws::{CloseFrame, Message, WebSocketUpgrade};
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use futures_util::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+/// WebSocket close code sent when a limit is exceeded ("Try Again Later").
+pub const CLOSE_TRY_AGAIN_LATER: u16 = 1013;
+
+/// Seconds clients are asked to wait before retrying a rejected stream.
+const RETRY_AFTER_SECS: &str = "5";
+
+/// Shared caps on concurrent streaming connections.
+///
+/// Clones share the same counts, so install one instance for all stream
+/// routes that should count against the same budget.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimits {
+    max_total: Option<usize>,
+    max_per_ip: Option<usize>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    total: usize,
+    per_ip: HashMap<IpAddr, usize>,
+}
+
+struct Gauges {
+    connections: Gauge,
+    clients: Gauge,
+    rejections: Counter,
+}
+
+fn gauges() -> Gauges {
+    Gauges {
+        connections: metrics::gauge("axum_egui_stream_connections"),
+        clients: metrics::gauge("axum_egui_stream_clients"),
+        rejections: metrics::counter("axum_egui_stream_rejections_total"),
+    }
+}
+
+impl ConnectionLimits {
+    /// Create limits with no caps set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cap the number of open streams across all clients.
+    pub fn max_total(mut self, max: usize) -> Self {
+        self.max_total = Some(max);
+        self
+    }
+
+    /// Cap the number of open streams from a single IP address.
+    pub fn max_per_ip(mut self, max: usize) -> Self {
+        self.max_per_ip = Some(max);
+        self
+    }
+
+    /// Reserve a slot for a stream from `ip`, if the limits allow it.
+    ///
+    /// `ip` may be `None` when the peer address is unknown, in which case
+    /// only the total cap applies.
+    pub fn try_acquire(&self, ip: Option<IpAddr>) -> Result<ConnectionPermit, LimitExceeded> {
+        let gauges = gauges();
+        let mut state = self.state.lock().unwrap();
+
+        if self.max_total.is_some_and(|max| state.total >= max) {
+            gauges.rejections.inc();
+            return Err(LimitExceeded::Total);
+        }
+        if let Some(ip) = ip {
+            let open = state.per_ip.get(&ip).copied().unwrap_or(0);
+            if self.max_per_ip.is_some_and(|max| open >= max) {
+                gauges.rejections.inc();
+                return Err(LimitExceeded::PerIp(ip));
+            }
+            if open == 0 {
+                gauges.clients.inc();
+            }
+            state.per_ip.insert(ip, open + 1);
+        }
+        state.total += 1;
+        gauges.connections.inc();
+
+        Ok(ConnectionPermit {
+            slot: Some(Slot {
+                state: self.state.clone(),
+                ip,
+            }),
+        })
+    }
+
+    /// Number of streams currently holding a permit.
+    pub fn active(&self) -> usize {
+        self.state.lock().unwrap().total
+    }
+
+    /// Number of streams from `ip` currently holding a permit.
+    pub fn active_for(&self, ip: IpAddr) -> usize {
+        let state = self.state.lock().unwrap();
+        state.per_ip.get(&ip).copied().unwrap_or(0)
+    }
+}
+
+/// A reserved stream slot. The slot is released when the permit is dropped.
+///
+/// Extract it in SSE/WebSocket handlers and keep it alive for the lifetime of
+/// the stream, e.g. with [`ConnectionPermit::hold`] or by moving it into the
+/// `on_upgrade` callback.
+#[derive(Debug)]
+#[must_use = "the slot is released as soon as the permit is dropped"]
+pub struct ConnectionPermit {
+    slot: Option<Slot>,
+}
+
+#[derive(Debug)]
+struct Slot {
+    state: Arc<Mutex<State>>,
+    ip: Option<IpAddr>,
+}
+
+impl ConnectionPermit {
+    /// A permit that is not tracked by any limits.
+    pub fn unlimited() -> Self {
+        Self { slot: None }
+    }
+
+    /// Tie the permit to `stream`, releasing it when the stream is dropped
+    /// (i.e. when the client disconnects).
+    pub fn hold<S: Stream>(self, stream: S) -> impl Stream<Item = S::Item> {
+        stream.map(move |item| {
+            let _ = &self;
+            item
+        })
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let Some(slot) = self.slot.take() else {
+            return;
+        };
+        let gauges = gauges();
+        let mut state = slot.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.total -= 1;
+        gauges.connections.dec();
+        if let Some(ip) = slot.ip {
+            if let Some(open) = state.per_ip.get_mut(&ip) {
+                *open -= 1;
+                if *open == 0 {
+                    state.per_ip.remove(&ip);
+                    gauges.clients.dec();
+                }
+            }
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ConnectionPermit {
+    type Rejection = LimitExceeded;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(limits) = parts.extensions.get::<ConnectionLimits>().cloned() else {
+            return Ok(Self::unlimited());
+        };
+        let ip = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        limits.try_acquire(ip).inspect_err(|exceeded| {
+            tracing::warn!("rejecting stream connection: {exceeded}");
+        })
+    }
+}
+
+/// A stream was refused because a [`ConnectionLimits`] cap was reached.
+///
+/// As a response this is `429 Too Many Requests` with a `Retry-After` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum LimitExceeded {
+    /// The server-wide cap was reached.
+    #[error("too many open connections")]
+    Total,
+    /// The client at this address already has the maximum number of streams.
+    #[error("too many open connections from {0}")]
+    PerIp(IpAddr),
+}
+
+impl LimitExceeded {
+    /// Accept the WebSocket handshake and immediately close it with
+    /// [`CLOSE_TRY_AGAIN_LATER`], so browser clients can see the reason.
+    pub fn reject_websocket(self, ws: WebSocketUpgrade) -> Response {
+        ws.on_upgrade(move |mut socket| async move {
+            let frame = CloseFrame {
+                code: CLOSE_TRY_AGAIN_LATER,
+                reason: self.to_string().into(),
+            };
+            let _ = socket.send(Message::Close(Some(frame))).await;
+        })
+    }
+}
+
+impl IntoResponse for LimitExceeded {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, RETRY_AFTER_SECS)],
+            self.to_string(),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::extract::connect_info::MockConnectInfo;
+    use axum::http::Request;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    const A: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const B: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn enforces_total_cap() {
+        let limits = ConnectionLimits::new().max_total(2);
+        let _a = limits.try_acquire(Some(A)).unwrap();
+        let _b = limits.try_acquire(Some(B)).unwrap();
+        assert_eq!(limits.try_acquire(None).unwrap_err(), LimitExceeded::Total);
+    }
+
+    #[test]
+    fn enforces_per_ip_cap() {
+        let limits = ConnectionLimits::new().max_per_ip(1);
+        let _a = limits.try_acquire(Some(A)).unwrap();
+        assert_eq!(
+            limits.try_acquire(Some(A)).unwrap_err(),
+            LimitExceeded::PerIp(A)
+        );
+        let _b = limits.try_acquire(Some(B)).unwrap();
+        assert_eq!(limits.active(), 2);
+    }
+
+    #[test]
+    fn dropping_permit_releases_slot() {
+        let limits = ConnectionLimits::new().max_total(1).max_per_ip(1);
+        let permit = limits.try_acquire(Some(A)).unwrap();
+        drop(permit);
+        assert_eq!(limits.active(), 0);
+        assert_eq!(limits.active_for(A), 0);
+        let _again = limits.try_acquire(Some(A)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn held_stream_releases_on_drop() {
+        let limits = ConnectionLimits::new();
+        let stream = limits
+            .try_acquire(Some(A))
+            .unwrap()
+            .hold(futures_util::stream::iter([1, 2, 3]));
+        assert_eq!(limits.active_for(A), 1);
+        assert_eq!(stream.collect::<Vec<_>>().await, [1, 2, 3]);
+        assert_eq!(limits.active_for(A), 0);
+    }
+
+    #[tokio::test]
+    async fn extractor_rejects_with_429() {
+        let limits = ConnectionLimits::new().max_per_ip(1);
+        let _held = limits.try_acquire(Some(A)).unwrap();
+        let app = Router::new()
+            .route("/events", get(|_permit: ConnectionPermit| async { "ok" }))
+            .layer(axum::Extension(limits))
+            .layer(MockConnectInfo(SocketAddr::new(A, 4000)));
+
+        let response = app
+            .oneshot(Request::get("/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "5");
+    }
+}
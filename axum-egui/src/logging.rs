@@ -0,0 +1,314 @@
+//! Structured logging of server function calls.
+//!
+//! [`CallLog`] is a tower layer that logs one line per `#[server]` call: the
+//! function, path, status, duration and caller IP, and optionally its
+//! arguments. Requests to other routes pass through untouched.
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/api/login", post(login_handler))
+//!     .layer(CallLog::new().args(true));
+//! ```
+//!
+//! Arguments are logged as JSON. Mask sensitive ones in the declaration;
+//! their values are replaced with [`REDACTED`]:
+//!
+//! ```ignore
+//! #[server(redact(password))]
+//! pub async fn login(user: String, password: String) -> Result<Session, ServerFnError> {
+//!     // ...
+//! }
+//! // INFO axum_egui::calls: function="login" status=200 args={"password":"[redacted]","user":"ada"} ...
+//! ```
+//!
+//! For streaming functions the duration is the time to open the stream.
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, Request, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::{Layer, Service};
+
+/// Replacement for the values of redacted arguments.
+pub const REDACTED: &str = "[redacted]";
+
+/// One logged call.
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    /// The server function name.
+    pub function: &'static str,
+    /// The request path.
+    pub path: String,
+    /// The response status.
+    pub status: StatusCode,
+    /// Time until the response (or the stream) started.
+    pub duration: Duration,
+    /// The peer address, when the server was started with connect info.
+    pub client_ip: Option<IpAddr>,
+    /// The arguments with redacted fields masked, when enabled with
+    /// [`CallLog::args`] and the request got as far as decoding them.
+    pub args: Option<serde_json::Value>,
+}
+
+type Sink = dyn Fn(&CallRecord) + Send + Sync;
+
+/// Layer logging server function calls. Logs with `tracing` at info level
+/// (target `axum_egui::calls`) unless given another [`Self::sink`].
+#[derive(Clone)]
+pub struct CallLog {
+    args: bool,
+    sink: Arc<Sink>,
+}
+
+impl std::fmt::Debug for CallLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallLog")
+            .field("args", &self.args)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for CallLog {
+    fn default() -> Self {
+        Self {
+            args: false,
+            sink: Arc::new(trace),
+        }
+    }
+}
+
+impl CallLog {
+    /// Log calls with `tracing`, without arguments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also log the arguments of each call (default: off).
+    pub fn args(mut self, enabled: bool) -> Self {
+        self.args = enabled;
+        self
+    }
+
+    /// Hand records to `sink` instead of `tracing`.
+    pub fn sink(mut self, sink: impl Fn(&CallRecord) + Send + Sync + 'static) -> Self {
+        self.sink = Arc::new(sink);
+        self
+    }
+}
+
+fn trace(record: &CallRecord) {
+    tracing::info!(
+        target: "axum_egui::calls",
+        function = record.function,
+        path = %record.path,
+        status = record.status.as_u16(),
+        duration_ms = record.duration.as_secs_f64() * 1000.0,
+        client_ip = record.client_ip.map(tracing::field::display),
+        args = record.args.as_ref().map(tracing::field::display),
+    );
+}
+
+/// Marker asking the generated handler for the call's arguments.
+#[derive(Clone, Copy)]
+struct CaptureArgs;
+
+/// Arguments captured by a generated handler, in the response extensions.
+#[doc(hidden)]
+#[derive(Clone)]
+pub struct LoggedArgs(serde_json::Value);
+
+/// Serialize `args` for the [`CallLog`] if it asked for them, masking the
+/// `redacted` fields. Called by the generated handlers.
+#[doc(hidden)]
+pub fn capture_args(
+    extensions: &Extensions,
+    args: &impl Serialize,
+    redacted: &[&str],
+) -> Option<LoggedArgs> {
+    extensions.get::<CaptureArgs>()?;
+    let mut value = serde_json::to_value(args).ok()?;
+    if let serde_json::Value::Object(fields) = &mut value {
+        for name in redacted {
+            if let Some(field) = fields.get_mut(*name) {
+                *field = REDACTED.into();
+            }
+        }
+    }
+    Some(LoggedArgs(value))
+}
+
+/// Attach captured arguments to the handler's response.
+#[doc(hidden)]
+pub fn attach_args<B>(mut response: Response<B>, args: Option<LoggedArgs>) -> Response<B> {
+    if let Some(args) = args {
+        response.extensions_mut().insert(args);
+    }
+    response
+}
+
+impl<S> Layer<S> for CallLog {
+    type Service = CallLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        let functions = crate::registry::routes()
+            .into_iter()
+            .map(|route| (route.path, route.name))
+            .collect();
+        CallLogService {
+            inner,
+            log: self.clone(),
+            functions: Arc::new(functions),
+        }
+    }
+}
+
+/// Service produced by [`CallLog`].
+#[derive(Clone)]
+pub struct CallLogService<S> {
+    inner: S,
+    log: CallLog,
+    functions: Arc<HashMap<&'static str, &'static str>>,
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for CallLogService<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallLogService")
+            .field("inner", &self.inner)
+            .field("log", &self.log)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CallLogService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let Some(&function) = self.functions.get(request.uri().path()) else {
+            let future = self.inner.call(request);
+            return Box::pin(future);
+        };
+        if self.log.args {
+            request.extensions_mut().insert(CaptureArgs);
+        }
+        let path = request.uri().path().to_string();
+        let client_ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        let sink = self.log.sink.clone();
+        let start = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            let args = response.extensions_mut().remove::<LoggedArgs>();
+            sink(&CallRecord {
+                function,
+                path,
+                status: response.status(),
+                duration: start.elapsed(),
+                client_ip,
+                args: args.map(|args| args.0),
+            });
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::{get, post};
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    #[derive(Serialize)]
+    struct LoginArgs {
+        user: String,
+        password: String,
+    }
+
+    inventory::submit! {
+        crate::registry::ServerFnRoute {
+            name: "logging_test_login",
+            module: module_path!(),
+            path: "/api/logging_test_login",
+            kind: crate::registry::RouteKind::Rpc,
+            post_params: false,
+            auth: false,
+            accepts: &["application/json"],
+            produces: &["application/json"],
+            headers: &[],
+        }
+    }
+
+    // Stands in for a `#[server(redact(password))]` handler
+    async fn login(parts: axum::http::request::Parts) -> Response<Body> {
+        let args = LoginArgs {
+            user: "ada".into(),
+            password: "hunter2".into(),
+        };
+        let logged = capture_args(&parts.extensions, &args, &["password"]);
+        attach_args(Response::new(Body::empty()), logged)
+    }
+
+    #[tokio::test]
+    async fn logs_server_fn_calls_with_redacted_args() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let app = || {
+            Router::new()
+                .route("/api/logging_test_login", post(login))
+                .route("/health", get(|| async { "ok" }))
+        };
+        let log = CallLog::new().sink({
+            let records = records.clone();
+            move |record: &CallRecord| records.lock().unwrap().push(record.clone())
+        });
+        let request = |path: &str| Request::post(path).body(Body::empty()).unwrap();
+
+        let response = app()
+            .layer(log.clone())
+            .oneshot(request("/api/logging_test_login"))
+            .await
+            .unwrap();
+        assert!(response.extensions().get::<LoggedArgs>().is_none());
+        app()
+            .layer(log.clone())
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        app()
+            .layer(log.args(true))
+            .oneshot(request("/api/logging_test_login"))
+            .await
+            .unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].function, "logging_test_login");
+        assert_eq!(records[0].status, StatusCode::OK);
+        assert!(records[0].args.is_none());
+        assert_eq!(
+            records[1].args,
+            Some(serde_json::json!({ "user": "ada", "password": REDACTED }))
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Per-page `<title>`, description and OpenGraph tags for link previews.
+//!
+//! Chat apps and social networks unfurl links from the HTML alone; an egui
+//! app draws everything on a canvas, so every link would otherwise preview
+//! as the same generic page. Describe each page with [`PageMeta`]:
+//!
+//! ```ignore
+//! axum_egui::serve(app, addr)
+//!     .assets::<Assets>()
+//!     .page_meta(|path: &str| match path.strip_prefix("/items/") {
+//!         Some(id) => PageMeta::new()
+//!             .title(format!("Item {id} · Shop"))
+//!             .description("In stock, ships tomorrow")
+//!             .image(format!("https://shop.example.com/thumbs/{id}.png")),
+//!         None => PageMeta::new().title("Shop"),
+//!     })
+//!     .await?;
+//! ```
+//!
+//! The tags are filled into the page served for non-asset paths. Index
+//! handlers (see [`Serve::index`](crate::serve::Serve::index)) extract the
+//! `PageMeta` of the request and pass it on, possibly after changing it:
+//!
+//! ```ignore
+//! async fn index(meta: PageMeta, uri: Uri) -> App<MyApp, Assets> {
+//!     App::new(MyApp::for_path(uri.path()).await).meta(&meta)
+//! }
+//! ```
+//!
+//! The title replaces the template's `<title>`; the other tags go at the
+//! `<!--AXUM_EGUI:head-->` placeholder, or the end of `<head>`.
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Title, description and preview image of a page.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PageMeta {
+    title: Option<String>,
+    description: Option<String>,
+    image: Option<String>,
+    url: Option<String>,
+    properties: Vec<(String, String)>,
+}
+
+impl PageMeta {
+    /// No tags; the template is left as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The page title, for `<title>` and `og:title`.
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// A sentence or two about the page, for the `description` meta tag and
+    /// `og:description`.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Absolute URL of the preview image (`og:image`). Also asks for a
+    /// large Twitter card.
+    pub fn image(mut self, url: impl Into<String>) -> Self {
+        self.image = Some(url.into());
+        self
+    }
+
+    /// Canonical absolute URL of the page (`og:url`).
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Any other OpenGraph-style property, e.g. `("og:site_name", "Shop")`.
+    pub fn property(mut self, property: impl Into<String>, content: impl Into<String>) -> Self {
+        self.properties.push((property.into(), content.into()));
+        self
+    }
+
+    /// Whether no tag has been set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The `<meta>` tags, without the `<title>`.
+    fn tags(&self) -> String {
+        let mut tags = String::new();
+        let mut tag = |attribute: &str, name: &str, content: &str| {
+            tags.push_str(&format!(
+                r#"<meta {attribute}="{}" content="{}">"#,
+                escape(name),
+                escape(content)
+            ));
+        };
+        if let Some(title) = &self.title {
+            tag("property", "og:title", title);
+        }
+        if let Some(description) = &self.description {
+            tag("name", "description", description);
+            tag("property", "og:description", description);
+        }
+        if let Some(image) = &self.image {
+            tag("property", "og:image", image);
+            tag("name", "twitter:card", "summary_large_image");
+        }
+        if let Some(url) = &self.url {
+            tag("property", "og:url", url);
+        }
+        for (property, content) in &self.properties {
+            tag("property", property, content);
+        }
+        tags
+    }
+
+    /// Fill the tags into `html`.
+    pub(crate) fn apply(&self, html: &str) -> String {
+        if self.is_empty() {
+            return html.to_string();
+        }
+        let mut html = html.to_string();
+        if let Some(title) = &self.title {
+            let title = format!("<title>{}</title>", escape(title));
+            let existing = html.find("<title").and_then(|start| {
+                let end = start + html[start..].find("</title>")? + "</title>".len();
+                Some((start, end))
+            });
+            html = match existing {
+                Some((start, end)) => format!("{}{title}{}", &html[..start], &html[end..]),
+                None => crate::app::inject(&html, "head", &title),
+            };
+        }
+        crate::app::inject(&html, "head", &self.tags())
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+type Resolve = dyn Fn(&str) -> PageMeta + Send + Sync;
+
+/// The callback given to [`Serve::page_meta`](crate::serve::Serve::page_meta),
+/// in the request extensions.
+#[derive(Clone)]
+pub(crate) struct PageMetaSource(Arc<Resolve>);
+
+impl PageMetaSource {
+    pub(crate) fn new(resolve: impl Fn(&str) -> PageMeta + Send + Sync + 'static) -> Self {
+        Self(Arc::new(resolve))
+    }
+}
+
+/// Extracts the [`PageMeta`] of the request path from the callback given
+/// to [`Serve::page_meta`](crate::serve::Serve::page_meta); empty without one.
+impl<S: Send + Sync> FromRequestParts<S> for PageMeta {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<PageMetaSource>()
+            .map(|source| (source.0)(parts.uri.path()))
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_title_and_tags() {
+        let meta = PageMeta::new()
+            .title("Item \"42\"")
+            .description("Fresh & tasty")
+            .image("https://shop.example.com/42.png")
+            .property("og:site_name", "Shop");
+        let html = meta.apply("<head><title>axum-egui</title></head><body></body>");
+        assert_eq!(
+            html,
+            concat!(
+                "<head><title>Item &quot;42&quot;</title>",
+                r#"<meta property="og:title" content="Item &quot;42&quot;">"#,
+                r#"<meta name="description" content="Fresh &amp; tasty">"#,
+                r#"<meta property="og:description" content="Fresh &amp; tasty">"#,
+                r#"<meta property="og:image" content="https://shop.example.com/42.png">"#,
+                r#"<meta name="twitter:card" content="summary_large_image">"#,
+                r#"<meta property="og:site_name" content="Shop">"#,
+                "</head><body></body>"
+            )
+        );
+
+        let untitled = "<head><!--AXUM_EGUI:head--></head>";
+        assert_eq!(
+            PageMeta::new().title("Shop").apply(untitled),
+            r#"<head><title>Shop</title><meta property="og:title" content="Shop"><!--AXUM_EGUI:head--></head>"#
+        );
+        assert_eq!(PageMeta::new().apply(untitled), untitled);
+    }
+}
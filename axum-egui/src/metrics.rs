@@ -0,0 +1,372 @@
+//! Process-wide gauges and counters for axum-egui internals.
+//!
+//! axum-egui records a few numbers about itself (open streams, rejected
+//! connections, ...) without depending on a metrics framework. Read them with
+//! [`snapshot`], or mount [`metrics_handler`] to expose them in the Prometheus
+//! text format:
+//!
+//! ```ignore
+//! let app = Router::new().route("/metrics", get(axum_egui::metrics::metrics_handler));
+//! ```
+//!
+//! # Recorded metrics
+//!
+//! | Name | Type | Meaning |
+//! |------|------|---------|
+//! | `axum_egui_stream_connections` | gauge | Open SSE/WebSocket connections holding a permit |
+//! | `axum_egui_stream_clients` | gauge | Distinct client IPs with an open stream |
+//! | `axum_egui_stream_rejections_total` | counter | Streams refused by [`ConnectionLimits`](crate::limits::ConnectionLimits) |
+//! | `axum_egui_sse_connections` | gauge | Open SSE responses of streaming server functions |
+//! | `axum_egui_ws_connections` | gauge | Open WebSocket connections of streaming server functions |
+//! | `axum_egui_messages_sent_total` | counter | Items sent over those streams |
+//! | `axum_egui_messages_received_total` | counter | Items received over WebSockets |
+//! | `axum_egui_serialization_failures_total` | counter | Stream items that failed to serialize, see [`SerializationPolicy`](crate::stream::SerializationPolicy) |
+//! | `axum_egui_coalesced_calls_total` | counter | Calls that shared the result of an identical call in flight, see [`coalesce`](crate::coalesce) |
+//! | `axum_egui_cache_hits_total` | counter | Calls answered from the [`cache`](crate::cache), fresh or stale |
+//!
+//! [`canary`](crate::canary) splits add their calls, errors and latency by
+//! endpoint and variant.
+//!
+//! The stream numbers are also available to the app, e.g. from a server
+//! function feeding an admin dashboard, as [`stream_stats`]:
+//!
+//! ```ignore
+//! #[server]
+//! async fn dashboard() -> Result<StreamStats, ServerFnError> {
+//!     Ok(axum_egui::metrics::stream_stats())
+//! }
+//! ```
+
+use axum::http::header;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
+
+const SSE_CONNECTIONS: &str = "axum_egui_sse_connections";
+const WS_CONNECTIONS: &str = "axum_egui_ws_connections";
+const MESSAGES_SENT: &str = "axum_egui_messages_sent_total";
+const MESSAGES_RECEIVED: &str = "axum_egui_messages_received_total";
+pub(crate) const SERIALIZATION_FAILURES: &str = "axum_egui_serialization_failures_total";
+
+/// Seconds over which [`StreamStats::messages_per_sec`] is averaged.
+pub const RATE_WINDOW_SECS: u64 = 10;
+
+/// Whether a metric is a [`Gauge`] or a [`Counter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+/// A value that can go up and down.
+#[derive(Debug, Clone)]
+pub struct Gauge(Arc<AtomicI64>);
+
+impl Gauge {
+    /// Increase the gauge by one.
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Decrease the gauge by one.
+    pub fn dec(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Set the gauge to `value`.
+    pub fn set(&self, value: i64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// The current value.
+    pub fn get(&self) -> i64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A monotonically increasing count.
+#[derive(Debug, Clone)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    /// Increase the counter by one.
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The current value.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+enum Metric {
+    Gauge(Gauge),
+    Counter(Counter),
+}
+
+fn registry() -> &'static Mutex<BTreeMap<&'static str, Metric>> {
+    static REGISTRY: OnceLock<Mutex<BTreeMap<&'static str, Metric>>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Get the gauge named `name`, registering it on first use.
+///
+/// # Panics
+///
+/// Panics if `name` is already registered as a counter.
+pub fn gauge(name: &'static str) -> Gauge {
+    let metric = match registry()
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert_with(|| Metric::Gauge(Gauge(Arc::default())))
+    {
+        Metric::Gauge(gauge) => Some(gauge.clone()),
+        Metric::Counter(_) => None,
+    };
+    metric.unwrap_or_else(|| panic!("metric {name} is registered as a counter"))
+}
+
+/// Get the counter named `name`, registering it on first use.
+///
+/// # Panics
+///
+/// Panics if `name` is already registered as a gauge.
+pub fn counter(name: &'static str) -> Counter {
+    let metric = match registry()
+        .lock()
+        .unwrap()
+        .entry(name)
+        .or_insert_with(|| Metric::Counter(Counter(Arc::default())))
+    {
+        Metric::Counter(counter) => Some(counter.clone()),
+        Metric::Gauge(_) => None,
+    };
+    metric.unwrap_or_else(|| panic!("metric {name} is registered as a gauge"))
+}
+
+/// Current values of all registered metrics, sorted by name.
+pub fn snapshot() -> Vec<(&'static str, MetricKind, i64)> {
+    registry()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, metric)| match metric {
+            Metric::Gauge(gauge) => (*name, MetricKind::Gauge, gauge.get()),
+            Metric::Counter(counter) => (*name, MetricKind::Counter, counter.get() as i64),
+        })
+        .collect()
+}
+
+/// Render all registered metrics in the Prometheus text exposition format.
+pub fn render_prometheus() -> String {
+    let mut out = String::new();
+    for (name, kind, value) in snapshot() {
+        let kind = match kind {
+            MetricKind::Gauge => "gauge",
+            MetricKind::Counter => "counter",
+        };
+        let _ = writeln!(out, "# TYPE {name} {kind}\n{name} {value}");
+    }
+    crate::canary::render_prometheus(&mut out);
+    out
+}
+
+/// Axum handler serving [`render_prometheus`].
+pub async fn metrics_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_prometheus(),
+    )
+}
+
+/// Live numbers about streaming server functions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StreamStats {
+    /// Open SSE responses.
+    pub sse_connections: i64,
+    /// Open WebSocket connections.
+    pub ws_connections: i64,
+    /// Items sent since startup, over both transports.
+    pub messages_sent: u64,
+    /// Items received over WebSockets since startup.
+    pub messages_received: u64,
+    /// Items sent and received per second, averaged over the last
+    /// [`RATE_WINDOW_SECS`] whole seconds.
+    pub messages_per_sec: f64,
+}
+
+/// The current [`StreamStats`].
+pub fn stream_stats() -> StreamStats {
+    StreamStats {
+        sse_connections: gauge(SSE_CONNECTIONS).get(),
+        ws_connections: gauge(WS_CONNECTIONS).get(),
+        messages_sent: counter(MESSAGES_SENT).get(),
+        messages_received: counter(MESSAGES_RECEIVED).get(),
+        messages_per_sec: message_rate().per_sec(message_rate().now()),
+    }
+}
+
+/// Which transport a [`StreamGuard`] counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Sse,
+    Ws,
+}
+
+/// Counts one open stream for as long as it lives, and the messages over it.
+#[derive(Debug)]
+pub(crate) struct StreamGuard {
+    connections: Gauge,
+    sent: Counter,
+    received: Counter,
+}
+
+impl StreamGuard {
+    pub(crate) fn new(transport: Transport) -> Self {
+        let connections = gauge(match transport {
+            Transport::Sse => SSE_CONNECTIONS,
+            Transport::Ws => WS_CONNECTIONS,
+        });
+        connections.inc();
+        Self {
+            connections,
+            sent: counter(MESSAGES_SENT),
+            received: counter(MESSAGES_RECEIVED),
+        }
+    }
+
+    pub(crate) fn sent(&self) {
+        self.sent.inc();
+        message_rate().record(message_rate().now());
+    }
+
+    pub(crate) fn received(&self) {
+        self.received.inc();
+        message_rate().record(message_rate().now());
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        self.connections.dec();
+    }
+}
+
+/// Message counts of the last [`RATE_WINDOW_SECS`] seconds, one bucket per
+/// second, tagged with the second it counts.
+struct MessageRate {
+    start: Instant,
+    buckets: Mutex<[(u64, u64); RATE_WINDOW_SECS as usize + 1]>,
+}
+
+impl MessageRate {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            buckets: Mutex::default(),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_secs()
+    }
+
+    fn record(&self, second: u64) {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = &mut buckets[(second % (RATE_WINDOW_SECS + 1)) as usize];
+        if bucket.0 != second {
+            *bucket = (second, 0);
+        }
+        bucket.1 += 1;
+    }
+
+    /// The average over the whole seconds before `second`, which is still
+    /// being counted.
+    fn per_sec(&self, second: u64) -> f64 {
+        let buckets = self.buckets.lock().unwrap();
+        let total: u64 = buckets
+            .iter()
+            .filter(|(at, _)| *at < second && second - at <= RATE_WINDOW_SECS)
+            .map(|(_, count)| count)
+            .sum();
+        total as f64 / RATE_WINDOW_SECS as f64
+    }
+}
+
+fn message_rate() -> &'static MessageRate {
+    static RATE: OnceLock<MessageRate> = OnceLock::new();
+    RATE.get_or_init(MessageRate::new)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gauges_are_shared_by_name() {
+        let a = gauge("test_metrics_shared_gauge");
+        let b = gauge("test_metrics_shared_gauge");
+        a.inc();
+        a.inc();
+        b.dec();
+        assert_eq!(b.get(), 1);
+    }
+
+    #[test]
+    fn renders_prometheus_text() {
+        counter("test_metrics_rendered_total").inc();
+        let text = render_prometheus();
+        assert!(text.contains("# TYPE test_metrics_rendered_total counter\n"));
+        assert!(text.contains("\ntest_metrics_rendered_total 1\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "registered as a gauge")]
+    fn kind_mismatch_panics() {
+        gauge("test_metrics_kind_mismatch");
+        counter("test_metrics_kind_mismatch");
+    }
+
+    #[test]
+    fn stream_guards_count_connections_and_messages() {
+        let before = stream_stats();
+        let sse = StreamGuard::new(Transport::Sse);
+        let ws = StreamGuard::new(Transport::Ws);
+        sse.sent();
+        ws.sent();
+        ws.received();
+        let during = stream_stats();
+        // Other tests run streams concurrently, so only check lower bounds
+        assert!(during.sse_connections >= 1 && during.ws_connections >= 1);
+        assert!(during.messages_sent >= before.messages_sent + 2);
+        assert!(during.messages_received > before.messages_received);
+        drop((sse, ws));
+    }
+
+    #[test]
+    fn message_rate_averages_whole_seconds() {
+        let rate = MessageRate::new();
+        for _ in 0..20 {
+            rate.record(100);
+        }
+        for _ in 0..10 {
+            rate.record(105);
+        }
+        rate.record(106);
+        // Second 106 is not over yet
+        assert_eq!(rate.per_sec(106), 3.0);
+        assert_eq!(rate.per_sec(110), 3.1);
+        // Second 100 has left the window
+        assert_eq!(rate.per_sec(111), 1.1);
+        assert_eq!(rate.per_sec(200), 0.0);
+        // Buckets are reused
+        rate.record(111);
+        assert_eq!(rate.per_sec(112), 1.2);
+    }
+}
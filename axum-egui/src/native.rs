@@ -0,0 +1,432 @@
+//! Server function calls from native programs (`native-client` feature).
+//!
+//! In the browser, `#[server]` functions call the server with `fetch` on the
+//! page's origin. Desktop eframe apps and CLI tools can reuse the same
+//! functions: enable `axum-egui/native-client` behind a `native` feature of
+//! the crate defining them, and the macro generates callers going through
+//! this module. Set the server's address once at startup:
+//!
+//! ```ignore
+//! // Cargo.toml of the API crate
+//! // [features]
+//! // native = ["axum-egui/native-client"]
+//!
+//! axum_egui::native::set_base_url("https://app.example.com");
+//!
+//! let greeting = api::greet("Ferris".into()).await?;
+//! ```
+//!
+//! Calls are made with a [`ReqwestTransport`] (rustls, with the webpki
+//! roots) and run on a tokio runtime, whose timer also ends calls of
+//! `timeout` functions. To use a client of your own, e.g. with a proxy or
+//! other TLS roots, wrap it in a `ReqwestTransport` or implement
+//! [`Transport`], and pass it to [`set_transport`]:
+//!
+//! ```ignore
+//! let client = reqwest::Client::builder().proxy(proxy).build()?;
+//! axum_egui::native::set_transport(ReqwestTransport::new(client));
+//! ```
+//!
+//! Only RPC functions are supported; uploads are sent in one piece.
+//! Streaming functions (`#[server(sse)]`, `#[server(ws)]`) return
+//! [`ServerFnError::Request`] on native targets.
+
+use crate::error::ErrorEnvelope;
+use crate::rpc::{CallPriority, Encoding, PRIORITY_HEADER, ServerFnError};
+use crate::upload::{Bytes, FileUpload, UPLOAD_CONTENT_TYPE};
+use futures_util::future::{self, Either};
+use futures_util::{Stream, StreamExt};
+use serde::{Serialize, de::DeserializeOwned};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock, RwLock};
+
+static BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+static TRANSPORT: RwLock<Option<Arc<dyn Transport>>> = RwLock::new(None);
+
+/// The request of a server function call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    /// `POST`, or `GET` for `#[server(get)]` functions.
+    pub method: &'static str,
+    /// The absolute URL of the endpoint, with the query string of `GET`
+    /// calls.
+    pub url: String,
+    /// Request headers, including the `content-type` and `accept` of
+    /// `POST` calls.
+    pub headers: Vec<(&'static str, String)>,
+    /// The encoded arguments of `POST` calls, empty for `GET` calls.
+    pub body: Vec<u8>,
+}
+
+/// The server's answer to an [`HttpRequest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response body.
+    pub body: Vec<u8>,
+}
+
+/// The future returned by [`Transport::send`].
+pub type TransportFuture = Pin<Box<dyn Future<Output = Result<HttpResponse, String>> + Send>>;
+
+/// The HTTP client making the calls: a [`ReqwestTransport`] unless another
+/// was set with [`set_transport`].
+pub trait Transport: Send + Sync + 'static {
+    /// Send `request`. Errors are network failures; HTTP error statuses
+    /// are responses.
+    fn send(&self, request: HttpRequest) -> TransportFuture;
+}
+
+/// The default [`Transport`], sending calls with a [`reqwest::Client`].
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport(reqwest::Client);
+
+impl ReqwestTransport {
+    /// Send calls with `client`.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self(client)
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> TransportFuture {
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).expect("calls use POST or GET");
+        let mut builder = self.0.request(method, request.url).body(request.body);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        Box::pin(async move {
+            let response = builder.send().await.map_err(|e| e.to_string())?;
+            let status = response.status().as_u16();
+            let body = response.bytes().await.map_err(|e| e.to_string())?;
+            Ok(HttpResponse {
+                status,
+                body: body.to_vec(),
+            })
+        })
+    }
+}
+
+/// Send calls to the server at `url`, e.g. `https://app.example.com`.
+/// Function paths are appended to it.
+pub fn set_base_url(url: impl Into<String>) {
+    let url = url.into().trim_end_matches('/').to_string();
+    *BASE_URL.write().unwrap_or_else(|e| e.into_inner()) = Some(url);
+}
+
+/// The URL set with [`set_base_url`].
+pub fn base_url() -> Option<String> {
+    BASE_URL.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Make calls with `transport` instead of the default [`ReqwestTransport`].
+pub fn set_transport(transport: impl Transport) {
+    *TRANSPORT.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(transport));
+}
+
+/// The transport set with [`set_transport`], or the default one.
+fn transport() -> Arc<dyn Transport> {
+    static DEFAULT: OnceLock<Arc<dyn Transport>> = OnceLock::new();
+    TRANSPORT
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_else(|| {
+            DEFAULT
+                .get_or_init(|| Arc::new(ReqwestTransport::default()))
+                .clone()
+        })
+}
+
+/// Call the server function at `path` with `args`, as
+/// [`rpc::call`](crate::rpc::call) does in the browser.
+///
+/// Fails with [`ServerFnError::Request`] before [`set_base_url`] was
+/// called.
+pub async fn call<Args, Resp>(path: &str, args: &Args) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    call_encoded(path, args, Encoding::Json).await
+}
+
+/// [`call`] with the arguments and response in `encoding`, as
+/// [`rpc::call_encoded`](crate::rpc::call_encoded) does in the browser.
+pub async fn call_encoded<Args, Resp>(
+    path: &str,
+    args: &Args,
+    encoding: Encoding,
+) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    let body = encoding.encode(args)?;
+    let headers = vec![
+        ("content-type", encoding.content_type().to_string()),
+        ("accept", encoding.content_type().to_string()),
+    ];
+    send("POST", path.to_string(), headers, body, encoding).await
+}
+
+/// Call the `#[server(get)]` function at `path` with `args` in the query
+/// string, as [`rpc::call_get`](crate::rpc::call_get) does in the browser.
+pub async fn call_get<Args, Resp>(path: &str, args: &Args) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    let url = crate::rpc::encode_get_args(path, args)?;
+    send("GET", url, Vec::new(), Vec::new(), Encoding::Json).await
+}
+
+/// Call the upload function at `path` with `args` in the query string and
+/// `body` as the request body (see [`upload`](crate::upload)). The body is
+/// read to the end first: [`HttpRequest`] carries it in one piece.
+pub async fn call_upload<Args, Resp>(
+    path: &str,
+    args: &Args,
+    body: impl Stream<Item = Bytes>,
+) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    let url = crate::rpc::encode_query_args(path, args)?;
+    let mut body = std::pin::pin!(body);
+    let mut buffer = Vec::new();
+    while let Some(chunk) = body.next().await {
+        buffer.extend_from_slice(&chunk);
+    }
+    let headers = vec![
+        ("content-type", UPLOAD_CONTENT_TYPE.to_string()),
+        ("accept", Encoding::Json.content_type().to_string()),
+    ];
+    send("POST", url, headers, buffer, Encoding::Json).await
+}
+
+/// Call the function at `path` taking files, with `args` and `files` (named
+/// after their arguments) in a multipart body (see [`upload`](crate::upload)).
+pub async fn call_multipart<Args, Resp>(
+    path: &str,
+    args: &Args,
+    files: impl IntoIterator<Item = (&'static str, FileUpload)>,
+) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    let args = Encoding::Json.encode(args)?;
+    let files: Vec<_> = files.into_iter().collect();
+    let (content_type, body) = crate::upload::encode_multipart(&args, &files);
+    let headers = vec![
+        ("content-type", content_type),
+        ("accept", Encoding::Json.content_type().to_string()),
+    ];
+    send("POST", path.to_string(), headers, body, Encoding::Json).await
+}
+
+async fn send<Resp: DeserializeOwned>(
+    method: &'static str,
+    path: String,
+    mut headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+    encoding: Encoding,
+) -> Result<Resp, ServerFnError> {
+    let base_url = base_url().ok_or_else(|| {
+        ServerFnError::Request(
+            "no server for native calls; call axum_egui::native::set_base_url at startup".into(),
+        )
+    })?;
+    let transport = transport();
+
+    headers.push((
+        PRIORITY_HEADER,
+        CallPriority::Interactive.header_value().to_string(),
+    ));
+    headers.extend(
+        crate::rpc::current_headers()
+            .iter()
+            .map(|(name, value)| (*name, value.to_string())),
+    );
+    let request = HttpRequest {
+        method,
+        url: format!("{base_url}{path}"),
+        headers,
+        body,
+    };
+    // Calls of `timeout` functions drop the transport's future once the
+    // deadline passes, which cancels the request
+    let response = match crate::rpc::current_timeout() {
+        Some(timeout) => {
+            let elapsed = std::pin::pin!(tokio::time::sleep(timeout));
+            match future::select(transport.send(request), elapsed).await {
+                Either::Left((response, _)) => response,
+                Either::Right(((), _)) => {
+                    return Err(ServerFnError::Timeout {
+                        timeout_ms: timeout.as_millis() as u64,
+                    });
+                }
+            }
+        }
+        None => transport.send(request).await,
+    }
+    .map_err(ServerFnError::Request)?;
+
+    if !(200..300).contains(&response.status) {
+        // Errors of server functions come as an `ErrorEnvelope`; others
+        // (a proxy's error page) as whatever they are
+        return Err(
+            match serde_json::from_slice::<ErrorEnvelope>(&response.body) {
+                Ok(envelope) => envelope.into(),
+                Err(_) => ServerFnError::ServerError(format!(
+                    "HTTP {}: {}",
+                    response.status,
+                    String::from_utf8_lossy(&response.body)
+                )),
+            },
+        );
+    }
+    encoding.decode(&response.body)
+}
+
+/// The error of streaming functions called on native targets.
+pub fn unsupported_stream(path: &str) -> ServerFnError {
+    ServerFnError::Request(format!(
+        "{path} is a streaming function, which native clients can't call yet"
+    ))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::{get, post};
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    /// Serves requests with a router, in process.
+    struct RouterTransport(Router);
+
+    impl Transport for RouterTransport {
+        fn send(&self, request: HttpRequest) -> TransportFuture {
+            let router = self.0.clone();
+            Box::pin(async move {
+                let path = request.url.trim_start_matches("http://test.invalid");
+                let mut builder = axum::http::Request::builder()
+                    .method(request.method)
+                    .uri(path);
+                for (name, value) in request.headers {
+                    builder = builder.header(name, value);
+                }
+                let request = builder.body(Body::from(request.body)).unwrap();
+                let response = router.oneshot(request).await.unwrap();
+                let status = response.status().as_u16();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                Ok(HttpResponse {
+                    status,
+                    body: body.to_vec(),
+                })
+            })
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct AddArgs {
+        a: i32,
+        b: i32,
+    }
+
+    #[tokio::test]
+    async fn calls_go_through_the_transport() {
+        let result = call::<_, i32>("/api/add", &AddArgs { a: 1, b: 2 }).await;
+        assert!(matches!(result, Err(ServerFnError::Request(_))));
+
+        let router = Router::new()
+            .route(
+                "/api/add",
+                post(
+                    |axum::Json(args): axum::Json<serde_json::Value>| async move {
+                        axum::Json(args["a"].as_i64().unwrap() + args["b"].as_i64().unwrap())
+                    },
+                ),
+            )
+            .route(
+                "/api/sub",
+                get(
+                    |axum::extract::Query(args): axum::extract::Query<AddArgs>| async move {
+                        axum::Json(args.a - args.b)
+                    },
+                ),
+            )
+            .route(
+                "/api/slow",
+                post(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    axum::Json(0)
+                }),
+            )
+            .route(
+                "/api/fail",
+                post(|| async {
+                    crate::error::error_response(
+                        axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                        ServerFnError::ServerError("boom".into()),
+                        None,
+                    )
+                }),
+            );
+        set_base_url("http://test.invalid/");
+        set_transport(RouterTransport(router));
+
+        let sum: i32 = call("/api/add", &AddArgs { a: 1, b: 2 }).await.unwrap();
+        assert_eq!(sum, 3);
+        let difference: i32 = call_get("/api/sub", &AddArgs { a: 5, b: 2 }).await.unwrap();
+        assert_eq!(difference, 3);
+        let error = call::<_, i32>("/api/fail", &AddArgs { a: 1, b: 2 })
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ServerFnError::ServerError(message) if message == "boom"));
+        let slow = call::<_, i32>("/api/slow", &AddArgs { a: 1, b: 2 });
+        let error = crate::rpc::with_timeout(Duration::from_millis(20), slow)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, ServerFnError::Timeout { timeout_ms: 20 }));
+    }
+
+    #[tokio::test]
+    async fn reqwest_transport_sends_calls_over_http() {
+        let router = Router::new().route(
+            "/api/echo",
+            post(|headers: axum::http::HeaderMap, body: String| async move {
+                let content_type = headers[axum::http::header::CONTENT_TYPE].clone();
+                (
+                    axum::http::StatusCode::CREATED,
+                    format!("{content_type:?} {body}"),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        let response = ReqwestTransport::default()
+            .send(HttpRequest {
+                method: "POST",
+                url: format!("http://{addr}/api/echo"),
+                headers: vec![("content-type", "application/json".into())],
+                body: b"[1,2]".to_vec(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, b"\"application/json\" [1,2]");
+    }
+}
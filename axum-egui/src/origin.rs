@@ -0,0 +1,155 @@
+//! `Origin` checks for streaming endpoints.
+//!
+//! Browsers attach cookies to WebSocket handshakes from any site and do not
+//! apply CORS to them, so without a check any page a user visits can open a
+//! socket to a cookie-authenticated app in their name (cross-site WebSocket
+//! hijacking). The handlers generated for `#[server(sse)]` and
+//! `#[server(ws)]` therefore reject requests whose `Origin` is neither the
+//! server's own nor explicitly allowed, with `403 Forbidden`.
+//!
+//! The policy defaults to same-origin. Allow other origins, e.g. a separately
+//! hosted frontend, with an [`OriginPolicy`] installed by
+//! [`Serve::origin_policy`](crate::serve::Serve::origin_policy) (or as an
+//! [`Extension`](axum::Extension) on a hand-built router):
+//!
+//! ```ignore
+//! axum_egui::serve(app, addr)
+//!     .origin_policy(OriginPolicy::same_origin().allow("https://app.example.com"))
+//!     .await?;
+//! ```
+//!
+//! Requests without an `Origin` header (non-browser clients, and same-origin
+//! `EventSource` requests in some browsers) are allowed.
+
+use crate::error::ErrorEnvelope;
+use axum::http::request::Parts;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+/// Which origins may open streams.
+#[derive(Debug, Clone, Default)]
+pub struct OriginPolicy {
+    allowed: Vec<String>,
+    any: bool,
+}
+
+impl OriginPolicy {
+    /// Only the server's own origin (the default).
+    pub fn same_origin() -> Self {
+        Self::default()
+    }
+
+    /// Any origin; disables the check.
+    pub fn any() -> Self {
+        Self {
+            allowed: Vec::new(),
+            any: true,
+        }
+    }
+
+    /// Also allow `origin`, e.g. `https://app.example.com`.
+    pub fn allow(mut self, origin: impl Into<String>) -> Self {
+        let origin = origin.into();
+        self.allowed
+            .push(origin.trim_end_matches('/').to_ascii_lowercase());
+        self
+    }
+
+    /// Check the `Origin` of a request against this policy.
+    pub fn check(&self, parts: &Parts) -> Result<(), OriginRejected> {
+        if self.any {
+            return Ok(());
+        }
+        let Some(origin) = parts.headers.get(header::ORIGIN) else {
+            return Ok(());
+        };
+        let origin = origin.to_str().unwrap_or_default().to_ascii_lowercase();
+
+        // `scheme://host[:port]` against the Host the request was sent to
+        let authority = origin.split_once("://").map(|(_, authority)| authority);
+        let host = parts
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .or_else(|| parts.uri.authority().map(|a| a.as_str()));
+        if authority
+            .is_some_and(|authority| host.is_some_and(|h| h.eq_ignore_ascii_case(authority)))
+        {
+            return Ok(());
+        }
+        if self.allowed.contains(&origin) {
+            return Ok(());
+        }
+        Err(OriginRejected { origin })
+    }
+}
+
+/// Check the `Origin` of a request against the installed [`OriginPolicy`],
+/// or same-origin if there is none. Called by the generated stream handlers.
+pub fn check(parts: &Parts) -> Result<(), OriginRejected> {
+    match parts.extensions.get::<OriginPolicy>() {
+        Some(policy) => policy.check(parts),
+        None => OriginPolicy::same_origin().check(parts),
+    }
+}
+
+/// A request from an origin the [`OriginPolicy`] does not allow. Responds
+/// with `403 Forbidden`.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("cross-origin request from `{origin}` is not allowed")]
+pub struct OriginRejected {
+    origin: String,
+}
+
+impl OriginRejected {
+    /// The rejected `Origin` header value.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+}
+
+impl IntoResponse for OriginRejected {
+    fn into_response(self) -> Response {
+        let error = ErrorEnvelope::new("forbidden_origin", self.to_string());
+        crate::error::error_response(StatusCode::FORBIDDEN, error, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    fn parts(host: &str, origin: Option<&str>) -> Parts {
+        let mut request = Request::get("/api/chat").header(header::HOST, host);
+        if let Some(origin) = origin {
+            request = request.header(header::ORIGIN, origin);
+        }
+        request.body(()).unwrap().into_parts().0
+    }
+
+    #[test]
+    fn same_origin_by_default() {
+        assert!(check(&parts("app.example.com", Some("https://app.example.com"))).is_ok());
+        assert!(check(&parts("localhost:3000", Some("http://localhost:3000"))).is_ok());
+        assert!(check(&parts("localhost:3000", None)).is_ok());
+
+        let rejected = check(&parts("app.example.com", Some("https://evil.example"))).unwrap_err();
+        assert_eq!(rejected.origin(), "https://evil.example");
+        assert_eq!(rejected.into_response().status(), StatusCode::FORBIDDEN);
+        // A different port is a different origin
+        assert!(check(&parts("localhost:3000", Some("http://localhost:8080"))).is_err());
+    }
+
+    #[test]
+    fn installed_policy_allows_listed_origins() {
+        let policy = OriginPolicy::same_origin().allow("https://Admin.example.com/");
+        let mut request = parts("app.example.com", Some("https://admin.example.com"));
+        assert!(check(&request).is_err());
+        request.extensions.insert(policy);
+        assert!(check(&request).is_ok());
+
+        let evil = parts("app.example.com", Some("https://evil.example"));
+        assert!(OriginPolicy::any().check(&evil).is_ok());
+    }
+}
@@ -0,0 +1,510 @@
+//! Differential state updates pushed from the server to the egui app.
+//!
+//! Instead of re-sending a whole snapshot on every change, the server watches
+//! a state value, computes a [JSON Patch](https://www.rfc-editor.org/rfc/rfc6902)
+//! between consecutive versions and streams only the patch over SSE. The
+//! client applies patches to its copy of the state and records which fields
+//! changed, so a dashboard can update with tiny payloads:
+//!
+//! ```ignore
+//! // Server
+//! let (tx, rx) = tokio::sync::watch::channel(Dashboard::default());
+//! let app = Router::new().route("/api/dashboard", get(move || async move {
+//!     axum_egui::patch::sse(rx.clone())
+//! }));
+//! tx.send_modify(|d| d.requests += 1); // clients receive a one-op patch
+//!
+//! // Client
+//! let live = axum_egui::patch::subscribe::<Dashboard>("/api/dashboard", {
+//!     let ctx = cc.egui_ctx.clone();
+//!     move || ctx.request_repaint()
+//! })?;
+//!
+//! // In `update()`
+//! let mut live = live.lock();
+//! if let Some(dashboard) = live.state() {
+//!     ui.label(format!("{} requests", dashboard.requests));
+//! }
+//! if live.is_changed("/requests") { /* highlight */ }
+//! live.clear_changes();
+//! ```
+//!
+//! Every connection starts with a full snapshot ([`SNAPSHOT_EVENT`]), then
+//! receives numbered patches ([`PATCH_EVENT`]). Changes made faster than a
+//! client reads are coalesced into one patch. If a patch cannot be applied,
+//! the client reconnects and starts over from a fresh snapshot.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+pub use json_patch::Patch;
+
+/// SSE event name of the initial full state.
+pub const SNAPSHOT_EVENT: &str = "state-snapshot";
+
+/// SSE event name of an incremental update.
+pub const PATCH_EVENT: &str = "state-patch";
+
+/// The full state, sent when a client connects.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Sequence number of this version; the next patch is `seq + 1`.
+    pub seq: u64,
+    /// The state as JSON.
+    pub state: Value,
+}
+
+/// The changes between two consecutive versions of the state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatePatch {
+    /// Sequence number of the version this patch produces.
+    pub seq: u64,
+    /// JSON Patch operations turning the previous version into this one.
+    pub ops: Patch,
+}
+
+/// An update could not be applied to a [`PatchedState`].
+#[derive(Debug, thiserror::Error)]
+pub enum PatchStateError {
+    /// A patch arrived before the initial snapshot.
+    #[error("received a patch before the initial snapshot")]
+    NoSnapshot,
+    /// A patch was skipped or repeated.
+    #[error("expected patch {expected}, got {got}")]
+    OutOfOrder {
+        /// The sequence number that was due.
+        expected: u64,
+        /// The sequence number received.
+        got: u64,
+    },
+    /// The operations do not apply to the current state.
+    #[error("invalid patch: {0}")]
+    Patch(#[from] json_patch::PatchError),
+    /// The patched JSON no longer matches the state type.
+    #[error("patched state does not deserialize: {0}")]
+    Deserialize(#[from] serde_json::Error),
+}
+
+/// Client-side copy of a patched state value.
+///
+/// Tracks the JSON document the patches apply to, the deserialized state,
+/// and the JSON pointers (e.g. `/stats/requests`) changed since the last
+/// [`clear_changes`](Self::clear_changes).
+#[derive(Debug)]
+pub struct PatchedState<T> {
+    value: Value,
+    state: Option<T>,
+    seq: u64,
+    changed: BTreeSet<String>,
+}
+
+impl<T> Default for PatchedState<T> {
+    fn default() -> Self {
+        Self {
+            value: Value::Null,
+            state: None,
+            seq: 0,
+            changed: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> PatchedState<T> {
+    /// An empty state, waiting for its first snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the state with a full snapshot. Marks everything as changed.
+    pub fn apply_snapshot(&mut self, snapshot: StateSnapshot) -> Result<(), PatchStateError> {
+        self.state = Some(serde_json::from_value(snapshot.state.clone())?);
+        self.value = snapshot.state;
+        self.seq = snapshot.seq;
+        self.changed.clear();
+        self.changed.insert(String::new());
+        Ok(())
+    }
+
+    /// Apply the next patch. On error the state is left unchanged.
+    pub fn apply_patch(&mut self, patch: &StatePatch) -> Result<(), PatchStateError> {
+        if self.state.is_none() {
+            return Err(PatchStateError::NoSnapshot);
+        }
+        if patch.seq != self.seq + 1 {
+            return Err(PatchStateError::OutOfOrder {
+                expected: self.seq + 1,
+                got: patch.seq,
+            });
+        }
+
+        let mut value = self.value.clone();
+        json_patch::patch(&mut value, &patch.ops)?;
+        self.state = Some(serde_json::from_value(value.clone())?);
+        self.value = value;
+        self.seq = patch.seq;
+        self.changed
+            .extend(patch.ops.iter().map(|op| op.path().to_string()));
+        Ok(())
+    }
+}
+
+impl<T> PatchedState<T> {
+    /// The current state, once the first snapshot has arrived.
+    pub fn state(&self) -> Option<&T> {
+        self.state.as_ref()
+    }
+
+    /// Sequence number of the current version.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Whether the value at `pointer` (e.g. `/stats/requests`, or `""` for
+    /// anything) changed since the last [`clear_changes`](Self::clear_changes).
+    ///
+    /// A change to a parent or a child of `pointer` counts as a change.
+    pub fn is_changed(&self, pointer: &str) -> bool {
+        self.changed
+            .iter()
+            .any(|changed| contains(pointer, changed) || contains(changed, pointer))
+    }
+
+    /// The JSON pointers changed since the last
+    /// [`clear_changes`](Self::clear_changes). A snapshot shows up as `""`.
+    pub fn changes(&self) -> &BTreeSet<String> {
+        &self.changed
+    }
+
+    /// Forget recorded changes, typically at the end of a frame.
+    pub fn clear_changes(&mut self) {
+        self.changed.clear();
+    }
+}
+
+/// Whether JSON pointer `inner` is `outer` or points inside it.
+fn contains(outer: &str, inner: &str) -> bool {
+    inner
+        .strip_prefix(outer)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{PATCH_EVENT, SNAPSHOT_EVENT, StatePatch, StateSnapshot};
+    use crate::sse::{Event, KeepAlive, Sse};
+    use axum::response::sse::Event as AxumEvent;
+    use futures_util::Stream;
+    use serde::Serialize;
+    use serde_json::Value;
+    use std::convert::Infallible;
+    use tokio::sync::watch;
+
+    /// SSE events for a watched value: a [`SNAPSHOT_EVENT`] with the current
+    /// value, then a [`PATCH_EVENT`] for every change.
+    ///
+    /// The stream ends when the sender side of `rx` is dropped.
+    pub fn stream<T>(rx: watch::Receiver<T>) -> impl Stream<Item = Result<AxumEvent, Infallible>>
+    where
+        T: Serialize + Send + Sync + 'static,
+    {
+        futures_util::stream::unfold(
+            (rx, None::<Value>, 0u64),
+            |(mut rx, last, seq)| async move {
+                let Some(mut last) = last else {
+                    let state = to_value(&*rx.borrow_and_update())?;
+                    let event = event(
+                        SNAPSHOT_EVENT,
+                        &StateSnapshot {
+                            seq,
+                            state: state.clone(),
+                        },
+                    );
+                    return Some((event, (rx, Some(state), seq)));
+                };
+
+                loop {
+                    rx.changed().await.ok()?;
+                    let Some(state) = to_value(&*rx.borrow_and_update()) else {
+                        continue;
+                    };
+                    let ops = json_patch::diff(&last, &state);
+                    if ops.0.is_empty() {
+                        continue;
+                    }
+                    last = state;
+                    let patch = StatePatch { seq: seq + 1, ops };
+                    return Some((event(PATCH_EVENT, &patch), (rx, Some(last), seq + 1)));
+                }
+            },
+        )
+    }
+
+    /// An SSE response streaming patches of a watched value; see [`stream`].
+    pub fn sse<T>(rx: watch::Receiver<T>) -> Sse<impl Stream<Item = Result<AxumEvent, Infallible>>>
+    where
+        T: Serialize + Send + Sync + 'static,
+    {
+        Sse::new(stream(rx)).keep_alive(KeepAlive::default())
+    }
+
+    fn to_value<T: Serialize>(state: &T) -> Option<Value> {
+        serde_json::to_value(state)
+            .inspect_err(|e| tracing::error!("failed to serialize patched state: {e}"))
+            .ok()
+    }
+
+    fn event(name: &str, data: &impl Serialize) -> Result<AxumEvent, Infallible> {
+        // Snapshots and patches only contain `serde_json::Value`s
+        let event = Event::new()
+            .event(name)
+            .json_data(data)
+            .expect("state events serialize");
+        Ok(event.into())
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{PATCH_EVENT, PatchStateError, PatchedState, SNAPSHOT_EVENT};
+    use crate::sse::SseError;
+    use crate::stream::TaskHandle;
+    use futures_util::StreamExt;
+    use gloo_net::eventsource::futures::EventSource;
+    use serde::de::DeserializeOwned;
+    use std::sync::{Arc, Mutex, MutexGuard, Weak};
+    use wasm_bindgen::JsCast;
+
+    /// A [`PatchedState`] kept up to date by [`subscribe`]. Cheap to clone.
+    #[derive(Debug)]
+    pub struct LiveState<T> {
+        inner: Arc<Mutex<PatchedState<T>>>,
+        task: Arc<TaskHandle>,
+    }
+
+    impl<T> Clone for LiveState<T> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+                task: self.task.clone(),
+            }
+        }
+    }
+
+    impl<T> LiveState<T> {
+        /// Lock the state for reading (and clearing changes).
+        pub fn lock(&self) -> MutexGuard<'_, PatchedState<T>> {
+            self.inner.lock().unwrap_or_else(|e| e.into_inner())
+        }
+    }
+
+    /// Follow the patch stream at `url` (served by [`sse`](super::sse) on
+    /// the server). `on_change` runs after every applied update; pass
+    /// `egui::Context::request_repaint` so the UI redraws.
+    ///
+    /// The subscription stops, closing its connection, as soon as every
+    /// [`LiveState`] handle is dropped.
+    pub fn subscribe<T>(url: &str, on_change: impl Fn() + 'static) -> Result<LiveState<T>, SseError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let source = open(url)?;
+        let inner = Arc::new(Mutex::new(PatchedState::new()));
+        let weak = Arc::downgrade(&inner);
+        let url = url.to_string();
+        let task = TaskHandle::spawn(run(url, source, weak, on_change));
+        Ok(LiveState {
+            inner,
+            task: Arc::new(task),
+        })
+    }
+
+    fn open(url: &str) -> Result<EventSource, SseError> {
+        EventSource::new(url).map_err(|e| SseError::Connection(format!("{:?}", e)))
+    }
+
+    async fn run<T: DeserializeOwned>(
+        url: String,
+        mut source: EventSource,
+        state: Weak<Mutex<PatchedState<T>>>,
+        on_change: impl Fn(),
+    ) {
+        loop {
+            let (Ok(snapshots), Ok(patches)) = (
+                source.subscribe(SNAPSHOT_EVENT),
+                source.subscribe(PATCH_EVENT),
+            ) else {
+                return;
+            };
+            let mut events = futures_util::stream::select(snapshots, patches);
+
+            while let Some(event) = events.next().await {
+                // Connection errors are retried by the EventSource itself
+                let Ok((name, msg)) = event else { continue };
+                let Some(state) = state.upgrade() else { return };
+                let data = msg.data().dyn_into::<js_sys::JsString>().ok();
+                let size = data.as_ref().map_or(0, |data| data.length() as usize);
+                if let Err(e) = crate::payload::check(size, crate::payload::limits().sse_limit()) {
+                    // Resyncing would most likely run into the limit again
+                    web_sys::console::error_1(&format!("state updates stopped: {e}").into());
+                    return;
+                }
+                let data = data.map(String::from).unwrap_or_default();
+
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                let result = if name == SNAPSHOT_EVENT {
+                    serde_json::from_str(&data)
+                        .map_err(PatchStateError::from)
+                        .and_then(|snapshot| state.apply_snapshot(snapshot))
+                } else {
+                    serde_json::from_str(&data)
+                        .map_err(PatchStateError::from)
+                        .and_then(|patch| state.apply_patch(&patch))
+                };
+                drop(state);
+
+                match result {
+                    Ok(()) => on_change(),
+                    Err(e) => {
+                        web_sys::console::warn_1(
+                            &format!("state patch failed, resyncing: {e}").into(),
+                        );
+                        break;
+                    }
+                }
+            }
+
+            // Start over from a fresh snapshot
+            drop(events);
+            source.close();
+            source = match open(&url) {
+                Ok(source) => source,
+                Err(_) => return,
+            };
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Dashboard {
+        requests: u64,
+        stats: Stats,
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Stats {
+        errors: u64,
+        label: String,
+    }
+
+    fn patch(seq: u64, old: &Dashboard, new: &Dashboard) -> StatePatch {
+        let old = serde_json::to_value(old).unwrap();
+        let new = serde_json::to_value(new).unwrap();
+        StatePatch {
+            seq,
+            ops: json_patch::diff(&old, &new),
+        }
+    }
+
+    #[test]
+    fn applies_patches_and_tracks_changes() {
+        let v0 = Dashboard::default();
+        let mut v1 = v0.clone();
+        v1.stats.errors = 3;
+
+        let mut state = PatchedState::<Dashboard>::new();
+        state
+            .apply_snapshot(StateSnapshot {
+                seq: 0,
+                state: serde_json::to_value(&v0).unwrap(),
+            })
+            .unwrap();
+        assert!(state.is_changed("/requests"));
+        state.clear_changes();
+
+        state.apply_patch(&patch(1, &v0, &v1)).unwrap();
+        assert_eq!(state.state(), Some(&v1));
+        assert!(state.is_changed("/stats/errors"));
+        assert!(state.is_changed("/stats"));
+        assert!(!state.is_changed("/stats/label"));
+        assert!(!state.is_changed("/requests"));
+    }
+
+    #[test]
+    fn rejects_out_of_order_patches() {
+        let mut state = PatchedState::<Dashboard>::new();
+        let p = patch(1, &Dashboard::default(), &Dashboard::default());
+        assert!(matches!(
+            state.apply_patch(&p),
+            Err(PatchStateError::NoSnapshot)
+        ));
+
+        state
+            .apply_snapshot(StateSnapshot {
+                seq: 5,
+                state: serde_json::to_value(Dashboard::default()).unwrap(),
+            })
+            .unwrap();
+        assert!(matches!(
+            state.apply_patch(&p),
+            Err(PatchStateError::OutOfOrder {
+                expected: 6,
+                got: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn sse_can_be_routed() {
+        let (_tx, rx) = tokio::sync::watch::channel(Dashboard::default());
+        let _app: axum::Router = axum::Router::new().route(
+            "/api/dashboard",
+            axum::routing::get(move || async move { sse(rx.clone()) }),
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_sends_snapshot_then_minimal_patches() {
+        let (tx, rx) = tokio::sync::watch::channel(Dashboard::default());
+        let mut events = Box::pin(stream(rx));
+        assert!(events.next().await.is_some());
+
+        tx.send_modify(|d| d.requests = 1);
+        // No-op updates are not sent
+        tx.send_modify(|_| {});
+        tx.send_modify(|d| d.stats.label = "ok".into());
+        drop(tx);
+
+        let response = axum::response::IntoResponse::into_response(crate::sse::Sse::new(events));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        // Both changes were coalesced into one patch
+        assert_eq!(body.matches(PATCH_EVENT).count(), 1);
+        assert!(body.contains(r#""seq":1"#));
+        assert!(body.contains(r#""path":"/requests""#));
+        assert!(body.contains(r#""path":"/stats/label""#));
+    }
+}
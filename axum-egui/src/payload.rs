@@ -0,0 +1,119 @@
+//! Client-side caps on the size of incoming messages.
+//!
+//! The UI runs on the browser's main thread: parsing a multi-hundred-MB
+//! response or stream message (a query without a `LIMIT`, a runaway debug
+//! dump) locks up the tab for seconds, or runs the WASM heap out of memory.
+//! Payloads over the limit are rejected before they are parsed:
+//!
+//! - server function calls fail with [`ServerFnError::TooLarge`], checked
+//!   against `Content-Length` before the body is read where the server sends
+//!   one
+//! - SSE and WebSocket messages yield [`ServerFnError::TooLarge`] (or the
+//!   `TooLarge` variant of `SseError`/`WsError`) and the stream carries on
+//!   with the next message
+//!
+//! ```ignore
+//! axum_egui::payload::set_limits(
+//!     MessageLimits::new()
+//!         .rpc(8 * 1024 * 1024)
+//!         // Live updates are small; anything bigger is a bug
+//!         .sse(256 * 1024)
+//!         .ws(256 * 1024),
+//! );
+//! ```
+//!
+//! Every limit is [`DEFAULT_MAX_MESSAGE_SIZE`] unless set. Sizes are in
+//! bytes; SSE messages, which arrive as JavaScript strings, are measured in
+//! UTF-16 code units before they are copied into WASM memory.
+
+use crate::rpc::ServerFnError;
+use std::cell::Cell;
+
+/// The default limit of every transport: 64 MiB.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Maximum sizes of incoming messages, per transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLimits {
+    rpc: usize,
+    sse: usize,
+    ws: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageLimits {
+    /// [`DEFAULT_MAX_MESSAGE_SIZE`] for every transport.
+    pub const fn new() -> Self {
+        Self::all(DEFAULT_MAX_MESSAGE_SIZE)
+    }
+
+    /// The same limit for every transport.
+    pub const fn all(max: usize) -> Self {
+        Self {
+            rpc: max,
+            sse: max,
+            ws: max,
+        }
+    }
+
+    /// Maximum size of a server function response body.
+    pub const fn rpc(mut self, max: usize) -> Self {
+        self.rpc = max;
+        self
+    }
+
+    /// Maximum size of one Server-Sent Event's data.
+    pub const fn sse(mut self, max: usize) -> Self {
+        self.sse = max;
+        self
+    }
+
+    /// Maximum size of one WebSocket message.
+    pub const fn ws(mut self, max: usize) -> Self {
+        self.ws = max;
+        self
+    }
+
+    /// The limit of server function responses.
+    pub const fn rpc_limit(&self) -> usize {
+        self.rpc
+    }
+
+    /// The limit of Server-Sent Events.
+    pub const fn sse_limit(&self) -> usize {
+        self.sse
+    }
+
+    /// The limit of WebSocket messages.
+    pub const fn ws_limit(&self) -> usize {
+        self.ws
+    }
+}
+
+thread_local! {
+    static LIMITS: Cell<MessageLimits> = const { Cell::new(MessageLimits::new()) };
+}
+
+/// Replace the message limits. Applies to messages received from now on,
+/// including those of streams that are already open.
+pub fn set_limits(limits: MessageLimits) {
+    LIMITS.set(limits);
+}
+
+/// The message limits in effect.
+pub fn limits() -> MessageLimits {
+    LIMITS.get()
+}
+
+/// [`ServerFnError::TooLarge`] if `size` is over `limit`.
+pub(crate) fn check(size: usize, limit: usize) -> Result<(), ServerFnError> {
+    if size > limit {
+        return Err(ServerFnError::TooLarge { size, limit });
+    }
+    Ok(())
+}
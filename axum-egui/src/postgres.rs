@@ -0,0 +1,114 @@
+//! Postgres `LISTEN`/`NOTIFY` as a source of topic events.
+//!
+//! Let the database announce its changes and forward them to a
+//! [`LoggedTopic`](crate::topic::LoggedTopic), so every connected client
+//! sees them without polling:
+//!
+//! ```sql
+//! CREATE FUNCTION notify_orders() RETURNS trigger AS $$
+//! BEGIN
+//!     PERFORM pg_notify('orders', row_to_json(NEW)::text);
+//!     RETURN NEW;
+//! END $$ LANGUAGE plpgsql;
+//!
+//! CREATE TRIGGER orders_notify AFTER INSERT OR UPDATE ON orders
+//!     FOR EACH ROW EXECUTE FUNCTION notify_orders();
+//! ```
+//!
+//! ```ignore
+//! let orders = LoggedTopic::<Order>::new(100);
+//! orders.forward(axum_egui::postgres::listen::<Order>(&database_url, "orders").await?);
+//! ```
+//!
+//! Payloads are parsed as JSON; those that don't parse as `T` are logged and
+//! skipped. The stream ends when the connection is lost, so call [`listen`]
+//! again to resume; notifications sent in between are not delivered.
+//! [`listen`] connects without TLS; to use TLS, connect yourself and pass the
+//! client and connection to [`listen_with`].
+
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, Client, Connection, NoTls};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// Connect to the database at `config` (a connection string or URL) and
+/// stream the notifications sent on `channel`, parsed as `T`.
+pub async fn listen<T>(
+    config: &str,
+    channel: &str,
+) -> Result<impl Stream<Item = T> + Send + 'static, tokio_postgres::Error>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+    listen_with(client, connection, channel).await
+}
+
+/// Stream the notifications sent on `channel` over an established
+/// connection, parsed as `T`. The connection is driven by a background task
+/// and closed when the stream is dropped.
+pub async fn listen_with<T, S, Tls>(
+    client: Client,
+    mut connection: Connection<S, Tls>,
+    channel: &str,
+) -> Result<impl Stream<Item = T> + Send + 'static, tokio_postgres::Error>
+where
+    T: DeserializeOwned + Send + 'static,
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    Tls: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            match std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                Some(Ok(AsyncMessage::Notification(notification))) => {
+                    if tx.send(notification.payload().to_string()).is_err() {
+                        break;
+                    }
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    tracing::error!("postgres notification connection failed: {e}");
+                    break;
+                }
+                None => break,
+            }
+        }
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {}", quote_ident(channel)))
+        .await?;
+    let channel = channel.to_string();
+    Ok(UnboundedReceiverStream::new(rx).filter_map(move |payload| {
+        // The connection closes once the client is dropped
+        let _ = &client;
+        let event = serde_json::from_str(&payload)
+            .inspect_err(
+                |e| tracing::warn!(channel = %channel, "skipping unparseable notification: {e}"),
+            )
+            .ok();
+        std::future::ready(event)
+    }))
+}
+
+/// `name` as a quoted SQL identifier.
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_names_are_quoted() {
+        assert_eq!(quote_ident("orders"), r#""orders""#);
+        assert_eq!(
+            quote_ident(r#"x"; DROP TABLE orders; --"#),
+            r#""x""; DROP TABLE orders; --""#
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Server-initiated messages to one user's or one connection's open streams.
+//!
+//! A streaming server function [`subscribe`]s its connection, which joins the
+//! presence registry under the [authenticated user](crate::auth::user), and
+//! returns the subscription as its stream. Any other code (a server function,
+//! a background job) can then reach exactly that user's open tabs:
+//!
+//! ```ignore
+//! #[server(sse, auth)]
+//! pub async fn notifications() -> Result<ServerStream<Notice>, ServerFnError> {
+//!     Ok(push::subscribe::<Notice>().1)
+//! }
+//!
+//! #[server]
+//! pub async fn export(report: ReportId) -> Result<(), ServerFnError> {
+//!     let user = current_user()?;
+//!     tokio::spawn(async move {
+//!         let url = run_export(report).await;
+//!         push::push_to_user(&user.id, &Notice::ExportReady { url });
+//!     });
+//!     Ok(())
+//! }
+//! ```
+//!
+//! A connection leaves the registry as soon as its stream is dropped, i.e.
+//! when the client disconnects. Messages are buffered per connection up to
+//! [`PUSH_BUFFER`]; beyond that, messages to a connection that is not keeping
+//! up are dropped. The registry is in memory, so with several server
+//! instances a push only reaches connections to the instance it was made on,
+//! unless the instances share a [`backplane`].
+
+use crate::backplane::{Backplane, Link};
+use crate::rpc::ServerFnError;
+use crate::stream::ServerStream;
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Messages buffered for a connection that is not reading them.
+pub const PUSH_BUFFER: usize = 64;
+
+/// [`Backplane`] channel relaying pushes between instances.
+pub const PUSH_CHANNEL: &str = "axum-egui:push";
+
+/// Identifies one subscribed connection, e.g. one browser tab. Unique
+/// across instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ConnectionId(pub u64);
+
+impl std::fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The channel of the public API. Other modules (like
+/// [`diagnostics`](crate::diagnostics)) subscribe on channels of their own,
+/// so their messages never reach the app's streams.
+const APP_CHANNEL: &str = "";
+
+/// A user's connections on one channel.
+type UserKey = (String, String);
+
+#[derive(Default)]
+struct Registry {
+    connections: HashMap<ConnectionId, (Option<UserKey>, mpsc::Sender<Value>)>,
+    users: HashMap<UserKey, BTreeSet<ConnectionId>>,
+}
+
+fn user_key(channel: &str, user_id: &str) -> UserKey {
+    (channel.to_string(), user_id.to_string())
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Removes the connection from the registry when its stream is dropped.
+struct Registration(ConnectionId);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = registry().lock().unwrap();
+        let Some((Some(user), _)) = registry.connections.remove(&self.0) else {
+            return;
+        };
+        if let Some(connections) = registry.users.get_mut(&user) {
+            connections.remove(&self.0);
+            if connections.is_empty() {
+                registry.users.remove(&user);
+            }
+        }
+    }
+}
+
+/// Subscribe the current connection under the [authenticated
+/// user](crate::auth::user), if any. Returns its id and the stream of
+/// messages pushed to it, to return from the server function.
+pub fn subscribe<T>() -> (ConnectionId, ServerStream<T>)
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    subscribe_on(APP_CHANNEL, crate::auth::user().map(|user| user.id))
+}
+
+/// Like [`subscribe`], under `user_id`, for apps that identify users
+/// themselves.
+pub fn subscribe_as<T>(user_id: impl Into<String>) -> (ConnectionId, ServerStream<T>)
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    subscribe_on(APP_CHANNEL, Some(user_id.into()))
+}
+
+/// Subscribe a connection of `user_id` on `channel`.
+pub(crate) fn subscribe_on<T>(
+    channel: &str,
+    user_id: Option<String>,
+) -> (ConnectionId, ServerStream<T>)
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let user = user_id.map(|user_id| (channel.to_string(), user_id));
+    let id = next_id();
+    let (tx, rx) = mpsc::channel(PUSH_BUFFER);
+    {
+        let mut registry = registry().lock().unwrap();
+        if let Some(user) = &user {
+            registry.users.entry(user.clone()).or_default().insert(id);
+        }
+        registry.connections.insert(id, (user, tx));
+    }
+
+    let registration = Registration(id);
+    let stream = ReceiverStream::new(rx).map(move |value| {
+        let _ = &registration;
+        serde_json::from_value(value).map_err(|e| ServerFnError::Deserialization(e.to_string()))
+    });
+    (id, ServerStream::from_results(stream))
+}
+
+/// A random per-process prefix and a counter, so ids don't collide across
+/// instances.
+fn next_id() -> ConnectionId {
+    static PREFIX: OnceLock<u64> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    let prefix = PREFIX.get_or_init(|| {
+        let mut bytes = [0u8; 4];
+        getrandom::fill(&mut bytes).expect("system random number generator");
+        u64::from(u32::from_le_bytes(bytes)) << 32
+    });
+    ConnectionId(prefix | NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// The target of a push relayed over the backplane.
+#[derive(Serialize, Deserialize)]
+enum Relayed {
+    User(String, Value),
+    Connection(ConnectionId, Value),
+    /// A user's connections on a channel other than the app's.
+    ChannelUser(String, String, Value),
+}
+
+static LINK: OnceLock<Link> = OnceLock::new();
+
+/// Relay pushes to and from the other instances sharing `backplane`, so they
+/// reach connections on any of them. Call once, in a runtime; later calls are
+/// ignored.
+pub fn backplane(backplane: Arc<dyn Backplane>) {
+    LINK.get_or_init(|| {
+        Link::new(backplane, PUSH_CHANNEL, |relayed| match relayed {
+            Relayed::User(user, value) => {
+                deliver_to_user(APP_CHANNEL, &user, value);
+            }
+            Relayed::ChannelUser(channel, user, value) => {
+                deliver_to_user(&channel, &user, value);
+            }
+            Relayed::Connection(id, value) => {
+                deliver_to_connection(id, value);
+            }
+        })
+    });
+}
+
+fn send(id: ConnectionId, sender: &mpsc::Sender<Value>, value: Value) -> bool {
+    match sender.try_send(value) {
+        Ok(()) => true,
+        Err(mpsc::error::TrySendError::Full(_)) => {
+            tracing::warn!(connection = %id, "push buffer full; dropping message");
+            false
+        }
+        Err(mpsc::error::TrySendError::Closed(_)) => false,
+    }
+}
+
+fn to_value(msg: &impl Serialize) -> Option<Value> {
+    serde_json::to_value(msg)
+        .inspect_err(|e| tracing::error!("failed to serialize pushed message: {e}"))
+        .ok()
+}
+
+/// Send `msg` to every open connection of `user_id`. Returns how many
+/// connections of this instance it was queued for.
+pub fn push_to_user(user_id: &str, msg: &impl Serialize) -> usize {
+    push_to_user_on(APP_CHANNEL, user_id, msg)
+}
+
+/// Send `msg` to every connection of `user_id` on `channel`.
+pub(crate) fn push_to_user_on(channel: &str, user_id: &str, msg: &impl Serialize) -> usize {
+    let Some(value) = to_value(msg) else {
+        return 0;
+    };
+    if let Some(link) = LINK.get() {
+        let relayed = if channel == APP_CHANNEL {
+            Relayed::User(user_id.to_string(), value.clone())
+        } else {
+            Relayed::ChannelUser(channel.to_string(), user_id.to_string(), value.clone())
+        };
+        link.send(&relayed);
+    }
+    deliver_to_user(channel, user_id, value)
+}
+
+fn deliver_to_user(channel: &str, user_id: &str, value: Value) -> usize {
+    let registry = registry().lock().unwrap();
+    let Some(connections) = registry.users.get(&user_key(channel, user_id)) else {
+        return 0;
+    };
+    connections
+        .iter()
+        .filter_map(|id| Some((id, &registry.connections.get(id)?.1)))
+        .filter(|(id, sender)| send(**id, sender, value.clone()))
+        .count()
+}
+
+/// Send `msg` to the connection `id`. Returns whether it was queued on this
+/// instance, i.e. whether the connection is open here and keeping up.
+pub fn push_to_connection(id: ConnectionId, msg: &impl Serialize) -> bool {
+    let Some(value) = to_value(msg) else {
+        return false;
+    };
+    if let Some(link) = LINK.get() {
+        link.send(&Relayed::Connection(id, value.clone()));
+    }
+    deliver_to_connection(id, value)
+}
+
+fn deliver_to_connection(id: ConnectionId, value: Value) -> bool {
+    let registry = registry().lock().unwrap();
+    registry
+        .connections
+        .get(&id)
+        .is_some_and(|(_, sender)| send(id, sender, value))
+}
+
+/// The open connections of `user_id` on this instance.
+pub fn connections(user_id: &str) -> Vec<ConnectionId> {
+    connections_on(APP_CHANNEL, user_id)
+}
+
+/// The open connections of `user_id` on `channel` on this instance.
+pub(crate) fn connections_on(channel: &str, user_id: &str) -> Vec<ConnectionId> {
+    let registry = registry().lock().unwrap();
+    registry
+        .users
+        .get(&user_key(channel, user_id))
+        .map(|connections| connections.iter().copied().collect())
+        .unwrap_or_default()
+}
+
+/// Whether `user_id` has at least one open connection on this instance.
+pub fn is_online(user_id: &str) -> bool {
+    registry()
+        .lock()
+        .unwrap()
+        .users
+        .contains_key(&user_key(APP_CHANNEL, user_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pushes_reach_exactly_the_targeted_connections() {
+        let (tab1, mut alice1) = subscribe_as::<String>("test-push-alice");
+        let (_, mut alice2) = subscribe_as::<String>("test-push-alice");
+        let (bob, mut bob_stream) = subscribe_as::<String>("test-push-bob");
+        assert_eq!(connections("test-push-alice").len(), 2);
+        assert!(connections("test-push-alice").contains(&tab1));
+
+        assert_eq!(push_to_user("test-push-alice", &"export ready"), 2);
+        assert_eq!(alice1.next().await.unwrap().unwrap(), "export ready");
+        assert_eq!(alice2.next().await.unwrap().unwrap(), "export ready");
+
+        assert!(push_to_connection(bob, &"hi bob"));
+        assert_eq!(bob_stream.next().await.unwrap().unwrap(), "hi bob");
+
+        // Messages of the wrong shape fail the item, not the stream
+        assert!(push_to_connection(tab1, &42));
+        assert!(matches!(
+            alice1.next().await,
+            Some(Err(ServerFnError::Deserialization(_)))
+        ));
+
+        drop(alice1);
+        assert_eq!(connections("test-push-alice").len(), 1);
+        assert!(!push_to_connection(tab1, &"gone"));
+        drop(alice2);
+        assert!(!is_online("test-push-alice"));
+        assert_eq!(push_to_user("test-push-alice", &"anyone?"), 0);
+        assert!(is_online("test-push-bob"));
+    }
+
+    #[tokio::test]
+    async fn pushes_from_other_instances_are_delivered() {
+        use crate::backplane::MemoryBackplane;
+
+        let memory = MemoryBackplane::new();
+        backplane(Arc::new(memory.clone()));
+        for _ in 0..4 {
+            tokio::task::yield_now().await;
+        }
+        let (id, mut stream) = subscribe_as::<String>("test-push-remote");
+
+        // What another instance's link sends
+        let relayed = |target: Relayed| {
+            serde_json::to_vec(&serde_json::json!({ "origin": 0, "data": target })).unwrap()
+        };
+        let user = Relayed::User("test-push-remote".into(), "to user".into());
+        let connection = Relayed::Connection(id, "to connection".into());
+        for target in [user, connection] {
+            memory.publish(PUSH_CHANNEL, relayed(target)).await.unwrap();
+        }
+        assert_eq!(stream.next().await.unwrap().unwrap(), "to user");
+        assert_eq!(stream.next().await.unwrap().unwrap(), "to connection");
+    }
+
+    #[tokio::test]
+    async fn full_buffers_drop_messages() {
+        let (id, mut stream) = subscribe_as::<usize>("test-push-slow");
+        for i in 0..PUSH_BUFFER {
+            assert!(push_to_connection(id, &i));
+        }
+        assert!(!push_to_connection(id, &PUSH_BUFFER));
+        assert_eq!(stream.next().await.unwrap().unwrap(), 0);
+        assert!(push_to_connection(id, &PUSH_BUFFER));
+    }
+}
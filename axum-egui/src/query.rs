@@ -0,0 +1,507 @@
+//! Client-side cache of server function results.
+//!
+//! Results are cached under a key built from the endpoint and its arguments
+//! with [`key`]. [`fetch`] returns the cached result, joins a call already in
+//! flight, or makes the call; [`prefetch`] makes it ahead of time at
+//! [`CallPriority::Background`], so the data is there by the time the user
+//! asks for it:
+//!
+//! ```ignore
+//! let key = query::key(DetailsArgs::PATH, &DetailsArgs { id });
+//!
+//! // With the `egui` feature: start loading when the pointer is on the button
+//! let button = ui.button("Details").prefetch_on_hover(&key, move || details(id));
+//! if button.clicked() {
+//!     spawn_local(async move {
+//!         let details = query::fetch(&key, move || details(id)).await;
+//!         // ...
+//!     });
+//! }
+//!
+//! // Or read synchronously while drawing
+//! if let Some(details) = query::peek::<Details>(&key) { /* ... */ }
+//! ```
+//!
+//! Failed calls are not cached. Entries stay until [`invalidate`]d or
+//! [`clear`]ed.
+//!
+//! # Persistence
+//!
+//! The cache lives in memory, so after a reload the app starts blank until
+//! its calls return, which on a slow connection can take a while. Results
+//! fetched with [`fetch_persisted`] are also written to IndexedDB with a
+//! time to live. [`load_persisted`] reads them back at startup, and
+//! [`peek_persisted`] shows them until the fresh result arrives:
+//!
+//! ```ignore
+//! // At startup, before the first frame (`Bootstrap::restore_queries` does it)
+//! query::load_persisted().await?;
+//!
+//! // While drawing: the fresh rows, or last session's while they load
+//! if let Some(rows) = query::peek_persisted::<Vec<Row>>(&key) { /* ... */ }
+//! query::prefetch_persisted(&key, Duration::from_secs(24 * 3600), move || rows(filter));
+//! ```
+//!
+//! Expired entries are dropped when loading. [`invalidate`] and [`clear`]
+//! also forget the persisted copies.
+
+use crate::concurrency::with_priority;
+use crate::rpc::{CallPriority, ServerFnError};
+use futures_util::FutureExt;
+use futures_util::future::{LocalBoxFuture, Shared};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+
+type Entry = Shared<LocalBoxFuture<'static, Result<Rc<dyn Any>, ServerFnError>>>;
+
+thread_local! {
+    static CACHE: RefCell<HashMap<String, Entry>> = RefCell::default();
+    /// Results of earlier sessions, from [`load_persisted`].
+    static PERSISTED: RefCell<HashMap<String, PersistedEntry>> = RefCell::default();
+    /// The IndexedDB database, once opened.
+    static DATABASE: RefCell<Option<web_sys::IdbDatabase>> = const { RefCell::new(None) };
+}
+
+/// Name of the IndexedDB database.
+pub const DATABASE_NAME: &str = "axum-egui";
+
+/// Object store of persisted results in [`DATABASE_NAME`].
+pub const STORE_NAME: &str = "query";
+
+/// The cache key of a call to `path` with `args`, e.g. a server function's
+/// `PATH` and args struct.
+pub fn key(path: &str, args: &impl Serialize) -> String {
+    let args = serde_json::to_string(args).unwrap_or_default();
+    format!("{path}#{args}")
+}
+
+/// The cached call under `key`, started with `call` if there is none (or it
+/// holds another type).
+fn entry<T, F>(key: &str, call: impl FnOnce() -> F, priority: CallPriority) -> Entry
+where
+    T: 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    if let Some(entry) = CACHE.with_borrow(|cache| cache.get(key).cloned()) {
+        let wrong_type = entry
+            .peek()
+            .is_some_and(|result| result.as_ref().is_ok_and(|value| !value.is::<T>()));
+        if !wrong_type {
+            return entry;
+        }
+    }
+    let owned_key = key.to_string();
+    let call = with_priority(priority, call());
+    let entry = async move {
+        let result = call.await;
+        if result.is_err() {
+            CACHE.with_borrow_mut(|cache| cache.remove(&owned_key));
+        }
+        result.map(|value| Rc::new(value) as Rc<dyn Any>)
+    }
+    .boxed_local()
+    .shared();
+    CACHE.with_borrow_mut(|cache| cache.insert(key.to_string(), entry.clone()));
+    entry
+}
+
+fn downcast<T: Clone + 'static>(
+    result: Result<Rc<dyn Any>, ServerFnError>,
+) -> Result<T, ServerFnError> {
+    let value = result?;
+    value.downcast_ref::<T>().cloned().ok_or_else(|| {
+        ServerFnError::Deserialization("query cache entry holds another type".to_string())
+    })
+}
+
+/// The result of the call under `key`: cached, shared with the call in
+/// flight, or from `call`.
+pub async fn fetch<T, F>(key: &str, call: impl FnOnce() -> F) -> Result<T, ServerFnError>
+where
+    T: Clone + 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    let priority = crate::concurrency::current_priority();
+    downcast(entry(key, call, priority).await)
+}
+
+/// Start the call under `key` in the background, unless it is cached or in
+/// flight. Cheap enough to call every frame.
+pub fn prefetch<T, F>(key: &str, call: impl FnOnce() -> F)
+where
+    T: 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    if CACHE.with_borrow(|cache| cache.contains_key(key)) {
+        return;
+    }
+    let entry = entry(key, call, CallPriority::Background);
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = entry.await;
+    });
+}
+
+/// The cached value under `key`, if its call has completed.
+pub fn peek<T: Clone + 'static>(key: &str) -> Option<T> {
+    let entry = CACHE.with_borrow(|cache| cache.get(key).cloned())?;
+    entry.peek()?.as_ref().ok()?.downcast_ref::<T>().cloned()
+}
+
+/// Forget the entry under `key`, and its persisted copy, so the next
+/// [`fetch`] calls again.
+pub fn invalidate(key: &str) {
+    CACHE.with_borrow_mut(|cache| cache.remove(key));
+    PERSISTED.with_borrow_mut(|persisted| persisted.remove(key));
+    if is_open() {
+        let key = JsValue::from_str(key);
+        spawn_write(move |store| store.delete(&key));
+    }
+}
+
+/// Forget all entries, and the persisted copies.
+pub fn clear() {
+    CACHE.with_borrow_mut(|cache| cache.clear());
+    PERSISTED.with_borrow_mut(|persisted| persisted.clear());
+    if is_open() {
+        spawn_write(|store| store.clear());
+    }
+}
+
+// ============================================================================
+// Persistence
+// ============================================================================
+
+/// IndexedDB could not be used.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PersistError {
+    /// The browser has no IndexedDB, e.g. in some private modes.
+    #[error("IndexedDB is not available")]
+    Unavailable,
+    /// A request to IndexedDB failed.
+    #[error("IndexedDB request failed: {0}")]
+    Request(String),
+}
+
+impl From<JsValue> for PersistError {
+    fn from(error: JsValue) -> Self {
+        PersistError::Request(format!("{error:?}"))
+    }
+}
+
+/// A result as stored in IndexedDB.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PersistedEntry {
+    /// When the entry expires, in milliseconds since the Unix epoch.
+    expires_ms: f64,
+    value: serde_json::Value,
+}
+
+impl PersistedEntry {
+    fn is_fresh(&self, now_ms: f64) -> bool {
+        now_ms < self.expires_ms
+    }
+}
+
+/// Read the unexpired results of earlier sessions from IndexedDB, for
+/// [`peek_persisted`], and delete the expired ones. Returns how many were
+/// read.
+pub async fn load_persisted() -> Result<usize, PersistError> {
+    let database = open().await?;
+    let store = database
+        .transaction_with_str(STORE_NAME)?
+        .object_store(STORE_NAME)?;
+    let keys = request(&store.get_all_keys()?).await?;
+    let values = request(&store.get_all()?).await?;
+    let (keys, values) = (js_sys::Array::from(&keys), js_sys::Array::from(&values));
+
+    let now_ms = js_sys::Date::now();
+    let mut expired = Vec::new();
+    let mut loaded = HashMap::new();
+    for (key, value) in keys.iter().zip(values.iter()) {
+        let Some(key) = key.as_string() else {
+            continue;
+        };
+        match value
+            .as_string()
+            .and_then(|json| serde_json::from_str::<PersistedEntry>(&json).ok())
+        {
+            Some(entry) if entry.is_fresh(now_ms) => {
+                loaded.insert(key, entry);
+            }
+            _ => expired.push(key),
+        }
+    }
+    for key in expired {
+        let key = JsValue::from_str(&key);
+        spawn_write(move |store| store.delete(&key));
+    }
+    let count = loaded.len();
+    PERSISTED.set(loaded);
+    Ok(count)
+}
+
+/// [`fetch`], also writing the result of a call made to IndexedDB, where it
+/// is kept for `ttl`.
+pub async fn fetch_persisted<T, F>(
+    key: &str,
+    ttl: Duration,
+    call: impl FnOnce() -> F,
+) -> Result<T, ServerFnError>
+where
+    T: Clone + Serialize + 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    fetch(key, persisting(key, ttl, call)).await
+}
+
+/// [`prefetch`], also writing the result to IndexedDB, where it is kept for
+/// `ttl`.
+pub fn prefetch_persisted<T, F>(key: &str, ttl: Duration, call: impl FnOnce() -> F)
+where
+    T: Serialize + 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    prefetch(key, persisting(key, ttl, call));
+}
+
+/// The fresh value under `key` if its call has completed, else the value
+/// persisted by an earlier session, if it hasn't expired.
+pub fn peek_persisted<T>(key: &str) -> Option<T>
+where
+    T: Clone + DeserializeOwned + 'static,
+{
+    peek(key).or_else(|| persisted(key, js_sys::Date::now()))
+}
+
+fn persisted<T: DeserializeOwned>(key: &str, now_ms: f64) -> Option<T> {
+    let entry = PERSISTED.with_borrow(|persisted| persisted.get(key).cloned())?;
+    if !entry.is_fresh(now_ms) {
+        return None;
+    }
+    serde_json::from_value(entry.value).ok()
+}
+
+/// `call`, writing its result under `key` once it succeeds.
+fn persisting<T, F>(
+    key: &str,
+    ttl: Duration,
+    call: impl FnOnce() -> F,
+) -> impl FnOnce() -> LocalBoxFuture<'static, Result<T, ServerFnError>>
+where
+    T: Serialize + 'static,
+    F: Future<Output = Result<T, ServerFnError>> + 'static,
+{
+    let key = key.to_string();
+    move || {
+        let call = call();
+        async move {
+            let value = call.await?;
+            if let Ok(json) = serde_json::to_value(&value) {
+                let entry = PersistedEntry {
+                    expires_ms: js_sys::Date::now() + ttl.as_millis() as f64,
+                    value: json,
+                };
+                if let Ok(json) = serde_json::to_string(&entry) {
+                    let (key, json) = (JsValue::from_str(&key), JsValue::from_str(&json));
+                    spawn_write(move |store| store.put_with_key(&json, &key));
+                }
+                PERSISTED.with_borrow_mut(|persisted| persisted.insert(key, entry));
+            }
+            Ok(value)
+        }
+        .boxed_local()
+    }
+}
+
+fn is_open() -> bool {
+    DATABASE.with_borrow(Option::is_some)
+}
+
+/// The database, opened (and created) on first use.
+async fn open() -> Result<web_sys::IdbDatabase, PersistError> {
+    if let Some(database) = DATABASE.with_borrow(Clone::clone) {
+        return Ok(database);
+    }
+    let factory = web_sys::window()
+        .and_then(|window| window.indexed_db().ok().flatten())
+        .ok_or(PersistError::Unavailable)?;
+    let open = factory.open_with_u32(DATABASE_NAME, 1)?;
+    let on_upgrade = Closure::<dyn FnMut()>::new({
+        let open = open.clone();
+        move || {
+            if let Ok(database) = open.result() {
+                let _ = database
+                    .unchecked_into::<web_sys::IdbDatabase>()
+                    .create_object_store(STORE_NAME);
+            }
+        }
+    });
+    open.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+    let database: web_sys::IdbDatabase = request(&open).await?.unchecked_into();
+    DATABASE.set(Some(database.clone()));
+    Ok(database)
+}
+
+/// Wait for `request` to succeed.
+async fn request(request: &web_sys::IdbRequest) -> Result<JsValue, PersistError> {
+    let (send, receive) = futures_channel::oneshot::channel::<bool>();
+    let send = Rc::new(RefCell::new(Some(send)));
+    let done = |succeeded: bool| {
+        let send = send.clone();
+        Closure::<dyn FnMut()>::new(move || {
+            if let Some(send) = send.borrow_mut().take() {
+                let _ = send.send(succeeded);
+            }
+        })
+    };
+    let (on_success, on_error) = (done(true), done(false));
+    request.set_onsuccess(Some(on_success.as_ref().unchecked_ref()));
+    request.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let succeeded = receive.await.unwrap_or(false);
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    if succeeded {
+        Ok(request.result()?)
+    } else {
+        let error = request.error().ok().flatten();
+        Err(PersistError::Request(error.map_or_else(
+            || "unknown error".to_string(),
+            |error| error.message(),
+        )))
+    }
+}
+
+/// Run `write` on the store in a transaction of its own, logging failures.
+fn spawn_write(
+    write: impl FnOnce(&web_sys::IdbObjectStore) -> Result<web_sys::IdbRequest, JsValue> + 'static,
+) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = async {
+            let store = open()
+                .await?
+                .transaction_with_str_and_mode(STORE_NAME, web_sys::IdbTransactionMode::Readwrite)?
+                .object_store(STORE_NAME)?;
+            request(&write(&store)?).await
+        }
+        .await;
+        if let Err(e) = result {
+            web_sys::console::warn_1(&format!("query cache not persisted: {e}").into());
+        }
+    });
+}
+
+/// Prefetching from egui widgets.
+#[cfg(feature = "egui")]
+pub trait PrefetchExt {
+    /// [`prefetch`] the call under `key` while the pointer is on the
+    /// widget, before it is clicked.
+    fn prefetch_on_hover<T, F>(self, key: &str, call: impl FnOnce() -> F) -> Self
+    where
+        T: 'static,
+        F: Future<Output = Result<T, ServerFnError>> + 'static;
+}
+
+#[cfg(feature = "egui")]
+impl PrefetchExt for egui::Response {
+    fn prefetch_on_hover<T, F>(self, key: &str, call: impl FnOnce() -> F) -> Self
+    where
+        T: 'static,
+        F: Future<Output = Result<T, ServerFnError>> + 'static,
+    {
+        if self.hovered() {
+            prefetch(key, call);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn fetch_caches_results_but_not_errors() {
+        let calls = Rc::new(Cell::new(0));
+        let details = |ok: bool| {
+            let calls = calls.clone();
+            move || async move {
+                calls.set(calls.get() + 1);
+                if ok {
+                    Ok(String::from("details"))
+                } else {
+                    Err(ServerFnError::Request("offline".into()))
+                }
+            }
+        };
+        let key = key("/api/details", &serde_json::json!({ "id": 7 }));
+        assert_eq!(key, r#"/api/details#{"id":7}"#);
+        assert_eq!(peek::<String>(&key), None);
+
+        let failed = fetch(&key, details(false)).now_or_never().unwrap();
+        assert!(failed.is_err());
+        assert_eq!(peek::<String>(&key), None);
+
+        let fetched = fetch(&key, details(true)).now_or_never().unwrap();
+        assert_eq!(fetched.unwrap(), "details");
+        let cached = fetch(&key, details(true)).now_or_never().unwrap();
+        assert_eq!(cached.unwrap(), "details");
+        assert_eq!(peek::<String>(&key).as_deref(), Some("details"));
+        assert_eq!(calls.get(), 2);
+
+        invalidate(&key);
+        assert_eq!(peek::<String>(&key), None);
+        fetch(&key, details(true)).now_or_never().unwrap().unwrap();
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn concurrent_fetches_share_one_call() {
+        let (send, receive) = futures_channel::oneshot::channel::<u32>();
+        let calls = Rc::new(Cell::new(0));
+        let call = || {
+            let calls = calls.clone();
+            calls.set(calls.get() + 1);
+            async move { Ok(receive.await.unwrap()) }
+        };
+        let mut first = Box::pin(fetch("rows", call));
+        assert!((&mut first).now_or_never().is_none());
+        let mut second = Box::pin(fetch("rows", || async { Ok(0u32) }));
+        assert!((&mut second).now_or_never().is_none());
+
+        send.send(42).unwrap();
+        assert_eq!(first.now_or_never().unwrap().unwrap(), 42);
+        assert_eq!(second.now_or_never().unwrap().unwrap(), 42);
+        assert_eq!(calls.get(), 1);
+        clear();
+        assert_eq!(peek::<u32>("rows"), None);
+    }
+
+    #[test]
+    fn persisted_values_are_read_until_they_expire() {
+        let entry = PersistedEntry {
+            expires_ms: 1000.0,
+            value: serde_json::json!(["a", "b"]),
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"expires_ms":1000.0,"value":["a","b"]}"#);
+        PERSISTED.with_borrow_mut(|persisted| {
+            persisted.insert("list".into(), serde_json::from_str(&json).unwrap())
+        });
+
+        assert_eq!(persisted::<Vec<String>>("list", 999.0).unwrap(), ["a", "b"]);
+        assert_eq!(persisted::<Vec<String>>("list", 1000.0), None);
+        assert_eq!(persisted::<u32>("list", 0.0), None);
+        assert_eq!(persisted::<Vec<String>>("other", 0.0), None);
+
+        invalidate("list");
+        assert_eq!(persisted::<Vec<String>>("list", 0.0), None);
+    }
+}
@@ -0,0 +1,497 @@
+//! Per-user quotas on expensive server functions.
+//!
+//! A function declared with `quota = "name:limit/period"` counts every call
+//! against the caller's allowance for that period; once it is used up, calls
+//! fail with a [`QuotaExceeded`] error until the period resets. Periods are
+//! fixed UTC windows (`minute`, `hour`, `day`, `week`), so a daily quota
+//! resets at midnight UTC. Functions naming the same quota share it:
+//!
+//! ```ignore
+//! #[server(quota = "exports:10/day")]
+//! pub async fn export_csv(report: ReportId) -> Result<Vec<u8>, ServerFnError> { ... }
+//!
+//! // Server: where usage is counted and who the caller is
+//! let quotas = Quotas::new(MemoryQuotaStore::default());
+//! let app = app.layer(Extension(quotas));
+//!
+//! // Client: tell the user when they can export again
+//! if let Err(e) = export_csv(id).await {
+//!     if let Some(exceeded) = QuotaExceeded::from_error(&e) {
+//!         self.notice = format!("Export limit reached, try again in {} min",
+//!             exceeded.resets_in(now_ms()).as_secs() / 60);
+//!     }
+//! }
+//! ```
+//!
+//! Calls are counted when they start, whether they succeed or not. Quotas
+//! count requests, not executions: a call answered from the
+//! [`cache`](crate::cache) or sharing the result of a
+//! [`coalesce`](crate::coalesce)d call in flight is counted like one that ran
+//! the function, as the caller asked for the work either way. Callers
+//! are identified by the [`AuthUser`](crate::auth::AuthUser) of the request
+//! unless [`Quotas::identify`] says otherwise; unidentified calls are
+//! rejected with `401 Unauthorized`, and calls of quota functions fail with
+//! `500 Internal Server Error` if no [`Quotas`] is installed, rather than run
+//! unmetered.
+
+use crate::error::ErrorCode;
+use crate::rpc::ServerFnError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Error code of [`QuotaExceeded`].
+pub const QUOTA_EXCEEDED: &str = "quota_exceeded";
+
+/// The length of a quota's window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaPeriod {
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl QuotaPeriod {
+    /// The period in milliseconds.
+    pub fn as_millis(self) -> u64 {
+        const MINUTE: u64 = 60 * 1000;
+        match self {
+            Self::Minute => MINUTE,
+            Self::Hour => 60 * MINUTE,
+            Self::Day => 24 * 60 * MINUTE,
+            Self::Week => 7 * 24 * 60 * MINUTE,
+        }
+    }
+
+    /// The name used in `quota = "..."`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+}
+
+/// A named allowance of calls per period, e.g. `exports:10/day`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quota {
+    /// The quota's name, shared by the functions it covers.
+    pub name: &'static str,
+    /// Calls allowed per period.
+    pub limit: u64,
+    /// The window the calls are counted in.
+    pub period: QuotaPeriod,
+}
+
+impl Quota {
+    /// `limit` calls per `period` under `name`.
+    pub const fn new(name: &'static str, limit: u64, period: QuotaPeriod) -> Self {
+        Self {
+            name,
+            limit,
+            period,
+        }
+    }
+
+    /// Start and end of the window containing `now_ms` (Unix epoch
+    /// milliseconds). Weeks start on Thursdays, like the epoch.
+    pub fn window(&self, now_ms: u64) -> (u64, u64) {
+        let period = self.period.as_millis();
+        let start = now_ms - now_ms % period;
+        (start, start + period)
+    }
+}
+
+/// A call was refused because the caller used up a quota.
+///
+/// Reaches the client as a [`ServerFnError::Custom`] with code
+/// [`QUOTA_EXCEEDED`] and these fields as details; read them back with
+/// [`QuotaExceeded::from_error`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[error("quota {quota} of {limit} calls per {} is used up", period.as_str())]
+pub struct QuotaExceeded {
+    /// The quota's name.
+    pub quota: String,
+    /// Calls allowed per period.
+    pub limit: u64,
+    /// The quota's period.
+    pub period: QuotaPeriod,
+    /// When calls are allowed again, in milliseconds since the Unix epoch.
+    pub resets_at_ms: u64,
+}
+
+impl QuotaExceeded {
+    /// The [`QuotaExceeded`] carried by `error`, if it is one.
+    pub fn from_error(error: &ServerFnError) -> Option<Self> {
+        match error {
+            ServerFnError::Custom {
+                code,
+                details: Some(details),
+                ..
+            } if code == QUOTA_EXCEEDED => serde_json::from_value(details.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    /// Time left until the quota resets, from `now_ms`.
+    pub fn resets_in(&self, now_ms: u64) -> Duration {
+        Duration::from_millis(self.resets_at_ms.saturating_sub(now_ms))
+    }
+}
+
+impl ErrorCode for QuotaExceeded {
+    fn code(&self) -> &'static str {
+        QUOTA_EXCEEDED
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self).ok()
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{Quota, QuotaExceeded};
+    use crate::auth::AuthUser;
+    use crate::error::ErrorEnvelope;
+    use crate::rpc::ServerFnError;
+    use axum::http::request::Parts;
+    use axum::http::{StatusCode, header};
+    use axum::response::{IntoResponse, Response};
+    use std::collections::HashMap;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Future returned by [`QuotaStore`] methods.
+    pub type QuotaFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, QuotaError>> + Send + 'a>>;
+
+    /// Counts calls per user and window, e.g. in Redis or a database.
+    ///
+    /// Keys name the quota, the user and the window start, so every window
+    /// starts from zero.
+    pub trait QuotaStore: Send + Sync + 'static {
+        /// Add one to the count at `key` and return the new count. The count
+        /// is not needed after `expires_at_ms`; Redis users would
+        /// `INCR` and `EXPIREAT`.
+        fn increment(&self, key: &str, expires_at_ms: u64) -> QuotaFuture<'_, u64>;
+
+        /// The count at `key`, 0 if none.
+        fn get(&self, key: &str) -> QuotaFuture<'_, u64>;
+    }
+
+    /// Why a quota could not be checked.
+    #[derive(Debug, thiserror::Error)]
+    pub enum QuotaError {
+        /// A quota function was called without [`Quotas`] installed.
+        #[error("quotas are not configured")]
+        NotConfigured,
+        /// The caller could not be identified (401 Unauthorized).
+        #[error("{0} needs an identified caller")]
+        Unidentified(&'static str),
+        /// The store failed.
+        #[error("quota store error: {0}")]
+        Store(String),
+    }
+
+    impl IntoResponse for QuotaError {
+        fn into_response(self) -> Response {
+            let status = match self {
+                QuotaError::Unidentified(_) => StatusCode::UNAUTHORIZED,
+                QuotaError::NotConfigured | QuotaError::Store(_) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            };
+            let error = ErrorEnvelope::new("quota_failed", self.to_string());
+            crate::error::error_response(status, error, None)
+        }
+    }
+
+    /// As a response, `429 Too Many Requests` with a `Retry-After` header
+    /// and the quota in the error details.
+    impl IntoResponse for QuotaExceeded {
+        fn into_response(self) -> Response {
+            let now_ms = now_ms();
+            let retry_after = self.resets_in(now_ms).as_secs().max(1).to_string();
+            let mut response = crate::error::error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                ServerFnError::from(self),
+                None,
+            );
+            if let Ok(value) = retry_after.parse() {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            response
+        }
+    }
+
+    /// Counts in memory. For single-instance deployments, tests and
+    /// development; counts are lost on restart.
+    #[derive(Debug, Clone, Default)]
+    pub struct MemoryQuotaStore {
+        counts: Arc<Mutex<HashMap<String, (u64, u64)>>>,
+    }
+
+    impl QuotaStore for MemoryQuotaStore {
+        fn increment(&self, key: &str, expires_at_ms: u64) -> QuotaFuture<'_, u64> {
+            let now_ms = now_ms();
+            let mut counts = self.counts.lock().unwrap();
+            counts.retain(|_, (_, expires_at)| *expires_at > now_ms);
+            let (count, _) = counts.entry(key.to_string()).or_insert((0, expires_at_ms));
+            *count += 1;
+            let count = *count;
+            Box::pin(async move { Ok(count) })
+        }
+
+        fn get(&self, key: &str) -> QuotaFuture<'_, u64> {
+            let count = self
+                .counts
+                .lock()
+                .unwrap()
+                .get(key)
+                .map_or(0, |(count, _)| *count);
+            Box::pin(async move { Ok(count) })
+        }
+    }
+
+    type Identify = dyn Fn(&Parts) -> Option<String> + Send + Sync;
+
+    /// The quota configuration: where calls are counted and who the caller
+    /// is. Install it as an [`Extension`](axum::Extension). Cheap to clone.
+    #[derive(Clone)]
+    pub struct Quotas {
+        store: Arc<dyn QuotaStore>,
+        identify: Arc<Identify>,
+    }
+
+    impl std::fmt::Debug for Quotas {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Quotas").finish_non_exhaustive()
+        }
+    }
+
+    impl Quotas {
+        /// Count calls in `store`, per [`AuthUser`] of the request.
+        pub fn new(store: impl QuotaStore) -> Self {
+            Self {
+                store: Arc::new(store),
+                identify: Arc::new(|request: &Parts| {
+                    request
+                        .extensions
+                        .get::<AuthUser>()
+                        .map(|user| user.id.clone())
+                }),
+            }
+        }
+
+        /// Identify the caller of a request, e.g. by API key or tenant.
+        pub fn identify(
+            mut self,
+            identify: impl Fn(&Parts) -> Option<String> + Send + Sync + 'static,
+        ) -> Self {
+            self.identify = Arc::new(identify);
+            self
+        }
+
+        /// Calls `user` made against `quota` in the current window, e.g. to
+        /// show the remaining allowance.
+        pub async fn usage(&self, user: &str, quota: &Quota) -> Result<u64, QuotaError> {
+            let (start, _) = quota.window(now_ms());
+            self.store.get(&key(quota, user, start)).await
+        }
+
+        /// Count a call of `user` against `quota`.
+        pub async fn charge(&self, user: &str, quota: &Quota) -> Result<(), QuotaCheck> {
+            let (start, end) = quota.window(now_ms());
+            let count = self
+                .store
+                .increment(&key(quota, user, start), end)
+                .await
+                .map_err(QuotaCheck::Failed)?;
+            if count > quota.limit {
+                return Err(QuotaCheck::Exceeded(QuotaExceeded {
+                    quota: quota.name.to_string(),
+                    limit: quota.limit,
+                    period: quota.period,
+                    resets_at_ms: end,
+                }));
+            }
+            Ok(())
+        }
+    }
+
+    /// Why [`charge`] refused a call.
+    #[derive(Debug, thiserror::Error)]
+    pub enum QuotaCheck {
+        /// The quota is used up (429 Too Many Requests).
+        #[error(transparent)]
+        Exceeded(QuotaExceeded),
+        /// The quota could not be checked.
+        #[error(transparent)]
+        Failed(QuotaError),
+    }
+
+    impl IntoResponse for QuotaCheck {
+        fn into_response(self) -> Response {
+            match self {
+                QuotaCheck::Exceeded(exceeded) => exceeded.into_response(),
+                QuotaCheck::Failed(error) => error.into_response(),
+            }
+        }
+    }
+
+    /// Count a call to `function` against `quota` for the caller of
+    /// `request`. Called by the handlers generated for `quota` functions.
+    #[doc(hidden)]
+    pub async fn charge(
+        request: &Parts,
+        function: &'static str,
+        quota: &Quota,
+    ) -> Result<(), QuotaCheck> {
+        let quotas = request
+            .extensions
+            .get::<Quotas>()
+            .ok_or(QuotaCheck::Failed(QuotaError::NotConfigured))?;
+        let user = (quotas.identify)(request)
+            .ok_or(QuotaCheck::Failed(QuotaError::Unidentified(function)))?;
+        quotas.charge(&user, quota).await
+    }
+
+    fn key(quota: &Quota, user: &str, window_start: u64) -> String {
+        format!("axum-egui:quota:{}:{user}:{window_start}", quota.name)
+    }
+
+    fn now_ms() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_are_aligned_to_the_period() {
+        let quota = Quota::new("exports", 10, QuotaPeriod::Day);
+        let day = QuotaPeriod::Day.as_millis();
+        assert_eq!(quota.window(3 * day + 5), (3 * day, 4 * day));
+        assert_eq!(quota.window(3 * day), (3 * day, 4 * day));
+    }
+
+    #[test]
+    fn exceeded_round_trips_through_server_fn_error() {
+        let exceeded = QuotaExceeded {
+            quota: "exports".into(),
+            limit: 10,
+            period: QuotaPeriod::Day,
+            resets_at_ms: 86_400_000,
+        };
+        let error = ServerFnError::from(exceeded.clone());
+        assert_eq!(error.code(), QUOTA_EXCEEDED);
+        assert_eq!(
+            error.to_string(),
+            "quota exports of 10 calls per day is used up"
+        );
+        let envelope = crate::error::ErrorEnvelope::from(error);
+        let error = ServerFnError::from(envelope);
+        assert_eq!(QuotaExceeded::from_error(&error), Some(exceeded.clone()));
+        assert_eq!(
+            exceeded.resets_in(86_000_000),
+            Duration::from_millis(400_000)
+        );
+        assert_eq!(QuotaExceeded::from_error(&ServerFnError::Reconnect), None);
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn calls_beyond_the_limit_are_refused() {
+        use axum::http::{Request, StatusCode, header};
+        use axum::response::IntoResponse;
+
+        let quota = Quota::new("exports", 2, QuotaPeriod::Hour);
+        let (mut parts, ()) = Request::new(()).into_parts();
+        let result = charge(&parts, "export", &quota).await;
+        assert!(matches!(
+            result,
+            Err(QuotaCheck::Failed(QuotaError::NotConfigured))
+        ));
+
+        parts
+            .extensions
+            .insert(Quotas::new(MemoryQuotaStore::default()));
+        let result = charge(&parts, "export", &quota).await;
+        assert_eq!(
+            result.unwrap_err().into_response().status(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        parts.extensions.insert(crate::auth::AuthUser::new("ada"));
+        charge(&parts, "export", &quota).await.unwrap();
+        charge(&parts, "export", &quota).await.unwrap();
+        let Err(QuotaCheck::Exceeded(exceeded)) = charge(&parts, "export", &quota).await else {
+            panic!("the third call is over the quota");
+        };
+        assert_eq!(exceeded.limit, 2);
+        let quotas = parts.extensions.get::<Quotas>().unwrap();
+        assert_eq!(quotas.usage("ada", &quota).await.unwrap(), 3);
+        assert_eq!(quotas.usage("grace", &quota).await.unwrap(), 0);
+
+        let response = exceeded.into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn coalesced_calls_are_each_counted() {
+        use axum::http::Request;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // As in the handlers of `quota` + `coalesce` functions: charge, then run
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+        let quota = Quota::new("reports", 10, QuotaPeriod::Hour);
+        let (mut parts, ()) = Request::new(()).into_parts();
+        parts
+            .extensions
+            .insert(Quotas::new(MemoryQuotaStore::default()));
+        parts.extensions.insert(crate::auth::AuthUser::new("ada"));
+        let (gate, wait) = tokio::sync::oneshot::channel::<()>();
+        let wait = futures_util::FutureExt::shared(wait);
+        let call = || async {
+            charge(&parts, "report", &quota).await.unwrap();
+            let wait = wait.clone();
+            crate::coalesce::run(
+                crate::coalesce::key("quota_test_report", &1),
+                move || async move {
+                    let _ = wait.await;
+                    Ok(RUNS.fetch_add(1, Ordering::SeqCst))
+                },
+            )
+            .await
+        };
+        let both = futures_util::future::join(call(), call());
+        let release = async {
+            tokio::task::yield_now().await;
+            gate.send(()).unwrap();
+        };
+        let ((first, second), ()) = futures_util::future::join(both, release).await;
+        assert_eq!((first.unwrap(), second.unwrap()), (0, 0));
+        assert_eq!(RUNS.load(Ordering::SeqCst), 1);
+
+        let quotas = parts.extensions.get::<Quotas>().unwrap();
+        assert_eq!(quotas.usage("ada", &quota).await.unwrap(), 2);
+    }
+}
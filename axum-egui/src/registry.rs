@@ -0,0 +1,222 @@
+//! Registry of every `#[server]` function linked into the binary.
+//!
+//! Each server function registers a [`ServerFnRoute`] when compiled with the
+//! `ssr` feature, so the server can enumerate its endpoints without a
+//! hand-maintained list, e.g. to generate reverse proxy configuration or to
+//! check that every route was mounted:
+//!
+//! ```ignore
+//! for route in axum_egui::registry::routes() {
+//!     println!("{} {} -> {}", route.kind.method(), route.path, route.name);
+//! }
+//! ```
+//!
+//! [`export`] describes the same routes as data, with their methods,
+//! content types, required headers and whether they need a signed-in user,
+//! to generate nginx, envoy or API gateway definitions at build time so the
+//! infrastructure always matches the functions:
+//!
+//! ```ignore
+//! // src/bin/routes.rs, run by the deploy pipeline
+//! fn main() {
+//!     let routes = axum_egui::registry::export();
+//!     println!("{}", serde_json::to_string_pretty(&routes).unwrap());
+//! }
+//! ```
+//!
+//! In code, prefer the typed items generated on each args struct over
+//! path strings:
+//!
+//! ```ignore
+//! #[server(sse)]
+//! pub async fn ticks(every_ms: u64) -> Result<ServerStream<u64>, ServerFnError> { ... }
+//!
+//! assert_eq!(TicksArgs::PATH, "/api/ticks");
+//! let url = TicksArgs { every_ms: 500 }.url()?; // "/api/ticks?args=..."
+//! ```
+
+use serde::Serialize;
+
+/// How a server function is exposed over HTTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteKind {
+    /// `#[server]`: JSON request/response over POST.
+    Rpc,
+    /// `#[server(get)]`: JSON response to a GET with the arguments in the
+    /// query string.
+    Get,
+    /// `#[server(sse)]`: Server-Sent Events over GET.
+    Sse,
+    /// `#[server(ws)]`: WebSocket upgrade over GET.
+    Ws,
+}
+
+impl RouteKind {
+    /// The HTTP method the handler must be routed with.
+    pub const fn method(self) -> &'static str {
+        match self {
+            RouteKind::Rpc => "POST",
+            RouteKind::Get | RouteKind::Sse | RouteKind::Ws => "GET",
+        }
+    }
+}
+
+/// A server function endpoint, registered by the `#[server]` macro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ServerFnRoute {
+    /// The function name.
+    pub name: &'static str,
+    /// Module path of the function, as given by `module_path!()`.
+    pub module: &'static str,
+    /// The API path.
+    pub path: &'static str,
+    /// How the function is exposed.
+    pub kind: RouteKind,
+    /// Whether the path also accepts a POST minting parameter tickets
+    /// (`post_params`, see [`crate::tickets`]).
+    pub post_params: bool,
+    /// Whether calls need a stream ticket of the signed-in user (`auth`).
+    pub auth: bool,
+    /// Content types of the request bodies the handler accepts, preferred
+    /// first; empty if it takes none.
+    pub accepts: &'static [&'static str],
+    /// Content types of its responses, preferred first; empty for
+    /// WebSockets.
+    pub produces: &'static [&'static str],
+    /// Names of the headers calls must carry (`headers(...)`).
+    pub headers: &'static [&'static str],
+}
+
+/// A registered route as described by [`export`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteExport {
+    /// The function name.
+    pub name: &'static str,
+    /// Module path of the function.
+    pub module: &'static str,
+    /// The API path.
+    pub path: &'static str,
+    /// The HTTP methods routed at the path.
+    pub methods: Vec<&'static str>,
+    /// How the function is exposed.
+    pub kind: RouteKind,
+    /// Whether the response is a long-lived stream, which proxies must
+    /// neither buffer nor time out like a regular request.
+    pub streaming: bool,
+    /// Whether requests upgrade to a WebSocket, which proxies must forward.
+    pub websocket: bool,
+    /// Whether calls need a stream ticket of the signed-in user.
+    pub auth: bool,
+    /// Content types of the request bodies accepted.
+    pub accepts: &'static [&'static str],
+    /// Content types of the responses.
+    pub produces: &'static [&'static str],
+    /// Names of the headers calls must carry, which proxies must forward.
+    pub headers: &'static [&'static str],
+}
+
+inventory::collect!(ServerFnRoute);
+
+#[doc(hidden)]
+pub use inventory::submit as __submit;
+
+/// All registered server function routes, sorted by path.
+pub fn routes() -> Vec<&'static ServerFnRoute> {
+    let mut routes: Vec<_> = inventory::iter::<ServerFnRoute>.into_iter().collect();
+    routes.sort_by_key(|route| (route.path, route.name));
+    routes
+}
+
+/// A machine-readable description of all registered routes, sorted by
+/// path, for generating proxy or gateway configuration. Serializes to
+/// JSON, TOML or any other serde format.
+pub fn export() -> Vec<RouteExport> {
+    routes()
+        .into_iter()
+        .map(|route| {
+            let mut methods = vec![route.kind.method()];
+            if route.post_params {
+                methods.push("POST");
+            }
+            RouteExport {
+                name: route.name,
+                module: route.module,
+                path: route.path,
+                methods,
+                kind: route.kind,
+                streaming: matches!(route.kind, RouteKind::Sse | RouteKind::Ws),
+                websocket: route.kind == RouteKind::Ws,
+                auth: route.auth,
+                accepts: route.accepts,
+                produces: route.produces,
+                headers: route.headers,
+            }
+        })
+        .collect()
+}
+
+/// The registered route of the server function `name`, if any.
+///
+/// Names are not unique across modules; this returns the first match by path.
+pub fn find(name: &str) -> Option<&'static ServerFnRoute> {
+    routes().into_iter().find(|route| route.name == name)
+}
+
+/// The registered route of the function named by its module path and name,
+/// e.g. `my_api::search`. Unlike [`find`], it can't mix up functions of the
+/// same name in two modules.
+pub fn find_qualified(path: &str) -> Option<&'static ServerFnRoute> {
+    routes().into_iter().find(|route| {
+        path.strip_suffix(route.name)
+            .and_then(|module| module.strip_suffix("::"))
+            == Some(route.module)
+    })
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    inventory::submit! {
+        ServerFnRoute {
+            name: "registry_test_fn",
+            module: module_path!(),
+            path: "/api/registry_test_fn",
+            kind: RouteKind::Sse,
+            post_params: true,
+            auth: true,
+            accepts: &["application/json"],
+            produces: &["text/event-stream"],
+            headers: &[],
+        }
+    }
+
+    #[test]
+    fn lists_submitted_routes() {
+        let route = find("registry_test_fn").unwrap();
+        assert_eq!(route.path, "/api/registry_test_fn");
+        assert_eq!(route.kind.method(), "GET");
+        assert!(routes().contains(&route));
+
+        let qualified = concat!(module_path!(), "::registry_test_fn");
+        assert_eq!(find_qualified(qualified), Some(route));
+        assert_eq!(find_qualified("other::registry_test_fn"), None);
+        assert_eq!(find_qualified("registry_test_fn"), None);
+    }
+
+    #[test]
+    fn exports_routes_as_data() {
+        let exported = export();
+        let route = exported
+            .iter()
+            .find(|route| route.name == "registry_test_fn")
+            .unwrap();
+        assert_eq!(route.methods, ["GET", "POST"]);
+        assert!(route.streaming && !route.websocket && route.auth);
+
+        let json = serde_json::to_value(route).unwrap();
+        assert_eq!(json["kind"], "sse");
+        assert_eq!(json["produces"], serde_json::json!(["text/event-stream"]));
+    }
+}
@@ -0,0 +1,381 @@
+//! Deep links and browser history for egui views.
+//!
+//! The app describes its views as a [`Route`]: a type that converts to and
+//! from a URL path. The server resolves the requested path to a route, so the
+//! initial state can depend on it, and passes it to the page in the
+//! [`ROUTE_SLOT`] slot; on the client, [`History`] keeps the route in sync
+//! with the address bar, so links can be shared and the back and forward
+//! buttons work:
+//!
+//! ```ignore
+//! #[derive(Clone, PartialEq)]
+//! enum Page { Home, Project(u64) }
+//!
+//! impl Route for Page {
+//!     fn from_path(path: &str) -> Option<Self> {
+//!         match path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..] {
+//!             [""] => Some(Page::Home),
+//!             ["projects", id] => id.parse().ok().map(Page::Project),
+//!             _ => None,
+//!         }
+//!     }
+//!     fn to_path(&self) -> String {
+//!         match self {
+//!             Page::Home => "/".into(),
+//!             Page::Project(id) => format!("/projects/{id}"),
+//!         }
+//!     }
+//! }
+//!
+//! // Server: one handler for every page
+//! async fn index(Routed(page): Routed<Page>) -> App<MyApp, Assets> {
+//!     App::new(MyApp::for_page(&page).await).route(&page)
+//! }
+//! axum_egui::serve(app, addr).assets::<Assets>().index(get(index)).await?;
+//!
+//! // Client
+//! let mut history = History::new(RouteMode::path(), Page::Home)
+//!     .repaint_on_change(cc.egui_ctx.clone());
+//! // In `update`
+//! history.poll();
+//! if ui.link("Project 7").clicked() {
+//!     history.navigate(Page::Project(7));
+//! }
+//! ```
+
+/// Name of the [slot](crate::slots) carrying the path of the page's route.
+pub const ROUTE_SLOT: &str = "route";
+
+/// A view of the app that has a URL.
+pub trait Route: Clone + PartialEq + Sized + 'static {
+    /// The route at `path` (e.g. `/projects/7`), or `None` if there is none.
+    fn from_path(path: &str) -> Option<Self>;
+
+    /// The path of the route, starting with `/`.
+    fn to_path(&self) -> String;
+}
+
+/// Where [`History`] keeps the route in the URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteMode {
+    /// In the path (`/dashboard/projects/7`), under `base`. The server must
+    /// serve the page at every route, as `static_handler` does by falling
+    /// back to `index.html`.
+    Path {
+        /// Path prefix the app is mounted under, e.g. `/dashboard`.
+        base: String,
+    },
+    /// In the fragment (`#/projects/7`); works with any server, but the
+    /// server never sees the route.
+    Hash,
+}
+
+impl RouteMode {
+    /// Routes in the path, with the app at the root.
+    pub fn path() -> Self {
+        Self::path_under("")
+    }
+
+    /// Routes in the path, with the app mounted under `base`.
+    pub fn path_under(base: impl Into<String>) -> Self {
+        Self::Path {
+            base: base.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// The route path in a URL with this `pathname` and `hash`, or `None`
+    /// if the URL is outside the app.
+    pub fn route_path(&self, pathname: &str, hash: &str) -> Option<String> {
+        let path = match self {
+            Self::Path { base } => pathname.strip_prefix(base.as_str())?,
+            Self::Hash => hash.trim_start_matches('#'),
+        };
+        if path.is_empty() {
+            return Some("/".to_string());
+        }
+        path.starts_with('/').then(|| path.to_string())
+    }
+
+    /// The URL to push for the route `path`.
+    pub fn url(&self, path: &str) -> String {
+        match self {
+            Self::Path { base } => format!("{base}{path}"),
+            Self::Hash => format!("#{path}"),
+        }
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::Route;
+    use axum::extract::FromRequestParts;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+
+    /// Extracts the [`Route`] of the request path; `404 Not Found` if it
+    /// matches none. Under a nested router, the path is relative to it.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Routed<R>(pub R);
+
+    impl<R: Route, S: Send + Sync> FromRequestParts<S> for Routed<R> {
+        type Rejection = StatusCode;
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            _state: &S,
+        ) -> Result<Self, Self::Rejection> {
+            R::from_path(parts.uri.path())
+                .map(Routed)
+                .ok_or(StatusCode::NOT_FOUND)
+        }
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{ROUTE_SLOT, Route, RouteMode};
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen::closure::Closure;
+
+    type OnChange = Rc<Cell<Option<Box<dyn Fn()>>>>;
+
+    /// The current [`Route`], kept in sync with the address bar.
+    ///
+    /// [`Self::navigate`] pushes history entries; the back and forward
+    /// buttons are picked up by [`Self::poll`], which should be called every
+    /// frame. Dropping it stops listening.
+    pub struct History<R: Route> {
+        mode: RouteMode,
+        current: R,
+        fallback: R,
+        popped: Rc<Cell<bool>>,
+        on_change: OnChange,
+        listener: Option<Closure<dyn FnMut()>>,
+    }
+
+    impl<R: Route> std::fmt::Debug for History<R> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("History")
+                .field("mode", &self.mode)
+                .field("path", &self.current.to_path())
+                .finish_non_exhaustive()
+        }
+    }
+
+    fn location() -> Option<(String, String)> {
+        let location = web_sys::window()?.location();
+        Some((location.pathname().ok()?, location.hash().ok()?))
+    }
+
+    impl<R: Route> History<R> {
+        /// Start at the route the server resolved ([`ROUTE_SLOT`]), else the
+        /// one in the URL, else `fallback`; the URL is corrected to match.
+        pub fn new(mode: RouteMode, fallback: R) -> Self {
+            let popped = Rc::new(Cell::new(false));
+            let on_change = OnChange::default();
+            let listener = Closure::<dyn FnMut()>::new({
+                let popped = popped.clone();
+                let on_change = on_change.clone();
+                move || {
+                    popped.set(true);
+                    if let Some(callback) = on_change.take() {
+                        callback();
+                        on_change.set(Some(callback));
+                    }
+                }
+            });
+            if let Some(window) = web_sys::window() {
+                let _ = window.add_event_listener_with_callback(
+                    "popstate",
+                    listener.as_ref().unchecked_ref(),
+                );
+            }
+
+            let mut history = Self {
+                current: fallback.clone(),
+                mode,
+                fallback,
+                popped,
+                on_change,
+                listener: Some(listener),
+            };
+            let in_url = history.route_in_url();
+            let initial = crate::slots::read::<String>(ROUTE_SLOT)
+                .and_then(|path| R::from_path(&path))
+                .or(in_url.clone());
+            match initial {
+                Some(route) if Some(&route) == in_url.as_ref() => history.current = route,
+                Some(route) => history.replace(route),
+                None => history.replace(history.fallback.clone()),
+            }
+            history
+        }
+
+        /// Call `callback` when the user goes back or forward, e.g. to
+        /// request a repaint.
+        pub fn on_change(self, callback: impl Fn() + 'static) -> Self {
+            self.on_change.set(Some(Box::new(callback)));
+            self
+        }
+
+        /// Repaint `ctx` when the user goes back or forward.
+        #[cfg(feature = "egui")]
+        pub fn repaint_on_change(self, ctx: egui::Context) -> Self {
+            self.on_change(move || ctx.request_repaint())
+        }
+
+        fn route_in_url(&self) -> Option<R> {
+            let (pathname, hash) = location()?;
+            R::from_path(&self.mode.route_path(&pathname, &hash)?)
+        }
+
+        /// The current route.
+        pub fn current(&self) -> &R {
+            &self.current
+        }
+
+        /// Go to `route`, adding a history entry.
+        pub fn navigate(&mut self, route: R) {
+            if route == self.current {
+                return;
+            }
+            self.update_url(&route, true);
+            self.current = route;
+        }
+
+        /// Go to `route`, replacing the current history entry.
+        pub fn replace(&mut self, route: R) {
+            self.update_url(&route, false);
+            self.current = route;
+        }
+
+        fn update_url(&self, route: &R, push: bool) {
+            let Some(history) = web_sys::window().and_then(|w| w.history().ok()) else {
+                return;
+            };
+            let url = self.mode.url(&route.to_path());
+            let _ = if push {
+                history.push_state_with_url(&JsValue::NULL, "", Some(&url))
+            } else {
+                history.replace_state_with_url(&JsValue::NULL, "", Some(&url))
+            };
+        }
+
+        /// Pick up back and forward navigation. Returns whether the route
+        /// changed; URLs that match no route go to the fallback.
+        pub fn poll(&mut self) -> bool {
+            if !self.popped.replace(false) {
+                return false;
+            }
+            let route = self.route_in_url().unwrap_or_else(|| self.fallback.clone());
+            let changed = route != self.current;
+            self.current = route;
+            changed
+        }
+    }
+
+    impl<R: Route> Drop for History<R> {
+        fn drop(&mut self) {
+            if let (Some(window), Some(listener)) = (web_sys::window(), self.listener.take()) {
+                let _ = window.remove_event_listener_with_callback(
+                    "popstate",
+                    listener.as_ref().unchecked_ref(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Page {
+        Home,
+        Project(u64),
+    }
+
+    impl Route for Page {
+        fn from_path(path: &str) -> Option<Self> {
+            match path.trim_start_matches('/').split('/').collect::<Vec<_>>()[..] {
+                [""] => Some(Page::Home),
+                ["projects", id] => id.parse().ok().map(Page::Project),
+                _ => None,
+            }
+        }
+
+        fn to_path(&self) -> String {
+            match self {
+                Page::Home => "/".into(),
+                Page::Project(id) => format!("/projects/{id}"),
+            }
+        }
+    }
+
+    #[test]
+    fn modes_map_urls_to_route_paths() {
+        let path = RouteMode::path_under("/dashboard/");
+        assert_eq!(path.route_path("/dashboard", ""), Some("/".into()));
+        assert_eq!(
+            path.route_path("/dashboard/projects/7", "#top"),
+            Some("/projects/7".into())
+        );
+        assert_eq!(path.route_path("/dashboardx", ""), None);
+        assert_eq!(path.route_path("/other", ""), None);
+        assert_eq!(path.url("/projects/7"), "/dashboard/projects/7");
+        assert_eq!(RouteMode::path().url("/"), "/");
+
+        let hash = RouteMode::Hash;
+        assert_eq!(hash.route_path("/app", ""), Some("/".into()));
+        assert_eq!(
+            hash.route_path("/app", "#/projects/7"),
+            Some("/projects/7".into())
+        );
+        assert_eq!(hash.url("/projects/7"), "#/projects/7");
+
+        let route = hash.route_path("/", "#/projects/7").unwrap();
+        assert_eq!(Page::from_path(&route), Some(Page::Project(7)));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn routed_extracts_the_route_or_404s() {
+        use axum::Router;
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use axum::routing::get;
+        use tower::ServiceExt;
+
+        let app = Router::new().route(
+            "/{*path}",
+            get(|Routed(page): Routed<Page>| async move { page.to_path() }),
+        );
+        let status = |path: &'static str| {
+            let app = app.clone();
+            async move {
+                let request = Request::get(path).body(Body::empty()).unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+        assert_eq!(status("/projects/7").await, StatusCode::OK);
+        assert_eq!(status("/projects/seven").await, StatusCode::NOT_FOUND);
+    }
+}
@@ -49,44 +49,602 @@ pub enum ServerFnError {
     /// Server returned an error response.
     #[error("Server error: {0}")]
     ServerError(String),
+
+    /// The server sent a stream message of another schema version, usually
+    /// because it was redeployed (see `axum_egui::schema`).
+    #[error("Schema mismatch: expected version {expected:016x}, server sent {found:016x}")]
+    SchemaMismatch {
+        /// The version the client was built with.
+        expected: u64,
+        /// The version the server sent.
+        found: u64,
+    },
+
+    /// The server closed the stream to rebalance or restart and asks the
+    /// client to connect again (see `axum_egui::instance`).
+    #[error("The server asked to reconnect")]
+    Reconnect,
+
+    /// A response or stream message was over the client's size limit and
+    /// was dropped unparsed (see `axum_egui::payload`).
+    #[error("Message of {size} bytes exceeds the limit of {limit} bytes")]
+    TooLarge {
+        /// The size of the message.
+        size: usize,
+        /// The limit it exceeded.
+        limit: usize,
+    },
+
+    /// The call got no response within the function's `timeout` and was
+    /// aborted, or the server gave up on it after its `server_timeout`.
+    #[error("No response within {timeout_ms} ms")]
+    Timeout {
+        /// The deadline that passed, in milliseconds.
+        timeout_ms: u64,
+    },
+
+    /// The consumer fell behind a broadcast channel and `skipped` items
+    /// were dropped (see `ServerStream::from_broadcast`). The stream
+    /// carries on with the oldest item still buffered.
+    #[error("Fell behind the stream and skipped {skipped} messages")]
+    Lagged {
+        /// How many items were skipped.
+        skipped: u64,
+    },
+
+    /// An application error with its own code, usually converted from an
+    /// error type implementing [`ErrorCode`](crate::error::ErrorCode).
+    #[error("{message}")]
+    Custom {
+        /// The error code.
+        code: String,
+        /// Human-readable description.
+        message: String,
+        /// Structured data about the error.
+        details: Option<serde_json::Value>,
+    },
+}
+
+/// Header carrying the [`CallPriority`] of a call, as an RFC 9218 urgency.
+pub const PRIORITY_HEADER: &str = "priority";
+
+/// How urgent a call is to the user.
+///
+/// On the client, background calls yield to interactive ones when calls are
+/// queued (see [`crate::concurrency`]). The priority is also sent in the
+/// [`PRIORITY_HEADER`], and server handlers can extract it to make their own
+/// quality-of-service decisions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CallPriority {
+    /// Triggered by the user, who is waiting for the result (the default).
+    #[default]
+    Interactive,
+    /// Prefetches and other work nobody is waiting for yet.
+    Background,
+}
+
+impl CallPriority {
+    /// The [`PRIORITY_HEADER`] value for this priority.
+    pub fn header_value(self) -> &'static str {
+        match self {
+            Self::Interactive => "u=1",
+            Self::Background => "u=5",
+        }
+    }
+
+    /// Parse a [`PRIORITY_HEADER`] value. Urgencies up to the RFC 9218
+    /// default of 3 are interactive, lower ones (higher numbers) background.
+    pub fn from_header_value(value: &str) -> Option<Self> {
+        let urgency = value
+            .split(',')
+            .find_map(|member| member.trim().strip_prefix("u="))?
+            .parse::<u8>()
+            .ok()?;
+        Some(if urgency <= 3 {
+            Self::Interactive
+        } else {
+            Self::Background
+        })
+    }
+}
+
+/// Query parameter carrying JSON-encoded arguments for streaming endpoints.
+///
+/// `EventSource` and `WebSocket` can only make GET requests, so the arguments
+/// of `#[server(sse)]`/`#[server(ws)]` functions travel as
+/// `?args=<percent-encoded JSON>`.
+pub const ARGS_QUERY_PARAM: &str = "args";
+
+/// Append `args` to `path` as the [`ARGS_QUERY_PARAM`] query parameter.
+pub fn encode_query_args<Args: Serialize>(
+    path: &str,
+    args: &Args,
+) -> Result<String, ServerFnError> {
+    let json =
+        serde_json::to_string(args).map_err(|e| ServerFnError::Serialization(e.to_string()))?;
+    let separator = if path.contains('?') { '&' } else { '?' };
+    Ok(format!(
+        "{path}{separator}{ARGS_QUERY_PARAM}={}",
+        percent_encode(&json)
+    ))
+}
+
+/// Decode arguments encoded by [`encode_query_args`] from a query string.
+///
+/// A missing parameter decodes as `{}`, so functions without arguments work
+/// with a bare URL.
+pub fn decode_query_args<Args: for<'de> Deserialize<'de>>(
+    query: Option<&str>,
+) -> Result<Args, ServerFnError> {
+    let prefix = format!("{ARGS_QUERY_PARAM}=");
+    let json = query
+        .unwrap_or_default()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(&prefix))
+        .map(percent_decode)
+        .transpose()?
+        .unwrap_or_else(|| "{}".to_string());
+    serde_json::from_str(&json).map_err(|e| ServerFnError::Deserialization(e.to_string()))
+}
+
+/// Append `args` to `path` as URL-encoded query parameters, one per field,
+/// as `#[server(get)]` functions are called: `/api/search?q=egui&page=2`.
+///
+/// Only structs of scalar fields (numbers, strings, booleans, options and
+/// unit enums) can be encoded this way; others fail with
+/// [`ServerFnError::Serialization`].
+#[cfg(any(feature = "server", feature = "client", feature = "native-client"))]
+pub fn encode_get_args<Args: Serialize>(path: &str, args: &Args) -> Result<String, ServerFnError> {
+    let query = serde_urlencoded::to_string(args)
+        .map_err(|e| ServerFnError::Serialization(e.to_string()))?;
+    if query.is_empty() {
+        return Ok(path.to_string());
+    }
+    let separator = if path.contains('?') { '&' } else { '?' };
+    Ok(format!("{path}{separator}{query}"))
+}
+
+/// The wire format of a server function's arguments and response.
+///
+/// Functions use JSON unless declared otherwise, e.g. `#[server(postcard)]`
+/// or `#[server(cbor)]`.
+/// Handlers of binary functions also accept JSON and answer in the format
+/// listed in `Accept`, so curl and non-Rust clients can keep calling them
+/// with JSON. Errors are always [`ErrorEnvelope`](crate::error::ErrorEnvelope)
+/// JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// JSON (`application/json`), the default.
+    #[default]
+    Json,
+    /// [postcard](https://docs.rs/postcard) (`application/x-postcard`,
+    /// `postcard` feature): compact, but not self-describing, so types like
+    /// `serde_json::Value` or `#[serde(flatten)]` fields don't round-trip.
+    #[cfg(feature = "postcard")]
+    Postcard,
+    /// [CBOR](https://cbor.io) (`application/cbor`, `cbor` feature): binary
+    /// and self-describing, with libraries in most languages. Blobs marked
+    /// `#[serde(with = "serde_bytes")]` travel as raw byte strings rather
+    /// than JSON's arrays of numbers.
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Encoding {
+    /// The `Content-Type` of bodies in this encoding.
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            #[cfg(feature = "postcard")]
+            Self::Postcard => "application/x-postcard",
+            #[cfg(feature = "cbor")]
+            Self::Cbor => "application/cbor",
+        }
+    }
+
+    /// The encoding of a `Content-Type` value, ignoring parameters.
+    /// `application/*+json` types are JSON.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match essence.as_str() {
+            "application/json" => Some(Self::Json),
+            #[cfg(feature = "postcard")]
+            "application/x-postcard" => Some(Self::Postcard),
+            #[cfg(feature = "cbor")]
+            "application/cbor" => Some(Self::Cbor),
+            essence if essence.starts_with("application/") && essence.ends_with("+json") => {
+                Some(Self::Json)
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode `value`, failing with [`ServerFnError::Serialization`].
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, ServerFnError> {
+        let encoded = match self {
+            Self::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => postcard::to_allocvec(value).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)
+                    .map(|()| bytes)
+                    .map_err(|e| e.to_string())
+            }
+        };
+        encoded.map_err(ServerFnError::Serialization)
+    }
+
+    /// Decode `bytes`, failing with [`ServerFnError::Deserialization`].
+    pub fn decode<T: for<'de> Deserialize<'de>>(self, bytes: &[u8]) -> Result<T, ServerFnError> {
+        let decoded = match self {
+            Self::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "postcard")]
+            Self::Postcard => postcard::from_bytes(bytes).map_err(|e| e.to_string()),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => ciborium::from_reader(bytes).map_err(|e| e.to_string()),
+        };
+        decoded.map_err(ServerFnError::Deserialization)
+    }
+}
+
+pub(crate) fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+fn percent_decode(input: &str) -> Result<String, ServerFnError> {
+    let invalid = || ServerFnError::Deserialization("invalid percent-encoding".to_string());
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = bytes.get(i + 1..i + 3).ok_or_else(invalid)?;
+                let hex = std::str::from_utf8(hex).map_err(|_| invalid())?;
+                out.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(out).map_err(|_| invalid())
+}
+
+/// Static headers sent with a call: name and value.
+#[cfg(any(feature = "client", feature = "native-client"))]
+pub type CallHeaders = &'static [(&'static str, &'static str)];
+
+#[cfg(any(feature = "client", feature = "native-client"))]
+thread_local! {
+    static HEADERS: std::cell::Cell<CallHeaders> = const { std::cell::Cell::new(&[]) };
+}
+
+/// Run `future` with calls made inside it carrying `headers`, as
+/// `#[server(headers(...))]` functions are called. An inner scope replaces
+/// the headers of an outer one.
+#[cfg(any(feature = "client", feature = "native-client"))]
+pub async fn with_headers<F: std::future::Future>(headers: CallHeaders, future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let outer = HEADERS.replace(headers);
+        let poll = future.as_mut().poll(cx);
+        HEADERS.set(outer);
+        poll
+    })
+    .await
+}
+
+/// The headers of calls made now: those of the innermost [`with_headers`].
+#[cfg(any(feature = "client", feature = "native-client"))]
+pub fn current_headers() -> CallHeaders {
+    HEADERS.get()
+}
+
+#[cfg(any(feature = "client", feature = "native-client"))]
+thread_local! {
+    static TIMEOUT: std::cell::Cell<Option<std::time::Duration>> = const { std::cell::Cell::new(None) };
+}
+
+/// Run `future` with calls made inside it given up after `timeout`, as
+/// `#[server(timeout = "...")]` functions are called: the request is
+/// aborted and the call returns [`ServerFnError::Timeout`]. An inner scope
+/// replaces the timeout of an outer one.
+#[cfg(any(feature = "client", feature = "native-client"))]
+pub async fn with_timeout<F: std::future::Future>(
+    timeout: std::time::Duration,
+    future: F,
+) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let outer = TIMEOUT.replace(Some(timeout));
+        let poll = future.as_mut().poll(cx);
+        TIMEOUT.set(outer);
+        poll
+    })
+    .await
+}
+
+/// The timeout of calls made now: that of the innermost [`with_timeout`].
+#[cfg(any(feature = "client", feature = "native-client"))]
+pub fn current_timeout() -> Option<std::time::Duration> {
+    TIMEOUT.get()
+}
+
+/// Aborts the requests given its [`signal`](Self::signal) once the
+/// [`current_timeout`] passes. Cleared when dropped.
+#[cfg(feature = "client")]
+pub(crate) struct Deadline {
+    armed: Option<Armed>,
+}
+
+#[cfg(feature = "client")]
+struct Armed {
+    controller: web_sys::AbortController,
+    timeout_ms: u64,
+    fired: std::rc::Rc<std::cell::Cell<bool>>,
+    handle: i32,
+    _on_timeout: wasm_bindgen::closure::Closure<dyn FnMut()>,
+}
+
+#[cfg(feature = "client")]
+impl Deadline {
+    /// Start the current timeout, if any.
+    pub(crate) fn start() -> Self {
+        use wasm_bindgen::JsCast;
+
+        let armed = current_timeout().and_then(|timeout| {
+            let window = web_sys::window()?;
+            let controller = web_sys::AbortController::new().ok()?;
+            let fired = std::rc::Rc::new(std::cell::Cell::new(false));
+            let on_timeout = wasm_bindgen::closure::Closure::<dyn FnMut()>::new({
+                let controller = controller.clone();
+                let fired = fired.clone();
+                move || {
+                    fired.set(true);
+                    controller.abort();
+                }
+            });
+            let timeout_ms = timeout.as_millis().min(i32::MAX as u128) as i32;
+            let handle = window
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    on_timeout.as_ref().unchecked_ref(),
+                    timeout_ms,
+                )
+                .ok()?;
+            Some(Armed {
+                controller,
+                timeout_ms: timeout_ms as u64,
+                fired,
+                handle,
+                _on_timeout: on_timeout,
+            })
+        });
+        Deadline { armed }
+    }
+
+    /// The signal to abort the request with.
+    pub(crate) fn signal(&self) -> Option<web_sys::AbortSignal> {
+        self.armed.as_ref().map(|armed| armed.controller.signal())
+    }
+
+    /// `result`, or [`ServerFnError::Timeout`] if it failed because the
+    /// deadline passed.
+    pub(crate) fn finish<T>(self, result: Result<T, ServerFnError>) -> Result<T, ServerFnError> {
+        match &self.armed {
+            Some(armed) if result.is_err() && armed.fired.get() => Err(ServerFnError::Timeout {
+                timeout_ms: armed.timeout_ms,
+            }),
+            _ => result,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl Drop for Deadline {
+    fn drop(&mut self) {
+        if let (Some(armed), Some(window)) = (&self.armed, web_sys::window()) {
+            window.clear_timeout_with_handle(armed.handle);
+        }
+    }
 }
 
 /// Client-side function to call a server API endpoint.
 ///
 /// This makes a POST request to the given path with JSON-serialized arguments,
-/// and deserializes the JSON response.
+/// and deserializes the JSON response. The request waits for a permit if too
+/// many calls are already in flight (see [`crate::concurrency`]).
+///
+/// The call has the priority set with
+/// [`with_priority`](crate::concurrency::with_priority), interactive by
+/// default.
 #[cfg(feature = "client")]
 pub async fn call<Args, Resp>(path: &str, args: &Args) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    call_with_priority(path, args, crate::concurrency::current_priority()).await
+}
+
+/// [`call`] with an explicit priority.
+#[cfg(feature = "client")]
+pub async fn call_with_priority<Args, Resp>(
+    path: &str,
+    args: &Args,
+    priority: CallPriority,
+) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    post(path, args, priority, Encoding::Json).await
+}
+
+/// [`call`] with the arguments and response in `encoding`, as
+/// `#[server(postcard)]` and `#[server(cbor)]` functions are called.
+#[cfg(feature = "client")]
+pub async fn call_encoded<Args, Resp>(
+    path: &str,
+    args: &Args,
+    encoding: Encoding,
+) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    post(path, args, crate::concurrency::current_priority(), encoding).await
+}
+
+#[cfg(feature = "client")]
+async fn post<Args, Resp>(
+    path: &str,
+    args: &Args,
+    priority: CallPriority,
+    encoding: Encoding,
+) -> Result<Resp, ServerFnError>
 where
     Args: Serialize,
     Resp: DeserializeOwned,
 {
     use gloo_net::http::Request;
 
-    let response = Request::post(path)
-        .header("Content-Type", "application/json")
-        .json(args)
-        .map_err(|e| ServerFnError::Serialization(e.to_string()))?
-        .send()
-        .await
-        .map_err(|e| ServerFnError::Request(e.to_string()))?;
+    let body = encoding.encode(args)?;
+    let _permit = crate::concurrency::acquire(path, priority).await;
+    #[cfg(feature = "devtools")]
+    crate::devtools::inject().await?;
+    let mut request = Request::post(path)
+        .header("Content-Type", encoding.content_type())
+        .header("Accept", encoding.content_type())
+        .header(PRIORITY_HEADER, priority.header_value())
+        .header(
+            crate::trace::TRACEPARENT_HEADER,
+            &crate::trace::outgoing().to_string(),
+        );
+    for (name, value) in current_headers() {
+        request = request.header(name, value);
+    }
+    let deadline = Deadline::start();
+    let request = request
+        .abort_signal(deadline.signal().as_ref())
+        .body(js_sys::Uint8Array::from(body.as_slice()))
+        .map_err(|e| ServerFnError::Serialization(e.to_string()))?;
+    let result = async {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+        read_response(response, encoding).await
+    }
+    .await;
+    deadline.finish(result)
+}
+
+/// Call a `#[server(get)]` endpoint: a GET request with `args` in the query
+/// string (see [`encode_get_args`]), which the browser may answer from its
+/// HTTP cache.
+///
+/// Like [`call`], it waits for a permit and has the current priority.
+#[cfg(feature = "client")]
+pub async fn call_get<Args, Resp>(path: &str, args: &Args) -> Result<Resp, ServerFnError>
+where
+    Args: Serialize,
+    Resp: DeserializeOwned,
+{
+    use gloo_net::http::Request;
+
+    let priority = crate::concurrency::current_priority();
+    let url = encode_get_args(path, args)?;
+    let _permit = crate::concurrency::acquire(path, priority).await;
+    #[cfg(feature = "devtools")]
+    crate::devtools::inject().await?;
+    let mut request = Request::get(&url)
+        .header(PRIORITY_HEADER, priority.header_value())
+        .header(
+            crate::trace::TRACEPARENT_HEADER,
+            &crate::trace::outgoing().to_string(),
+        );
+    for (name, value) in current_headers() {
+        request = request.header(name, value);
+    }
+    let deadline = Deadline::start();
+    let request = request.abort_signal(deadline.signal().as_ref());
+    let result = async {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+        read_response(response, Encoding::Json).await
+    }
+    .await;
+    deadline.finish(result)
+}
 
+/// The result of a call from its response: the body, in the encoding its
+/// `Content-Type` names or else `expected`, or the error it carries.
+#[cfg(feature = "client")]
+pub(crate) async fn read_response<Resp: DeserializeOwned>(
+    response: gloo_net::http::Response,
+    expected: Encoding,
+) -> Result<Resp, ServerFnError> {
     if !response.ok() {
         let status = response.status();
         let text = response
             .text()
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(ServerFnError::ServerError(format!(
-            "HTTP {}: {}",
-            status, text
-        )));
+        // Errors of server functions come as an `ErrorEnvelope`; others
+        // (a proxy's error page) as whatever they are
+        return Err(
+            match serde_json::from_str::<crate::error::ErrorEnvelope>(&text) {
+                Ok(envelope) => envelope.into(),
+                Err(_) => ServerFnError::ServerError(format!("HTTP {}: {}", status, text)),
+            },
+        );
     }
 
-    response
-        .json()
+    // Refuse oversized bodies before reading them when the length is known
+    let limit = crate::payload::limits().rpc_limit();
+    if let Some(length) = response
+        .headers()
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+    {
+        crate::payload::check(length, limit)?;
+    }
+    let body = response
+        .binary()
         .await
-        .map_err(|e| ServerFnError::Deserialization(e.to_string()))
+        .map_err(|e| ServerFnError::Request(e.to_string()))?;
+    crate::payload::check(body.len(), limit)?;
+    let encoding = response
+        .headers()
+        .get("content-type")
+        .and_then(|content_type| Encoding::from_content_type(&content_type))
+        .unwrap_or(expected);
+    encoding.decode(&body)
 }
 
 /// Server-side helper to extract JSON and call a handler.
@@ -94,9 +652,15 @@ where
 /// This is a convenience wrapper for axum handlers that take JSON input.
 #[cfg(feature = "server")]
 pub mod server {
-    use super::ServerFnError;
+    use super::{CallPriority, Encoding, PRIORITY_HEADER, ServerFnError};
+    use crate::error::ErrorEnvelope;
+    use axum::extract::FromRequestParts;
+    use axum::http::header::{ACCEPT, CONTENT_TYPE};
+    use axum::http::{HeaderMap, request::Parts};
     use axum::{Json, http::StatusCode, response::IntoResponse};
+    use serde::de::DeserializeOwned;
     use serde::{Deserialize, Serialize};
+    use std::convert::Infallible;
 
     /// Response wrapper that serializes errors as JSON.
     pub struct ApiResponse<T>(pub Result<T, ServerFnError>);
@@ -105,12 +669,7 @@ pub mod server {
         fn into_response(self) -> axum::response::Response {
             match self.0 {
                 Ok(value) => Json(value).into_response(),
-                Err(e) => {
-                    let body = serde_json::json!({
-                        "error": e.to_string()
-                    });
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
-                }
+                Err(e) => crate::error::error_response(StatusCode::INTERNAL_SERVER_ERROR, e, None),
             }
         }
     }
@@ -163,7 +722,613 @@ pub mod server {
             Box::pin(async move { ApiResponse(f(args).await) })
         }
     }
+
+    /// The content type of server function arguments and responses.
+    pub const JSON_CONTENT_TYPE: &str = "application/json";
+
+    /// Why the body of a server function call was rejected, naming the
+    /// function so a client developer can tell which call is wrong.
+    ///
+    /// Responds with an [`ErrorEnvelope`] of code `unsupported_media_type` or
+    /// `invalid_arguments`, with the function and the expected content type
+    /// in its details.
+    #[derive(Debug, thiserror::Error)]
+    pub enum ArgsRejection {
+        /// The request is not in the function's encoding or JSON (415
+        /// Unsupported Media Type).
+        #[error("{function} expects Content-Type {expected}, got {}", found.as_deref().unwrap_or("none"))]
+        UnsupportedMediaType {
+            /// The server function called.
+            function: &'static str,
+            /// The content type it accepts.
+            expected: &'static str,
+            /// The content type sent, if any.
+            found: Option<String>,
+        },
+        /// The body does not decode as the function's arguments (400 Bad
+        /// Request).
+        #[error("invalid arguments for {function}: {message}")]
+        InvalidBody {
+            /// The server function called.
+            function: &'static str,
+            /// The content type the body was decoded as.
+            expected: &'static str,
+            /// The decoding error.
+            message: String,
+        },
+        /// The query string of a `#[server(get)]` call does not decode as
+        /// the function's arguments (400 Bad Request).
+        #[error("invalid query arguments for {function}: {message}")]
+        InvalidQuery {
+            /// The server function called.
+            function: &'static str,
+            /// The decoding error.
+            message: String,
+        },
+    }
+
+    impl ArgsRejection {
+        /// `415 Unsupported Media Type` or `400 Bad Request`.
+        pub fn status(&self) -> StatusCode {
+            match self {
+                ArgsRejection::UnsupportedMediaType { .. } => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                ArgsRejection::InvalidBody { .. } | ArgsRejection::InvalidQuery { .. } => {
+                    StatusCode::BAD_REQUEST
+                }
+            }
+        }
+    }
+
+    impl From<ArgsRejection> for ErrorEnvelope {
+        fn from(rejection: ArgsRejection) -> Self {
+            let message = rejection.to_string();
+            match rejection {
+                ArgsRejection::UnsupportedMediaType {
+                    function,
+                    expected,
+                    found,
+                } => ErrorEnvelope::new("unsupported_media_type", message).details(
+                    serde_json::json!({ "function": function, "expected": expected, "found": found }),
+                ),
+                ArgsRejection::InvalidBody {
+                    function, expected, ..
+                } => ErrorEnvelope::new("invalid_arguments", message)
+                    .details(serde_json::json!({ "function": function, "expected": expected })),
+                ArgsRejection::InvalidQuery { function, .. } => {
+                    ErrorEnvelope::new("invalid_arguments", message)
+                        .details(serde_json::json!({ "function": function }))
+                }
+            }
+        }
+    }
+
+    impl IntoResponse for ArgsRejection {
+        fn into_response(self) -> axum::response::Response {
+            crate::error::error_response(self.status(), self, None)
+        }
+    }
+
+    /// A call to a `#[server(headers(...))]` function lacks one of its
+    /// headers, or has another value (400 Bad Request).
+    ///
+    /// Responds with an [`ErrorEnvelope`] of code `invalid_header` naming the
+    /// function and the header, but not the expected value, which may be a
+    /// shared secret.
+    #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+    #[error("{function} requires the header {header} with its expected value")]
+    pub struct HeaderRejection {
+        /// The server function called.
+        pub function: &'static str,
+        /// The missing or mismatched header.
+        pub header: &'static str,
+    }
+
+    impl HeaderRejection {
+        /// `400 Bad Request`.
+        pub fn status(&self) -> StatusCode {
+            StatusCode::BAD_REQUEST
+        }
+    }
+
+    impl From<HeaderRejection> for ErrorEnvelope {
+        fn from(rejection: HeaderRejection) -> Self {
+            ErrorEnvelope::new("invalid_header", rejection.to_string()).details(
+                serde_json::json!({ "function": rejection.function, "header": rejection.header }),
+            )
+        }
+    }
+
+    impl IntoResponse for HeaderRejection {
+        fn into_response(self) -> axum::response::Response {
+            crate::error::error_response(self.status(), self, None)
+        }
+    }
+
+    /// Check that a call to `function` carries each of `required` with its
+    /// value, compared in constant time.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn check_headers(
+        function: &'static str,
+        headers: &HeaderMap,
+        required: &[(&'static str, &'static str)],
+    ) -> Result<(), HeaderRejection> {
+        for &(header, expected) in required {
+            let found = headers
+                .get(header)
+                .map_or(&[][..], |value| value.as_bytes());
+            let same = found.len() == expected.len()
+                && found
+                    .iter()
+                    .zip(expected.as_bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0;
+            if !same {
+                return Err(HeaderRejection { function, header });
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the call `future`, giving up with [`ServerFnError::Timeout`]
+    /// once `timeout` passes. The call is dropped, so it stops at its next
+    /// `.await`.
+    ///
+    /// Used by the handlers `#[server(server_timeout = "...")]` generates.
+    pub async fn deadline<T>(
+        timeout: std::time::Duration,
+        future: impl std::future::Future<Output = Result<T, ServerFnError>>,
+    ) -> Result<T, ServerFnError> {
+        tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or(Err(ServerFnError::Timeout {
+                timeout_ms: timeout.as_millis() as u64,
+            }))
+    }
+
+    /// The status of a failed call: `504 Gateway Timeout` for
+    /// [`ServerFnError::Timeout`], `500 Internal Server Error` otherwise.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn error_status(error: &ServerFnError) -> StatusCode {
+        match error {
+            ServerFnError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Decode the JSON arguments of a call to `function`, checking the
+    /// `Content-Type` first. `application/json` and `+json` types are
+    /// accepted, with or without parameters.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn decode_json_args<T: DeserializeOwned>(
+        function: &'static str,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<T, ArgsRejection> {
+        let found = headers
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        if found.as_deref().and_then(Encoding::from_content_type) != Some(Encoding::Json) {
+            return Err(ArgsRejection::UnsupportedMediaType {
+                function,
+                expected: JSON_CONTENT_TYPE,
+                found,
+            });
+        }
+        serde_json::from_slice(body).map_err(|e| ArgsRejection::InvalidBody {
+            function,
+            expected: JSON_CONTENT_TYPE,
+            message: e.to_string(),
+        })
+    }
+
+    /// Decode the arguments of a call to `function`, a function using
+    /// `encoding`: bodies in `encoding` and JSON bodies are accepted, per
+    /// their `Content-Type`.
+    ///
+    /// Used by the handlers `#[server(postcard)]` and `#[server(cbor)]`
+    /// generate.
+    pub fn decode_args<T: DeserializeOwned>(
+        function: &'static str,
+        encoding: Encoding,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Result<T, ArgsRejection> {
+        let found = headers
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let sent = found
+            .as_deref()
+            .and_then(Encoding::from_content_type)
+            .filter(|sent| *sent == encoding || *sent == Encoding::Json);
+        let Some(sent) = sent else {
+            return Err(ArgsRejection::UnsupportedMediaType {
+                function,
+                expected: encoding.content_type(),
+                found,
+            });
+        };
+        sent.decode(body).map_err(|e| ArgsRejection::InvalidBody {
+            function,
+            expected: sent.content_type(),
+            message: match e {
+                ServerFnError::Deserialization(message) => message,
+                other => other.to_string(),
+            },
+        })
+    }
+
+    /// The encoding to answer a call to a function using `encoding` in:
+    /// `encoding` if the client lists it in `Accept`, JSON otherwise.
+    pub fn response_encoding(headers: &HeaderMap, encoding: Encoding) -> Encoding {
+        let accepted = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media_range| Encoding::from_content_type(media_range) == Some(encoding));
+        if accepted { encoding } else { Encoding::Json }
+    }
+
+    /// A `200 OK` response carrying `value` in `encoding`, or a `500` error
+    /// response if it doesn't encode.
+    pub fn encoded_response<T: Serialize>(
+        encoding: Encoding,
+        value: &T,
+        request_id: Option<String>,
+    ) -> axum::response::Response {
+        match encoding.encode(value) {
+            Ok(body) => ([(CONTENT_TYPE, encoding.content_type())], body).into_response(),
+            Err(e) => {
+                crate::error::error_response(StatusCode::INTERNAL_SERVER_ERROR, e, request_id)
+            }
+        }
+    }
+
+    /// Decode the arguments of a `#[server(get)]` call to `function` from
+    /// its query string (see [`encode_get_args`](super::encode_get_args)).
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn decode_get_args<T: DeserializeOwned>(
+        function: &'static str,
+        query: Option<&str>,
+    ) -> Result<T, ArgsRejection> {
+        serde_urlencoded::from_str(query.unwrap_or_default()).map_err(|e| {
+            ArgsRejection::InvalidQuery {
+                function,
+                message: e.to_string(),
+            }
+        })
+    }
+
+    /// Extracts the priority the client sent, [`CallPriority::Interactive`]
+    /// if it sent none.
+    impl<S: Send + Sync> FromRequestParts<S> for CallPriority {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            _state: &S,
+        ) -> Result<Self, Self::Rejection> {
+            Ok(parts
+                .headers
+                .get(PRIORITY_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(CallPriority::from_header_value)
+                .unwrap_or_default())
+        }
+    }
 }
 
 #[cfg(feature = "server")]
-pub use server::{ApiResponse, IntoApiResponse, json_handler};
+pub use server::{ApiResponse, ArgsRejection, HeaderRejection, IntoApiResponse, json_handler};
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Args {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct NoArgs {}
+
+    #[test]
+    fn query_args_round_trip() {
+        let args = Args {
+            name: "a&b=c d/é".into(),
+            tags: vec!["x".into(), "%y".into()],
+        };
+        let url = encode_query_args("/api/feed", &args).unwrap();
+        let (path, query) = url.split_once('?').unwrap();
+        assert_eq!(path, "/api/feed");
+        assert_eq!(decode_query_args::<Args>(Some(query)).unwrap(), args);
+    }
+
+    #[test]
+    fn get_args_round_trip_through_the_query_string() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Search {
+            q: String,
+            page: u32,
+            exact: Option<bool>,
+        }
+
+        let args = Search {
+            q: "a&b=c d/é".into(),
+            page: 2,
+            exact: None,
+        };
+        let url = encode_get_args("/api/search", &args).unwrap();
+        let (path, query) = url.split_once('?').unwrap();
+        assert_eq!(path, "/api/search");
+        assert_eq!(
+            server::decode_get_args::<Search>("search", Some(query)).unwrap(),
+            args
+        );
+        assert_eq!(encode_get_args("/api/now", &NoArgs {}).unwrap(), "/api/now");
+
+        let rejection =
+            server::decode_get_args::<Search>("search", Some("q=x&page=two")).unwrap_err();
+        assert_eq!(rejection.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert!(
+            encode_get_args(
+                "/api/feed",
+                &Args {
+                    name: "a".into(),
+                    tags: vec![]
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn missing_query_args_decode_as_empty_object() {
+        assert_eq!(decode_query_args::<NoArgs>(None).unwrap(), NoArgs {});
+        assert_eq!(
+            decode_query_args::<NoArgs>(Some("other=1")).unwrap(),
+            NoArgs {}
+        );
+    }
+
+    #[tokio::test]
+    async fn priority_header_round_trip() {
+        use axum::extract::FromRequestParts;
+
+        for priority in [CallPriority::Interactive, CallPriority::Background] {
+            assert_eq!(
+                CallPriority::from_header_value(priority.header_value()),
+                Some(priority)
+            );
+        }
+        // Other clients' values, with the incremental flag
+        assert_eq!(
+            CallPriority::from_header_value("u=4, i"),
+            Some(CallPriority::Background)
+        );
+        assert_eq!(CallPriority::from_header_value("i"), None);
+
+        let (mut parts, ()) = axum::http::Request::post("/api/row")
+            .header(PRIORITY_HEADER, "u=5")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let priority = CallPriority::from_request_parts(&mut parts, &()).await;
+        assert_eq!(priority, Ok(CallPriority::Background));
+    }
+
+    #[test]
+    fn invalid_query_args_are_deserialization_errors() {
+        let err = decode_query_args::<Args>(Some("args=%7B%zz")).unwrap_err();
+        assert!(matches!(err, ServerFnError::Deserialization(_)));
+    }
+
+    #[tokio::test]
+    async fn json_args_check_content_type() {
+        use axum::http::{HeaderMap, HeaderValue, StatusCode, header::CONTENT_TYPE};
+        use axum::response::IntoResponse;
+        use server::decode_json_args;
+
+        let body = br#"{"name":"a","tags":[]}"#;
+        let mut headers = HeaderMap::new();
+        for content_type in [
+            "application/json",
+            "application/json; charset=utf-8",
+            "application/merge-patch+json",
+        ] {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+            let args: Args = decode_json_args("add", &headers, body).unwrap();
+            assert_eq!(args.name, "a");
+        }
+
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/msgpack"),
+        );
+        let rejection = decode_json_args::<Args>("add", &headers, body).unwrap_err();
+        let response = rejection.into_response();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "unsupported_media_type");
+        assert_eq!(body["details"]["function"], "add");
+        assert_eq!(body["details"]["expected"], "application/json");
+        assert_eq!(
+            body["message"],
+            "add expects Content-Type application/json, got application/msgpack"
+        );
+
+        headers.remove(CONTENT_TYPE);
+        let rejection = decode_json_args::<Args>("add", &headers, b"{}").unwrap_err();
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let rejection = decode_json_args::<Args>("add", &headers, b"{}").unwrap_err();
+        assert!(
+            rejection
+                .to_string()
+                .starts_with("invalid arguments for add: ")
+        );
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "postcard")]
+    #[tokio::test]
+    async fn postcard_calls_negotiate_the_encoding() {
+        use axum::http::{HeaderMap, HeaderValue, header::ACCEPT, header::CONTENT_TYPE};
+        use server::{decode_args, encoded_response, response_encoding};
+
+        let args = Args {
+            name: "a".into(),
+            tags: vec!["x".into()],
+        };
+        let postcard = Encoding::Postcard.encode(&args).unwrap();
+        assert!(postcard.len() < Encoding::Json.encode(&args).unwrap().len());
+        assert_eq!(Encoding::Postcard.decode::<Args>(&postcard).unwrap(), args);
+        assert_eq!(
+            Encoding::from_content_type("application/x-postcard"),
+            Some(Encoding::Postcard)
+        );
+
+        // Both the function's encoding and JSON are accepted
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-postcard"),
+        );
+        let decoded: Args = decode_args("rows", Encoding::Postcard, &headers, &postcard).unwrap();
+        assert_eq!(decoded, args);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let json = br#"{"name":"a","tags":["x"]}"#;
+        let decoded: Args = decode_args("rows", Encoding::Postcard, &headers, json).unwrap();
+        assert_eq!(decoded, args);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        let rejection = decode_args::<Args>("rows", Encoding::Postcard, &headers, json);
+        assert!(matches!(
+            rejection,
+            Err(ArgsRejection::UnsupportedMediaType {
+                expected: "application/x-postcard",
+                ..
+            })
+        ));
+
+        // Answers are postcard only if the client accepts it
+        assert_eq!(
+            response_encoding(&headers, Encoding::Postcard),
+            Encoding::Json
+        );
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/x-postcard, application/json;q=0.5"),
+        );
+        let encoding = response_encoding(&headers, Encoding::Postcard);
+        assert_eq!(encoding, Encoding::Postcard);
+        let response = encoded_response(encoding, &args, None);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/x-postcard");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &postcard[..]);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn cbor_round_trips_and_is_accepted_besides_json() {
+        use axum::http::{HeaderMap, HeaderValue, header::CONTENT_TYPE};
+
+        let args = Args {
+            name: "blob".into(),
+            tags: vec!["raw".into()],
+        };
+        let cbor = Encoding::Cbor.encode(&args).unwrap();
+        assert_eq!(Encoding::Cbor.decode::<Args>(&cbor).unwrap(), args);
+        assert_eq!(
+            Encoding::from_content_type("application/cbor; charset=binary"),
+            Some(Encoding::Cbor)
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/cbor"));
+        let decoded: Args = server::decode_args("upload", Encoding::Cbor, &headers, &cbor).unwrap();
+        assert_eq!(decoded, args);
+        let rejection =
+            server::decode_args::<Args>("upload", Encoding::Cbor, &headers, b"\xff").unwrap_err();
+        assert!(matches!(
+            rejection,
+            ArgsRejection::InvalidBody {
+                expected: "application/cbor",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn calls_must_carry_the_required_headers() {
+        use axum::http::{HeaderMap, HeaderValue};
+
+        let required = [("x-app-version", "1.2.0"), ("x-secret", "s3cret")];
+        let mut headers = HeaderMap::new();
+        headers.insert("x-app-version", HeaderValue::from_static("1.2.0"));
+        let rejection = server::check_headers("save", &headers, &required).unwrap_err();
+        assert_eq!(rejection.header, "x-secret");
+        assert_eq!(rejection.status(), axum::http::StatusCode::BAD_REQUEST);
+
+        headers.insert("x-secret", HeaderValue::from_static("s3cre"));
+        assert!(server::check_headers("save", &headers, &required).is_err());
+        headers.insert("x-secret", HeaderValue::from_static("s3cret"));
+        assert!(server::check_headers("save", &headers, &required).is_ok());
+    }
+
+    #[tokio::test]
+    async fn deadline_gives_up_on_slow_calls() {
+        let timeout = std::time::Duration::from_millis(20);
+        let fast = server::deadline(timeout, async { Ok(1) }).await;
+        assert_eq!(fast.unwrap(), 1);
+
+        let slow = server::deadline(timeout, async {
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            Ok(1)
+        })
+        .await
+        .unwrap_err();
+        assert!(matches!(slow, ServerFnError::Timeout { timeout_ms: 20 }));
+        assert_eq!(
+            server::error_status(&slow),
+            axum::http::StatusCode::GATEWAY_TIMEOUT
+        );
+    }
+
+    #[cfg(feature = "native-client")]
+    #[test]
+    fn with_timeout_scopes_the_call_timeout() {
+        let timeout = std::time::Duration::from_secs(5);
+        let inner = with_timeout(timeout, async { current_timeout() });
+        assert_eq!(
+            futures_util::FutureExt::now_or_never(inner).unwrap(),
+            Some(timeout)
+        );
+        assert_eq!(current_timeout(), None);
+    }
+
+    #[cfg(feature = "native-client")]
+    #[test]
+    fn with_headers_scopes_the_call_headers() {
+        let inner = with_headers(&[("x-app-version", "1.2.0")], async { current_headers() });
+        assert_eq!(
+            futures_util::FutureExt::now_or_never(inner).unwrap(),
+            [("x-app-version", "1.2.0")]
+        );
+        assert!(current_headers().is_empty());
+    }
+}
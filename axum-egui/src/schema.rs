@@ -0,0 +1,266 @@
+//! Schema versions on stream messages, to notice a redeployed server.
+//!
+//! A browser tab keeps running the frontend it loaded, while streams
+//! reconnect to whatever server is deployed now. If the message type changed
+//! in between, every message fails to parse. Streaming functions declared
+//! with `versioned` wrap each message in an [`Envelope`] carrying the
+//! [`StreamSchema::SCHEMA_VERSION`] of its type; a client built against a
+//! different version gets a single [`ServerFnError::SchemaMismatch`] and the
+//! stream ends, and [`mismatch`] reports it so the app can ask for a reload:
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize, StreamSchema)]
+//! pub struct Tick { pub at: u64 }
+//!
+//! #[server(sse, versioned)]
+//! pub async fn ticks() -> Result<ServerStream<Tick>, ServerFnError> {
+//!     Ok(ServerStream::new(tick_stream()))
+//! }
+//!
+//! // Client, in `update`
+//! if axum_egui::schema::mismatch().is_some() {
+//!     egui::Modal::new("reload".into()).show(ctx, |ui| {
+//!         ui.label("A new version is available.");
+//!         if ui.button("Reload").clicked() {
+//!             axum_egui::schema::reload();
+//!         }
+//!     });
+//! }
+//! ```
+//!
+//! `#[derive(StreamSchema)]` fingerprints the type's own definition (names,
+//! field types, serde attributes; not doc comments). A change inside a nested
+//! type that leaves the outer definition intact is not detected. For `ws`
+//! functions, only the server's messages are versioned.
+//!
+//! [`ServerFnError::SchemaMismatch`]: crate::rpc::ServerFnError::SchemaMismatch
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// A message type with a version that changes when its definition does.
+///
+/// Derive it with `#[derive(StreamSchema)]`; it is implemented for the
+/// standard scalar types and containers.
+pub trait StreamSchema {
+    /// Fingerprint of the type's definition.
+    const SCHEMA_VERSION: u64;
+}
+
+/// A stream message with the schema version of its type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// [`StreamSchema::SCHEMA_VERSION`] of the sender's message type.
+    pub v: u64,
+    /// The message.
+    pub data: T,
+}
+
+/// 64-bit FNV-1a hash of `text`, as used by `#[derive(StreamSchema)]`.
+pub const fn fingerprint(text: &str) -> u64 {
+    let bytes = text.as_bytes();
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Fingerprint of a generic type from those of its parts.
+pub const fn combine(outer: u64, inner: u64) -> u64 {
+    (outer ^ inner.rotate_left(17)).wrapping_mul(0x0100_0000_01b3)
+}
+
+macro_rules! scalar_schemas {
+    ($($ty:ty),*) => {
+        $(impl StreamSchema for $ty {
+            const SCHEMA_VERSION: u64 = fingerprint(stringify!($ty));
+        })*
+    };
+}
+
+scalar_schemas!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    String,
+    serde_json::Value
+);
+
+macro_rules! container_schemas {
+    ($($name:literal => $ty:ident<$($param:ident),*>),*) => {
+        $(impl<$($param: StreamSchema),*> StreamSchema for $ty<$($param),*> {
+            const SCHEMA_VERSION: u64 = {
+                let mut version = fingerprint($name);
+                $(version = combine(version, $param::SCHEMA_VERSION);)*
+                version
+            };
+        })*
+    };
+}
+
+container_schemas!(
+    "Option" => Option<T>,
+    "Vec" => Vec<T>,
+    "Box" => Box<T>,
+    "HashMap" => HashMap<K, V>,
+    "BTreeMap" => BTreeMap<K, V>
+);
+
+impl<T: StreamSchema, E: StreamSchema> StreamSchema for Result<T, E> {
+    const SCHEMA_VERSION: u64 = combine(
+        combine(fingerprint("Result"), T::SCHEMA_VERSION),
+        E::SCHEMA_VERSION,
+    );
+}
+
+/// Client and server versions of a stream's message type differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaMismatch {
+    /// The version the client was built with.
+    pub expected: u64,
+    /// The version the server sent.
+    pub found: u64,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{Envelope, StreamSchema};
+    use crate::stream::ServerStream;
+    use futures_util::StreamExt;
+
+    /// Wrap each item in an [`Envelope`]. Used by `versioned` handlers.
+    pub fn envelope<T>(stream: ServerStream<T>) -> ServerStream<Envelope<T>>
+    where
+        T: StreamSchema + 'static,
+    {
+        ServerStream::from_results(stream.map(|item| {
+            item.map(|data| Envelope {
+                v: T::SCHEMA_VERSION,
+                data,
+            })
+        }))
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{Envelope, SchemaMismatch, StreamSchema};
+    use crate::rpc::ServerFnError;
+    use crate::stream::ServerStream;
+    use futures_util::StreamExt;
+    use serde::de::DeserializeOwned;
+    use std::sync::Mutex;
+
+    static MISMATCH: Mutex<Option<SchemaMismatch>> = Mutex::new(None);
+
+    /// The first schema mismatch any stream ran into, if any. Once set, the
+    /// page should be reloaded to pick up the new frontend.
+    pub fn mismatch() -> Option<SchemaMismatch> {
+        *MISMATCH.lock().unwrap()
+    }
+
+    /// Reload the page, e.g. from the prompt shown after a [`mismatch`].
+    pub fn reload() {
+        if let Some(window) = web_sys::window() {
+            let _ = window.location().reload();
+        }
+    }
+
+    /// Unwrap the [`Envelope`]s of a `versioned` stream. On the first message
+    /// of another version, records the [`mismatch`], yields
+    /// [`ServerFnError::SchemaMismatch`] and ends.
+    pub fn open<T>(stream: ServerStream<Envelope<serde_json::Value>>) -> ServerStream<T>
+    where
+        T: StreamSchema + DeserializeOwned + Send + 'static,
+    {
+        let items = stream.scan(false, |mismatched, item| {
+            if *mismatched {
+                return std::future::ready(None);
+            }
+            let item = item.and_then(|envelope| {
+                if envelope.v != T::SCHEMA_VERSION {
+                    *mismatched = true;
+                    let mismatch = SchemaMismatch {
+                        expected: T::SCHEMA_VERSION,
+                        found: envelope.v,
+                    };
+                    MISMATCH.lock().unwrap().get_or_insert(mismatch);
+                    return Err(ServerFnError::SchemaMismatch {
+                        expected: mismatch.expected,
+                        found: mismatch.found,
+                    });
+                }
+                serde_json::from_value(envelope.data)
+                    .map_err(|e| ServerFnError::Deserialization(e.to_string()))
+            });
+            std::future::ready(Some(item))
+        });
+        ServerStream::from_results(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_depend_on_the_whole_type() {
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(u32::SCHEMA_VERSION, u64::SCHEMA_VERSION);
+        assert_ne!(Vec::<u32>::SCHEMA_VERSION, Vec::<u64>::SCHEMA_VERSION);
+        assert_ne!(
+            Option::<Vec<String>>::SCHEMA_VERSION,
+            Vec::<Option<String>>::SCHEMA_VERSION
+        );
+        assert_ne!(
+            HashMap::<String, u8>::SCHEMA_VERSION,
+            HashMap::<u8, String>::SCHEMA_VERSION
+        );
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn envelopes_carry_the_version() {
+        use crate::stream::ServerStream;
+        use futures_util::StreamExt;
+
+        let stream = server::envelope(ServerStream::new(futures_util::stream::iter([7u32])));
+        let item = stream.collect::<Vec<_>>().await.remove(0).unwrap();
+        assert_eq!(
+            serde_json::to_value(item).unwrap(),
+            serde_json::json!({ "v": u32::SCHEMA_VERSION, "data": 7 })
+        );
+    }
+}
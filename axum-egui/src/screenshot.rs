@@ -0,0 +1,241 @@
+//! Headless screenshots of the egui UI, used as a loading placeholder.
+//!
+//! While the WASM downloads, the page shows a blank canvas. [`render_png`]
+//! runs the app's UI code natively, rasterizes it in software and encodes a
+//! PNG; served next to the app and shown with [`placeholder_style`], users
+//! see the actual UI instantly, and the same image works as the `og:image`
+//! of [link previews](crate::meta):
+//!
+//! ```ignore
+//! // At startup (or in build.rs, writing the PNG into the dist directory)
+//! let png = axum_egui::screenshot::render_png([1280, 800], 1.0, |ctx| {
+//!     MyApp::default().ui(ctx)
+//! });
+//! let app = Router::new()
+//!     .route("/placeholder.png", get(move || async move {
+//!         ([(header::CONTENT_TYPE, "image/png")], png.clone())
+//!     }))
+//!     .route("/", get(|| async {
+//!         App::new(state).inject("head", placeholder_style("/placeholder.png"))
+//!     }));
+//! ```
+//!
+//! Requires the `screenshot` feature. The rasterizer draws egui's meshes
+//! only: paint callbacks (custom wgpu/glow rendering) are left out.
+
+use egui::epaint::{ClippedPrimitive, Primitive};
+use egui::{Color32, ColorImage, Context, ImageData, Pos2, RawInput, Rect, TextureId, Vec2};
+use std::collections::HashMap;
+
+/// Render `ui` on a `size` pixel screen at `pixels_per_point`.
+///
+/// The UI runs twice, so layouts that size themselves from the previous
+/// frame settle before the picture is taken.
+pub fn render(size: [u32; 2], pixels_per_point: f32, mut ui: impl FnMut(&Context)) -> ColorImage {
+    let ctx = Context::default();
+    ctx.set_pixels_per_point(pixels_per_point);
+    let screen = Vec2::new(size[0] as f32, size[1] as f32) / pixels_per_point;
+    let input = || RawInput {
+        screen_rect: Some(Rect::from_min_size(Pos2::ZERO, screen)),
+        ..Default::default()
+    };
+
+    let mut textures = Textures::default();
+    let mut output = ctx.run(input(), &mut ui);
+    textures.update(&output.textures_delta);
+    output = ctx.run(input(), &mut ui);
+    textures.update(&output.textures_delta);
+
+    let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+    let background = ctx.style().visuals.panel_fill;
+    let mut image = ColorImage::new([size[0] as usize, size[1] as usize], background);
+    for primitive in &primitives {
+        draw(&mut image, primitive, output.pixels_per_point, &textures);
+    }
+    image
+}
+
+/// [`render`] `ui` and encode it as a PNG.
+pub fn render_png(size: [u32; 2], pixels_per_point: f32, ui: impl FnMut(&Context)) -> Vec<u8> {
+    encode_png(&render(size, pixels_per_point, ui))
+}
+
+/// Encode `image` as a PNG.
+pub fn encode_png(image: &ColorImage) -> Vec<u8> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, image.size[0] as u32, image.size[1] as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let rgba: Vec<u8> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_array())
+        .collect();
+    // Writing to a Vec of matching size can't fail
+    let mut writer = encoder.write_header().expect("PNG header");
+    writer.write_image_data(&rgba).expect("PNG image data");
+    writer.finish().expect("PNG end");
+    png
+}
+
+/// A `<style>` showing the image at `url` behind the canvas until the app
+/// draws its first frame. Inject it into the page head.
+pub fn placeholder_style(url: &str) -> String {
+    format!(
+        "<style>body {{ background: url(\"{}\") top left / cover no-repeat; }} \
+         #loading_text {{ display: none; }}</style>",
+        url.replace('"', "%22")
+    )
+}
+
+/// The textures egui asked for, as plain RGBA images.
+#[derive(Default)]
+struct Textures(HashMap<TextureId, ColorImage>);
+
+impl Textures {
+    fn update(&mut self, delta: &egui::TexturesDelta) {
+        for (id, delta) in &delta.set {
+            let patch = match &delta.image {
+                ImageData::Color(image) => (**image).clone(),
+                ImageData::Font(font) => ColorImage {
+                    size: font.size,
+                    pixels: font.srgba_pixels(None).collect(),
+                },
+            };
+            match (delta.pos, self.0.get_mut(id)) {
+                (Some([x, y]), Some(texture)) => {
+                    for row in 0..patch.size[1] {
+                        for column in 0..patch.size[0] {
+                            let to = (y + row) * texture.size[0] + x + column;
+                            texture.pixels[to] = patch.pixels[row * patch.size[0] + column];
+                        }
+                    }
+                }
+                _ => {
+                    self.0.insert(*id, patch);
+                }
+            }
+        }
+    }
+
+    /// Nearest-neighbour sample of `id` at normalized `uv`.
+    fn sample(&self, id: TextureId, uv: Pos2) -> Color32 {
+        let Some(texture) = self.0.get(&id) else {
+            return Color32::WHITE;
+        };
+        let [width, height] = texture.size;
+        let x = ((uv.x * width as f32) as usize).min(width - 1);
+        let y = ((uv.y * height as f32) as usize).min(height - 1);
+        texture.pixels[y * width + x]
+    }
+}
+
+/// Rasterize the triangles of `primitive` into `image`, blending
+/// premultiplied colors like egui's renderers.
+fn draw(
+    image: &mut ColorImage,
+    primitive: &ClippedPrimitive,
+    pixels_per_point: f32,
+    textures: &Textures,
+) {
+    let Primitive::Mesh(mesh) = &primitive.primitive else {
+        return;
+    };
+    let [width, height] = image.size;
+    let clip = primitive.clip_rect * pixels_per_point;
+    let clip_x = (
+        clip.min.x.max(0.0) as usize,
+        (clip.max.x.min(width as f32)).ceil() as usize,
+    );
+    let clip_y = (
+        clip.min.y.max(0.0) as usize,
+        (clip.max.y.min(height as f32)).ceil() as usize,
+    );
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+        let [pa, pb, pc] = [a, b, c].map(|v| v.pos * pixels_per_point);
+        let area = edge(pa, pb, pc);
+        if area == 0.0 {
+            continue;
+        }
+
+        let min_x = (pa.x.min(pb.x).min(pc.x).floor().max(0.0) as usize).max(clip_x.0);
+        let max_x = (pa.x.max(pb.x).max(pc.x).ceil() as usize).min(clip_x.1);
+        let min_y = (pa.y.min(pb.y).min(pc.y).floor().max(0.0) as usize).max(clip_y.0);
+        let max_y = (pa.y.max(pb.y).max(pc.y).ceil() as usize).min(clip_y.1);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let (wa, wb, wc) = (
+                    edge(pb, pc, p) / area,
+                    edge(pc, pa, p) / area,
+                    edge(pa, pb, p) / area,
+                );
+                if wa < 0.0 || wb < 0.0 || wc < 0.0 {
+                    continue;
+                }
+                let uv = Pos2::new(
+                    wa * a.uv.x + wb * b.uv.x + wc * c.uv.x,
+                    wa * a.uv.y + wb * b.uv.y + wc * c.uv.y,
+                );
+                let channel = |i: usize| {
+                    wa * a.color[i] as f32 + wb * b.color[i] as f32 + wc * c.color[i] as f32
+                };
+                let texel = textures.sample(mesh.texture_id, uv);
+                let src = [0, 1, 2, 3].map(|i| channel(i) * texel[i] as f32 / 255.0);
+
+                let dst = &mut image.pixels[y * width + x];
+                let keep = 1.0 - src[3] / 255.0;
+                *dst = Color32::from_rgba_premultiplied(
+                    (src[0] + dst[0] as f32 * keep).round() as u8,
+                    (src[1] + dst[1] as f32 * keep).round() as u8,
+                    (src[2] + dst[2] as f32 * keep).round() as u8,
+                    (src[3] + dst[3] as f32 * keep).round() as u8,
+                );
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `a`, `b`, `p`.
+fn edge(a: Pos2, b: Pos2, p: Pos2) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_panels_and_text() {
+        let image = render([200, 100], 1.0, |ctx| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::NONE.fill(Color32::from_rgb(200, 0, 0)))
+                .show(ctx, |ui| {
+                    ui.label("Hello");
+                });
+        });
+        assert_eq!(image.size, [200, 100]);
+        // The panel fills the screen...
+        assert_eq!(image.pixels[100 * 50 + 150], Color32::from_rgb(200, 0, 0));
+        // ...and the label draws on top of it
+        let label = &image.pixels[..200 * 20];
+        assert!(
+            label
+                .iter()
+                .any(|pixel| *pixel != Color32::from_rgb(200, 0, 0))
+        );
+
+        let png = encode_png(&image);
+        assert_eq!(&png[1..4], b"PNG");
+    }
+
+    #[test]
+    fn placeholder_style_sets_background() {
+        let style = placeholder_style("/placeholder.png");
+        assert!(style.contains(r#"url("/placeholder.png")"#));
+        assert!(style.contains("#loading_text { display: none; }"));
+    }
+}
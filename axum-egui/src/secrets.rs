@@ -0,0 +1,473 @@
+//! One master key for everything the server signs or encrypts.
+//!
+//! Stream tickets, sessions, signed state, CSRF tokens and private cookies
+//! all need a server-side secret. Rather than one setting per feature,
+//! [`Secrets`] holds a single master key and derives an independent key for
+//! each purpose (HKDF-SHA256), so a value signed for one feature is never
+//! accepted by another:
+//!
+//! ```ignore
+//! // AXUM_EGUI_SECRET_KEY=<base64 of at least 32 random bytes>
+//! let secrets = Secrets::from_env()?;
+//!
+//! axum_egui::serve(app, addr)
+//!     .stream_auth(StreamAuth::with_secrets(&secrets, authenticate))
+//!     .secrets(secrets)
+//!     .await?;
+//!
+//! // In a handler
+//! async fn login(Extension(secrets): Extension<Secrets>) -> impl IntoResponse {
+//!     let cookie = secrets.private_cookies().set_cookie("session", &session_id);
+//!     ([(header::SET_COOKIE, cookie)], "welcome")
+//! }
+//! ```
+//!
+//! Generate a key with [`Secrets::generate_key`] (or `openssl rand -base64 32`)
+//! and share it between every server instance.
+//!
+//! # Rotation
+//!
+//! Put the new key in [`SECRET_KEY_ENV`] and move the old one to
+//! [`PREVIOUS_SECRET_KEYS_ENV`]. New values are signed and encrypted with the
+//! new key; values made with a previous key are still accepted, until the
+//! previous key is removed.
+
+use base64::Engine as _;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// Environment variable holding the current master key (base64).
+pub const SECRET_KEY_ENV: &str = "AXUM_EGUI_SECRET_KEY";
+
+/// Environment variable holding previous master keys (base64,
+/// comma-separated), still accepted for reads.
+pub const PREVIOUS_SECRET_KEYS_ENV: &str = "AXUM_EGUI_PREVIOUS_SECRET_KEYS";
+
+/// Minimum master key length in bytes.
+pub const MIN_KEY_LEN: usize = 32;
+
+/// Purposes keys are derived for by the crate. Use your own names for
+/// anything else.
+pub mod purpose {
+    /// Stream tickets (see [`crate::auth::StreamAuth::with_secrets`]).
+    pub const STREAM_AUTH: &str = "stream-auth";
+    /// Session identifiers and session cookies.
+    pub const SESSIONS: &str = "sessions";
+    /// State signed by the server and handed to the client.
+    pub const SIGNED_STATE: &str = "signed-state";
+    /// CSRF tokens.
+    pub const CSRF: &str = "csrf";
+    /// Private (encrypted) cookies.
+    pub const PRIVATE_COOKIES: &str = "private-cookies";
+}
+
+/// Errors from loading [`Secrets`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SecretsError {
+    /// The environment variable is not set.
+    #[error("{0} is not set")]
+    MissingEnv(&'static str),
+    /// The environment variable is not valid base64.
+    #[error("{0} is not valid base64")]
+    InvalidEncoding(&'static str),
+    /// A key is shorter than [`MIN_KEY_LEN`].
+    #[error("secret keys must be at least {MIN_KEY_LEN} bytes, got {0}")]
+    TooShort(usize),
+}
+
+/// The current master key and the previous ones still accepted. Cheap to
+/// clone.
+#[derive(Clone)]
+pub struct Secrets {
+    /// Current key first.
+    keys: Arc<[Vec<u8>]>,
+}
+
+impl std::fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Secrets")
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl Secrets {
+    /// Sign and encrypt with `key`, at least [`MIN_KEY_LEN`] bytes.
+    pub fn new(key: impl AsRef<[u8]>) -> Result<Self, SecretsError> {
+        let key = checked(key.as_ref())?;
+        Ok(Self {
+            keys: Arc::new([key]),
+        })
+    }
+
+    /// Also accept values made with `key`, e.g. the key being rotated out.
+    pub fn previous(self, key: impl AsRef<[u8]>) -> Result<Self, SecretsError> {
+        let key = checked(key.as_ref())?;
+        let mut keys = self.keys.to_vec();
+        keys.push(key);
+        Ok(Self { keys: keys.into() })
+    }
+
+    /// Load the keys from [`SECRET_KEY_ENV`] and [`PREVIOUS_SECRET_KEYS_ENV`].
+    pub fn from_env() -> Result<Self, SecretsError> {
+        let current =
+            std::env::var(SECRET_KEY_ENV).map_err(|_| SecretsError::MissingEnv(SECRET_KEY_ENV))?;
+        let previous = std::env::var(PREVIOUS_SECRET_KEYS_ENV).unwrap_or_default();
+        Self::from_base64(&current, &previous)
+    }
+
+    fn from_base64(current: &str, previous: &str) -> Result<Self, SecretsError> {
+        let decode = |key: &str, var| {
+            STANDARD
+                .decode(key.trim())
+                .map_err(|_| SecretsError::InvalidEncoding(var))
+        };
+        let mut secrets = Self::new(decode(current, SECRET_KEY_ENV)?)?;
+        for key in previous.split(',').filter(|key| !key.trim().is_empty()) {
+            secrets = secrets.previous(decode(key, PREVIOUS_SECRET_KEYS_ENV)?)?;
+        }
+        Ok(secrets)
+    }
+
+    /// Random keys, for development: everything signed or encrypted with
+    /// them is invalid after a restart.
+    pub fn generate() -> Self {
+        Self::new(random::<MIN_KEY_LEN>()).expect("generated keys are long enough")
+    }
+
+    /// A new random master key, base64-encoded for [`SECRET_KEY_ENV`].
+    pub fn generate_key() -> String {
+        STANDARD.encode(random::<MIN_KEY_LEN>())
+    }
+
+    /// The keys for `purpose`, e.g. one of the [`purpose`] constants.
+    pub fn keyring(&self, purpose: &str) -> Keyring {
+        Keyring::derive(self.keys.iter().map(Vec::as_slice), purpose)
+    }
+
+    /// Encrypted cookies, readable only by the server.
+    pub fn private_cookies(&self) -> PrivateCookies {
+        PrivateCookies {
+            keyring: self.keyring(purpose::PRIVATE_COOKIES),
+        }
+    }
+
+    /// A CSRF token bound to `session`, e.g. the session id.
+    pub fn csrf_token(&self, session: &str) -> String {
+        let nonce = random::<16>();
+        let tag = self
+            .keyring(purpose::CSRF)
+            .sign(&csrf_message(session, &nonce));
+        format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(nonce),
+            URL_SAFE_NO_PAD.encode(tag)
+        )
+    }
+
+    /// Whether `token` was made by [`Self::csrf_token`] for `session`.
+    pub fn verify_csrf_token(&self, session: &str, token: &str) -> bool {
+        let Some((nonce, tag)) = token.split_once('.') else {
+            return false;
+        };
+        let (Ok(nonce), Ok(tag)) = (URL_SAFE_NO_PAD.decode(nonce), URL_SAFE_NO_PAD.decode(tag))
+        else {
+            return false;
+        };
+        self.keyring(purpose::CSRF)
+            .verify(&csrf_message(session, &nonce), &tag)
+    }
+}
+
+fn checked(key: &[u8]) -> Result<Vec<u8>, SecretsError> {
+    if key.len() < MIN_KEY_LEN {
+        return Err(SecretsError::TooShort(key.len()));
+    }
+    Ok(key.to_vec())
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    getrandom::fill(&mut bytes).expect("system random number generator");
+    bytes
+}
+
+fn csrf_message(session: &str, nonce: &[u8]) -> Vec<u8> {
+    [session.as_bytes(), &[0], nonce].concat()
+}
+
+/// Keys derived for one purpose: signs and encrypts with the current key,
+/// verifies and decrypts with any. Cheap to clone.
+#[derive(Clone)]
+pub struct Keyring {
+    keys: Arc<[[u8; 32]]>,
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// Derive a key for `purpose` from each master key, current first.
+    pub(crate) fn derive<'a>(masters: impl IntoIterator<Item = &'a [u8]>, purpose: &str) -> Self {
+        let info = format!("axum-egui {purpose}");
+        let keys = masters
+            .into_iter()
+            .map(|master| {
+                let mut key = [0u8; 32];
+                Hkdf::<Sha256>::new(None, master)
+                    .expand(info.as_bytes(), &mut key)
+                    .expect("32 bytes is a valid HKDF-SHA256 output length");
+                key
+            })
+            .collect();
+        Self { keys }
+    }
+
+    fn mac(key: &[u8; 32]) -> Hmac<Sha256> {
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length")
+    }
+
+    /// HMAC-SHA256 of `message` with the current key.
+    pub fn sign(&self, message: &[u8]) -> [u8; 32] {
+        Self::mac(&self.keys[0])
+            .chain_update(message)
+            .finalize()
+            .into_bytes()
+            .into()
+    }
+
+    /// Whether `tag` is the HMAC of `message` under any key. Constant-time.
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        self.keys.iter().any(|key| {
+            Self::mac(key)
+                .chain_update(message)
+                .verify_slice(tag)
+                .is_ok()
+        })
+    }
+
+    /// `value` as JSON followed by its signature, both base64url:
+    /// tamper-proof but readable by the client.
+    pub fn sign_value(&self, value: &impl Serialize) -> Result<String, serde_json::Error> {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(value)?);
+        let tag = URL_SAFE_NO_PAD.encode(self.sign(payload.as_bytes()));
+        Ok(format!("{payload}.{tag}"))
+    }
+
+    /// The value of a token made by [`Self::sign_value`], or `None` if it
+    /// is malformed or was not signed by any key.
+    pub fn verify_value<T: DeserializeOwned>(&self, token: &str) -> Option<T> {
+        let (payload, tag) = token.split_once('.')?;
+        let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+        if !self.verify(payload.as_bytes(), &tag) {
+            return None;
+        }
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()
+    }
+
+    /// Encrypt and authenticate `plaintext` with the current key
+    /// (XChaCha20-Poly1305, random nonce). `context` is authenticated but
+    /// not encrypted; [`Self::decrypt`] must be given the same.
+    pub fn encrypt(&self, plaintext: &[u8], context: &[u8]) -> Vec<u8> {
+        let nonce = random::<24>();
+        let ciphertext = XChaCha20Poly1305::new(&self.keys[0].into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: context,
+                },
+            )
+            .expect("in-memory encryption does not fail");
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    /// The plaintext of a value made by [`Self::encrypt`] under any key, or
+    /// `None` if it was tampered with or `context` differs.
+    pub fn decrypt(&self, sealed: &[u8], context: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < 24 {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(24);
+        self.keys.iter().find_map(|key| {
+            XChaCha20Poly1305::new(key.into())
+                .decrypt(
+                    XNonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: context,
+                    },
+                )
+                .ok()
+        })
+    }
+}
+
+/// Encrypted cookies: the client can neither read nor forge their values.
+/// Values are bound to the cookie name, so one cookie cannot stand in for
+/// another.
+#[derive(Debug, Clone)]
+pub struct PrivateCookies {
+    keyring: Keyring,
+}
+
+impl PrivateCookies {
+    /// The encrypted cookie value for `value`.
+    pub fn seal(&self, name: &str, value: &str) -> String {
+        URL_SAFE_NO_PAD.encode(self.keyring.encrypt(value.as_bytes(), name.as_bytes()))
+    }
+
+    /// The value of a cookie made by [`Self::seal`] under the same `name`.
+    pub fn open(&self, name: &str, sealed: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+        let value = self.keyring.decrypt(&sealed, name.as_bytes())?;
+        String::from_utf8(value).ok()
+    }
+
+    /// A `Set-Cookie` header value for the private cookie `name`:
+    /// `HttpOnly`, `Secure`, `SameSite=Lax` and site-wide.
+    pub fn set_cookie(&self, name: &str, value: &str) -> String {
+        format!(
+            "{name}={}; Path=/; HttpOnly; Secure; SameSite=Lax",
+            self.seal(name, value)
+        )
+    }
+
+    /// The value of the private cookie `name` in the request `headers`, if
+    /// present and valid.
+    pub fn get(&self, headers: &axum::http::HeaderMap, name: &str) -> Option<String> {
+        headers
+            .get_all(axum::http::header::COOKIE)
+            .iter()
+            .filter_map(|header| header.to_str().ok())
+            .flat_map(|header| header.split(';'))
+            .filter_map(|pair| pair.trim().split_once('='))
+            .filter(|(cookie, _)| *cookie == name)
+            .find_map(|(_, sealed)| self.open(name, sealed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, HeaderValue, header};
+
+    const OLD: [u8; 32] = [1; 32];
+    const NEW: [u8; 32] = [2; 32];
+
+    #[test]
+    fn keys_are_validated_and_loaded_from_base64() {
+        assert_eq!(
+            Secrets::new([0; 16]).unwrap_err(),
+            SecretsError::TooShort(16)
+        );
+        assert_eq!(
+            Secrets::from_base64("not base64!", "").unwrap_err(),
+            SecretsError::InvalidEncoding(SECRET_KEY_ENV)
+        );
+        let secrets = Secrets::from_base64(
+            &STANDARD.encode(NEW),
+            &format!(" {},", STANDARD.encode(OLD)),
+        )
+        .unwrap();
+        assert_eq!(secrets.keys.len(), 2);
+        assert_eq!(STANDARD.decode(Secrets::generate_key()).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn rotated_keys_still_verify_and_decrypt() {
+        let old = Secrets::new(OLD).unwrap();
+        let rotated = Secrets::new(NEW).unwrap().previous(OLD).unwrap();
+        let new_only = Secrets::new(NEW).unwrap();
+
+        let token = old
+            .keyring(purpose::SIGNED_STATE)
+            .sign_value(&("cart", 3))
+            .unwrap();
+        let cookie = old.private_cookies().seal("session", "user-7");
+        let csrf = old.csrf_token("session-1");
+
+        let keyring = rotated.keyring(purpose::SIGNED_STATE);
+        assert_eq!(keyring.verify_value(&token), Some(("cart".to_string(), 3)));
+        assert_eq!(
+            rotated
+                .private_cookies()
+                .open("session", &cookie)
+                .as_deref(),
+            Some("user-7")
+        );
+        assert!(rotated.verify_csrf_token("session-1", &csrf));
+
+        // Once the old key is dropped its values are rejected
+        let keyring = new_only.keyring(purpose::SIGNED_STATE);
+        assert_eq!(keyring.verify_value::<(String, u32)>(&token), None);
+        assert_eq!(new_only.private_cookies().open("session", &cookie), None);
+        assert!(!new_only.verify_csrf_token("session-1", &csrf));
+
+        // New values use the new key
+        let token = keyring.sign_value(&1).unwrap();
+        assert_eq!(
+            rotated.keyring(purpose::SIGNED_STATE).verify_value(&token),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn purposes_and_contexts_are_separate() {
+        let secrets = Secrets::new(NEW).unwrap();
+        let token = secrets
+            .keyring(purpose::SESSIONS)
+            .sign_value(&"admin")
+            .unwrap();
+        assert_eq!(
+            secrets
+                .keyring(purpose::SIGNED_STATE)
+                .verify_value::<String>(&token),
+            None
+        );
+
+        let cookies = secrets.private_cookies();
+        let sealed = cookies.seal("session", "user-7");
+        assert_eq!(cookies.open("remember", &sealed), None);
+        let (_, tag) = token.split_once('.').unwrap();
+        let forged = format!("{}.{tag}", URL_SAFE_NO_PAD.encode(b"\"root\""));
+        assert_eq!(
+            secrets
+                .keyring(purpose::SESSIONS)
+                .verify_value::<String>(&forged),
+            None
+        );
+
+        let csrf = secrets.csrf_token("session-1");
+        assert!(!secrets.verify_csrf_token("session-2", &csrf));
+        assert!(!secrets.verify_csrf_token("session-1", "garbage"));
+    }
+
+    #[test]
+    fn private_cookies_round_trip_through_headers() {
+        let cookies = Secrets::new(NEW).unwrap().private_cookies();
+        let set_cookie = cookies.set_cookie("session", "user-7");
+        assert!(set_cookie.ends_with("; Path=/; HttpOnly; Secure; SameSite=Lax"));
+        let pair = set_cookie.split(';').next().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_str(&format!("theme=dark; {pair}")).unwrap(),
+        );
+        assert_eq!(cookies.get(&headers, "session").as_deref(), Some("user-7"));
+        assert_eq!(cookies.get(&headers, "theme"), None);
+    }
+}
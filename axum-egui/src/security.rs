@@ -0,0 +1,402 @@
+//! Security headers for egui apps.
+//!
+//! [`security_headers()`] returns a tower layer that adds sensible defaults to
+//! every response:
+//!
+//! - `X-Content-Type-Options: nosniff`
+//! - `X-Frame-Options: DENY` (plus `frame-ancestors 'none'` in the CSP)
+//! - `Referrer-Policy: strict-origin-when-cross-origin`
+//! - optionally `Strict-Transport-Security`
+//! - optionally a `Content-Security-Policy`, which can be derived from the
+//!   embedded assets so inline bootstrap scripts are allowed by hash
+//!
+//! Headers already set by a handler are left untouched.
+//!
+//! # Example
+//!
+//! ```ignore
+//! use axum_egui::security::{ContentSecurityPolicy, FrameOptions};
+//! use std::time::Duration;
+//!
+//! let app = Router::new()
+//!     .route("/", get(index))
+//!     .fallback(axum_egui::static_handler::<Assets>)
+//!     .layer(
+//!         axum_egui::security_headers()
+//!             .frame_options(FrameOptions::SameOrigin)
+//!             .hsts(Duration::from_secs(31_536_000))
+//!             .content_security_policy(ContentSecurityPolicy::for_assets::<Assets>()),
+//!     );
+//! ```
+
+use axum::http::{HeaderName, HeaderValue, Request, Response, header};
+use base64::Engine;
+use rust_embed::RustEmbed;
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+/// Create a security headers layer with default settings.
+pub fn security_headers() -> SecurityHeaders {
+    SecurityHeaders::default()
+}
+
+/// Whether the app may be embedded in frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// Never allow framing (`X-Frame-Options: DENY`).
+    Deny,
+    /// Allow framing by same-origin pages only.
+    SameOrigin,
+    /// Don't restrict framing.
+    Allow,
+}
+
+/// A `Content-Security-Policy` built directive by directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    directives: Vec<(String, Vec<String>)>,
+}
+
+impl Default for ContentSecurityPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentSecurityPolicy {
+    /// A restrictive base policy suitable for egui apps.
+    ///
+    /// WASM instantiation needs `'wasm-unsafe-eval'`; the default template's
+    /// inline `<style>` needs `'unsafe-inline'` for styles.
+    pub fn new() -> Self {
+        Self {
+            directives: Vec::new(),
+        }
+        .add("default-src", "'self'")
+        .add("script-src", "'self'")
+        .add("script-src", "'wasm-unsafe-eval'")
+        .add("style-src", "'self'")
+        .add("style-src", "'unsafe-inline'")
+        .add("img-src", "'self'")
+        .add("img-src", "data:")
+        .add("connect-src", "'self'")
+        .add("base-uri", "'self'")
+        .add("object-src", "'none'")
+    }
+
+    /// The base policy, plus hashes of the inline scripts in `A`'s `index.html`.
+    ///
+    /// JSON data blocks (such as the injected initial state) are not executed
+    /// and need no hash.
+    pub fn for_assets<A: RustEmbed>() -> Self {
+        let mut csp = Self::new();
+        if let Some(index) = A::get("index.html") {
+            let html = String::from_utf8_lossy(&index.data);
+            for script in inline_scripts(&html) {
+                let digest = Sha256::digest(script.as_bytes());
+                let hash = base64::engine::general_purpose::STANDARD.encode(digest);
+                csp = csp.add("script-src", format!("'sha256-{hash}'"));
+            }
+        }
+        csp
+    }
+
+    /// Add a source to a directive, creating the directive if needed.
+    ///
+    /// Use this to allow a CDN origin (see `App::asset_base_url`) or an
+    /// external API: `.add("script-src", "https://cdn.example.com")`.
+    pub fn add(mut self, directive: impl Into<String>, source: impl Into<String>) -> Self {
+        let directive = directive.into();
+        let source = source.into();
+        match self.directives.iter_mut().find(|(d, _)| *d == directive) {
+            Some((_, sources)) => {
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
+            }
+            None => self.directives.push((directive, vec![source])),
+        }
+        self
+    }
+
+    /// Render the policy as a header value.
+    pub fn to_header_string(&self) -> String {
+        self.directives
+            .iter()
+            .map(|(directive, sources)| format!("{} {}", directive, sources.join(" ")))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Contents of executable inline `<script>` elements in `html`.
+fn inline_scripts(html: &str) -> Vec<&str> {
+    let mut scripts = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<script") {
+        rest = &rest[start..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        let body = &rest[tag_end + 1..];
+        let Some(close) = body.find("</script>") else {
+            break;
+        };
+
+        let is_data_block = tag.contains("application/json");
+        if !tag.contains("src=") && !is_data_block {
+            scripts.push(&body[..close]);
+        }
+        rest = &body[close..];
+    }
+
+    scripts
+}
+
+/// Layer adding security headers to responses. Created by [`security_headers()`].
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    frame_options: FrameOptions,
+    referrer_policy: HeaderValue,
+    hsts: Option<(Duration, bool)>,
+    csp: Option<ContentSecurityPolicy>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        Self {
+            frame_options: FrameOptions::Deny,
+            referrer_policy: HeaderValue::from_static("strict-origin-when-cross-origin"),
+            hsts: None,
+            csp: None,
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// Set the framing policy (default: [`FrameOptions::Deny`]).
+    pub fn frame_options(mut self, frame_options: FrameOptions) -> Self {
+        self.frame_options = frame_options;
+        self
+    }
+
+    /// Set the `Referrer-Policy` (default: `strict-origin-when-cross-origin`).
+    pub fn referrer_policy(mut self, policy: &'static str) -> Self {
+        self.referrer_policy = HeaderValue::from_static(policy);
+        self
+    }
+
+    /// Enable `Strict-Transport-Security` with the given max age, including
+    /// subdomains. Only enable this when the site is served over HTTPS.
+    pub fn hsts(mut self, max_age: Duration) -> Self {
+        self.hsts = Some((max_age, true));
+        self
+    }
+
+    /// Enable `Strict-Transport-Security` without `includeSubDomains`.
+    pub fn hsts_this_domain_only(mut self, max_age: Duration) -> Self {
+        self.hsts = Some((max_age, false));
+        self
+    }
+
+    /// Set a `Content-Security-Policy`.
+    ///
+    /// A `frame-ancestors` directive matching [`Self::frame_options`] is
+    /// added automatically.
+    pub fn content_security_policy(mut self, csp: ContentSecurityPolicy) -> Self {
+        self.csp = Some(csp);
+        self
+    }
+
+    fn headers(&self) -> Vec<(HeaderName, HeaderValue)> {
+        let mut headers = vec![
+            (
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            ),
+            (header::REFERRER_POLICY, self.referrer_policy.clone()),
+        ];
+
+        let frame_ancestors = match self.frame_options {
+            FrameOptions::Deny => {
+                headers.push((header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")));
+                Some("'none'")
+            }
+            FrameOptions::SameOrigin => {
+                headers.push((
+                    header::X_FRAME_OPTIONS,
+                    HeaderValue::from_static("SAMEORIGIN"),
+                ));
+                Some("'self'")
+            }
+            FrameOptions::Allow => None,
+        };
+
+        if let Some((max_age, include_subdomains)) = self.hsts {
+            let mut value = format!("max-age={}", max_age.as_secs());
+            if include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            if let Ok(value) = HeaderValue::from_str(&value) {
+                headers.push((header::STRICT_TRANSPORT_SECURITY, value));
+            }
+        }
+
+        if let Some(csp) = &self.csp {
+            let mut csp = csp.clone();
+            if let Some(ancestors) = frame_ancestors {
+                csp = csp.add("frame-ancestors", ancestors);
+            }
+            if let Ok(value) = HeaderValue::from_str(&csp.to_header_string()) {
+                headers.push((header::CONTENT_SECURITY_POLICY, value));
+            }
+        }
+
+        headers
+    }
+}
+
+impl<S> Layer<S> for SecurityHeaders {
+    type Service = SecurityHeadersService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeadersService {
+            inner,
+            headers: Arc::new(self.headers()),
+        }
+    }
+}
+
+/// Service produced by [`SecurityHeaders`].
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersService<S> {
+    inner: S,
+    headers: Arc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for SecurityHeadersService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        let future = self.inner.call(request);
+        let headers = self.headers.clone();
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            let response_headers = response.headers_mut();
+            for (name, value) in headers.iter() {
+                if !response_headers.contains_key(name) {
+                    response_headers.insert(name.clone(), value.clone());
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::body::Body;
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets/"]
+    struct TestAssets;
+
+    async fn headers_for(layer: SecurityHeaders) -> axum::http::HeaderMap {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(layer);
+        let response = app
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn sets_default_headers() {
+        let headers = headers_for(security_headers()).await;
+
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["x-frame-options"], "DENY");
+        assert_eq!(
+            headers["referrer-policy"],
+            "strict-origin-when-cross-origin"
+        );
+        assert!(!headers.contains_key("strict-transport-security"));
+        assert!(!headers.contains_key("content-security-policy"));
+    }
+
+    #[tokio::test]
+    async fn hsts_and_csp_are_opt_in() {
+        let layer = security_headers()
+            .frame_options(FrameOptions::SameOrigin)
+            .hsts(Duration::from_secs(60))
+            .content_security_policy(ContentSecurityPolicy::new());
+        let headers = headers_for(layer).await;
+
+        assert_eq!(
+            headers["strict-transport-security"],
+            "max-age=60; includeSubDomains"
+        );
+        let csp = headers["content-security-policy"].to_str().unwrap();
+        assert!(csp.contains("script-src 'self' 'wasm-unsafe-eval'"));
+        assert!(csp.ends_with("frame-ancestors 'self'"));
+    }
+
+    #[test]
+    fn csp_for_assets_hashes_inline_scripts() {
+        let index = TestAssets::get("index.html").unwrap();
+        let html = String::from_utf8_lossy(&index.data);
+        let scripts = inline_scripts(&html);
+        assert_eq!(scripts.len(), 1);
+
+        let digest = Sha256::digest(scripts[0].as_bytes());
+        let expected = format!(
+            "'sha256-{}'",
+            base64::engine::general_purpose::STANDARD.encode(digest)
+        );
+        let csp = ContentSecurityPolicy::for_assets::<TestAssets>().to_header_string();
+        assert!(csp.contains(&expected));
+    }
+
+    #[test]
+    fn inline_scripts_skip_external_and_json_blocks() {
+        let html = r#"<script src="./app.js"></script>
+<script id="axum-egui-state" type="application/json">{"a":1}</script>
+<script type="module">init();</script>"#;
+        assert_eq!(inline_scripts(html), vec!["init();"]);
+    }
+
+    #[test]
+    fn csp_add_deduplicates_sources() {
+        let csp = ContentSecurityPolicy::new()
+            .add("script-src", "'self'")
+            .add("script-src", "https://cdn.example.com");
+        assert!(
+            csp.to_header_string()
+                .contains("script-src 'self' 'wasm-unsafe-eval' https://cdn.example.com;")
+        );
+    }
+}
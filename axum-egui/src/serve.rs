@@ -0,0 +1,685 @@
+//! One-call server startup with production defaults.
+//!
+//! [`serve()`] wraps a router with the layers every deployment ends up adding
+//! by hand:
+//!
+//! - response compression (gzip and brotli; SSE streams are left alone)
+//! - request tracing via `tracing`
+//! - a request body limit (2 MiB by default)
+//! - graceful shutdown on Ctrl+C / SIGTERM
+//! - optionally, the embedded-asset fallback for a frontend
+//!
+//! Each default can be overridden on the returned [`Serve`] builder, which
+//! also supports mounting under a base path, CORS origins and a cache policy
+//! for static assets. With the `config` feature, `serve_with_config()` sets
+//! all of these from an `AxumEguiConfig`.
+//!
+//! ```ignore
+//! #[tokio::main]
+//! async fn main() {
+//!     let app = Router::new()
+//!         .route("/", get(index))
+//!         .route("/api/add", post(add_handler));
+//!
+//!     axum_egui::serve(app, ([0, 0, 0, 0], 3000))
+//!         .assets::<Assets>()
+//!         .await
+//!         .unwrap();
+//! }
+//! ```
+
+use crate::app::index_with_meta;
+use crate::auth::StreamAuth;
+use crate::health::Health;
+use crate::instance::StickySessions;
+use crate::limits::ConnectionLimits;
+use crate::logging::CallLog;
+use crate::meta::{PageMeta, PageMetaSource};
+use crate::origin::OriginPolicy;
+use crate::secrets::Secrets;
+use crate::site::SiteFiles;
+use crate::{spa_router, static_handler};
+use axum::Router;
+use axum::extract::DefaultBodyLimit;
+use axum::http::{HeaderValue, Response, header};
+use axum::routing::{MethodRouter, get};
+use rust_embed::RustEmbed;
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+use tower_http::compression::CompressionLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::trace::TraceLayer;
+
+/// Default maximum request body size (2 MiB).
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+type ShutdownSignal = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Serve `router` on `addr` with production defaults.
+///
+/// Returns a [`Serve`] builder; `.await` it to run the server.
+pub fn serve(router: Router, addr: impl Into<SocketAddr>) -> Serve {
+    Serve {
+        router,
+        addr: addr.into(),
+        fallback: None,
+        index: None,
+        page_meta: None,
+        base_path: None,
+        cors_origins: Vec::new(),
+        asset_cache_control: None,
+        connection_limits: None,
+        origin_policy: None,
+        stream_auth: None,
+        secrets: None,
+        site: None,
+        health: None,
+        call_log: None,
+        sticky_sessions: None,
+        drain_on_shutdown: None,
+        compression: true,
+        trace: true,
+        body_limit: Some(DEFAULT_BODY_LIMIT),
+        shutdown: None,
+    }
+}
+
+/// Builder returned by [`serve()`]. Runs the server when awaited.
+#[must_use = "Serve does nothing unless `.await`ed"]
+pub struct Serve {
+    router: Router,
+    addr: SocketAddr,
+    fallback: Option<fn(Option<MethodRouter>, bool) -> Router>,
+    index: Option<MethodRouter>,
+    page_meta: Option<PageMetaSource>,
+    base_path: Option<String>,
+    cors_origins: Vec<HeaderValue>,
+    asset_cache_control: Option<HeaderValue>,
+    connection_limits: Option<ConnectionLimits>,
+    origin_policy: Option<OriginPolicy>,
+    stream_auth: Option<StreamAuth>,
+    secrets: Option<Secrets>,
+    site: Option<SiteFiles>,
+    health: Option<Health>,
+    call_log: Option<CallLog>,
+    sticky_sessions: Option<StickySessions>,
+    drain_on_shutdown: Option<Duration>,
+    compression: bool,
+    trace: bool,
+    body_limit: Option<usize>,
+    shutdown: Option<ShutdownSignal>,
+}
+
+/// The asset fallback. Pages get the `index` handler, or `index.html` with
+/// the page metadata filled in if `meta` is set.
+fn static_router<A: RustEmbed + Send + Sync + 'static>(
+    index: Option<MethodRouter>,
+    meta: bool,
+) -> Router {
+    match index {
+        Some(index) => spa_router::<A, ()>(index),
+        None if meta => spa_router::<A, ()>(get(index_with_meta::<A>)),
+        None => Router::new().fallback(static_handler::<A>),
+    }
+}
+
+impl Serve {
+    /// Serve embedded frontend assets for any unmatched route.
+    pub fn assets<A: RustEmbed + Send + Sync + 'static>(mut self) -> Self {
+        self.fallback = Some(static_router::<A>);
+        self
+    }
+
+    /// With [`Self::assets`], answer paths that are not assets with `index`
+    /// instead of the bare `index.html`, so it can build the initial state
+    /// for the requested URL (see [`spa_router`]).
+    pub fn index(mut self, index: MethodRouter) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Describe the page at each path for link previews with `meta` (see
+    /// [`crate::meta`]). Without an [`Self::index`] handler, the tags are
+    /// filled into `index.html`; index handlers extract the [`PageMeta`].
+    pub fn page_meta(mut self, meta: impl Fn(&str) -> PageMeta + Send + Sync + 'static) -> Self {
+        self.page_meta = Some(PageMetaSource::new(meta));
+        self
+    }
+
+    /// Mount the whole app under `path` (e.g. `/dashboard`).
+    ///
+    /// An empty path or `/` mounts at the root.
+    pub fn base_path(mut self, path: impl Into<String>) -> Self {
+        let path = path.into();
+        let path = path.trim_end_matches('/');
+        self.base_path = if path.is_empty() {
+            None
+        } else if path.starts_with('/') {
+            Some(path.to_string())
+        } else {
+            Some(format!("/{path}"))
+        };
+        self
+    }
+
+    /// Allow cross-origin requests from the given origins, with
+    /// [`cors()`](crate::cors()), and let them open streams.
+    ///
+    /// Invalid origins are ignored. An empty list disables CORS handling.
+    pub fn cors_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.cors_origins = origins
+            .into_iter()
+            .filter_map(|origin| HeaderValue::from_str(origin.as_ref()).ok())
+            .collect();
+        self
+    }
+
+    /// Set the `Cache-Control` header for static assets served by
+    /// [`Self::assets`] (e.g. `public, max-age=31536000, immutable`).
+    ///
+    /// HTML responses are exempt, so a new deploy is picked up on reload.
+    pub fn asset_cache_control(mut self, value: &str) -> Self {
+        self.asset_cache_control = HeaderValue::from_str(value).ok();
+        self
+    }
+
+    /// Enable or disable response compression (default: enabled).
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.compression = enabled;
+        self
+    }
+
+    /// Cap concurrent SSE/WebSocket connections for handlers that extract a
+    /// [`ConnectionPermit`](crate::limits::ConnectionPermit).
+    pub fn connection_limits(mut self, limits: ConnectionLimits) -> Self {
+        self.connection_limits = Some(limits);
+        self
+    }
+
+    /// Which origins may open `#[server(sse)]`/`#[server(ws)]` streams
+    /// (see [`crate::origin`]).
+    ///
+    /// Defaults to the server's own origin plus the [`Self::cors_origins`].
+    pub fn origin_policy(mut self, policy: OriginPolicy) -> Self {
+        self.origin_policy = Some(policy);
+        self
+    }
+
+    /// Authenticate `#[server(sse, auth)]`/`#[server(ws, auth)]` streams with
+    /// tickets minted by `auth` (see [`crate::auth`]), and serve its ticket
+    /// endpoint.
+    pub fn stream_auth(mut self, auth: StreamAuth) -> Self {
+        self.stream_auth = Some(auth);
+        self
+    }
+
+    /// Make `secrets` available to handlers as an `Extension<Secrets>` (see
+    /// [`crate::secrets`]).
+    pub fn secrets(mut self, secrets: Secrets) -> Self {
+        self.secrets = Some(secrets);
+        self
+    }
+
+    /// Serve `robots.txt`, the sitemap and `/.well-known/` files from `site`
+    /// (see [`crate::site`]). They stay at the root when a
+    /// [`Self::base_path`] is set, where crawlers look for them.
+    pub fn site(mut self, site: SiteFiles) -> Self {
+        self.site = Some(site);
+        self
+    }
+
+    /// Serve the `/healthz` and `/readyz` endpoints of `health` (see
+    /// [`crate::health`]), at the root like [`Self::site`].
+    pub fn health(mut self, health: Health) -> Self {
+        self.health = Some(health);
+        self
+    }
+
+    /// Log every server function call with `log` (see [`crate::logging`]).
+    pub fn log_calls(mut self, log: CallLog) -> Self {
+        self.call_log = Some(log);
+        self
+    }
+
+    /// Set a cookie naming this instance for load balancers that route on it
+    /// (see [`crate::instance`]).
+    pub fn sticky_sessions(mut self, sessions: StickySessions) -> Self {
+        self.sticky_sessions = Some(sessions);
+        self
+    }
+
+    /// On the shutdown signal, [drain](crate::instance::drain) the open
+    /// streams over `over` before shutting down, instead of waiting for
+    /// them to end.
+    pub fn drain_on_shutdown(mut self, over: Duration) -> Self {
+        self.drain_on_shutdown = Some(over);
+        self
+    }
+
+    /// Enable or disable request tracing (default: enabled).
+    pub fn trace(mut self, enabled: bool) -> Self {
+        self.trace = enabled;
+        self
+    }
+
+    /// Set the maximum request body size in bytes.
+    pub fn body_limit(mut self, bytes: usize) -> Self {
+        self.body_limit = Some(bytes);
+        self
+    }
+
+    /// Remove the request body limit added by default.
+    ///
+    /// Axum's own default limit still applies to its body extractors.
+    pub fn no_body_limit(mut self) -> Self {
+        self.body_limit = None;
+        self
+    }
+
+    /// Shut down gracefully when `signal` completes, instead of on
+    /// Ctrl+C / SIGTERM.
+    pub fn with_graceful_shutdown<F>(mut self, signal: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.shutdown = Some(Box::pin(signal));
+        self
+    }
+
+    /// The address the server will bind to.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Apply the configured layers and fallback, returning the final router.
+    ///
+    /// Useful to run the same stack on a different server (e.g. `dev-tls`).
+    pub fn into_router(self) -> Router {
+        self.split().0
+    }
+
+    pub(crate) fn split(self) -> (Router, SocketAddr, Option<ShutdownSignal>) {
+        let mut router = self.router;
+
+        if let Some(fallback) = self.fallback {
+            let mut assets = fallback(self.index, self.page_meta.is_some());
+            if let Some(cache_control) = self.asset_cache_control {
+                assets = assets.layer(SetResponseHeaderLayer::if_not_present(
+                    header::CACHE_CONTROL,
+                    move |response: &Response<_>| {
+                        let is_html = response
+                            .headers()
+                            .get(header::CONTENT_TYPE)
+                            .is_some_and(|v| v.as_bytes().starts_with(b"text/html"));
+                        (!is_html).then(|| cache_control.clone())
+                    },
+                ));
+            }
+            router = router.fallback_service(assets);
+        }
+        if let Some(limits) = self.connection_limits {
+            router = router.layer(axum::Extension(limits));
+        }
+        let origin_policy = self.origin_policy.unwrap_or_else(|| {
+            self.cors_origins
+                .iter()
+                .filter_map(|origin| origin.to_str().ok())
+                .fold(OriginPolicy::same_origin(), OriginPolicy::allow)
+        });
+        router = router.layer(axum::Extension(origin_policy));
+        if let Some(auth) = self.stream_auth {
+            router = router.merge(auth.router()).layer(axum::Extension(auth));
+        }
+        router = router.merge(crate::lazy::routes());
+        #[cfg(feature = "snapshot")]
+        {
+            router = router.merge(crate::snapshot::routes());
+        }
+        if let Some(page_meta) = self.page_meta {
+            router = router.layer(axum::Extension(page_meta));
+        }
+        if let Some(secrets) = self.secrets {
+            router = router.layer(axum::Extension(secrets));
+        }
+        if let Some(log) = self.call_log {
+            router = router.layer(log);
+        }
+        if let Some(sessions) = self.sticky_sessions {
+            router = router.layer(sessions);
+        }
+        if let Some(base_path) = &self.base_path {
+            router = Router::new().nest(base_path, router);
+        }
+        if let Some(site) = self.site {
+            router = router.merge(site.router());
+        }
+        if let Some(health) = self.health {
+            router = router.merge(health.router());
+        }
+        if !self.cors_origins.is_empty() {
+            let origins = self
+                .cors_origins
+                .iter()
+                .filter_map(|origin| origin.to_str().ok());
+            router = router.layer(crate::cors::cors(origins));
+        }
+        if let Some(limit) = self.body_limit {
+            router = router.layer(DefaultBodyLimit::max(limit));
+        }
+        if self.compression {
+            router = router.layer(CompressionLayer::new());
+        }
+        if self.trace {
+            router = router.layer(TraceLayer::new_for_http());
+        }
+
+        let shutdown = match self.drain_on_shutdown {
+            Some(over) => {
+                let signal = self.shutdown.unwrap_or_else(|| Box::pin(shutdown_signal()));
+                Some(Box::pin(async move {
+                    signal.await;
+                    crate::instance::drain(over);
+                    tokio::time::sleep(over).await;
+                }) as ShutdownSignal)
+            }
+            None => self.shutdown,
+        };
+
+        (router, self.addr, shutdown)
+    }
+
+    async fn run(self) -> io::Result<()> {
+        let (router, addr, shutdown) = self.split();
+        let shutdown = shutdown.unwrap_or_else(|| Box::pin(shutdown_signal()));
+
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tracing::info!("listening on http://{}", listener.local_addr()?);
+
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown)
+        .await
+    }
+}
+
+impl IntoFuture for Serve {
+    type Output = io::Result<()>;
+    type IntoFuture = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.run())
+    }
+}
+
+/// Resolves on Ctrl+C, or SIGTERM on Unix.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("shutdown signal received, draining connections");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Method, Request, StatusCode, header};
+    use axum::routing::{get, post};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[derive(RustEmbed)]
+    #[folder = "src/test_assets/"]
+    struct TestAssets;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/big", get(|| async { "x".repeat(4096) }))
+            .route("/echo", post(|body: String| async move { body }))
+    }
+
+    #[tokio::test]
+    async fn compresses_responses() {
+        let router = serve(app(), ([127, 0, 0, 1], 0)).into_router();
+        let response = router
+            .oneshot(
+                Request::get("/big")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+    }
+
+    #[tokio::test]
+    async fn compression_can_be_disabled() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .compression(false)
+            .into_router();
+        let response = router
+            .oneshot(
+                Request::get("/big")
+                    .header(header::ACCEPT_ENCODING, "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    }
+
+    #[tokio::test]
+    async fn enforces_body_limit() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .body_limit(16)
+            .into_router();
+        let response = router
+            .oneshot(
+                Request::post("/echo")
+                    .body(Body::from("x".repeat(64)))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn serves_asset_fallback() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .assets::<TestAssets>()
+            .into_router();
+        let response = router
+            .oneshot(Request::get("/app.wasm").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/wasm");
+    }
+
+    #[tokio::test]
+    async fn serves_index_handler_for_non_asset_paths() {
+        let index = |uri: axum::http::Uri| async move {
+            crate::App::<String, TestAssets>::new(uri.path().to_string())
+        };
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .assets::<TestAssets>()
+            .index(get(index))
+            .into_router();
+        let request = |method: Method, path: &str| {
+            Request::builder()
+                .method(method)
+                .uri(path)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(request(Method::GET, "/items/42"))
+            .await
+            .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(String::from_utf8_lossy(&body).contains(r#">"/items/42"</script>"#));
+
+        let wasm = router
+            .clone()
+            .oneshot(request(Method::GET, "/app.wasm"))
+            .await
+            .unwrap();
+        assert_eq!(wasm.headers()[header::CONTENT_TYPE], "application/wasm");
+        let post = router
+            .oneshot(request(Method::POST, "/app.wasm"))
+            .await
+            .unwrap();
+        assert_eq!(post.status(), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[tokio::test]
+    async fn fills_page_meta_with_and_without_index_handler() {
+        let meta = |path: &str| PageMeta::new().title(format!("Page {path}"));
+        let index = |meta: PageMeta| async move {
+            crate::App::<(), TestAssets>::new(()).meta(&meta.description("From the handler"))
+        };
+        let page = |router: Router| async move {
+            let request = Request::get("/items/42").body(Body::empty()).unwrap();
+            let response = router.oneshot(request).await.unwrap();
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        let plain = serve(app(), ([127, 0, 0, 1], 0))
+            .assets::<TestAssets>()
+            .page_meta(meta)
+            .into_router();
+        let html = page(plain).await;
+        assert!(html.contains("<title>Page /items/42</title>"));
+        assert!(!html.contains("axum-egui-state"));
+
+        let with_index = serve(app(), ([127, 0, 0, 1], 0))
+            .assets::<TestAssets>()
+            .index(get(index))
+            .page_meta(meta)
+            .into_router();
+        let html = page(with_index).await;
+        assert!(html.contains("<title>Page /items/42</title>"));
+        assert!(html.contains(r#"<meta name="description" content="From the handler">"#));
+        assert!(html.contains("axum-egui-state"));
+    }
+
+    #[tokio::test]
+    async fn applies_cache_control_to_assets_but_not_html() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .assets::<TestAssets>()
+            .asset_cache_control("public, max-age=60")
+            .into_router();
+
+        let wasm = router
+            .clone()
+            .oneshot(Request::get("/app.wasm").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(wasm.headers()[header::CACHE_CONTROL], "public, max-age=60");
+
+        let html = router
+            .oneshot(Request::get("/some/route").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(!html.headers().contains_key(header::CACHE_CONTROL));
+    }
+
+    #[tokio::test]
+    async fn mounts_under_base_path() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .base_path("dashboard/")
+            .site(crate::site::SiteFiles::new("https://example.com/dashboard"))
+            .into_router();
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/dashboard/big").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = router
+            .clone()
+            .oneshot(Request::get("/big").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // Site files stay at the root
+        let response = router
+            .oneshot(Request::get("/robots.txt").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn answers_cors_preflight_for_allowed_origin() {
+        let router = serve(app(), ([127, 0, 0, 1], 0))
+            .cors_origins(["https://app.example.com"])
+            .into_router();
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/echo")
+                    .header(header::ORIGIN, "https://app.example.com")
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn shuts_down_on_custom_signal() {
+        serve(app(), ([127, 0, 0, 1], 0))
+            .with_graceful_shutdown(async {})
+            .await
+            .unwrap();
+    }
+}
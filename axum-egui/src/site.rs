@@ -0,0 +1,252 @@
+//! `/robots.txt`, `/sitemap.xml` and `/.well-known/*` for single-page apps.
+//!
+//! Crawlers, app stores and security scanners expect a few files at fixed
+//! paths. Without them the asset fallback answers with `index.html`, which
+//! is worse than a 404. [`SiteFiles`] serves them from the app server:
+//!
+//! ```ignore
+//! let site = SiteFiles::new("https://app.example.com")
+//!     .page("/")
+//!     .page("/pricing")
+//!     .disallow("/admin")
+//!     .well_known("security.txt", "Contact: mailto:security@example.com\n")
+//!     .well_known_json("assetlinks.json", &asset_links);
+//! axum_egui::serve(app, addr).assets::<Assets>().site(site).await?;
+//! ```
+//!
+//! Unless given one with [`SiteFiles::robots`], `robots.txt` allows
+//! everything except the [`SiteFiles::disallow`]ed paths and points at the
+//! sitemap, which lists the [`SiteFiles::page`]s.
+
+use axum::Router;
+use axum::http::header;
+use axum::routing::get;
+use serde::Serialize;
+
+/// Path of the robots file.
+pub const ROBOTS_PATH: &str = "/robots.txt";
+
+/// Path of the sitemap.
+pub const SITEMAP_PATH: &str = "/sitemap.xml";
+
+/// Files served at `/robots.txt`, `/sitemap.xml` and under `/.well-known/`.
+#[derive(Debug, Clone)]
+pub struct SiteFiles {
+    base_url: String,
+    pages: Vec<String>,
+    disallow: Vec<String>,
+    robots: Option<String>,
+    well_known: Vec<(String, String, String)>,
+}
+
+impl SiteFiles {
+    /// Site files for the app served at `base_url`, e.g.
+    /// `https://app.example.com` (including the base path, if any). Sitemap
+    /// entries are absolute URLs under it.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            pages: Vec::new(),
+            disallow: Vec::new(),
+            robots: None,
+            well_known: Vec::new(),
+        }
+    }
+
+    /// List the frontend route `path` (e.g. `/` or `/pricing`) in the
+    /// sitemap. Without pages, no sitemap is served.
+    pub fn page(mut self, path: impl Into<String>) -> Self {
+        self.pages.push(path.into());
+        self
+    }
+
+    /// Ask crawlers to stay out of `path` in the generated `robots.txt`.
+    pub fn disallow(mut self, path: impl Into<String>) -> Self {
+        self.disallow.push(path.into());
+        self
+    }
+
+    /// Serve `contents` as `robots.txt` instead of generating it.
+    pub fn robots(mut self, contents: impl Into<String>) -> Self {
+        self.robots = Some(contents.into());
+        self
+    }
+
+    /// Serve `contents` at `/.well-known/{name}`, with a content type guessed
+    /// from the name (`text/plain` if it has no known extension).
+    pub fn well_known(mut self, name: &str, contents: impl Into<String>) -> Self {
+        let name = name.trim_start_matches('/');
+        let content_type = mime_guess::from_path(name)
+            .first_raw()
+            .unwrap_or("text/plain; charset=utf-8");
+        self.well_known
+            .push((name.to_string(), content_type.to_string(), contents.into()));
+        self
+    }
+
+    /// Serve `value` as JSON at `/.well-known/{name}`, e.g.
+    /// `apple-app-site-association`, which has no extension.
+    pub fn well_known_json(mut self, name: &str, value: &impl Serialize) -> Self {
+        let contents = serde_json::to_string(value).expect("well-known JSON serializes");
+        self.well_known.push((
+            name.trim_start_matches('/').to_string(),
+            "application/json".to_string(),
+            contents,
+        ));
+        self
+    }
+
+    fn robots_txt(&self) -> String {
+        if let Some(robots) = &self.robots {
+            return robots.clone();
+        }
+        let mut robots = String::from("User-agent: *\n");
+        if self.disallow.is_empty() {
+            robots.push_str("Allow: /\n");
+        }
+        for path in &self.disallow {
+            robots.push_str(&format!("Disallow: {path}\n"));
+        }
+        if !self.pages.is_empty() {
+            robots.push_str(&format!("\nSitemap: {}{SITEMAP_PATH}\n", self.base_url));
+        }
+        robots
+    }
+
+    fn sitemap_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for page in &self.pages {
+            let path = page.trim_start_matches('/');
+            let url = format!("{}/{path}", self.base_url);
+            xml.push_str(&format!("  <url><loc>{}</loc></url>\n", escape_xml(&url)));
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    /// Routes serving the files.
+    pub fn router<S>(&self) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let robots = self.robots_txt();
+        let mut router = Router::new().route(
+            ROBOTS_PATH,
+            get(move || async move {
+                (
+                    [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+                    robots,
+                )
+            }),
+        );
+        if !self.pages.is_empty() {
+            let sitemap = self.sitemap_xml();
+            router = router.route(
+                SITEMAP_PATH,
+                get(move || async move { ([(header::CONTENT_TYPE, "application/xml")], sitemap) }),
+            );
+        }
+        for (name, content_type, contents) in self.well_known.clone() {
+            router = router.route(
+                &format!("/.well-known/{name}"),
+                get(move || async move { ([(header::CONTENT_TYPE, content_type)], contents) }),
+            );
+        }
+        router
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    async fn get_file(router: &Router, path: &str) -> (StatusCode, String, String) {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .map(|value| value.to_str().unwrap().to_string())
+            .unwrap_or_default();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (
+            status,
+            content_type,
+            String::from_utf8(body.to_vec()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn serves_robots_sitemap_and_well_known_files() {
+        let router: Router = SiteFiles::new("https://app.example.com/")
+            .page("/")
+            .page("/search?q=a&b")
+            .disallow("/admin")
+            .well_known("security.txt", "Contact: mailto:security@example.com\n")
+            .well_known_json(
+                "apple-app-site-association",
+                &serde_json::json!({ "webcredentials": {} }),
+            )
+            .router();
+
+        let (_, content_type, robots) = get_file(&router, ROBOTS_PATH).await;
+        assert_eq!(content_type, "text/plain; charset=utf-8");
+        assert_eq!(
+            robots,
+            "User-agent: *\nDisallow: /admin\n\nSitemap: https://app.example.com/sitemap.xml\n"
+        );
+
+        let (_, content_type, sitemap) = get_file(&router, SITEMAP_PATH).await;
+        assert_eq!(content_type, "application/xml");
+        assert!(sitemap.contains("<loc>https://app.example.com/</loc>"));
+        assert!(sitemap.contains("<loc>https://app.example.com/search?q=a&amp;b</loc>"));
+
+        let (_, content_type, security) = get_file(&router, "/.well-known/security.txt").await;
+        assert_eq!(content_type, "text/plain");
+        assert!(security.starts_with("Contact:"));
+        let (_, content_type, association) =
+            get_file(&router, "/.well-known/apple-app-site-association").await;
+        assert_eq!(content_type, "application/json");
+        assert_eq!(association, r#"{"webcredentials":{}}"#);
+
+        let (status, _, _) = get_file(&router, "/.well-known/other").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn robots_default_and_override() {
+        let router: Router = SiteFiles::new("https://app.example.com").router();
+        assert_eq!(
+            get_file(&router, ROBOTS_PATH).await.2,
+            "User-agent: *\nAllow: /\n"
+        );
+        assert_eq!(
+            get_file(&router, SITEMAP_PATH).await.0,
+            StatusCode::NOT_FOUND
+        );
+
+        let router: Router = SiteFiles::new("https://app.example.com")
+            .robots("User-agent: *\nDisallow: /\n")
+            .router();
+        assert_eq!(
+            get_file(&router, ROBOTS_PATH).await.2,
+            "User-agent: *\nDisallow: /\n"
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Named JSON values injected into the page next to the initial state.
+//!
+//! Besides its main state, an [`App`](crate::App) can carry extra values
+//! under a name, such as the [feature flags](crate::flags) of the request:
+//!
+//! ```ignore
+//! // Server
+//! App::new(state).slot("build", &BuildInfo::current())
+//!
+//! // Client
+//! let build: Option<BuildInfo> = axum_egui::slots::read("build");
+//! ```
+//!
+//! Each slot is a `<script type="application/json">` element whose id is the
+//! name with [`ID_PREFIX`] in front. The main state is in the element
+//! [`STATE_ID`]; frontends read it with [`read_initial_state`]:
+//!
+//! ```ignore
+//! let state: AppState = slots::read_initial_state().unwrap_or_else(|e| {
+//!     log::error!("{e}");
+//!     AppState::default()
+//! });
+//! ```
+//!
+//! [`read_state`] and [`read_initial_state`] say what went wrong: a missing
+//! element, or JSON that doesn't fit the type, with the serde error and the
+//! JSON around it. [`read`] logs the latter and returns `None`.
+
+/// Prefix of the element id of a slot.
+pub const ID_PREFIX: &str = "axum-egui-slot-";
+
+/// The element id of the main state.
+pub const STATE_ID: &str = "axum-egui-state";
+
+/// Characters of JSON shown on each side of a deserialization error.
+const CONTEXT_CHARS: usize = 40;
+
+/// The element id of the slot `name`.
+pub fn element_id(name: &str) -> String {
+    format!("{ID_PREFIX}{name}")
+}
+
+/// The `<script>` element carrying `json` in the slot `name`.
+#[cfg(feature = "server")]
+pub(crate) fn script(name: &str, json: &str) -> String {
+    format!(
+        r#"<script id="{}" type="application/json">{}</script>"#,
+        element_id(name),
+        escape_json(json)
+    )
+}
+
+/// `json` made safe to embed in a `<script>` element: `</` is written as
+/// `<\/` so a `</script>` in a value can't end the element early, and
+/// `<!--` as `\u003c!--` so it can't keep the element open past its end
+/// tag.
+#[cfg(feature = "server")]
+pub(crate) fn escape_json(json: &str) -> String {
+    json.replace("</", "<\\/").replace("<!--", "\\u003c!--")
+}
+
+/// A state or slot could not be read from the page.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ReadStateError {
+    /// The page has no element with this id.
+    #[error("the page has no #{id} element")]
+    Missing {
+        /// The element id.
+        id: String,
+    },
+    /// The element's JSON does not deserialize into the expected type.
+    #[error("#{id} does not hold a `{type_name}`: {error}, near `{context}`")]
+    Invalid {
+        /// The element id.
+        id: String,
+        /// The expected type.
+        type_name: &'static str,
+        /// The serde error, with its line and column.
+        error: String,
+        /// The JSON around the error.
+        context: String,
+    },
+}
+
+/// Deserialize `json`, the content of the element `id`, into `T`.
+pub fn from_json<T: serde::de::DeserializeOwned>(
+    id: &str,
+    json: &str,
+) -> Result<T, ReadStateError> {
+    serde_json::from_str(json).map_err(|e| {
+        // serde_json counts lines and columns from 1
+        let line = json.lines().nth(e.line().saturating_sub(1)).unwrap_or("");
+        let line: Vec<char> = line.chars().collect();
+        let column = e.column().saturating_sub(1).min(line.len());
+        let context = line[column.saturating_sub(CONTEXT_CHARS)..]
+            .iter()
+            .take(2 * CONTEXT_CHARS)
+            .collect();
+        ReadStateError::Invalid {
+            id: id.to_string(),
+            type_name: std::any::type_name::<T>(),
+            error: e.to_string(),
+            context,
+        }
+    })
+}
+
+/// Read the slot `name` from the page.
+#[cfg(feature = "client")]
+pub fn read_state<T: serde::de::DeserializeOwned>(name: &str) -> Result<T, ReadStateError> {
+    read_element(&element_id(name))
+}
+
+/// Read the main state of the [`App`](crate::App) from the page.
+#[cfg(feature = "client")]
+pub fn read_initial_state<T: serde::de::DeserializeOwned>() -> Result<T, ReadStateError> {
+    read_element(STATE_ID)
+}
+
+#[cfg(feature = "client")]
+fn read_element<T: serde::de::DeserializeOwned>(id: &str) -> Result<T, ReadStateError> {
+    let json = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(id))
+        .and_then(|element| element.text_content())
+        .ok_or_else(|| ReadStateError::Missing { id: id.to_string() })?;
+    from_json(id, &json)
+}
+
+/// Read the slot `name` from the page, or `None` if the page has no such
+/// slot or it does not deserialize into `T`, which is logged.
+#[cfg(feature = "client")]
+pub fn read<T: serde::de::DeserializeOwned>(name: &str) -> Option<T> {
+    match read_state(name) {
+        Ok(value) => Some(value),
+        Err(ReadStateError::Missing { .. }) => None,
+        Err(e) => {
+            web_sys::console::warn_1(&e.to_string().into());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct AppState {
+        label: String,
+        value: f32,
+    }
+
+    #[test]
+    fn errors_name_the_type_and_show_the_json() {
+        let json = r#"{"label": "Hello", "value": "not a number", "rest": [1, 2, 3]}"#;
+        let ReadStateError::Invalid {
+            id,
+            type_name,
+            error,
+            context,
+        } = from_json::<AppState>(STATE_ID, json).unwrap_err()
+        else {
+            panic!("expected a deserialization error");
+        };
+        assert_eq!(id, STATE_ID);
+        assert!(type_name.ends_with("AppState"), "{type_name}");
+        assert!(
+            error.contains("invalid type: string \"not a number\""),
+            "{error}"
+        );
+        assert!(error.contains("line 1 column"), "{error}");
+        assert!(context.contains(r#""value": "not a number""#), "{context}");
+
+        assert!(from_json::<AppState>(STATE_ID, r#"{"label": "a", "value": 1}"#).is_ok());
+    }
+}
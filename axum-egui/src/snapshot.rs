@@ -0,0 +1,286 @@
+//! Two-phase hydration for apps with very large initial states.
+//!
+//! Inlining a state of several megabytes as JSON into the page delays
+//! everything: the HTML is slow to arrive and the frontend parses it all
+//! before drawing anything. Instead, put what the first frame needs into the
+//! state and the rest into a snapshot. [`App::snapshot`](crate::App::snapshot)
+//! stores it in MessagePack and injects only its id; the frontend starts with
+//! the small state and then [`load`]s the snapshot, reporting the download
+//! progress on the loading screen:
+//!
+//! ```ignore
+//! // Server
+//! App::<Shell, Assets>::new(shell).snapshot(&dataset)
+//!
+//! // Client
+//! wasm_bindgen_futures::spawn_local(async move {
+//!     if let Ok(Some(dataset)) = axum_egui::snapshot::load::<Dataset>().await {
+//!         *shared.lock().unwrap() = Some(dataset);
+//!     }
+//! });
+//! ```
+//!
+//! Snapshots are kept in memory for [`SNAPSHOT_TTL`] and served at
+//! [`SNAPSHOT_PATH`], which [`serve()`](crate::serve()) mounts; use
+//! [`routes`] with a plain router. Behind a load balancer, the page and the
+//! snapshot must come from the same instance.
+//!
+//! Requires the `snapshot` feature.
+
+use serde::{Deserialize, Serialize};
+
+/// Path prefix of the snapshot endpoint; a snapshot is at
+/// `{SNAPSHOT_PATH}/{id}`.
+pub const SNAPSHOT_PATH: &str = "/api/axum-egui/snapshot";
+
+/// Name of the [slot](crate::slots) carrying the [`SnapshotRef`].
+pub const SNAPSHOT_SLOT: &str = "snapshot";
+
+/// Content type of snapshot responses.
+pub const CONTENT_TYPE: &str = "application/msgpack";
+
+/// Id of a `<progress>` element the client updates while loading a
+/// snapshot. Without one, the `loading_text` element shows the percentage.
+pub const PROGRESS_ELEMENT_ID: &str = "loading_progress";
+
+/// Where to find a snapshot, injected into the page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRef {
+    /// Random id of the snapshot.
+    pub id: String,
+    /// Size of the encoded snapshot in bytes.
+    pub size: u64,
+}
+
+impl SnapshotRef {
+    /// The URL of the snapshot.
+    pub fn url(&self) -> String {
+        format!("{SNAPSHOT_PATH}/{}", self.id)
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{CONTENT_TYPE, SNAPSHOT_PATH, SnapshotRef};
+    use axum::Router;
+    use axum::extract::Path;
+    use axum::http::{StatusCode, header};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use bytes::Bytes;
+    use serde::Serialize;
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// How long a snapshot can be fetched after it was stored.
+    pub const SNAPSHOT_TTL: Duration = Duration::from_secs(60);
+
+    /// Upper bound on the bytes of stored snapshots; the oldest are evicted
+    /// first.
+    pub const MAX_SNAPSHOT_BYTES: usize = 512 * 1024 * 1024;
+
+    struct Entry {
+        data: Bytes,
+        expires: Instant,
+    }
+
+    fn store() -> &'static Mutex<HashMap<String, Entry>> {
+        static STORE: OnceLock<Mutex<HashMap<String, Entry>>> = OnceLock::new();
+        STORE.get_or_init(Default::default)
+    }
+
+    fn random_id() -> String {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).expect("failed to read OS randomness");
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Encode `value` as MessagePack and store it for [`SNAPSHOT_TTL`].
+    pub fn insert(value: &impl Serialize) -> Result<SnapshotRef, rmp_serde::encode::Error> {
+        let data = Bytes::from(rmp_serde::to_vec_named(value)?);
+        let now = Instant::now();
+        let mut store = store().lock().unwrap();
+        store.retain(|_, entry| entry.expires > now);
+        let mut total: usize = store.values().map(|entry| entry.data.len()).sum();
+        while total + data.len() > MAX_SNAPSHOT_BYTES {
+            let Some(oldest) = store
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(id, _)| id.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = store.remove(&oldest) {
+                total -= entry.data.len();
+            }
+        }
+
+        let id = random_id();
+        let size = data.len() as u64;
+        store.insert(
+            id.clone(),
+            Entry {
+                data,
+                expires: now + SNAPSHOT_TTL,
+            },
+        );
+        Ok(SnapshotRef { id, size })
+    }
+
+    async fn snapshot(Path(id): Path<String>) -> Response {
+        let data = {
+            let store = store().lock().unwrap();
+            store
+                .get(&id)
+                .filter(|entry| entry.expires > Instant::now())
+                .map(|entry| entry.data.clone())
+        };
+        match data {
+            Some(data) => (
+                [
+                    (header::CONTENT_TYPE, CONTENT_TYPE),
+                    (header::CACHE_CONTROL, "no-store"),
+                ],
+                data,
+            )
+                .into_response(),
+            None => (StatusCode::NOT_FOUND, "snapshot expired").into_response(),
+        }
+    }
+
+    /// The snapshot endpoint.
+    pub fn routes<S>() -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        Router::new().route(&format!("{SNAPSHOT_PATH}/{{id}}"), get(snapshot))
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{PROGRESS_ELEMENT_ID, SNAPSHOT_SLOT, SnapshotRef};
+    use crate::rpc::ServerFnError;
+    use serde::de::DeserializeOwned;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_futures::JsFuture;
+
+    fn request_error(e: impl std::fmt::Debug) -> ServerFnError {
+        ServerFnError::Request(format!("{e:?}"))
+    }
+
+    /// Load the page's snapshot, showing the progress on the loading
+    /// screen. `None` if the page has no snapshot.
+    pub async fn load<T: DeserializeOwned>() -> Result<Option<T>, ServerFnError> {
+        load_with_progress(show_progress).await
+    }
+
+    /// Like [`load`], reporting the bytes received so far and the total to
+    /// `progress` instead.
+    pub async fn load_with_progress<T: DeserializeOwned>(
+        mut progress: impl FnMut(u64, u64),
+    ) -> Result<Option<T>, ServerFnError> {
+        let Some(snapshot) = crate::slots::read::<SnapshotRef>(SNAPSHOT_SLOT) else {
+            return Ok(None);
+        };
+        let response = gloo_net::http::Request::get(&snapshot.url())
+            .send()
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+        if !response.ok() {
+            return Err(ServerFnError::ServerError(format!(
+                "HTTP {}: snapshot {} is not available",
+                response.status(),
+                snapshot.id
+            )));
+        }
+        let body = response
+            .body()
+            .ok_or_else(|| ServerFnError::Request("snapshot response has no body".into()))?;
+        let reader: web_sys::ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+        let mut data = Vec::with_capacity(snapshot.size as usize);
+        progress(0, snapshot.size);
+        loop {
+            let chunk = JsFuture::from(reader.read()).await.map_err(request_error)?;
+            let done = js_sys::Reflect::get(&chunk, &"done".into()).map_err(request_error)?;
+            if done.is_truthy() {
+                break;
+            }
+            let value = js_sys::Reflect::get(&chunk, &"value".into()).map_err(request_error)?;
+            data.extend(js_sys::Uint8Array::new(&value).to_vec());
+            progress(data.len() as u64, snapshot.size);
+        }
+
+        rmp_serde::from_slice(&data)
+            .map(Some)
+            .map_err(|e| ServerFnError::Deserialization(e.to_string()))
+    }
+
+    /// Update the `<progress>` element, or the loading text.
+    fn show_progress(loaded: u64, total: u64) {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        if let Some(element) = document.get_element_by_id(PROGRESS_ELEMENT_ID) {
+            let _ = element.set_attribute("max", &total.to_string());
+            let _ = element.set_attribute("value", &loaded.to_string());
+        } else if let Some(element) = document.get_element_by_id("loading_text") {
+            let percent = (loaded * 100).checked_div(total).unwrap_or(100);
+            element.set_text_content(Some(&format!("Loading… {percent}%")));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode, header};
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Dataset {
+        rows: Vec<(u32, String)>,
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_served_as_msgpack() {
+        let dataset = Dataset {
+            rows: vec![(1, "one".into()), (2, "two".into())],
+        };
+        let snapshot = insert(&dataset).unwrap();
+        let response = routes::<()>()
+            .oneshot(Request::get(snapshot.url()).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], CONTENT_TYPE);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len() as u64, snapshot.size);
+        assert_eq!(rmp_serde::from_slice::<Dataset>(&body).unwrap(), dataset);
+
+        let missing = format!("{SNAPSHOT_PATH}/unknown");
+        let response = routes::<()>()
+            .oneshot(Request::get(missing).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -22,11 +22,45 @@
 //!     Sse::new(stream).keep_alive(KeepAlive::default())
 //! }
 //! ```
+//!
+//! # Slow Consumers
+//!
+//! A client that stops reading (a backgrounded tab, a stalled network) stops
+//! the server from polling its stream, so events produced for it pile up.
+//! [`channel`] gives each connection a bounded buffer and a
+//! [`SlowConsumerPolicy`] for what happens when it fills:
+//!
+//! ```ignore
+//! use axum_egui::sse::{self, SseBuffer, SlowConsumerPolicy};
+//!
+//! async fn prices() -> Sse<impl Stream<Item = Result<axum::response::sse::Event, Infallible>>> {
+//!     let (tx, stream) = sse::channel(
+//!         SseBuffer::new(64)
+//!             .policy(SlowConsumerPolicy::Coalesce)
+//!             .on_lagging(|lag| tracing::info!(buffered = lag.buffered, "client is lagging")),
+//!     );
+//!     tokio::spawn(async move {
+//!         while let Some(quote) = next_quote().await {
+//!             let event = Event::new().event("quote").json_data(quote).unwrap();
+//!             if tx.send(event).is_err() {
+//!                 break; // client gone or disconnected for lagging
+//!             }
+//!         }
+//!     });
+//!     Sse::new(stream)
+//! }
+//! ```
 
 #[cfg(feature = "server")]
 mod server {
+    use super::DISCONNECT_EVENT;
     use axum::response::sse::{Event as AxumEvent, KeepAlive as AxumKeepAlive, Sse as AxumSse};
     use serde::Serialize;
+    use std::collections::VecDeque;
+    use std::convert::Infallible;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
 
     /// SSE response type. Wraps axum's Sse.
     pub type Sse<S> = AxumSse<S>;
@@ -38,6 +72,7 @@ mod server {
     #[derive(Debug, Clone)]
     pub struct Event {
         inner: AxumEvent,
+        name: Option<String>,
     }
 
     impl Default for Event {
@@ -51,6 +86,7 @@ mod server {
         pub fn new() -> Self {
             Self {
                 inner: AxumEvent::default(),
+                name: None,
             }
         }
 
@@ -58,8 +94,12 @@ mod server {
         ///
         /// This is the most common way to send structured data to clients.
         pub fn json_data<T: Serialize>(mut self, data: T) -> Result<Self, serde_json::Error> {
-            let json = serde_json::to_string(&data)?;
-            self.inner = self.inner.data(json);
+            // Serialized straight into the event, without a `String` in between
+            self.inner = self.inner.json_data(data).map_err(|e| {
+                *e.into_inner()
+                    .downcast::<serde_json::Error>()
+                    .expect("axum reports serde_json errors")
+            })?;
             Ok(self)
         }
 
@@ -73,6 +113,7 @@ mod server {
         ///
         /// Clients can filter events by name using `EventSource.addEventListener()`.
         pub fn event<T: AsRef<str>>(mut self, event: T) -> Self {
+            self.name = Some(event.as_ref().to_string());
             self.inner = self.inner.event(event);
             self
         }
@@ -104,9 +145,290 @@ mod server {
         }
     }
 
+    /// What to do when a connection's [`SseBuffer`] is full.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum SlowConsumerPolicy {
+        /// Discard the oldest buffered event to make room for the new one.
+        DropOldest,
+        /// Keep only the newest buffered event of each event name.
+        ///
+        /// Suits streams where each event replaces the previous one, like
+        /// state snapshots or latest prices.
+        Coalesce,
+        /// Send a final [`DISCONNECT_EVENT`] carrying `reason` and close the
+        /// stream. The client streams of this crate end with an error and
+        /// close their `EventSource` instead of reconnecting.
+        Disconnect { reason: String },
+    }
+
+    /// Details passed to the [`SseBuffer::on_lagging`] hook.
+    #[derive(Debug, Clone)]
+    pub struct LagInfo {
+        /// Events waiting to be sent, at the time the buffer filled up.
+        pub buffered: usize,
+        /// Events dropped or coalesced away on this connection so far.
+        pub dropped: u64,
+        /// The policy being applied.
+        pub policy: SlowConsumerPolicy,
+    }
+
+    type LagHook = Arc<dyn Fn(&LagInfo) + Send + Sync>;
+
+    /// Per-connection send buffer settings for [`channel`].
+    #[derive(Clone)]
+    pub struct SseBuffer {
+        high_water: usize,
+        policy: SlowConsumerPolicy,
+        on_lagging: Option<LagHook>,
+    }
+
+    impl std::fmt::Debug for SseBuffer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("SseBuffer")
+                .field("high_water", &self.high_water)
+                .field("policy", &self.policy)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl Default for SseBuffer {
+        fn default() -> Self {
+            Self::new(256)
+        }
+    }
+
+    impl SseBuffer {
+        /// Buffer up to `high_water` events per connection (minimum 1),
+        /// dropping the oldest beyond that.
+        pub fn new(high_water: usize) -> Self {
+            Self {
+                high_water: high_water.max(1),
+                policy: SlowConsumerPolicy::DropOldest,
+                on_lagging: None,
+            }
+        }
+
+        /// Set the policy applied when the buffer is full.
+        pub fn policy(mut self, policy: SlowConsumerPolicy) -> Self {
+            self.policy = policy;
+            self
+        }
+
+        /// Call `hook` when the client falls behind.
+        ///
+        /// The hook runs once when the buffer first fills up, and again only
+        /// after the client has caught up (drained the buffer) and then fallen
+        /// behind again. It runs on the sending task, so keep it cheap.
+        pub fn on_lagging(mut self, hook: impl Fn(&LagInfo) + Send + Sync + 'static) -> Self {
+            self.on_lagging = Some(Arc::new(hook));
+            self
+        }
+    }
+
+    /// Error returned by [`SseSender::send`].
+    #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+    pub enum SendError {
+        /// The client disconnected and the stream was dropped.
+        #[error("SSE stream closed")]
+        Closed,
+        /// The stream was closed by [`SlowConsumerPolicy::Disconnect`].
+        #[error("SSE client disconnected for lagging: {0}")]
+        Disconnected(String),
+    }
+
+    struct Shared {
+        queue: VecDeque<Event>,
+        waker: Option<Waker>,
+        dropped: u64,
+        lagging: bool,
+        senders: usize,
+        receiver_alive: bool,
+        disconnect: Option<String>,
+    }
+
+    /// Sending half of a buffered SSE connection created by [`channel`].
+    pub struct SseSender {
+        shared: Arc<Mutex<Shared>>,
+        config: Arc<SseBuffer>,
+    }
+
+    /// Streaming half of a buffered SSE connection, for [`Sse::new`].
+    pub struct SseReceiver {
+        shared: Arc<Mutex<Shared>>,
+        finished: bool,
+    }
+
+    /// Create a buffered SSE connection.
+    ///
+    /// Push events with the returned [`SseSender`] (it never blocks) and
+    /// respond with `Sse::new(receiver)`. The stream ends once all senders
+    /// are dropped and the buffer is drained.
+    pub fn channel(config: SseBuffer) -> (SseSender, SseReceiver) {
+        let shared = Arc::new(Mutex::new(Shared {
+            queue: VecDeque::with_capacity(config.high_water.min(64)),
+            waker: None,
+            dropped: 0,
+            lagging: false,
+            senders: 1,
+            receiver_alive: true,
+            disconnect: None,
+        }));
+        (
+            SseSender {
+                shared: shared.clone(),
+                config: Arc::new(config),
+            },
+            SseReceiver {
+                shared,
+                finished: false,
+            },
+        )
+    }
+
+    impl SseSender {
+        /// Queue `event` for the client, applying the slow-consumer policy if
+        /// the buffer is full.
+        pub fn send(&self, event: Event) -> Result<(), SendError> {
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(reason) = &shared.disconnect {
+                return Err(SendError::Disconnected(reason.clone()));
+            }
+            if !shared.receiver_alive {
+                return Err(SendError::Closed);
+            }
+
+            let mut lag = None;
+            if shared.queue.len() >= self.config.high_water {
+                if !shared.lagging {
+                    shared.lagging = true;
+                    lag = Some(LagInfo {
+                        buffered: shared.queue.len(),
+                        dropped: shared.dropped,
+                        policy: self.config.policy.clone(),
+                    });
+                }
+                match &self.config.policy {
+                    SlowConsumerPolicy::DropOldest => {
+                        shared.queue.pop_front();
+                        shared.dropped += 1;
+                        shared.queue.push_back(event);
+                    }
+                    SlowConsumerPolicy::Coalesce => {
+                        let before = shared.queue.len();
+                        shared.queue.retain(|queued| queued.name != event.name);
+                        shared.queue.push_back(event);
+                        // Every event had a distinct name: fall back to dropping the oldest
+                        if shared.queue.len() > self.config.high_water {
+                            shared.queue.pop_front();
+                        }
+                        shared.dropped += (before + 1 - shared.queue.len()) as u64;
+                    }
+                    SlowConsumerPolicy::Disconnect { reason } => {
+                        shared.dropped += shared.queue.len() as u64;
+                        shared.queue.clear();
+                        shared.disconnect = Some(reason.clone());
+                    }
+                }
+            } else {
+                shared.queue.push_back(event);
+            }
+
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+            let disconnected = shared.disconnect.clone();
+            drop(shared);
+
+            if let (Some(lag), Some(hook)) = (lag, &self.config.on_lagging) {
+                hook(&lag);
+            }
+            match disconnected {
+                Some(reason) => Err(SendError::Disconnected(reason)),
+                None => Ok(()),
+            }
+        }
+
+        /// Whether the client is still connected.
+        pub fn is_closed(&self) -> bool {
+            let shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            !shared.receiver_alive || shared.disconnect.is_some()
+        }
+
+        /// Events dropped or coalesced away on this connection so far.
+        pub fn dropped(&self) -> u64 {
+            self.shared
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .dropped
+        }
+    }
+
+    impl Clone for SseSender {
+        fn clone(&self) -> Self {
+            self.shared
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .senders += 1;
+            Self {
+                shared: self.shared.clone(),
+                config: self.config.clone(),
+            }
+        }
+    }
+
+    impl Drop for SseSender {
+        fn drop(&mut self) {
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            shared.senders -= 1;
+            if shared.senders == 0 {
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    impl futures_util::Stream for SseReceiver {
+        type Item = Result<AxumEvent, Infallible>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(event) = shared.queue.pop_front() {
+                if shared.queue.is_empty() {
+                    shared.lagging = false;
+                }
+                return Poll::Ready(Some(Ok(event.into())));
+            }
+            if let Some(reason) = shared.disconnect.clone() {
+                drop(shared);
+                self.finished = true;
+                let event = Event::new().event(DISCONNECT_EVENT).data(reason);
+                return Poll::Ready(Some(Ok(event.into())));
+            }
+            if shared.senders == 0 {
+                return Poll::Ready(None);
+            }
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    impl Drop for SseReceiver {
+        fn drop(&mut self) {
+            let mut shared = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+            shared.receiver_alive = false;
+            shared.queue.clear();
+        }
+    }
+
     /// Extension trait for creating SSE streams from iterators.
     pub trait SseExt<T, E>: Sized {
-        /// Convert a stream of serializable items into an SSE stream.
+        /// Convert a stream of serializable items into an SSE stream, in the
+        /// wire format of [`into_sse_events`](super::into_sse_events). `Err`
+        /// items are sent as `ServerFnError::ServerError`.
         fn into_sse_stream(
             self,
         ) -> impl futures_util::Stream<Item = Result<AxumEvent, std::convert::Infallible>>;
@@ -123,15 +445,9 @@ mod server {
         ) -> impl futures_util::Stream<Item = Result<AxumEvent, std::convert::Infallible>> {
             use futures_util::StreamExt;
 
-            self.map(|result| {
-                Ok(match result {
-                    Ok(data) => Event::new()
-                        .json_data(&data)
-                        .unwrap_or_else(|e| Event::new().data(format!("serialization error: {e}")))
-                        .into(),
-                    Err(e) => Event::new().event("error").data(e.to_string()).into(),
-                })
-            })
+            super::into_sse_events(self.map(|result| {
+                result.map_err(|e| crate::rpc::ServerFnError::ServerError(e.to_string()))
+            }))
         }
     }
 }
@@ -139,13 +455,188 @@ mod server {
 #[cfg(feature = "server")]
 pub use server::*;
 
+/// Event name used for `Err` items of a server function stream.
+///
+/// The data is the `ServerFnError` as a JSON
+/// [`ErrorEnvelope`](crate::error::ErrorEnvelope). (The browser already uses
+/// `error` for connection failures, hence the distinct name.)
+pub const ERROR_EVENT: &str = "server-error";
+
+/// Event name sent after the last item of a server function stream.
+///
+/// `EventSource` reconnects whenever a response ends, so the client needs an
+/// explicit marker to tell "finished" apart from "connection dropped".
+pub const END_EVENT: &str = "server-end";
+
+/// Event name sent when a [drained](crate::instance::drain) server closes a
+/// stream. Its `retry` field spreads the browser's reconnects out, and the
+/// client stream carries on once reconnected, restarting the server function.
+pub const RECONNECT_EVENT: &str = "server-reconnect";
+
+/// Event name sent when the server closes the stream of a client too slow to
+/// keep up (`SlowConsumerPolicy::Disconnect`), with the reason as data.
+///
+/// The stream is over: clients close their `EventSource` rather than let it
+/// reconnect into the same backlog.
+pub const DISCONNECT_EVENT: &str = "disconnect";
+
+/// What a client stream yields for an event of a server function stream,
+/// `None` marking its end.
+#[cfg(any(feature = "client", all(test, feature = "server")))]
+fn read_event<T: serde::de::DeserializeOwned>(
+    name: &str,
+    data: &str,
+) -> Vec<Option<Result<T, crate::rpc::ServerFnError>>> {
+    use crate::rpc::ServerFnError;
+    match name {
+        END_EVENT => vec![None],
+        DISCONNECT_EVENT => vec![
+            Some(Err(ServerFnError::ServerError(format!(
+                "stream closed by the server: {data}"
+            )))),
+            None,
+        ],
+        ERROR_EVENT => vec![Some(Err(
+            serde_json::from_str::<crate::error::ErrorEnvelope>(data).map_or_else(
+                |e| ServerFnError::Deserialization(e.to_string()),
+                Into::into,
+            ),
+        ))],
+        _ => vec![Some(
+            serde_json::from_str(data).map_err(|e| ServerFnError::Deserialization(e.to_string())),
+        )],
+    }
+}
+
+#[cfg(feature = "server")]
+mod server_fn {
+    use super::{END_EVENT, ERROR_EVENT, Event, KeepAlive, RECONNECT_EVENT, Sse};
+    use crate::error::ErrorEnvelope;
+    use crate::instance::{self, INSTANCE_HEADER};
+    use crate::rpc::ServerFnError;
+    use crate::stream::{SerializationPolicy, serialization_failed};
+    use axum::response::{IntoResponse, Response};
+    use futures_util::{Stream, StreamExt};
+    use serde::Serialize;
+    use std::convert::Infallible;
+
+    /// `data` of the [`ERROR_EVENT`] sent when even the error can't be
+    /// serialized, so the client still receives an error.
+    const FALLBACK_ERROR: &str =
+        r#"{"code":"serialization","message":"failed to serialize stream error"}"#;
+
+    fn error_event(error: ServerFnError) -> Event {
+        Event::new()
+            .event(ERROR_EVENT)
+            .json_data(ErrorEnvelope::from(error))
+            .unwrap_or_else(|_| Event::new().event(ERROR_EVENT).data(FALLBACK_ERROR))
+    }
+
+    // Browsers don't dispatch events without data
+    fn end_event() -> Event {
+        Event::new().event(END_EVENT).data("end")
+    }
+
+    /// The events carrying one item of a server function stream, `None`
+    /// marking the end of the stream.
+    ///
+    /// Items that fail to serialize are handled according to the
+    /// [`SerializationPolicy`].
+    pub(super) fn item_events<T: Serialize>(item: Result<T, ServerFnError>) -> Vec<Option<Event>> {
+        let value = match item {
+            Ok(value) => value,
+            Err(e) => return vec![Some(error_event(e))],
+        };
+        let e = match Event::new().json_data(value) {
+            Ok(event) => return vec![Some(event)],
+            Err(e) => e,
+        };
+        let error = Some(error_event(ServerFnError::Serialization(e.to_string())));
+        match serialization_failed(&e) {
+            SerializationPolicy::Skip => vec![],
+            SerializationPolicy::Error => vec![error],
+            SerializationPolicy::Close => vec![error, Some(end_event()), None],
+        }
+    }
+
+    /// Turn a server function's item stream into SSE events, in the wire
+    /// format `sse::connect` reads.
+    ///
+    /// `Ok` items become `message` events with JSON data; `Err` items become
+    /// [`ERROR_EVENT`] events with an [`ErrorEnvelope`], as do
+    /// items that fail to serialize unless the [`SerializationPolicy`] says
+    /// otherwise. An [`END_EVENT`] follows the last item. When the instance is [drained](crate::instance::drain), the
+    /// stream ends early with a [`RECONNECT_EVENT`].
+    ///
+    /// [`into_sse_response`] wraps these in a response; use this directly to
+    /// add events or settings of your own, e.g. a custom keep-alive.
+    pub fn into_sse_events<S, T>(
+        stream: S,
+    ) -> impl Stream<Item = Result<axum::response::sse::Event, Infallible>>
+    where
+        S: Stream<Item = Result<T, ServerFnError>>,
+        T: Serialize,
+    {
+        let stats = crate::metrics::StreamGuard::new(crate::metrics::Transport::Sse);
+        let events = stream.flat_map(move |item| {
+            let events = item_events(item);
+            if !events.is_empty() {
+                stats.sent();
+            }
+            futures_util::stream::iter(events)
+        });
+        let end = futures_util::stream::once(async { Some(end_event()) });
+        let reconnect = futures_util::stream::once(async {
+            instance::drained().await;
+            let event = Event::new()
+                .event(RECONNECT_EVENT)
+                .data(instance::id())
+                .retry(instance::reconnect_jitter());
+            Some(event)
+        });
+        // Whichever comes first of the end of the stream and the drain, each
+        // followed by a `None` to stop at
+        let stop = || futures_util::stream::once(async { None });
+        futures_util::stream::select(events.chain(end).chain(stop()), reconnect.chain(stop()))
+            .take_while(|event| std::future::ready(event.is_some()))
+            .filter_map(|event| std::future::ready(event.map(|event| Ok(event.into()))))
+    }
+
+    /// Turn a server function's item stream into an SSE response.
+    ///
+    /// The events are those of [`into_sse_events`]. Keep-alive comments are
+    /// sent so that client disconnects are noticed even when the stream is
+    /// idle, and the [`INSTANCE_HEADER`] names the instance serving it.
+    ///
+    /// This is what `#[server(sse)]` handlers return; use it directly for
+    /// hand-written handlers on routes of their own that should be readable
+    /// by `sse::connect`.
+    pub fn into_sse_response<S, T>(stream: S) -> Response
+    where
+        S: Stream<Item = Result<T, ServerFnError>> + Send + 'static,
+        T: Serialize + 'static,
+    {
+        let mut response = Sse::new(into_sse_events(stream))
+            .keep_alive(KeepAlive::default())
+            .into_response();
+        response
+            .headers_mut()
+            .insert(INSTANCE_HEADER, instance::header_value());
+        response
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server_fn::{into_sse_events, into_sse_response};
+
 // ============================================================================
 // Client-side SSE support
 // ============================================================================
 
 #[cfg(feature = "client")]
 mod client {
-    use futures_util::stream::Stream;
+    use super::DISCONNECT_EVENT;
+    use futures_util::stream::{Select, Stream};
     use gloo_net::eventsource::futures::{EventSource, EventSourceSubscription};
     use serde::de::DeserializeOwned;
     use std::pin::Pin;
@@ -161,6 +652,17 @@ mod client {
         Parse(String),
         /// The stream was closed.
         Closed,
+        /// The server closed the stream with a [`DISCONNECT_EVENT`], for
+        /// the reason given; the `EventSource` is closed too.
+        Disconnected(String),
+        /// An event was over the SSE message limit (see
+        /// [`crate::payload`]) and was dropped unparsed.
+        TooLarge {
+            /// The size of the event data.
+            size: usize,
+            /// The limit it exceeded.
+            limit: usize,
+        },
     }
 
     impl std::fmt::Display for SseError {
@@ -169,6 +671,16 @@ mod client {
                 SseError::Connection(msg) => write!(f, "SSE connection error: {}", msg),
                 SseError::Parse(msg) => write!(f, "SSE parse error: {}", msg),
                 SseError::Closed => write!(f, "SSE stream closed"),
+                SseError::Disconnected(reason) => {
+                    write!(f, "SSE stream closed by the server: {}", reason)
+                }
+                SseError::TooLarge { size, limit } => {
+                    write!(
+                        f,
+                        "SSE event of {} bytes exceeds the limit of {}",
+                        size, limit
+                    )
+                }
             }
         }
     }
@@ -178,11 +690,12 @@ mod client {
     /// A client-side SSE stream that deserializes JSON events.
     ///
     /// This stream connects to an SSE endpoint and automatically deserializes
-    /// incoming JSON events into the specified type.
+    /// incoming JSON events into the specified type. A [`DISCONNECT_EVENT`]
+    /// ends it with [`SseError::Disconnected`].
     pub struct SseStream<T> {
-        #[allow(dead_code)]
-        source: EventSource,
-        subscription: EventSourceSubscription,
+        // Dropped on a disconnect, which closes the EventSource
+        source: Option<EventSource>,
+        subscription: Select<EventSourceSubscription, EventSourceSubscription>,
         _phantom: std::marker::PhantomData<T>,
     }
 
@@ -194,13 +707,16 @@ mod client {
             let mut source =
                 EventSource::new(url).map_err(|e| SseError::Connection(format!("{:?}", e)))?;
 
-            let subscription = source
+            let messages = source
                 .subscribe("message")
                 .map_err(|e| SseError::Connection(format!("{:?}", e)))?;
+            let disconnect = source
+                .subscribe(DISCONNECT_EVENT)
+                .map_err(|e| SseError::Connection(format!("{:?}", e)))?;
 
             Ok(Self {
-                source,
-                subscription,
+                source: Some(source),
+                subscription: futures_util::stream::select(messages, disconnect),
                 _phantom: std::marker::PhantomData,
             })
         }
@@ -211,12 +727,20 @@ mod client {
 
         fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
             match Pin::new(&mut self.subscription).poll_next(cx) {
+                Poll::Ready(Some(Ok((name, msg)))) if name == DISCONNECT_EVENT => {
+                    // Closed now so the browser doesn't reconnect
+                    self.source = None;
+                    let reason = msg.data().as_string().unwrap_or_default();
+                    Poll::Ready(Some(Err(SseError::Disconnected(reason))))
+                }
                 Poll::Ready(Some(Ok((_, msg)))) => {
-                    let data = msg
-                        .data()
-                        .dyn_into::<js_sys::JsString>()
-                        .map(String::from)
-                        .unwrap_or_default();
+                    let data = msg.data().dyn_into::<js_sys::JsString>().ok();
+                    let size = data.as_ref().map_or(0, |data| data.length() as usize);
+                    let limit = crate::payload::limits().sse_limit();
+                    if size > limit {
+                        return Poll::Ready(Some(Err(SseError::TooLarge { size, limit })));
+                    }
+                    let data = data.map(String::from).unwrap_or_default();
 
                     match serde_json::from_str(&data) {
                         Ok(value) => Poll::Ready(Some(Ok(value))),
@@ -236,6 +760,212 @@ mod client {
 #[cfg(feature = "client")]
 pub use client::*;
 
+#[cfg(feature = "client")]
+mod client_fn {
+    use super::{DISCONNECT_EVENT, END_EVENT, ERROR_EVENT, RECONNECT_EVENT, read_event};
+    use crate::rpc::{ServerFnError, encode_query_args};
+    use crate::stream::ServerStream;
+    use crate::stream::TaskHandle;
+    use futures_channel::mpsc;
+    use futures_util::{Stream, StreamExt};
+    use gloo_net::eventsource::futures::EventSource;
+    use send_wrapper::SendWrapper;
+    use serde::{Serialize, de::DeserializeOwned};
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashMap;
+    use std::rc::{Rc, Weak};
+    use wasm_bindgen::JsCast;
+
+    /// Open the SSE stream of a `#[server(sse)]` function.
+    ///
+    /// This is what the macro generates on the client; `args` are sent as a
+    /// query parameter (see [`encode_query_args`]). The connection closes when
+    /// the returned stream is dropped or the server signals the end of the
+    /// stream, or disconnects it with a [`DISCONNECT_EVENT`](super::DISCONNECT_EVENT);
+    /// identical streams share it (see [`connect_at`]).
+    pub async fn connect<Args, T>(path: &str, args: &Args) -> Result<ServerStream<T>, ServerFnError>
+    where
+        Args: Serialize,
+        T: DeserializeOwned + 'static,
+    {
+        connect_at(&encode_query_args(path, args)?)
+    }
+
+    /// Open the SSE stream of a `#[server(sse, post_params)]` function.
+    ///
+    /// `args` are POSTed first in exchange for a ticket, which is the only
+    /// thing put in the stream URL (see [`crate::tickets`]).
+    pub async fn connect_with_ticket<Args, T>(
+        path: &str,
+        args: &Args,
+    ) -> Result<ServerStream<T>, ServerFnError>
+    where
+        Args: Serialize,
+        T: DeserializeOwned + 'static,
+    {
+        connect_at(&crate::tickets::ticket_url(path, args).await?)
+    }
+
+    /// Open the SSE stream of a `#[server(sse)]` function at a URL that
+    /// already carries its arguments.
+    ///
+    /// Generated for functions declared with `auth`, whose URL also carries
+    /// a ticket (see [`crate::auth`]).
+    ///
+    /// Streams of the same URL, i.e. the same function and arguments, share
+    /// one `EventSource`: its events are fanned out to every open stream, and
+    /// it is closed once the last of them is dropped. A stream opened while
+    /// another is running sees the events from then on.
+    pub fn connect_at<T>(url: &str) -> Result<ServerStream<T>, ServerFnError>
+    where
+        T: DeserializeOwned + 'static,
+    {
+        let connection = match Connection::find(url) {
+            Some(connection) => connection,
+            None => Connection::open(url)?,
+        };
+        let (tx, rx) = mpsc::unbounded();
+        connection.subscribers.borrow_mut().push(tx);
+
+        let items = rx
+            .flat_map(move |event: SharedEvent| {
+                // Keeps the connection open for as long as this stream lives
+                let _ = &connection;
+                futures_util::stream::iter(match event {
+                    Ok((name, data)) => read_event(&name, &data),
+                    Err(e) => vec![Some(Err(e))],
+                })
+            })
+            .take_while(|item| std::future::ready(item.is_some()))
+            .filter_map(std::future::ready);
+
+        Ok(ServerStream::from_results(SendWrapper::new(items)))
+    }
+
+    /// An event of a shared connection: its name and data, or a connection
+    /// error or oversized event.
+    type SharedEvent = Result<(String, String), ServerFnError>;
+
+    /// One `EventSource`, shared by the streams of its URL.
+    struct Connection {
+        url: String,
+        subscribers: RefCell<Vec<mpsc::UnboundedSender<SharedEvent>>>,
+        ended: Cell<bool>,
+        // Dropped with the last stream, which closes the EventSource
+        _task: TaskHandle,
+    }
+
+    thread_local! {
+        static CONNECTIONS: RefCell<HashMap<String, Weak<Connection>>> = RefCell::default();
+    }
+
+    impl Connection {
+        /// The open connection to `url`, if a stream still uses it.
+        fn find(url: &str) -> Option<Rc<Self>> {
+            CONNECTIONS.with_borrow(|connections| {
+                connections
+                    .get(url)
+                    .and_then(Weak::upgrade)
+                    .filter(|connection| !connection.ended.get())
+            })
+        }
+
+        fn open(url: &str) -> Result<Rc<Self>, ServerFnError> {
+            let mut source =
+                EventSource::new(url).map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+            let messages = source
+                .subscribe("message")
+                .map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+            let errors = source
+                .subscribe(ERROR_EVENT)
+                .map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+            let end = source
+                .subscribe(END_EVENT)
+                .map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+            let reconnect = source
+                .subscribe(RECONNECT_EVENT)
+                .map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+            let disconnect = source
+                .subscribe(DISCONNECT_EVENT)
+                .map_err(|e| ServerFnError::Request(format!("{:?}", e)))?;
+
+            let events = futures_util::stream::select(
+                futures_util::stream::select(messages, errors),
+                futures_util::stream::select(
+                    futures_util::stream::select(end, reconnect),
+                    disconnect,
+                ),
+            );
+            // Set by a reconnect request, whose connection error is expected
+            let mut reconnecting = false;
+            let events = events.filter_map(move |event| {
+                let event = match event {
+                    Ok((name, _)) if name == RECONNECT_EVENT => {
+                        reconnecting = true;
+                        None
+                    }
+                    Err(_) if std::mem::take(&mut reconnecting) => None,
+                    Ok((name, msg)) => {
+                        let data = msg.data().dyn_into::<js_sys::JsString>().ok();
+                        let size = data.as_ref().map_or(0, |data| data.length() as usize);
+                        Some(
+                            crate::payload::check(size, crate::payload::limits().sse_limit())
+                                .map(|()| (name, data.map(String::from).unwrap_or_default())),
+                        )
+                    }
+                    Err(e) => Some(Err(ServerFnError::Request(e.to_string()))),
+                };
+                std::future::ready(event)
+            });
+
+            let connection = Rc::new_cyclic(|this: &Weak<Self>| Self {
+                url: url.to_string(),
+                subscribers: RefCell::default(),
+                ended: Cell::new(false),
+                _task: TaskHandle::spawn(Self::run(this.clone(), source, events)),
+            });
+            CONNECTIONS.with_borrow_mut(|connections| {
+                connections.retain(|_, connection| connection.strong_count() > 0);
+                connections.insert(url.to_string(), Rc::downgrade(&connection));
+            });
+            Ok(connection)
+        }
+
+        /// Fan the events out to the streams, until the end marker or a
+        /// disconnect.
+        async fn run(
+            this: Weak<Self>,
+            source: EventSource,
+            events: impl Stream<Item = SharedEvent>,
+        ) {
+            let mut events = std::pin::pin!(events);
+            while let Some(event) = events.next().await {
+                let Some(this) = this.upgrade() else { return };
+                let end = matches!(
+                    &event,
+                    Ok((name, _)) if name == END_EVENT || name == DISCONNECT_EVENT
+                );
+                this.subscribers
+                    .borrow_mut()
+                    .retain(|subscriber| subscriber.unbounded_send(event.clone()).is_ok());
+                if end {
+                    // Later streams of this URL open a new connection
+                    this.ended.set(true);
+                    CONNECTIONS.with_borrow_mut(|connections| {
+                        connections.remove(&this.url);
+                    });
+                    break;
+                }
+            }
+            // Closed now so the browser doesn't reconnect
+            drop(source);
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+pub use client_fn::{connect, connect_at, connect_with_ticket};
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -243,6 +973,40 @@ pub use client::*;
 #[cfg(all(test, feature = "server"))]
 mod tests {
     use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts the allocations of a thread while it runs [`allocations`].
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get().map(|n| n + 1)));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// The allocations `f` makes, growing an allocation not counted.
+    fn allocations<R>(f: impl FnOnce() -> R) -> usize {
+        ALLOCATIONS.with(|count| count.set(Some(0)));
+        drop(f());
+        ALLOCATIONS.with(|count| count.take()).unwrap()
+    }
 
     #[test]
     fn event_new_creates_empty_event() {
@@ -310,6 +1074,179 @@ mod tests {
         let _: axum::response::sse::Event = event.into();
     }
 
+    #[test]
+    fn stream_items_serialize_straight_into_the_event() {
+        use super::server_fn::item_events;
+
+        let item = vec!["a stream item of some length"; 16];
+        let json = serde_json::to_string(&item).unwrap();
+        // What the event itself costs: its buffer and the returned Vec
+        let event = allocations(|| vec![Some(Event::new().data(&json))]);
+        for _ in 0..3 {
+            let serialized = allocations(|| item_events::<_>(Ok(&item)));
+            assert_eq!(serialized, event, "no String per message");
+        }
+    }
+
+    async fn body_of(receiver: SseReceiver) -> String {
+        use axum::response::IntoResponse;
+        let response = Sse::new(receiver).into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn buffered_channel_drops_oldest() {
+        let (tx, rx) = channel(SseBuffer::new(2));
+        for i in 0..4 {
+            tx.send(Event::new().data(i.to_string())).unwrap();
+        }
+        assert_eq!(tx.dropped(), 2);
+        drop(tx);
+
+        assert_eq!(body_of(rx).await, "data: 2\n\ndata: 3\n\n");
+    }
+
+    #[tokio::test]
+    async fn buffered_channel_coalesces_by_event_name() {
+        let (tx, rx) = channel(SseBuffer::new(2).policy(SlowConsumerPolicy::Coalesce));
+        tx.send(Event::new().event("a").data("a1")).unwrap();
+        tx.send(Event::new().event("b").data("b1")).unwrap();
+        tx.send(Event::new().event("a").data("a2")).unwrap();
+        drop(tx);
+
+        assert_eq!(
+            body_of(rx).await,
+            "event: b\ndata: b1\n\nevent: a\ndata: a2\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn buffered_channel_disconnects_lagging_client() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let lagged = Arc::new(AtomicUsize::new(0));
+        let counter = lagged.clone();
+        let (tx, rx) = channel(
+            SseBuffer::new(1)
+                .policy(SlowConsumerPolicy::Disconnect {
+                    reason: "too slow".into(),
+                })
+                .on_lagging(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }),
+        );
+        tx.send(Event::new().data("1")).unwrap();
+        assert_eq!(
+            tx.send(Event::new().data("2")),
+            Err(SendError::Disconnected("too slow".into()))
+        );
+        assert!(tx.is_closed());
+        assert_eq!(lagged.load(Ordering::SeqCst), 1);
+
+        assert_eq!(body_of(rx).await, "event: disconnect\ndata: too slow\n\n");
+    }
+
+    #[test]
+    fn client_streams_end_on_disconnect() {
+        use crate::rpc::ServerFnError;
+
+        assert!(matches!(
+            read_event::<u32>("message", "7").as_slice(),
+            [Some(Ok(7))]
+        ));
+        assert!(matches!(
+            read_event::<u32>(END_EVENT, "end").as_slice(),
+            [None]
+        ));
+        let events = read_event::<u32>(DISCONNECT_EVENT, "too slow");
+        assert!(matches!(
+            events.as_slice(),
+            [Some(Err(ServerFnError::ServerError(message))), None]
+                if message == "stream closed by the server: too slow"
+        ));
+    }
+
+    #[tokio::test]
+    async fn into_sse_response_maps_items_errors_and_end() {
+        use crate::rpc::ServerFnError;
+        use axum::response::IntoResponse;
+
+        let items =
+            futures_util::stream::iter([Ok(1), Err(ServerFnError::ServerError("boom".into()))]);
+        let response = into_sse_response(items).into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "data: 1\n\n\
+             event: server-error\ndata: {\"code\":\"server_error\",\"message\":\"boom\"}\n\n\
+             event: server-end\ndata: end\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn unserializable_items_follow_the_serialization_policy() {
+        use crate::stream::{SerializationPolicy, set_serialization_policy};
+        use axum::response::IntoResponse;
+
+        struct Unserializable;
+
+        impl serde::Serialize for Unserializable {
+            fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                Err(serde::ser::Error::custom("no"))
+            }
+        }
+
+        async fn body(policy: SerializationPolicy) -> String {
+            set_serialization_policy(policy);
+            let items = futures_util::stream::iter([Err("boom"), Ok(Unserializable), Err("after")]);
+            let response = Sse::new(items.into_sse_stream()).into_response();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        }
+
+        let failures = crate::metrics::counter(crate::metrics::SERIALIZATION_FAILURES);
+        let before = failures.get();
+        let error = |code: &str, message: &str| {
+            format!(
+                "event: server-error\ndata: {{\"code\":\"{code}\",\"message\":\"{message}\"}}\n\n"
+            )
+        };
+        let boom = error("server_error", "boom");
+        let failed = error("serialization", "no");
+        let after = error("server_error", "after");
+        let end = "event: server-end\ndata: end\n\n";
+
+        assert_eq!(
+            body(SerializationPolicy::Skip).await,
+            format!("{boom}{after}{end}")
+        );
+        assert_eq!(
+            body(SerializationPolicy::Close).await,
+            format!("{boom}{failed}{end}")
+        );
+        assert_eq!(
+            body(SerializationPolicy::Error).await,
+            format!("{boom}{failed}{after}{end}")
+        );
+        assert!(failures.get() >= before + 3);
+    }
+
+    #[test]
+    fn buffered_channel_reports_closed_receiver() {
+        let (tx, rx) = channel(SseBuffer::default());
+        drop(rx);
+        assert_eq!(tx.send(Event::new().data("x")), Err(SendError::Closed));
+    }
+
     #[test]
     fn event_chaining() {
         let event = Event::new()
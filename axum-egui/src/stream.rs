@@ -0,0 +1,457 @@
+//! The stream type used by streaming server functions.
+//!
+//! `#[server(sse)]` functions return a `ServerStream` of items for the
+//! client; `#[server(ws)]` functions additionally take one as the stream of
+//! messages from the client:
+//!
+//! ```ignore
+//! use axum_egui::{server, ServerFnError, ServerStream};
+//!
+//! #[server(sse)]
+//! pub async fn ticks(every_ms: u64) -> Result<ServerStream<u64>, ServerFnError> {
+//!     let interval = tokio::time::interval(Duration::from_millis(every_ms));
+//!     Ok(ServerStream::new(IntervalStream::new(interval).enumerate().map(|(i, _)| i as u64)))
+//! }
+//!
+//! #[server(ws)]
+//! pub async fn echo(
+//!     prefix: String,
+//!     input: ServerStream<String>,
+//! ) -> Result<ServerStream<String>, ServerFnError> {
+//!     Ok(ServerStream::from_results(
+//!         input.map(move |msg| msg.map(|msg| format!("{prefix}{msg}"))),
+//!     ))
+//! }
+//! ```
+//!
+//! On the client the same calls return a `ServerStream` fed by an
+//! `EventSource` or `WebSocket`. The tasks feeding it are owned by the
+//! stream through [`TaskHandle`]s: dropping it closes the connection right
+//! away, so recreated widgets don't leave connections behind.
+//!
+//! An item that fails to serialize (a map with non-string keys, a failing
+//! `Serialize` impl) is a bug in the app. It is logged and counted in the
+//! `axum_egui_serialization_failures_total` [metric](crate::metrics), and by
+//! default sent to the client as a `ServerFnError::Serialization` error;
+//! [`set_serialization_policy`] skips such items or closes the stream
+//! instead.
+
+use crate::rpc::ServerFnError;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A boxed stream of `Result<T, ServerFnError>` items.
+pub struct ServerStream<T> {
+    inner: Pin<Box<dyn Stream<Item = Result<T, ServerFnError>> + Send>>,
+}
+
+impl<T: 'static> ServerStream<T> {
+    /// Wrap a stream of plain items.
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = T> + Send + 'static,
+    {
+        use futures_util::StreamExt;
+        Self::from_results(stream.map(Ok))
+    }
+
+    /// Wrap a stream whose items may fail.
+    ///
+    /// Errors are delivered to the client as individual items; they do not
+    /// end the stream.
+    pub fn from_results<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<T, ServerFnError>> + Send + 'static,
+    {
+        Self {
+            inner: Box::pin(stream),
+        }
+    }
+
+    /// A stream that ends immediately.
+    pub fn empty() -> Self {
+        Self::from_results(futures_util::stream::poll_fn(|_| Poll::Ready(None)))
+    }
+}
+
+impl<T: Send + 'static> ServerStream<T> {
+    /// Keep only the newest of the items that arrived since the last one
+    /// was taken, for streams where each item replaces the previous ones
+    /// (prices, cursor positions). A consumer falling behind a burst then
+    /// skips to the current value instead of working through a backlog.
+    ///
+    /// Errors are never dropped. This is what `#[server(sse, conflate)]`
+    /// applies on both ends.
+    pub fn latest_only(self) -> Self {
+        self.latest_by(|_| ())
+    }
+
+    /// Like [`latest_only`](Self::latest_only), but keeping the newest
+    /// pending item of every `key`, e.g. one per instrument of a price
+    /// feed. Pending items are delivered in the order they were last
+    /// updated.
+    pub fn latest_by<K, F>(self, key: F) -> Self
+    where
+        K: PartialEq + Send + 'static,
+        F: FnMut(&T) -> K + Send + 'static,
+    {
+        Self::from_results(Conflate {
+            inner: self,
+            key,
+            pending: std::collections::VecDeque::new(),
+            done: false,
+        })
+    }
+}
+
+/// Most items taken from the inner stream per poll, so that a stream that
+/// is always ready still yields.
+const CONFLATE_BATCH: usize = 1024;
+
+/// The stream of [`ServerStream::latest_by`].
+struct Conflate<T, K, F> {
+    inner: ServerStream<T>,
+    key: F,
+    // Errors have no key and are never replaced
+    pending: std::collections::VecDeque<(Option<K>, Result<T, ServerFnError>)>,
+    done: bool,
+}
+
+// Nothing is pinned through it: the inner stream is boxed
+impl<T, K, F> Unpin for Conflate<T, K, F> {}
+
+impl<T, K, F> Stream for Conflate<T, K, F>
+where
+    K: PartialEq,
+    F: FnMut(&T) -> K,
+{
+    type Item = Result<T, ServerFnError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut taken = 0;
+        while !this.done && taken < CONFLATE_BATCH {
+            match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => {
+                    let key = (this.key)(&item);
+                    this.pending
+                        .retain(|(pending, _)| pending.as_ref() != Some(&key));
+                    this.pending.push_back((Some(key), Ok(item)));
+                }
+                Poll::Ready(Some(Err(e))) => this.pending.push_back((None, Err(e))),
+                Poll::Ready(None) => this.done = true,
+                Poll::Pending => break,
+            }
+            taken += 1;
+        }
+        match this.pending.pop_front() {
+            Some((_, item)) => Poll::Ready(Some(item)),
+            None if this.done => Poll::Ready(None),
+            None if taken == CONFLATE_BATCH => {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Stream for ServerStream<T> {
+    type Item = Result<T, ServerFnError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> std::fmt::Debug for ServerStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerStream").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "server")]
+impl<T: Clone + Send + 'static> ServerStream<T> {
+    /// The values sent on a `tokio::sync::broadcast` channel from now on,
+    /// e.g. to serve an app's existing event bus over SSE.
+    ///
+    /// A receiver that falls more than the channel's capacity behind skips
+    /// the values it missed: the stream yields one
+    /// [`ServerFnError::Lagged`] saying how many, then carries on with the
+    /// oldest value still in the channel. It ends once every sender is
+    /// dropped.
+    pub fn from_broadcast(rx: tokio::sync::broadcast::Receiver<T>) -> Self {
+        use tokio::sync::broadcast::error::RecvError;
+
+        Self::from_results(futures_util::stream::unfold(rx, |mut rx| async move {
+            let item = match rx.recv().await {
+                Ok(value) => Ok(value),
+                Err(RecvError::Lagged(skipped)) => Err(ServerFnError::Lagged { skipped }),
+                Err(RecvError::Closed) => return None,
+            };
+            Some((item, rx))
+        }))
+    }
+}
+
+#[cfg(feature = "server")]
+impl<T: Clone + Send + Sync + 'static> ServerStream<T> {
+    /// The current value of a `tokio::sync::watch` channel, then each new
+    /// one. Values replaced before the consumer got to them are skipped, so
+    /// a slow client goes straight to the latest. It ends once the sender is
+    /// dropped.
+    pub fn from_watch(rx: tokio::sync::watch::Receiver<T>) -> Self {
+        Self::new(futures_util::stream::unfold(
+            (rx, true),
+            |(mut rx, first)| async move {
+                if !first && rx.changed().await.is_err() {
+                    return None;
+                }
+                let value = rx.borrow_and_update().clone();
+                Some((value, (rx, false)))
+            },
+        ))
+    }
+}
+
+/// What streaming server functions do with an item that fails to
+/// serialize.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SerializationPolicy {
+    /// Drop the item and carry on.
+    Skip,
+    /// Send a `ServerFnError::Serialization` error in its place and carry on.
+    #[default]
+    Error,
+    /// Send the error, then end the stream.
+    Close,
+}
+
+#[cfg(feature = "server")]
+static SERIALIZATION_POLICY: std::sync::Mutex<SerializationPolicy> =
+    std::sync::Mutex::new(SerializationPolicy::Error);
+
+/// Set the [`SerializationPolicy`] of all streams, including open ones.
+#[cfg(feature = "server")]
+pub fn set_serialization_policy(policy: SerializationPolicy) {
+    *SERIALIZATION_POLICY.lock().unwrap() = policy;
+}
+
+/// Log and count a stream item that failed to serialize, and return what to
+/// do with it.
+#[cfg(feature = "server")]
+pub(crate) fn serialization_failed(error: &dyn std::fmt::Display) -> SerializationPolicy {
+    crate::metrics::counter(crate::metrics::SERIALIZATION_FAILURES).inc();
+    let policy = *SERIALIZATION_POLICY.lock().unwrap();
+    tracing::error!(?policy, "failed to serialize stream item: {error}");
+    policy
+}
+
+/// A buffer the WebSocket messages of one connection are serialized into.
+///
+/// Each [`encode`](Self::encode) hands out the bytes of one message, sharing
+/// the buffer's allocation, which is reclaimed once they have been sent and
+/// dropped. High-frequency sockets thus serialize without an allocation per
+/// message. (SSE events have a buffer of their own, which
+/// [`Event::json_data`](crate::sse::Event::json_data) serializes into.)
+#[cfg(feature = "server")]
+#[derive(Debug, Default)]
+pub(crate) struct JsonBuffer {
+    buffer: bytes::BytesMut,
+    // Size of the last message, to reserve room for the next
+    last: usize,
+}
+
+#[cfg(feature = "server")]
+impl JsonBuffer {
+    /// `value` as JSON.
+    pub(crate) fn encode<T: serde::Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<bytes::Bytes, serde_json::Error> {
+        use bytes::BufMut;
+        self.buffer.reserve(self.last.max(64));
+        match serde_json::to_writer((&mut self.buffer).writer(), value) {
+            Ok(()) => {
+                self.last = self.buffer.len();
+                Ok(self.buffer.split().freeze())
+            }
+            Err(e) => {
+                self.buffer.clear();
+                Err(e)
+            }
+        }
+    }
+
+    /// `value` as a JSON WebSocket text message.
+    pub(crate) fn encode_text<T: serde::Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<axum::extract::ws::Utf8Bytes, serde_json::Error> {
+        let json = self.encode(value)?;
+        Ok(json.try_into().expect("serde_json writes UTF-8"))
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::TaskHandle;
+
+#[cfg(feature = "client")]
+mod client {
+    use futures_util::future::{AbortHandle, Abortable};
+
+    /// A task on the browser's event loop, cancelled when the handle is
+    /// dropped.
+    ///
+    /// Client streams own the tasks that feed them this way, rather than
+    /// leaving them to notice on their next message that nobody listens.
+    #[derive(Debug)]
+    #[must_use = "the task is cancelled when the handle is dropped"]
+    pub struct TaskHandle(AbortHandle);
+
+    impl TaskHandle {
+        /// Spawn `future` with `spawn_local`.
+        pub fn spawn(future: impl Future<Output = ()> + 'static) -> Self {
+            let (handle, registration) = AbortHandle::new_pair();
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = Abortable::new(future, registration).await;
+            });
+            Self(handle)
+        }
+
+        /// Cancel the task now. It stops at its next await point and drops
+        /// what it owns.
+        pub fn abort(&self) {
+            self.0.abort();
+        }
+
+        /// Whether the task was cancelled.
+        pub fn is_aborted(&self) -> bool {
+            self.0.is_aborted()
+        }
+    }
+
+    impl Drop for TaskHandle {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn wraps_plain_items_as_ok() {
+        let stream = ServerStream::new(futures_util::stream::iter([1, 2]));
+        let items: Vec<_> = stream.map(Result::unwrap).collect().await;
+        assert_eq!(items, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn empty_stream_ends() {
+        assert!(ServerStream::<u8>::empty().next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn latest_only_skips_to_the_newest_pending_item() {
+        let (tx, rx) = futures_channel::mpsc::unbounded();
+        let mut stream = ServerStream::from_results(rx).latest_only();
+        for i in 0..3 {
+            tx.unbounded_send(Ok(i)).unwrap();
+        }
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+
+        tx.unbounded_send(Ok(3)).unwrap();
+        tx.unbounded_send(Err(ServerFnError::ServerError("boom".into())))
+            .unwrap();
+        tx.unbounded_send(Ok(4)).unwrap();
+        tx.unbounded_send(Ok(5)).unwrap();
+        drop(tx);
+        let items: Vec<_> = stream.map(|item| item.map_err(|_| ())).collect().await;
+        // 3 is superseded by 5, the error is kept
+        assert_eq!(items, [Err(()), Ok(5)]);
+    }
+
+    #[tokio::test]
+    async fn latest_by_keeps_one_item_per_key() {
+        let quotes = [("eur", 1), ("usd", 2), ("eur", 3), ("gbp", 4), ("usd", 5)];
+        let stream = ServerStream::new(futures_util::stream::iter(quotes));
+        let items: Vec<_> = stream
+            .latest_by(|(symbol, _)| *symbol)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(items, [("eur", 3), ("gbp", 4), ("usd", 5)]);
+    }
+
+    #[tokio::test]
+    async fn broadcast_receivers_report_skipped_items() {
+        let (tx, rx) = tokio::sync::broadcast::channel(2);
+        let stream = ServerStream::from_broadcast(rx);
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        drop(tx);
+        let items: Vec<_> = stream.collect().await;
+        assert!(matches!(
+            items[0],
+            Err(ServerFnError::Lagged { skipped: 3 })
+        ));
+        let values: Vec<_> = items[1..]
+            .iter()
+            .map(|item| *item.as_ref().unwrap())
+            .collect();
+        assert_eq!(values, [3, 4]);
+    }
+
+    #[tokio::test]
+    async fn watch_receivers_yield_the_latest_value() {
+        let (tx, rx) = tokio::sync::watch::channel(0);
+        let mut stream = ServerStream::from_watch(rx);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 0);
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+
+        drop(tx);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn json_buffer_reuses_its_allocation() {
+        let mut buffer = JsonBuffer::default();
+        let first = buffer.encode(&[1, 2, 3]).unwrap();
+        assert_eq!(&first[..], b"[1,2,3]");
+        let address = first.as_ptr();
+        drop(first);
+
+        // Sent messages give their memory back
+        let second = buffer.encode(&"abc").unwrap();
+        assert_eq!(&second[..], b"\"abc\"");
+        assert_eq!(second.as_ptr(), address);
+
+        // Messages still held are left alone
+        let third = buffer.encode(&true).unwrap();
+        assert_eq!(&second[..], b"\"abc\"");
+        assert_eq!(&third[..], b"true");
+
+        let mut map = std::collections::HashMap::new();
+        map.insert((1, 2), 3);
+        assert!(buffer.encode(&map).is_err());
+        assert_eq!(&buffer.encode(&4).unwrap()[..], b"4");
+    }
+}
@@ -0,0 +1,306 @@
+//! Live-synced state structs, built on [`crate::patch`].
+//!
+//! `#[derive(SyncedState)]` turns a struct into a value shared by the server
+//! and every connected client: the server holds the authoritative copy in a
+//! watch channel, clients follow it through JSON Patch diffs over SSE, and
+//! client mutations are sent back as patches to the same path.
+//!
+//! ```ignore
+//! #[derive(Clone, Default, Serialize, Deserialize, SyncedState)]
+//! #[synced(path = "/api/board")] // default: /api/sync/{snake_case_name}
+//! pub struct Board {
+//!     pub cards: Vec<Card>,
+//! }
+//!
+//! // Server
+//! let app = Router::new().merge(Board::router());
+//! Board::modify(|board| board.cards.push(card));
+//!
+//! // Client
+//! let board = Board::connect({
+//!     let ctx = cc.egui_ctx.clone();
+//!     move || ctx.request_repaint()
+//! })?;
+//! if let Some(current) = board.read().state() { /* draw */ }
+//! board.mutate(|b| b.cards.clear());
+//! ```
+//!
+//! Mutations are not applied optimistically: the UI shows them once the
+//! server has accepted and broadcast them. [`SyncedState::router`] lets any
+//! client mutate the state, so put it behind authentication, or mount
+//! [`SyncedState::read_only_router`] and change the state only on the server.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// A struct kept in sync between the server and its clients. Derive it with
+/// `#[derive(SyncedState)]` rather than implementing it by hand.
+pub trait SyncedState: Serialize + DeserializeOwned + Default + Send + Sync + 'static {
+    /// The path serving the patch stream (GET) and accepting mutations (POST).
+    const PATH: &'static str;
+
+    /// The server's copy of the state, created from `Default` on first use.
+    #[cfg(feature = "server")]
+    fn sender() -> &'static tokio::sync::watch::Sender<Self> {
+        server::sender::<Self>()
+    }
+
+    /// A clone of the current server state.
+    #[cfg(feature = "server")]
+    fn get() -> Self
+    where
+        Self: Clone,
+    {
+        Self::sender().borrow().clone()
+    }
+
+    /// Replace the server state and notify clients.
+    #[cfg(feature = "server")]
+    fn set(value: Self) {
+        Self::sender().send_replace(value);
+    }
+
+    /// Change the server state in place and notify clients.
+    #[cfg(feature = "server")]
+    fn modify(f: impl FnOnce(&mut Self)) {
+        Self::sender().send_modify(f);
+    }
+
+    /// Apply a JSON Patch to the server state, as sent by
+    /// [`SyncedHandle::mutate`]. Leaves the state unchanged on error.
+    #[cfg(feature = "server")]
+    fn apply(ops: &crate::patch::Patch) -> Result<(), crate::patch::PatchStateError> {
+        server::apply::<Self>(ops)
+    }
+
+    /// Routes for the patch stream (GET) and mutations (POST) at
+    /// [`PATH`](Self::PATH).
+    #[cfg(feature = "server")]
+    fn router<S>() -> axum::Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        server::router::<Self, S>(true)
+    }
+
+    /// Only the patch stream; the state can only change on the server.
+    #[cfg(feature = "server")]
+    fn read_only_router<S>() -> axum::Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        server::router::<Self, S>(false)
+    }
+
+    /// Follow the server state. `on_change` runs after every update; pass
+    /// `egui::Context::request_repaint` so the UI redraws.
+    #[cfg(feature = "client")]
+    fn connect(on_change: impl Fn() + 'static) -> Result<SyncedHandle<Self>, crate::sse::SseError> {
+        let live = crate::patch::subscribe(Self::PATH, on_change)?;
+        Ok(SyncedHandle { live })
+    }
+}
+
+#[cfg(feature = "server")]
+mod server {
+    use super::SyncedState;
+    use crate::error::ErrorEnvelope;
+    use crate::patch::{self, Patch, PatchStateError};
+    use axum::Router;
+    use axum::extract::Json;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use std::any::{Any, TypeId};
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use tokio::sync::watch;
+
+    type Senders = HashMap<TypeId, &'static (dyn Any + Send + Sync)>;
+
+    pub(super) fn sender<T: SyncedState>() -> &'static watch::Sender<T> {
+        static SENDERS: OnceLock<Mutex<Senders>> = OnceLock::new();
+        let mut senders = SENDERS.get_or_init(Default::default).lock().unwrap();
+        let sender = *senders
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::leak(Box::new(watch::Sender::new(T::default()))));
+        sender
+            .downcast_ref()
+            .expect("synced state sender has the wrong type")
+    }
+
+    pub(super) fn apply<T: SyncedState>(ops: &Patch) -> Result<(), PatchStateError> {
+        let mut result = Ok(());
+        T::sender().send_if_modified(|state| {
+            let patched = serde_json::to_value(&*state)
+                .map_err(PatchStateError::from)
+                .and_then(|mut value| {
+                    json_patch::patch(&mut value, ops)?;
+                    Ok(serde_json::from_value(value)?)
+                });
+            match patched {
+                Ok(patched) => {
+                    *state = patched;
+                    !ops.0.is_empty()
+                }
+                Err(e) => {
+                    result = Err(e);
+                    false
+                }
+            }
+        });
+        result
+    }
+
+    pub(super) fn router<T, S>(mutable: bool) -> Router<S>
+    where
+        T: SyncedState,
+        S: Clone + Send + Sync + 'static,
+    {
+        let stream = get(|| async { patch::sse(T::sender().subscribe()) });
+        let route = if mutable {
+            stream.post(|Json(ops): Json<Patch>| async move { mutate::<T>(&ops) })
+        } else {
+            stream
+        };
+        Router::new().route(T::PATH, route)
+    }
+
+    fn mutate<T: SyncedState>(ops: &Patch) -> Response {
+        match T::apply(ops) {
+            Ok(()) => Json(()).into_response(),
+            Err(e) => {
+                let (status, code) = match e {
+                    PatchStateError::Patch(_) => (StatusCode::CONFLICT, "conflict"),
+                    _ => (StatusCode::UNPROCESSABLE_ENTITY, "invalid_patch"),
+                };
+                let error = ErrorEnvelope::new(code, e.to_string());
+                crate::error::error_response(status, error, None)
+            }
+        }
+    }
+}
+
+/// Client handle of a [`SyncedState`], returned by
+/// [`SyncedState::connect`]. Cheap to clone.
+#[cfg(feature = "client")]
+#[derive(Debug)]
+pub struct SyncedHandle<T> {
+    live: crate::patch::LiveState<T>,
+}
+
+#[cfg(feature = "client")]
+impl<T> Clone for SyncedHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            live: self.live.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl<T: SyncedState> SyncedHandle<T> {
+    /// The latest state received from the server, with change flags.
+    pub fn read(&self) -> std::sync::MutexGuard<'_, crate::patch::PatchedState<T>> {
+        self.live.lock()
+    }
+
+    /// Change the state: `f` runs on a copy of the current state and the
+    /// difference is sent to the server, which broadcasts it to every
+    /// client (including this one). Does nothing before the first update
+    /// has arrived.
+    pub fn mutate(&self, f: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        let Some(current) = self.read().state().cloned() else {
+            return;
+        };
+        let mut next = current.clone();
+        f(&mut next);
+        let (Ok(before), Ok(after)) = (serde_json::to_value(&current), serde_json::to_value(&next))
+        else {
+            return;
+        };
+        let ops = json_patch::diff(&before, &after);
+        if ops.0.is_empty() {
+            return;
+        }
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Err(e) = crate::rpc::call::<_, ()>(T::PATH, &ops).await {
+                web_sys::console::warn_1(&format!("synced state mutation failed: {e}").into());
+            }
+        });
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde::Deserialize;
+    use tower::ServiceExt;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Board {
+        cards: Vec<String>,
+    }
+
+    impl SyncedState for Board {
+        const PATH: &'static str = "/api/sync/board";
+    }
+
+    #[tokio::test]
+    async fn mutations_apply_and_conflicts_are_rejected() {
+        let mut rx = Board::sender().subscribe();
+        let app: axum::Router = Board::router();
+        let post = |body: &'static str| {
+            Request::post(Board::PATH)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(post(r#"[{"op":"add","path":"/cards/-","value":"todo"}]"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(rx.has_changed().unwrap());
+        assert_eq!(Board::get().cards, ["todo"]);
+
+        let response = app
+            .oneshot(post(r#"[{"op":"remove","path":"/cards/7"}]"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(rx.borrow_and_update().cards, ["todo"]);
+    }
+
+    #[tokio::test]
+    async fn read_only_router_rejects_mutations() {
+        #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+        struct Status {
+            up: bool,
+        }
+        impl SyncedState for Status {
+            const PATH: &'static str = "/api/sync/status";
+        }
+
+        Status::set(Status { up: true });
+        let app: axum::Router = Status::read_only_router();
+        let response = app
+            .oneshot(
+                Request::post(Status::PATH)
+                    .header("content-type", "application/json")
+                    .body(Body::from("[]"))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert!(Status::get().up);
+    }
+}
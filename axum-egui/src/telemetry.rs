@@ -0,0 +1,414 @@
+//! Memory and frame-time telemetry of the WASM client.
+//!
+//! Heavy egui screens regress quietly: a table that allocated a little more
+//! each release, a panel that takes a few more milliseconds per frame. The
+//! client keeps the last [`FRAME_WINDOW`] frame times and reads the size of
+//! the WASM memory; [`overlay`] shows both in a corner of the app, and the
+//! opt-in reporter sends them to [`TELEMETRY_PATH`] with the app version,
+//! where the server hands them to a [`TelemetrySink`]:
+//!
+//! ```ignore
+//! // Server
+//! let app = Router::new().merge(telemetry::router(telemetry::TracingSink));
+//!
+//! // Client, once at startup
+//! telemetry::start_reporting(ReporterConfig::new(env!("CARGO_PKG_VERSION")));
+//!
+//! // Client, in `App::update`
+//! let _frame = telemetry::FrameTimer::start();
+//! // ... or, with eframe: telemetry::record_frame(frame.info().cpu_usage)
+//! telemetry::overlay(ctx);
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Path of the endpoint served by [`router`].
+pub const TELEMETRY_PATH: &str = "/api/axum-egui/telemetry";
+
+/// Number of recent frames the client keeps.
+pub const FRAME_WINDOW: usize = 240;
+
+/// Summary of recent frame times, in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameStats {
+    /// Number of frames summarized.
+    pub frames: u32,
+    /// Average frame time.
+    pub mean_ms: f32,
+    /// 95th percentile frame time.
+    pub p95_ms: f32,
+    /// Slowest frame.
+    pub max_ms: f32,
+}
+
+impl FrameStats {
+    /// Summarize frame times given in milliseconds.
+    pub fn from_samples(samples_ms: &[f32]) -> Self {
+        if samples_ms.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples_ms.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let p95 = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+        Self {
+            frames: sorted.len() as u32,
+            mean_ms: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            p95_ms: sorted[p95],
+            max_ms: sorted[sorted.len() - 1],
+        }
+    }
+}
+
+/// What the reporter sends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryReport {
+    /// Version of the app, as given to the reporter.
+    pub version: String,
+    /// Size of the WASM linear memory in bytes.
+    pub memory_bytes: u64,
+    /// Frame times since the previous report, up to [`FRAME_WINDOW`].
+    pub frames: FrameStats,
+    /// When the report was made, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{TELEMETRY_PATH, TelemetryReport};
+    use axum::Router;
+    use axum::body::Bytes;
+    use axum::http::StatusCode;
+    use axum::http::request::Parts;
+    use axum::routing::post;
+    use std::sync::Arc;
+
+    /// Receives the reports sent by clients. Implemented for closures.
+    pub trait TelemetrySink: Send + Sync + 'static {
+        /// Store a report sent with `request`.
+        fn record(&self, report: TelemetryReport, request: &Parts);
+    }
+
+    impl<F> TelemetrySink for F
+    where
+        F: Fn(TelemetryReport, &Parts) + Send + Sync + 'static,
+    {
+        fn record(&self, report: TelemetryReport, request: &Parts) {
+            self(report, request)
+        }
+    }
+
+    /// Logs every report with `tracing` at info level.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct TracingSink;
+
+    impl TelemetrySink for TracingSink {
+        fn record(&self, report: TelemetryReport, _request: &Parts) {
+            tracing::info!(
+                target: "axum_egui::telemetry",
+                version = %report.version,
+                memory_bytes = report.memory_bytes,
+                frames = report.frames.frames,
+                mean_ms = report.frames.mean_ms,
+                p95_ms = report.frames.p95_ms,
+                max_ms = report.frames.max_ms,
+            );
+        }
+    }
+
+    /// The endpoint at [`TELEMETRY_PATH`], feeding `sink`.
+    ///
+    /// Reports are sent as beacons (`text/plain`), so the body is parsed as
+    /// JSON whatever its content type. Invalid reports get
+    /// `400 Bad Request`.
+    pub fn router<S>(sink: impl TelemetrySink) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let sink: Arc<dyn TelemetrySink> = Arc::new(sink);
+        Router::new().route(
+            TELEMETRY_PATH,
+            post(move |parts: Parts, body: Bytes| {
+                let sink = sink.clone();
+                async move {
+                    let Ok(report) = serde_json::from_slice::<TelemetryReport>(&body) else {
+                        return StatusCode::BAD_REQUEST;
+                    };
+                    sink.record(report, &parts);
+                    StatusCode::NO_CONTENT
+                }
+            }),
+        )
+    }
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{FRAME_WINDOW, FrameStats, TELEMETRY_PATH, TelemetryReport};
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::time::Duration;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+
+    struct Frames {
+        /// The last [`FRAME_WINDOW`] frame times, in milliseconds.
+        recent: VecDeque<f32>,
+        /// Frames recorded since the last report.
+        unreported: usize,
+    }
+
+    thread_local! {
+        static FRAMES: RefCell<Frames> = const {
+            RefCell::new(Frames {
+                recent: VecDeque::new(),
+                unreported: 0,
+            })
+        };
+    }
+
+    /// Size of the WASM linear memory in bytes. It only grows: this is the
+    /// high-water mark of the app's heap.
+    pub fn memory_bytes() -> u64 {
+        wasm_bindgen::memory()
+            .unchecked_into::<js_sys::WebAssembly::Memory>()
+            .buffer()
+            .unchecked_into::<js_sys::ArrayBuffer>()
+            .byte_length() as u64
+    }
+
+    /// Record the time one frame took, e.g. eframe's
+    /// `frame.info().cpu_usage` (in seconds; `None` is ignored).
+    pub fn record_frame(seconds: Option<f32>) {
+        let Some(seconds) = seconds else {
+            return;
+        };
+        FRAMES.with_borrow_mut(|frames| {
+            if frames.recent.len() == FRAME_WINDOW {
+                frames.recent.pop_front();
+            }
+            frames.recent.push_back(seconds * 1000.0);
+            frames.unreported += 1;
+        });
+    }
+
+    /// The recorded frame times, oldest first, in milliseconds.
+    pub fn frame_times() -> Vec<f32> {
+        FRAMES.with_borrow(|frames| frames.recent.iter().copied().collect())
+    }
+
+    /// Summary of the recorded frame times.
+    pub fn frame_stats() -> FrameStats {
+        FRAMES.with_borrow_mut(|frames| FrameStats::from_samples(frames.recent.make_contiguous()))
+    }
+
+    fn now_ms() -> f64 {
+        web_sys::window()
+            .and_then(|window| window.performance())
+            .map(|performance| performance.now())
+            .unwrap_or_else(js_sys::Date::now)
+    }
+
+    /// Records the time from [`start`](Self::start) until it is dropped as
+    /// one frame. Create it at the top of `update`.
+    #[derive(Debug)]
+    #[must_use = "the frame is recorded when the timer is dropped"]
+    pub struct FrameTimer {
+        start_ms: f64,
+    }
+
+    impl FrameTimer {
+        /// Start timing a frame.
+        pub fn start() -> Self {
+            Self { start_ms: now_ms() }
+        }
+    }
+
+    impl Drop for FrameTimer {
+        fn drop(&mut self) {
+            record_frame(Some(((now_ms() - self.start_ms) / 1000.0) as f32));
+        }
+    }
+
+    /// Reporting settings for [`start_reporting`].
+    #[derive(Debug, Clone)]
+    pub struct ReporterConfig {
+        version: String,
+        interval: Duration,
+    }
+
+    impl ReporterConfig {
+        /// Report as app `version`, e.g. `env!("CARGO_PKG_VERSION")`, so
+        /// reports of different releases can be compared.
+        pub fn new(version: impl Into<String>) -> Self {
+            Self {
+                version: version.into(),
+                interval: Duration::from_secs(60),
+            }
+        }
+
+        /// How often to report (default: a minute).
+        pub fn interval(mut self, interval: Duration) -> Self {
+            self.interval = interval;
+            self
+        }
+    }
+
+    /// Send a [`TelemetryReport`] to [`TELEMETRY_PATH`] every interval, and
+    /// when the page is hidden. Reporting can't be stopped; call once.
+    pub fn start_reporting(config: ReporterConfig) {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let version = config.version;
+        let report = move || report(&version);
+        let on_interval = Closure::<dyn FnMut()>::new(report.clone());
+        let _ = window.set_interval_with_callback_and_timeout_and_arguments_0(
+            on_interval.as_ref().unchecked_ref(),
+            config.interval.as_millis().try_into().unwrap_or(i32::MAX),
+        );
+        on_interval.forget();
+
+        let on_hide = Closure::<dyn FnMut()>::new(report);
+        let _ =
+            window.add_event_listener_with_callback("pagehide", on_hide.as_ref().unchecked_ref());
+        on_hide.forget();
+    }
+
+    /// Send a report of the frames since the previous one, if there were any.
+    fn report(version: &str) {
+        let frames = FRAMES.with_borrow_mut(|frames| {
+            let unreported = std::mem::take(&mut frames.unreported).min(frames.recent.len());
+            let recent = frames.recent.make_contiguous();
+            FrameStats::from_samples(&recent[recent.len() - unreported..])
+        });
+        if frames.frames == 0 {
+            return;
+        }
+        let report = TelemetryReport {
+            version: version.to_string(),
+            memory_bytes: memory_bytes(),
+            frames,
+            timestamp_ms: js_sys::Date::now() as u64,
+        };
+        let (Some(window), Ok(json)) = (web_sys::window(), serde_json::to_string(&report)) else {
+            return;
+        };
+        let _ = window
+            .navigator()
+            .send_beacon_with_opt_str(TELEMETRY_PATH, Some(&json));
+    }
+
+    /// Show memory and frame times in the bottom-right corner, with a graph
+    /// of the recent frames.
+    #[cfg(feature = "egui")]
+    pub fn overlay(ctx: &egui::Context) {
+        let stats = frame_stats();
+        let times = frame_times();
+        egui::Area::new(egui::Id::new("axum_egui_telemetry"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.monospace(format!(
+                        "mem {:.1} MiB",
+                        memory_bytes() as f64 / (1024.0 * 1024.0)
+                    ));
+                    ui.monospace(format!(
+                        "frame {:.1} ms, p95 {:.1}, max {:.1}",
+                        stats.mean_ms, stats.p95_ms, stats.max_ms
+                    ));
+
+                    let (rect, _) = ui.allocate_exact_size(
+                        egui::vec2(FRAME_WINDOW as f32, 32.0),
+                        egui::Sense::hover(),
+                    );
+                    // Scaled so 60 fps sits at half height
+                    let scale = rect.height() / (2.0 * 1000.0 / 60.0);
+                    let color = ui.visuals().text_color();
+                    let painter = ui.painter_at(rect);
+                    for (i, ms) in times.iter().enumerate() {
+                        let x = rect.left() + i as f32 + 0.5;
+                        let top = (rect.bottom() - ms * scale).max(rect.top());
+                        painter.line_segment(
+                            [egui::pos2(x, rect.bottom()), egui::pos2(x, top)],
+                            egui::Stroke::new(1.0_f32, color),
+                        );
+                    }
+                });
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_summarize_samples() {
+        assert_eq!(FrameStats::from_samples(&[]), FrameStats::default());
+
+        let samples: Vec<f32> = (1..=100).map(|ms| ms as f32).collect();
+        let stats = FrameStats::from_samples(&samples);
+        assert_eq!(stats.frames, 100);
+        assert_eq!(stats.mean_ms, 50.5);
+        assert_eq!(stats.p95_ms, 95.0);
+        assert_eq!(stats.max_ms, 100.0);
+
+        let stats = FrameStats::from_samples(&[16.0]);
+        assert_eq!((stats.p95_ms, stats.max_ms), (16.0, 16.0));
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn router_hands_reports_to_sink() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use std::sync::{Arc, Mutex};
+        use tower::ServiceExt;
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app: axum::Router = router({
+            let received = received.clone();
+            move |report: TelemetryReport, _: &axum::http::request::Parts| {
+                received.lock().unwrap().push(report)
+            }
+        });
+        let post = |body: String| {
+            Request::post(TELEMETRY_PATH)
+                .header("content-type", "text/plain;charset=UTF-8")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let report = TelemetryReport {
+            version: "1.2.0".into(),
+            memory_bytes: 64 << 20,
+            frames: FrameStats::from_samples(&[8.0, 12.0]),
+            timestamp_ms: 1,
+        };
+        let response = app
+            .clone()
+            .oneshot(post(serde_json::to_string(&report).unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(*received.lock().unwrap(), [report]);
+
+        let response = app.oneshot(post("nope".into())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,2 @@
+// Test JavaScript file
+console.log("test");
@@ -0,0 +1,207 @@
+//! Short-lived tickets carrying the arguments of streaming server functions.
+//!
+//! `EventSource` and `WebSocket` can only make GET requests, so by default the
+//! arguments of `#[server(sse)]`/`#[server(ws)]` functions are encoded in the
+//! query string. That breaks past URL length limits and writes the arguments
+//! into every access log along the way.
+//!
+//! With `post_params`, the client first POSTs the arguments to the same path
+//! and receives an opaque ticket, then opens the stream with only
+//! `?ticket=<id>` in the URL:
+//!
+//! ```ignore
+//! #[server(sse, post_params)]
+//! pub async fn search(query: SearchQuery) -> Result<ServerStream<Hit>, ServerFnError> {
+//!     // ...
+//! }
+//!
+//! // Both routes are needed: POST mints the ticket, GET opens the stream
+//! let app = Router::new().route(
+//!     "/api/search",
+//!     get(search_handler).post(search_params_handler),
+//! );
+//! ```
+//!
+//! Tickets are random, stored in memory on the server that issued them, and
+//! expire [`TICKET_TTL`] after they were last used. Redeeming does not consume
+//! them, so `EventSource`'s automatic reconnects keep working; a reconnect
+//! after a longer outage is rejected with `400`, and the function should be
+//! called again. Behind a load balancer, the POST and the stream must reach
+//! the same instance.
+
+use serde::{Deserialize, Serialize};
+
+/// Query parameter carrying a ticket id.
+pub const TICKET_QUERY_PARAM: &str = "ticket";
+
+/// Response body of a `{name}_params_handler`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamTicket {
+    /// Opaque ticket id to pass as [`TICKET_QUERY_PARAM`].
+    pub ticket: String,
+}
+
+impl ParamTicket {
+    /// The stream URL for `path` using this ticket.
+    pub fn url(&self, path: &str) -> String {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        format!("{path}{separator}{TICKET_QUERY_PARAM}={}", self.ticket)
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{ParamTicket, TICKET_QUERY_PARAM};
+    use crate::rpc::{ServerFnError, decode_query_args};
+    use axum::Json;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use serde::{Serialize, de::DeserializeOwned};
+    use std::collections::HashMap;
+    use std::sync::{Mutex, OnceLock};
+    use std::time::{Duration, Instant};
+
+    /// How long a ticket stays valid after it was issued or last redeemed.
+    pub const TICKET_TTL: Duration = Duration::from_secs(5 * 60);
+
+    /// Upper bound on stored tickets; the closest to expiry are evicted first.
+    const MAX_TICKETS: usize = 10_000;
+
+    struct Entry {
+        args: String,
+        expires: Instant,
+    }
+
+    fn store() -> &'static Mutex<HashMap<String, Entry>> {
+        static STORE: OnceLock<Mutex<HashMap<String, Entry>>> = OnceLock::new();
+        STORE.get_or_init(Default::default)
+    }
+
+    fn random_id() -> String {
+        let mut bytes = [0u8; 16];
+        getrandom::fill(&mut bytes).expect("failed to read OS randomness");
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Store JSON-encoded arguments and return a ticket for them.
+    pub fn issue(args_json: String) -> ParamTicket {
+        let now = Instant::now();
+        let mut store = store().lock().unwrap();
+        store.retain(|_, entry| entry.expires > now);
+        if store.len() >= MAX_TICKETS {
+            if let Some(oldest) = store
+                .iter()
+                .min_by_key(|(_, entry)| entry.expires)
+                .map(|(id, _)| id.clone())
+            {
+                store.remove(&oldest);
+            }
+        }
+
+        let ticket = random_id();
+        store.insert(
+            ticket.clone(),
+            Entry {
+                args: args_json,
+                expires: now + TICKET_TTL,
+            },
+        );
+        ParamTicket { ticket }
+    }
+
+    /// Look up the arguments for `ticket`, extending its lifetime.
+    pub fn redeem(ticket: &str) -> Option<String> {
+        let now = Instant::now();
+        let mut store = store().lock().unwrap();
+        let entry = store.get_mut(ticket).filter(|entry| entry.expires > now)?;
+        entry.expires = now + TICKET_TTL;
+        Some(entry.args.clone())
+    }
+
+    /// Issue a ticket for `args`, as the response of a `post_params` handler.
+    pub fn issue_response<Args: Serialize>(args: &Args) -> Response {
+        match serde_json::to_string(args) {
+            Ok(json) => Json(issue(json)).into_response(),
+            Err(e) => crate::error::error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ServerFnError::Serialization(e.to_string()),
+                None,
+            ),
+        }
+    }
+
+    /// Decode streaming server function arguments from a query string,
+    /// either inline (`?args=`) or by ticket (`?ticket=`).
+    pub fn decode_args<Args: DeserializeOwned>(query: Option<&str>) -> Result<Args, ServerFnError> {
+        let prefix = format!("{TICKET_QUERY_PARAM}=");
+        let ticket = query
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(&prefix));
+        match ticket {
+            Some(ticket) => {
+                let json = redeem(ticket).ok_or_else(|| {
+                    ServerFnError::Request("unknown or expired parameter ticket".to_string())
+                })?;
+                serde_json::from_str(&json)
+                    .map_err(|e| ServerFnError::Deserialization(e.to_string()))
+            }
+            None => decode_query_args(query),
+        }
+    }
+}
+
+/// POST `args` to `path` and return the stream URL carrying the ticket.
+#[cfg(feature = "client")]
+pub async fn ticket_url<Args: Serialize>(
+    path: &str,
+    args: &Args,
+) -> Result<String, crate::rpc::ServerFnError> {
+    let ticket: ParamTicket = crate::rpc::call(path, args).await?;
+    Ok(ticket.url(path))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::rpc::ServerFnError;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Args {
+        query: String,
+    }
+
+    #[test]
+    fn ticket_round_trip() {
+        let ticket = issue(r#"{"query":"rust"}"#.to_string());
+        let url = ticket.url("/api/search");
+        let query = url.split_once('?').unwrap().1;
+
+        let args: Args = decode_args(Some(query)).unwrap();
+        assert_eq!(args.query, "rust");
+        // Reusable for reconnects
+        assert!(decode_args::<Args>(Some(query)).is_ok());
+    }
+
+    #[test]
+    fn unknown_ticket_is_rejected() {
+        let err = decode_args::<Args>(Some("ticket=nope")).unwrap_err();
+        assert!(matches!(err, ServerFnError::Request(_)));
+    }
+
+    #[test]
+    fn inline_args_still_work() {
+        let url =
+            crate::rpc::encode_query_args("/api/search", &Args { query: "x".into() }).unwrap();
+        let args: Args = decode_args(url.split_once('?').map(|(_, q)| q)).unwrap();
+        assert_eq!(args.query, "x");
+    }
+
+    #[test]
+    fn ticket_ids_are_unique() {
+        assert_ne!(issue("{}".into()).ticket, issue("{}".into()).ticket);
+    }
+}
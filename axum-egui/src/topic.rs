@@ -0,0 +1,448 @@
+//! Topics that keep a bounded history, replayed to new subscribers.
+//!
+//! A [`LoggedTopic`] fans events out to every subscriber, like a broadcast
+//! channel, but also remembers the last ones. A new subscriber first receives
+//! that history, then the live tail, so a chat room or audit feed opened
+//! mid-way isn't empty. Each event carries a sequence number; a client that
+//! reconnects passes the last one it saw and gets only what it missed:
+//!
+//! ```ignore
+//! static ROOM: LazyLock<LoggedTopic<ChatMessage>> =
+//!     LazyLock::new(|| LoggedTopic::new(500).replay(50));
+//!
+//! #[server(sse)]
+//! pub async fn room(since: Option<u64>) -> Result<ServerStream<Logged<ChatMessage>>, ServerFnError> {
+//!     Ok(match since {
+//!         Some(cursor) => ROOM.subscribe_since(cursor),
+//!         None => ROOM.subscribe(),
+//!     })
+//! }
+//!
+//! ROOM.publish(ChatMessage { from, text });
+//! ```
+//!
+//! The history lives in memory, or also in a JSON Lines file with
+//! [`LoggedTopic::persistent`], so it survives restarts. Subscribers that
+//! fall behind catch up from the log instead of missing events, as long as
+//! the events are still in it.
+
+use serde::{Deserialize, Serialize};
+
+/// An event of a [`LoggedTopic`] with its sequence number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Logged<T> {
+    /// Position in the topic, starting at 1. Pass the last one seen to
+    /// `subscribe_since` to resume.
+    pub seq: u64,
+    /// The event.
+    pub event: T,
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::Logged;
+    use crate::backplane::{Backplane, Link};
+    use crate::stream::ServerStream;
+    use futures_util::{Stream, StreamExt};
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use std::collections::VecDeque;
+    use std::fs::{File, OpenOptions};
+    use std::io::{BufRead, BufReader, Write};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast;
+
+    /// A broadcast topic that keeps its last events. Cheap to clone.
+    pub struct LoggedTopic<T> {
+        inner: Arc<Inner<T>>,
+        replay: usize,
+        link: Option<Arc<Link>>,
+    }
+
+    impl<T> Clone for LoggedTopic<T> {
+        fn clone(&self) -> Self {
+            Self {
+                inner: self.inner.clone(),
+                replay: self.replay,
+                link: self.link.clone(),
+            }
+        }
+    }
+
+    impl<T> std::fmt::Debug for LoggedTopic<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let state = self.inner.state.lock().unwrap();
+            f.debug_struct("LoggedTopic")
+                .field("capacity", &self.inner.capacity)
+                .field("replay", &self.replay)
+                .field("len", &state.log.len())
+                .field("next_seq", &state.next_seq)
+                .field("backplane", &self.link.is_some())
+                .finish_non_exhaustive()
+        }
+    }
+
+    struct Inner<T> {
+        capacity: usize,
+        state: Mutex<State<T>>,
+        live: broadcast::Sender<Logged<T>>,
+    }
+
+    struct State<T> {
+        log: VecDeque<Logged<T>>,
+        next_seq: u64,
+        file: Option<LogFile>,
+    }
+
+    /// The JSON Lines file of a persistent topic, rewritten with only the
+    /// retained events once it holds twice as many.
+    struct LogFile {
+        path: PathBuf,
+        file: File,
+        lines: usize,
+    }
+
+    impl<T> LoggedTopic<T>
+    where
+        T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        /// A topic keeping its last `capacity` events in memory.
+        pub fn new(capacity: usize) -> Self {
+            Self::with_log(capacity, VecDeque::new(), None)
+        }
+
+        /// A topic keeping its last `capacity` events in memory and in the
+        /// JSON Lines file at `path`, which is created if missing and
+        /// otherwise loaded.
+        pub fn persistent(path: impl AsRef<Path>, capacity: usize) -> std::io::Result<Self> {
+            let path = path.as_ref().to_path_buf();
+            let mut log = VecDeque::new();
+            let mut lines = 0;
+            if path.exists() {
+                for line in BufReader::new(File::open(&path)?).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let logged: Logged<T> = serde_json::from_str(&line)
+                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                    log.push_back(logged);
+                    lines += 1;
+                    if log.len() > capacity {
+                        log.pop_front();
+                    }
+                }
+            }
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            Ok(Self::with_log(
+                capacity,
+                log,
+                Some(LogFile { path, file, lines }),
+            ))
+        }
+
+        fn with_log(capacity: usize, log: VecDeque<Logged<T>>, file: Option<LogFile>) -> Self {
+            let next_seq = log.back().map_or(1, |last| last.seq + 1);
+            let (live, _) = broadcast::channel(capacity.max(1));
+            Self {
+                inner: Arc::new(Inner {
+                    capacity,
+                    state: Mutex::new(State {
+                        log,
+                        next_seq,
+                        file,
+                    }),
+                    live,
+                }),
+                replay: capacity,
+                link: None,
+            }
+        }
+
+        /// How many of the retained events [`Self::subscribe`] replays;
+        /// all of them by default.
+        pub fn replay(mut self, events: usize) -> Self {
+            self.replay = events;
+            self
+        }
+
+        /// Also publish the events of this topic on the other instances
+        /// sharing `backplane`, and theirs here, over `channel` (unique per
+        /// topic). Must be called in a runtime.
+        pub fn backplane(
+            mut self,
+            backplane: Arc<dyn Backplane>,
+            channel: impl Into<String>,
+        ) -> Self {
+            let local = self.clone();
+            self.link = Some(Arc::new(Link::new(backplane, channel, move |event| {
+                local.publish_local(event);
+            })));
+            self
+        }
+
+        /// Append `event` and send it to the subscribers, on every instance
+        /// if the topic has a [backplane](Self::backplane). Returns its
+        /// sequence number here.
+        pub fn publish(&self, event: T) -> u64 {
+            if let Some(link) = &self.link {
+                link.send(&event);
+            }
+            self.publish_local(event)
+        }
+
+        fn publish_local(&self, event: T) -> u64 {
+            let mut state = self.inner.state.lock().unwrap();
+            let logged = Logged {
+                seq: state.next_seq,
+                event,
+            };
+            state.next_seq += 1;
+            if let Some(file) = &mut state.file {
+                if let Err(e) = file.append(&logged) {
+                    tracing::error!(path = %file.path.display(), "failed to persist topic event: {e}");
+                }
+            }
+            state.log.push_back(logged.clone());
+            if state.log.len() > self.inner.capacity {
+                state.log.pop_front();
+            }
+            let State { log, file, .. } = &mut *state;
+            if let Some(file) = file {
+                if file.lines >= 2 * self.inner.capacity.max(1) {
+                    if let Err(e) = file.compact(log) {
+                        tracing::error!(path = %file.path.display(), "failed to compact topic log: {e}");
+                    }
+                }
+            }
+            let seq = logged.seq;
+            // Sent under the lock, so subscribers see events in order
+            let _ = self.inner.live.send(logged);
+            seq
+        }
+
+        /// Publish every item of `source`, e.g. database change
+        /// notifications (see `axum_egui::postgres`), on a background task
+        /// that ends with the source.
+        pub fn forward<S>(&self, source: S) -> tokio::task::JoinHandle<()>
+        where
+            S: Stream<Item = T> + Send + 'static,
+        {
+            let topic = self.clone();
+            tokio::spawn(async move {
+                let mut source = std::pin::pin!(source);
+                while let Some(event) = source.next().await {
+                    topic.publish(event);
+                }
+            })
+        }
+
+        /// The retained events, oldest first.
+        pub fn history(&self) -> Vec<Logged<T>> {
+            self.inner
+                .state
+                .lock()
+                .unwrap()
+                .log
+                .iter()
+                .cloned()
+                .collect()
+        }
+
+        /// The sequence number of the last event, 0 before the first one.
+        pub fn last_seq(&self) -> u64 {
+            self.inner.state.lock().unwrap().next_seq - 1
+        }
+
+        /// The last [`Self::replay`] events, then the live tail.
+        pub fn subscribe(&self) -> ServerStream<Logged<T>> {
+            self.subscribe_with(|log| log.len().saturating_sub(self.replay))
+        }
+
+        /// The retained events after `cursor`, then the live tail. Events
+        /// already dropped from the log are skipped.
+        pub fn subscribe_since(&self, cursor: u64) -> ServerStream<Logged<T>> {
+            self.subscribe_with(|log| log.partition_point(|logged| logged.seq <= cursor))
+        }
+
+        fn subscribe_with(
+            &self,
+            first: impl FnOnce(&VecDeque<Logged<T>>) -> usize,
+        ) -> ServerStream<Logged<T>> {
+            let (backlog, live, last) = {
+                let state = self.inner.state.lock().unwrap();
+                let first = first(&state.log);
+                let backlog: VecDeque<_> = state.log.range(first..).cloned().collect();
+                (backlog, self.inner.live.subscribe(), state.next_seq - 1)
+            };
+            let topic = self.clone();
+            let events = futures_util::stream::unfold(
+                (backlog, live, last),
+                move |(mut pending, mut live, mut last)| {
+                    let topic = topic.clone();
+                    async move {
+                        loop {
+                            if let Some(logged) = pending.pop_front() {
+                                last = last.max(logged.seq);
+                                return Some((logged, (pending, live, last)));
+                            }
+                            match live.recv().await {
+                                Ok(logged) if logged.seq <= last => {}
+                                Ok(logged) => pending.push_back(logged),
+                                // Fell behind; catch up from the log
+                                Err(broadcast::error::RecvError::Lagged(_)) => {
+                                    let state = topic.inner.state.lock().unwrap();
+                                    pending
+                                        .extend(state.log.iter().filter(|l| l.seq > last).cloned());
+                                }
+                                Err(broadcast::error::RecvError::Closed) => return None,
+                            }
+                        }
+                    }
+                },
+            );
+            ServerStream::new(events)
+        }
+    }
+
+    impl LogFile {
+        fn append<T: Serialize>(&mut self, logged: &Logged<T>) -> std::io::Result<()> {
+            let mut line = serde_json::to_vec(logged)?;
+            line.push(b'\n');
+            self.file.write_all(&line)?;
+            self.lines += 1;
+            Ok(())
+        }
+
+        fn compact<T: Serialize>(&mut self, log: &VecDeque<Logged<T>>) -> std::io::Result<()> {
+            let tmp = self.path.with_extension("tmp");
+            let mut file = File::create(&tmp)?;
+            for logged in log {
+                let mut line = serde_json::to_vec(logged)?;
+                line.push(b'\n');
+                file.write_all(&line)?;
+            }
+            file.sync_all()?;
+            std::fs::rename(&tmp, &self.path)?;
+            self.file = OpenOptions::new().append(true).open(&self.path)?;
+            self.lines = log.len();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    async fn next<T: Clone + Send + 'static>(
+        stream: &mut crate::stream::ServerStream<Logged<T>>,
+    ) -> (u64, T) {
+        let logged = stream.next().await.unwrap().unwrap();
+        (logged.seq, logged.event)
+    }
+
+    #[tokio::test]
+    async fn replays_history_then_live_tail() {
+        let topic = LoggedTopic::new(3).replay(2);
+        for i in 1..=4 {
+            topic.publish(i);
+        }
+        assert_eq!(topic.last_seq(), 4);
+        let retained: Vec<_> = topic.history().iter().map(|l| l.event).collect();
+        assert_eq!(retained, [2, 3, 4]);
+
+        let mut latest = topic.subscribe();
+        let mut resumed = topic.subscribe_since(2);
+        let mut from_start = topic.subscribe_since(0);
+        topic.publish(5);
+
+        assert_eq!(next(&mut latest).await, (3, 3));
+        assert_eq!(next(&mut latest).await, (4, 4));
+        assert_eq!(next(&mut latest).await, (5, 5));
+        assert_eq!(next(&mut resumed).await, (3, 3));
+        // Event 1 has left the log
+        assert_eq!(next(&mut from_start).await, (2, 2));
+    }
+
+    #[tokio::test]
+    async fn lagging_subscribers_catch_up_from_the_log() {
+        let topic = LoggedTopic::new(8).replay(0);
+        let mut slow = topic.subscribe();
+        // More than the live channel holds
+        for i in 1..=12 {
+            topic.publish(i);
+        }
+        let mut seen = Vec::new();
+        for _ in 0..8 {
+            seen.push(next(&mut slow).await.0);
+        }
+        assert_eq!(seen, (5..=12).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn forwards_a_source_stream() {
+        let topic = LoggedTopic::new(8);
+        let mut events = topic.subscribe();
+        topic
+            .forward(futures_util::stream::iter(
+                ["insert", "update"].map(String::from),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(next(&mut events).await, (1, "insert".to_string()));
+        assert_eq!(next(&mut events).await, (2, "update".to_string()));
+    }
+
+    #[tokio::test]
+    async fn backplanes_relay_events_between_instances() {
+        use crate::backplane::{Backplane, MemoryBackplane};
+        use std::sync::Arc;
+
+        let backplane: Arc<dyn Backplane> = Arc::new(MemoryBackplane::new());
+        let a = LoggedTopic::<String>::new(8).backplane(backplane.clone(), "test:room");
+        let b = LoggedTopic::<String>::new(8).backplane(backplane, "test:room");
+        for _ in 0..4 {
+            tokio::task::yield_now().await;
+        }
+        let mut on_b = b.subscribe();
+        a.publish("hello".to_string());
+        assert_eq!(next(&mut on_b).await, (1, "hello".to_string()));
+        // Not echoed back to the sender
+        assert_eq!(a.last_seq(), 1);
+    }
+
+    #[tokio::test]
+    async fn persistent_topics_reload_and_compact() {
+        let path = std::env::temp_dir().join(format!(
+            "axum-egui-topic-{}-{}.jsonl",
+            std::process::id(),
+            line!()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let topic = LoggedTopic::persistent(&path, 2).unwrap();
+        for word in ["a", "b", "c", "d", "e"] {
+            topic.publish(word.to_string());
+        }
+        drop(topic);
+        // Compacted after the fourth event, then one more appended
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert_eq!(lines, 3);
+
+        let topic = LoggedTopic::<String>::persistent(&path, 2).unwrap();
+        let retained: Vec<_> = topic.history().into_iter().map(|l| l.event).collect();
+        assert_eq!(retained, ["d", "e"]);
+        assert_eq!(topic.publish("f".to_string()), 6);
+        let _ = std::fs::remove_file(&path);
+    }
+}
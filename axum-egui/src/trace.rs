@@ -0,0 +1,321 @@
+//! W3C Trace Context propagation from the browser into server functions.
+//!
+//! Every server function call carries a [`TRACEPARENT_HEADER`], so
+//! distributed tracing tools can connect a click in the UI to the spans the
+//! call causes on the server, down to the database.
+//!
+//! On the client, calls continue the innermost [`with_trace`], else the
+//! trace of the page, else start a trace of their own. The page's trace is
+//! injected by the server as the [`SLOT`] slot, usually a child of the trace
+//! that loaded the page:
+//!
+//! ```ignore
+//! // Server
+//! async fn index(trace: TraceContext) -> App<MyApp, Assets> {
+//!     App::new(state).slot(trace::SLOT, &trace.child())
+//! }
+//!
+//! // Client: both calls of this click share one trace
+//! spawn_local(with_trace(TraceContext::new(), async move {
+//!     let order = place_order(cart).await?;
+//!     send_receipt(order.id).await
+//! }));
+//! ```
+//!
+//! On the server, the handlers `#[server]` generates run RPC functions in a
+//! `server_fn` span recording the `trace_id` and caller's `parent_id`, and
+//! [`current`] returns the caller's context, to continue the trace in
+//! outgoing requests (`current().map(|t| t.child())`) or hand it to an
+//! OpenTelemetry layer. `TraceContext` is also an axum extractor; requests
+//! without a valid header get a new trace.
+//!
+//! Streams are not traced: browsers can't set headers on `EventSource` and
+//! WebSocket connections.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The W3C Trace Context header.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// The [slot](crate::slots) carrying the trace of the page.
+pub const SLOT: &str = "traceparent";
+
+/// A position in a distributed trace: the trace, and the span that is the
+/// parent of whatever happens next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct TraceContext {
+    /// The id of the whole trace, never zero.
+    pub trace_id: u128,
+    /// The id of the parent span, never zero.
+    pub span_id: u64,
+    /// Trace flags; bit 0 is "sampled".
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// A new, sampled trace.
+    pub fn new() -> Self {
+        let trace_id = (u128::from(random_id()) << 64) | u128::from(random_id());
+        Self {
+            trace_id,
+            span_id: random_id(),
+            flags: 0x01,
+        }
+    }
+
+    /// A span in the same trace, with this one as its parent.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_id(),
+            ..*self
+        }
+    }
+
+    /// Whether the trace is sampled.
+    pub fn sampled(&self) -> bool {
+        self.flags & 0x01 != 0
+    }
+
+    /// Parse a `traceparent` header value, `None` if it is invalid.
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut fields = header.trim().split('-');
+        let version = fields.next().filter(|v| v.len() == 2)?;
+        let trace_id = fields.next().filter(|id| id.len() == 32)?;
+        let span_id = fields.next().filter(|id| id.len() == 16)?;
+        let flags = fields.next().filter(|flags| flags.len() == 2)?;
+        // Version 00 has exactly four fields; later versions may add more
+        if version == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        let hex = |field: &str| {
+            field
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        if ![version, trace_id, span_id, flags].into_iter().all(hex) {
+            return None;
+        }
+        let context = Self {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+        };
+        (context.trace_id != 0 && context.span_id != 0).then_some(context)
+    }
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `traceparent` header value.
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+impl From<TraceContext> for String {
+    fn from(context: TraceContext) -> Self {
+        context.to_string()
+    }
+}
+
+impl TryFrom<String> for TraceContext {
+    type Error = String;
+
+    fn try_from(header: String) -> Result<Self, Self::Error> {
+        Self::parse(&header).ok_or_else(|| format!("invalid traceparent {header:?}"))
+    }
+}
+
+/// A random, non-zero id.
+#[cfg(feature = "server")]
+fn random_id() -> u64 {
+    let mut bytes = [0; 8];
+    getrandom::fill(&mut bytes).expect("system random number generator");
+    u64::from_le_bytes(bytes).max(1)
+}
+
+/// A random, non-zero id. Trace ids need to be unique, not secret.
+#[cfg(all(feature = "client", not(feature = "server")))]
+fn random_id() -> u64 {
+    (0..4)
+        .fold(0, |id, _| {
+            (id << 16) | (js_sys::Math::random() * 65536.0) as u64
+        })
+        .max(1)
+}
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{TRACEPARENT_HEADER, TraceContext};
+    use axum::extract::FromRequestParts;
+    use axum::http::HeaderMap;
+    use axum::http::request::Parts;
+    use std::convert::Infallible;
+    use std::future::Future;
+    use tracing::Instrument;
+
+    impl TraceContext {
+        /// The context in the [`TRACEPARENT_HEADER`] of `headers`.
+        pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+            headers
+                .get(TRACEPARENT_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Self::parse)
+        }
+    }
+
+    /// The caller's trace, or a new one if the request has none.
+    impl<S: Send + Sync> FromRequestParts<S> for TraceContext {
+        type Rejection = Infallible;
+
+        async fn from_request_parts(
+            parts: &mut Parts,
+            _state: &S,
+        ) -> Result<Self, Self::Rejection> {
+            Ok(Self::from_headers(&parts.headers).unwrap_or_default())
+        }
+    }
+
+    /// The trace of the server function currently running, if its caller
+    /// sent one.
+    pub fn current() -> Option<TraceContext> {
+        crate::context::current().and_then(|ctx| TraceContext::from_headers(ctx.headers()))
+    }
+
+    /// Run the server function `function` in a `server_fn` span joining the
+    /// caller's `trace`. Used by the handlers `#[server]` generates.
+    pub async fn instrument<F: Future>(
+        function: &'static str,
+        trace: Option<TraceContext>,
+        future: F,
+    ) -> F::Output {
+        let span = match trace {
+            Some(trace) => tracing::info_span!(
+                "server_fn",
+                function,
+                trace_id = %format_args!("{:032x}", trace.trace_id),
+                parent_id = %format_args!("{:016x}", trace.span_id),
+            ),
+            None => tracing::info_span!("server_fn", function),
+        };
+        future.instrument(span).await
+    }
+}
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{SLOT, TraceContext};
+    use std::cell::Cell;
+    use std::future::Future;
+
+    thread_local! {
+        static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+        static PAGE: Option<TraceContext> = crate::slots::read(SLOT);
+    }
+
+    /// Run `future` with calls made inside it continuing `trace`.
+    pub async fn with_trace<F: Future>(trace: TraceContext, future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        std::future::poll_fn(|cx| {
+            let outer = CURRENT.replace(Some(trace));
+            let poll = future.as_mut().poll(cx);
+            CURRENT.set(outer);
+            poll
+        })
+        .await
+    }
+
+    /// The trace calls made now continue: the innermost [`with_trace`], or
+    /// the trace of the page.
+    pub fn current_trace() -> Option<TraceContext> {
+        CURRENT.get().or_else(|| PAGE.with(|page| *page))
+    }
+
+    /// The `traceparent` of a call made now: a child of
+    /// [`current_trace`], or a new trace.
+    pub(crate) fn outgoing() -> TraceContext {
+        current_trace().map_or_else(TraceContext::new, |trace| trace.child())
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use axum::extract::FromRequestParts;
+
+    #[test]
+    fn traceparent_round_trip() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let trace = TraceContext::parse(header).unwrap();
+        assert_eq!(trace.trace_id, 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(trace.span_id, 0x00f067aa0ba902b7);
+        assert!(trace.sampled());
+        assert_eq!(trace.to_string(), header);
+
+        let child = trace.child();
+        assert_eq!(child.trace_id, trace.trace_id);
+        assert_ne!(child.span_id, trace.span_id);
+
+        let json = serde_json::to_string(&trace).unwrap();
+        assert_eq!(json, format!("\"{header}\""));
+        assert_eq!(serde_json::from_str::<TraceContext>(&json).unwrap(), trace);
+    }
+
+    #[test]
+    fn invalid_traceparents_are_rejected() {
+        for header in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+        ] {
+            assert_eq!(TraceContext::parse(header), None, "{header}");
+        }
+        // Later versions may carry more fields
+        assert!(
+            TraceContext::parse("01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra")
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn extractor_continues_or_starts_a_trace() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00";
+        let (mut parts, ()) = axum::http::Request::get("/")
+            .header(TRACEPARENT_HEADER, header)
+            .body(())
+            .unwrap()
+            .into_parts();
+        let trace = TraceContext::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!(trace.to_string(), header);
+        assert!(!trace.sampled());
+
+        let (mut parts, ()) = axum::http::Request::get("/").body(()).unwrap().into_parts();
+        let trace = TraceContext::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert!(trace.trace_id != 0 && trace.sampled());
+    }
+}
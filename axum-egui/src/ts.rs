@@ -0,0 +1,247 @@
+//! TypeScript definitions for server functions (`ts` feature).
+//!
+//! With this feature enabled, e.g. through a feature of the crate defining
+//! the server functions, `#[server]` derives [`TS`] for the generated args
+//! struct and registers the endpoint here. [`export`] then writes one `.ts` file per type (via
+//! [`ts-rs`](https://docs.rs/ts-rs)) plus an `endpoints.ts` describing every
+//! endpoint, so a parallel JS surface can call the same API with type safety:
+//!
+//! ```ignore
+//! // shared/Cargo.toml: ts = ["axum-egui/ts"]
+//! #[derive(Serialize, Deserialize, axum_egui::ts::TS)]
+//! pub struct Hit { pub title: String, pub score: f32 }
+//!
+//! #[server(sse)]
+//! pub async fn search(query: String) -> Result<ServerStream<Hit>, ServerFnError> { ... }
+//!
+//! // e.g. in a test or a `--export-ts` flag of the server binary:
+//! axum_egui::ts::export("web/src/api")?;
+//! ```
+//!
+//! ```ts
+//! import type { Endpoints } from "./api/endpoints";
+//! import { endpoints } from "./api/endpoints";
+//!
+//! type Hits = Endpoints["search"]["response"]; // Hit
+//! new EventSource(`${endpoints.search.path}?args=...`);
+//! ```
+//!
+//! Argument and response types must implement [`TS`] as well. For streaming
+//! functions, `response` is the item type; `ws` functions additionally list
+//! the client message type as `input`.
+
+use crate::registry::RouteKind;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+pub use ts_rs::{ExportError, TS};
+
+#[doc(hidden)]
+pub use ts_rs as __ts_rs;
+
+/// Name of the endpoint map written by [`export`].
+pub const ENDPOINTS_FILE: &str = "endpoints.ts";
+
+/// `(TypeScript name, file)` pairs to import from the export directory.
+type Imports = Vec<(String, PathBuf)>;
+
+/// A Rust type exposed to TypeScript.
+#[derive(Debug, Clone, Copy)]
+pub struct TsType {
+    name: fn() -> String,
+    export: fn(&Path) -> Result<Imports, ExportError>,
+}
+
+impl TsType {
+    /// Describe `T`.
+    pub const fn of<T: TS + ?Sized + 'static>() -> Self {
+        Self {
+            name: T::name,
+            export: export_type::<T>,
+        }
+    }
+
+    /// The TypeScript name of the type, e.g. `number` or `Array<Hit>`.
+    pub fn name(&self) -> String {
+        (self.name)()
+    }
+}
+
+/// A server function endpoint with TypeScript types, registered by the
+/// `#[server]` macro when the `ts` feature is enabled.
+#[derive(Debug, Clone, Copy)]
+pub struct TsEndpoint {
+    /// The function name.
+    pub name: &'static str,
+    /// The API path.
+    pub path: &'static str,
+    /// How the function is exposed.
+    pub kind: RouteKind,
+    /// The generated args struct.
+    pub args: TsType,
+    /// The response type (the item type for streaming functions).
+    pub response: TsType,
+    /// The client message type of `ws` functions.
+    pub input: Option<TsType>,
+}
+
+inventory::collect!(TsEndpoint);
+
+/// All registered endpoints, sorted by name.
+pub fn endpoints() -> Vec<&'static TsEndpoint> {
+    let mut endpoints: Vec<_> = inventory::iter::<TsEndpoint>.into_iter().collect();
+    endpoints.sort_by_key(|endpoint| (endpoint.name, endpoint.path));
+    endpoints
+}
+
+/// Write TypeScript definitions of all endpoints and their types to `dir`.
+///
+/// Returns the path of the generated [`ENDPOINTS_FILE`].
+pub fn export(dir: impl AsRef<Path>) -> Result<PathBuf, ExportError> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let mut imports = BTreeMap::new();
+    for endpoint in endpoints() {
+        let types = [Some(endpoint.args), Some(endpoint.response), endpoint.input];
+        for ty in types.into_iter().flatten() {
+            imports.extend((ty.export)(dir)?);
+        }
+    }
+
+    let path = dir.join(ENDPOINTS_FILE);
+    std::fs::write(&path, render(&imports)?)?;
+    Ok(path)
+}
+
+fn render(imports: &BTreeMap<String, PathBuf>) -> Result<String, ExportError> {
+    let mut out = String::from("// Generated by axum-egui. Do not edit.\n\n");
+    for (name, path) in imports {
+        let module = path.to_string_lossy().replace('\\', "/");
+        let module = module.trim_end_matches(".ts");
+        writeln!(out, "import type {{ {name} }} from \"./{module}\";")?;
+    }
+    if !imports.is_empty() {
+        out.push('\n');
+    }
+
+    out.push_str("export interface Endpoints {\n");
+    for endpoint in endpoints() {
+        write!(
+            out,
+            "  {name:?}: {{ path: {path:?}; method: {method:?}; kind: {kind:?}; args: {args}; response: {response}",
+            name = endpoint.name,
+            path = endpoint.path,
+            method = endpoint.kind.method(),
+            kind = kind_name(endpoint.kind),
+            args = endpoint.args.name(),
+            response = endpoint.response.name(),
+        )?;
+        if let Some(input) = endpoint.input {
+            write!(out, "; input: {}", input.name())?;
+        }
+        out.push_str(" };\n");
+    }
+    out.push_str("}\n\nexport const endpoints = {\n");
+    for endpoint in endpoints() {
+        writeln!(
+            out,
+            "  {:?}: {{ path: {:?}, method: {:?}, kind: {:?} }},",
+            endpoint.name,
+            endpoint.path,
+            endpoint.kind.method(),
+            kind_name(endpoint.kind),
+        )?;
+    }
+    out.push_str("} as const;\n");
+    Ok(out)
+}
+
+fn kind_name(kind: RouteKind) -> &'static str {
+    match kind {
+        RouteKind::Rpc => "rpc",
+        RouteKind::Get => "get",
+        RouteKind::Sse => "sse",
+        RouteKind::Ws => "ws",
+    }
+}
+
+/// Export `T` and its dependencies, returning the `(name, file)` pairs to
+/// import. Primitives and containers (`number`, `Array<Hit>`) have no file
+/// of their own, so only their dependencies are exported.
+fn export_type<T: TS + ?Sized + 'static>(dir: &Path) -> Result<Imports, ExportError> {
+    if let Some(path) = T::output_path() {
+        T::export_all_to(dir)?;
+        return Ok(vec![(T::ident(), path)]);
+    }
+
+    // Containers list their element types as generics, not dependencies
+    struct Visit<'a> {
+        dir: &'a Path,
+        imports: Imports,
+        result: Result<(), ExportError>,
+    }
+    impl ts_rs::TypeVisitor for Visit<'_> {
+        fn visit<D: TS + 'static + ?Sized>(&mut self) {
+            if let (Ok(()), Some(path)) = (&self.result, D::output_path()) {
+                self.result = D::export_all_to(self.dir);
+                self.imports.push((D::ident(), path));
+            }
+        }
+    }
+    let mut visit = Visit {
+        dir,
+        imports: Vec::new(),
+        result: Ok(()),
+    };
+    T::visit_dependencies(&mut visit);
+    T::visit_generics(&mut visit);
+    visit.result.map(|()| visit.imports)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[derive(TS)]
+    #[ts(crate = "crate::ts::__ts_rs")]
+    struct TsTestArgs {
+        #[allow(dead_code)]
+        query: String,
+    }
+
+    #[derive(TS)]
+    #[ts(crate = "crate::ts::__ts_rs")]
+    struct TsTestHit {
+        #[allow(dead_code)]
+        score: f32,
+    }
+
+    inventory::submit! {
+        TsEndpoint {
+            name: "ts_test_search",
+            path: "/api/ts_test_search",
+            kind: RouteKind::Sse,
+            args: TsType::of::<TsTestArgs>(),
+            response: TsType::of::<Vec<TsTestHit>>(),
+            input: None,
+        }
+    }
+
+    #[test]
+    fn exports_types_and_endpoint_map() {
+        let dir = std::env::temp_dir().join(format!("axum-egui-ts-{}", std::process::id()));
+        let path = export(&dir).unwrap();
+
+        let endpoints = std::fs::read_to_string(&path).unwrap();
+        assert!(endpoints.contains(r#"import type { TsTestArgs } from "./TsTestArgs";"#));
+        assert!(endpoints.contains(r#"import type { TsTestHit } from "./TsTestHit";"#));
+        assert!(endpoints.contains(
+            r#""ts_test_search": { path: "/api/ts_test_search"; method: "GET"; kind: "sse"; args: TsTestArgs; response: Array<TsTestHit> };"#
+        ));
+        assert!(dir.join("TsTestHit.ts").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
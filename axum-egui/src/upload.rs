@@ -0,0 +1,820 @@
+//! Uploads: server functions taking files, or the request body as a stream.
+//!
+//! # Files
+//!
+//! A server function with [`FileUpload`] arguments is called with a
+//! `multipart/form-data` body: one part per file, named after its argument,
+//! and an [`ARGS_PART`] with the other arguments as JSON. Files come from a
+//! `web_sys::File` of an `<input type="file">` or from egui's dropped files:
+//!
+//! ```ignore
+//! use axum_egui::upload::FileUpload;
+//!
+//! #[server]
+//! pub async fn set_avatar(user: UserId, image: FileUpload) -> Result<(), ServerFnError> {
+//!     avatars::store(user, &image.content_type, &image.data).await
+//! }
+//!
+//! // Client, in the egui update loop
+//! for file in &ctx.input(|i| i.raw.dropped_files.clone()) {
+//!     if let Some(image) = FileUpload::from_dropped(file) {
+//!         spawn_local(async move { set_avatar(user, image).await; });
+//!     }
+//! }
+//! ```
+//!
+//! The whole body is read before the function runs, within
+//! [`serve`](crate::serve)'s `body_limit`. For files too large to hold in
+//! memory, take a stream instead.
+//!
+//! # Streams
+//!
+//! A server function with an `impl Stream<Item = Bytes>` argument receives
+//! the request body chunk by chunk as it arrives, so a large file never has
+//! to fit in memory on either side. Its other arguments travel in the query
+//! string (see [`encode_query_args`](crate::rpc::encode_query_args)):
+//!
+//! ```ignore
+//! use axum_egui::upload::{Bytes, Stream};
+//! use futures_util::StreamExt;
+//!
+//! #[server]
+//! pub async fn upload(
+//!     name: String,
+//!     body: impl Stream<Item = Bytes>,
+//! ) -> Result<UploadId, ServerFnError> {
+//!     let mut file = storage::create(&name).await?;
+//!     let mut body = std::pin::pin!(body);
+//!     while let Some(chunk) = body.next().await {
+//!         file.write_all(&chunk).await?;
+//!     }
+//!     Ok(file.id())
+//! }
+//!
+//! // Client: any stream of chunks, e.g. read from a `File`
+//! let id = upload("report.csv".into(), chunks).await?;
+//! ```
+//!
+//! In the browser, the body is sent as a `ReadableStream` fed from the
+//! stream while the request is in flight. Browsers only stream request
+//! bodies over HTTP/2, so on plain HTTP pages, and in browsers without
+//! support, the stream is read to the end first and sent in one piece, as
+//! native clients always do.
+//!
+//! If the body breaks off (the client disconnects, or a limit layer stops
+//! it), the stream ends early and the call fails with an
+//! [`UPLOAD_INTERRUPTED`] error, whatever the function returned. Uploads are
+//! not subject to [`serve`](crate::serve)'s `body_limit`: the function
+//! decides how much to read.
+
+pub use bytes::Bytes;
+pub use futures_util::Stream;
+
+/// Content type of upload bodies.
+pub const UPLOAD_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Error code of calls whose body broke off.
+pub const UPLOAD_INTERRUPTED: &str = "upload_interrupted";
+
+/// Content type of calls to functions taking [`FileUpload`]s.
+pub const MULTIPART_CONTENT_TYPE: &str = "multipart/form-data";
+
+/// Name of the multipart part carrying the JSON of the arguments besides
+/// the files.
+pub const ARGS_PART: &str = "args";
+
+/// A file sent to a server function, as a part of a multipart body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileUpload {
+    /// The file's name on the client, without its directory.
+    pub file_name: String,
+    /// Its MIME type, `application/octet-stream` if unknown.
+    pub content_type: String,
+    /// Its contents.
+    pub data: Bytes,
+}
+
+impl FileUpload {
+    /// A file named `file_name` holding `data`, of type
+    /// `application/octet-stream`.
+    pub fn new(file_name: impl Into<String>, data: impl Into<Bytes>) -> Self {
+        Self {
+            file_name: file_name.into(),
+            content_type: UPLOAD_CONTENT_TYPE.to_string(),
+            data: data.into(),
+        }
+    }
+
+    /// Set the MIME type of the file.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = content_type.into();
+        self
+    }
+
+    /// A file dropped on the egui window, if its contents were loaded.
+    ///
+    /// In the browser they always are; native eframe apps get the file's
+    /// `path` instead, to read into [`FileUpload::new`].
+    #[cfg(feature = "egui")]
+    pub fn from_dropped(file: &egui::DroppedFile) -> Option<Self> {
+        let data = file.bytes.as_ref()?;
+        let upload = Self::new(file.name.clone(), Bytes::copy_from_slice(data));
+        Some(if file.mime.is_empty() {
+            upload
+        } else {
+            upload.content_type(file.mime.clone())
+        })
+    }
+}
+
+/// Encode the JSON `args` and `files` of a call as a multipart body,
+/// returning its content type and bytes.
+#[cfg(any(feature = "client", feature = "native-client", test))]
+pub(crate) fn encode_multipart(args: &[u8], files: &[(&str, FileUpload)]) -> (String, Vec<u8>) {
+    use std::hash::{BuildHasher, Hasher};
+
+    // A random boundary, drawn again in the unlikely case the content
+    // holds it
+    let boundary = std::iter::repeat_with(|| {
+        let seed = std::collections::hash_map::RandomState::new()
+            .build_hasher()
+            .finish();
+        format!("axum-egui-{seed:016x}")
+    })
+    .find(|boundary| {
+        std::iter::once(args)
+            .chain(files.iter().map(|(_, file)| file.data.as_ref()))
+            .all(|data| find(data, boundary.as_bytes()).is_none())
+    })
+    .expect("repeat_with is endless");
+
+    let mut body = Vec::new();
+    let mut part = |disposition: String, content_type: &str, data: &[u8]| {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; {disposition}\r\n\
+                Content-Type: {content_type}\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    };
+    part(format!("name=\"{ARGS_PART}\""), "application/json", args);
+    for (name, file) in files {
+        // Escaped like browsers do, so a name can't break out of its quotes
+        let file_name = file
+            .file_name
+            .replace('"', "%22")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A");
+        let content_type = if file.content_type.contains(['\r', '\n']) {
+            UPLOAD_CONTENT_TYPE
+        } else {
+            &file.content_type
+        };
+        part(
+            format!("name=\"{name}\"; filename=\"{file_name}\""),
+            content_type,
+            &file.data,
+        );
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    (
+        format!("{MULTIPART_CONTENT_TYPE}; boundary={boundary}"),
+        body,
+    )
+}
+
+/// Position of the first `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// ============================================================================
+// Client
+// ============================================================================
+
+#[cfg(feature = "client")]
+pub use client::*;
+
+#[cfg(feature = "client")]
+mod client {
+    use super::{Bytes, FileUpload, UPLOAD_CONTENT_TYPE};
+    use crate::rpc::{CallPriority, Encoding, PRIORITY_HEADER, ServerFnError};
+    use futures_channel::mpsc;
+    use futures_util::future::{self, Either};
+    use futures_util::{SinkExt, Stream, StreamExt};
+    use serde::{Serialize, de::DeserializeOwned};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    fn request_error(e: impl std::fmt::Debug) -> ServerFnError {
+        ServerFnError::Request(format!("{e:?}"))
+    }
+
+    thread_local! {
+        static STREAMING: bool = streaming_supported();
+    }
+
+    /// Call the upload function at `path` with `args` in the query string
+    /// and `body` as the request body, streamed where the browser can.
+    ///
+    /// Like [`rpc::call`](crate::rpc::call), it waits for a permit and has
+    /// the current priority.
+    pub async fn call<Args, Resp>(
+        path: &str,
+        args: &Args,
+        body: impl Stream<Item = Bytes>,
+    ) -> Result<Resp, ServerFnError>
+    where
+        Args: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let url = crate::rpc::encode_query_args(path, args)?;
+        let priority = crate::concurrency::current_priority();
+        let _permit = crate::concurrency::acquire(path, priority).await;
+        #[cfg(feature = "devtools")]
+        crate::devtools::inject().await?;
+
+        let init = post_init(UPLOAD_CONTENT_TYPE, priority)?;
+        let deadline = crate::rpc::Deadline::start();
+        init.set_signal(deadline.signal().as_ref());
+        let mut body = std::pin::pin!(body);
+        let result = async move {
+            let response = if STREAMING.with(|streaming| *streaming) {
+                // The browser pulls chunks from the channel while `pump` feeds
+                // it; the response may come before the body was read
+                let (mut sender, receiver) = mpsc::channel(1);
+                init.set_body(&readable_stream(receiver)?.into());
+                js_sys::Reflect::set(&init, &"duplex".into(), &"half".into())
+                    .map_err(request_error)?;
+                let request =
+                    web_sys::Request::new_with_str_and_init(&url, &init).map_err(request_error)?;
+                let send = std::pin::pin!(gloo_net::http::Request::from(request).send());
+                let pump = std::pin::pin!(async move {
+                    while let Some(chunk) = body.next().await {
+                        if sender.send(chunk).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+                match future::select(send, pump).await {
+                    Either::Left((response, _)) => response,
+                    Either::Right(((), send)) => send.await,
+                }
+            } else {
+                let mut buffer = Vec::new();
+                while let Some(chunk) = body.next().await {
+                    buffer.extend_from_slice(&chunk);
+                }
+                init.set_body(&js_sys::Uint8Array::from(buffer.as_slice()));
+                let request =
+                    web_sys::Request::new_with_str_and_init(&url, &init).map_err(request_error)?;
+                gloo_net::http::Request::from(request).send().await
+            };
+            let response = response.map_err(|e| ServerFnError::Request(e.to_string()))?;
+            crate::rpc::read_response(response, Encoding::Json).await
+        }
+        .await;
+        deadline.finish(result)
+    }
+
+    /// Call the function at `path` taking files, with `args` and `files`
+    /// (named after their arguments) in a multipart body.
+    ///
+    /// Like [`rpc::call`](crate::rpc::call), it waits for a permit and has
+    /// the current priority.
+    pub async fn call_multipart<Args, Resp>(
+        path: &str,
+        args: &Args,
+        files: impl IntoIterator<Item = (&'static str, FileUpload)>,
+    ) -> Result<Resp, ServerFnError>
+    where
+        Args: Serialize,
+        Resp: DeserializeOwned,
+    {
+        let args = Encoding::Json.encode(args)?;
+        let files: Vec<_> = files.into_iter().collect();
+        let (content_type, body) = super::encode_multipart(&args, &files);
+        let priority = crate::concurrency::current_priority();
+        let _permit = crate::concurrency::acquire(path, priority).await;
+        #[cfg(feature = "devtools")]
+        crate::devtools::inject().await?;
+
+        let init = post_init(&content_type, priority)?;
+        init.set_body(&js_sys::Uint8Array::from(body.as_slice()));
+        let deadline = crate::rpc::Deadline::start();
+        init.set_signal(deadline.signal().as_ref());
+        let request =
+            web_sys::Request::new_with_str_and_init(path, &init).map_err(request_error)?;
+        let result = async {
+            let response = gloo_net::http::Request::from(request)
+                .send()
+                .await
+                .map_err(|e| ServerFnError::Request(e.to_string()))?;
+            crate::rpc::read_response(response, Encoding::Json).await
+        }
+        .await;
+        deadline.finish(result)
+    }
+
+    impl FileUpload {
+        /// The contents of a file picked by the user, e.g. from the `files`
+        /// of an `<input type="file">`.
+        pub async fn from_file(file: &web_sys::File) -> Result<Self, ServerFnError> {
+            let buffer = wasm_bindgen_futures::JsFuture::from(file.array_buffer())
+                .await
+                .map_err(request_error)?;
+            let data = js_sys::Uint8Array::new(&buffer).to_vec();
+            let upload = Self::new(file.name(), data);
+            let content_type = file.type_();
+            Ok(if content_type.is_empty() {
+                upload
+            } else {
+                upload.content_type(content_type)
+            })
+        }
+    }
+
+    /// A POST with a body of `content_type`, answered in JSON, with the
+    /// headers of server function calls.
+    fn post_init(
+        content_type: &str,
+        priority: CallPriority,
+    ) -> Result<web_sys::RequestInit, ServerFnError> {
+        let headers = web_sys::Headers::new().map_err(request_error)?;
+        let traceparent = crate::trace::outgoing().to_string();
+        for (name, value) in [
+            ("Content-Type", content_type),
+            ("Accept", Encoding::Json.content_type()),
+            (PRIORITY_HEADER, priority.header_value()),
+            (crate::trace::TRACEPARENT_HEADER, &traceparent),
+        ] {
+            headers.set(name, value).map_err(request_error)?;
+        }
+        for (name, value) in crate::rpc::current_headers() {
+            headers.set(name, value).map_err(request_error)?;
+        }
+        let init = web_sys::RequestInit::new();
+        init.set_method("POST");
+        init.set_headers(&headers);
+        Ok(init)
+    }
+
+    /// A `ReadableStream` of the chunks sent to `receiver`, closed when its
+    /// senders are dropped.
+    fn readable_stream(
+        receiver: mpsc::Receiver<Bytes>,
+    ) -> Result<web_sys::ReadableStream, ServerFnError> {
+        let receiver = Rc::new(RefCell::new(Some(receiver)));
+        let pull =
+            Closure::<dyn FnMut(web_sys::ReadableStreamDefaultController) -> js_sys::Promise>::new(
+                move |controller: web_sys::ReadableStreamDefaultController| {
+                    let receiver = receiver.clone();
+                    wasm_bindgen_futures::future_to_promise(async move {
+                        // The browser waits for this promise before pulling again
+                        let taken = receiver.borrow_mut().take();
+                        let Some(mut chunks) = taken else {
+                            controller.close()?;
+                            return Ok(JsValue::UNDEFINED);
+                        };
+                        match chunks.next().await {
+                            Some(chunk) => {
+                                controller.enqueue_with_chunk(&js_sys::Uint8Array::from(
+                                    chunk.as_ref(),
+                                ))?;
+                                *receiver.borrow_mut() = Some(chunks);
+                            }
+                            None => controller.close()?,
+                        }
+                        Ok(JsValue::UNDEFINED)
+                    })
+                },
+            );
+        let source = web_sys::UnderlyingSource::new();
+        source.set_pull(pull.into_js_value().unchecked_ref());
+        web_sys::ReadableStream::new_with_underlying_source(&source).map_err(request_error)
+    }
+
+    /// Whether request bodies can be streamed: the browser supports it
+    /// (a stream body isn't turned into text) and the page is served over
+    /// HTTPS, which HTTP/2 needs.
+    fn streaming_supported() -> bool {
+        let secure = web_sys::window()
+            .and_then(|window| window.location().protocol().ok())
+            .is_some_and(|protocol| protocol == "https:");
+        let probe = || -> Result<bool, JsValue> {
+            let init = web_sys::RequestInit::new();
+            init.set_method("POST");
+            init.set_body(&web_sys::ReadableStream::new()?.into());
+            js_sys::Reflect::set(&init, &"duplex".into(), &"half".into())?;
+            let request = web_sys::Request::new_with_str_and_init("", &init)?;
+            Ok(!request.headers().has("Content-Type")?)
+        };
+        secure && probe().unwrap_or(false)
+    }
+}
+
+// ============================================================================
+// Server
+// ============================================================================
+
+#[cfg(feature = "server")]
+pub use server::*;
+
+#[cfg(feature = "server")]
+mod server {
+    use super::{
+        ARGS_PART, Bytes, FileUpload, MULTIPART_CONTENT_TYPE, UPLOAD_CONTENT_TYPE,
+        UPLOAD_INTERRUPTED, find,
+    };
+    use crate::error::ErrorCode;
+    use crate::rpc::ServerFnError;
+    use crate::rpc::server::ArgsRejection;
+    use axum::body::{Body, BodyDataStream};
+    use axum::http::HeaderMap;
+    use axum::http::header::CONTENT_TYPE;
+    use futures_util::Stream;
+    use serde::de::DeserializeOwned;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    /// The body of an upload, as its server function reads it.
+    ///
+    /// Ends early if the body breaks off; the call then fails with
+    /// [`UploadInterrupted`].
+    pub struct UploadBody {
+        chunks: BodyDataStream,
+        failure: Arc<Mutex<Option<String>>>,
+        done: bool,
+    }
+
+    impl Stream for UploadBody {
+        type Item = Bytes;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match Pin::new(&mut self.chunks).poll_next(cx) {
+                Poll::Ready(Some(Ok(chunk))) => Poll::Ready(Some(chunk)),
+                Poll::Ready(Some(Err(e))) => {
+                    self.done = true;
+                    *self.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(e.to_string());
+                    Poll::Ready(None)
+                }
+                Poll::Ready(None) => {
+                    self.done = true;
+                    Poll::Ready(None)
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
+
+    /// Tells whether the [`UploadBody`] it was split from arrived whole.
+    #[derive(Debug)]
+    pub struct UploadCheck {
+        function: &'static str,
+        failure: Arc<Mutex<Option<String>>>,
+    }
+
+    impl UploadCheck {
+        /// `result`, unless the body broke off.
+        pub fn finish<T>(self, result: Result<T, ServerFnError>) -> Result<T, ServerFnError> {
+            match self
+                .failure
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .take()
+            {
+                Some(message) => Err(UploadInterrupted {
+                    function: self.function,
+                    message,
+                }
+                .into()),
+                None => result,
+            }
+        }
+    }
+
+    /// The body of an upload to `function` broke off before its end.
+    #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+    #[error("the upload to {function} broke off: {message}")]
+    pub struct UploadInterrupted {
+        /// The server function called.
+        pub function: &'static str,
+        /// Why the body broke off.
+        pub message: String,
+    }
+
+    impl ErrorCode for UploadInterrupted {
+        fn code(&self) -> &'static str {
+            UPLOAD_INTERRUPTED
+        }
+
+        fn details(&self) -> Option<serde_json::Value> {
+            Some(serde_json::json!({ "function": self.function }))
+        }
+    }
+
+    /// Split the request body of a call to `function` into the stream the
+    /// function reads and the check run when it returns.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn body(function: &'static str, body: Body) -> (UploadBody, UploadCheck) {
+        let failure = Arc::new(Mutex::new(None));
+        let upload = UploadBody {
+            chunks: body.into_data_stream(),
+            failure: failure.clone(),
+            done: false,
+        };
+        (upload, UploadCheck { function, failure })
+    }
+
+    /// Decode the arguments of an upload to `function` from its query
+    /// string.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn decode_args<T: DeserializeOwned>(
+        function: &'static str,
+        query: Option<&str>,
+    ) -> Result<T, ArgsRejection> {
+        crate::rpc::decode_query_args(query).map_err(|e| ArgsRejection::InvalidQuery {
+            function,
+            message: e.to_string(),
+        })
+    }
+
+    /// Decode the multipart body of a call to `function` into its JSON
+    /// arguments and the files named `files`, rejected with 415 if it isn't
+    /// multipart and 400 if a part is malformed or missing.
+    ///
+    /// Used by the handlers `#[server]` generates.
+    pub fn decode_multipart<T: DeserializeOwned, const N: usize>(
+        function: &'static str,
+        headers: &HeaderMap,
+        body: &Bytes,
+        files: [&'static str; N],
+    ) -> Result<(T, [FileUpload; N]), ArgsRejection> {
+        let found = headers
+            .get(CONTENT_TYPE)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let Some(boundary) = found.as_deref().and_then(boundary) else {
+            return Err(ArgsRejection::UnsupportedMediaType {
+                function,
+                expected: MULTIPART_CONTENT_TYPE,
+                found,
+            });
+        };
+        let invalid = |message: String| ArgsRejection::InvalidBody {
+            function,
+            expected: MULTIPART_CONTENT_TYPE,
+            message,
+        };
+        let parts = parse_multipart(body, boundary).map_err(invalid)?;
+        let part = |name: &str| parts.iter().find(|part| part.name == name);
+
+        // No arguments besides the files may come without their part
+        let args = part(ARGS_PART).map_or(&b"{}"[..], |part| part.data.as_ref());
+        let args = serde_json::from_slice(args).map_err(|e| invalid(e.to_string()))?;
+        if let Some(missing) = files.iter().find(|name| part(name).is_none()) {
+            return Err(invalid(format!("missing file `{missing}`")));
+        }
+        let files = files.map(|name| {
+            let part = part(name).expect("checked above");
+            FileUpload {
+                file_name: part.file_name.clone().unwrap_or_default(),
+                content_type: part
+                    .content_type
+                    .clone()
+                    .unwrap_or_else(|| UPLOAD_CONTENT_TYPE.to_string()),
+                data: part.data.clone(),
+            }
+        });
+        Ok((args, files))
+    }
+
+    /// The boundary of a `multipart/form-data` content type.
+    fn boundary(content_type: &str) -> Option<&str> {
+        let (essence, params) = content_type.split_once(';')?;
+        if !essence.trim().eq_ignore_ascii_case(MULTIPART_CONTENT_TYPE) {
+            return None;
+        }
+        params.split(';').find_map(|param| {
+            let (key, value) = param.split_once('=')?;
+            key.trim()
+                .eq_ignore_ascii_case("boundary")
+                .then(|| value.trim().trim_matches('"'))
+                .filter(|boundary| !boundary.is_empty())
+        })
+    }
+
+    /// A part of a multipart body.
+    struct Part {
+        name: String,
+        file_name: Option<String>,
+        content_type: Option<String>,
+        data: Bytes,
+    }
+
+    /// Split a multipart `body` into its parts; their data shares `body`'s
+    /// memory.
+    fn parse_multipart(body: &Bytes, boundary: &str) -> Result<Vec<Part>, String> {
+        let delimiter = format!("\r\n--{boundary}");
+        let delimiter = delimiter.as_bytes();
+        // The first delimiter may start the body, without a line break
+        let mut at = find(body, &delimiter[2..]).ok_or("no multipart boundary in the body")?
+            + delimiter.len()
+            - 2;
+        let mut parts = Vec::new();
+        loop {
+            let rest = &body[at..];
+            if rest.starts_with(b"--") {
+                return Ok(parts);
+            }
+            if !rest.starts_with(b"\r\n") {
+                return Err("malformed multipart boundary".to_string());
+            }
+            let headers_start = at + 2;
+            let headers_end = find(&body[headers_start..], b"\r\n\r\n")
+                .ok_or("unterminated part headers")?
+                + headers_start;
+            let headers = std::str::from_utf8(&body[headers_start..headers_end])
+                .map_err(|_| "part headers are not UTF-8")?;
+            let data_start = headers_end + 4;
+            let data_end = find(&body[data_start..], delimiter)
+                .ok_or("missing closing multipart boundary")?
+                + data_start;
+            parts.push(part(headers, body.slice(data_start..data_end))?);
+            at = data_end + delimiter.len();
+        }
+    }
+
+    /// A part with `headers` holding `data`.
+    fn part(headers: &str, data: Bytes) -> Result<Part, String> {
+        let mut part = Part {
+            name: String::new(),
+            file_name: None,
+            content_type: None,
+            data,
+        };
+        let mut named = false;
+        for line in headers.split("\r\n") {
+            let Some((header, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if header.trim().eq_ignore_ascii_case("content-type") {
+                part.content_type = Some(value.to_string());
+            } else if header.trim().eq_ignore_ascii_case("content-disposition") {
+                for (key, value) in disposition_params(value) {
+                    match key.as_str() {
+                        "name" => {
+                            part.name = value;
+                            named = true;
+                        }
+                        "filename" => part.file_name = Some(value),
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if !named {
+            return Err("a part without a name".to_string());
+        }
+        Ok(part)
+    }
+
+    /// The parameters of a `Content-Disposition` value, with quoted values
+    /// unescaped as browsers escape them.
+    fn disposition_params(value: &str) -> Vec<(String, String)> {
+        let mut params = Vec::new();
+        let mut rest = value;
+        while let Some((_, after)) = rest.split_once(';') {
+            let Some((key, after)) = after.split_once('=') else {
+                break;
+            };
+            let after = after.trim_start();
+            let (value, after) = match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted.find('"').unwrap_or(quoted.len());
+                    let value = quoted[..end]
+                        .replace("%22", "\"")
+                        .replace("%0D", "\r")
+                        .replace("%0A", "\n");
+                    (value, quoted.get(end + 1..).unwrap_or(""))
+                }
+                None => {
+                    let end = after.find(';').unwrap_or(after.len());
+                    (after[..end].trim().to_string(), &after[end..])
+                }
+            };
+            params.push((key.trim().to_ascii_lowercase(), value));
+            rest = after;
+        }
+        params
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::rpc::ServerFnError;
+    use axum::body::Body;
+    use futures_util::StreamExt;
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct UploadArgs {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn uploads_stream_the_body_and_take_args_from_the_query() {
+        let url = crate::rpc::encode_query_args("/api/upload", &UploadArgs { name: "a b".into() })
+            .unwrap();
+        let query = url.split_once('?').map(|(_, query)| query);
+        let args: UploadArgs = decode_args("upload", query).unwrap();
+        assert_eq!(args.name, "a b");
+
+        let chunks = futures_util::stream::iter([Ok::<_, std::io::Error>("ab"), Ok("cd")]);
+        let (upload, check) = body("upload", Body::from_stream(chunks));
+        let received: Vec<Bytes> = upload.collect().await;
+        assert_eq!(received, [Bytes::from("ab"), Bytes::from("cd")]);
+        assert_eq!(check.finish(Ok(received.len())).unwrap(), 2);
+    }
+
+    #[test]
+    fn files_and_args_round_trip_through_multipart() {
+        let files = [
+            (
+                "image",
+                FileUpload::new("a \"b\"\r\n.png", &b"\r\n--\x00\xff"[..])
+                    .content_type("image/png"),
+            ),
+            ("notes", FileUpload::new("notes; draft.txt", "")),
+        ];
+        let args = serde_json::to_vec(&UploadArgs { name: "a".into() }).unwrap();
+        let (content_type, body) = encode_multipart(&args, &files);
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("content-type", content_type.parse().unwrap());
+
+        let (args, [image, notes]): (UploadArgs, _) =
+            decode_multipart("upload", &headers, &Bytes::from(body), ["image", "notes"]).unwrap();
+        assert_eq!(args.name, "a");
+        assert_eq!(image, files[0].1);
+        assert_eq!(notes, files[1].1);
+    }
+
+    #[test]
+    fn multipart_bodies_without_the_files_are_rejected() {
+        let mut headers = axum::http::HeaderMap::new();
+        let decode = |headers: &_, body: &str| {
+            decode_multipart::<UploadArgs, 1>(
+                "upload",
+                headers,
+                &Bytes::from(body.to_string()),
+                ["image"],
+            )
+        };
+        assert!(matches!(
+            decode(&headers, "{}"),
+            Err(crate::rpc::server::ArgsRejection::UnsupportedMediaType { .. })
+        ));
+
+        headers.insert(
+            "content-type",
+            "multipart/form-data; boundary=b".parse().unwrap(),
+        );
+        let body = "--b\r\nContent-Disposition: form-data; name=\"args\"\r\n\r\n\
+            {\"name\":\"a\"}\r\n--b--\r\n";
+        let rejection = decode(&headers, body).unwrap_err();
+        assert_eq!(rejection.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert!(rejection.to_string().contains("missing file `image`"));
+        assert!(decode(&headers, "--b\r\nContent-Disposition: form-data").is_err());
+    }
+
+    #[tokio::test]
+    async fn broken_off_uploads_fail() {
+        let chunks = futures_util::stream::iter([
+            Ok("ab"),
+            Err(std::io::Error::other("connection reset")),
+            Ok("cd"),
+        ]);
+        let (upload, check) = body("upload", Body::from_stream(chunks));
+        let received: Vec<Bytes> = upload.collect().await;
+        assert_eq!(received, [Bytes::from("ab")]);
+
+        let error = check.finish(Ok(())).unwrap_err();
+        assert!(matches!(
+            error,
+            ServerFnError::Custom { ref code, .. } if code == UPLOAD_INTERRUPTED
+        ));
+    }
+}
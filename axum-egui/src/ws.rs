@@ -54,6 +54,7 @@
 mod server {
     pub use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 
+    use super::FrameEncoding;
     use axum::extract::ws::Message as WsMessage;
     use bytes::Bytes;
     use futures_util::{SinkExt, Stream, StreamExt};
@@ -66,9 +67,10 @@ mod server {
     /// A JSON-based WebSocket stream for sending typed messages.
     ///
     /// This wraps an axum WebSocket and provides automatic JSON serialization.
+    /// Binary frames are read as MessagePack (see [`FrameEncoding`]).
     pub struct JsonWebSocket<T, R> {
         rx: ReceiverStream<Result<R, String>>,
-        tx: mpsc::Sender<T>,
+        tx: mpsc::Sender<(T, FrameEncoding)>,
     }
 
     impl<T, R> JsonWebSocket<T, R>
@@ -82,19 +84,29 @@ mod server {
         pub fn new(socket: WebSocket) -> Self {
             let (mut ws_tx, mut ws_rx) = socket.split();
             let (incoming_tx, incoming_rx) = mpsc::channel::<Result<R, String>>(256);
-            let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<T>(256);
+            let (outgoing_tx, mut outgoing_rx) = mpsc::channel::<(T, FrameEncoding)>(256);
 
             // Spawn task to handle the WebSocket
             tokio::spawn(async move {
+                let mut buffer = crate::stream::JsonBuffer::default();
                 loop {
                     tokio::select! {
                         // Handle outgoing messages (T -> WebSocket)
                         outgoing = outgoing_rx.recv() => {
                             match outgoing {
-                                Some(msg) => {
-                                    match serde_json::to_string(&msg) {
-                                        Ok(json) => {
-                                            if ws_tx.send(WsMessage::Text(json.into())).await.is_err() {
+                                Some((msg, encoding)) => {
+                                    let frame = match encoding {
+                                        FrameEncoding::Json => buffer
+                                            .encode_text(&msg)
+                                            .map(WsMessage::Text)
+                                            .map_err(|e| e.to_string()),
+                                        FrameEncoding::MessagePack => encoding
+                                            .encode(&msg)
+                                            .map(|bytes| WsMessage::Binary(bytes.into())),
+                                    };
+                                    match frame {
+                                        Ok(frame) => {
+                                            if ws_tx.send(frame).await.is_err() {
                                                 break;
                                             }
                                         }
@@ -110,27 +122,17 @@ mod server {
                         incoming = ws_rx.next() => {
                             match incoming {
                                 Some(Ok(WsMessage::Text(text))) => {
-                                    match serde_json::from_str::<R>(&text) {
-                                        Ok(msg) => {
-                                            if incoming_tx.send(Ok(msg)).await.is_err() {
-                                                break;
-                                            }
-                                        }
-                                        Err(e) => {
-                                            let _ = incoming_tx.send(Err(format!("Parse error: {}", e))).await;
-                                        }
+                                    let msg = FrameEncoding::Json.decode::<R>(text.as_bytes());
+                                    let msg = msg.map_err(|e| format!("Parse error: {}", e));
+                                    if incoming_tx.send(msg).await.is_err() {
+                                        break;
                                     }
                                 }
                                 Some(Ok(WsMessage::Binary(bytes))) => {
-                                    match serde_json::from_slice::<R>(&bytes) {
-                                        Ok(msg) => {
-                                            if incoming_tx.send(Ok(msg)).await.is_err() {
-                                                break;
-                                            }
-                                        }
-                                        Err(e) => {
-                                            let _ = incoming_tx.send(Err(format!("Parse error: {}", e))).await;
-                                        }
+                                    let msg = FrameEncoding::MessagePack.decode::<R>(&bytes);
+                                    let msg = msg.map_err(|e| format!("Parse error: {}", e));
+                                    if incoming_tx.send(msg).await.is_err() {
+                                        break;
                                     }
                                 }
                                 Some(Ok(WsMessage::Ping(bytes))) => {
@@ -159,11 +161,14 @@ mod server {
             }
         }
 
-        /// Send a message to the client.
+        /// Send a message to the client, as JSON in a text frame.
         pub fn send(&self, msg: T) -> Result<(), String> {
-            self.tx
-                .try_send(msg)
-                .map_err(|e| format!("Send error: {}", e))
+            send(&self.tx, msg, FrameEncoding::Json)
+        }
+
+        /// Send a message to the client, as MessagePack in a binary frame.
+        pub fn send_binary(&self, msg: T) -> Result<(), String> {
+            send(&self.tx, msg, FrameEncoding::MessagePack)
         }
 
         /// Split into separate sender and receiver.
@@ -180,17 +185,29 @@ mod server {
         }
     }
 
+    fn send<T>(
+        tx: &mpsc::Sender<(T, FrameEncoding)>,
+        msg: T,
+        encoding: FrameEncoding,
+    ) -> Result<(), String> {
+        tx.try_send((msg, encoding))
+            .map_err(|e| format!("Send error: {}", e))
+    }
+
     /// Sender half of a split JsonWebSocket.
     pub struct WsSender<T> {
-        tx: mpsc::Sender<T>,
+        tx: mpsc::Sender<(T, FrameEncoding)>,
     }
 
     impl<T> WsSender<T> {
-        /// Send a message.
+        /// Send a message, as JSON in a text frame.
         pub fn send(&self, msg: T) -> Result<(), String> {
-            self.tx
-                .try_send(msg)
-                .map_err(|e| format!("Send error: {}", e))
+            send(&self.tx, msg, FrameEncoding::Json)
+        }
+
+        /// Send a message, as MessagePack in a binary frame.
+        pub fn send_binary(&self, msg: T) -> Result<(), String> {
+            send(&self.tx, msg, FrameEncoding::MessagePack)
         }
     }
 
@@ -295,20 +312,371 @@ mod server {
 #[cfg(feature = "server")]
 pub use server::*;
 
+/// Close code sent when a `#[server(ws)]` function returns an error
+/// (1011, "Internal Error").
+pub const CLOSE_INTERNAL_ERROR: u16 = 1011;
+
+/// Close code sent to connections closed by a
+/// [drain](crate::instance::drain) (1012, "Service Restart").
+pub const CLOSE_SERVICE_RESTART: u16 = 1012;
+
+/// Close code sent to connections idle for longer than their
+/// [`WsLimits::idle_timeout`] (1001, "Going Away").
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+
+/// Close code sent to connections closed by an administrator (see
+/// [`crate::connections`]) (1008, "Policy Violation").
+pub const CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// Close code sent to clients sending a message larger than
+/// [`WsLimits::max_message_size`] (1009, "Message Too Big").
+pub const CLOSE_MESSAGE_TOO_BIG: u16 = 1009;
+
+// ============================================================================
+// Frame encodings
+// ============================================================================
+
+/// How typed messages are encoded in WebSocket frames.
+///
+/// Every typed API of this module follows the same rule on both ends
+/// (`JsonWebSocket`, `serve_stream`, `WsStream` and the generated
+/// `#[server(ws)]` functions): text frames carry JSON and binary frames carry
+/// MessagePack. Receivers accept both, so a peer may choose per message.
+/// Typed senders use text frames, or binary ones with `send_binary`. The raw
+/// byte APIs (`into_raw_websocket`, `open_raw_websocket`) leave frames as
+/// they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEncoding {
+    /// JSON, in a text frame.
+    Json,
+    /// MessagePack with structs as maps, in a binary frame.
+    MessagePack,
+}
+
+impl FrameEncoding {
+    /// Encode `value` as the payload of a frame.
+    pub fn encode<T: serde::Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            FrameEncoding::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            FrameEncoding::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Decode the payload of a frame.
+    pub fn decode<R: serde::de::DeserializeOwned>(self, payload: &[u8]) -> Result<R, String> {
+        match self {
+            FrameEncoding::Json => serde_json::from_slice(payload).map_err(|e| e.to_string()),
+            FrameEncoding::MessagePack => rmp_serde::from_slice(payload).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+mod server_fn {
+    use super::{
+        CLOSE_GOING_AWAY, CLOSE_INTERNAL_ERROR, CLOSE_MESSAGE_TOO_BIG, CLOSE_POLICY_VIOLATION,
+        CLOSE_SERVICE_RESTART, FrameEncoding,
+    };
+    use crate::context::{self, RequestContext};
+    use crate::error::ErrorEnvelope;
+    use crate::instance::{self, INSTANCE_HEADER};
+    use crate::rpc::ServerFnError;
+    use crate::stream::{SerializationPolicy, ServerStream, serialization_failed};
+    use axum::extract::ws::{CloseFrame, Message, WebSocketUpgrade};
+    use axum::response::Response;
+    use futures_util::{SinkExt, StreamExt};
+    use serde::{Serialize, de::DeserializeOwned};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{Notify, mpsc, oneshot};
+    use tokio::time::Instant;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    /// Limits of one `#[server(ws)]` endpoint, set with
+    /// `#[server(ws, idle_timeout = "60s", max_message_size = "256KB")]`.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct WsLimits {
+        idle_timeout: Option<Duration>,
+        max_message_size: Option<usize>,
+    }
+
+    impl WsLimits {
+        /// No limits besides axum's defaults.
+        pub const fn new() -> Self {
+            Self {
+                idle_timeout: None,
+                max_message_size: None,
+            }
+        }
+
+        /// Close connections with [`CLOSE_GOING_AWAY`] once no message went
+        /// either way for `timeout`. Pings and pongs don't count.
+        pub const fn idle_timeout(mut self, timeout: Duration) -> Self {
+            self.idle_timeout = Some(timeout);
+            self
+        }
+
+        /// Close connections with [`CLOSE_MESSAGE_TOO_BIG`] when the client
+        /// sends a message (or frame) larger than `bytes`.
+        pub const fn max_message_size(mut self, bytes: usize) -> Self {
+            self.max_message_size = Some(bytes);
+            self
+        }
+    }
+
+    /// Whether a read failed because the message exceeded the size limit.
+    /// axum doesn't expose tungstenite's error type, so this checks its
+    /// message ("Space limit exceeded: Message too long: ...").
+    fn is_too_big(error: &axum::Error) -> bool {
+        let error =
+            std::error::Error::source(error).map_or_else(|| error.to_string(), |e| e.to_string());
+        error.contains("Space limit exceeded")
+    }
+
+    /// Negotiate the `Sec-WebSocket-Protocol` of a connection.
+    ///
+    /// Selects the first protocol offered by the client that is in
+    /// `protocols` (the server's list, e.g. `["chat.v2", "chat.v1"]`), so an
+    /// endpoint can be versioned. Fails for connections offering none of
+    /// them; the generated handler answers those with `400 Bad Request`. An
+    /// empty list accepts any connection without selecting a protocol.
+    ///
+    /// Generated for `#[server(ws, protocols("chat.v2", "chat.v1"))]`. The
+    /// function body can see the client's offer in the
+    /// `sec-websocket-protocol` header of [`RequestContext::headers`].
+    pub fn select_protocol(
+        upgrade: WebSocketUpgrade,
+        protocols: &'static [&'static str],
+    ) -> Result<WebSocketUpgrade, ServerFnError> {
+        if protocols.is_empty() {
+            return Ok(upgrade);
+        }
+        let upgrade = upgrade.protocols(protocols.iter().copied());
+        if upgrade.selected_protocol().is_none() {
+            return Err(ServerFnError::Request(format!(
+                "unsupported WebSocket subprotocol, expected one of: {}",
+                protocols.join(", ")
+            )));
+        }
+        Ok(upgrade)
+    }
+
+    /// Upgrade to a WebSocket and run a `#[server(ws)]` function on it.
+    ///
+    /// Incoming frames are decoded as `In` values (text frames as JSON, binary
+    /// ones as MessagePack, see [`FrameEncoding`]) and fed to `f` as its
+    /// input stream. Each item of the stream `f` returns is sent as
+    /// a JSON-serialized `Result<Out, ErrorEnvelope>` (see
+    /// [`crate::error`]), so errors reach the client without closing the
+    /// socket. If `f` itself fails, or an item
+    /// fails to serialize under [`SerializationPolicy::Close`], the socket is
+    /// closed with [`CLOSE_INTERNAL_ERROR`].
+    ///
+    /// `f` runs with `ctx` as the current [`context`], and the connection
+    /// ends when either side closes: the client's close frame, or the
+    /// returned stream ending. A [drained](crate::instance::drain) instance
+    /// closes it with [`CLOSE_SERVICE_RESTART`], one closed by an
+    /// administrator (see [`crate::connections`]) with
+    /// [`CLOSE_POLICY_VIOLATION`].
+    pub fn serve_stream<In, Out, F, Fut>(
+        upgrade: WebSocketUpgrade,
+        ctx: RequestContext,
+        f: F,
+    ) -> Response
+    where
+        In: DeserializeOwned + Send + 'static,
+        Out: Serialize + Send + 'static,
+        F: FnOnce(ServerStream<In>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<ServerStream<Out>, ServerFnError>> + Send + 'static,
+    {
+        serve_stream_with(upgrade, ctx, WsLimits::new(), f)
+    }
+
+    /// [`serve_stream`] enforcing `limits`: connections idle for too long or
+    /// sending oversized messages are closed with [`CLOSE_GOING_AWAY`] and
+    /// [`CLOSE_MESSAGE_TOO_BIG`].
+    pub fn serve_stream_with<In, Out, F, Fut>(
+        mut upgrade: WebSocketUpgrade,
+        ctx: RequestContext,
+        limits: WsLimits,
+        f: F,
+    ) -> Response
+    where
+        In: DeserializeOwned + Send + 'static,
+        Out: Serialize + Send + 'static,
+        F: FnOnce(ServerStream<In>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<ServerStream<Out>, ServerFnError>> + Send + 'static,
+    {
+        if let Some(max) = limits.max_message_size {
+            upgrade = upgrade.max_message_size(max).max_frame_size(max);
+        }
+        let mut response = upgrade.on_upgrade(move |socket| async move {
+            let guard = ctx.disconnect_guard();
+            let connection = ctx.connection().clone();
+            let stats = Arc::new(crate::metrics::StreamGuard::new(
+                crate::metrics::Transport::Ws,
+            ));
+            let (mut ws_tx, mut ws_rx) = socket.split();
+            let (input_tx, input_rx) = mpsc::channel::<Result<In, ServerFnError>>(256);
+
+            // Read in a separate task so `f` may await input before returning.
+            // The close frame it asks for is sent before the input ends.
+            let reader_stats = stats.clone();
+            let reader_connection = connection.clone();
+            let activity = Arc::new(Notify::new());
+            let reader_activity = activity.clone();
+            let (close_tx, mut close_rx) = oneshot::channel();
+            let mut reader = tokio::spawn(async move {
+                let mut forwarding = true;
+                while let Some(msg) = ws_rx.next().await {
+                    let msg = match msg {
+                        Ok(msg) => msg,
+                        Err(e) if is_too_big(&e) => {
+                            let _ = close_tx.send(CloseFrame {
+                                code: CLOSE_MESSAGE_TOO_BIG,
+                                reason: "message too big".into(),
+                            });
+                            break;
+                        }
+                        Err(_) => break,
+                    };
+                    let item = match msg {
+                        Message::Text(text) => FrameEncoding::Json.decode(text.as_bytes()),
+                        Message::Binary(bytes) => FrameEncoding::MessagePack.decode(&bytes),
+                        Message::Ping(_) | Message::Pong(_) => continue,
+                        Message::Close(_) => break,
+                    };
+                    reader_stats.received();
+                    reader_connection.received();
+                    reader_activity.notify_one();
+                    let item = item.map_err(ServerFnError::Deserialization);
+                    // Keep reading after the input stream is dropped, to notice the close
+                    if forwarding && input_tx.send(item).await.is_err() {
+                        forwarding = false;
+                    }
+                }
+            });
+
+            let input = ServerStream::from_results(ReceiverStream::new(input_rx));
+            let output = match context::scope(ctx, f(input)).await {
+                Ok(output) => output,
+                Err(e) => {
+                    reader.abort();
+                    let frame = CloseFrame {
+                        code: CLOSE_INTERNAL_ERROR,
+                        reason: e.to_string().into(),
+                    };
+                    let _ = ws_tx.send(Message::Close(Some(frame))).await;
+                    return;
+                }
+            };
+
+            let mut output = guard.bind(output);
+            let drained = instance::drained();
+            tokio::pin!(drained);
+            // Far enough to never fire without an idle timeout
+            let idle_timeout = limits
+                .idle_timeout
+                .unwrap_or(Duration::from_secs(86400 * 365));
+            let idle = tokio::time::sleep(idle_timeout);
+            tokio::pin!(idle);
+            let mut close = None;
+            let mut buffer = crate::stream::JsonBuffer::default();
+            loop {
+                tokio::select! {
+                    item = output.next() => {
+                        let Some(item) = item else { break };
+                        let item = item.map_err(ErrorEnvelope::from);
+                        let (json, policy) = match buffer.encode_text(&item) {
+                            Ok(json) => (json, None),
+                            Err(e) => {
+                                let policy = serialization_failed(&e);
+                                if policy == SerializationPolicy::Skip {
+                                    continue;
+                                }
+                                let err: Result<Out, _> = Err(ErrorEnvelope::from(
+                                    ServerFnError::Serialization(e.to_string()),
+                                ));
+                                (buffer.encode_text(&err).unwrap_or_default(), Some(policy))
+                            }
+                        };
+                        if ws_tx.send(Message::Text(json)).await.is_err() {
+                            break;
+                        }
+                        stats.sent();
+                        idle.as_mut().reset(Instant::now() + idle_timeout);
+                        if policy == Some(SerializationPolicy::Close) {
+                            close = Some(CloseFrame {
+                                code: CLOSE_INTERNAL_ERROR,
+                                reason: "failed to serialize stream item".into(),
+                            });
+                            break;
+                        }
+                    }
+                    _ = &mut reader => break,
+                    _ = activity.notified() => idle.as_mut().reset(Instant::now() + idle_timeout),
+                    _ = &mut idle => {
+                        close = Some(CloseFrame {
+                            code: CLOSE_GOING_AWAY,
+                            reason: "idle timeout".into(),
+                        });
+                        break;
+                    }
+                    _ = &mut drained => {
+                        close = Some(CloseFrame {
+                            code: CLOSE_SERVICE_RESTART,
+                            reason: instance::id().into(),
+                        });
+                        break;
+                    }
+                }
+            }
+
+            reader.abort();
+            if close.is_none() && connection.is_closing() {
+                close = Some(CloseFrame {
+                    code: CLOSE_POLICY_VIOLATION,
+                    reason: "closed by an administrator".into(),
+                });
+            }
+            if close.is_none() {
+                close = close_rx.try_recv().ok();
+            }
+            // Drops the user's stream and runs the disconnect hooks
+            drop(output);
+            let _ = ws_tx.send(Message::Close(close)).await;
+        });
+        response
+            .headers_mut()
+            .insert(INSTANCE_HEADER, instance::header_value());
+        response
+    }
+}
+
+#[cfg(feature = "server")]
+pub use server_fn::{WsLimits, select_protocol, serve_stream, serve_stream_with};
+
 // ============================================================================
 // Client-side WebSocket support
 // ============================================================================
 
 #[cfg(feature = "client")]
 mod client {
+    use super::FrameEncoding;
+    use crate::stream::TaskHandle;
     use futures_channel::mpsc;
     use futures_util::{SinkExt, Stream, StreamExt};
-    use gloo_net::websocket::{Message, futures::WebSocket};
+    use gloo_net::websocket::{Message, WebSocketError, futures::WebSocket};
     use send_wrapper::SendWrapper;
     use serde::{Serialize, de::DeserializeOwned};
     use std::pin::Pin;
+    use std::sync::Arc;
     use std::task::{Context, Poll};
 
+    /// The tasks sending and receiving a connection's messages, shared by its
+    /// two halves. The socket is closed once both halves are dropped.
+    type Tasks = Arc<[TaskHandle; 2]>;
+
     /// Error type for WebSocket client operations.
     #[derive(Debug, Clone)]
     pub enum WsError {
@@ -320,6 +688,18 @@ mod client {
         Send(String),
         /// The connection was closed.
         Closed,
+        /// The server closed the connection with
+        /// [`CLOSE_SERVICE_RESTART`](crate::ws::CLOSE_SERVICE_RESTART) and
+        /// asks to connect again.
+        Reconnect,
+        /// A message was over the WebSocket message limit (see
+        /// [`crate::payload`]) and was dropped unparsed.
+        TooLarge {
+            /// The size of the message.
+            size: usize,
+            /// The limit it exceeded.
+            limit: usize,
+        },
     }
 
     impl std::fmt::Display for WsError {
@@ -329,15 +709,72 @@ mod client {
                 WsError::Parse(msg) => write!(f, "WebSocket parse error: {}", msg),
                 WsError::Send(msg) => write!(f, "WebSocket send error: {}", msg),
                 WsError::Closed => write!(f, "WebSocket closed"),
+                WsError::Reconnect => write!(f, "WebSocket closed; server asks to reconnect"),
+                WsError::TooLarge { size, limit } => write!(
+                    f,
+                    "WebSocket message of {} bytes exceeds the limit of {}",
+                    size, limit
+                ),
             }
         }
     }
 
     impl std::error::Error for WsError {}
 
+    /// Connection settings for [`WsStream::connect_with`].
+    #[derive(Debug, Clone, Default)]
+    pub struct ConnectOptions {
+        protocols: Vec<String>,
+        query: Vec<(String, String)>,
+    }
+
+    impl ConnectOptions {
+        /// No subprotocols and no extra query parameters.
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Offer a `Sec-WebSocket-Protocol`. Call repeatedly to offer
+        /// several, most preferred first.
+        pub fn protocol(mut self, protocol: impl Into<String>) -> Self {
+            self.protocols.push(protocol.into());
+            self
+        }
+
+        /// Offer several `Sec-WebSocket-Protocol`s, most preferred first.
+        pub fn protocols<I: IntoIterator<Item = impl Into<String>>>(
+            mut self,
+            protocols: I,
+        ) -> Self {
+            self.protocols.extend(protocols.into_iter().map(Into::into));
+            self
+        }
+
+        /// Append a query parameter to the URL, e.g. an auth ticket.
+        /// Browsers cannot set headers on WebSocket requests, so this is how
+        /// metadata reaches the server at connect time.
+        pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+            self.query.push((name.into(), value.into()));
+            self
+        }
+
+        fn apply_query(&self, url: &str) -> String {
+            let mut url = url.to_string();
+            for (name, value) in &self.query {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                url.push(separator);
+                url.push_str(&crate::rpc::percent_encode(name));
+                url.push('=');
+                url.push_str(&crate::rpc::percent_encode(value));
+            }
+            url
+        }
+    }
+
     /// Client-side WebSocket connection helper with JSON serialization.
     ///
     /// Use `WsStream::connect` to establish a typed WebSocket connection.
+    /// Binary frames are read as MessagePack (see [`FrameEncoding`]).
     pub struct WsStream<T, R>(std::marker::PhantomData<(T, R)>);
 
     impl<T, R> WsStream<T, R>
@@ -351,6 +788,17 @@ mod client {
         pub async fn connect(
             url: &str,
         ) -> Result<(WsClientSender<T>, WsClientReceiver<R>), WsError> {
+            Self::connect_with(url, &ConnectOptions::default()).await
+        }
+
+        /// Connect to a WebSocket endpoint, offering subprotocols and adding
+        /// query parameters from `options`.
+        pub async fn connect_with(
+            url: &str,
+            options: &ConnectOptions,
+        ) -> Result<(WsClientSender<T>, WsClientReceiver<R>), WsError> {
+            let url = options.apply_query(url);
+            let url = url.as_str();
             // Convert relative URL to absolute WebSocket URL
             let ws_url = if url.starts_with("ws://") || url.starts_with("wss://") {
                 url.to_string()
@@ -365,26 +813,36 @@ mod client {
                 format!("{}//{}{}", ws_protocol, host, url)
             };
 
-            let websocket =
-                WebSocket::open(&ws_url).map_err(|e| WsError::Connection(format!("{:?}", e)))?;
+            let websocket = if options.protocols.is_empty() {
+                WebSocket::open(&ws_url)
+            } else {
+                WebSocket::open_with_protocols(&ws_url, &options.protocols)
+            }
+            .map_err(|e| WsError::Connection(format!("{:?}", e)))?;
 
             let (ws_sink, ws_stream) = websocket.split();
 
             // Create unbounded channels for typed messages
-            let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded::<T>();
+            let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded::<(T, FrameEncoding)>();
             let (incoming_tx, incoming_rx) = mpsc::unbounded::<Result<R, WsError>>();
 
             // Wrap sink for sending
             let ws_sink = SendWrapper::new(ws_sink);
             let ws_stream = SendWrapper::new(ws_stream);
 
-            // Spawn task to handle outgoing messages
-            wasm_bindgen_futures::spawn_local(async move {
+            // Task to handle outgoing messages
+            let outgoing = TaskHandle::spawn(async move {
                 let mut ws_sink = ws_sink;
-                while let Some(msg) = outgoing_rx.next().await {
-                    match serde_json::to_string(&msg) {
-                        Ok(json) => {
-                            if ws_sink.send(Message::Text(json)).await.is_err() {
+                while let Some((msg, encoding)) = outgoing_rx.next().await {
+                    let frame = match encoding {
+                        FrameEncoding::Json => serde_json::to_string(&msg)
+                            .map(Message::Text)
+                            .map_err(|e| e.to_string()),
+                        FrameEncoding::MessagePack => encoding.encode(&msg).map(Message::Bytes),
+                    };
+                    match frame {
+                        Ok(frame) => {
+                            if ws_sink.send(frame).await.is_err() {
                                 break;
                             }
                         }
@@ -397,66 +855,80 @@ mod client {
                 }
             });
 
-            // Spawn task to handle incoming messages
-            wasm_bindgen_futures::spawn_local(async move {
+            // Task to handle incoming messages
+            let incoming = TaskHandle::spawn(async move {
                 let mut ws_stream = ws_stream;
                 while let Some(msg) = ws_stream.next().await {
-                    match msg {
-                        Ok(Message::Text(text)) => match serde_json::from_str::<R>(&text) {
-                            Ok(parsed) => {
-                                if incoming_tx.unbounded_send(Ok(parsed)).is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                let _ =
-                                    incoming_tx.unbounded_send(Err(WsError::Parse(e.to_string())));
-                            }
-                        },
-                        Ok(Message::Bytes(bytes)) => match serde_json::from_slice::<R>(&bytes) {
-                            Ok(parsed) => {
-                                if incoming_tx.unbounded_send(Ok(parsed)).is_err() {
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                let _ =
-                                    incoming_tx.unbounded_send(Err(WsError::Parse(e.to_string())));
-                            }
-                        },
+                    if let Some(Err(e)) = msg.as_ref().ok().map(check_size) {
+                        let _ = incoming_tx.unbounded_send(Err(e));
+                        continue;
+                    }
+                    let parsed = match msg {
+                        Ok(Message::Text(text)) => FrameEncoding::Json.decode::<R>(text.as_bytes()),
+                        Ok(Message::Bytes(bytes)) => FrameEncoding::MessagePack.decode::<R>(&bytes),
+                        Err(WebSocketError::ConnectionClose(close))
+                            if close.code == crate::ws::CLOSE_SERVICE_RESTART =>
+                        {
+                            let _ = incoming_tx.unbounded_send(Err(WsError::Reconnect));
+                            break;
+                        }
                         Err(e) => {
                             web_sys::console::error_1(&format!("WebSocket error: {:?}", e).into());
                             let _ = incoming_tx
                                 .unbounded_send(Err(WsError::Connection(format!("{:?}", e))));
                             break;
                         }
+                    };
+                    if incoming_tx
+                        .unbounded_send(parsed.map_err(WsError::Parse))
+                        .is_err()
+                    {
+                        break;
                     }
                 }
             });
 
+            let tasks = Arc::new([outgoing, incoming]);
             Ok((
                 WsClientSender {
                     tx: outgoing_tx,
+                    _tasks: tasks.clone(),
                     _phantom: std::marker::PhantomData,
                 },
-                WsClientReceiver { rx: incoming_rx },
+                WsClientReceiver {
+                    rx: incoming_rx,
+                    _tasks: tasks,
+                },
             ))
         }
     }
 
     /// Sender half for client WebSocket.
+    ///
+    /// The connection stays open while this or the [`WsClientReceiver`] is
+    /// alive, and is closed as soon as both are dropped.
     pub struct WsClientSender<T> {
-        tx: mpsc::UnboundedSender<T>,
+        tx: mpsc::UnboundedSender<(T, FrameEncoding)>,
+        _tasks: Tasks,
         _phantom: std::marker::PhantomData<T>,
     }
 
     impl<T> WsClientSender<T> {
-        /// Send a message to the server.
+        /// Send a message to the server, as JSON in a text frame.
         ///
         /// This is a synchronous operation that queues the message for sending.
         pub fn send(&self, msg: T) -> Result<(), WsError> {
+            self.send_as(msg, FrameEncoding::Json)
+        }
+
+        /// Send a message to the server, as MessagePack in a binary frame.
+        pub fn send_binary(&self, msg: T) -> Result<(), WsError> {
+            self.send_as(msg, FrameEncoding::MessagePack)
+        }
+
+        fn send_as(&self, msg: T, encoding: FrameEncoding) -> Result<(), WsError> {
             self.tx
-                .unbounded_send(msg)
+                .unbounded_send((msg, encoding))
                 .map_err(|e| WsError::Send(e.to_string()))
         }
     }
@@ -464,6 +936,7 @@ mod client {
     /// Receiver half for client WebSocket.
     pub struct WsClientReceiver<R> {
         rx: mpsc::UnboundedReceiver<Result<R, WsError>>,
+        _tasks: Tasks,
     }
 
     impl<R> Stream for WsClientReceiver<R> {
@@ -504,8 +977,8 @@ mod client {
         let ws_sink = SendWrapper::new(ws_sink);
         let ws_stream = SendWrapper::new(ws_stream);
 
-        // Spawn task for outgoing messages
-        wasm_bindgen_futures::spawn_local(async move {
+        // Task for outgoing messages
+        let outgoing = TaskHandle::spawn(async move {
             let mut ws_sink = ws_sink;
             while let Some(bytes) = outgoing_rx.next().await {
                 if ws_sink.send(Message::Bytes(bytes)).await.is_err() {
@@ -514,13 +987,15 @@ mod client {
             }
         });
 
-        // Spawn task for incoming messages
-        wasm_bindgen_futures::spawn_local(async move {
+        // Task for incoming messages
+        let incoming = TaskHandle::spawn(async move {
             let mut ws_stream = ws_stream;
             while let Some(msg) = ws_stream.next().await {
                 let result = match msg {
-                    Ok(Message::Text(text)) => Ok(text.into_bytes()),
-                    Ok(Message::Bytes(bytes)) => Ok(bytes),
+                    Ok(msg) => check_size(&msg).map(|()| match msg {
+                        Message::Text(text) => text.into_bytes(),
+                        Message::Bytes(bytes) => bytes,
+                    }),
                     Err(e) => Err(WsError::Connection(format!("{:?}", e))),
                 };
                 if incoming_tx.unbounded_send(result).is_err() {
@@ -529,15 +1004,37 @@ mod client {
             }
         });
 
+        let tasks = Arc::new([outgoing, incoming]);
         Ok((
-            WsRawSender { tx: outgoing_tx },
-            WsRawReceiver { rx: incoming_rx },
+            WsRawSender {
+                tx: outgoing_tx,
+                _tasks: tasks.clone(),
+            },
+            WsRawReceiver {
+                rx: incoming_rx,
+                _tasks: tasks,
+            },
         ))
     }
 
-    /// Sender for raw WebSocket bytes.
+    /// [`WsError::TooLarge`] if `msg` is over the WebSocket message limit.
+    fn check_size(msg: &Message) -> Result<(), WsError> {
+        let size = match msg {
+            Message::Text(text) => text.len(),
+            Message::Bytes(bytes) => bytes.len(),
+        };
+        let limit = crate::payload::limits().ws_limit();
+        if size > limit {
+            return Err(WsError::TooLarge { size, limit });
+        }
+        Ok(())
+    }
+
+    /// Sender for raw WebSocket bytes. Like [`WsClientSender`], the
+    /// connection is closed once both halves are dropped.
     pub struct WsRawSender {
         tx: mpsc::UnboundedSender<Vec<u8>>,
+        _tasks: Tasks,
     }
 
     impl WsRawSender {
@@ -552,6 +1049,7 @@ mod client {
     /// Receiver for raw WebSocket bytes.
     pub struct WsRawReceiver {
         rx: mpsc::UnboundedReceiver<Result<Vec<u8>, WsError>>,
+        _tasks: Tasks,
     }
 
     impl Stream for WsRawReceiver {
@@ -566,6 +1064,111 @@ mod client {
 #[cfg(feature = "client")]
 pub use client::*;
 
+#[cfg(feature = "client")]
+mod client_fn {
+    use super::client::{ConnectOptions, WsError, WsStream};
+    use crate::error::ErrorEnvelope;
+    use crate::rpc::{ServerFnError, encode_query_args};
+    use crate::stream::{ServerStream, TaskHandle};
+    use futures_util::StreamExt;
+    use serde::{Serialize, de::DeserializeOwned};
+
+    /// Connect to a `#[server(ws)]` function.
+    ///
+    /// This is what the macro generates on the client: `args` go in the
+    /// query string (see [`encode_query_args`]), items of `input` are sent
+    /// as JSON frames, and the returned stream yields the server's items.
+    pub async fn connect_stream<Args, In, Out>(
+        path: &str,
+        args: &Args,
+        input: ServerStream<In>,
+    ) -> Result<ServerStream<Out>, ServerFnError>
+    where
+        Args: Serialize,
+        In: Serialize + 'static,
+        Out: DeserializeOwned + Send + 'static,
+    {
+        connect_stream_at(
+            &encode_query_args(path, args)?,
+            input,
+            &ConnectOptions::default(),
+        )
+        .await
+    }
+
+    /// Connect to a `#[server(ws, post_params)]` function.
+    ///
+    /// `args` are POSTed first in exchange for a ticket, which is the only
+    /// thing put in the socket URL (see [`crate::tickets`]).
+    pub async fn connect_stream_with_ticket<Args, In, Out>(
+        path: &str,
+        args: &Args,
+        input: ServerStream<In>,
+    ) -> Result<ServerStream<Out>, ServerFnError>
+    where
+        Args: Serialize,
+        In: Serialize + 'static,
+        Out: DeserializeOwned + Send + 'static,
+    {
+        let url = crate::tickets::ticket_url(path, args).await?;
+        connect_stream_at(&url, input, &ConnectOptions::default()).await
+    }
+
+    /// Connect to a `#[server(ws)]` function at a URL that already carries
+    /// its arguments, with connect `options`.
+    ///
+    /// Generated for functions declared with `protocols(...)`, which offer
+    /// those subprotocols.
+    pub async fn connect_stream_at<In, Out>(
+        url: &str,
+        input: ServerStream<In>,
+        options: &ConnectOptions,
+    ) -> Result<ServerStream<Out>, ServerFnError>
+    where
+        In: Serialize + 'static,
+        Out: DeserializeOwned + Send + 'static,
+    {
+        let (tx, rx) = WsStream::<In, Result<Out, ErrorEnvelope>>::connect_with(url, options)
+            .await
+            .map_err(|e| ServerFnError::Request(e.to_string()))?;
+
+        // Owned by the returned stream: dropping it stops forwarding the
+        // input, which drops the last sender and closes the socket
+        let forward = TaskHandle::spawn(async move {
+            let mut input = input;
+            while let Some(item) = input.next().await {
+                match item {
+                    Ok(msg) => {
+                        if tx.send(msg).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        web_sys::console::error_1(&format!("Input stream error: {}", e).into())
+                    }
+                }
+            }
+        });
+
+        let output = rx.map(move |item| {
+            let _ = &forward;
+            match item {
+                Ok(result) => result.map_err(ServerFnError::from),
+                Err(WsError::Parse(msg)) => Err(ServerFnError::Deserialization(msg)),
+                Err(WsError::Reconnect) => Err(ServerFnError::Reconnect),
+                Err(WsError::TooLarge { size, limit }) => {
+                    Err(ServerFnError::TooLarge { size, limit })
+                }
+                Err(e) => Err(ServerFnError::Request(e.to_string())),
+            }
+        });
+        Ok(ServerStream::from_results(output))
+    }
+}
+
+#[cfg(feature = "client")]
+pub use client_fn::{connect_stream, connect_stream_at, connect_stream_with_ticket};
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -580,4 +1183,262 @@ mod tests {
         let _msg = Message::Text("test".into());
         let _msg = Message::Binary(vec![1, 2, 3].into());
     }
+
+    #[tokio::test]
+    async fn serve_stream_round_trip_runs_disconnect_hooks() {
+        use crate::context::{self, RequestContext};
+        use crate::stream::ServerStream;
+        use axum::Router;
+        use axum::http::request::Parts;
+        use axum::routing::get;
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let (hook_tx, hook_rx) = tokio::sync::oneshot::channel::<()>();
+        let hook_tx = std::sync::Mutex::new(Some(hook_tx));
+        let hook_tx = std::sync::Arc::new(hook_tx);
+
+        let app = Router::new().route(
+            "/echo",
+            get(move |parts: Parts, upgrade: WebSocketUpgrade| {
+                let hook_tx = hook_tx.clone();
+                async move {
+                    let ctx = RequestContext::from_parts(parts);
+                    serve_stream(
+                        upgrade,
+                        ctx,
+                        move |input: ServerStream<String>| async move {
+                            context::on_disconnect(move || {
+                                if let Some(tx) = hook_tx.lock().unwrap().take() {
+                                    let _ = tx.send(());
+                                }
+                            });
+                            Ok(ServerStream::from_results(
+                                input.map(|msg| msg.map(|msg| format!("> {msg}"))),
+                            ))
+                        },
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/echo"))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Text("\"hi\"".into()))
+            .await
+            .unwrap();
+        let reply = socket.next().await.unwrap().unwrap();
+        assert_eq!(reply.into_text().unwrap().as_str(), r#"{"Ok":"> hi"}"#);
+
+        socket.close(None).await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), hook_rx)
+            .await
+            .expect("disconnect hook did not run")
+            .unwrap();
+    }
+
+    #[test]
+    fn frame_encodings_round_trip() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Move {
+            x: i32,
+            label: String,
+        }
+
+        let value = Move {
+            x: -3,
+            label: "north".into(),
+        };
+        for encoding in [FrameEncoding::Json, FrameEncoding::MessagePack] {
+            let payload = encoding.encode(&value).unwrap();
+            assert_eq!(encoding.decode::<Move>(&payload).unwrap(), value);
+        }
+        let json = FrameEncoding::Json.encode(&value).unwrap();
+        assert!(FrameEncoding::MessagePack.decode::<Move>(&json).is_err());
+    }
+
+    #[tokio::test]
+    async fn typed_sockets_read_text_as_json_and_binary_as_msgpack() {
+        use crate::context::RequestContext;
+        use crate::stream::ServerStream;
+        use axum::Router;
+        use axum::http::request::Parts;
+        use axum::routing::get;
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let app = Router::new()
+            .route(
+                "/stream",
+                get(|parts: Parts, upgrade: WebSocketUpgrade| async move {
+                    serve_stream(
+                        upgrade,
+                        RequestContext::from_parts(parts),
+                        |input: ServerStream<Vec<u8>>| async move { Ok(input) },
+                    )
+                }),
+            )
+            .route(
+                "/json",
+                get(|upgrade: WebSocketUpgrade| async move {
+                    upgrade.on_upgrade_json(
+                        |mut socket: JsonWebSocket<Vec<u8>, Vec<u8>>| async move {
+                            while let Some(Ok(msg)) = socket.next().await {
+                                let _ = socket.send_binary(msg);
+                            }
+                        },
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let payload = vec![0xff, 0x00, 0x7b];
+        let msgpack = FrameEncoding::MessagePack.encode(&payload).unwrap();
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/stream"))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Text("[255,0,123]".into()))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Binary(msgpack.clone().into()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let reply = socket.next().await.unwrap().unwrap();
+            assert_eq!(reply.into_text().unwrap().as_str(), r#"{"Ok":[255,0,123]}"#);
+        }
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/json"))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Text("[255,0,123]".into()))
+            .await
+            .unwrap();
+        socket
+            .send(ClientMessage::Binary(msgpack.into()))
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            let ClientMessage::Binary(reply) = socket.next().await.unwrap().unwrap() else {
+                panic!("expected a binary frame");
+            };
+            let reply: Vec<u8> = FrameEncoding::MessagePack.decode(&reply).unwrap();
+            assert_eq!(reply, payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn select_protocol_negotiates_or_rejects() {
+        use axum::Router;
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+        use axum::routing::get;
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let app = Router::new().route(
+            "/chat",
+            get(|upgrade: WebSocketUpgrade| async move {
+                match select_protocol(upgrade, &["chat.v2", "chat.v1"]) {
+                    Ok(upgrade) => upgrade.on_upgrade(|_| async {}),
+                    Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let request = |protocols: &str| {
+            let mut request = format!("ws://{addr}/chat").into_client_request().unwrap();
+            request
+                .headers_mut()
+                .insert("sec-websocket-protocol", protocols.parse().unwrap());
+            request
+        };
+
+        let (_, response) = tokio_tungstenite::connect_async(request("chat.v3, chat.v1"))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["sec-websocket-protocol"], "chat.v1");
+
+        let Err(tokio_tungstenite::tungstenite::Error::Http(response)) =
+            tokio_tungstenite::connect_async(request("chat.v3")).await
+        else {
+            panic!("connection without a supported protocol was accepted");
+        };
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn serve_stream_with_closes_idle_and_oversized_connections() {
+        use crate::context::RequestContext;
+        use crate::stream::ServerStream;
+        use axum::Router;
+        use axum::http::request::Parts;
+        use axum::routing::get;
+        use futures_util::{SinkExt, StreamExt};
+        use std::time::Duration;
+        use tokio_tungstenite::tungstenite::Message as ClientMessage;
+
+        let limits = WsLimits::new()
+            .idle_timeout(Duration::from_millis(300))
+            .max_message_size(16);
+        let app = Router::new().route(
+            "/echo",
+            get(move |parts: Parts, upgrade: WebSocketUpgrade| async move {
+                serve_stream_with(
+                    upgrade,
+                    RequestContext::from_parts(parts),
+                    limits,
+                    |input: ServerStream<String>| async move { Ok(input) },
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let close_code = |message: Option<ClientMessage>| match message {
+            Some(ClientMessage::Close(Some(frame))) => u16::from(frame.code),
+            other => panic!("expected a close frame, got {other:?}"),
+        };
+
+        // Messages keep the connection open past the timeout
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/echo"))
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            socket
+                .send(ClientMessage::Text("\"hi\"".into()))
+                .await
+                .unwrap();
+            let reply = socket.next().await.unwrap().unwrap();
+            assert_eq!(reply.into_text().unwrap().as_str(), r#"{"Ok":"hi"}"#);
+        }
+        let closed = socket.next().await.unwrap().ok();
+        assert_eq!(close_code(closed), CLOSE_GOING_AWAY);
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/echo"))
+            .await
+            .unwrap();
+        let oversized = format!("\"{}\"", "x".repeat(32));
+        socket
+            .send(ClientMessage::Text(oversized.into()))
+            .await
+            .unwrap();
+        let closed = socket.next().await.unwrap().ok();
+        assert_eq!(close_code(closed), CLOSE_MESSAGE_TOO_BIG);
+    }
 }
@@ -0,0 +1,68 @@
+//! `cargo axum-egui build`: build the server with its embedded frontend.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// The `wasm-opt` level used for release builds unless overridden.
+const DEFAULT_WASM_OPT_LEVEL: &str = "z";
+
+/// Build the server package.
+///
+/// Release builds set `AXUM_EGUI_WASM_OPT`, which makes `axum-egui-build`
+/// run `wasm-opt` on the frontend before it is embedded, and
+/// `AXUM_EGUI_PRECOMPRESS`, which makes it write brotli and gzip copies of the
+/// WASM and JS for `static_handler` to serve. An explicit
+/// `AXUM_EGUI_WASM_OPT` in the environment takes precedence.
+///
+/// `profiling` sets `AXUM_EGUI_PROFILING` and turns debug info on (and
+/// stripping off) for the profile, so the frontend keeps its function names.
+pub fn run(root: &Path, package: &str, release: bool, profiling: bool) -> Result<(), String> {
+    let mut command = cargo_command(root);
+    command.args(["build", "-p", package]);
+
+    if profiling {
+        let profile = if release { "RELEASE" } else { "DEV" };
+        command
+            .env("AXUM_EGUI_PROFILING", "1")
+            .env(format!("CARGO_PROFILE_{}_DEBUG", profile), "true")
+            .env(format!("CARGO_PROFILE_{}_STRIP", profile), "none");
+    }
+
+    if release {
+        command.arg("--release");
+        if env::var_os("AXUM_EGUI_WASM_OPT").is_none() {
+            command.env("AXUM_EGUI_WASM_OPT", DEFAULT_WASM_OPT_LEVEL);
+        }
+        command.env("AXUM_EGUI_PRECOMPRESS", "1");
+    }
+
+    println!(
+        "Building {} ({}{})",
+        package,
+        if release { "release" } else { "debug" },
+        if profiling { ", profiling" } else { "" }
+    );
+
+    let status = command
+        .status()
+        .map_err(|e| format!("failed to run cargo: {e}"))?;
+    if !status.success() {
+        return Err(format!("cargo build failed for {}", package));
+    }
+
+    let profile = if release { "release" } else { "debug" };
+    println!("Built target/{}/{}", profile, package);
+    Ok(())
+}
+
+/// A `cargo` invocation rooted at `root`.
+///
+/// Uses the `CARGO` variable Cargo sets for subcommands, so the same
+/// toolchain (nightly, for artifact dependencies) builds the project.
+pub fn cargo_command(root: &Path) -> Command {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command.current_dir(root);
+    command
+}
@@ -0,0 +1,116 @@
+//! `cargo axum-egui dev`: run the server and restart it on source changes.
+//!
+//! Changes are detected by polling modification times, which keeps the tool
+//! dependency-free. The server is started with `AXUM_EGUI_LIVE_RELOAD` set, so
+//! pages served by `axum_egui::App` reload themselves after each restart.
+
+use crate::build::cargo_command;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the source tree is scanned for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Directories never watched (build output and VCS metadata).
+const IGNORED_DIRS: &[&str] = &["target", ".git", "node_modules", "dist"];
+
+/// File extensions that trigger a rebuild.
+const WATCHED_EXTENSIONS: &[&str] = &["rs", "toml", "html", "css", "js"];
+
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+pub fn run(root: &Path, package: &str) -> Result<(), String> {
+    println!("Watching {} for changes (Ctrl+C to stop)", root.display());
+
+    let mut snapshot = scan(root);
+    let mut server = start(root, package)?;
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+
+        let current = scan(root);
+        if current == snapshot {
+            continue;
+        }
+        snapshot = current;
+
+        println!("Change detected, rebuilding {}...", package);
+        let _ = server.kill();
+        let _ = server.wait();
+        server = start(root, package)?;
+    }
+}
+
+fn start(root: &Path, package: &str) -> Result<Child, String> {
+    cargo_command(root)
+        .args(["run", "-p", package])
+        .env("AXUM_EGUI_LIVE_RELOAD", "1")
+        .spawn()
+        .map_err(|e| format!("failed to run cargo: {e}"))
+}
+
+/// Collect modification times of every watched file below `root`.
+fn scan(root: &Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    scan_dir(root, &mut snapshot);
+    snapshot
+}
+
+fn scan_dir(dir: &Path, snapshot: &mut Snapshot) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        if metadata.is_dir() {
+            let ignored = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| IGNORED_DIRS.contains(&name));
+            if !ignored {
+                scan_dir(&path, snapshot);
+            }
+        } else if is_watched(&path) {
+            if let Ok(modified) = metadata.modified() {
+                snapshot.insert(path, modified);
+            }
+        }
+    }
+}
+
+fn is_watched(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| WATCHED_EXTENSIONS.contains(&ext))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn scan_skips_target_and_unwatched_files() {
+        let root = env::temp_dir().join(format!("axum-egui-scan-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+
+        let snapshot = scan(&root);
+        let files: Vec<_> = snapshot.keys().collect();
+        assert_eq!(files, vec![&root.join("src/lib.rs")]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! `cargo axum-egui` - project tooling for axum-egui.
+//!
+//! Subcommands:
+//!
+//! - `new <name>`: generate a `shared` + `frontend` + `server` workspace wired
+//!   to `axum-egui-build`
+//! - `dev`: rebuild and restart the server on source changes, with live reload
+//!   in the browser
+//! - `build [--release] [--profiling]`: build the server (and its embedded
+//!   frontend), running `wasm-opt` and precompressing the WASM and JS on
+//!   release builds; `--profiling` keeps debug info and function names in
+//!   the WASM
+//!
+//! Installed as `cargo-axum-egui`, so Cargo exposes it as `cargo axum-egui`.
+
+mod build;
+mod dev;
+mod new;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+Usage: cargo axum-egui <COMMAND>
+
+Commands:
+  new <NAME> [--axum-egui-path <DIR>]   Create a new axum-egui workspace
+  dev [-p <PACKAGE>]                     Run the server, restarting on changes
+  build [--release] [--profiling] [-p <PACKAGE>]
+                                         Build the server and embedded frontend
+
+Options:
+  -h, --help                             Print this help";
+
+/// A parsed command line.
+#[derive(Debug, PartialEq, Eq)]
+enum Cli {
+    New {
+        name: String,
+        axum_egui_path: Option<PathBuf>,
+    },
+    Dev {
+        package: Option<String>,
+    },
+    Build {
+        release: bool,
+        profiling: bool,
+        package: Option<String>,
+    },
+    Help,
+}
+
+fn parse_args(args: &[String]) -> Result<Cli, String> {
+    // When invoked as `cargo axum-egui ...`, cargo passes the subcommand name first
+    let args = match args.first().map(String::as_str) {
+        Some("axum-egui") => &args[1..],
+        _ => args,
+    };
+
+    let Some((command, rest)) = args.split_first() else {
+        return Ok(Cli::Help);
+    };
+
+    let mut positional = Vec::new();
+    let mut release = false;
+    let mut profiling = false;
+    let mut package = None;
+    let mut axum_egui_path = None;
+
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Cli::Help),
+            "--release" | "-r" => release = true,
+            "--profiling" => profiling = true,
+            "-p" | "--package" => {
+                let value = iter.next().ok_or("missing value for --package")?;
+                package = Some(value.clone());
+            }
+            "--axum-egui-path" => {
+                let value = iter.next().ok_or("missing value for --axum-egui-path")?;
+                axum_egui_path = Some(PathBuf::from(value));
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            value => positional.push(value.to_string()),
+        }
+    }
+
+    match command.as_str() {
+        "new" => {
+            let [name] = positional.as_slice() else {
+                return Err("`new` expects exactly one project name".to_string());
+            };
+            Ok(Cli::New {
+                name: name.clone(),
+                axum_egui_path,
+            })
+        }
+        "dev" => Ok(Cli::Dev { package }),
+        "build" => Ok(Cli::Build {
+            release,
+            profiling,
+            package,
+        }),
+        "-h" | "--help" | "help" => Ok(Cli::Help),
+        other => Err(format!("unknown command '{}'", other)),
+    }
+}
+
+/// Find the server package of the workspace in `root`.
+///
+/// Projects generated by `cargo axum-egui new` keep the server crate in
+/// `server/`, so its package name is read from `server/Cargo.toml`.
+fn detect_server_package(root: &Path) -> Result<String, String> {
+    let manifest = root.join("server").join("Cargo.toml");
+    let contents = fs::read_to_string(&manifest).map_err(|_| {
+        format!(
+            "could not read {}; pass the server package explicitly with -p <PACKAGE>",
+            manifest.display()
+        )
+    })?;
+
+    contents
+        .lines()
+        .map(str::trim)
+        .find_map(|line| {
+            let value = line.strip_prefix("name")?.trim_start().strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+        .ok_or_else(|| format!("no package name found in {}", manifest.display()))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let cwd = env::current_dir().map_err(|e| format!("cannot read current directory: {e}"))?;
+
+    match cli {
+        Cli::Help => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        Cli::New {
+            name,
+            axum_egui_path,
+        } => new::run(&cwd, &name, axum_egui_path.as_deref()),
+        Cli::Dev { package } => {
+            let package = package.map_or_else(|| detect_server_package(&cwd), Ok)?;
+            dev::run(&cwd, &package)
+        }
+        Cli::Build {
+            release,
+            profiling,
+            package,
+        } => {
+            let package = package.map_or_else(|| detect_server_package(&cwd), Ok)?;
+            build::run(&cwd, &package, release, profiling)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = parse_args(&args).and_then(run);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            eprintln!();
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_cargo_subcommand_invocation() {
+        let cli = parse_args(&args(&["axum-egui", "new", "my-app"])).unwrap();
+        assert_eq!(
+            cli,
+            Cli::New {
+                name: "my-app".into(),
+                axum_egui_path: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_build_flags() {
+        let cli = parse_args(&args(&["build", "--release", "-p", "my-server"])).unwrap();
+        assert_eq!(
+            cli,
+            Cli::Build {
+                release: true,
+                profiling: false,
+                package: Some("my-server".into()),
+            }
+        );
+
+        let cli = parse_args(&args(&["build", "--profiling"])).unwrap();
+        assert_eq!(
+            cli,
+            Cli::Build {
+                release: false,
+                profiling: true,
+                package: None,
+            }
+        );
+    }
+
+    #[test]
+    fn no_command_prints_help() {
+        assert_eq!(parse_args(&args(&["axum-egui"])).unwrap(), Cli::Help);
+    }
+
+    #[test]
+    fn rejects_unknown_command() {
+        assert!(parse_args(&args(&["deploy"])).is_err());
+    }
+
+    #[test]
+    fn new_requires_a_name() {
+        assert!(parse_args(&args(&["new"])).is_err());
+    }
+
+    #[test]
+    fn detects_server_package_name() {
+        let root = env::temp_dir().join(format!("axum-egui-detect-{}", std::process::id()));
+        fs::create_dir_all(root.join("server")).unwrap();
+        fs::write(
+            root.join("server/Cargo.toml"),
+            "[package]\nname = \"demo-server\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(detect_server_package(&root).unwrap(), "demo-server");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
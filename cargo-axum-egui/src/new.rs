@@ -0,0 +1,486 @@
+//! `cargo axum-egui new`: generate a ready-to-run axum-egui workspace.
+//!
+//! The generated layout mirrors the examples in this repository:
+//!
+//! ```text
+//! my-app/
+//! ├── .cargo/config.toml    # Enables artifact dependencies
+//! ├── rust-toolchain.toml   # Nightly + wasm32 target
+//! ├── Cargo.toml            # Workspace
+//! ├── shared/               # App state + #[server] functions (ssr/hydrate)
+//! ├── frontend/             # egui WASM app
+//! └── server/               # axum server embedding the frontend
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where generated crates get their axum-egui dependencies from.
+enum DepSource {
+    /// Published crates on crates.io.
+    Registry,
+    /// A local checkout of the axum-egui repository.
+    Path(PathBuf),
+}
+
+impl DepSource {
+    /// Dependency spec for one of the axum-egui crates, with extra inline keys.
+    fn spec(&self, krate: &str, extra: &str) -> String {
+        let source = match self {
+            DepSource::Registry => format!("version = \"{}\"", env!("CARGO_PKG_VERSION")),
+            DepSource::Path(root) => format!("path = \"{}\"", root.join(krate).display()),
+        };
+        if extra.is_empty() {
+            format!("{{ {} }}", source)
+        } else {
+            format!("{{ {}, {} }}", source, extra)
+        }
+    }
+}
+
+/// Names derived from the project name.
+struct Names {
+    project: String,
+    shared: String,
+    frontend: String,
+    server: String,
+}
+
+impl Names {
+    fn new(project: &str) -> Self {
+        Self {
+            project: project.to_string(),
+            shared: format!("{}-shared", project),
+            frontend: format!("{}-frontend", project),
+            server: format!("{}-server", project),
+        }
+    }
+
+    /// Rust identifier for a crate name (`my-app-shared` -> `my_app_shared`).
+    fn ident(krate: &str) -> String {
+        krate.replace('-', "_")
+    }
+
+    /// The dist env var set by `axum_egui_build::frontend`.
+    fn dist_env(&self) -> String {
+        format!("{}_DIST", Self::ident(&self.frontend).to_uppercase())
+    }
+}
+
+/// Validate a project name: it must be usable as a directory and crate name.
+fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("project name must not be empty".to_string());
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return Err(format!("project name '{}' must start with a letter", name));
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '-' && *c != '_')
+    {
+        return Err(format!(
+            "project name '{}' contains invalid character '{}'. \
+             Allowed characters: alphanumeric, '-', '_'",
+            name, c
+        ));
+    }
+    Ok(())
+}
+
+pub fn run(cwd: &Path, name: &str, axum_egui_path: Option<&Path>) -> Result<(), String> {
+    validate_name(name)?;
+
+    let root = cwd.join(name);
+    if root.exists() {
+        return Err(format!("destination '{}' already exists", root.display()));
+    }
+
+    let deps = match axum_egui_path {
+        Some(path) => {
+            let path = path
+                .canonicalize()
+                .map_err(|e| format!("invalid --axum-egui-path '{}': {e}", path.display()))?;
+            DepSource::Path(path)
+        }
+        None => DepSource::Registry,
+    };
+
+    let names = Names::new(name);
+    for (path, contents) in files(&names, &deps) {
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("failed to create {}: {e}", parent.display()))?;
+        }
+        fs::write(&path, contents)
+            .map_err(|e| format!("failed to write {}: {e}", path.display()))?;
+    }
+
+    println!("Created axum-egui project '{}'", name);
+    println!();
+    println!("Next steps:");
+    println!("  cd {}", name);
+    println!("  cargo axum-egui dev");
+    Ok(())
+}
+
+/// All files of a new project, as (relative path, contents).
+fn files(names: &Names, deps: &DepSource) -> Vec<(&'static str, String)> {
+    vec![
+        (".cargo/config.toml", CARGO_CONFIG.to_string()),
+        ("rust-toolchain.toml", RUST_TOOLCHAIN.to_string()),
+        (".gitignore", "/target\n".to_string()),
+        ("Cargo.toml", workspace_manifest(names)),
+        ("shared/Cargo.toml", shared_manifest(names, deps)),
+        ("shared/src/lib.rs", SHARED_LIB.to_string()),
+        ("frontend/Cargo.toml", frontend_manifest(names)),
+        ("frontend/src/lib.rs", frontend_lib(names)),
+        ("server/Cargo.toml", server_manifest(names, deps)),
+        (
+            "server/build.rs",
+            format!(
+                "fn main() {{\n    axum_egui_build::frontend(\"{}\");\n}}\n",
+                names.frontend
+            ),
+        ),
+        ("server/src/main.rs", server_main(names)),
+    ]
+}
+
+const CARGO_CONFIG: &str = r#"# Enable unstable artifact dependencies (RFC 3028)
+# This allows the server to depend on the frontend WASM as a build artifact
+[unstable]
+bindeps = true
+"#;
+
+const RUST_TOOLCHAIN: &str = r#"[toolchain]
+channel = "nightly"
+targets = ["wasm32-unknown-unknown"]
+"#;
+
+fn workspace_manifest(names: &Names) -> String {
+    format!(
+        r#"[workspace]
+members = ["shared", "frontend", "server"]
+resolver = "2"
+
+# The frontend is built for wasm32 via the server's artifact dependency
+default-members = ["shared", "server"]
+
+[workspace.dependencies]
+serde = {{ version = "1", features = ["derive"] }}
+serde_json = "1"
+
+# Generated by cargo-axum-egui for '{}'
+"#,
+        names.project
+    )
+}
+
+fn shared_manifest(names: &Names, deps: &DepSource) -> String {
+    format!(
+        r#"[package]
+name = "{shared}"
+version = "0.1.0"
+edition = "2024"
+publish = false
+
+[features]
+default = []
+# Server side: #[server] functions execute directly
+ssr = ["axum-egui/server", "dep:axum"]
+# Client side: #[server] functions make HTTP requests
+hydrate = ["axum-egui/client"]
+
+[dependencies]
+axum-egui = {axum_egui}
+axum = {{ version = "0.8", optional = true }}
+serde = {{ workspace = true }}
+serde_json = {{ workspace = true }}
+"#,
+        shared = names.shared,
+        axum_egui = deps.spec("axum-egui", "default-features = false"),
+    )
+}
+
+const SHARED_LIB: &str = r#"//! Types and server functions shared by the frontend and the server.
+
+use axum_egui::{ServerFnError, server};
+use serde::{Deserialize, Serialize};
+
+/// Initial app state, serialized by the server and read by the frontend.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppState {
+    pub counter: i32,
+    pub message: String,
+}
+
+/// Increment a value on the server.
+#[server]
+pub async fn increment(value: i32) -> Result<i32, ServerFnError> {
+    Ok(value + 1)
+}
+"#;
+
+fn frontend_manifest(names: &Names) -> String {
+    format!(
+        r#"[package]
+name = "{frontend}"
+version = "0.1.0"
+edition = "2024"
+publish = false
+
+[lib]
+crate-type = ["cdylib"]
+
+[dependencies]
+{shared} = {{ path = "../shared", features = ["hydrate"] }}
+
+# egui
+egui = "0.31"
+eframe = {{ version = "0.31", default-features = false, features = ["wgpu", "web_screen_reader"] }}
+
+# WASM
+wasm-bindgen = "=0.2.104"
+wasm-bindgen-futures = "0.4"
+web-sys = {{ version = "0.3", features = ["Document", "Element", "HtmlElement", "HtmlCanvasElement"] }}
+log = "0.4"
+
+serde = {{ workspace = true }}
+serde_json = {{ workspace = true }}
+"#,
+        frontend = names.frontend,
+        shared = names.shared,
+    )
+}
+
+fn frontend_lib(names: &Names) -> String {
+    format!(
+        r#"//! The egui frontend, compiled to WASM.
+
+use {shared}::{{AppState, increment}};
+use std::sync::mpsc::{{Receiver, Sender, channel}};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(start)]
+pub fn main() {{
+    eframe::WebLogger::init(log::LevelFilter::Debug).ok();
+
+    wasm_bindgen_futures::spawn_local(async {{
+        let document = web_sys::window()
+            .expect("No window")
+            .document()
+            .expect("No document");
+
+        // Read the initial state injected by the server
+        let state: AppState = document
+            .get_element_by_id("axum-egui-state")
+            .and_then(|el| el.text_content())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+
+        let canvas = document
+            .get_element_by_id("the_canvas_id")
+            .expect("Failed to find canvas")
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .expect("Not a canvas element");
+
+        let start_result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(move |_cc| Ok(Box::new(App::new(state)))),
+            )
+            .await;
+
+        if let Some(loading_text) = document.get_element_by_id("loading_text") {{
+            match start_result {{
+                Ok(_) => loading_text.remove(),
+                Err(e) => {{
+                    loading_text.set_inner_html("<p>App crashed. See console.</p>");
+                    panic!("Failed to start eframe: {{e:?}}");
+                }}
+            }}
+        }}
+    }});
+}}
+
+struct App {{
+    state: AppState,
+    result_rx: Receiver<i32>,
+    result_tx: Sender<i32>,
+}}
+
+impl App {{
+    fn new(state: AppState) -> Self {{
+        let (result_tx, result_rx) = channel();
+        Self {{
+            state,
+            result_rx,
+            result_tx,
+        }}
+    }}
+}}
+
+impl eframe::App for App {{
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {{
+        while let Ok(value) = self.result_rx.try_recv() {{
+            self.state.counter = value;
+        }}
+
+        egui::CentralPanel::default().show(ctx, |ui| {{
+            ui.heading("{project}");
+            ui.label(&self.state.message);
+            ui.label(format!("Counter: {{}}", self.state.counter));
+
+            if ui.button("Increment (server)").clicked() {{
+                let tx = self.result_tx.clone();
+                let ctx = ctx.clone();
+                let current = self.state.counter;
+                wasm_bindgen_futures::spawn_local(async move {{
+                    match increment(current).await {{
+                        Ok(value) => {{
+                            let _ = tx.send(value);
+                            ctx.request_repaint();
+                        }}
+                        Err(e) => log::error!("increment failed: {{e}}"),
+                    }}
+                }});
+            }}
+        }});
+    }}
+}}
+"#,
+        shared = Names::ident(&names.shared),
+        project = names.project,
+    )
+}
+
+fn server_manifest(names: &Names, deps: &DepSource) -> String {
+    format!(
+        r#"[package]
+name = "{server}"
+version = "0.1.0"
+edition = "2024"
+publish = false
+
+[dependencies]
+{shared} = {{ path = "../shared", features = ["ssr"] }}
+axum-egui = {axum_egui}
+axum = "0.8"
+tokio = {{ version = "1", features = ["full"] }}
+tracing-subscriber = "0.3"
+rust-embed = {{ version = "8", features = ["interpolate-folder-path"] }}
+serde = {{ workspace = true }}
+serde_json = {{ workspace = true }}
+
+# Artifact dependency: the frontend WASM (RFC 3028)
+[build-dependencies]
+axum-egui-build = {axum_egui_build}
+{frontend} = {{ path = "../frontend", artifact = "cdylib", target = "wasm32-unknown-unknown" }}
+"#,
+        server = names.server,
+        shared = names.shared,
+        frontend = names.frontend,
+        axum_egui = deps.spec("axum-egui", "features = [\"server\"]"),
+        axum_egui_build = deps.spec("axum-egui-build", ""),
+    )
+}
+
+fn server_main(names: &Names) -> String {
+    format!(
+        r#"//! Server for {project}: serves the embedded frontend and its API.
+
+use axum::Router;
+use axum::routing::{{get, post}};
+use {shared}::{{AppState, increment_handler}};
+use rust_embed::RustEmbed;
+use std::net::SocketAddr;
+
+// Embed the frontend assets built by build.rs
+#[derive(RustEmbed)]
+#[folder = "${dist_env}"]
+struct Assets;
+
+async fn index() -> axum_egui::App<AppState, Assets> {{
+    axum_egui::App::new(AppState {{
+        counter: 0,
+        message: "Hello from the server!".into(),
+    }})
+}}
+
+#[tokio::main]
+async fn main() {{
+    tracing_subscriber::fmt::init();
+
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/api/increment", post(increment_handler));
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    println!("Server running on http://{{addr}}");
+
+    // Compression, tracing, body limits, graceful shutdown and static assets
+    axum_egui::serve(app, addr).assets::<Assets>().await.unwrap();
+}}
+"#,
+        project = names.project,
+        shared = Names::ident(&names.shared),
+        dist_env = names.dist_env(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(validate_name("").is_err());
+        assert!(validate_name("1app").is_err());
+        assert!(validate_name("my app").is_err());
+        assert!(validate_name("my-app_2").is_ok());
+    }
+
+    #[test]
+    fn derives_dist_env_var_from_frontend_crate() {
+        assert_eq!(Names::new("my-app").dist_env(), "MY_APP_FRONTEND_DIST");
+    }
+
+    #[test]
+    fn path_deps_point_into_checkout() {
+        let deps = DepSource::Path(PathBuf::from("/src/axum-egui"));
+        assert_eq!(
+            deps.spec("axum-egui-build", ""),
+            r#"{ path = "/src/axum-egui/axum-egui-build" }"#
+        );
+    }
+
+    #[test]
+    fn generates_wired_workspace() {
+        let cwd = env::temp_dir().join(format!("axum-egui-new-{}", std::process::id()));
+        fs::create_dir_all(&cwd).unwrap();
+
+        run(&cwd, "demo", None).unwrap();
+        let root = cwd.join("demo");
+
+        let build_rs = fs::read_to_string(root.join("server/build.rs")).unwrap();
+        assert!(build_rs.contains(r#"axum_egui_build::frontend("demo-frontend")"#));
+
+        let main_rs = fs::read_to_string(root.join("server/src/main.rs")).unwrap();
+        assert!(main_rs.contains(r#"#[folder = "$DEMO_FRONTEND_DIST"]"#));
+        assert!(main_rs.contains("use demo_shared::{AppState, increment_handler};"));
+
+        let server_toml = fs::read_to_string(root.join("server/Cargo.toml")).unwrap();
+        assert!(
+            server_toml.contains(r#"demo-frontend = { path = "../frontend", artifact = "cdylib""#)
+        );
+
+        assert!(root.join(".cargo/config.toml").exists());
+        assert!(run(&cwd, "demo", None).is_err(), "must not overwrite");
+
+        fs::remove_dir_all(&cwd).unwrap();
+    }
+}
@@ -28,7 +28,10 @@ pub async fn greet(name: String) -> Result<String, ServerFnError> {
 }
 
 /// Get information about the server.
-#[server]
+///
+/// With the `mock` feature, the client answers this itself (see
+/// [`mock_whoami`]), so the UI can be worked on without a server.
+#[server(mock = mock_whoami)]
 pub async fn whoami() -> Result<ServerInfo, ServerFnError> {
     use std::time::{SystemTime, UNIX_EPOCH};
     let timestamp = SystemTime::now()
@@ -40,3 +43,12 @@ pub async fn whoami() -> Result<ServerInfo, ServerFnError> {
         timestamp,
     })
 }
+
+/// The answer of [`whoami`] in frontend-only builds.
+#[cfg(feature = "mock")]
+async fn mock_whoami() -> Result<ServerInfo, ServerFnError> {
+    Ok(ServerInfo {
+        message: "I am a mock, no server involved".into(),
+        timestamp: 0,
+    })
+}
@@ -19,61 +19,18 @@ mod app {
     use crate::api::{self, ServerInfo};
     use crate::state::AppState;
     use axum_egui::ServerFnError;
+    use axum_egui::bootstrap::Bootstrap;
     use axum_egui::ws::{WsClientSender, WsStream};
     use futures_util::StreamExt;
     use std::sync::mpsc::{Receiver, Sender, channel};
-    use wasm_bindgen::prelude::*;
 
-    #[wasm_bindgen(start)]
-    pub fn main() {
+    #[axum_egui::main]
+    fn start() -> Bootstrap<AppState> {
         eframe::WebLogger::init(log::LevelFilter::Debug).ok();
 
-        wasm_bindgen_futures::spawn_local(async {
-            let document = web_sys::window()
-                .expect("No window")
-                .document()
-                .expect("No document");
-
-            // Try to read initial state from the DOM
-            let initial_state: AppState = read_initial_state(&document).unwrap_or_default();
-
-            let canvas = document
-                .get_element_by_id("the_canvas_id")
-                .expect("Failed to find canvas")
-                .dyn_into::<web_sys::HtmlCanvasElement>()
-                .expect("Not a canvas element");
-
-            let web_options = eframe::WebOptions::default();
-
-            // Create the app with initial state
-            let app = ExampleApp::new(initial_state);
-
-            let start_result = eframe::WebRunner::new()
-                .start(canvas, web_options, Box::new(move |_cc| Ok(Box::new(app))))
-                .await;
-
-            if let Some(loading_text) = document.get_element_by_id("loading_text") {
-                match start_result {
-                    Ok(_) => {
-                        loading_text.remove();
-                    }
-                    Err(e) => {
-                        loading_text.set_inner_html(
-                            "<p>The app has crashed. See the developer console for details.</p>",
-                        );
-                        panic!("Failed to start eframe: {e:?}");
-                    }
-                }
-            }
-        });
-    }
-
-    fn read_initial_state<T: serde::de::DeserializeOwned>(
-        document: &web_sys::Document,
-    ) -> Option<T> {
-        let script = document.get_element_by_id("axum-egui-state")?;
-        let json = script.text_content()?;
-        serde_json::from_str(&json).ok()
+        // The app is created from the initial state the server injected
+        Bootstrap::with_app(|initial_state, _cc| ExampleApp::new(initial_state))
+            .version(env!("CARGO_PKG_VERSION"))
     }
 
     // ============================================================================
@@ -75,6 +75,9 @@ async fn ws_echo(ws: WebSocketUpgrade) -> impl axum::response::IntoResponse {
 async fn main() {
     tracing_subscriber::fmt::init();
 
+    // Fail fast if the embedded frontend build is incomplete
+    axum_egui::assets::ensure::<Assets>();
+
     let app = Router::new()
         .route("/", get(index))
         // API endpoints (handlers generated by #[server] macro)
@@ -85,12 +88,17 @@ async fn main() {
         .route("/api/sse/counter", get(counter_sse))
         // WebSocket endpoint for bidirectional communication
         .route("/api/ws", get(ws_echo))
-        // Serve static assets
-        .fallback(axum_egui::static_handler::<Assets>);
+        // Panics of the frontend, with the id its crash screen shows
+        .merge(axum_egui::crash::router(axum_egui::crash::TracingSink))
+        // nosniff, frame denial and referrer policy on every response
+        .layer(axum_egui::security_headers());
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     println!("Server running on http://{addr}");
 
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Compression, tracing, body limits, graceful shutdown and static assets
+    axum_egui::serve(app, addr)
+        .assets::<Assets>()
+        .await
+        .unwrap();
 }
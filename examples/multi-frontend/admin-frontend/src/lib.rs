@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 pub struct AdminApp {
     pub total_users: i32,
     pub active_sessions: i32,
+    pub messages_per_sec: f64,
     pub server_uptime_secs: u64,
 }
 
@@ -30,8 +31,8 @@ impl AdminApp {
 #[cfg(feature = "hydrate")]
 mod app {
     use super::AdminApp;
+    use axum_egui::bootstrap::Bootstrap;
     use eframe::egui;
-    use wasm_bindgen::prelude::*;
 
     impl eframe::App for AdminApp {
         fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -49,6 +50,11 @@ mod app {
                     ui.strong(format!("{}", self.active_sessions));
                 });
 
+                ui.horizontal(|ui| {
+                    ui.label("Messages/sec:");
+                    ui.strong(format!("{:.1}", self.messages_per_sec));
+                });
+
                 ui.horizontal(|ui| {
                     ui.label("Server Uptime:");
                     ui.strong(self.format_uptime());
@@ -60,53 +66,11 @@ mod app {
         }
     }
 
-    /// Get initial state from the server-injected JSON.
-    fn get_initial_state() -> AdminApp {
-        let window = web_sys::window().expect("no window");
-        let document = window.document().expect("no document");
-
-        document
-            .get_element_by_id("axum-egui-state")
-            .and_then(|el| el.text_content())
-            .and_then(|json| serde_json::from_str(&json).ok())
-            .unwrap_or_default()
-    }
-
-    #[wasm_bindgen(start)]
-    pub fn main() {
+    #[axum_egui::main]
+    fn start() -> Bootstrap<AdminApp> {
         eframe::WebLogger::init(log::LevelFilter::Debug).ok();
 
-        wasm_bindgen_futures::spawn_local(async {
-            let document = web_sys::window()
-                .expect("No window")
-                .document()
-                .expect("No document");
-
-            let canvas = document
-                .get_element_by_id("the_canvas_id")
-                .expect("Failed to find canvas")
-                .dyn_into::<web_sys::HtmlCanvasElement>()
-                .expect("Not a canvas element");
-
-            let app = get_initial_state();
-
-            let start_result = eframe::WebRunner::new()
-                .start(
-                    canvas,
-                    eframe::WebOptions::default(),
-                    Box::new(move |_cc| Ok(Box::new(app))),
-                )
-                .await;
-
-            if let Some(loading_text) = document.get_element_by_id("loading_text") {
-                match start_result {
-                    Ok(_) => loading_text.remove(),
-                    Err(e) => {
-                        loading_text.set_inner_html("<p>App crashed. See console.</p>");
-                        panic!("Failed to start eframe: {e:?}");
-                    }
-                }
-            }
-        });
+        // The app is its own state, read from the server-injected JSON
+        Bootstrap::new()
     }
 }
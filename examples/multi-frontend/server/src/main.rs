@@ -6,11 +6,13 @@
 use admin_frontend::AdminApp;
 use axum::Router;
 use axum::extract::Request;
-use axum::http::Uri;
+use axum::http::{HeaderMap, Method, Uri};
 use axum::response::IntoResponse;
 use axum::routing::get;
 use rust_embed::RustEmbed;
 use std::net::SocketAddr;
+use std::sync::OnceLock;
+use std::time::Instant;
 use user_frontend::UserApp;
 
 // ============================================================================
@@ -28,8 +30,8 @@ async fn user_app() -> axum_egui::App<UserApp, UserAssets> {
     })
 }
 
-async fn user_static(uri: Uri) -> impl IntoResponse {
-    axum_egui::static_handler::<UserAssets>(uri).await
+async fn user_static(method: Method, headers: HeaderMap, uri: Uri) -> impl IntoResponse {
+    axum_egui::static_handler::<UserAssets>(method, headers, uri).await
 }
 
 // ============================================================================
@@ -40,11 +42,16 @@ async fn user_static(uri: Uri) -> impl IntoResponse {
 #[folder = "$ADMIN_FRONTEND_DIST"]
 struct AdminAssets;
 
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
 async fn admin_app() -> axum_egui::App<AdminApp, AdminAssets> {
+    // Live numbers from the streaming server functions
+    let stats = axum_egui::metrics::stream_stats();
     axum_egui::App::new(AdminApp {
         total_users: 42,
-        active_sessions: 7,
-        server_uptime_secs: 86400 + 3661, // 1 day, 1 hour, 1 minute, 1 second
+        active_sessions: (stats.sse_connections + stats.ws_connections) as i32,
+        messages_per_sec: stats.messages_per_sec,
+        server_uptime_secs: STARTED.get_or_init(Instant::now).elapsed().as_secs(),
     })
 }
 
@@ -53,7 +60,8 @@ async fn admin_static(request: Request) -> impl IntoResponse {
     let path = request.uri().path();
     let stripped = path.strip_prefix("/admin").unwrap_or(path);
     let new_uri: Uri = stripped.parse().unwrap_or_else(|_| "/".parse().unwrap());
-    axum_egui::static_handler::<AdminAssets>(new_uri).await
+    let (parts, _) = request.into_parts();
+    axum_egui::static_handler::<AdminAssets>(parts.method, parts.headers, new_uri).await
 }
 
 // ============================================================================
@@ -63,6 +71,11 @@ async fn admin_static(request: Request) -> impl IntoResponse {
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
+    STARTED.get_or_init(Instant::now);
+
+    // Fail fast if either embedded frontend build is incomplete
+    axum_egui::assets::ensure::<UserAssets>();
+    axum_egui::assets::ensure::<AdminAssets>();
 
     // User frontend routes
     let user_routes = Router::new()